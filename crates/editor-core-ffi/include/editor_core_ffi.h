@@ -85,6 +85,7 @@ char* editor_core_ffi_editor_state_text(const EcfEditorState* state);
 char* editor_core_ffi_editor_state_text_for_saving(const EcfEditorState* state);
 bool editor_core_ffi_editor_state_set_line_ending(EcfEditorState* state, const char* line_ending);
 char* editor_core_ffi_editor_state_get_line_ending(const EcfEditorState* state);
+bool editor_core_ffi_editor_state_set_fold_placeholder_template(EcfEditorState* state, const char* template_str);
 char* editor_core_ffi_editor_state_viewport_styled_json(const EcfEditorState* state, size_t start_visual_row, size_t count);
 char* editor_core_ffi_editor_state_minimap_json(const EcfEditorState* state, size_t start_visual_row, size_t count);
 char* editor_core_ffi_editor_state_viewport_composed_json(const EcfEditorState* state, size_t start_visual_row, size_t count);
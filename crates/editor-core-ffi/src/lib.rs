@@ -9,14 +9,17 @@ use editor_core::commands::{
 };
 use editor_core::decorations::{
     Decoration, DecorationKind, DecorationLayerId, DecorationPlacement, DecorationRange,
+    UnderlineStyle,
+};
+use editor_core::diagnostics::{
+    Diagnostic, DiagnosticRange, DiagnosticSeverity, DiagnosticTag, RelatedDiagnostic,
 };
-use editor_core::diagnostics::{Diagnostic, DiagnosticRange, DiagnosticSeverity};
 use editor_core::intervals::{FoldRegion, Interval, StyleLayerId};
 use editor_core::layout::{WrapIndent, WrapMode};
 use editor_core::processing::{DocumentProcessor, ProcessingEdit};
 use editor_core::snapshot::{
     Cell, ComposedCell, ComposedCellSource, ComposedGrid, ComposedLine, ComposedLineKind,
-    HeadlessGrid, HeadlessLine, MinimapGrid, MinimapLine,
+    HeadlessGrid, HeadlessLine, MinimapGrid, MinimapLine, SnapshotOptions,
 };
 use editor_core::state::{
     CursorState, DecorationsState, DiagnosticsState, DocumentState, EditorState,
@@ -30,15 +33,16 @@ use editor_core::workspace::{
     BufferId, OpenBufferResult, ViewId, ViewSmoothScrollState, Workspace, WorkspaceSearchResult,
     WorkspaceViewportState,
 };
-use editor_core::{LineEnding, SearchMatch, SearchOptions};
+use editor_core::{IndentSettings, LineEnding, SearchMatch, SearchOptions};
 use editor_core_lsp::{
-    CompletionTextEditMode, LspCoordinateConverter, apply_completion_item, apply_text_edits,
-    completion_item_to_text_edit_specs, decode_semantic_style_id, encode_semantic_style_id,
-    file_uri_to_path, locations_from_value, lsp_code_lens_to_processing_edit,
-    lsp_diagnostics_to_processing_edits, lsp_document_highlights_to_processing_edit,
-    lsp_document_links_to_processing_edit, lsp_document_symbols_to_processing_edit,
-    lsp_inlay_hints_to_processing_edit, lsp_workspace_symbols_to_results, path_to_file_uri,
-    percent_decode_path, percent_encode_path, semantic_tokens_to_intervals, text_edits_from_value,
+    CompletionTextEditMode, LspCoordinateConverter, LspSessionOutput, apply_completion_item,
+    apply_text_edits, completion_item_to_text_edit_specs, decode_semantic_style_id,
+    encode_semantic_style_id, file_uri_to_path, locations_from_value,
+    lsp_code_lens_to_processing_edit, lsp_diagnostics_to_processing_edits_for,
+    lsp_document_highlights_to_processing_edit, lsp_document_links_to_processing_edit,
+    lsp_document_symbols_to_processing_edit, lsp_inlay_hints_to_processing_edit,
+    lsp_workspace_symbols_to_results, path_to_file_uri, percent_decode_path, percent_encode_path,
+    semantic_tokens_to_intervals, text_edits_from_value,
 };
 use editor_core_sublime::{SublimeProcessor, SublimeScopeMapper, SublimeSyntaxSet};
 use editor_core_treesitter::{
@@ -265,9 +269,11 @@ fn build_viewport_blob(grid: &HeadlessGrid) -> Result<Vec<u8>, (EcfStatus, Strin
     let mut style_ids: Vec<u32> = Vec::new();
 
     for line in &grid.lines {
-        let line_cell_start_index = checked_u32(cell_records.len(), "cell_start_index")?;
-        let line_cell_count = checked_u32(line.cells.len(), "line_cell_count")?;
+        let line_first_cell = cell_records.len();
+        let line_cell_start_index = checked_u32(line_first_cell, "cell_start_index")?;
 
+        // Records stay one per `char`: the rest of a grapheme cluster follows its first `char`
+        // as zero-width records sharing its styles.
         for cell in &line.cells {
             let style_start_index = checked_u32(style_ids.len(), "style_start_index")?;
             let style_count = checked_u16(cell.styles.len(), "style_count")?;
@@ -279,7 +285,16 @@ fn build_viewport_blob(grid: &HeadlessGrid) -> Result<Vec<u8>, (EcfStatus, Strin
                 style_count,
                 style_start_index,
             });
+            for ch in cell.combining.chars() {
+                cell_records.push(EcfViewportCell {
+                    scalar_value: u32::from(ch),
+                    width: 0,
+                    style_count,
+                    style_start_index,
+                });
+            }
         }
+        let line_cell_count = checked_u32(cell_records.len() - line_first_cell, "line_cell_count")?;
 
         line_records.push(EcfViewportLine {
             logical_line_index: checked_u32(line.logical_line_index, "logical_line_index")?,
@@ -596,8 +611,9 @@ fn line_ending_from_str(s: &str) -> Result<LineEnding, String> {
     match s.trim().to_ascii_lowercase().as_str() {
         "lf" => Ok(LineEnding::Lf),
         "crlf" => Ok(LineEnding::Crlf),
+        "mixed" => Ok(LineEnding::Mixed),
         other => Err(format!(
-            "unsupported line ending: {other} (expected lf|crlf)"
+            "unsupported line ending: {other} (expected lf|crlf|mixed)"
         )),
     }
 }
@@ -606,6 +622,7 @@ fn line_ending_to_str(line_ending: LineEnding) -> &'static str {
     match line_ending {
         LineEnding::Lf => "lf",
         LineEnding::Crlf => "crlf",
+        LineEnding::Mixed => "mixed",
     }
 }
 
@@ -661,6 +678,28 @@ impl From<FfiSelection> for Selection {
     }
 }
 
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct FfiIndentSettings {
+    use_tabs: bool,
+    width: usize,
+    #[serde(default = "default_indent_confidence")]
+    confidence: u8,
+}
+
+fn default_indent_confidence() -> u8 {
+    100
+}
+
+impl From<FfiIndentSettings> for IndentSettings {
+    fn from(value: FfiIndentSettings) -> Self {
+        IndentSettings {
+            use_tabs: value.use_tabs,
+            width: value.width,
+            confidence: value.confidence,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct FfiTextEditSpec {
     start: usize,
@@ -690,6 +729,8 @@ struct FfiSearchOptions {
     whole_word: bool,
     #[serde(default)]
     regex: bool,
+    #[serde(default)]
+    dot_matches_newline: bool,
 }
 
 impl Default for FfiSearchOptions {
@@ -698,6 +739,7 @@ impl Default for FfiSearchOptions {
             case_sensitive: true,
             whole_word: false,
             regex: false,
+            dot_matches_newline: false,
         }
     }
 }
@@ -708,6 +750,8 @@ impl From<FfiSearchOptions> for SearchOptions {
             case_sensitive: value.case_sensitive,
             whole_word: value.whole_word,
             regex: value.regex,
+            dot_matches_newline: value.dot_matches_newline,
+            ..SearchOptions::default()
         }
     }
 }
@@ -832,6 +876,16 @@ enum FfiEditCommandInput {
     InsertText {
         text: String,
     },
+    InsertTextReindented {
+        text: String,
+    },
+    InsertTextWithAutoPairs {
+        text: String,
+        pairs: Vec<(char, char)>,
+    },
+    InsertSnippet {
+        template: String,
+    },
     InsertTab,
     InsertNewline {
         #[serde(default)]
@@ -840,23 +894,33 @@ enum FfiEditCommandInput {
     Indent,
     Outdent,
     DuplicateLines,
+    DuplicateSelection,
     DeleteLines,
     MoveLinesUp,
     MoveLinesDown,
     JoinLines,
     SplitLine,
+    TransposeChars,
+    TransposeWords,
     ToggleComment {
         config: FfiCommentConfig,
     },
     ApplyTextEdits {
         edits: Vec<FfiTextEditSpec>,
     },
+    ReindentDocument {
+        from: FfiIndentSettings,
+        to: FfiIndentSettings,
+    },
     DeleteToPrevTabStop,
     DeleteGraphemeBack,
     DeleteGraphemeForward,
     DeleteWordBack,
     DeleteWordForward,
     Backspace,
+    DeletePairBackspace {
+        pairs: Vec<(char, char)>,
+    },
     DeleteForward,
     Undo,
     Redo,
@@ -873,6 +937,14 @@ enum FfiEditCommandInput {
         #[serde(default)]
         options: FfiSearchOptions,
     },
+    ReplaceAllInRange {
+        query: String,
+        replacement: String,
+        #[serde(default)]
+        options: FfiSearchOptions,
+        #[serde(default)]
+        range: Option<(usize, usize)>,
+    },
 }
 
 impl FfiEditCommandInput {
@@ -890,28 +962,41 @@ impl FfiEditCommandInput {
                 text,
             },
             Self::InsertText { text } => EditCommand::InsertText { text },
+            Self::InsertTextReindented { text } => EditCommand::InsertTextReindented { text },
+            Self::InsertTextWithAutoPairs { text, pairs } => {
+                EditCommand::InsertTextWithAutoPairs { text, pairs }
+            }
+            Self::InsertSnippet { template } => EditCommand::InsertSnippet { template },
             Self::InsertTab => EditCommand::InsertTab,
             Self::InsertNewline { auto_indent } => EditCommand::InsertNewline { auto_indent },
             Self::Indent => EditCommand::Indent,
             Self::Outdent => EditCommand::Outdent,
             Self::DuplicateLines => EditCommand::DuplicateLines,
+            Self::DuplicateSelection => EditCommand::DuplicateSelection,
             Self::DeleteLines => EditCommand::DeleteLines,
             Self::MoveLinesUp => EditCommand::MoveLinesUp,
             Self::MoveLinesDown => EditCommand::MoveLinesDown,
             Self::JoinLines => EditCommand::JoinLines,
             Self::SplitLine => EditCommand::SplitLine,
+            Self::TransposeChars => EditCommand::TransposeChars,
+            Self::TransposeWords => EditCommand::TransposeWords,
             Self::ToggleComment { config } => EditCommand::ToggleComment {
                 config: config.into(),
             },
             Self::ApplyTextEdits { edits } => EditCommand::ApplyTextEdits {
                 edits: edits.into_iter().map(Into::into).collect(),
             },
+            Self::ReindentDocument { from, to } => EditCommand::ReindentDocument {
+                from: from.into(),
+                to: to.into(),
+            },
             Self::DeleteToPrevTabStop => EditCommand::DeleteToPrevTabStop,
             Self::DeleteGraphemeBack => EditCommand::DeleteGraphemeBack,
             Self::DeleteGraphemeForward => EditCommand::DeleteGraphemeForward,
             Self::DeleteWordBack => EditCommand::DeleteWordBack,
             Self::DeleteWordForward => EditCommand::DeleteWordForward,
             Self::Backspace => EditCommand::Backspace,
+            Self::DeletePairBackspace { pairs } => EditCommand::DeletePairBackspace { pairs },
             Self::DeleteForward => EditCommand::DeleteForward,
             Self::Undo => EditCommand::Undo,
             Self::Redo => EditCommand::Redo,
@@ -934,6 +1019,17 @@ impl FfiEditCommandInput {
                 replacement,
                 options: options.into(),
             },
+            Self::ReplaceAllInRange {
+                query,
+                replacement,
+                options,
+                range,
+            } => EditCommand::ReplaceAllInRange {
+                query,
+                replacement,
+                options: options.into(),
+                range,
+            },
         }
     }
 }
@@ -1004,6 +1100,27 @@ enum FfiCursorCommandInput {
         #[serde(default)]
         options: FfiSearchOptions,
     },
+    FindNextInRange {
+        query: String,
+        #[serde(default)]
+        options: FfiSearchOptions,
+        #[serde(default)]
+        range: Option<(usize, usize)>,
+    },
+    IncrementalFind {
+        query: String,
+        #[serde(default)]
+        options: FfiSearchOptions,
+        from_offset: usize,
+    },
+    GoToNextDiagnostic {
+        min_severity: FfiDiagnosticSeverity,
+    },
+    GoToPrevDiagnostic {
+        min_severity: FfiDiagnosticSeverity,
+    },
+    NextTabstop,
+    PrevTabstop,
 }
 
 impl FfiCursorCommandInput {
@@ -1064,6 +1181,32 @@ impl FfiCursorCommandInput {
                 query,
                 options: options.into(),
             },
+            Self::FindNextInRange {
+                query,
+                options,
+                range,
+            } => CursorCommand::FindNextInRange {
+                query,
+                options: options.into(),
+                range,
+            },
+            Self::IncrementalFind {
+                query,
+                options,
+                from_offset,
+            } => CursorCommand::IncrementalFind {
+                query,
+                options: options.into(),
+                from_offset,
+            },
+            Self::GoToNextDiagnostic { min_severity } => CursorCommand::GoToNextDiagnostic {
+                min_severity: min_severity.into(),
+            },
+            Self::GoToPrevDiagnostic { min_severity } => CursorCommand::GoToPrevDiagnostic {
+                min_severity: min_severity.into(),
+            },
+            Self::NextTabstop => CursorCommand::NextTabstop,
+            Self::PrevTabstop => CursorCommand::PrevTabstop,
         }
     }
 }
@@ -1076,6 +1219,7 @@ enum FfiViewCommandInput {
     SetWrapIndent { indent: FfiWrapIndent },
     SetTabWidth { width: usize },
     SetTabKeyBehavior { behavior: FfiTabKeyBehavior },
+    ApplyIndentSettings { settings: FfiIndentSettings },
     ScrollTo { line: usize },
     GetViewport { start_row: usize, count: usize },
 }
@@ -1092,6 +1236,9 @@ impl FfiViewCommandInput {
             Self::SetTabKeyBehavior { behavior } => ViewCommand::SetTabKeyBehavior {
                 behavior: behavior.into(),
             },
+            Self::ApplyIndentSettings { settings } => ViewCommand::ApplyIndentSettings {
+                settings: settings.into(),
+            },
             Self::ScrollTo { line } => ViewCommand::ScrollTo { line },
             Self::GetViewport { start_row, count } => ViewCommand::GetViewport { start_row, count },
         }
@@ -1189,6 +1336,7 @@ impl From<FfiFoldRegionInput> for FoldRegion {
             end_line: value.end_line,
             is_collapsed: value.is_collapsed,
             placeholder: value.placeholder,
+            summary: None,
         }
     }
 }
@@ -1222,13 +1370,61 @@ fn diagnostic_severity_to_str(value: DiagnosticSeverity) -> &'static str {
     }
 }
 
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FfiDiagnosticTag {
+    Unnecessary,
+    Deprecated,
+}
+
+impl From<FfiDiagnosticTag> for DiagnosticTag {
+    fn from(value: FfiDiagnosticTag) -> Self {
+        match value {
+            FfiDiagnosticTag::Unnecessary => DiagnosticTag::Unnecessary,
+            FfiDiagnosticTag::Deprecated => DiagnosticTag::Deprecated,
+        }
+    }
+}
+
+fn diagnostic_tag_to_str(value: DiagnosticTag) -> &'static str {
+    match value {
+        DiagnosticTag::Unnecessary => "unnecessary",
+        DiagnosticTag::Deprecated => "deprecated",
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FfiRelatedDiagnostic {
+    message: String,
+    uri: String,
+    range: FfiUtf16Range,
+}
+
+impl From<FfiRelatedDiagnostic> for RelatedDiagnostic {
+    fn from(value: FfiRelatedDiagnostic) -> Self {
+        RelatedDiagnostic {
+            message: value.message,
+            uri: value.uri,
+            range: value.range.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct FfiDiagnosticInput {
     range: FfiOffsetRange,
     severity: Option<FfiDiagnosticSeverity>,
     code: Option<String>,
     source: Option<String>,
+    #[serde(default)]
+    provider: Option<String>,
     message: String,
+    #[serde(default)]
+    code_description_href: Option<String>,
+    #[serde(default)]
+    tags: Vec<FfiDiagnosticTag>,
+    #[serde(default)]
+    related: Vec<FfiRelatedDiagnostic>,
     related_information_json: Option<String>,
     data_json: Option<String>,
 }
@@ -1240,7 +1436,11 @@ impl From<FfiDiagnosticInput> for Diagnostic {
             severity: value.severity.map(Into::into),
             code: value.code,
             source: value.source,
+            provider: value.provider,
             message: value.message,
+            code_description_href: value.code_description_href,
+            tags: value.tags.into_iter().map(Into::into).collect(),
+            related: value.related.into_iter().map(Into::into).collect(),
             related_information_json: value.related_information_json,
             data_json: value.data_json,
         }
@@ -1280,9 +1480,37 @@ enum FfiDecorationKind {
     CodeLens,
     DocumentLink,
     Highlight,
+    Underline(FfiUnderlineStyle),
+    LineBackground(u32),
     Custom(u32),
 }
 
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FfiUnderlineStyle {
+    Straight,
+    Wavy,
+    Dotted,
+}
+
+impl From<FfiUnderlineStyle> for UnderlineStyle {
+    fn from(value: FfiUnderlineStyle) -> Self {
+        match value {
+            FfiUnderlineStyle::Straight => UnderlineStyle::Straight,
+            FfiUnderlineStyle::Wavy => UnderlineStyle::Wavy,
+            FfiUnderlineStyle::Dotted => UnderlineStyle::Dotted,
+        }
+    }
+}
+
+fn underline_style_to_str(value: UnderlineStyle) -> &'static str {
+    match value {
+        UnderlineStyle::Straight => "straight",
+        UnderlineStyle::Wavy => "wavy",
+        UnderlineStyle::Dotted => "dotted",
+    }
+}
+
 impl From<FfiDecorationKind> for DecorationKind {
     fn from(value: FfiDecorationKind) -> Self {
         match value {
@@ -1290,6 +1518,12 @@ impl From<FfiDecorationKind> for DecorationKind {
             FfiDecorationKind::CodeLens => DecorationKind::CodeLens,
             FfiDecorationKind::DocumentLink => DecorationKind::DocumentLink,
             FfiDecorationKind::Highlight => DecorationKind::Highlight,
+            FfiDecorationKind::Underline(style) => DecorationKind::Underline {
+                style: style.into(),
+            },
+            FfiDecorationKind::LineBackground(style_id) => {
+                DecorationKind::LineBackground { style_id }
+            }
             FfiDecorationKind::Custom(v) => DecorationKind::Custom(v),
         }
     }
@@ -1301,6 +1535,12 @@ fn decoration_kind_to_json(value: DecorationKind) -> Value {
         DecorationKind::CodeLens => json!({ "kind": "code_lens" }),
         DecorationKind::DocumentLink => json!({ "kind": "document_link" }),
         DecorationKind::Highlight => json!({ "kind": "highlight" }),
+        DecorationKind::Underline { style } => {
+            json!({ "kind": "underline", "value": underline_style_to_str(style) })
+        }
+        DecorationKind::LineBackground { style_id } => {
+            json!({ "kind": "line_background", "value": style_id })
+        }
         DecorationKind::Custom(v) => json!({ "kind": "custom", "value": v }),
     }
 }
@@ -1482,6 +1722,10 @@ struct FfiDocumentSymbolInput {
     range: FfiOffsetRange,
     selection_range: FfiOffsetRange,
     #[serde(default)]
+    utf16_range: Option<FfiUtf16Range>,
+    #[serde(default)]
+    utf16_selection_range: Option<FfiUtf16Range>,
+    #[serde(default)]
     children: Vec<FfiDocumentSymbolInput>,
     data_json: Option<String>,
 }
@@ -1497,6 +1741,8 @@ impl From<FfiDocumentSymbolInput> for DocumentSymbol {
                 value.selection_range.start,
                 value.selection_range.end,
             ),
+            utf16_range: value.utf16_range.map(Into::into),
+            utf16_selection_range: value.utf16_selection_range.map(Into::into),
             children: value.children.into_iter().map(Into::into).collect(),
             data_json: value.data_json,
         }
@@ -1533,6 +1779,12 @@ enum FfiProcessingEditInput {
         layer: u32,
         intervals: Vec<FfiIntervalInput>,
     },
+    ReplaceStyleLayerRange {
+        layer: u32,
+        start: usize,
+        end: usize,
+        intervals: Vec<FfiIntervalInput>,
+    },
     ClearStyleLayer {
         layer: u32,
     },
@@ -1546,6 +1798,13 @@ enum FfiProcessingEditInput {
         diagnostics: Vec<FfiDiagnosticInput>,
     },
     ClearDiagnostics,
+    ReplaceProviderDiagnostics {
+        provider: String,
+        diagnostics: Vec<FfiDiagnosticInput>,
+    },
+    ClearProviderDiagnostics {
+        provider: String,
+    },
     ReplaceDecorations {
         layer: u32,
         decorations: Vec<FfiDecorationInput>,
@@ -1566,6 +1825,17 @@ impl FfiProcessingEditInput {
                 layer: StyleLayerId::new(layer),
                 intervals: intervals.into_iter().map(Into::into).collect(),
             },
+            Self::ReplaceStyleLayerRange {
+                layer,
+                start,
+                end,
+                intervals,
+            } => ProcessingEdit::ReplaceStyleLayerRange {
+                layer: StyleLayerId::new(layer),
+                start,
+                end,
+                intervals: intervals.into_iter().map(Into::into).collect(),
+            },
             Self::ClearStyleLayer { layer } => ProcessingEdit::ClearStyleLayer {
                 layer: StyleLayerId::new(layer),
             },
@@ -1581,6 +1851,16 @@ impl FfiProcessingEditInput {
                 diagnostics: diagnostics.into_iter().map(Into::into).collect(),
             },
             Self::ClearDiagnostics => ProcessingEdit::ClearDiagnostics,
+            Self::ReplaceProviderDiagnostics {
+                provider,
+                diagnostics,
+            } => ProcessingEdit::ReplaceProviderDiagnostics {
+                provider,
+                diagnostics: diagnostics.into_iter().map(Into::into).collect(),
+            },
+            Self::ClearProviderDiagnostics { provider } => {
+                ProcessingEdit::ClearProviderDiagnostics { provider }
+            }
             Self::ReplaceDecorations { layer, decorations } => ProcessingEdit::ReplaceDecorations {
                 layer: DecorationLayerId::new(layer),
                 decorations: decorations.into_iter().map(Into::into).collect(),
@@ -1656,6 +1936,8 @@ fn value_document_symbol(symbol: &DocumentSymbol) -> Value {
         "kind": symbol_kind_to_json(symbol.kind),
         "range": value_offset_range(symbol.range.start, symbol.range.end),
         "selection_range": value_offset_range(symbol.selection_range.start, symbol.selection_range.end),
+        "utf16_range": symbol.utf16_range.map(value_utf16_range),
+        "utf16_selection_range": symbol.utf16_selection_range.map(value_utf16_range),
         "children": symbol.children.iter().map(value_document_symbol).collect::<Vec<_>>(),
         "data_json": symbol.data_json
     })
@@ -1685,7 +1967,8 @@ fn value_fold_region(region: &FoldRegion) -> Value {
         "start_line": region.start_line,
         "end_line": region.end_line,
         "is_collapsed": region.is_collapsed,
-        "placeholder": region.placeholder
+        "placeholder": region.placeholder,
+        "summary": region.summary
     })
 }
 
@@ -1695,7 +1978,23 @@ fn value_diagnostic(diagnostic: &Diagnostic) -> Value {
         "severity": diagnostic.severity.map(diagnostic_severity_to_str),
         "code": diagnostic.code,
         "source": diagnostic.source,
+        "provider": diagnostic.provider,
         "message": diagnostic.message,
+        "code_description_href": diagnostic.code_description_href,
+        "tags": diagnostic
+            .tags
+            .iter()
+            .map(|tag| diagnostic_tag_to_str(*tag))
+            .collect::<Vec<_>>(),
+        "related": diagnostic
+            .related
+            .iter()
+            .map(|related| json!({
+                "message": related.message,
+                "uri": related.uri,
+                "range": value_utf16_range(related.range)
+            }))
+            .collect::<Vec<_>>(),
         "related_information_json": diagnostic.related_information_json,
         "data_json": diagnostic.data_json
     })
@@ -1720,6 +2019,18 @@ fn value_processing_edit(edit: &ProcessingEdit) -> Value {
             "layer": layer.0,
             "intervals": intervals.iter().map(value_interval).collect::<Vec<_>>()
         }),
+        ProcessingEdit::ReplaceStyleLayerRange {
+            layer,
+            start,
+            end,
+            intervals,
+        } => json!({
+            "op": "replace_style_layer_range",
+            "layer": layer.0,
+            "start": start,
+            "end": end,
+            "intervals": intervals.iter().map(value_interval).collect::<Vec<_>>()
+        }),
         ProcessingEdit::ClearStyleLayer { layer } => json!({
             "op": "clear_style_layer",
             "layer": layer.0
@@ -1738,6 +2049,18 @@ fn value_processing_edit(edit: &ProcessingEdit) -> Value {
             "diagnostics": diagnostics.iter().map(value_diagnostic).collect::<Vec<_>>()
         }),
         ProcessingEdit::ClearDiagnostics => json!({ "op": "clear_diagnostics" }),
+        ProcessingEdit::ReplaceProviderDiagnostics {
+            provider,
+            diagnostics,
+        } => json!({
+            "op": "replace_provider_diagnostics",
+            "provider": provider,
+            "diagnostics": diagnostics.iter().map(value_diagnostic).collect::<Vec<_>>()
+        }),
+        ProcessingEdit::ClearProviderDiagnostics { provider } => json!({
+            "op": "clear_provider_diagnostics",
+            "provider": provider,
+        }),
         ProcessingEdit::ReplaceDecorations { layer, decorations } => json!({
             "op": "replace_decorations",
             "layer": layer.0,
@@ -1785,6 +2108,7 @@ fn value_headless_line(line: &HeadlessLine) -> Value {
         "char_offset_end": line.char_offset_end,
         "segment_x_start_cells": line.segment_x_start_cells,
         "is_fold_placeholder_appended": line.is_fold_placeholder_appended,
+        "background_style": line.background_style,
         "cells": line.cells.iter().map(value_headless_cell).collect::<Vec<_>>(),
     })
 }
@@ -1834,6 +2158,7 @@ fn value_composed_cell(cell: &ComposedCell) -> Value {
         "ch": cell.ch.to_string(),
         "width": cell.width,
         "styles": cell.styles,
+        "underline": cell.underline.map(underline_style_to_str),
         "source": value_composed_cell_source(cell.source),
     })
 }
@@ -1857,7 +2182,14 @@ fn value_composed_line_kind(kind: ComposedLineKind) -> Value {
 fn value_composed_line(line: &ComposedLine) -> Value {
     json!({
         "kind": value_composed_line_kind(line.kind),
+        "background_style": line.background_style,
         "cells": line.cells.iter().map(value_composed_cell).collect::<Vec<_>>(),
+        "caret_columns": line.caret_columns,
+        "selection_columns": line
+            .selection_columns
+            .iter()
+            .map(|columns| value_offset_range(columns.start, columns.end))
+            .collect::<Vec<_>>(),
     })
 }
 
@@ -1949,7 +2281,14 @@ fn value_folding_state(state: &FoldingState) -> Value {
 }
 
 fn value_diagnostics_state(state: &DiagnosticsState) -> Value {
-    json!({ "diagnostics_count": state.diagnostics_count })
+    json!({
+        "diagnostics_count": state.diagnostics_count,
+        "error_count": state.error_count,
+        "warning_count": state.warning_count,
+        "information_count": state.information_count,
+        "hint_count": state.hint_count,
+        "provider_counts": state.provider_counts,
+    })
 }
 
 fn value_decorations_state(state: &DecorationsState) -> Value {
@@ -1960,7 +2299,11 @@ fn value_decorations_state(state: &DecorationsState) -> Value {
 }
 
 fn value_style_state(state: &StyleState) -> Value {
-    json!({ "style_count": state.style_count })
+    json!({
+        "style_count": state.style_count,
+        "search_query": state.search_query,
+        "search_match_count": state.search_match_count,
+    })
 }
 
 fn value_editor_state(state: &EditorState) -> Value {
@@ -2181,6 +2524,21 @@ pub extern "C" fn editor_core_ffi_editor_state_get_line_ending(
     })
 }
 
+/// Set the collapsed-fold summary template (e.g. `"{ … {lines} lines }"`); NULL restores
+/// static placeholders.
+#[unsafe(no_mangle)]
+pub extern "C" fn editor_core_ffi_editor_state_set_fold_placeholder_template(
+    state: *mut EcfEditorState,
+    template_str: *const c_char,
+) -> bool {
+    result_bool(false, || {
+        let state = require_mut(state, "state")?;
+        let template = optional_string(template_str, "template_str")?;
+        state.inner.set_fold_placeholder_template(template);
+        Ok(true)
+    })
+}
+
 /// Get styled viewport snapshot as JSON.
 #[unsafe(no_mangle)]
 pub extern "C" fn editor_core_ffi_editor_state_viewport_styled_json(
@@ -2211,7 +2569,7 @@ pub extern "C" fn editor_core_ffi_editor_state_minimap_json(
     })
 }
 
-/// Get decoration-aware composed snapshot as JSON.
+/// Get decoration-aware composed snapshot as JSON, including caret and selection columns.
 #[unsafe(no_mangle)]
 pub extern "C" fn editor_core_ffi_editor_state_viewport_composed_json(
     state: *const EcfEditorState,
@@ -2220,9 +2578,15 @@ pub extern "C" fn editor_core_ffi_editor_state_viewport_composed_json(
 ) -> *mut c_char {
     result_json_ptr(ptr::null_mut(), || {
         let state = require_ref(state, "state")?;
-        let grid = state
-            .inner
-            .get_viewport_content_composed(start_visual_row, count);
+        let options = SnapshotOptions {
+            include_selections: true,
+            ..SnapshotOptions::default()
+        };
+        let grid = state.inner.get_viewport_content_composed_with_options(
+            start_visual_row,
+            count,
+            &options,
+        );
         Ok(value_composed_grid(&grid))
     })
 }
@@ -2842,7 +3206,13 @@ pub extern "C" fn editor_core_ffi_lsp_diagnostics_to_processing_edits_json(
             return Err("invalid publishDiagnostics payload".to_string());
         };
 
-        let edits = lsp_diagnostics_to_processing_edits(&state.inner.editor().line_index, &params);
+        // The host owns the server connection and routes these edits itself, so keep the
+        // untagged `ReplaceDiagnostics` output here.
+        let edits = lsp_diagnostics_to_processing_edits_for(
+            &state.inner.editor().line_index,
+            &params,
+            &LspSessionOutput::default(),
+        );
         Ok(json!({
             "edits": edits.iter().map(value_processing_edit).collect::<Vec<_>>()
         }))
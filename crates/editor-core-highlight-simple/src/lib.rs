@@ -3,24 +3,42 @@
 //! This crate is intended for lightweight formats (JSON/INI/etc.) where full parsing or LSP
 //! integration is unnecessary.
 
-use editor_core::intervals::{Interval, StyleId, StyleLayerId};
+use editor_core::intervals::{Interval, StyleId, StyleLayerId, StyleRegistry};
+use editor_core::pattern::{CompiledPattern, PatternError, PatternLimits, PatternOptions};
 use editor_core::processing::{DocumentProcessor, ProcessingEdit};
 use editor_core::{EditorStateManager, LineIndex};
-use regex::Regex;
 use std::convert::Infallible;
 
 /// A single regex highlighting rule.
 #[derive(Debug, Clone)]
 pub struct RegexRule {
-    regex: Regex,
+    pattern: CompiledPattern,
     style_id: StyleId,
     capture_group: Option<usize>,
 }
 
 impl RegexRule {
-    pub fn new(pattern: &str, style_id: StyleId) -> Result<Self, regex::Error> {
+    pub fn new(pattern: &str, style_id: StyleId) -> Result<Self, PatternError> {
+        Self::with_limits(pattern, style_id, PatternLimits::default())
+    }
+
+    /// Compile a rule with explicit size limits.
+    ///
+    /// Oversized pure literal alternations (e.g. keyword lists) fall back to a literal-set
+    /// matcher instead of failing.
+    pub fn with_limits(
+        pattern: &str,
+        style_id: StyleId,
+        limits: PatternLimits,
+    ) -> Result<Self, PatternError> {
         Ok(Self {
-            regex: Regex::new(pattern)?,
+            pattern: editor_core::pattern::compile_pattern(
+                pattern,
+                PatternOptions {
+                    limits,
+                    ..PatternOptions::default()
+                },
+            )?,
             style_id,
             capture_group: None,
         })
@@ -70,31 +88,36 @@ impl RegexHighlighter {
             let line_start = line_index.position_to_char_offset(line, 0);
 
             for rule in &self.rules {
-                if let Some(group) = rule.capture_group {
-                    for caps in rule.regex.captures_iter(&line_text) {
-                        let Some(m) = caps.get(group) else {
-                            continue;
-                        };
-                        if let Some(interval) = interval_from_match(
-                            line_start,
-                            &line_text,
-                            m.start(),
-                            m.end(),
-                            rule.style_id,
-                        ) {
-                            intervals.push(interval);
+                match (&rule.pattern, rule.capture_group) {
+                    (CompiledPattern::Regex(regex), Some(group)) => {
+                        for caps in regex.captures_iter(&line_text) {
+                            let Some(m) = caps.get(group) else {
+                                continue;
+                            };
+                            if let Some(interval) = interval_from_match(
+                                line_start,
+                                &line_text,
+                                m.start(),
+                                m.end(),
+                                rule.style_id,
+                            ) {
+                                intervals.push(interval);
+                            }
                         }
                     }
-                } else {
-                    for m in rule.regex.find_iter(&line_text) {
-                        if let Some(interval) = interval_from_match(
-                            line_start,
-                            &line_text,
-                            m.start(),
-                            m.end(),
-                            rule.style_id,
-                        ) {
-                            intervals.push(interval);
+                    // A literal-set pattern has no capture groups other than the whole match.
+                    (CompiledPattern::Literals(_), Some(group)) if group > 0 => {}
+                    (pattern, _) => {
+                        for m in pattern.find_iter(&line_text) {
+                            if let Some(interval) = interval_from_match(
+                                line_start,
+                                &line_text,
+                                m.start,
+                                m.end,
+                                rule.style_id,
+                            ) {
+                                intervals.push(interval);
+                            }
                         }
                     }
                 }
@@ -107,7 +130,7 @@ impl RegexHighlighter {
     /// A small default JSON grammar (strings, numbers, booleans, null).
     ///
     /// Note: LSP semantic tokens are preferred for real code, but this is handy for simple formats.
-    pub fn json_default(styles: SimpleJsonStyles) -> Result<Self, regex::Error> {
+    pub fn json_default(styles: SimpleJsonStyles) -> Result<Self, PatternError> {
         Ok(Self::new(vec![
             // JSON string (single-line, handles escapes)
             RegexRule::new(r#""(?:\\.|[^"\\])*""#, styles.string)?,
@@ -123,7 +146,7 @@ impl RegexHighlighter {
     }
 
     /// A small default INI grammar (section, key, comment).
-    pub fn ini_default(styles: SimpleIniStyles) -> Result<Self, regex::Error> {
+    pub fn ini_default(styles: SimpleIniStyles) -> Result<Self, PatternError> {
         Ok(Self::new(vec![
             // Section header: [section]
             RegexRule::new(r#"^\s*\[([^\]]+)\]\s*$"#, styles.section)?.with_capture_group(1),
@@ -160,14 +183,14 @@ impl RegexHighlightProcessor {
         &mut self.highlighter
     }
 
-    pub fn json_default(styles: SimpleJsonStyles) -> Result<Self, regex::Error> {
+    pub fn json_default(styles: SimpleJsonStyles) -> Result<Self, PatternError> {
         Ok(Self::new(
             StyleLayerId::SIMPLE_SYNTAX,
             RegexHighlighter::json_default(styles)?,
         ))
     }
 
-    pub fn ini_default(styles: SimpleIniStyles) -> Result<Self, regex::Error> {
+    pub fn ini_default(styles: SimpleIniStyles) -> Result<Self, PatternError> {
         Ok(Self::new(
             StyleLayerId::SIMPLE_SYNTAX,
             RegexHighlighter::ini_default(styles)?,
@@ -206,6 +229,19 @@ impl Default for SimpleJsonStyles {
     }
 }
 
+impl SimpleJsonStyles {
+    /// Styles allocated from `registry` under the names `"string"`, `"number"`, `"boolean"`
+    /// and `"null"`.
+    pub fn from_registry(registry: &StyleRegistry) -> Self {
+        Self {
+            string: registry.style_id("string"),
+            number: registry.style_id("number"),
+            boolean: registry.style_id("boolean"),
+            null: registry.style_id("null"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct SimpleIniStyles {
     pub section: StyleId,
@@ -223,9 +259,22 @@ impl Default for SimpleIniStyles {
     }
 }
 
+impl SimpleIniStyles {
+    /// Styles allocated from `registry` under the names `"section"`, `"key"` and `"comment"`.
+    pub fn from_registry(registry: &StyleRegistry) -> Self {
+        Self {
+            section: registry.style_id("section"),
+            key: registry.style_id("key"),
+            comment: registry.style_id("comment"),
+        }
+    }
+}
+
 /// Default `StyleId` constants for `RegexHighlighter`-based grammars.
 ///
 /// These are only identifiers. UI/theme layer is expected to map them to actual colors.
+/// Hosts theming by name can use `SimpleJsonStyles::from_registry` /
+/// `SimpleIniStyles::from_registry` instead.
 pub const SIMPLE_STYLE_STRING: StyleId = 0x0200_0001;
 pub const SIMPLE_STYLE_NUMBER: StyleId = 0x0200_0002;
 pub const SIMPLE_STYLE_BOOLEAN: StyleId = 0x0200_0003;
@@ -287,4 +336,56 @@ mod tests {
         assert!(intervals.iter().any(|i| i.style_id == SIMPLE_STYLE_KEY));
         assert!(intervals.iter().any(|i| i.style_id == SIMPLE_STYLE_COMMENT));
     }
+
+    #[test]
+    fn test_styles_from_registry_share_names() {
+        let registry = StyleRegistry::new();
+        let json = SimpleJsonStyles::from_registry(&registry);
+        let ini = SimpleIniStyles::from_registry(&registry);
+
+        let text = "{ \"k\": 1 }";
+        let intervals = RegexHighlighter::json_default(json)
+            .unwrap()
+            .highlight(&LineIndex::from_text(text));
+        let string = intervals.iter().find(|i| i.start == 2).unwrap().style_id;
+        assert_eq!(
+            registry.name_for_style_id(string).as_deref(),
+            Some("string")
+        );
+        assert_eq!(
+            json.string,
+            SimpleJsonStyles::from_registry(&registry).string
+        );
+        assert_ne!(json.string, SIMPLE_STYLE_STRING);
+        assert_eq!(
+            registry.name_for_style_id(ini.comment).as_deref(),
+            Some("comment")
+        );
+    }
+
+    #[test]
+    fn test_regex_rule_literal_alternation_falls_back_when_too_large() {
+        let words: Vec<String> = (0..2_000).map(|i| format!("kw{}", i)).collect();
+        let pattern = format!("(?:{})", words.join("|"));
+        let limits = PatternLimits {
+            size_limit: 1024,
+            ..PatternLimits::default()
+        };
+
+        let rule = RegexRule::with_limits(&pattern, 7, limits).unwrap();
+        assert!(rule.pattern.is_literal_fallback());
+
+        let text = "kw1 kw10 x kw1999\nkw42kw7";
+        let line_index = LineIndex::from_text(text);
+        let fallback = RegexHighlighter::new(vec![rule]).highlight(&line_index);
+        let regex = RegexHighlighter::new(vec![RegexRule::new(&pattern, 7).unwrap()])
+            .highlight(&line_index);
+        assert_eq!(fallback, regex);
+        assert_eq!(fallback.len(), 5);
+
+        assert!(matches!(
+            RegexRule::with_limits("[a-z]{200}", 1, limits),
+            Err(PatternError::TooComplex { .. })
+        ));
+    }
 }
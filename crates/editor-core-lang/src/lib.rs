@@ -61,3 +61,41 @@ impl CommentConfig {
             && self.block_end.as_deref().is_some_and(|s| !s.is_empty())
     }
 }
+
+/// Editing configuration for a language: comment tokens, indentation and a language id.
+///
+/// `None` indentation fields leave the editor's current settings untouched.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LanguageConfig {
+    /// Language identifier (LSP-style, e.g. `rust`, `python`).
+    pub language_id: String,
+    /// Comment tokens used by comment toggling.
+    pub comment: CommentConfig,
+    /// Indent width in columns (also used as the tab width).
+    pub indent_width: Option<usize>,
+    /// Whether the Tab key inserts a tab character (`true`) or spaces (`false`).
+    pub use_tabs: Option<bool>,
+}
+
+impl LanguageConfig {
+    /// Create a config with only a language id.
+    pub fn new(language_id: impl Into<String>) -> Self {
+        Self {
+            language_id: language_id.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Set the comment tokens.
+    pub fn with_comment(mut self, comment: CommentConfig) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Set the indent width and whether to indent with tabs.
+    pub fn with_indent(mut self, width: usize, use_tabs: bool) -> Self {
+        self.indent_width = Some(width);
+        self.use_tabs = Some(use_tabs);
+        self
+    }
+}
@@ -9,26 +9,56 @@
 //! The API intentionally uses `serde_json::Value` instead of `lsp-types` to keep the dependency
 //! surface small and allow consumers to shape payloads as needed.
 
-use crate::lsp_client::{LspClient, LspInbound};
+use crate::lsp_client::{LspClient, LspInbound, LspRequestSender, LspRequestTimeouts};
+use crate::lsp_code_actions::{
+    CodeAction, CodeActionItem, LspCommand, code_action_context, code_action_items_from_value,
+};
+use crate::lsp_diagnostics::{
+    DocumentDiagnosticReport, DocumentDiagnostics, document_diagnostic_response_from_value,
+};
 use crate::lsp_events::{
-    LspEvent, LspNotification, LspResponse, LspResponseError, LspServerRequest,
-    LspServerRequestPolicy,
+    LspEvent, LspNotification, LspPublishDiagnosticsParams, LspResponse, LspResponseError,
+    LspServerRequest, LspServerRequestPolicy, LspServerRestart,
+};
+use crate::lsp_formatting::{LspFormattingOptions, apply_formatting_edits};
+use crate::lsp_highlights::CursorHighlightManager;
+use crate::lsp_hover::{HoverInfo, hover_from_value};
+use crate::lsp_locations::{LspLocation, locations_from_value};
+use crate::lsp_progress::{ProgressItem, ProgressTracker};
+use crate::lsp_rename::{PrepareRenameResult, prepare_rename_from_value};
+use crate::lsp_session_group::LspFeature;
+use crate::lsp_signature_help::{SignatureHelp, signature_help_from_value};
+use crate::lsp_symbols::{
+    document_symbols_from_value, parse_utf16_range, workspace_symbol_from_value,
+    workspace_symbol_needs_resolve,
 };
 use crate::lsp_sync::{
-    LspCoordinateConverter, LspPosition, LspRange, encode_semantic_style_id,
-    semantic_tokens_to_intervals,
+    LspCoordinateConverter, LspPosition, LspRange, SemanticTokensEdit, SemanticTokensManager,
+    encode_semantic_style_id, semantic_tokens_to_intervals,
 };
-use crate::lsp_text_edits::{apply_text_edits, workspace_edit_text_edits_for_uri};
-use editor_core::intervals::{FoldRegion, Interval, StyleId};
+use crate::lsp_text_edits::{
+    apply_text_edits, char_offsets_for_lsp_range, text_edits_from_value,
+    workspace_edit_text_edits_for_uri,
+};
+use crate::lsp_uri::path_to_file_uri;
+use crate::lsp_watched_files::{
+    FileChangeType, LspConfigurationItem, LspFileWatcher, configuration_items_from_value,
+    file_watchers_from_registrations, unregistration_ids,
+};
+use crate::lsp_workspace_symbols::WorkspaceSymbolQuery;
+use editor_core::intervals::{FoldRegion, Interval, StyleId, StyleRegistry};
 use editor_core::processing::{DocumentProcessor, ProcessingEdit};
 use editor_core::{
-    DecorationLayerId, Diagnostic, DiagnosticRange, DiagnosticSeverity, EditorStateManager,
-    LineIndex, StyleLayerId,
+    DecorationLayerId, Diagnostic, DiagnosticRange, DiagnosticSeverity, DocumentOutline,
+    EditorStateManager, FoldingMergePolicy, LineIndex, RelatedDiagnostic, StyleLayerId,
+    WorkspaceSymbol, diagnostic_tag_style_id,
 };
 use serde_json::{Value, json};
 use std::collections::{HashMap, VecDeque};
+use std::ffi::{OsStr, OsString};
 use std::io;
-use std::process::Command as ProcessCommand;
+use std::path::{Path, PathBuf};
+use std::process::{Command as ProcessCommand, Stdio};
 use std::time::{Duration, Instant};
 
 /// Clear LSP-derived state in the editor:
@@ -38,6 +68,7 @@ use std::time::{Duration, Instant};
 /// - `DecorationLayerId::INLAY_HINTS`
 /// - `DecorationLayerId::CODE_LENS`
 /// - `DecorationLayerId::DOCUMENT_LINKS`
+/// - `DecorationLayerId::HOVER`
 /// - all folding regions (typically sourced from LSP `foldingRange`)
 pub fn lsp_clear_edits() -> Vec<ProcessingEdit> {
     vec![
@@ -60,6 +91,9 @@ pub fn lsp_clear_edits() -> Vec<ProcessingEdit> {
         ProcessingEdit::ClearDecorations {
             layer: DecorationLayerId::DOCUMENT_LINKS,
         },
+        ProcessingEdit::ClearDecorations {
+            layer: DecorationLayerId::HOVER,
+        },
         ProcessingEdit::ClearFoldingRegions,
     ]
 }
@@ -78,6 +112,45 @@ pub struct SemanticTokensLegend {
     pub token_modifiers: Vec<String>,
 }
 
+impl SemanticTokensLegend {
+    /// Stable style name for a token, e.g. `semantic:function.declaration.static`.
+    ///
+    /// Modifiers are appended in bit order; indices missing from the legend are written as
+    /// numbers.
+    pub fn style_name(&self, token_type: u32, token_modifiers: u32) -> String {
+        let mut name = String::from("semantic:");
+        match self.token_types.get(token_type as usize) {
+            Some(ty) => name.push_str(ty),
+            None => name.push_str(&token_type.to_string()),
+        }
+        for bit in 0..u32::BITS {
+            if token_modifiers & (1 << bit) == 0 {
+                continue;
+            }
+            name.push('.');
+            match self.token_modifiers.get(bit as usize) {
+                Some(modifier) => name.push_str(modifier),
+                None => name.push_str(&bit.to_string()),
+            }
+        }
+        name
+    }
+}
+
+/// Map semantic tokens through `registry` when both it and the legend are known, otherwise
+/// fall back to [`encode_semantic_style_id`].
+fn semantic_style_resolver<'a>(
+    registry: Option<&'a StyleRegistry>,
+    legend: Option<&'a SemanticTokensLegend>,
+) -> impl Fn(u32, u32) -> StyleId + 'a {
+    move |token_type, token_modifiers| match (registry, legend) {
+        (Some(registry), Some(legend)) => {
+            registry.style_id(&legend.style_name(token_type, token_modifiers))
+        }
+        _ => encode_semantic_style_id(token_type, token_modifiers),
+    }
+}
+
 #[derive(Debug, Clone)]
 /// A document tracked by the LSP session.
 pub struct LspDocument {
@@ -128,6 +201,84 @@ impl Default for LspAutoRefreshOptions {
     }
 }
 
+/// Where an [`LspSession`] writes its derived state.
+///
+/// A session starts with [`LspSessionOutput::for_server`] of its server name, so its diagnostics
+/// replace only its own and other sources (e.g. a spell-checker) keep theirs. When several
+/// sessions serve the same document (see [`crate::LspSessionGroup`]), give each one its own
+/// `provider` and diagnostics layer so they don't overwrite each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspSessionOutput {
+    /// Diagnostic provider name. With `None`, diagnostics replace the whole list
+    /// (`ProcessingEdit::ReplaceDiagnostics`); otherwise only this provider's diagnostics
+    /// (`ProcessingEdit::ReplaceProviderDiagnostics`).
+    pub provider: Option<String>,
+    /// Style layer for semantic tokens; `None` turns semantic highlighting off for the session.
+    pub semantic_tokens_layer: Option<StyleLayerId>,
+    /// Style layer for diagnostic underlines.
+    pub diagnostics_layer: StyleLayerId,
+    /// Whether the session requests folding ranges (and clears them if unsupported).
+    pub folding_ranges: bool,
+}
+
+impl Default for LspSessionOutput {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            semantic_tokens_layer: Some(StyleLayerId::SEMANTIC_TOKENS),
+            diagnostics_layer: StyleLayerId::DIAGNOSTICS,
+            folding_ranges: true,
+        }
+    }
+}
+
+impl LspSessionOutput {
+    /// The default output, with diagnostics tagged as `server_name`'s.
+    pub fn for_server(server_name: impl Into<String>) -> Self {
+        Self {
+            provider: Some(server_name.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Output for one of several servers: `provider`'s diagnostics underlined in
+    /// `diagnostics_layer`, semantic tokens in `semantic_tokens_layer`.
+    pub fn for_provider(
+        provider: impl Into<String>,
+        semantic_tokens_layer: StyleLayerId,
+        diagnostics_layer: StyleLayerId,
+    ) -> Self {
+        Self {
+            provider: Some(provider.into()),
+            semantic_tokens_layer: Some(semantic_tokens_layer),
+            diagnostics_layer,
+            folding_ranges: true,
+        }
+    }
+
+    /// Edits that clear the state written through this output (semantic tokens, diagnostics
+    /// and, if enabled, folding regions).
+    pub fn clear_edits(&self) -> Vec<ProcessingEdit> {
+        let mut edits = Vec::new();
+        if let Some(layer) = self.semantic_tokens_layer {
+            edits.push(ProcessingEdit::ClearStyleLayer { layer });
+        }
+        edits.push(ProcessingEdit::ClearStyleLayer {
+            layer: self.diagnostics_layer,
+        });
+        edits.push(match &self.provider {
+            Some(provider) => ProcessingEdit::ClearProviderDiagnostics {
+                provider: provider.clone(),
+            },
+            None => ProcessingEdit::ClearDiagnostics,
+        });
+        if self.folding_ranges {
+            edits.push(ProcessingEdit::ClearFoldingRegions);
+        }
+        edits
+    }
+}
+
 #[derive(Debug)]
 /// Options used to spawn and initialize an [`LspSession`].
 pub struct LspSessionStartOptions {
@@ -144,14 +295,157 @@ pub struct LspSessionStartOptions {
     pub document: LspDocument,
     /// Initial full text to send in `textDocument/didOpen`.
     pub initial_text: String,
+    /// Automatic restart after the server crashes (disabled by default).
+    pub restart_policy: LspRestartPolicy,
+}
+
+/// When to restart a server whose connection was lost (see [`LspSession::restart`]).
+///
+/// The `n`-th automatic restart waits `initial_backoff * 2^(n-1)`, capped at `max_backoff`.
+/// Once `max_attempts` restarts were used, a lost connection is reported as an error by the
+/// poll functions, as without a policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LspRestartPolicy {
+    /// Maximum number of automatic restarts over the session's lifetime (`0` disables them).
+    pub max_attempts: u32,
+    /// Delay before the first automatic restart.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between restarts.
+    pub max_backoff: Duration,
+}
+
+impl LspRestartPolicy {
+    /// No automatic restarts.
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::from_millis(500), Duration::from_secs(30))
+    }
+
+    /// Restart up to `max_attempts` times with exponential backoff.
+    pub fn new(max_attempts: u32, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff,
+            max_backoff,
+        }
+    }
+
+    /// Delay before restart number `attempt` (1-based).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for LspRestartPolicy {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+/// Everything needed to spawn and initialize the server again.
+///
+/// [`ProcessCommand`] is not `Clone`, so the command is captured through its getters; its stdio
+/// configuration cannot be read back, and respawned servers get a null `stderr`.
+#[derive(Debug)]
+struct LspLaunchConfig {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, Option<OsString>)>,
+    current_dir: Option<PathBuf>,
+    workspace_folders: Vec<Value>,
+    initialize_params: Value,
+    initialize_timeout: Duration,
+}
+
+impl LspLaunchConfig {
+    fn command(&self) -> ProcessCommand {
+        let mut cmd = ProcessCommand::new(&self.program);
+        cmd.args(&self.args).stderr(Stdio::null());
+        for (key, value) in &self.envs {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+
+    /// Spawn `cmd`, run `initialize`, and open `document` with `text`.
+    ///
+    /// Returns the client and the `initialize` result.
+    fn connect(
+        &self,
+        cmd: ProcessCommand,
+        document: &LspDocument,
+        text: &str,
+        previous: Option<&LspClient>,
+    ) -> io::Result<(LspClient, Value)> {
+        let mut client = LspClient::spawn(cmd, self.workspace_folders.clone())?;
+        if let Some(previous) = previous {
+            client.continue_request_ids(previous);
+            client.set_request_timeouts(previous.request_timeouts().clone());
+        }
+        match self.handshake(&mut client, document, text) {
+            Ok(result) => Ok((client, result)),
+            Err(err) => {
+                let _ = client.kill();
+                Err(err)
+            }
+        }
+    }
+
+    fn handshake(
+        &self,
+        client: &mut LspClient,
+        document: &LspDocument,
+        text: &str,
+    ) -> io::Result<Value> {
+        let init_id = client.request("initialize", self.initialize_params.clone())?;
+        let init_resp = client.wait_for_response(init_id, self.initialize_timeout)?;
+        let result = init_resp.get("result").cloned().unwrap_or(Value::Null);
+
+        client.notify("initialized", json!({}))?;
+
+        client.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": document.uri.clone(),
+                    "languageId": document.language_id.clone(),
+                    "version": document.version,
+                    "text": text,
+                }
+            }),
+        )?;
+        Ok(result)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 enum PendingLspRequest {
-    SemanticTokens { version: i32 },
-    FoldingRanges { version: i32 },
+    SemanticTokens {
+        version: i32,
+        delta: bool,
+    },
+    SemanticTokensRange {
+        version: i32,
+        start_line: usize,
+        end_line: usize,
+    },
+    FoldingRanges {
+        version: i32,
+    },
+    DocumentHighlights,
 }
 
+/// Host callback answering one `workspace/configuration` item.
+type ConfigurationProvider = Box<dyn FnMut(&LspConfigurationItem) -> Value + Send>;
+
 /// A small, runtime-agnostic LSP integration for `editor-core`.
 ///
 /// This is designed to be generic across LSP servers:
@@ -168,21 +462,44 @@ pub struct LspSession {
     semantic_legend: Option<SemanticTokensLegend>,
     supports_semantic_tokens: bool,
     supports_semantic_tokens_delta: bool,
+    supports_semantic_tokens_range: bool,
     supports_folding_range: bool,
+    supports_pull_diagnostics: bool,
 
     pending: HashMap<u64, PendingLspRequest>,
     pending_client_requests: HashMap<u64, String>,
     refresh_due: Option<Instant>,
     auto_refresh: LspAutoRefreshOptions,
+    folding_merge_policy: FoldingMergePolicy,
 
-    semantic_tokens_result_id: Option<String>,
-    semantic_tokens_data: Vec<u32>,
+    semantic_tokens: SemanticTokensManager,
+    viewport_lines: Option<(usize, usize)>,
+    diagnostics: DocumentDiagnostics,
 
     // Headless UX + deferred server->client requests.
     events: VecDeque<LspEvent>,
     event_queue_capacity: usize,
     server_request_policy: LspServerRequestPolicy,
     deferred_requests: HashMap<u64, LspServerRequest>,
+
+    // Blocking requests (e.g. hover_at) + messages received while waiting for them.
+    request_timeout: Duration,
+    stashed_inbound: VecDeque<LspInbound>,
+
+    next_partial_result_token: u64,
+    progress: ProgressTracker,
+    output: LspSessionOutput,
+    style_registry: Option<StyleRegistry>,
+
+    file_watchers: Vec<LspFileWatcher>,
+    configuration_provider: Option<ConfigurationProvider>,
+    cursor_highlights: Option<CursorHighlightManager>,
+
+    launch: LspLaunchConfig,
+    restart_policy: LspRestartPolicy,
+    restart_attempts: u32,
+    /// A scheduled automatic restart and why it is needed.
+    restart_due: Option<(Instant, String)>,
 }
 
 impl LspSession {
@@ -198,63 +515,196 @@ impl LspSession {
             initialize_timeout,
             document,
             initial_text,
+            restart_policy,
         } = opts;
 
-        let mut client = LspClient::spawn(cmd, workspace_folders)?;
-
-        let init_id = client.request("initialize", initialize_params)?;
-        let init_resp = client.wait_for_response(init_id, initialize_timeout)?;
-
-        let result = init_resp.get("result").cloned().unwrap_or(Value::Null);
-        let server_info = parse_server_info(&result);
-        let server_capabilities = result.get("capabilities").cloned().unwrap_or(Value::Null);
-
-        let (supports_semantic_tokens, semantic_legend) =
-            parse_semantic_tokens_legend(&server_capabilities);
-        let supports_semantic_tokens_delta =
-            parse_supports_semantic_tokens_delta(&server_capabilities);
-        let supports_folding_range = parse_supports_folding_range(&server_capabilities);
-
-        client.notify("initialized", json!({}))?;
-
-        client.notify(
-            "textDocument/didOpen",
-            json!({
-                "textDocument": {
-                    "uri": document.uri.clone(),
-                    "languageId": document.language_id.clone(),
-                    "version": document.version,
-                    "text": initial_text,
-                }
-            }),
-        )?;
+        let launch = LspLaunchConfig {
+            program: cmd.get_program().to_owned(),
+            args: cmd.get_args().map(OsStr::to_owned).collect(),
+            envs: cmd
+                .get_envs()
+                .map(|(key, value)| (key.to_owned(), value.map(OsStr::to_owned)))
+                .collect(),
+            current_dir: cmd.get_current_dir().map(Path::to_path_buf),
+            workspace_folders,
+            initialize_params,
+            initialize_timeout,
+        };
+        let (client, result) = launch.connect(cmd, &document, &initial_text, None)?;
 
         let mut session = Self {
             client,
             document,
             extra_documents: HashMap::new(),
-            server_info,
-            server_capabilities,
-            semantic_legend,
-            supports_semantic_tokens,
-            supports_semantic_tokens_delta,
-            supports_folding_range,
+            server_info: None,
+            server_capabilities: Value::Null,
+            semantic_legend: None,
+            supports_semantic_tokens: false,
+            supports_semantic_tokens_delta: false,
+            supports_semantic_tokens_range: false,
+            supports_folding_range: false,
+            supports_pull_diagnostics: false,
             pending: HashMap::new(),
             pending_client_requests: HashMap::new(),
             refresh_due: None,
             auto_refresh: LspAutoRefreshOptions::default(),
-            semantic_tokens_result_id: None,
-            semantic_tokens_data: Vec::new(),
+            folding_merge_policy: FoldingMergePolicy::default(),
+            semantic_tokens: SemanticTokensManager::new(),
+            viewport_lines: None,
+            diagnostics: DocumentDiagnostics::new(),
             events: VecDeque::new(),
             event_queue_capacity: 256,
             server_request_policy: LspServerRequestPolicy::default(),
             deferred_requests: HashMap::new(),
+            request_timeout: Duration::from_secs(2),
+            stashed_inbound: VecDeque::new(),
+            next_partial_result_token: 1,
+            progress: ProgressTracker::new(),
+            output: LspSessionOutput::default(),
+            style_registry: None,
+            file_watchers: Vec::new(),
+            configuration_provider: None,
+            cursor_highlights: None,
+            launch,
+            restart_policy,
+            restart_attempts: 0,
+            restart_due: None,
         };
 
+        session.apply_initialize_result(&result);
+        session.output = LspSessionOutput::for_server(session.server_name());
         session.schedule_refresh(Duration::from_millis(0));
         Ok(session)
     }
 
+    fn apply_initialize_result(&mut self, result: &Value) {
+        self.server_info = parse_server_info(result);
+        self.server_capabilities = result.get("capabilities").cloned().unwrap_or(Value::Null);
+
+        let capabilities = &self.server_capabilities;
+        (self.supports_semantic_tokens, self.semantic_legend) =
+            parse_semantic_tokens_legend(capabilities);
+        self.supports_semantic_tokens_delta = parse_supports_semantic_tokens_delta(capabilities);
+        self.supports_semantic_tokens_range = parse_supports_semantic_tokens_range(capabilities);
+        self.supports_folding_range = parse_supports_folding_range(capabilities);
+        self.supports_pull_diagnostics = capabilities.get("diagnosticProvider").is_some();
+    }
+
+    /// Restart the server: `shutdown`/`exit` it (or notice it is dead), spawn the configured
+    /// command again, re-run `initialize` with the same params and reopen the active document
+    /// with `text` and its current version.
+    ///
+    /// Derived state is refreshed from the new server (semantic tokens, folding ranges; the
+    /// server republishes diagnostics). No clearing edits are produced, so the editor keeps the
+    /// old highlighting and diagnostics until fresh ones replace them. Requests still waiting
+    /// for a response are abandoned and extra documents are closed; both are listed in the
+    /// [`LspEvent::ServerRestarted`] event. This also resets the automatic restart count.
+    pub fn restart(&mut self, text: &str) -> Result<(), String> {
+        self.restart_attempts = 0;
+        self.restart_due = None;
+        self.restart_with_reason(text, "restart requested".to_string())
+    }
+
+    /// The automatic restart policy.
+    pub fn restart_policy(&self) -> LspRestartPolicy {
+        self.restart_policy
+    }
+
+    /// Set the automatic restart policy.
+    pub fn set_restart_policy(&mut self, policy: LspRestartPolicy) {
+        self.restart_policy = policy;
+    }
+
+    /// Returns `true` while an automatic restart is scheduled (the server is down).
+    pub fn is_restarting(&self) -> bool {
+        self.restart_due.is_some()
+    }
+
+    fn restart_with_reason(&mut self, text: &str, reason: String) -> Result<(), String> {
+        if self.client.is_running() {
+            if let Ok(id) = self.client.request("shutdown", Value::Null) {
+                let _ = self.client.wait_for_response(id, self.request_timeout);
+            }
+            let _ = self.client.notify("exit", Value::Null);
+        }
+        let _ = self.client.kill();
+
+        let (client, result) = self
+            .launch
+            .connect(
+                self.launch.command(),
+                &self.document,
+                text,
+                Some(&self.client),
+            )
+            .map_err(|err| format!("LSP 重启失败: {}", err))?;
+        self.client = client;
+
+        let mut abandoned_requests: Vec<u64> =
+            self.pending_client_requests.keys().copied().collect();
+        abandoned_requests.sort_unstable();
+        let mut closed_documents: Vec<String> = self.extra_documents.keys().cloned().collect();
+        closed_documents.sort();
+
+        self.pending.clear();
+        self.pending_client_requests.clear();
+        self.deferred_requests.clear();
+        self.extra_documents.clear();
+        self.stashed_inbound.clear();
+        self.progress.clear();
+        self.semantic_tokens = SemanticTokensManager::new();
+        self.diagnostics.clear();
+        self.apply_initialize_result(&result);
+        self.schedule_refresh(Duration::from_millis(0));
+        // The new server registers its watchers again.
+        if !self.file_watchers.is_empty() {
+            self.file_watchers.clear();
+            self.push_event(LspEvent::FileWatchersChanged);
+        }
+
+        let restarted = LspEvent::ServerRestarted(LspServerRestart {
+            attempt: self.restart_attempts,
+            reason,
+            abandoned_requests,
+            closed_documents,
+        });
+        // The highlight request (if any) was abandoned: ask the new server.
+        if let Some(highlights) = self.cursor_highlights.as_mut() {
+            highlights.handle_event(&restarted);
+        }
+        self.push_event(restarted);
+        Ok(())
+    }
+
+    /// Schedule an automatic restart if the policy allows another one.
+    fn schedule_restart(&mut self, reason: String) -> bool {
+        if self.restart_attempts >= self.restart_policy.max_attempts {
+            return false;
+        }
+        self.restart_attempts += 1;
+        let delay = self.restart_policy.backoff(self.restart_attempts);
+        self.restart_due = Some((Instant::now() + delay, reason));
+        true
+    }
+
+    /// Run a scheduled automatic restart once it is due.
+    ///
+    /// Returns `Ok(false)` while the server is still down.
+    fn run_due_restart(&mut self, line_index: &LineIndex) -> Result<bool, String> {
+        let Some((due, _)) = &self.restart_due else {
+            return Ok(true);
+        };
+        if Instant::now() < *due {
+            return Ok(false);
+        }
+        let (_, reason) = self.restart_due.take().expect("checked");
+        match self.restart_with_reason(&line_index.get_text(), reason) {
+            Ok(()) => Ok(true),
+            Err(err) if self.schedule_restart(err.clone()) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Get a reference to the underlying stdio JSON-RPC client.
     pub fn client(&self) -> &LspClient {
         &self.client
@@ -310,6 +760,19 @@ impl LspSession {
         self.server_info.as_ref()
     }
 
+    /// The server's name: `serverInfo.name` from `initialize`, or else the file name of the
+    /// server command.
+    pub fn server_name(&self) -> String {
+        match &self.server_info {
+            Some(info) => info.name.clone(),
+            None => Path::new(&self.launch.program)
+                .file_name()
+                .unwrap_or(self.launch.program.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+
     /// Raw `capabilities` JSON from the `initialize` response.
     pub fn server_capabilities(&self) -> &Value {
         &self.server_capabilities
@@ -322,7 +785,7 @@ impl LspSession {
 
     /// The last semantic tokens `resultId` received from the server (for delta requests).
     pub fn semantic_tokens_result_id(&self) -> Option<&str> {
-        self.semantic_tokens_result_id.as_deref()
+        self.semantic_tokens.result_id()
     }
 
     /// Returns `true` if the server advertises `semanticTokensProvider`.
@@ -335,11 +798,175 @@ impl LspSession {
         self.supports_semantic_tokens_delta
     }
 
+    /// Returns `true` if the server supports semantic tokens range requests.
+    pub fn supports_semantic_tokens_range(&self) -> bool {
+        self.supports_semantic_tokens_range
+    }
+
+    /// Set the logical lines (`start..end`, end exclusive) currently shown by the host.
+    ///
+    /// When set, a refresh that has to fetch full semantic tokens first requests
+    /// `semanticTokens/range` for these lines, so the viewport gets styled before the (possibly
+    /// slow) full response lands.
+    pub fn set_viewport_hint(&mut self, lines: Option<(usize, usize)>) {
+        self.viewport_lines = lines;
+    }
+
+    /// Set the viewport hint from the state manager's viewport (scroll position and height).
+    ///
+    /// Visual rows are mapped back to logical lines, so wrapping and folding are accounted for.
+    /// Without a known viewport height the hint is cleared.
+    pub fn set_viewport_hint_from_state(&mut self, state_manager: &EditorStateManager) {
+        let viewport = state_manager.get_viewport_state();
+        let lines = viewport.height.filter(|height| *height > 0).map(|height| {
+            let last_row = viewport
+                .scroll_top
+                .saturating_add(height - 1)
+                .min(viewport.total_visual_lines.saturating_sub(1));
+            let (start_line, _) = state_manager.visual_to_logical_line(viewport.scroll_top);
+            let (end_line, _) = state_manager.visual_to_logical_line(last_row);
+            (start_line, end_line.max(start_line) + 1)
+        });
+        self.set_viewport_hint(lines);
+    }
+
+    /// The current viewport hint, if any.
+    pub fn viewport_hint(&self) -> Option<(usize, usize)> {
+        self.viewport_lines
+    }
+
+    /// Highlight the symbol under the cursor automatically.
+    ///
+    /// After [`notify_cursor_moved`](Self::notify_cursor_moved), the session waits `debounce`,
+    /// requests `textDocument/documentHighlight` and writes the result into
+    /// `StyleLayerId::DOCUMENT_HIGHLIGHTS` (`DOCUMENT_HIGHLIGHT_*` style ids) on a later poll.
+    /// Highlights are cleared when the cursor leaves them and on edits.
+    pub fn enable_cursor_highlights(&mut self, debounce: Duration) {
+        self.cursor_highlights
+            .get_or_insert_with(|| CursorHighlightManager::new(self.document.uri.clone()))
+            .set_debounce(debounce);
+    }
+
+    /// Stop highlighting the symbol under the cursor.
+    ///
+    /// Returns the edit clearing the current highlights, if any are shown.
+    pub fn disable_cursor_highlights(&mut self) -> Option<ProcessingEdit> {
+        let highlights = self.cursor_highlights.take()?;
+        highlights
+            .has_highlights()
+            .then_some(ProcessingEdit::ClearStyleLayer {
+                layer: StyleLayerId::DOCUMENT_HIGHLIGHTS,
+            })
+    }
+
+    /// Report the cursor position (char offset in the active document) for cursor highlights.
+    ///
+    /// Moving within the current highlights keeps them; moving elsewhere clears them and
+    /// requests new ones once the cursor has rested for the debounce delay. Call this after
+    /// [`did_change`](Self::did_change) when an edit moved the cursor. Does nothing unless
+    /// [`enable_cursor_highlights`](Self::enable_cursor_highlights) was called.
+    pub fn notify_cursor_moved(&mut self, offset: usize) {
+        if let Some(highlights) = self.cursor_highlights.as_mut() {
+            highlights.notify_cursor_moved(offset, Instant::now());
+        }
+    }
+
+    /// Char ranges highlighted by cursor highlights (empty when disabled).
+    pub fn cursor_highlight_ranges(&self) -> &[(usize, usize)] {
+        self.cursor_highlights
+            .as_ref()
+            .map(CursorHighlightManager::ranges)
+            .unwrap_or_default()
+    }
+
     /// Returns `true` if the server supports folding ranges.
     pub fn supports_folding_range(&self) -> bool {
         self.supports_folding_range
     }
 
+    /// Returns `true` if the server supports pull diagnostics (`diagnosticProvider`).
+    pub fn supports_pull_diagnostics(&self) -> bool {
+        self.supports_pull_diagnostics
+    }
+
+    /// Pushed and pulled diagnostics of the active document.
+    pub fn diagnostics(&self) -> &DocumentDiagnostics {
+        &self.diagnostics
+    }
+
+    /// Work-done progress operations (indexing, builds, …) the server is running, in the order
+    /// they began.
+    ///
+    /// Updated while polling; each change is also emitted as [`LspEvent::ProgressChanged`].
+    pub fn active_progress(&self) -> Vec<ProgressItem> {
+        self.progress.active_progress()
+    }
+
+    /// Pull diagnostics for the active document (`textDocument/diagnostic`), waiting for the
+    /// server's answer.
+    ///
+    /// The request carries the `resultId` of the previous report. A full report replaces the
+    /// previously pulled diagnostics and yields edits for the merged push + pull set; an
+    /// unchanged report yields no edits. Full reports for `relatedDocuments` are queued as
+    /// [`LspNotification::PublishDiagnostics`] events.
+    pub fn pull_diagnostics(
+        &mut self,
+        line_index: &LineIndex,
+    ) -> Result<Vec<ProcessingEdit>, String> {
+        let mut params = json!({ "textDocument": { "uri": self.document.uri.as_str() } });
+        if let Some(result_id) = self.diagnostics.pull_result_id() {
+            params["previousResultId"] = json!(result_id);
+        }
+        let result = self.request_and_wait("textDocument/diagnostic", params)?;
+        let response = document_diagnostic_response_from_value(&result)
+            .ok_or_else(|| "Invalid textDocument/diagnostic response".to_string())?;
+
+        for (uri, report) in response.related_documents {
+            if let DocumentDiagnosticReport::Full { items, .. } = report {
+                self.push_event(LspEvent::Notification(LspNotification::PublishDiagnostics(
+                    LspPublishDiagnosticsParams {
+                        uri,
+                        diagnostics: items,
+                        version: None,
+                    },
+                )));
+            }
+        }
+
+        if !self.diagnostics.apply_pull_report(&response.report) {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .diagnostics
+            .to_processing_edits(&self.document.uri, line_index, &self.output))
+    }
+
+    /// Where this session writes semantic tokens, diagnostics and folding regions.
+    pub fn output(&self) -> &LspSessionOutput {
+        &self.output
+    }
+
+    /// Change where this session writes its derived state.
+    ///
+    /// Call this before polling; state already written through the previous output is not
+    /// moved (apply [`LspSessionOutput::clear_edits`] of the old output to remove it).
+    pub fn set_output(&mut self, output: LspSessionOutput) {
+        self.output = output;
+    }
+
+    /// The registry semantic token styles are allocated from, if any.
+    pub fn style_registry(&self) -> Option<&StyleRegistry> {
+        self.style_registry.as_ref()
+    }
+
+    /// Allocate semantic token styles from `registry` (named `semantic:<type>.<modifiers>`)
+    /// instead of encoding them with [`encode_semantic_style_id`].
+    ///
+    /// Applies to semantic tokens received after the call.
+    pub fn set_style_registry(&mut self, registry: Option<StyleRegistry>) {
+        self.style_registry = registry;
+    }
+
     /// Get the current auto-refresh options.
     pub fn auto_refresh_options(&self) -> LspAutoRefreshOptions {
         self.auto_refresh
@@ -350,6 +977,20 @@ impl LspSession {
         self.auto_refresh = opts;
     }
 
+    /// How server folding ranges are combined with indentation-based folding.
+    pub fn folding_merge_policy(&self) -> FoldingMergePolicy {
+        self.folding_merge_policy
+    }
+
+    /// Combine server folding ranges with indentation-based folding.
+    ///
+    /// With [`FoldingMergePolicy::IndentationFallback`], indentation regions fill in what the
+    /// server's ranges don't cover, and stand in for them when the server has no
+    /// `foldingRangeProvider`. Takes effect on the next refresh.
+    pub fn set_folding_merge_policy(&mut self, policy: FoldingMergePolicy) {
+        self.folding_merge_policy = policy;
+    }
+
     /// Configure how server->client requests are handled.
     ///
     /// - The default is [`LspServerRequestPolicy::auto_reply`], which responds immediately with
@@ -380,6 +1021,30 @@ impl LspSession {
         self.event_queue_capacity
     }
 
+    /// Set how long blocking requests such as [`LspSession::hover_at`] wait for a response.
+    pub fn set_request_timeout(&mut self, timeout: Duration) {
+        self.request_timeout = timeout;
+    }
+
+    /// Get the timeout used by blocking requests.
+    pub fn request_timeout(&self) -> Duration {
+        self.request_timeout
+    }
+
+    /// Set per-method deadlines for requests sent from now on.
+    ///
+    /// Unlike [`set_request_timeout`](Self::set_request_timeout), this applies to non-blocking
+    /// requests: each poll cancels the requests past their deadline (`$/cancelRequest`) and
+    /// emits [`LspEvent::RequestTimedOut`] for those sent through [`LspSession::request`].
+    pub fn set_request_timeouts(&mut self, timeouts: LspRequestTimeouts) {
+        self.client.set_request_timeouts(timeouts);
+    }
+
+    /// Get the per-method request deadlines.
+    pub fn request_timeouts(&self) -> &LspRequestTimeouts {
+        self.client.request_timeouts()
+    }
+
     /// Drain captured LSP events (UX notifications + deferred server requests).
     pub fn drain_events(&mut self) -> Vec<LspEvent> {
         let mut out = Vec::with_capacity(self.events.len());
@@ -454,6 +1119,9 @@ impl LspSession {
         }
 
         self.document.version = self.document.version.saturating_add(1);
+        if let Some(highlights) = self.cursor_highlights.as_mut() {
+            highlights.document_changed();
+        }
 
         let content_changes = changes
             .into_iter()
@@ -473,8 +1141,16 @@ impl LspSession {
             "contentChanges": content_changes,
         });
 
+        // While the server is down the restart re-opens the document with its current text.
+        if self.is_restarting() {
+            return Ok(());
+        }
         if let Err(err) = self.client.notify("textDocument/didChange", params) {
-            return Err(format!("LSP didChange 失败，已禁用: {}", err));
+            let reason = format!("LSP didChange 失败，已禁用: {}", err);
+            if !self.schedule_restart(reason.clone()) {
+                return Err(reason);
+            }
+            return Ok(());
         }
 
         self.schedule_refresh(self.auto_refresh.delay);
@@ -526,7 +1202,11 @@ impl LspSession {
 
         let prev = std::mem::replace(&mut self.document, next);
         self.extra_documents.insert(prev.uri.clone(), prev);
+        if let Some(highlights) = self.cursor_highlights.as_mut() {
+            highlights.set_uri(uri);
+        }
         self.clear_semantic_tokens_cache();
+        self.diagnostics.clear();
         self.schedule_refresh(Duration::from_millis(0));
         Ok(())
     }
@@ -543,7 +1223,11 @@ impl LspSession {
                 let next_uri = next_uri.clone();
                 let next = self.extra_documents.remove(&next_uri).expect("checked");
                 self.document = next;
+                if let Some(highlights) = self.cursor_highlights.as_mut() {
+                    highlights.set_uri(next_uri);
+                }
                 self.clear_semantic_tokens_cache();
+                self.diagnostics.clear();
                 self.schedule_refresh(Duration::from_millis(0));
             }
         } else {
@@ -695,9 +1379,110 @@ impl LspSession {
         )
     }
 
+    /// Report file changes seen by the host's file watcher (`workspace/didChangeWatchedFiles`).
+    ///
+    /// Paths are converted with [`path_to_file_uri`]. Filtering against
+    /// [`LspSession::watched_globs`] is left to the host.
+    pub fn notify_watched_files_changed<P: AsRef<Path>>(
+        &mut self,
+        changes: Vec<(P, FileChangeType)>,
+    ) -> Result<(), String> {
+        let changes = changes
+            .into_iter()
+            .map(|(path, change)| {
+                json!({
+                    "uri": path_to_file_uri(path.as_ref()),
+                    "type": change.to_lsp(),
+                })
+            })
+            .collect();
+        self.did_change_watched_files(changes)
+    }
+
+    /// Tell the server its settings changed (`workspace/didChangeConfiguration`).
+    ///
+    /// Many servers ignore `settings` and pull the new values with `workspace/configuration`;
+    /// set a [`LspSession::set_configuration_provider`] to answer those.
+    pub fn notify_configuration_changed(&mut self, settings: Value) -> Result<(), String> {
+        self.did_change_configuration(settings)
+    }
+
+    /// Answer `workspace/configuration` requests with `provider`, called once per requested
+    /// item (a `null` result means "no settings").
+    ///
+    /// Without a provider every item is answered with `null`. A deferring
+    /// [`LspServerRequestPolicy`] still takes precedence.
+    pub fn set_configuration_provider<F>(&mut self, provider: F)
+    where
+        F: FnMut(&LspConfigurationItem) -> Value + Send + 'static,
+    {
+        self.configuration_provider = Some(Box::new(provider));
+    }
+
+    /// File watchers the server registered for `workspace/didChangeWatchedFiles` (via
+    /// `client/registerCapability`), in registration order.
+    ///
+    /// [`LspEvent::FileWatchersChanged`] is emitted whenever this list changes.
+    pub fn watched_globs(&self) -> &[LspFileWatcher] {
+        &self.file_watchers
+    }
+
+    /// Track `client/registerCapability` / `client/unregisterCapability` for file watchers.
+    fn observe_registration_request(&mut self, method: &str, params: &Value) {
+        let changed = match method {
+            "client/registerCapability" => {
+                let watchers = file_watchers_from_registrations(params);
+                let changed = !watchers.is_empty();
+                self.file_watchers.extend(watchers);
+                changed
+            }
+            "client/unregisterCapability" => {
+                let ids = unregistration_ids(params);
+                let before = self.file_watchers.len();
+                self.file_watchers
+                    .retain(|watcher| !ids.contains(&watcher.registration_id));
+                self.file_watchers.len() != before
+            }
+            _ => false,
+        };
+        if changed {
+            self.push_event(LspEvent::FileWatchersChanged);
+        }
+    }
+
+    /// The response to a `workspace/configuration` request, if a provider is set.
+    fn configuration_response(&mut self, params: &Value) -> Option<Value> {
+        let provider = self.configuration_provider.as_mut()?;
+        Some(Value::Array(
+            configuration_items_from_value(params)
+                .iter()
+                .map(provider)
+                .collect(),
+        ))
+    }
+
     /// Client-side request cancellation (`$/cancelRequest`).
+    ///
+    /// `request_id` is the id returned by [`LspSession::request`] (or a helper built on it). A
+    /// response that still arrives for a canceled request is dropped: no [`LspEvent::Response`]
+    /// is emitted for it.
     pub fn cancel_request(&mut self, request_id: u64) -> Result<(), String> {
-        self.notify("$/cancelRequest", json!({ "id": request_id }))
+        self.pending.remove(&request_id);
+        self.pending_client_requests.remove(&request_id);
+        self.client
+            .cancel_request(request_id)
+            .map_err(|err| format!("LSP notify 失败 ($/cancelRequest): {}", err))
+    }
+
+    /// Cancel every request still waiting for a response, e.g. before [`LspSession::shutdown`].
+    ///
+    /// Returns the canceled ids; their responses are dropped if they still arrive.
+    pub fn cancel_all_pending(&mut self) -> Result<Vec<u64>, String> {
+        self.pending.clear();
+        self.pending_client_requests.clear();
+        self.client
+            .cancel_all()
+            .map_err(|err| format!("LSP notify 失败 ($/cancelRequest): {}", err))
     }
 
     /// Graceful shutdown: send `shutdown` request.
@@ -763,24 +1548,274 @@ impl LspSession {
         })
     }
 
-    fn text_document_range_params(&self, range: &LspRange) -> Value {
-        json!({
-            "textDocument": { "uri": self.document.uri.as_str() },
-            "range": lsp_range_to_json(range),
-        })
+    fn text_document_range_params(&self, range: &LspRange) -> Value {
+        json!({
+            "textDocument": { "uri": self.document.uri.as_str() },
+            "range": lsp_range_to_json(range),
+        })
+    }
+
+    /// Hover (`textDocument/hover`).
+    pub fn request_hover(
+        &mut self,
+        line_index: &LineIndex,
+        line: usize,
+        column: usize,
+    ) -> Result<u64, String> {
+        self.request(
+            "textDocument/hover",
+            self.text_document_position_params(line_index, line, column),
+        )
+    }
+
+    /// Hover at a char offset, waiting for the server's answer (`textDocument/hover`).
+    ///
+    /// Blocks for at most [`LspSession::request_timeout`]. Messages that arrive in the meantime are
+    /// kept and processed by the next poll. Returns `Ok(None)` when the server has nothing to show.
+    pub fn hover_at(
+        &mut self,
+        line_index: &LineIndex,
+        cursor_offset: usize,
+    ) -> Result<Option<HoverInfo>, String> {
+        let (line, column) = line_index.char_offset_to_position(cursor_offset);
+        let params = self.text_document_position_params(line_index, line, column);
+        let id = self
+            .client
+            .request("textDocument/hover", params)
+            .map_err(|err| format!("LSP request 失败 (textDocument/hover): {}", err))?;
+
+        let response = self.wait_for_response(id)?;
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(Value::as_str).unwrap_or("");
+            return Err(format!("LSP hover 失败: {}", message));
+        }
+        Ok(response
+            .get("result")
+            .and_then(|result| hover_from_value(line_index, result)))
+    }
+
+    /// Signature help at a char offset, waiting for the server's answer
+    /// (`textDocument/signatureHelp`).
+    ///
+    /// Returns `Ok(None)` if the server has no signature to show.
+    pub fn signature_help_at(
+        &mut self,
+        line_index: &LineIndex,
+        cursor_offset: usize,
+    ) -> Result<Option<SignatureHelp>, String> {
+        let (line, column) = line_index.char_offset_to_position(cursor_offset);
+        let params = self.text_document_position_params(line_index, line, column);
+        let result = self.request_and_wait("textDocument/signatureHelp", params)?;
+        Ok(signature_help_from_value(&result))
+    }
+
+    /// Document symbols of the active document, waiting for the server's answer
+    /// (`textDocument/documentSymbol`).
+    ///
+    /// Apply the outline with [`ProcessingEdit::ReplaceDocumentSymbols`] to make it queryable
+    /// through the state manager.
+    pub fn document_symbols(&mut self, line_index: &LineIndex) -> Result<DocumentOutline, String> {
+        let params = json!({ "textDocument": { "uri": self.document.uri.as_str() } });
+        let result = self.request_and_wait("textDocument/documentSymbol", params)?;
+        Ok(document_symbols_from_value(&result, line_index))
+    }
+
+    /// Start a `workspace/symbol` search.
+    ///
+    /// The request carries a `partialResultToken`, so servers can stream results through
+    /// `$/progress`. Feed events from [`LspSession::drain_events`] to the returned query; dropping
+    /// it before the final response cancels the request.
+    pub fn workspace_symbols(&mut self, query: &str) -> Result<WorkspaceSymbolQuery, String> {
+        let token = json!(format!(
+            "editor-core/workspaceSymbol/{}",
+            self.next_partial_result_token
+        ));
+        self.next_partial_result_token = self.next_partial_result_token.saturating_add(1);
+
+        let params = json!({ "query": query, "partialResultToken": token });
+        let id = self.request("workspace/symbol", params)?;
+        Ok(WorkspaceSymbolQuery::new(
+            id,
+            token,
+            Some(self.client.cancel_handle(id)),
+        ))
+    }
+
+    /// Resolve a workspace symbol whose location has no range (`workspaceSymbol/resolve`),
+    /// waiting for the server's answer.
+    ///
+    /// Symbols that do not need resolving (see [`workspace_symbol_needs_resolve`]) are returned
+    /// unchanged.
+    pub fn resolve_workspace_symbol(
+        &mut self,
+        symbol: &WorkspaceSymbol,
+    ) -> Result<WorkspaceSymbol, String> {
+        if !workspace_symbol_needs_resolve(symbol) {
+            return Ok(symbol.clone());
+        }
+        let raw = symbol
+            .data_json
+            .as_deref()
+            .and_then(|text| serde_json::from_str::<Value>(text).ok())
+            .ok_or_else(|| "Workspace symbol has no raw LSP payload to resolve".to_string())?;
+        let result = self.request_and_wait("workspaceSymbol/resolve", raw)?;
+        workspace_symbol_from_value(&result)
+            .ok_or_else(|| "Invalid workspaceSymbol/resolve response".to_string())
+    }
+
+    /// Go to definition at a char offset, waiting for the server's answer.
+    ///
+    /// See [`LspSession::references_at`] for how locations are normalized.
+    pub fn definition_at(
+        &mut self,
+        line_index: &LineIndex,
+        cursor_offset: usize,
+    ) -> Result<Vec<LspLocation>, String> {
+        self.locations_at("textDocument/definition", line_index, cursor_offset, None)
+    }
+
+    /// Go to type definition at a char offset, waiting for the server's answer.
+    pub fn type_definition_at(
+        &mut self,
+        line_index: &LineIndex,
+        cursor_offset: usize,
+    ) -> Result<Vec<LspLocation>, String> {
+        self.locations_at(
+            "textDocument/typeDefinition",
+            line_index,
+            cursor_offset,
+            None,
+        )
+    }
+
+    /// Go to implementation at a char offset, waiting for the server's answer.
+    pub fn implementation_at(
+        &mut self,
+        line_index: &LineIndex,
+        cursor_offset: usize,
+    ) -> Result<Vec<LspLocation>, String> {
+        self.locations_at(
+            "textDocument/implementation",
+            line_index,
+            cursor_offset,
+            None,
+        )
+    }
+
+    /// Find references at a char offset, waiting for the server's answer.
+    ///
+    /// Like the other `*_at` location helpers, this blocks for at most
+    /// [`LspSession::request_timeout`] and accepts `Location`, `Location[]` and `LocationLink[]`
+    /// results. Locations in the current document get `char_range` filled in using
+    /// `line_index`; other targets only carry their UTF-16 range (see
+    /// [`LspLocation::symbol_location`]).
+    pub fn references_at(
+        &mut self,
+        line_index: &LineIndex,
+        cursor_offset: usize,
+        include_declaration: bool,
+    ) -> Result<Vec<LspLocation>, String> {
+        self.locations_at(
+            "textDocument/references",
+            line_index,
+            cursor_offset,
+            Some(json!({ "includeDeclaration": include_declaration })),
+        )
+    }
+
+    fn locations_at(
+        &mut self,
+        method: &str,
+        line_index: &LineIndex,
+        cursor_offset: usize,
+        context: Option<Value>,
+    ) -> Result<Vec<LspLocation>, String> {
+        let (line, column) = line_index.char_offset_to_position(cursor_offset);
+        let mut params = self.text_document_position_params(line_index, line, column);
+        if let Some(context) = context {
+            params["context"] = context;
+        }
+        let result = self.request_and_wait(method, params)?;
+
+        let mut locations = locations_from_value(&result);
+        for location in &mut locations {
+            if location.uri == self.document.uri {
+                location.char_range = Some(char_offsets_for_lsp_range(line_index, &location.range));
+            }
+        }
+        Ok(locations)
+    }
+
+    /// Prepare rename at a char offset, waiting for the server's answer
+    /// (`textDocument/prepareRename`).
+    ///
+    /// Returns `Ok(None)` when the symbol at `cursor_offset` cannot be renamed.
+    pub fn prepare_rename_at(
+        &mut self,
+        line_index: &LineIndex,
+        cursor_offset: usize,
+    ) -> Result<Option<PrepareRenameResult>, String> {
+        let (line, column) = line_index.char_offset_to_position(cursor_offset);
+        let params = self.text_document_position_params(line_index, line, column);
+        let result = self.request_and_wait("textDocument/prepareRename", params)?;
+        Ok(prepare_rename_from_value(line_index, &result))
     }
 
-    /// Hover (`textDocument/hover`).
-    pub fn request_hover(
+    /// Rename the symbol at a char offset, waiting for the server's answer
+    /// (`textDocument/rename`).
+    ///
+    /// Returns the `WorkspaceEdit` to apply (e.g. with
+    /// [`crate::LspWorkspaceSync::apply_workspace_edit`]), or `Ok(None)` if the server returned
+    /// `null`.
+    pub fn rename_at(
         &mut self,
         line_index: &LineIndex,
-        line: usize,
-        column: usize,
-    ) -> Result<u64, String> {
-        self.request(
-            "textDocument/hover",
-            self.text_document_position_params(line_index, line, column),
-        )
+        cursor_offset: usize,
+        new_name: impl Into<String>,
+    ) -> Result<Option<Value>, String> {
+        let (line, column) = line_index.char_offset_to_position(cursor_offset);
+        let mut params = self.text_document_position_params(line_index, line, column);
+        params["newName"] = Value::String(new_name.into());
+        let result = self.request_and_wait("textDocument/rename", params)?;
+        Ok((!result.is_null()).then_some(result))
+    }
+
+    /// Send a request and wait for its `result` (`null` if absent).
+    fn request_and_wait(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self
+            .client
+            .request(method, params)
+            .map_err(|err| format!("LSP request 失败 ({}): {}", method, err))?;
+
+        let mut response = self.wait_for_response(id)?;
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(Value::as_str).unwrap_or("");
+            return Err(format!("LSP request 失败 ({}): {}", method, message));
+        }
+        Ok(response
+            .get_mut("result")
+            .map(Value::take)
+            .unwrap_or(Value::Null))
+    }
+
+    /// Wait for the response to one of our own requests, stashing everything else for `poll`.
+    fn wait_for_response(&mut self, request_id: u64) -> Result<Value, String> {
+        let deadline = Instant::now() + self.request_timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(inbound) = self.client.recv_timeout(remaining) else {
+                // Nobody is waiting for the answer any more.
+                let _ = self.client.cancel_request(request_id);
+                return Err(format!("等待 LSP 响应超时 (id={})", request_id));
+            };
+            if let LspInbound::Message(msg) = &inbound
+                && msg.get("method").is_none()
+                && msg.get("id").and_then(Value::as_u64) == Some(request_id)
+            {
+                return Ok(msg.clone());
+            }
+            self.stashed_inbound.push_back(inbound);
+        }
     }
 
     /// Go to definition (`textDocument/definition`).
@@ -1026,6 +2061,98 @@ impl LspSession {
         self.request("textDocument/codeAction", params)
     }
 
+    /// Code actions for a char range, waiting for the server's answer
+    /// (`textDocument/codeAction`).
+    ///
+    /// The request context carries the `diagnostics` overlapping `start_offset..end_offset`
+    /// (typically `state_manager.editor().diagnostics()`), converted back to LSP ranges and
+    /// severities, so the server can offer quick fixes for them.
+    pub fn code_actions_for_range(
+        &mut self,
+        line_index: &LineIndex,
+        start_offset: usize,
+        end_offset: usize,
+        diagnostics: &[Diagnostic],
+    ) -> Result<Vec<CodeActionItem>, String> {
+        let range = self.lsp_range_for_editor_offsets(line_index, start_offset, end_offset);
+        let mut params = self.text_document_range_params(&range);
+        params["context"] =
+            code_action_context(line_index, start_offset, end_offset, diagnostics, None);
+        let result = self.request_and_wait("textDocument/codeAction", params)?;
+        Ok(code_action_items_from_value(&result))
+    }
+
+    /// Returns `true` if the server supports `codeAction/resolve`.
+    pub fn supports_code_action_resolve(&self) -> bool {
+        self.server_capabilities
+            .get("codeActionProvider")
+            .and_then(|provider| provider.get("resolveProvider"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Resolve a code action, waiting for the server's answer (`codeAction/resolve`).
+    pub fn resolve_code_action(&mut self, action: &CodeAction) -> Result<CodeAction, String> {
+        let result = self.request_and_wait("codeAction/resolve", action.raw.clone())?;
+        CodeAction::from_value(&result)
+            .ok_or_else(|| "LSP codeAction/resolve 返回了无效的 CodeAction".to_string())
+    }
+
+    /// Split a code action item into its `WorkspaceEdit` and command.
+    ///
+    /// Actions that still need resolving ([`CodeAction::needs_resolve`]) are resolved first (if the
+    /// server supports `codeAction/resolve`). Disabled actions are rejected.
+    pub(crate) fn code_action_parts(
+        &mut self,
+        item: &CodeActionItem,
+    ) -> Result<(Option<Value>, Option<LspCommand>), String> {
+        match item {
+            CodeActionItem::Command(command) => Ok((None, Some(command.clone()))),
+            CodeActionItem::Action(action) => {
+                if let Some(reason) = action.disabled_reason.as_deref() {
+                    return Err(format!("Code action 已禁用: {}", reason));
+                }
+                let action = if action.needs_resolve() && self.supports_code_action_resolve() {
+                    self.resolve_code_action(action)?
+                } else {
+                    action.clone()
+                };
+                Ok((action.edit, action.command))
+            }
+        }
+    }
+
+    /// Apply a code action to the active document.
+    ///
+    /// The action's edit is applied via [`LspSession::apply_workspace_edit`] (resolving it first
+    /// if needed). Returns the command the host should run next with
+    /// [`LspSession::execute_command`], if any. For multi-document edits, use
+    /// [`crate::LspWorkspaceSync::apply_code_action`] instead.
+    pub fn apply_code_action(
+        &mut self,
+        state_manager: &mut EditorStateManager,
+        item: &CodeActionItem,
+    ) -> Result<Option<LspCommand>, String> {
+        let (edit, command) = self.code_action_parts(item)?;
+        if let Some(edit) = edit {
+            self.apply_workspace_edit(state_manager, &edit)?;
+        }
+        Ok(command)
+    }
+
+    /// Execute a server command, waiting for its result (`workspace/executeCommand`).
+    ///
+    /// Edits the command produces arrive separately, as server `workspace/applyEdit` requests.
+    pub fn execute_command(&mut self, command: &LspCommand) -> Result<Value, String> {
+        self.request_and_wait(
+            "workspace/executeCommand",
+            json!({
+                "command": command.command.as_str(),
+                "arguments": command.arguments,
+            }),
+        )
+    }
+
     /// Code action resolve (`codeAction/resolve`).
     pub fn request_code_action_resolve(&mut self, action: Value) -> Result<u64, String> {
         self.request("codeAction/resolve", action)
@@ -1059,6 +2186,65 @@ impl LspSession {
         self.request("codeLens/resolve", lens)
     }
 
+    /// Format the active document, waiting for the server's answer (`textDocument/formatting`).
+    ///
+    /// The returned edits are applied to `state_manager` with [`apply_formatting_edits`] (one
+    /// undo step, cursor kept at its logical position). Returns the changed ranges in pre-edit
+    /// char offsets. `options` usually comes from [`LspFormattingOptions::from_state_manager`].
+    pub fn format_document(
+        &mut self,
+        state_manager: &mut EditorStateManager,
+        options: &LspFormattingOptions,
+    ) -> Result<Vec<(usize, usize)>, String> {
+        let params = json!({
+            "textDocument": { "uri": self.document.uri.as_str() },
+            "options": options.to_value(),
+        });
+        let result = self.request_and_wait("textDocument/formatting", params)?;
+        apply_formatting_edits(state_manager, &text_edits_from_value(&result))
+    }
+
+    /// Format a char range of the active document (`textDocument/rangeFormatting`).
+    ///
+    /// See [`LspSession::format_document`].
+    pub fn format_range(
+        &mut self,
+        state_manager: &mut EditorStateManager,
+        start_offset: usize,
+        end_offset: usize,
+        options: &LspFormattingOptions,
+    ) -> Result<Vec<(usize, usize)>, String> {
+        let line_index = &state_manager.editor().line_index;
+        let range = self.lsp_range_for_editor_offsets(line_index, start_offset, end_offset);
+        let mut params = self.text_document_range_params(&range);
+        params["options"] = options.to_value();
+        let result = self.request_and_wait("textDocument/rangeFormatting", params)?;
+        apply_formatting_edits(state_manager, &text_edits_from_value(&result))
+    }
+
+    /// Format after typing `ch` at a char offset (`textDocument/onTypeFormatting`).
+    ///
+    /// `offset` is the caret position after `ch` was inserted. See
+    /// [`LspSession::format_document`].
+    pub fn format_on_type(
+        &mut self,
+        state_manager: &mut EditorStateManager,
+        offset: usize,
+        ch: char,
+        options: &LspFormattingOptions,
+    ) -> Result<Vec<(usize, usize)>, String> {
+        let line_index = &state_manager.editor().line_index;
+        let pos = lsp_position_for_offset(line_index, offset);
+        let params = json!({
+            "textDocument": { "uri": self.document.uri.as_str() },
+            "position": { "line": pos.line, "character": pos.character },
+            "ch": ch.to_string(),
+            "options": options.to_value(),
+        });
+        let result = self.request_and_wait("textDocument/onTypeFormatting", params)?;
+        apply_formatting_edits(state_manager, &text_edits_from_value(&result))
+    }
+
     /// Document formatting (`textDocument/formatting`).
     ///
     /// `options` should follow LSP `FormattingOptions`.
@@ -1134,6 +2320,56 @@ impl LspSession {
         )
     }
 
+    /// Request semantic tokens for logical lines `start_line..end_line` of the active document.
+    ///
+    /// Unlike [`LspSession::request_semantic_tokens_range`], the response is handled by
+    /// [`LspSession::poll`]: it is merged into the semantic tokens style layer right away and kept
+    /// until a full result for the same document version replaces it. Returns `Ok(None)` if the
+    /// server does not support range requests.
+    pub fn request_semantic_tokens_for_lines(
+        &mut self,
+        line_index: &LineIndex,
+        start_line: usize,
+        end_line: usize,
+    ) -> Result<Option<u64>, String> {
+        if !self.supports_semantic_tokens_range
+            || self.output.semantic_tokens_layer.is_none()
+            || end_line <= start_line
+        {
+            return Ok(None);
+        }
+        let line_start = |line: usize| {
+            if line < line_index.line_count() {
+                line_index.position_to_char_offset(line, 0)
+            } else {
+                line_index.char_count()
+            }
+        };
+        let range = LspRange::new(
+            lsp_position_for_offset(line_index, line_start(start_line)),
+            lsp_position_for_offset(line_index, line_start(end_line)),
+        );
+        let id = self
+            .client
+            .request(
+                "textDocument/semanticTokens/range",
+                json!({
+                    "textDocument": { "uri": self.document.uri.as_str() },
+                    "range": lsp_range_to_json(&range),
+                }),
+            )
+            .map_err(|err| format!("LSP semanticTokens 请求失败: {}", err))?;
+        self.pending.insert(
+            id,
+            PendingLspRequest::SemanticTokensRange {
+                version: self.document.version,
+                start_line,
+                end_line,
+            },
+        );
+        Ok(Some(id))
+    }
+
     /// Selection range (`textDocument/selectionRange`).
     ///
     /// `positions` are editor (line,column) pairs where column is a char offset within the line.
@@ -1338,24 +2574,64 @@ impl LspSession {
         G: FnMut(&LspNotification),
     {
         let mut edits = Vec::<ProcessingEdit>::new();
+        if !self.run_due_restart(line_index)? {
+            return Ok(edits);
+        }
+        self.expire_requests()?;
 
-        while let Some(inbound) = self.client.try_recv() {
+        while let Some(inbound) = self
+            .stashed_inbound
+            .pop_front()
+            .or_else(|| self.client.try_recv())
+        {
             match inbound {
-                LspInbound::IoError(err) => return Err(format!("LSP 连接已断开: {}", err)),
+                LspInbound::IoError(err) => {
+                    let reason = format!("LSP 连接已断开: {}", err);
+                    if self.schedule_restart(reason.clone()) {
+                        return Ok(edits);
+                    }
+                    return Err(reason);
+                }
                 LspInbound::Message(msg) => {
                     // server->client request: may be auto-replied or deferred.
                     if msg.get("method").is_some() && msg.get("id").is_some() {
+                        // Progress tokens must exist before the server can use them, so this
+                        // one is always answered here, whatever the request policy says.
+                        if msg.get("method").and_then(Value::as_str)
+                            == Some("window/workDoneProgress/create")
+                        {
+                            if let Err(err) = self.client.handle_server_request(&msg) {
+                                return Err(format!("LSP request 处理失败: {}", err));
+                            }
+                            continue;
+                        }
                         if let Some(request) = LspServerRequest::from_json(&msg) {
-                            if self.server_request_policy.should_defer(&request.method) {
+                            self.observe_registration_request(&request.method, &request.params);
+                            let defer = self.server_request_policy.should_defer(&request.method);
+                            let configuration = (!defer
+                                && request.method == "workspace/configuration")
+                                .then(|| self.configuration_response(&request.params))
+                                .flatten();
+                            if defer {
                                 self.deferred_requests.insert(request.id, request.clone());
                                 self.push_event(LspEvent::DeferredRequest(request));
                                 on_unhandled_message(msg);
+                            } else if let Some(result) = configuration {
+                                if let Err(err) = self.client.respond(request.id, result) {
+                                    return Err(format!("LSP request 处理失败: {}", err));
+                                }
                             } else if let Err(err) = self.client.handle_server_request(&msg) {
                                 return Err(format!("LSP request 处理失败: {}", err));
                             } else {
                                 // Some requests imply a follow-up client action.
-                                if request.method.as_str() == "workspace/semanticTokens/refresh" {
-                                    self.schedule_refresh(Duration::from_millis(0));
+                                match request.method.as_str() {
+                                    "workspace/semanticTokens/refresh" => {
+                                        self.schedule_refresh(Duration::from_millis(0));
+                                    }
+                                    "workspace/inlayHint/refresh" => {
+                                        self.push_event(LspEvent::InlayHintRefresh);
+                                    }
+                                    _ => {}
                                 }
                             }
                         } else if let Err(err) = self.client.handle_server_request(&msg) {
@@ -1406,10 +2682,23 @@ impl LspSession {
                             if let LspNotification::PublishDiagnostics(diags) = &notification
                                 && diags.uri == self.document.uri
                             {
-                                edits
-                                    .extend(lsp_diagnostics_to_processing_edits(line_index, diags));
+                                self.diagnostics.set_pushed(diags.diagnostics.clone());
+                                edits.extend(self.diagnostics.to_processing_edits(
+                                    &self.document.uri,
+                                    line_index,
+                                    &self.output,
+                                ));
                             }
+                            let progress = match &notification {
+                                LspNotification::Progress(params) => {
+                                    self.progress.handle_progress(params)
+                                }
+                                _ => None,
+                            };
                             self.push_event(LspEvent::Notification(notification));
+                            if let Some(item) = progress {
+                                self.push_event(LspEvent::ProgressChanged(item));
+                            }
                         }
                     }
 
@@ -1418,7 +2707,8 @@ impl LspSession {
             }
         }
 
-        self.maybe_refresh(&mut edits)?;
+        self.maybe_refresh(line_index, &mut edits)?;
+        self.maybe_request_cursor_highlights(line_index, &mut edits)?;
         Ok(edits)
     }
 
@@ -1433,121 +2723,134 @@ impl LspSession {
     }
 
     fn clear_semantic_tokens_cache(&mut self) {
-        self.semantic_tokens_result_id = None;
-        self.semantic_tokens_data.clear();
+        self.semantic_tokens.clear();
     }
 
+    /// Handle a `semanticTokens/full` or `semanticTokens/full/delta` response.
+    ///
+    /// If a delta cannot be applied (or the delta request failed), the cache is dropped and a
+    /// refresh is scheduled, so the next request is a full one.
     fn handle_semantic_tokens_result(
         &mut self,
-        result: &Value,
+        msg: &Value,
+        version: i32,
+        delta: bool,
         line_index: &LineIndex,
         edits: &mut Vec<ProcessingEdit>,
     ) {
-        // Full response: { resultId?, data: u32[] }
-        if let Some(data_arr) = result.get("data").and_then(Value::as_array) {
-            let mut data = Vec::with_capacity(data_arr.len());
-            for v in data_arr {
-                if let Some(n) = v.as_u64() {
-                    data.push(n as u32);
-                }
+        let result = msg.get("result").unwrap_or(&Value::Null);
+        let result_id = result
+            .get("resultId")
+            .and_then(Value::as_str)
+            .map(|s| s.to_string());
+        if let Some(data) = result.get("data") {
+            // Full response (servers may also answer a delta request with one): { resultId?, data }
+            self.semantic_tokens.set_full(parse_u32s(data), result_id);
+        } else if let Some(delta_edits) = result.get("edits").and_then(Value::as_array) {
+            // Delta response: { resultId?, edits: [{ start, deleteCount, data? }] }
+            let parsed: Vec<SemanticTokensEdit> = delta_edits
+                .iter()
+                .filter_map(|edit| {
+                    Some(SemanticTokensEdit {
+                        start: edit.get("start")?.as_u64()? as usize,
+                        delete_count: edit.get("deleteCount")?.as_u64()? as usize,
+                        data: edit.get("data").map(parse_u32s).unwrap_or_default(),
+                    })
+                })
+                .collect();
+            if self.semantic_tokens.result_id().is_none()
+                || self
+                    .semantic_tokens
+                    .apply_delta(&parsed, result_id)
+                    .is_err()
+            {
+                self.clear_semantic_tokens_cache();
+                self.schedule_refresh(Duration::from_millis(0));
+                return;
             }
-
-            self.semantic_tokens_result_id = result
-                .get("resultId")
-                .and_then(Value::as_str)
-                .map(|s| s.to_string());
-            self.semantic_tokens_data = data;
-
-            if let Ok(intervals) = semantic_tokens_to_intervals(
-                &self.semantic_tokens_data,
-                line_index,
-                encode_semantic_style_id,
-            ) {
-                edits.push(ProcessingEdit::ReplaceStyleLayer {
-                    layer: StyleLayerId::SEMANTIC_TOKENS,
-                    intervals,
-                });
+        } else {
+            if delta {
+                // E.g. the server no longer knows `previousResultId`.
+                self.clear_semantic_tokens_cache();
+                self.schedule_refresh(Duration::from_millis(0));
             }
-
             return;
         }
+        self.semantic_tokens.set_version(Some(version));
+
+        if let Some(layer) = self.output.semantic_tokens_layer
+            && let Ok(intervals) = semantic_tokens_to_intervals(
+                self.semantic_tokens.data(),
+                line_index,
+                semantic_style_resolver(
+                    self.style_registry.as_ref(),
+                    self.semantic_legend.as_ref(),
+                ),
+            )
+        {
+            edits.push(ProcessingEdit::ReplaceStyleLayer { layer, intervals });
+        }
+    }
 
-        // Delta response: { resultId?, edits: [{ start, deleteCount, data? }] }
-        let Some(delta_edits) = result.get("edits").and_then(Value::as_array) else {
+    /// Handle a `semanticTokens/range` response for lines `start_line..end_line`.
+    ///
+    /// The result is overlaid on the cached full tokens, unless a full result for the same
+    /// document version already landed.
+    fn handle_semantic_tokens_range_result(
+        &mut self,
+        msg: &Value,
+        version: i32,
+        (start_line, end_line): (usize, usize),
+        line_index: &LineIndex,
+        edits: &mut Vec<ProcessingEdit>,
+    ) {
+        let Some(data) = msg.get("result").and_then(|result| result.get("data")) else {
             return;
         };
-        if self.semantic_tokens_data.is_empty() {
-            // No baseline to apply the delta to; fall back to requesting full next refresh.
-            self.clear_semantic_tokens_cache();
+        let resolver =
+            semantic_style_resolver(self.style_registry.as_ref(), self.semantic_legend.as_ref());
+        let Ok(intervals) = semantic_tokens_to_intervals(&parse_u32s(data), line_index, &resolver)
+        else {
             return;
-        }
-
-        #[derive(Debug)]
-        struct DeltaEdit {
-            start: usize,
-            delete_count: usize,
-            data: Vec<u32>,
-        }
-
-        let mut parsed = Vec::<DeltaEdit>::new();
-        for edit in delta_edits {
-            let Some(start) = edit.get("start").and_then(Value::as_u64) else {
-                continue;
-            };
-            let Some(delete_count) = edit.get("deleteCount").and_then(Value::as_u64) else {
-                continue;
-            };
-            let data = edit
-                .get("data")
-                .and_then(Value::as_array)
-                .map(|arr| {
-                    arr.iter()
-                        .filter_map(Value::as_u64)
-                        .map(|n| n as u32)
-                        .collect::<Vec<_>>()
-                })
-                .unwrap_or_default();
-
-            parsed.push(DeltaEdit {
-                start: start as usize,
-                delete_count: delete_count as usize,
-                data,
+        };
+        let Some(layer) = self.output.semantic_tokens_layer else {
+            return;
+        };
+        if self
+            .semantic_tokens
+            .merge_range(version, start_line, end_line, intervals)
+        {
+            edits.push(ProcessingEdit::ReplaceStyleLayer {
+                layer,
+                intervals: self.semantic_tokens.to_intervals(
+                    line_index,
+                    semantic_style_resolver(
+                        self.style_registry.as_ref(),
+                        self.semantic_legend.as_ref(),
+                    ),
+                ),
             });
         }
+    }
 
-        // Apply in descending order to keep indices stable even if servers send unsorted edits.
-        parsed.sort_by_key(|e| std::cmp::Reverse(e.start));
-
-        let mut data = self.semantic_tokens_data.clone();
-        for edit in parsed {
-            if edit.start > data.len() {
-                self.clear_semantic_tokens_cache();
-                return;
+    /// Cancel requests past their deadline; host requests are reported as timed out.
+    fn expire_requests(&mut self) -> Result<(), String> {
+        let expired = self
+            .client
+            .expire_requests(Instant::now())
+            .map_err(|err| format!("LSP notify 失败 ($/cancelRequest): {}", err))?;
+        for request in expired {
+            // Internal requests (semantic tokens, folding, ...) are simply retried by the next
+            // refresh.
+            self.pending.remove(&request.id);
+            if let Some(highlights) = self.cursor_highlights.as_mut() {
+                highlights.handle_event(&LspEvent::RequestTimedOut(request.clone()));
             }
-            let end = edit.start.saturating_add(edit.delete_count);
-            if end > data.len() {
-                self.clear_semantic_tokens_cache();
-                return;
+            if self.pending_client_requests.remove(&request.id).is_some() {
+                self.push_event(LspEvent::RequestTimedOut(request));
             }
-            data.splice(edit.start..end, edit.data);
-        }
-
-        self.semantic_tokens_result_id = result
-            .get("resultId")
-            .and_then(Value::as_str)
-            .map(|s| s.to_string());
-        self.semantic_tokens_data = data;
-
-        if let Ok(intervals) = semantic_tokens_to_intervals(
-            &self.semantic_tokens_data,
-            line_index,
-            encode_semantic_style_id,
-        ) {
-            edits.push(ProcessingEdit::ReplaceStyleLayer {
-                layer: StyleLayerId::SEMANTIC_TOKENS,
-                intervals,
-            });
         }
+        Ok(())
     }
 
     fn handle_pending_response(
@@ -1558,32 +2861,97 @@ impl LspSession {
         edits: &mut Vec<ProcessingEdit>,
     ) -> Result<(), String> {
         match pending {
-            PendingLspRequest::SemanticTokens { version } => {
+            PendingLspRequest::SemanticTokens { version, delta } => {
                 if version != self.document.version {
                     return Ok(());
                 }
 
-                let result = msg.get("result").cloned().unwrap_or(Value::Null);
-                self.handle_semantic_tokens_result(&result, line_index, edits);
+                self.handle_semantic_tokens_result(msg, version, delta, line_index, edits);
             }
-            PendingLspRequest::FoldingRanges { version } => {
+            PendingLspRequest::SemanticTokensRange {
+                version,
+                start_line,
+                end_line,
+            } => {
                 if version != self.document.version {
                     return Ok(());
                 }
 
-                let regions =
-                    folding_regions_from_lsp_value(msg.get("result").unwrap_or(&Value::Null));
+                self.handle_semantic_tokens_range_result(
+                    msg,
+                    version,
+                    (start_line, end_line),
+                    line_index,
+                    edits,
+                );
+            }
+            PendingLspRequest::FoldingRanges { version } => {
+                if version != self.document.version || !self.output.folding_ranges {
+                    return Ok(());
+                }
+
+                let regions = self.folding_merge_policy.merge(
+                    folding_regions_from_lsp_value(msg.get("result").unwrap_or(&Value::Null)),
+                    line_index,
+                );
                 edits.push(ProcessingEdit::ReplaceFoldingRegions {
                     regions,
                     preserve_collapsed: true,
                 });
             }
+            PendingLspRequest::DocumentHighlights => {
+                let Some(highlights) = self.cursor_highlights.as_mut() else {
+                    return Ok(());
+                };
+                let response = LspResponse {
+                    id: msg.get("id").and_then(Value::as_u64).unwrap_or_default(),
+                    method: "textDocument/documentHighlight".to_string(),
+                    result: msg.get("result").cloned(),
+                    error: None,
+                };
+                highlights.handle_response(&response);
+                edits.extend(highlights.take_processing_edit(line_index));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Clear stale cursor highlights and request new ones once the debounce delay has passed.
+    fn maybe_request_cursor_highlights(
+        &mut self,
+        line_index: &LineIndex,
+        edits: &mut Vec<ProcessingEdit>,
+    ) -> Result<(), String> {
+        let Some(highlights) = self.cursor_highlights.as_mut() else {
+            return Ok(());
+        };
+        edits.extend(highlights.take_processing_edit(line_index));
+        if self.restart_due.is_some()
+            || !LspFeature::DocumentHighlight.is_supported_by(&self.server_capabilities)
+        {
+            return Ok(());
         }
 
+        let superseded = highlights.pending_request();
+        let sent = highlights
+            .update(&mut self.client, line_index, Instant::now())
+            .map_err(|err| format!("LSP documentHighlight 请求失败: {}", err))?;
+        if let Some(id) = sent {
+            if let Some(stale) = superseded {
+                self.pending.remove(&stale);
+            }
+            self.pending
+                .insert(id, PendingLspRequest::DocumentHighlights);
+        }
         Ok(())
     }
 
-    fn maybe_refresh(&mut self, edits: &mut Vec<ProcessingEdit>) -> Result<(), String> {
+    fn maybe_refresh(
+        &mut self,
+        line_index: &LineIndex,
+        edits: &mut Vec<ProcessingEdit>,
+    ) -> Result<(), String> {
         let Some(due) = self.refresh_due else {
             return Ok(());
         };
@@ -1602,22 +2970,35 @@ impl LspSession {
                     PendingLspRequest::SemanticTokens { version, .. } if *version == self.document.version
                     )
                 });
-            if self.supports_semantic_tokens && !has_pending_tokens {
-                let (method, params) = if self.supports_semantic_tokens_delta
-                    && self.semantic_tokens_result_id.is_some()
-                {
-                    (
+            if self.supports_semantic_tokens
+                && self.output.semantic_tokens_layer.is_some()
+                && !has_pending_tokens
+            {
+                // A full (non-delta) response can take a while on large files: style the
+                // viewport from a range request first.
+                let will_request_delta = self.supports_semantic_tokens_delta
+                    && self.semantic_tokens.result_id().is_some();
+                if !will_request_delta && let Some((start_line, end_line)) = self.viewport_lines {
+                    self.request_semantic_tokens_for_lines(line_index, start_line, end_line)?;
+                }
+
+                let previous_result_id = self
+                    .semantic_tokens
+                    .result_id()
+                    .filter(|_| self.supports_semantic_tokens_delta);
+                let delta = previous_result_id.is_some();
+                let (method, params) = match previous_result_id {
+                    Some(previous_result_id) => (
                         "textDocument/semanticTokens/full/delta",
                         json!({
                             "textDocument": { "uri": doc_uri.clone() },
-                            "previousResultId": self.semantic_tokens_result_id.clone().unwrap_or_default(),
+                            "previousResultId": previous_result_id,
                         }),
-                    )
-                } else {
-                    (
+                    ),
+                    None => (
                         "textDocument/semanticTokens/full",
                         json!({ "textDocument": { "uri": doc_uri.clone() } }),
-                    )
+                    ),
                 };
 
                 match self.client.request(method, params) {
@@ -1626,6 +3007,7 @@ impl LspSession {
                             id,
                             PendingLspRequest::SemanticTokens {
                                 version: self.document.version,
+                                delta,
                             },
                         );
                     }
@@ -1641,7 +3023,7 @@ impl LspSession {
                     PendingLspRequest::FoldingRanges { version } if *version == self.document.version
                 )
             });
-            if self.supports_folding_range && !has_pending_folds {
+            if self.supports_folding_range && self.output.folding_ranges && !has_pending_folds {
                 match self.client.request(
                     "textDocument/foldingRange",
                     json!({ "textDocument": { "uri": doc_uri.clone() } }),
@@ -1660,14 +3042,30 @@ impl LspSession {
         }
 
         // If the server doesn't support folding ranges, don't keep stale regions around.
-        if !self.supports_folding_range {
-            edits.push(ProcessingEdit::ClearFoldingRegions);
+        if !self.supports_folding_range && self.output.folding_ranges {
+            edits.push(match self.folding_merge_policy {
+                FoldingMergePolicy::ProviderOnly => ProcessingEdit::ClearFoldingRegions,
+                policy => ProcessingEdit::ReplaceFoldingRegions {
+                    regions: policy.merge(Vec::new(), line_index),
+                    preserve_collapsed: true,
+                },
+            });
         }
 
         Ok(())
     }
 }
 
+impl LspRequestSender for LspSession {
+    fn send_request(&mut self, method: &str, params: Value) -> Result<u64, String> {
+        self.request(method, params)
+    }
+
+    fn cancel_request(&mut self, request_id: u64) -> Result<(), String> {
+        LspSession::cancel_request(self, request_id)
+    }
+}
+
 impl DocumentProcessor for LspSession {
     type Error = String;
 
@@ -1728,6 +3126,30 @@ fn parse_semantic_tokens_legend(capabilities: &Value) -> (bool, Option<SemanticT
     (supports_semantic_tokens, semantic_legend)
 }
 
+fn parse_u32s(value: &Value) -> Vec<u32> {
+    value
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(Value::as_u64)
+                .map(|n| n as u32)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_supports_semantic_tokens_range(capabilities: &Value) -> bool {
+    // `range` can be `true` or an (empty) object.
+    match capabilities
+        .get("semanticTokensProvider")
+        .and_then(|provider| provider.get("range"))
+    {
+        Some(Value::Bool(supported)) => *supported,
+        Some(Value::Object(_)) => true,
+        _ => false,
+    }
+}
+
 fn parse_supports_semantic_tokens_delta(capabilities: &Value) -> bool {
     let Some(provider) = capabilities.get("semanticTokensProvider") else {
         return false;
@@ -1757,14 +3179,14 @@ fn parse_supports_folding_range(capabilities: &Value) -> bool {
     }
 }
 
-fn lsp_position_for_offset(line_index: &LineIndex, offset: usize) -> LspPosition {
+pub(crate) fn lsp_position_for_offset(line_index: &LineIndex, offset: usize) -> LspPosition {
     let (line, col) = line_index.char_offset_to_position(offset);
     let line_text = line_index.get_line_text(line).unwrap_or_default();
     let utf16 = LspCoordinateConverter::char_offset_to_utf16(&line_text, col) as u32;
     LspPosition::new(line as u32, utf16)
 }
 
-fn lsp_range_to_json(range: &LspRange) -> Value {
+pub(crate) fn lsp_range_to_json(range: &LspRange) -> Value {
     json!({
         "start": { "line": range.start.line, "character": range.start.character },
         "end": { "line": range.end.line, "character": range.end.character },
@@ -1831,6 +3253,7 @@ fn char_offset_for_lsp_position(line_index: &LineIndex, pos: LspPosition) -> usi
 fn diagnostics_to_style_edit(
     line_index: &LineIndex,
     params: &crate::lsp_events::LspPublishDiagnosticsParams,
+    layer: StyleLayerId,
 ) -> Option<ProcessingEdit> {
     let mut intervals = Vec::<Interval>::with_capacity(params.diagnostics.len());
 
@@ -1845,12 +3268,13 @@ fn diagnostics_to_style_edit(
             end,
             diagnostic_style_id(diag.severity),
         ));
+        // Tags overlay the severity style (see `editor_core::diagnostic_tag_style_id`).
+        for &tag in &diag.tags {
+            intervals.push(Interval::new(start, end, diagnostic_tag_style_id(tag)));
+        }
     }
 
-    Some(ProcessingEdit::ReplaceStyleLayer {
-        layer: StyleLayerId::DIAGNOSTICS,
-        intervals,
-    })
+    Some(ProcessingEdit::ReplaceStyleLayer { layer, intervals })
 }
 
 fn diagnostic_severity(
@@ -1877,16 +3301,56 @@ fn diagnostic_code(code: &Option<Value>) -> Option<String> {
     }
 }
 
-/// Convert an LSP `publishDiagnostics` payload into `editor-core` processing edits.
+/// Parse `DiagnosticRelatedInformation[]`.
+fn related_diagnostics_from_value(value: &Value) -> Vec<RelatedDiagnostic> {
+    let Some(items) = value.as_array() else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let location = item.get("location")?;
+            Some(RelatedDiagnostic {
+                message: item
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("")
+                    .to_string(),
+                uri: location.get("uri")?.as_str()?.to_string(),
+                range: parse_utf16_range(location.get("range")?)?,
+            })
+        })
+        .collect()
+}
+
+/// Convert an LSP `publishDiagnostics` payload from `server_name` into `editor-core` processing
+/// edits.
 ///
 /// The resulting edits include:
 /// - `StyleLayerId::DIAGNOSTICS` underline intervals (for rendering)
-/// - `ProcessingEdit::ReplaceDiagnostics` structured diagnostics (for UX / panels)
+/// - `ProcessingEdit::ReplaceProviderDiagnostics` structured diagnostics tagged with
+///   `server_name` (for UX / panels), leaving other providers' diagnostics in place
 pub fn lsp_diagnostics_to_processing_edits(
     line_index: &LineIndex,
     params: &crate::lsp_events::LspPublishDiagnosticsParams,
+    server_name: &str,
+) -> Vec<ProcessingEdit> {
+    lsp_diagnostics_to_processing_edits_for(
+        line_index,
+        params,
+        &LspSessionOutput::for_server(server_name),
+    )
+}
+
+/// Like [`lsp_diagnostics_to_processing_edits`], but writes underlines to
+/// `output.diagnostics_layer` and, if `output.provider` is set, replaces only that provider's
+/// diagnostics (`ProcessingEdit::ReplaceProviderDiagnostics`).
+pub fn lsp_diagnostics_to_processing_edits_for(
+    line_index: &LineIndex,
+    params: &crate::lsp_events::LspPublishDiagnosticsParams,
+    output: &LspSessionOutput,
 ) -> Vec<ProcessingEdit> {
-    let style_edit = diagnostics_to_style_edit(line_index, params);
+    let style_edit = diagnostics_to_style_edit(line_index, params, output.diagnostics_layer);
 
     let mut diagnostics = Vec::<Diagnostic>::with_capacity(params.diagnostics.len());
     for diag in &params.diagnostics {
@@ -1902,7 +3366,15 @@ pub fn lsp_diagnostics_to_processing_edits(
             severity: diagnostic_severity(diag.severity),
             code: diagnostic_code(&diag.code),
             source: diag.source.clone(),
+            provider: output.provider.clone(),
             message: diag.message.clone(),
+            code_description_href: diag.code_description_href.clone(),
+            tags: diag.tags.clone(),
+            related: diag
+                .related_information
+                .as_ref()
+                .map(related_diagnostics_from_value)
+                .unwrap_or_default(),
             related_information_json: diag.related_information.as_ref().map(|v| v.to_string()),
             data_json: diag.data.as_ref().map(|v| v.to_string()),
         });
@@ -1912,6 +3384,12 @@ pub fn lsp_diagnostics_to_processing_edits(
     if let Some(style_edit) = style_edit {
         out.push(style_edit);
     }
-    out.push(ProcessingEdit::ReplaceDiagnostics { diagnostics });
+    out.push(match &output.provider {
+        Some(provider) => ProcessingEdit::ReplaceProviderDiagnostics {
+            provider: provider.clone(),
+            diagnostics,
+        },
+        None => ProcessingEdit::ReplaceDiagnostics { diagnostics },
+    });
     out
 }
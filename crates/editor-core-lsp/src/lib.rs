@@ -7,55 +7,105 @@
 
 pub mod editor;
 pub mod lsp_client;
+pub mod lsp_code_actions;
 pub mod lsp_completion;
 pub mod lsp_decorations;
+pub mod lsp_diagnostics;
 pub mod lsp_events;
+pub mod lsp_formatting;
 pub mod lsp_highlights;
+pub mod lsp_hover;
+pub mod lsp_inlay_hints;
 pub mod lsp_locations;
+pub mod lsp_progress;
+pub mod lsp_rename;
+pub mod lsp_session_group;
+pub mod lsp_signature_help;
 pub mod lsp_symbols;
 pub mod lsp_sync;
 pub mod lsp_text_edits;
 pub mod lsp_transport;
 pub mod lsp_uri;
+pub mod lsp_watched_files;
+pub mod lsp_workspace_symbols;
 pub mod workspace_sync;
 
 pub use editor::{
-    LspContentChange, LspDocument, LspServerInfo, LspSession, LspSessionStartOptions,
-    SemanticTokensLegend, clear_lsp_state, lsp_clear_edits, lsp_diagnostics_to_processing_edits,
+    LspContentChange, LspDocument, LspRestartPolicy, LspServerInfo, LspSession, LspSessionOutput,
+    LspSessionStartOptions, SemanticTokensLegend, clear_lsp_state, lsp_clear_edits,
+    lsp_diagnostics_to_processing_edits, lsp_diagnostics_to_processing_edits_for,
+};
+pub use lsp_client::{
+    CANCELED_RESPONSE_GRACE, LspCancelHandle, LspClient, LspInbound, LspOutbound, LspRequestSender,
+    LspRequestTimeouts, LspTimedOutRequest,
+};
+pub use lsp_code_actions::{
+    CodeAction, CodeActionItem, LspCommand, code_action_context, code_action_items_from_value,
+    code_actions_from_value, diagnostic_to_lsp_value, preferred_quick_fix,
 };
-pub use lsp_client::{LspClient, LspInbound, LspOutbound};
 pub use lsp_completion::{
-    CompletionTextEditMode, apply_completion_item, completion_item_to_text_edit_specs,
+    CompletionSession, CompletionTextEditMode, apply_completion_item, completion_item_label,
+    completion_item_to_text_edit_specs, completion_items_from_value,
 };
 pub use lsp_decorations::{
     lsp_code_lens_to_decorations, lsp_code_lens_to_processing_edit,
     lsp_document_links_to_decorations, lsp_document_links_to_processing_edit,
-    lsp_inlay_hints_to_decorations, lsp_inlay_hints_to_processing_edit,
+    lsp_hover_to_decoration, lsp_hover_to_processing_edit, lsp_inlay_hints_to_decorations,
+    lsp_inlay_hints_to_processing_edit,
+};
+pub use lsp_diagnostics::{
+    DocumentDiagnosticReport, DocumentDiagnosticResponse, DocumentDiagnostics,
+    document_diagnostic_response_from_value,
 };
 pub use lsp_events::{
     LspDiagnostic, LspDiagnosticSeverity, LspEvent, LspLogMessageParams, LspMessageType,
     LspNotification, LspProgressParams, LspPublishDiagnosticsParams, LspResponse, LspResponseError,
-    LspServerRequest, LspServerRequestMode, LspServerRequestPolicy, LspShowMessageParams,
+    LspServerRequest, LspServerRequestMode, LspServerRequestPolicy, LspServerRestart,
+    LspShowMessageParams,
+};
+pub use lsp_formatting::{
+    LspFormattingOptions, apply_formatting_edits, format_edits_to_text_edit_specs,
+    formatting_edit_specs,
 };
 pub use lsp_highlights::{
-    lsp_document_highlights_to_intervals, lsp_document_highlights_to_processing_edit,
+    CursorHighlightManager, lsp_document_highlights_to_intervals,
+    lsp_document_highlights_to_processing_edit,
 };
+pub use lsp_hover::{HoverBlock, HoverInfo, hover_from_value, markdown_to_plain_text};
+pub use lsp_inlay_hints::{InlayHintLabelPart, InlayHintManager, inlay_hint_label_parts};
 pub use lsp_locations::{LspLocation, locations_from_value};
+pub use lsp_progress::{ProgressItem, ProgressTracker, WorkDoneProgress};
+pub use lsp_rename::{PrepareRenameResult, prepare_rename_from_value};
+pub use lsp_session_group::{LspFeature, LspSessionFailure, LspSessionGroup};
+pub use lsp_signature_help::{
+    ParameterInformation, SignatureHelp, SignatureInformation, signature_help_from_value,
+};
 pub use lsp_symbols::{
-    lsp_document_symbols_to_outline, lsp_document_symbols_to_processing_edit,
-    lsp_workspace_symbols_to_results,
+    document_symbols_from_value, lsp_document_symbols_to_outline,
+    lsp_document_symbols_to_processing_edit, lsp_workspace_symbols_to_results,
+    workspace_symbol_from_value, workspace_symbol_needs_resolve,
 };
 pub use lsp_sync::{
     DeltaCalculator, LspCoordinateConverter, LspPosition, LspRange, SemanticToken,
-    SemanticTokensError, SemanticTokensManager, TextChange, decode_semantic_style_id,
-    encode_semantic_style_id, semantic_tokens_to_intervals,
+    SemanticTokensEdit, SemanticTokensError, SemanticTokensManager, TextChange,
+    decode_semantic_style_id, encode_semantic_style_id, semantic_tokens_to_intervals,
 };
 pub use lsp_text_edits::{
     LspTextEdit, apply_text_edits, char_offsets_for_lsp_range, text_edits_from_value,
     workspace_edit_text_edits, workspace_edit_text_edits_for_uri,
 };
 pub use lsp_transport::{read_lsp_message, write_lsp_message};
-pub use lsp_uri::{file_uri_to_path, path_to_file_uri, percent_decode_path, percent_encode_path};
+pub use lsp_uri::{
+    file_uri_to_path, normalize_file_uri, path_to_file_uri, percent_decode_path,
+    percent_encode_path,
+};
+pub use lsp_watched_files::{
+    FileChangeType, LspConfigurationItem, LspFileWatcher, configuration_items_from_value,
+    file_watchers_from_registrations, unregistration_ids,
+};
+pub use lsp_workspace_symbols::WorkspaceSymbolQuery;
 pub use workspace_sync::{
-    AppliedWorkspaceEditDocument, ApplyWorkspaceEditResult, LspWorkspaceSync,
+    AppliedCodeAction, AppliedWorkspaceEditDocument, ApplyWorkspaceEditResult,
+    ExternalWorkspaceEditDocument, LspWorkspaceSync, WorkspaceEditVersionMismatch,
+    apply_workspace_edit_to_workspace,
 };
@@ -6,9 +6,10 @@
 
 use crate::lsp_transport::{read_lsp_message, write_lsp_message};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::io::{self, BufReader, BufWriter};
 use std::process::{Child, Command as ProcessCommand, Stdio};
-use std::sync::mpsc;
+use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -28,13 +29,144 @@ pub enum LspInbound {
     IoError(String),
 }
 
+/// Something that can send LSP requests and later deliver their responses.
+///
+/// Higher-level helpers (e.g. [`crate::CompletionSession`]) only need to *send* requests; the
+/// host routes responses back to them. Implemented by [`LspClient`] and
+/// [`crate::LspSession`], and easy to fake in tests.
+pub trait LspRequestSender {
+    /// Send a request and return its JSON-RPC id.
+    fn send_request(&mut self, method: &str, params: Value) -> Result<u64, String>;
+
+    /// Cancel a request sent through [`send_request`](Self::send_request) (`$/cancelRequest`).
+    ///
+    /// The default does nothing, for senders that cannot cancel.
+    fn cancel_request(&mut self, request_id: u64) -> Result<(), String> {
+        let _ = request_id;
+        Ok(())
+    }
+}
+
+/// Ids of canceled requests whose (late) responses should be dropped, with the time of their
+/// cancellation.
+type CanceledRequests = Arc<Mutex<HashMap<u64, Instant>>>;
+
+/// How long a late response to a canceled request is waited for (and dropped when it arrives).
+///
+/// Servers may never answer a canceled request; [`LspClient::expire_requests`] forgets ids
+/// canceled longer ago than this, so the bookkeeping stays bounded.
+pub const CANCELED_RESPONSE_GRACE: Duration = Duration::from_secs(60);
+
+/// Deadlines for outgoing requests, by method.
+///
+/// Requests whose method has no entry in `per_method` use `default`; `None` means no deadline.
+/// Expired requests are canceled by [`LspClient::expire_requests`] (which
+/// [`crate::LspSession`] calls on every poll).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LspRequestTimeouts {
+    /// Timeout for methods without a `per_method` entry.
+    pub default: Option<Duration>,
+    /// Timeouts by method name (e.g. a short one for `textDocument/completion`, a long one for
+    /// `textDocument/rename`).
+    pub per_method: HashMap<String, Duration>,
+}
+
+impl LspRequestTimeouts {
+    /// No deadlines.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the timeout for methods without a specific one.
+    pub fn with_default(mut self, timeout: Duration) -> Self {
+        self.default = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for `method`.
+    pub fn with_method(mut self, method: impl Into<String>, timeout: Duration) -> Self {
+        self.per_method.insert(method.into(), timeout);
+        self
+    }
+
+    /// The timeout that applies to `method`, if any.
+    pub fn timeout_for(&self, method: &str) -> Option<Duration> {
+        self.per_method.get(method).copied().or(self.default)
+    }
+}
+
+/// A request canceled by [`LspClient::expire_requests`] because its deadline passed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspTimedOutRequest {
+    /// Request id.
+    pub id: u64,
+    /// Request method.
+    pub method: String,
+    /// The timeout that expired.
+    pub timeout: Duration,
+}
+
+/// A request sent by the client whose response has not arrived yet.
+#[derive(Debug, Clone)]
+struct InFlightRequest {
+    method: String,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+/// A handle that can cancel one in-flight request (`$/cancelRequest`) without borrowing the
+/// client.
+///
+/// Handles from [`LspClient::cancel_handle`] also make the client drop the request's response
+/// if it still arrives.
+#[derive(Debug, Clone)]
+pub struct LspCancelHandle {
+    tx: mpsc::Sender<LspOutbound>,
+    request_id: u64,
+    canceled: CanceledRequests,
+}
+
+impl LspCancelHandle {
+    /// Create a handle that sends its cancellation through `tx`.
+    pub fn new(tx: mpsc::Sender<LspOutbound>, request_id: u64) -> Self {
+        Self {
+            tx,
+            request_id,
+            canceled: CanceledRequests::default(),
+        }
+    }
+
+    /// The request this handle cancels.
+    pub fn request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    /// Send `$/cancelRequest` for the request.
+    ///
+    /// Fails only if the connection is gone, in which case there is nothing left to cancel.
+    pub fn cancel(&self) -> io::Result<()> {
+        if let Ok(mut canceled) = self.canceled.lock() {
+            canceled.insert(self.request_id, Instant::now());
+        }
+        self.tx
+            .send(LspOutbound::Message(json_rpc_notification(
+                "$/cancelRequest",
+                serde_json::json!({ "id": self.request_id }),
+            )))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "LSP writer thread stopped"))
+    }
+}
+
 /// A minimal JSON-RPC/LSP client implemented on top of stdio pipes.
 pub struct LspClient {
-    _child: Child,
+    child: Child,
     tx: mpsc::Sender<LspOutbound>,
     rx: mpsc::Receiver<LspInbound>,
     next_id: u64,
     workspace_folders: Vec<Value>,
+    canceled: CanceledRequests,
+    timeouts: LspRequestTimeouts,
+    in_flight: Mutex<HashMap<u64, InFlightRequest>>,
 }
 
 impl LspClient {
@@ -70,14 +202,51 @@ impl LspClient {
         thread::spawn(move || lsp_read_loop(stdout, tx_in));
 
         Ok(Self {
-            _child: child,
+            child,
             tx: tx_out,
             rx: rx_in,
             next_id: 1,
             workspace_folders,
+            canceled: CanceledRequests::default(),
+            timeouts: LspRequestTimeouts::default(),
+            in_flight: Mutex::new(HashMap::new()),
         })
     }
 
+    /// Returns `true` while the server process has not exited.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Kill the server process (if still running) and reap it.
+    ///
+    /// Canceled requests are forgotten: no late response can arrive any more.
+    pub fn kill(&mut self) -> io::Result<()> {
+        if let Ok(mut canceled) = self.canceled.lock() {
+            canceled.clear();
+        }
+        if self.is_running() {
+            self.child.kill()?;
+        }
+        self.child.wait().map(|_| ())
+    }
+
+    /// Continue allocating request ids after `previous`'s, so ids stay unique across a server
+    /// restart and a late answer for an old id can never match a new request.
+    pub(crate) fn continue_request_ids(&mut self, previous: &LspClient) {
+        self.next_id = self.next_id.max(previous.next_id);
+    }
+
+    /// Set the per-method request deadlines (applies to requests sent from now on).
+    pub fn set_request_timeouts(&mut self, timeouts: LspRequestTimeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// The per-method request deadlines.
+    pub fn request_timeouts(&self) -> &LspRequestTimeouts {
+        &self.timeouts
+    }
+
     /// Send a JSON-RPC notification to the server.
     pub fn notify(&self, method: &str, params: Value) -> io::Result<()> {
         self.send_message(json_rpc_notification(method, params))
@@ -89,9 +258,135 @@ impl LspClient {
         self.next_id = self.next_id.saturating_add(1);
 
         self.send_message(json_rpc_request(id, method, params))?;
+        let timeout = self.timeouts.timeout_for(method);
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.insert(
+                id,
+                InFlightRequest {
+                    method: method.to_string(),
+                    timeout,
+                    deadline: timeout.map(|timeout| Instant::now() + timeout),
+                },
+            );
+        }
         Ok(id)
     }
 
+    /// Ids of requests whose responses have not arrived (and that were not canceled), ascending.
+    pub fn in_flight_requests(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .in_flight
+            .lock()
+            .map(|in_flight| in_flight.keys().copied().collect())
+            .unwrap_or_default();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The method of an in-flight request.
+    pub fn in_flight_method(&self, request_id: u64) -> Option<String> {
+        let in_flight = self.in_flight.lock().ok()?;
+        in_flight
+            .get(&request_id)
+            .map(|request| request.method.clone())
+    }
+
+    /// Cancel every in-flight request whose deadline is at or before `now`.
+    ///
+    /// Each expired request gets a `$/cancelRequest` and its late response is dropped, like
+    /// [`cancel_request`](Self::cancel_request). Returns the expired requests in id order.
+    ///
+    /// This also forgets requests canceled at least [`CANCELED_RESPONSE_GRACE`] before `now`
+    /// (a response to one of them is then delivered like any other).
+    pub fn expire_requests(&self, now: Instant) -> io::Result<Vec<LspTimedOutRequest>> {
+        if let Ok(mut canceled) = self.canceled.lock() {
+            canceled.retain(|_, at| now.saturating_duration_since(*at) < CANCELED_RESPONSE_GRACE);
+        }
+        let mut expired: Vec<LspTimedOutRequest> = match self.in_flight.lock() {
+            Ok(mut in_flight) => {
+                let ids: Vec<u64> = in_flight
+                    .iter()
+                    .filter(|(_, request)| request.deadline.is_some_and(|d| d <= now))
+                    .map(|(id, _)| *id)
+                    .collect();
+                ids.into_iter()
+                    .filter_map(|id| {
+                        let request = in_flight.remove(&id)?;
+                        Some(LspTimedOutRequest {
+                            id,
+                            method: request.method,
+                            timeout: request.timeout.unwrap_or_default(),
+                        })
+                    })
+                    .collect()
+            }
+            Err(_) => Vec::new(),
+        };
+        expired.sort_by_key(|request| request.id);
+        for request in &expired {
+            self.cancel_request(request.id)?;
+        }
+        Ok(expired)
+    }
+
+    /// Cancel every in-flight request (e.g. before `shutdown`). Returns their ids, ascending.
+    pub fn cancel_all(&self) -> io::Result<Vec<u64>> {
+        let ids = self.in_flight_requests();
+        for &id in &ids {
+            self.cancel_request(id)?;
+        }
+        Ok(ids)
+    }
+
+    /// Send `$/cancelRequest` for one of our requests.
+    ///
+    /// The request's response, if the server still sends one, is dropped by
+    /// [`try_recv`](Self::try_recv), [`recv_timeout`](Self::recv_timeout) and
+    /// [`wait_for_response`](Self::wait_for_response) instead of being delivered.
+    pub fn cancel_request(&self, request_id: u64) -> io::Result<()> {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&request_id);
+        }
+        self.cancel_handle(request_id).cancel()
+    }
+
+    /// A handle that can cancel `request_id` later, e.g. from a `Drop` impl.
+    pub fn cancel_handle(&self, request_id: u64) -> LspCancelHandle {
+        LspCancelHandle {
+            tx: self.tx.clone(),
+            request_id,
+            canceled: self.canceled.clone(),
+        }
+    }
+
+    /// Returns `true` if `request_id` was canceled and its response has not arrived yet.
+    pub fn is_canceled(&self, request_id: u64) -> bool {
+        self.canceled
+            .lock()
+            .is_ok_and(|canceled| canceled.contains_key(&request_id))
+    }
+
+    /// Returns `true` (and forgets the id) if `inbound` is the response to a canceled request.
+    ///
+    /// Other responses complete their in-flight request.
+    fn is_canceled_response(&self, inbound: &LspInbound) -> bool {
+        let LspInbound::Message(msg) = inbound else {
+            return false;
+        };
+        if msg.get("method").is_some() {
+            return false;
+        }
+        let Some(id) = msg.get("id").and_then(Value::as_u64) else {
+            return false;
+        };
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&id);
+        }
+        self.canceled
+            .lock()
+            .is_ok_and(|mut canceled| canceled.remove(&id).is_some())
+    }
+
     /// Send a successful JSON-RPC response for a server-initiated request.
     pub fn respond(&self, id: u64, result: Value) -> io::Result<()> {
         self.send_message(json_rpc_response(id, result))
@@ -115,15 +410,44 @@ impl LspClient {
     }
 
     /// Try to receive the next inbound message without blocking.
+    ///
+    /// Responses to canceled requests are skipped.
     pub fn try_recv(&self) -> Option<LspInbound> {
-        self.rx.try_recv().ok()
+        loop {
+            let inbound = self.rx.try_recv().ok()?;
+            if !self.is_canceled_response(&inbound) {
+                return Some(inbound);
+            }
+        }
+    }
+
+    /// Receive the next inbound message, waiting up to `timeout`.
+    ///
+    /// Responses to canceled requests are skipped.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<LspInbound> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let inbound = self.rx.recv_timeout(remaining).ok()?;
+            if !self.is_canceled_response(&inbound) {
+                return Some(inbound);
+            }
+        }
     }
 
     /// Wait for a matching JSON-RPC response message `{ id: request_id, ... }`.
     ///
     /// While waiting, this also answers common server->client requests (e.g. `workspace/configuration`)
     /// via [`Self::handle_server_request`], to avoid deadlocks.
+    ///
+    /// Fails right away if `request_id` was canceled.
     pub fn wait_for_response(&mut self, request_id: u64, timeout: Duration) -> io::Result<Value> {
+        if self.is_canceled(request_id) {
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                format!("LSP request id={} was canceled", request_id),
+            ));
+        }
         let deadline = Instant::now() + timeout;
 
         loop {
@@ -140,6 +464,9 @@ impl LspClient {
                 .rx
                 .recv_timeout(remaining)
                 .map_err(|err| io::Error::new(io::ErrorKind::TimedOut, err))?;
+            if self.is_canceled_response(&inbound) {
+                continue;
+            }
 
             match inbound {
                 LspInbound::IoError(err) => {
@@ -200,6 +527,18 @@ impl LspClient {
     }
 }
 
+impl LspRequestSender for LspClient {
+    fn send_request(&mut self, method: &str, params: Value) -> Result<u64, String> {
+        self.request(method, params)
+            .map_err(|err| format!("LSP request 失败 ({}): {}", method, err))
+    }
+
+    fn cancel_request(&mut self, request_id: u64) -> Result<(), String> {
+        LspClient::cancel_request(self, request_id)
+            .map_err(|err| format!("LSP notify 失败 ($/cancelRequest): {}", err))
+    }
+}
+
 fn json_rpc_notification(method: &str, params: Value) -> Value {
     let mut obj = serde_json::Map::new();
     obj.insert("jsonrpc".to_string(), Value::String("2.0".to_string()));
@@ -267,7 +606,12 @@ fn lsp_read_loop(stdout: std::process::ChildStdout, tx: mpsc::Sender<LspInbound>
                     break;
                 }
             }
-            Ok(None) => break,
+            Ok(None) => {
+                let _ = tx.send(LspInbound::IoError(
+                    "LSP server closed the connection".to_string(),
+                ));
+                break;
+            }
             Err(err) => {
                 let _ = tx.send(LspInbound::IoError(err.to_string()));
                 break;
@@ -0,0 +1,290 @@
+//! Code action helpers (`textDocument/codeAction`).
+//!
+//! - [`code_action_context`] builds a `CodeActionContext` from `editor-core` diagnostics, so
+//!   servers can offer quick fixes for the problems under the requested range.
+//! - [`code_action_items_from_value`] normalizes the result, which may mix bare `Command`s and
+//!   `CodeAction` literals, into [`CodeActionItem`]s.
+//!
+//! [`code_actions_from_value`] is the flat variant returning [`CodeAction`]s only.
+//!
+//! Applying an item is a two-step affair: its `edit` (a `WorkspaceEdit`) is applied through the
+//! usual workspace-edit path, then its `command` (if any) is sent back to the server with
+//! `workspace/executeCommand`.
+
+use crate::editor::{lsp_position_for_offset, lsp_range_to_json};
+use crate::lsp_events::LspDiagnostic;
+use crate::lsp_sync::LspRange;
+use editor_core::{Diagnostic, DiagnosticSeverity, DiagnosticTag, LineIndex};
+use serde_json::{Map, Value, json};
+
+/// An LSP `Command`: a server-side action identified by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LspCommand {
+    /// Title shown in the UI.
+    pub title: String,
+    /// Command identifier (e.g. `rust-analyzer.applySourceChange`).
+    pub command: String,
+    /// Command arguments.
+    pub arguments: Vec<Value>,
+}
+
+impl LspCommand {
+    /// Parse an LSP `Command` object.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        Some(Self {
+            title: value
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string(),
+            command: value.get("command")?.as_str()?.to_string(),
+            arguments: value
+                .get("arguments")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// An LSP `CodeAction` literal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeAction {
+    /// Title shown in the UI.
+    pub title: String,
+    /// Action kind (e.g. `quickfix`, `refactor.extract`).
+    pub kind: Option<String>,
+    /// Whether the server marks this as the preferred fix.
+    pub is_preferred: bool,
+    /// Diagnostics this action resolves.
+    pub diagnostics: Vec<LspDiagnostic>,
+    /// Reason the action is currently unavailable (`disabled.reason`).
+    pub disabled_reason: Option<String>,
+    /// The `WorkspaceEdit` to apply, if already known.
+    pub edit: Option<Value>,
+    /// Command to execute after the edit is applied.
+    pub command: Option<LspCommand>,
+    /// The original JSON object (sent back for `codeAction/resolve`).
+    pub raw: Value,
+}
+
+impl CodeAction {
+    /// Parse an LSP `CodeAction` object.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        Some(Self {
+            title: value.get("title")?.as_str()?.to_string(),
+            kind: value
+                .get("kind")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            is_preferred: value
+                .get("isPreferred")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            diagnostics: value
+                .get("diagnostics")
+                .and_then(Value::as_array)
+                .map(|diags| diags.iter().filter_map(LspDiagnostic::from_value).collect())
+                .unwrap_or_default(),
+            disabled_reason: value
+                .get("disabled")
+                .and_then(|disabled| disabled.get("reason"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            edit: value.get("edit").filter(|edit| !edit.is_null()).cloned(),
+            command: value.get("command").and_then(LspCommand::from_value),
+            raw: value.clone(),
+        })
+    }
+
+    /// Returns `true` if the action must go through `codeAction/resolve` before it can be applied.
+    ///
+    /// Servers that resolve lazily omit `edit` and keep a `data` payload instead; an action with
+    /// neither an edit nor a command carries nothing to apply either.
+    pub fn needs_resolve(&self) -> bool {
+        self.edit.is_none() && (self.command.is_none() || self.raw.get("data").is_some())
+    }
+}
+
+impl From<LspCommand> for CodeAction {
+    /// A bare `Command` as a command-only code action.
+    fn from(command: LspCommand) -> Self {
+        let raw = json!({
+            "title": command.title,
+            "command": command.command,
+            "arguments": command.arguments,
+        });
+        Self {
+            title: command.title.clone(),
+            kind: None,
+            is_preferred: false,
+            diagnostics: Vec::new(),
+            disabled_reason: None,
+            edit: None,
+            command: Some(command),
+            raw,
+        }
+    }
+}
+
+/// One entry of a `textDocument/codeAction` result.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodeActionItem {
+    /// A bare `Command` (only executable via `workspace/executeCommand`).
+    Command(LspCommand),
+    /// A `CodeAction` literal.
+    Action(CodeAction),
+}
+
+impl CodeActionItem {
+    /// Title shown in the UI.
+    pub fn title(&self) -> &str {
+        match self {
+            CodeActionItem::Command(command) => &command.title,
+            CodeActionItem::Action(action) => &action.title,
+        }
+    }
+
+    /// Action kind, if any (bare commands have none).
+    pub fn kind(&self) -> Option<&str> {
+        match self {
+            CodeActionItem::Command(_) => None,
+            CodeActionItem::Action(action) => action.kind.as_deref(),
+        }
+    }
+
+    /// Returns `true` for `quickfix` (and `quickfix.*`) actions.
+    pub fn is_quick_fix(&self) -> bool {
+        self.kind()
+            .is_some_and(|kind| kind == "quickfix" || kind.starts_with("quickfix."))
+    }
+
+    /// Returns `true` if the action is disabled and should not be applied.
+    pub fn is_disabled(&self) -> bool {
+        matches!(self, CodeActionItem::Action(action) if action.disabled_reason.is_some())
+    }
+}
+
+/// Normalize a `textDocument/codeAction` result (`(Command | CodeAction)[] | null`).
+pub fn code_action_items_from_value(value: &Value) -> Vec<CodeActionItem> {
+    let Some(items) = value.as_array() else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            // A `Command` has a string `command`; a `CodeAction`'s `command` is an object.
+            if item.get("command").is_some_and(Value::is_string) {
+                LspCommand::from_value(item).map(CodeActionItem::Command)
+            } else {
+                CodeAction::from_value(item).map(CodeActionItem::Action)
+            }
+        })
+        .collect()
+}
+
+/// Decode a `textDocument/codeAction` result into a flat list of [`CodeAction`]s.
+///
+/// Like [`code_action_items_from_value`], but bare `Command`s become command-only actions, which
+/// is convenient for hosts that render a single quick-fix menu.
+pub fn code_actions_from_value(value: &Value) -> Vec<CodeAction> {
+    code_action_items_from_value(value)
+        .into_iter()
+        .map(|item| match item {
+            CodeActionItem::Command(command) => CodeAction::from(command),
+            CodeActionItem::Action(action) => action,
+        })
+        .collect()
+}
+
+/// Pick the quick fix to apply for a range: the preferred one if any, else the first enabled one.
+pub fn preferred_quick_fix(items: &[CodeActionItem]) -> Option<&CodeActionItem> {
+    let mut fixes = items
+        .iter()
+        .filter(|item| item.is_quick_fix() && !item.is_disabled());
+    let first = fixes.clone().next();
+    fixes
+        .find(|item| matches!(item, CodeActionItem::Action(action) if action.is_preferred))
+        .or(first)
+}
+
+/// Convert an `editor-core` diagnostic back into an LSP `Diagnostic` object.
+pub fn diagnostic_to_lsp_value(line_index: &LineIndex, diagnostic: &Diagnostic) -> Value {
+    let range = LspRange::new(
+        lsp_position_for_offset(line_index, diagnostic.range.start),
+        lsp_position_for_offset(line_index, diagnostic.range.end),
+    );
+
+    let mut out = Map::new();
+    out.insert("range".to_string(), lsp_range_to_json(&range));
+    out.insert("message".to_string(), json!(diagnostic.message));
+    if let Some(severity) = diagnostic.severity {
+        let severity = match severity {
+            DiagnosticSeverity::Error => 1,
+            DiagnosticSeverity::Warning => 2,
+            DiagnosticSeverity::Information => 3,
+            DiagnosticSeverity::Hint => 4,
+        };
+        out.insert("severity".to_string(), json!(severity));
+    }
+    if let Some(code) = diagnostic.code.as_deref() {
+        // Codes are stringified on the way in; restore integer codes.
+        let code = code
+            .parse::<i64>()
+            .map_or_else(|_| json!(code), |n| json!(n));
+        out.insert("code".to_string(), code);
+    }
+    if let Some(href) = diagnostic.code_description_href.as_deref() {
+        out.insert("codeDescription".to_string(), json!({ "href": href }));
+    }
+    if let Some(source) = diagnostic.source.as_deref() {
+        out.insert("source".to_string(), json!(source));
+    }
+    if !diagnostic.tags.is_empty() {
+        let tags: Vec<u8> = diagnostic
+            .tags
+            .iter()
+            .map(|tag| match tag {
+                DiagnosticTag::Unnecessary => 1,
+                DiagnosticTag::Deprecated => 2,
+            })
+            .collect();
+        out.insert("tags".to_string(), json!(tags));
+    }
+    let parse_json = |text: &Option<String>| {
+        text.as_deref()
+            .and_then(|text| serde_json::from_str::<Value>(text).ok())
+    };
+    if let Some(related) = parse_json(&diagnostic.related_information_json) {
+        out.insert("relatedInformation".to_string(), related);
+    }
+    if let Some(data) = parse_json(&diagnostic.data_json) {
+        out.insert("data".to_string(), data);
+    }
+    Value::Object(out)
+}
+
+/// Build a `CodeActionContext` for the char range `start..end`.
+///
+/// Only diagnostics overlapping the range are included; an empty range (a caret) picks up the
+/// diagnostics that touch it. `only` restricts the requested action kinds (e.g. `["quickfix"]`).
+pub fn code_action_context(
+    line_index: &LineIndex,
+    start: usize,
+    end: usize,
+    diagnostics: &[Diagnostic],
+    only: Option<&[&str]>,
+) -> Value {
+    let (start, end) = (start.min(end), start.max(end));
+    let diagnostics: Vec<Value> = diagnostics
+        .iter()
+        .filter(|diag| diag.range.start <= end && start <= diag.range.end)
+        .map(|diag| diagnostic_to_lsp_value(line_index, diag))
+        .collect();
+
+    let mut context = json!({ "diagnostics": diagnostics, "triggerKind": 1 });
+    if let Some(only) = only {
+        context["only"] = json!(only);
+    }
+    context
+}
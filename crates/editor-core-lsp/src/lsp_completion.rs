@@ -6,12 +6,21 @@
 //! - `CompletionItem.textEdit` as `TextEdit`
 //! - `CompletionItem.textEdit` as `InsertReplaceEdit` (choose insert vs replace)
 //! - `CompletionItem.additionalTextEdits`
-//! - Snippet-shaped inserts (`insertTextFormat == 2`) are downgraded to plain text (best-effort).
+//! - Snippet-shaped inserts (`insertTextFormat == 2`): the literal text is inserted and
+//!   [`apply_completion_item`] returns the tabstops (see [`editor_core::parse_snippet`]).
+//!
+//! [`CompletionSession`] manages the list side: requesting `textDocument/completion`, filtering
+//! the items as the user keeps typing, and `completionItem/resolve`.
 
-use crate::lsp_sync::{LspPosition, LspRange};
+use crate::lsp_client::LspRequestSender;
+use crate::lsp_events::LspResponse;
+use crate::lsp_sync::{LspCoordinateConverter, LspPosition, LspRange};
 use crate::lsp_text_edits::{LspTextEdit, char_offsets_for_lsp_range, text_edits_from_value};
-use editor_core::{Command, EditCommand, EditorStateManager, LineIndex, TextEditSpec};
-use serde_json::Value;
+use editor_core::{
+    Command, CompletionAcceptMode, EditCommand, EditorStateManager, LineIndex, ParsedSnippet,
+    SnippetTabstop, TextEditSpec, completion_edit_at_caret, fuzzy_match_score, parse_snippet,
+};
+use serde_json::{Value, json};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Which range to apply when a completion item uses an LSP `InsertReplaceEdit`.
@@ -42,115 +51,14 @@ fn completion_item_insert_text_is_snippet(item: &Value) -> bool {
         .unwrap_or(false)
 }
 
-fn snippet_to_plain_text(snippet: &str) -> String {
-    // Best-effort "downgrade" of LSP snippets:
-    // - `${1:foo}` -> `foo`
-    // - `${1}` / `$1` -> ``
-    // - `$0` -> ``
-    //
-    // This is intentionally not a full snippet engine. It aims to produce a reasonable insert
-    // text for headless usage until a real snippet subsystem lands (roadmap P1).
-    fn parse_braced_placeholder(content: &str) -> String {
-        // `${1:foo}` / `${1|a,b|}` / `${1}`
-        let mut chars = content.chars().peekable();
-        let mut number = String::new();
-        while let Some(c) = chars.peek().copied() {
-            if c.is_ascii_digit() {
-                number.push(c);
-                chars.next();
-            } else {
-                break;
-            }
-        }
-
-        if number.is_empty() {
-            // Variables like `${TM_FILENAME}` - cannot be resolved here.
-            return String::new();
-        }
-
-        // `$0` and `${0:...}` mean final cursor position; no textual output.
-        if number == "0" {
-            return String::new();
-        }
-
-        match chars.next() {
-            None => String::new(),
-            Some('}') => String::new(),
-            Some(':') => snippet_to_plain_text(chars.collect::<String>().as_str()),
-            Some('|') => {
-                // `${1|a,b,c|}`: pick first option.
-                let rest: String = chars.collect();
-                let trimmed = rest.strip_suffix("|}").unwrap_or(rest.as_str());
-                trimmed.split(',').next().unwrap_or("").to_string()
-            }
-            _ => String::new(),
-        }
-    }
-
-    let mut out = String::with_capacity(snippet.len());
-    let mut chars = snippet.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        match c {
-            '\\' => {
-                // Escape: keep the next char as-is if any.
-                if let Some(next) = chars.next() {
-                    out.push(next);
-                }
-            }
-            '$' => match chars.peek().copied() {
-                Some('{') => {
-                    chars.next(); // consume '{'
-                    let mut content = String::new();
-                    for ch in chars.by_ref() {
-                        if ch == '}' {
-                            break;
-                        }
-                        content.push(ch);
-                    }
-                    out.push_str(&parse_braced_placeholder(&content));
-                }
-                Some(d) if d.is_ascii_digit() => {
-                    // `$1` / `$0`
-                    let mut number = String::new();
-                    while let Some(ch) = chars.peek().copied() {
-                        if ch.is_ascii_digit() {
-                            number.push(ch);
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                    if number == "0" {
-                        // final cursor; ignore
-                    } else {
-                        // placeholder; ignore
-                    }
-                }
-                Some(ch) if ch == '_' || ch.is_ascii_alphabetic() => {
-                    // `$VAR` - cannot be resolved here.
-                    while let Some(next) = chars.peek().copied() {
-                        if next == '_' || next.is_ascii_alphanumeric() {
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                }
-                _ => out.push('$'),
-            },
-            other => out.push(other),
-        }
-    }
-
-    out
-}
-
-fn normalize_completion_insert_text(item: &Value, text: &str) -> String {
+/// The text a completion inserts for `text` (`newText` / `insertText` / `label`), and the parsed
+/// snippet if the item is snippet-shaped.
+fn completion_insert_text(item: &Value, text: &str) -> (String, Option<ParsedSnippet>) {
     if completion_item_insert_text_is_snippet(item) {
-        snippet_to_plain_text(text)
+        let snippet = parse_snippet(text);
+        (snippet.text.clone(), Some(snippet))
     } else {
-        text.to_string()
+        (text.to_string(), None)
     }
 }
 
@@ -162,9 +70,7 @@ fn completion_item_main_text_edit(
 
     // Standard `TextEdit`: { range, newText }
     if text_edit.get("range").is_some() {
-        let mut edit = LspTextEdit::from_value(text_edit)?;
-        edit.new_text = normalize_completion_insert_text(item, &edit.new_text);
-        return Some(edit);
+        return LspTextEdit::from_value(text_edit);
     }
 
     // `InsertReplaceEdit`: { insert, replace, newText }
@@ -179,88 +85,468 @@ fn completion_item_main_text_edit(
 
     Some(LspTextEdit {
         range,
-        new_text: normalize_completion_insert_text(item, new_text),
+        new_text: new_text.to_string(),
     })
 }
 
-fn completion_item_fallback_insert_text(item: &Value) -> Option<String> {
+fn completion_item_fallback_insert_text(item: &Value) -> Option<&str> {
     item.get("insertText")
         .and_then(Value::as_str)
         .or_else(|| item.get("label").and_then(Value::as_str))
-        .map(|s| normalize_completion_insert_text(item, s))
-        .filter(|s| !s.is_empty())
 }
 
-/// Convert an LSP `CompletionItem` value into a batch of `TextEditSpec`s (pre-edit char offsets).
-///
-/// - Always includes `additionalTextEdits` if present.
-/// - Uses `textEdit` if present, otherwise falls back to `insertText`/`label` + `fallback_range`.
-pub fn completion_item_to_text_edit_specs(
+/// The main edit of a completion item (with its parsed snippet, if any) and its
+/// `additionalTextEdits`, in pre-edit char offsets.
+fn completion_item_edits(
     line_index: &LineIndex,
     item: &Value,
     mode: CompletionTextEditMode,
     fallback_range: Option<(usize, usize)>,
-) -> Vec<TextEditSpec> {
-    let mut out = Vec::<TextEditSpec>::new();
-
-    if let Some(edit) = completion_item_main_text_edit(item, mode) {
+) -> (
+    Option<(TextEditSpec, Option<ParsedSnippet>)>,
+    Vec<TextEditSpec>,
+) {
+    let main = if let Some(edit) = completion_item_main_text_edit(item, mode) {
         let (start, end) = char_offsets_for_lsp_range(line_index, &edit.range);
-        out.push(TextEditSpec {
-            start,
-            end,
-            text: edit.new_text,
-        });
+        let (text, snippet) = completion_insert_text(item, &edit.new_text);
+        Some((TextEditSpec { start, end, text }, snippet))
     } else if let (Some((start, end)), Some(text)) =
         (fallback_range, completion_item_fallback_insert_text(item))
     {
-        out.push(TextEditSpec { start, end, text });
-    }
+        let (text, snippet) = completion_insert_text(item, text);
+        (!text.is_empty()).then_some((TextEditSpec { start, end, text }, snippet))
+    } else {
+        None
+    };
 
-    if let Some(additional) = item.get("additionalTextEdits") {
-        for edit in text_edits_from_value(additional) {
-            let (start, end) = char_offsets_for_lsp_range(line_index, &edit.range);
-            out.push(TextEditSpec {
-                start,
-                end,
-                text: edit.new_text,
-            });
-        }
-    }
+    let additional = item
+        .get("additionalTextEdits")
+        .map(|additional| {
+            text_edits_from_value(additional)
+                .into_iter()
+                .map(|edit| {
+                    let (start, end) = char_offsets_for_lsp_range(line_index, &edit.range);
+                    TextEditSpec {
+                        start,
+                        end,
+                        text: edit.new_text,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
 
-    out
+    (main, additional)
 }
 
-fn primary_selection_char_range(state_manager: &EditorStateManager) -> (usize, usize) {
-    let editor = state_manager.editor();
-    let line_index = &editor.line_index;
+/// Convert an LSP `CompletionItem` value into a batch of `TextEditSpec`s (pre-edit char offsets).
+///
+/// - Always includes `additionalTextEdits` if present.
+/// - Uses `textEdit` if present, otherwise falls back to `insertText`/`label` + `fallback_range`.
+/// - Snippets are inserted as their literal text (see [`editor_core::parse_snippet`]).
+pub fn completion_item_to_text_edit_specs(
+    line_index: &LineIndex,
+    item: &Value,
+    mode: CompletionTextEditMode,
+    fallback_range: Option<(usize, usize)>,
+) -> Vec<TextEditSpec> {
+    let (main, additional) = completion_item_edits(line_index, item, mode, fallback_range);
+    main.map(|(edit, _)| edit)
+        .into_iter()
+        .chain(additional)
+        .collect()
+}
 
-    if let Some(sel) = editor.selection() {
-        let a = line_index.position_to_char_offset(sel.start.line, sel.start.column);
-        let b = line_index.position_to_char_offset(sel.end.line, sel.end.column);
-        return (a.min(b), a.max(b));
+fn accept_mode(mode: CompletionTextEditMode) -> CompletionAcceptMode {
+    match mode {
+        CompletionTextEditMode::Insert => CompletionAcceptMode::Insert,
+        CompletionTextEditMode::Replace => CompletionAcceptMode::Replace,
     }
-
-    let pos = editor.cursor_position();
-    let offset = line_index.position_to_char_offset(pos.line, pos.column);
-    (offset, offset)
 }
 
 /// Apply a completion item to the editor as a **single undoable step** (uses `ApplyTextEdits`).
+///
+/// Snippet items (`insertTextFormat == 2`) insert their literal text; the returned tabstops are
+/// in tab order (see [`ParsedSnippet::tabstops_in_tab_order`]) and in post-edit document char
+/// offsets, so the host can select the first stop (and its mirrors) as a multi-cursor. Plain
+/// items return no tabstops.
 pub fn apply_completion_item(
     state_manager: &mut EditorStateManager,
     item: &Value,
     mode: CompletionTextEditMode,
-) -> Result<(), String> {
-    let fallback = primary_selection_char_range(state_manager);
-    let line_index = &state_manager.editor().line_index;
-    let edits = completion_item_to_text_edit_specs(line_index, item, mode, Some(fallback));
+) -> Result<Vec<SnippetTabstop>, String> {
+    // Items without a `textEdit` replace the word around the caret exactly like buffer-word
+    // completion does (see `editor_core::completion_edit_at_caret`).
+    let editor = state_manager.editor();
+    let fallback = completion_edit_at_caret(editor, "", accept_mode(mode));
+    let (main, additional) = completion_item_edits(
+        &editor.line_index,
+        item,
+        mode,
+        Some((fallback.start, fallback.end)),
+    );
 
+    // Where the main edit starts once the additional edits before it are applied.
+    let tabstops = match &main {
+        Some((edit, Some(snippet))) => {
+            let mut base = edit.start as i64;
+            for other in additional.iter().filter(|other| other.end <= edit.start) {
+                base += other.text.chars().count() as i64 - (other.end - other.start) as i64;
+            }
+            let base = base.max(0) as usize;
+            snippet
+                .tabstops_in_tab_order()
+                .into_iter()
+                .map(|tabstop| SnippetTabstop {
+                    index: tabstop.index,
+                    start: base + tabstop.start,
+                    end: base + tabstop.end,
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let edits: Vec<TextEditSpec> = main
+        .map(|(edit, _)| edit)
+        .into_iter()
+        .chain(additional)
+        .collect();
     if edits.is_empty() {
         return Err("completion item 没有可应用的 textEdit / insertText".to_string());
     }
 
     state_manager
         .execute(Command::Edit(EditCommand::ApplyTextEdits { edits }))
-        .map(|_| ())
-        .map_err(|err| format!("apply completion item 失败: {}", err))
+        .map_err(|err| format!("apply completion item 失败: {}", err))?;
+    Ok(tabstops)
+}
+
+/// Parse a `textDocument/completion` result (`CompletionItem[]`, `CompletionList` or `null`).
+///
+/// Returns `(items, is_incomplete)`.
+pub fn completion_items_from_value(result: &Value) -> (Vec<Value>, bool) {
+    match result {
+        Value::Array(items) => (items.clone(), false),
+        Value::Object(list) => {
+            let items = list
+                .get("items")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            let is_incomplete = list
+                .get("isIncomplete")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            (items, is_incomplete)
+        }
+        _ => (Vec::new(), false),
+    }
+}
+
+/// Display label of a completion item (`label`, or an empty string).
+pub fn completion_item_label(item: &Value) -> &str {
+    item.get("label").and_then(Value::as_str).unwrap_or("")
+}
+
+fn completion_item_filter_text(item: &Value) -> &str {
+    item.get("filterText")
+        .and_then(Value::as_str)
+        .unwrap_or_else(|| completion_item_label(item))
+}
+
+fn completion_item_sort_text(item: &Value) -> &str {
+    item.get("sortText")
+        .and_then(Value::as_str)
+        .unwrap_or_else(|| completion_item_label(item))
+}
+
+/// `CompletionTriggerKind.Invoked`.
+const TRIGGER_INVOKED: u64 = 1;
+/// `CompletionTriggerKind.TriggerForIncompleteCompletions`.
+const TRIGGER_FOR_INCOMPLETE: u64 = 3;
+
+/// Client-side state of one completion popup.
+///
+/// The session is transport-agnostic: it sends requests through an [`LspRequestSender`] and the
+/// host feeds responses back via [`CompletionSession::handle_response`]. Typical flow:
+///
+/// 1. [`trigger`](Self::trigger) at the caret (sends `textDocument/completion`).
+/// 2. [`handle_response`](Self::handle_response) for every [`LspResponse`] the host receives.
+/// 3. [`update`](Self::update) after each edit/caret move: re-filters locally with
+///    [`fuzzy_match_score`], re-requests while the server reported `isIncomplete`, and closes
+///    the session once the caret leaves the completed word.
+/// 4. [`accept`](Self::accept) an item (via [`apply_completion_item`]), optionally after
+///    [`resolve`](Self::resolve)-ing it.
+#[derive(Debug, Clone)]
+pub struct CompletionSession {
+    uri: String,
+    /// Char offset of the start of the word being completed.
+    anchor: usize,
+    prefix: String,
+    items: Vec<Value>,
+    is_incomplete: bool,
+    /// `(index into items, score)` in display order.
+    filtered: Vec<(usize, i64)>,
+    pending_completion: Option<u64>,
+    /// `(request id, index into items)`.
+    pending_resolves: Vec<(u64, usize)>,
+    active: bool,
+}
+
+impl CompletionSession {
+    /// Create an inactive session for the document `uri`.
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            anchor: 0,
+            prefix: String::new(),
+            items: Vec::new(),
+            is_incomplete: false,
+            filtered: Vec::new(),
+            pending_completion: None,
+            pending_resolves: Vec::new(),
+            active: false,
+        }
+    }
+
+    /// Whether a completion popup is open (between [`trigger`](Self::trigger) and
+    /// [`close`](Self::close)/[`accept`](Self::accept)).
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// The text typed since the start of the completed word.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Whether the last response was marked `isIncomplete`.
+    pub fn is_incomplete(&self) -> bool {
+        self.is_incomplete
+    }
+
+    /// Id of the `textDocument/completion` request awaiting a response, if any.
+    pub fn pending_request(&self) -> Option<u64> {
+        self.pending_completion
+    }
+
+    /// Number of items matching the current prefix.
+    pub fn len(&self) -> usize {
+        self.filtered.len()
+    }
+
+    /// Returns `true` if no item matches the current prefix.
+    pub fn is_empty(&self) -> bool {
+        self.filtered.is_empty()
+    }
+
+    /// The `index`-th matching item (best match first).
+    pub fn item(&self, index: usize) -> Option<&Value> {
+        self.filtered.get(index).map(|&(i, _)| &self.items[i])
+    }
+
+    /// Matching items, best match first.
+    pub fn items(&self) -> impl Iterator<Item = &Value> + '_ {
+        self.filtered.iter().map(|&(i, _)| &self.items[i])
+    }
+
+    /// Open the popup at the primary caret and send `textDocument/completion`.
+    pub fn trigger(
+        &mut self,
+        sender: &mut impl LspRequestSender,
+        state: &EditorStateManager,
+    ) -> Result<u64, String> {
+        let editor = state.editor();
+        let caret = editor.cursor_position();
+        let caret_offset = editor
+            .line_index
+            .position_to_char_offset(caret.line, caret.column);
+        let word = completion_edit_at_caret(editor, "", CompletionAcceptMode::Insert);
+
+        self.anchor = word.start.min(caret_offset);
+        self.prefix = self.text_to_caret(state).unwrap_or_default();
+        self.items.clear();
+        self.filtered.clear();
+        self.is_incomplete = false;
+        self.pending_resolves.clear();
+        self.active = true;
+        self.request(sender, state, TRIGGER_INVOKED)
+    }
+
+    /// Close the popup and forget all items and pending requests.
+    pub fn close(&mut self) {
+        self.active = false;
+        self.prefix.clear();
+        self.items.clear();
+        self.filtered.clear();
+        self.is_incomplete = false;
+        self.pending_completion = None;
+        self.pending_resolves.clear();
+    }
+
+    /// Sync the session with the editor after an edit or caret move.
+    ///
+    /// Closes the session (returning `Ok(false)`) when the caret moved before the word start or
+    /// the typed text is no longer a single word. Otherwise re-filters the current items and,
+    /// if the last list was incomplete, sends a new request.
+    pub fn update(
+        &mut self,
+        sender: &mut impl LspRequestSender,
+        state: &EditorStateManager,
+    ) -> Result<bool, String> {
+        if !self.active {
+            return Ok(false);
+        }
+        let Some(prefix) = self.text_to_caret(state) else {
+            self.close();
+            return Ok(false);
+        };
+
+        if prefix != self.prefix {
+            self.prefix = prefix;
+            self.refilter();
+            if self.is_incomplete {
+                self.request(sender, state, TRIGGER_FOR_INCOMPLETE)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Feed a response to the session.
+    ///
+    /// Returns `true` if it answered one of this session's requests (stale completion
+    /// responses are ignored).
+    pub fn handle_response(&mut self, response: &LspResponse) -> bool {
+        if self.pending_completion == Some(response.id) {
+            self.pending_completion = None;
+            let (items, is_incomplete) = response
+                .result
+                .as_ref()
+                .map(completion_items_from_value)
+                .unwrap_or_default();
+            self.items = items;
+            self.is_incomplete = is_incomplete;
+            self.pending_resolves.clear();
+            self.refilter();
+            return true;
+        }
+
+        let Some(pos) = self
+            .pending_resolves
+            .iter()
+            .position(|(id, _)| *id == response.id)
+        else {
+            return false;
+        };
+        let (_, index) = self.pending_resolves.remove(pos);
+        if let Some(resolved) = response.result.as_ref().filter(|v| v.is_object())
+            && let Some(item) = self.items.get_mut(index)
+        {
+            *item = resolved.clone();
+        }
+        true
+    }
+
+    /// Send `completionItem/resolve` for the `index`-th matching item.
+    ///
+    /// The resolved item replaces the original once its response is passed to
+    /// [`handle_response`](Self::handle_response).
+    pub fn resolve(
+        &mut self,
+        sender: &mut impl LspRequestSender,
+        index: usize,
+    ) -> Result<u64, String> {
+        let &(item_index, _) = self
+            .filtered
+            .get(index)
+            .ok_or_else(|| format!("completion item 不存在: {}", index))?;
+        let id = sender.send_request("completionItem/resolve", self.items[item_index].clone())?;
+        self.pending_resolves.push((id, item_index));
+        Ok(id)
+    }
+
+    /// Apply the `index`-th matching item (see [`apply_completion_item`]) and close the session.
+    ///
+    /// Returns the snippet tabstops of the inserted item, if any.
+    pub fn accept(
+        &mut self,
+        state: &mut EditorStateManager,
+        index: usize,
+        mode: CompletionTextEditMode,
+    ) -> Result<Vec<SnippetTabstop>, String> {
+        let item = self
+            .item(index)
+            .cloned()
+            .ok_or_else(|| format!("completion item 不存在: {}", index))?;
+        self.close();
+        apply_completion_item(state, &item, mode)
+    }
+
+    fn request(
+        &mut self,
+        sender: &mut impl LspRequestSender,
+        state: &EditorStateManager,
+        trigger_kind: u64,
+    ) -> Result<u64, String> {
+        let editor = state.editor();
+        let caret = editor.cursor_position();
+        let line_text = editor
+            .line_index
+            .get_line_text(caret.line)
+            .unwrap_or_default();
+        let pos = LspCoordinateConverter::position_to_lsp(&line_text, caret.line, caret.column);
+        let params = json!({
+            "textDocument": { "uri": self.uri.as_str() },
+            "position": { "line": pos.line, "character": pos.character },
+            "context": { "triggerKind": trigger_kind },
+        });
+        // A newer request supersedes the pending one; its list would only flash briefly.
+        if let Some(stale) = self.pending_completion.take() {
+            sender.cancel_request(stale)?;
+        }
+        let id = sender.send_request("textDocument/completion", params)?;
+        self.pending_completion = Some(id);
+        Ok(id)
+    }
+
+    /// Text from the anchor to the primary caret, or `None` if that is not (part of) one word.
+    fn text_to_caret(&self, state: &EditorStateManager) -> Option<String> {
+        let editor = state.editor();
+        let caret = editor.cursor_position();
+        let line_index = &editor.line_index;
+        let caret_offset = line_index.position_to_char_offset(caret.line, caret.column);
+        let line_start = line_index.position_to_char_offset(caret.line, 0);
+        if caret_offset < self.anchor || self.anchor < line_start {
+            return None;
+        }
+        let text: String = line_index
+            .get_line_text(caret.line)
+            .unwrap_or_default()
+            .chars()
+            .skip(self.anchor - line_start)
+            .take(caret_offset - self.anchor)
+            .collect();
+        text.chars()
+            .all(|ch| ch.is_alphanumeric() || ch == '_')
+            .then_some(text)
+    }
+
+    fn refilter(&mut self) {
+        let items = &self.items;
+        let mut filtered: Vec<(usize, i64)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                fuzzy_match_score(&self.prefix, completion_item_filter_text(item))
+                    .map(|score| (i, score))
+            })
+            .collect();
+        filtered.sort_by(|&(a, score_a), &(b, score_b)| {
+            score_b.cmp(&score_a).then_with(|| {
+                completion_item_sort_text(&items[a]).cmp(completion_item_sort_text(&items[b]))
+            })
+        });
+        self.filtered = filtered;
+    }
 }
@@ -3,6 +3,7 @@
 //! This module intentionally avoids pulling in `lsp-types`. It parses the small subset needed
 //! to bridge common LSP "virtual text" features into `editor-core`'s decoration model.
 
+use crate::lsp_hover::HoverInfo;
 use crate::lsp_sync::{LspCoordinateConverter, LspPosition};
 use editor_core::processing::ProcessingEdit;
 use editor_core::{
@@ -228,3 +229,72 @@ pub fn lsp_code_lens_to_processing_edit(line_index: &LineIndex, result: &Value)
         decorations: lsp_code_lens_to_decorations(line_index, result),
     }
 }
+
+/// Convert a hover result into a single line of virtual text.
+///
+/// - `anchor_offset` is where the hover was requested (usually the caret); the hover's own range
+///   is preferred when the server provided one.
+/// - [`DecorationPlacement::AboveLine`] anchors the text at the start of the anchor line and uses
+///   [`DecorationKind::CodeLens`]; other placements anchor it at the end of the hovered range and
+///   use [`DecorationKind::InlayHint`], so existing renderers for those kinds can draw it.
+/// - Multi-line hover text is joined with ` ⏐ `; the full plain text is kept as the tooltip.
+///
+/// Returns `None` if the hover has no text.
+pub fn lsp_hover_to_decoration(
+    line_index: &LineIndex,
+    hover: &HoverInfo,
+    anchor_offset: usize,
+    placement: DecorationPlacement,
+) -> Option<Decoration> {
+    let plain = hover.plain_text();
+    let text = plain
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ⏐ ");
+    if text.is_empty() {
+        return None;
+    }
+
+    let (start, end) = hover.range.unwrap_or((anchor_offset, anchor_offset));
+    let (offset, kind) = match placement {
+        DecorationPlacement::AboveLine => {
+            let (line, _) = line_index.char_offset_to_position(start);
+            (
+                line_index.position_to_char_offset(line, 0),
+                DecorationKind::CodeLens,
+            )
+        }
+        DecorationPlacement::Before => (start, DecorationKind::InlayHint),
+        DecorationPlacement::After => (end, DecorationKind::InlayHint),
+    };
+
+    Some(Decoration {
+        range: DecorationRange::new(offset, offset),
+        placement,
+        kind,
+        text: Some(text),
+        styles: Vec::new(),
+        tooltip: Some(plain),
+        data_json: None,
+    })
+}
+
+/// Convert a hover result into a processing edit that replaces the `HOVER` layer.
+///
+/// `None` (no hover) clears the layer.
+pub fn lsp_hover_to_processing_edit(
+    line_index: &LineIndex,
+    hover: Option<&HoverInfo>,
+    anchor_offset: usize,
+    placement: DecorationPlacement,
+) -> ProcessingEdit {
+    ProcessingEdit::ReplaceDecorations {
+        layer: DecorationLayerId::HOVER,
+        decorations: hover
+            .and_then(|hover| lsp_hover_to_decoration(line_index, hover, anchor_offset, placement))
+            .into_iter()
+            .collect(),
+    }
+}
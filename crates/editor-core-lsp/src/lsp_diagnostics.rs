@@ -0,0 +1,183 @@
+//! Pull diagnostics (`textDocument/diagnostic`) and merging with pushed diagnostics.
+//!
+//! Servers may report diagnostics both ways: pushed (`textDocument/publishDiagnostics`) and
+//! pulled (`textDocument/diagnostic`, LSP 3.17). [`DocumentDiagnostics`] keeps the two sources
+//! apart, so a new pull report only replaces the previous pull report (and likewise for pushes),
+//! and merges them without duplicates for rendering through
+//! [`crate::lsp_diagnostics_to_processing_edits_for`].
+//!
+//! Pull requests carry the `resultId` of the previous report; a server answering
+//! `{ "kind": "unchanged" }` means the cached diagnostics are still current.
+
+use crate::editor::{LspSessionOutput, lsp_diagnostics_to_processing_edits_for};
+use crate::lsp_events::{LspDiagnostic, LspPublishDiagnosticsParams};
+use editor_core::LineIndex;
+use editor_core::processing::ProcessingEdit;
+use serde_json::Value;
+
+/// A `FullDocumentDiagnosticReport` or `UnchangedDocumentDiagnosticReport`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocumentDiagnosticReport {
+    /// The complete set of diagnostics for the document.
+    Full {
+        /// Id to send as `previousResultId` in the next request.
+        result_id: Option<String>,
+        /// Diagnostics.
+        items: Vec<LspDiagnostic>,
+    },
+    /// The diagnostics of the report with `result_id` are still current.
+    Unchanged {
+        /// Id of the (unchanged) report.
+        result_id: String,
+    },
+}
+
+impl DocumentDiagnosticReport {
+    /// Parse a document diagnostic report (`kind` is `full` or `unchanged`).
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let result_id = value
+            .get("resultId")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        match value.get("kind")?.as_str()? {
+            "full" => Some(Self::Full {
+                result_id,
+                items: value
+                    .get("items")
+                    .and_then(Value::as_array)
+                    .map(|items| items.iter().filter_map(LspDiagnostic::from_value).collect())
+                    .unwrap_or_default(),
+            }),
+            "unchanged" => Some(Self::Unchanged {
+                result_id: result_id?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The report's `resultId`, if any.
+    pub fn result_id(&self) -> Option<&str> {
+        match self {
+            Self::Full { result_id, .. } => result_id.as_deref(),
+            Self::Unchanged { result_id } => Some(result_id),
+        }
+    }
+}
+
+/// A parsed `textDocument/diagnostic` response (`RelatedFullDocumentDiagnosticReport` or
+/// `RelatedUnchangedDocumentDiagnosticReport`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentDiagnosticResponse {
+    /// Report for the requested document.
+    pub report: DocumentDiagnosticReport,
+    /// Reports for other documents affected by this one (`relatedDocuments`), by URI.
+    pub related_documents: Vec<(String, DocumentDiagnosticReport)>,
+}
+
+/// Parse a `textDocument/diagnostic` response.
+pub fn document_diagnostic_response_from_value(
+    value: &Value,
+) -> Option<DocumentDiagnosticResponse> {
+    let report = DocumentDiagnosticReport::from_value(value)?;
+    let mut related_documents: Vec<(String, DocumentDiagnosticReport)> = value
+        .get("relatedDocuments")
+        .and_then(Value::as_object)
+        .map(|related| {
+            related
+                .iter()
+                .filter_map(|(uri, report)| {
+                    Some((uri.clone(), DocumentDiagnosticReport::from_value(report)?))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    related_documents.sort_by(|a, b| a.0.cmp(&b.0));
+    Some(DocumentDiagnosticResponse {
+        report,
+        related_documents,
+    })
+}
+
+/// Pushed and pulled diagnostics of one document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentDiagnostics {
+    pushed: Vec<LspDiagnostic>,
+    pulled: Vec<LspDiagnostic>,
+    pull_result_id: Option<String>,
+}
+
+impl DocumentDiagnostics {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the pushed diagnostics (`textDocument/publishDiagnostics`).
+    pub fn set_pushed(&mut self, diagnostics: Vec<LspDiagnostic>) {
+        self.pushed = diagnostics;
+    }
+
+    /// Apply a pull report.
+    ///
+    /// A full report replaces the previous pull results; an unchanged one keeps them. Returns
+    /// `true` if the pulled diagnostics changed.
+    pub fn apply_pull_report(&mut self, report: &DocumentDiagnosticReport) -> bool {
+        self.pull_result_id = report.result_id().map(str::to_string);
+        match report {
+            DocumentDiagnosticReport::Full { items, .. } => {
+                let changed = self.pulled != *items;
+                self.pulled = items.clone();
+                changed
+            }
+            DocumentDiagnosticReport::Unchanged { .. } => false,
+        }
+    }
+
+    /// `resultId` of the last pull report (send as `previousResultId`).
+    pub fn pull_result_id(&self) -> Option<&str> {
+        self.pull_result_id.as_deref()
+    }
+
+    /// Pushed diagnostics.
+    pub fn pushed(&self) -> &[LspDiagnostic] {
+        &self.pushed
+    }
+
+    /// Pulled diagnostics.
+    pub fn pulled(&self) -> &[LspDiagnostic] {
+        &self.pulled
+    }
+
+    /// Pushed diagnostics followed by the pulled ones, skipping pulled diagnostics that are
+    /// identical to a pushed one.
+    pub fn merged(&self) -> Vec<LspDiagnostic> {
+        let mut out = self.pushed.clone();
+        for diagnostic in &self.pulled {
+            if !out.contains(diagnostic) {
+                out.push(diagnostic.clone());
+            }
+        }
+        out
+    }
+
+    /// Processing edits for the merged diagnostics of `uri`, written through `output` (see
+    /// [`lsp_diagnostics_to_processing_edits_for`]).
+    pub fn to_processing_edits(
+        &self,
+        uri: &str,
+        line_index: &LineIndex,
+        output: &LspSessionOutput,
+    ) -> Vec<ProcessingEdit> {
+        let params = LspPublishDiagnosticsParams {
+            uri: uri.to_string(),
+            diagnostics: self.merged(),
+            version: None,
+        };
+        lsp_diagnostics_to_processing_edits_for(line_index, &params, output)
+    }
+
+    /// Forget all diagnostics and the pull `resultId`.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -8,7 +8,10 @@
 //! - observe those messages as events
 //! - (optionally) defer answering server->client requests until the UI is ready
 
+use crate::lsp_client::LspTimedOutRequest;
+use crate::lsp_progress::ProgressItem;
 use crate::lsp_sync::{LspPosition, LspRange};
+use editor_core::DiagnosticTag;
 use serde_json::Value;
 
 /// LSP `MessageType` used by `window/showMessage` and `window/logMessage`.
@@ -103,12 +106,59 @@ pub struct LspDiagnostic {
     pub source: Option<String>,
     /// Diagnostic message.
     pub message: String,
+    /// Link to documentation for the code (`codeDescription.href`).
+    pub code_description_href: Option<String>,
+    /// Tags (`1` = unnecessary, `2` = deprecated; unknown tags are dropped).
+    pub tags: Vec<DiagnosticTag>,
     /// Optional related information (server-specific JSON).
     pub related_information: Option<Value>,
     /// Optional extra data (server-specific JSON).
     pub data: Option<Value>,
 }
 
+impl LspDiagnostic {
+    /// Parse an LSP `Diagnostic` object.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        Some(Self {
+            range: lsp_range_from_value(value.get("range")?)?,
+            severity: value
+                .get("severity")
+                .and_then(Value::as_u64)
+                .and_then(LspDiagnosticSeverity::from_u64),
+            code: value.get("code").cloned(),
+            source: value
+                .get("source")
+                .and_then(Value::as_str)
+                .map(|s| s.to_string()),
+            message: value
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string(),
+            code_description_href: value
+                .get("codeDescription")
+                .and_then(|description| description.get("href"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            tags: value
+                .get("tags")
+                .and_then(Value::as_array)
+                .map(|tags| {
+                    tags.iter()
+                        .filter_map(|tag| match tag.as_u64()? {
+                            1 => Some(DiagnosticTag::Unnecessary),
+                            2 => Some(DiagnosticTag::Deprecated),
+                            _ => None,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            related_information: value.get("relatedInformation").cloned(),
+            data: value.get("data").cloned(),
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 /// Parameters for `textDocument/publishDiagnostics`.
 pub struct LspPublishDiagnosticsParams {
@@ -204,39 +254,7 @@ impl LspNotification {
                 let diagnostics = params
                     .get("diagnostics")
                     .and_then(Value::as_array)
-                    .map(|arr| {
-                        arr.iter()
-                            .filter_map(|d| {
-                                let range = lsp_range_from_value(d.get("range")?)?;
-                                let severity = d
-                                    .get("severity")
-                                    .and_then(Value::as_u64)
-                                    .and_then(LspDiagnosticSeverity::from_u64);
-                                let code = d.get("code").cloned();
-                                let source = d
-                                    .get("source")
-                                    .and_then(Value::as_str)
-                                    .map(|s| s.to_string());
-                                let message = d
-                                    .get("message")
-                                    .and_then(Value::as_str)
-                                    .unwrap_or("")
-                                    .to_string();
-                                let related_information = d.get("relatedInformation").cloned();
-                                let data = d.get("data").cloned();
-
-                                Some(LspDiagnostic {
-                                    range,
-                                    severity,
-                                    code,
-                                    source,
-                                    message,
-                                    related_information,
-                                    data,
-                                })
-                            })
-                            .collect::<Vec<_>>()
-                    })
+                    .map(|arr| arr.iter().filter_map(LspDiagnostic::from_value).collect())
                     .unwrap_or_default();
 
                 Some(Self::PublishDiagnostics(LspPublishDiagnosticsParams {
@@ -259,6 +277,36 @@ pub enum LspEvent {
     DeferredRequest(LspServerRequest),
     /// A JSON-RPC response for a client-initiated request.
     Response(LspResponse),
+    /// A work-done progress operation began, reported or ended (see
+    /// [`crate::LspSession::active_progress`]).
+    ///
+    /// Emitted after the [`LspNotification::Progress`] it was derived from.
+    ProgressChanged(ProgressItem),
+    /// A client-initiated request passed its deadline (see [`crate::LspRequestTimeouts`]) and
+    /// was canceled; no [`LspEvent::Response`] follows for it.
+    RequestTimedOut(LspTimedOutRequest),
+    /// The server was restarted (see [`crate::LspSession::restart`]).
+    ServerRestarted(LspServerRestart),
+    /// The server sent `workspace/inlayHint/refresh` (already answered); see
+    /// [`crate::InlayHintManager`].
+    InlayHintRefresh,
+    /// The server registered or unregistered file watchers (see
+    /// [`crate::LspSession::watched_globs`]).
+    FileWatchersChanged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Details of a server restart.
+pub struct LspServerRestart {
+    /// Automatic restart number (1-based), or `0` for [`crate::LspSession::restart`].
+    pub attempt: u32,
+    /// Why the server was restarted.
+    pub reason: String,
+    /// Ids of requests that were waiting for a response; they will never get one.
+    pub abandoned_requests: Vec<u64>,
+    /// URIs of extra documents that were open on the old server. The host should reopen them
+    /// with [`crate::LspSession::open_document`].
+    pub closed_documents: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
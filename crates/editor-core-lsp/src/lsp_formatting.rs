@@ -0,0 +1,143 @@
+//! Formatting helpers (`textDocument/formatting`, `rangeFormatting`, `onTypeFormatting`).
+//!
+//! All three requests answer with `TextEdit[]`. [`apply_formatting_edits`] applies such a list as
+//! a single [`EditCommand::ApplyTextEdits`] (one undo step) and puts the cursor back where it was.
+//! Servers are not always careful about edit order or overlap, so the list is normalized first
+//! (see [`formatting_edit_specs`]).
+
+use crate::lsp_text_edits::{LspTextEdit, char_offsets_for_lsp_range};
+use editor_core::{
+    Command, CursorCommand, EditCommand, EditorStateManager, LineIndex, TabKeyBehavior,
+    TextEditSpec,
+};
+use serde_json::{Value, json};
+
+/// LSP `FormattingOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LspFormattingOptions {
+    /// Size of a tab in spaces.
+    pub tab_size: usize,
+    /// Prefer spaces over tabs.
+    pub insert_spaces: bool,
+}
+
+impl LspFormattingOptions {
+    /// Create formatting options.
+    pub fn new(tab_size: usize, insert_spaces: bool) -> Self {
+        Self {
+            tab_size,
+            insert_spaces,
+        }
+    }
+
+    /// Options matching the editor's current tab width and tab key behavior.
+    pub fn from_state_manager(state_manager: &EditorStateManager) -> Self {
+        Self::new(
+            state_manager.editor().layout_engine.tab_width(),
+            state_manager.tab_key_behavior() == TabKeyBehavior::Spaces,
+        )
+    }
+
+    /// The options as an LSP `FormattingOptions` object.
+    pub fn to_value(&self) -> Value {
+        json!({
+            "tabSize": self.tab_size,
+            "insertSpaces": self.insert_spaces,
+        })
+    }
+}
+
+/// Convert formatting `TextEdit`s into non-overlapping [`TextEditSpec`]s (char offsets).
+///
+/// Edits are sorted by position (keeping server order for edits at the same position, as LSP
+/// requires for inserts). Edits that start at the same offset, or overlap the previous one, are
+/// merged into a single replacement whose text is the concatenation in server order.
+pub fn formatting_edit_specs(line_index: &LineIndex, edits: &[LspTextEdit]) -> Vec<TextEditSpec> {
+    let mut resolved: Vec<TextEditSpec> = edits
+        .iter()
+        .map(|edit| {
+            let (start, end) = char_offsets_for_lsp_range(line_index, &edit.range);
+            TextEditSpec {
+                start,
+                end,
+                text: edit.new_text.clone(),
+            }
+        })
+        .collect();
+    resolved.sort_by_key(|edit| (edit.start, edit.end));
+
+    let mut out: Vec<TextEditSpec> = Vec::with_capacity(resolved.len());
+    for edit in resolved {
+        match out.last_mut() {
+            Some(prev) if edit.start == prev.start || edit.start < prev.end => {
+                prev.end = prev.end.max(edit.end);
+                prev.text.push_str(&edit.text);
+            }
+            _ => out.push(edit),
+        }
+    }
+    out
+}
+
+/// Convert a formatting response (`TextEdit[] | null`) into sorted, non-overlapping
+/// [`TextEditSpec`]s, ready for a single [`EditCommand::ApplyTextEdits`].
+///
+/// Positions past the end of a line or of the document are clamped, so an insertion "after the
+/// last line" (as some servers send for a final newline) lands at EOF. Unlike
+/// [`crate::text_edits_from_value`], a malformed response is an error: applying only part of a
+/// reformat would leave the document half-formatted.
+pub fn format_edits_to_text_edit_specs(
+    value: &Value,
+    line_index: &LineIndex,
+) -> Result<Vec<TextEditSpec>, String> {
+    if value.is_null() {
+        return Ok(Vec::new());
+    }
+    let items = value
+        .as_array()
+        .ok_or_else(|| "Invalid formatting response: expected TextEdit[]".to_string())?;
+    let edits = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            LspTextEdit::from_value(item)
+                .ok_or_else(|| format!("Invalid formatting response: bad TextEdit at {}", index))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(formatting_edit_specs(line_index, &edits))
+}
+
+/// Apply formatting `TextEdit`s to an [`EditorStateManager`] as one undo step.
+///
+/// The cursor keeps its logical line/column (clamped to the formatted text), and any selection is
+/// cleared. Returns the changed `(start, end)` ranges in *pre-edit* char offsets.
+pub fn apply_formatting_edits(
+    state_manager: &mut EditorStateManager,
+    edits: &[LspTextEdit],
+) -> Result<Vec<(usize, usize)>, String> {
+    let editor = state_manager.editor();
+    let specs = formatting_edit_specs(&editor.line_index, edits);
+    if specs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let cursor = editor.cursor_position();
+    let changed = specs.iter().map(|edit| (edit.start, edit.end)).collect();
+
+    state_manager
+        .execute(Command::Edit(EditCommand::ApplyTextEdits { edits: specs }))
+        .map_err(|err| format!("Failed to apply formatting edits: {}", err))?;
+
+    let line_index = &state_manager.editor().line_index;
+    let line = cursor.line.min(line_index.line_count().saturating_sub(1));
+    let line_len = line_index
+        .position_to_char_offset(line, usize::MAX)
+        .saturating_sub(line_index.position_to_char_offset(line, 0));
+    state_manager
+        .execute(Command::Cursor(CursorCommand::MoveTo {
+            line,
+            column: cursor.column.min(line_len),
+        }))
+        .map_err(|err| format!("Failed to restore cursor after formatting: {}", err))?;
+
+    Ok(changed)
+}
@@ -2,7 +2,10 @@
 //!
 //! This module intentionally avoids pulling in `lsp-types`. It parses the small subset needed
 //! to bridge `textDocument/documentHighlight` results into a dedicated `StyleLayerId`.
+//! [`CursorHighlightManager`] requests them for the symbol under the cursor.
 
+use crate::lsp_client::LspRequestSender;
+use crate::lsp_events::{LspEvent, LspResponse};
 use crate::lsp_sync::{LspCoordinateConverter, LspPosition, LspRange};
 use editor_core::intervals::{Interval, StyleId};
 use editor_core::processing::ProcessingEdit;
@@ -10,7 +13,8 @@ use editor_core::{
     DOCUMENT_HIGHLIGHT_READ_STYLE_ID, DOCUMENT_HIGHLIGHT_TEXT_STYLE_ID,
     DOCUMENT_HIGHLIGHT_WRITE_STYLE_ID, LineIndex, StyleLayerId,
 };
-use serde_json::Value;
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
 
 fn parse_lsp_position(value: &Value) -> Option<LspPosition> {
     Some(LspPosition {
@@ -92,3 +96,228 @@ pub fn lsp_document_highlights_to_processing_edit(
         intervals: lsp_document_highlights_to_intervals(line_index, result),
     }
 }
+
+/// Client-side state of the highlights of the symbol under the cursor.
+///
+/// Like [`crate::InlayHintManager`], the manager is transport-agnostic: requests go through an
+/// [`LspRequestSender`] and the host feeds events back. It takes the current time as a
+/// parameter, so hosts (and tests) control the debounce clock. Typical flow:
+///
+/// 1. [`notify_cursor_moved`](Self::notify_cursor_moved) when the cursor moves and
+///    [`document_changed`](Self::document_changed) on every edit.
+/// 2. Once per frame/poll, [`handle_event`](Self::handle_event) for every [`LspEvent`] the host
+///    receives, then [`update`](Self::update): requests `textDocument/documentHighlight` once the
+///    cursor has rested for the debounce delay.
+/// 3. Apply [`take_processing_edit`](Self::take_processing_edit) when it returns an edit.
+///
+/// Highlights are cleared when the cursor leaves them and on edits; moving within them keeps
+/// them without asking the server again.
+#[derive(Debug, Clone)]
+pub struct CursorHighlightManager {
+    uri: String,
+    debounce: Duration,
+    /// Last offset passed to `notify_cursor_moved`.
+    cursor: Option<usize>,
+    /// Bumped on every cursor move and edit; responses for older generations are stale.
+    generation: u64,
+    /// When to request highlights for `cursor`.
+    due: Option<Instant>,
+    /// Request on the next `update`, ignoring `due` (after a server restart).
+    request_due: bool,
+    /// `(request id, generation)` of the request awaiting a response.
+    pending: Option<(u64, u64)>,
+    /// Result of the last response, until `take_processing_edit` converts it.
+    result: Option<Value>,
+    /// Char ranges currently highlighted.
+    ranges: Vec<(usize, usize)>,
+    /// The highlights must be cleared by the next `take_processing_edit`.
+    clear: bool,
+}
+
+impl CursorHighlightManager {
+    /// Create a manager for the document `uri` with a 200ms debounce.
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            debounce: Duration::from_millis(200),
+            cursor: None,
+            generation: 0,
+            due: None,
+            request_due: false,
+            pending: None,
+            result: None,
+            ranges: Vec::new(),
+            clear: false,
+        }
+    }
+
+    /// Set how long the cursor must rest before highlights are requested.
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// The debounce delay.
+    pub fn debounce(&self) -> Duration {
+        self.debounce
+    }
+
+    /// Switch to another document: the highlights of the previous one are cleared.
+    pub fn set_uri(&mut self, uri: impl Into<String>) {
+        self.uri = uri.into();
+        self.document_changed();
+    }
+
+    /// Char ranges currently highlighted.
+    pub fn ranges(&self) -> &[(usize, usize)] {
+        &self.ranges
+    }
+
+    /// Whether highlights are shown (or their clearing edit has not been taken yet).
+    pub fn has_highlights(&self) -> bool {
+        self.clear || !self.ranges.is_empty()
+    }
+
+    /// Id of the `textDocument/documentHighlight` request awaiting a response, if any.
+    pub fn pending_request(&self) -> Option<u64> {
+        self.pending.map(|(id, _)| id)
+    }
+
+    /// Report the cursor position (char offset) at time `now`.
+    ///
+    /// Moving within the current highlights keeps them; moving elsewhere clears them and
+    /// requests new ones once the cursor has rested for the debounce delay.
+    pub fn notify_cursor_moved(&mut self, offset: usize, now: Instant) {
+        if self.cursor == Some(offset) {
+            return;
+        }
+        self.cursor = Some(offset);
+        // All ranges belong to the same symbol: no need to ask again.
+        if self
+            .ranges
+            .iter()
+            .any(|(start, end)| *start <= offset && offset <= *end)
+        {
+            return;
+        }
+        self.invalidate();
+        self.due = Some(now + self.debounce);
+    }
+
+    /// Forget the highlights and the cursor after an edit; responses to requests sent before it
+    /// are dropped. Report the cursor again if the edit moved it.
+    pub fn document_changed(&mut self) {
+        self.invalidate();
+        self.due = None;
+        self.request_due = false;
+        self.cursor = None;
+    }
+
+    /// Request highlights if the cursor has rested for the debounce delay at `now`.
+    ///
+    /// A request still in flight is superseded (canceled). Returns the id of a request sent by
+    /// this call.
+    pub fn update(
+        &mut self,
+        sender: &mut impl LspRequestSender,
+        line_index: &LineIndex,
+        now: Instant,
+    ) -> Result<Option<u64>, String> {
+        let Some(offset) = self.cursor else {
+            return Ok(None);
+        };
+        let due = self.request_due || self.due.is_some_and(|due| now >= due);
+        if !due {
+            return Ok(None);
+        }
+
+        let (line, column) = line_index.char_offset_to_position(offset);
+        let line_text = line_index.get_line_text(line).unwrap_or_default();
+        let pos = LspCoordinateConverter::position_to_lsp(&line_text, line, column);
+        let params = json!({
+            "textDocument": { "uri": self.uri.as_str() },
+            "position": { "line": pos.line, "character": pos.character },
+        });
+        // Only the latest request matters.
+        if let Some((stale, _)) = self.pending.take() {
+            sender.cancel_request(stale)?;
+        }
+        let id = sender.send_request("textDocument/documentHighlight", params)?;
+        self.pending = Some((id, self.generation));
+        self.due = None;
+        self.request_due = false;
+        Ok(Some(id))
+    }
+
+    /// Feed an event to the manager: responses to its requests, timeouts and server restarts.
+    ///
+    /// A timed-out request is forgotten; after a restart the highlights for the current cursor
+    /// are requested again on the next [`update`](Self::update). Returns `true` if the event
+    /// concerned cursor highlights.
+    pub fn handle_event(&mut self, event: &LspEvent) -> bool {
+        match event {
+            LspEvent::Response(response) => self.handle_response(response),
+            LspEvent::RequestTimedOut(request) => {
+                let ours = self.pending_request() == Some(request.id);
+                if ours {
+                    self.pending = None;
+                }
+                ours
+            }
+            LspEvent::ServerRestarted(_) => {
+                self.pending = None;
+                self.generation += 1;
+                self.request_due = self.cursor.is_some();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Feed a response to the manager.
+    ///
+    /// Returns `true` if it answered its request. Responses to requests sent before the last
+    /// cursor move or edit are dropped.
+    pub fn handle_response(&mut self, response: &LspResponse) -> bool {
+        let Some((id, generation)) = self.pending else {
+            return false;
+        };
+        if id != response.id {
+            return false;
+        }
+        self.pending = None;
+        // The cursor moved (or the text changed) since the request.
+        if generation == self.generation && response.error.is_none() {
+            self.result = Some(response.result.clone().unwrap_or(Value::Null));
+        }
+        true
+    }
+
+    /// The processing edit for the `DOCUMENT_HIGHLIGHTS` layer, if the highlights changed since
+    /// the last call: the new highlights, or clearing the stale ones.
+    ///
+    /// `line_index` must describe the current document.
+    pub fn take_processing_edit(&mut self, line_index: &LineIndex) -> Option<ProcessingEdit> {
+        if let Some(result) = self.result.take() {
+            let intervals = lsp_document_highlights_to_intervals(line_index, &result);
+            self.ranges = intervals.iter().map(|i| (i.start, i.end)).collect();
+            self.clear = false;
+            return Some(ProcessingEdit::ReplaceStyleLayer {
+                layer: StyleLayerId::DOCUMENT_HIGHLIGHTS,
+                intervals,
+            });
+        }
+        std::mem::take(&mut self.clear).then_some(ProcessingEdit::ClearStyleLayer {
+            layer: StyleLayerId::DOCUMENT_HIGHLIGHTS,
+        })
+    }
+
+    /// Drop pending responses and the shown highlights.
+    fn invalidate(&mut self) {
+        self.generation += 1;
+        self.result = None;
+        if !self.ranges.is_empty() {
+            self.ranges.clear();
+            self.clear = true;
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Helpers for normalizing LSP hover results (`textDocument/hover`).
+//!
+//! `Hover.contents` comes in several shapes depending on the server and protocol version:
+//! - `MarkupContent`: `{ kind: "markdown" | "plaintext", value }`
+//! - `MarkedString`: a markdown string, or the deprecated `{ language, value }` code block
+//! - `MarkedString[]`
+//!
+//! This module converts all of them into a flat list of [`HoverBlock`]s, and provides
+//! [`markdown_to_plain_text`] for hosts that cannot render markdown.
+
+use crate::lsp_locations::range_from_value;
+use crate::lsp_text_edits::char_offsets_for_lsp_range;
+use editor_core::LineIndex;
+use serde_json::Value;
+
+/// One block of hover content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HoverBlock {
+    /// Plain text.
+    PlainText(String),
+    /// Markdown source.
+    Markdown(String),
+    /// A code block (from a `{ language, value }` marked string).
+    Code {
+        /// Language identifier (e.g. `rust`).
+        language: String,
+        /// Code text.
+        value: String,
+    },
+}
+
+impl HoverBlock {
+    /// The block as plain text (markdown is flattened, code is kept verbatim).
+    pub fn plain_text(&self) -> String {
+        match self {
+            HoverBlock::PlainText(text) => text.clone(),
+            HoverBlock::Markdown(text) => markdown_to_plain_text(text),
+            HoverBlock::Code { value, .. } => value.clone(),
+        }
+    }
+}
+
+/// A normalized hover result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoverInfo {
+    /// Content blocks, in server order (empty blocks are dropped).
+    pub contents: Vec<HoverBlock>,
+    /// Hovered range as `(start, end)` char offsets, if the server provided one.
+    pub range: Option<(usize, usize)>,
+}
+
+impl HoverInfo {
+    /// All blocks as plain text, separated by blank lines.
+    pub fn plain_text(&self) -> String {
+        self.contents
+            .iter()
+            .map(HoverBlock::plain_text)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+pub(crate) fn hover_block_from_value(value: &Value) -> Option<HoverBlock> {
+    let block = if let Some(text) = value.as_str() {
+        // MarkedString (string form) is markdown.
+        HoverBlock::Markdown(text.to_string())
+    } else if let Some(kind) = value.get("kind").and_then(Value::as_str) {
+        let text = value.get("value").and_then(Value::as_str)?.to_string();
+        match kind {
+            "markdown" => HoverBlock::Markdown(text),
+            _ => HoverBlock::PlainText(text),
+        }
+    } else {
+        // Deprecated MarkedString: { language, value }
+        HoverBlock::Code {
+            language: value
+                .get("language")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string(),
+            value: value.get("value").and_then(Value::as_str)?.to_string(),
+        }
+    };
+
+    let empty = match &block {
+        HoverBlock::PlainText(text) | HoverBlock::Markdown(text) => text.trim().is_empty(),
+        HoverBlock::Code { value, .. } => value.trim().is_empty(),
+    };
+    (!empty).then_some(block)
+}
+
+/// Normalize a `textDocument/hover` result.
+///
+/// Returns `None` for a `null` result or when the hover has no non-empty content. The optional
+/// `range` is converted from UTF-16 positions to char offsets using `line_index`.
+pub fn hover_from_value(line_index: &LineIndex, value: &Value) -> Option<HoverInfo> {
+    let contents = value.get("contents")?;
+    let contents: Vec<HoverBlock> = match contents.as_array() {
+        Some(items) => items.iter().filter_map(hover_block_from_value).collect(),
+        None => hover_block_from_value(contents).into_iter().collect(),
+    };
+    if contents.is_empty() {
+        return None;
+    }
+
+    let range = value
+        .get("range")
+        .and_then(range_from_value)
+        .map(|range| char_offsets_for_lsp_range(line_index, &range));
+
+    Some(HoverInfo { contents, range })
+}
+
+/// Flatten markdown into plain text.
+///
+/// This is intentionally lossy and small: code fences, heading/quote/list markers, emphasis,
+/// inline code backticks, link/image syntax and backslash escapes are removed, while the text
+/// itself (including code block contents) is kept. Runs of blank lines collapse into one.
+pub fn markdown_to_plain_text(markdown: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_fence = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            lines.push(line.to_string());
+            continue;
+        }
+        if is_thematic_break(trimmed) {
+            lines.push(String::new());
+            continue;
+        }
+
+        let mut rest = trimmed;
+        while let Some(stripped) = rest.strip_prefix('>') {
+            rest = stripped.trim_start();
+        }
+        let heading = rest.trim_start_matches('#');
+        if heading.len() < rest.len() && (heading.is_empty() || heading.starts_with(' ')) {
+            rest = heading.trim_start();
+        }
+        for marker in ["- ", "* ", "+ "] {
+            if let Some(stripped) = rest.strip_prefix(marker) {
+                rest = stripped;
+                break;
+            }
+        }
+
+        lines.push(strip_inline_markdown(rest).trim_end().to_string());
+    }
+
+    let mut out = String::new();
+    let mut blank_run = true;
+    for line in lines {
+        let blank = line.trim().is_empty();
+        if blank && blank_run {
+            continue;
+        }
+        blank_run = blank;
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&line);
+    }
+    out.trim_end().to_string()
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let line = line.trim_end();
+    ['-', '*', '_']
+        .iter()
+        .any(|&marker| line.len() >= 3 && line.chars().all(|ch| ch == marker || ch == ' '))
+}
+
+fn strip_inline_markdown(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        match ch {
+            '\\' if chars.get(i + 1).is_some_and(|c| c.is_ascii_punctuation()) => {
+                out.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => i += 1,
+            '*' | '_' if chars.get(i + 1) == Some(&ch) => i += 2,
+            // Single `*` / `_` only count as emphasis at word edges (keeps `snake_case`, `a * b`).
+            '*' | '_' if is_emphasis_edge(&chars, i) => i += 1,
+            '!' if chars.get(i + 1) == Some(&'[') && link_end(&chars, i + 1).is_some() => i += 1,
+            '[' => match link_end(&chars, i) {
+                Some((text_end, end)) => {
+                    out.push_str(&strip_inline_markdown(
+                        &chars[i + 1..text_end].iter().collect::<String>(),
+                    ));
+                    i = end;
+                }
+                None => {
+                    out.push(ch);
+                    i += 1;
+                }
+            },
+            _ => {
+                out.push(ch);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn is_emphasis_edge(chars: &[char], i: usize) -> bool {
+    let flank = |c: Option<&char>| c.is_none_or(|c| !c.is_alphanumeric());
+    let before = i.checked_sub(1).and_then(|j| chars.get(j));
+    flank(before) != flank(chars.get(i + 1))
+}
+
+/// For `[text](target)` starting at `start`, return `(index of ']', index after ')')`.
+fn link_end(chars: &[char], start: usize) -> Option<(usize, usize)> {
+    let text_end = start + chars[start..].iter().position(|&c| c == ']')?;
+    if chars.get(text_end + 1) != Some(&'(') {
+        return None;
+    }
+    let target_end = text_end + 1 + chars[text_end + 1..].iter().position(|&c| c == ')')?;
+    Some((text_end, target_end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_plain_text() {
+        let markdown = "# Title\n\n```rust\nfn snake_case() -> u8\n```\n\n---\n\n\
+                        Returns **bold** and *em*, see [`Vec`](https://doc.rust-lang.org) \\*.";
+        assert_eq!(
+            markdown_to_plain_text(markdown),
+            "Title\n\nfn snake_case() -> u8\n\nReturns bold and em, see Vec *."
+        );
+        assert_eq!(markdown_to_plain_text("> - a * b"), "a * b");
+    }
+}
@@ -0,0 +1,352 @@
+//! Inlay hint requests (`textDocument/inlayHint`, `inlayHint/resolve`).
+//!
+//! [`InlayHintManager`] decides when to ask the server for hints: for the visible lines, again
+//! after edits (debounced) and whenever the server sends `workspace/inlayHint/refresh`. Results
+//! are turned into decorations with [`lsp_inlay_hints_to_processing_edit`]; each decoration keeps
+//! the raw hint in `data_json`, so [`inlay_hint_label_parts`] can recover clickable label parts.
+
+use crate::lsp_client::LspRequestSender;
+use crate::lsp_decorations::lsp_inlay_hints_to_processing_edit;
+use crate::lsp_events::{LspEvent, LspResponse};
+use crate::lsp_locations::{LspLocation, location_from_value};
+use crate::lsp_sync::LspCoordinateConverter;
+use editor_core::LineIndex;
+use editor_core::processing::ProcessingEdit;
+use serde_json::{Value, json};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+const REFRESH_METHOD: &str = "workspace/inlayHint/refresh";
+
+/// One part of an `InlayHintLabelPart[]` label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlayHintLabelPart {
+    /// Text of the part.
+    pub value: String,
+    /// Tooltip (plain text, or the `value` of a `MarkupContent`).
+    pub tooltip: Option<String>,
+    /// Go-to target of the part (e.g. the definition of a type shown in the hint).
+    pub location: Option<LspLocation>,
+}
+
+/// The label parts of an `InlayHint`; a plain string label is a single part.
+pub fn inlay_hint_label_parts(hint: &Value) -> Vec<InlayHintLabelPart> {
+    match hint.get("label") {
+        Some(Value::String(label)) => vec![InlayHintLabelPart {
+            value: label.clone(),
+            tooltip: None,
+            location: None,
+        }],
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .map(|part| InlayHintLabelPart {
+                value: part
+                    .get("value")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                tooltip: part.get("tooltip").and_then(|tooltip| {
+                    tooltip
+                        .as_str()
+                        .or_else(|| tooltip.get("value").and_then(Value::as_str))
+                        .map(str::to_string)
+                }),
+                location: part.get("location").and_then(location_from_value),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Client-side state of the inlay hints of one document.
+///
+/// Like [`crate::CompletionSession`], the manager is transport-agnostic: requests go through an
+/// [`LspRequestSender`] and the host feeds events back. Typical flow, once per frame/poll:
+///
+/// 1. [`handle_event`](Self::handle_event) for every [`LspEvent`] the host receives (responses
+///    and `workspace/inlayHint/refresh`).
+/// 2. [`update`](Self::update) with the visible lines and the document version: requests hints
+///    when the range or the version changed (after the debounce delay) or a refresh is due.
+/// 3. Apply [`take_processing_edit`](Self::take_processing_edit) when it returns an edit.
+///
+/// An edit invalidates the cached hints at once ([`hints`](Self::hints) becomes empty and
+/// responses for older versions are dropped), but no clearing edit is produced: the editor keeps
+/// showing the old decorations, which move with the text, until the new hints replace them.
+#[derive(Debug, Clone)]
+pub struct InlayHintManager {
+    uri: String,
+    debounce: Duration,
+    hints: Vec<Value>,
+    /// Document version and line range of `hints`.
+    hints_for: Option<(i32, Range<usize>)>,
+    /// `(request id, version, lines)` of the request awaiting a response.
+    pending: Option<(u64, i32, Range<usize>)>,
+    /// `(request id, index into hints)`.
+    pending_resolves: Vec<(u64, usize)>,
+    /// Latest document version seen by `update` and when it was first seen.
+    version_seen: Option<(i32, Instant)>,
+    refresh_due: bool,
+    edit_due: bool,
+    /// The hints changed since the last [`InlayHintManager::take_processing_edit`].
+    changed: bool,
+}
+
+impl InlayHintManager {
+    /// Create a manager for the document `uri` with a 200ms edit debounce.
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            debounce: Duration::from_millis(200),
+            hints: Vec::new(),
+            hints_for: None,
+            pending: None,
+            pending_resolves: Vec::new(),
+            version_seen: None,
+            refresh_due: false,
+            edit_due: false,
+            changed: false,
+        }
+    }
+
+    /// Set how long to wait after an edit before re-requesting hints.
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// The edit debounce delay.
+    pub fn debounce(&self) -> Duration {
+        self.debounce
+    }
+
+    /// Hints of the last response (raw `InlayHint` objects), empty once the document changed.
+    pub fn hints(&self) -> &[Value] {
+        &self.hints
+    }
+
+    /// Document version and line range the current hints were requested for.
+    pub fn hints_range(&self) -> Option<(i32, Range<usize>)> {
+        self.hints_for.clone()
+    }
+
+    /// Id of the `textDocument/inlayHint` request awaiting a response, if any.
+    pub fn pending_request(&self) -> Option<u64> {
+        self.pending.as_ref().map(|(id, _, _)| *id)
+    }
+
+    /// Re-request hints on the next [`update`](Self::update), ignoring the debounce (what
+    /// `workspace/inlayHint/refresh` does).
+    pub fn request_refresh(&mut self) {
+        self.refresh_due = true;
+    }
+
+    /// Request hints for `visible_lines` (logical lines, end exclusive) if needed.
+    ///
+    /// `version` is the LSP document version (e.g. [`crate::LspSession::document`]`.version`);
+    /// a new version drops the cached hints and schedules a request once `debounce` has passed
+    /// since the version was first seen. Returns the id of a request sent by this call.
+    pub fn update(
+        &mut self,
+        sender: &mut impl LspRequestSender,
+        line_index: &LineIndex,
+        version: i32,
+        visible_lines: Range<usize>,
+    ) -> Result<Option<u64>, String> {
+        let now = Instant::now();
+        match self.version_seen {
+            Some((seen, _)) if seen == version => {}
+            Some(_) => {
+                self.version_seen = Some((version, now));
+                self.hints.clear();
+                self.pending_resolves.clear();
+                self.edit_due = true;
+            }
+            None => self.version_seen = Some((version, now)),
+        }
+
+        let requested = self
+            .pending
+            .as_ref()
+            .map(|(_, pending_version, lines)| (*pending_version, lines.clone()))
+            .or_else(|| self.hints_for.clone());
+        let up_to_date = requested
+            .as_ref()
+            .is_some_and(|(requested_version, lines)| {
+                *requested_version == version
+                    && lines.start <= visible_lines.start
+                    && visible_lines.end <= lines.end
+            });
+        if up_to_date && !self.refresh_due {
+            return Ok(None);
+        }
+        if self.edit_due
+            && !self.refresh_due
+            && self
+                .version_seen
+                .is_some_and(|(_, seen_at)| now.duration_since(seen_at) < self.debounce)
+        {
+            return Ok(None);
+        }
+
+        let params = json!({
+            "textDocument": { "uri": self.uri.as_str() },
+            "range": lsp_line_range(line_index, &visible_lines),
+        });
+        // A newer request supersedes the pending one.
+        if let Some((stale, _, _)) = self.pending.take() {
+            sender.cancel_request(stale)?;
+        }
+        let id = sender.send_request("textDocument/inlayHint", params)?;
+        self.pending = Some((id, version, visible_lines));
+        self.refresh_due = false;
+        self.edit_due = false;
+        Ok(Some(id))
+    }
+
+    /// Feed an event to the manager: responses to its requests, and
+    /// `workspace/inlayHint/refresh` (as [`LspEvent::InlayHintRefresh`] or a deferred request,
+    /// which the host still has to answer).
+    ///
+    /// A timeout of one of its requests ([`LspEvent::RequestTimedOut`]) or a server restart
+    /// drops the requests in flight and re-requests hints on the next [`update`](Self::update).
+    ///
+    /// Returns `true` if the event concerned inlay hints.
+    pub fn handle_event(&mut self, event: &LspEvent) -> bool {
+        match event {
+            LspEvent::Response(response) => self.handle_response(response),
+            LspEvent::InlayHintRefresh => {
+                self.refresh_due = true;
+                true
+            }
+            LspEvent::DeferredRequest(request) if request.method == REFRESH_METHOD => {
+                self.refresh_due = true;
+                true
+            }
+            LspEvent::RequestTimedOut(request) => {
+                let ours = self
+                    .pending
+                    .as_ref()
+                    .is_some_and(|(id, _, _)| *id == request.id)
+                    || self
+                        .pending_resolves
+                        .iter()
+                        .any(|(id, _)| *id == request.id);
+                if ours {
+                    self.abandon_requests();
+                }
+                ours
+            }
+            LspEvent::ServerRestarted(_) => {
+                self.abandon_requests();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forget requests that will never be answered and ask again on the next
+    /// [`update`](Self::update).
+    fn abandon_requests(&mut self) {
+        self.pending = None;
+        self.pending_resolves.clear();
+        self.refresh_due = true;
+    }
+
+    /// Feed a response to the manager.
+    ///
+    /// Returns `true` if it answered one of its requests. Responses for a document version older
+    /// than the latest one passed to [`update`](Self::update) are dropped.
+    pub fn handle_response(&mut self, response: &LspResponse) -> bool {
+        if let Some((id, version, lines)) = self.pending.take() {
+            if id == response.id {
+                let current = self.version_seen.is_none_or(|(seen, _)| seen == version);
+                if current && response.error.is_none() {
+                    self.hints = response
+                        .result
+                        .as_ref()
+                        .and_then(Value::as_array)
+                        .cloned()
+                        .unwrap_or_default();
+                    self.hints_for = Some((version, lines));
+                    self.pending_resolves.clear();
+                    self.changed = true;
+                }
+                return true;
+            }
+            self.pending = Some((id, version, lines));
+        }
+
+        let Some(pos) = self
+            .pending_resolves
+            .iter()
+            .position(|(id, _)| *id == response.id)
+        else {
+            return false;
+        };
+        let (_, index) = self.pending_resolves.remove(pos);
+        if let Some(resolved) = response.result.as_ref().filter(|v| v.is_object())
+            && let Some(hint) = self.hints.get_mut(index)
+        {
+            *hint = resolved.clone();
+            self.changed = true;
+        }
+        true
+    }
+
+    /// Send `inlayHint/resolve` for the `index`-th hint.
+    ///
+    /// The resolved hint replaces the original once its response is passed to
+    /// [`handle_event`](Self::handle_event).
+    pub fn resolve_hint(
+        &mut self,
+        sender: &mut impl LspRequestSender,
+        index: usize,
+    ) -> Result<u64, String> {
+        let hint = self
+            .hints
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format!("inlay hint 不存在: {}", index))?;
+        let id = sender.send_request("inlayHint/resolve", hint)?;
+        self.pending_resolves.push((id, index));
+        Ok(id)
+    }
+
+    /// Label parts of the `index`-th hint (see [`inlay_hint_label_parts`]).
+    pub fn label_parts(&self, index: usize) -> Vec<InlayHintLabelPart> {
+        self.hints
+            .get(index)
+            .map(inlay_hint_label_parts)
+            .unwrap_or_default()
+    }
+
+    /// The processing edit replacing the `INLAY_HINTS` decorations, if new or resolved hints
+    /// arrived since the last call.
+    ///
+    /// `line_index` must describe the current document.
+    pub fn take_processing_edit(&mut self, line_index: &LineIndex) -> Option<ProcessingEdit> {
+        if !std::mem::take(&mut self.changed) {
+            return None;
+        }
+        Some(lsp_inlay_hints_to_processing_edit(
+            line_index,
+            &Value::Array(self.hints.clone()),
+        ))
+    }
+}
+
+/// LSP range covering the logical lines `lines` (end exclusive, clamped to the document).
+fn lsp_line_range(line_index: &LineIndex, lines: &Range<usize>) -> Value {
+    let line_count = line_index.line_count().max(1);
+    let start = lines.start.min(line_count - 1);
+    let end = if lines.end < line_count {
+        json!({ "line": lines.end, "character": 0 })
+    } else {
+        let last = line_count - 1;
+        let text = line_index.get_line_text(last).unwrap_or_default();
+        json!({
+            "line": last,
+            "character": LspCoordinateConverter::utf8_to_utf16_len(&text),
+        })
+    };
+    json!({ "start": { "line": start, "character": 0 }, "end": end })
+}
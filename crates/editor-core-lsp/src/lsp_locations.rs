@@ -10,7 +10,10 @@
 //! unified list of `(uri, range)` pairs.
 
 use crate::lsp_sync::{LspPosition, LspRange};
+use crate::lsp_uri::file_uri_to_path;
+use editor_core::{SymbolLocation, Utf16Position, Utf16Range};
 use serde_json::Value;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 /// A normalized LSP location (URI + range).
@@ -19,6 +22,31 @@ pub struct LspLocation {
     pub uri: String,
     /// Target range within the document.
     pub range: LspRange,
+    /// Local filesystem path for `file://` URIs.
+    pub path: Option<PathBuf>,
+    /// Target range as `(start, end)` char offsets, when it could be resolved (i.e. the target
+    /// is a document whose text is known, such as the session's current document).
+    pub char_range: Option<(usize, usize)>,
+}
+
+impl LspLocation {
+    fn new(uri: &str, range: LspRange) -> Self {
+        Self {
+            uri: uri.to_string(),
+            range,
+            path: file_uri_to_path(uri),
+            char_range: None,
+        }
+    }
+
+    /// Convert into an `editor-core` location, e.g. for [`editor_core::Workspace::open_location`].
+    pub fn symbol_location(&self) -> SymbolLocation {
+        let position = |pos: LspPosition| Utf16Position::new(pos.line, pos.character);
+        SymbolLocation {
+            uri: self.uri.clone(),
+            range: Utf16Range::new(position(self.range.start), position(self.range.end)),
+        }
+    }
 }
 
 fn position_from_value(value: &Value) -> Option<LspPosition> {
@@ -28,23 +56,20 @@ fn position_from_value(value: &Value) -> Option<LspPosition> {
     })
 }
 
-fn range_from_value(value: &Value) -> Option<LspRange> {
+pub(crate) fn range_from_value(value: &Value) -> Option<LspRange> {
     Some(LspRange {
         start: position_from_value(value.get("start")?)?,
         end: position_from_value(value.get("end")?)?,
     })
 }
 
-fn location_from_value(value: &Value) -> Option<LspLocation> {
+pub(crate) fn location_from_value(value: &Value) -> Option<LspLocation> {
     // Location: { uri, range }
     if let (Some(uri), Some(range)) = (
         value.get("uri").and_then(Value::as_str),
         value.get("range").and_then(range_from_value),
     ) {
-        return Some(LspLocation {
-            uri: uri.to_string(),
-            range,
-        });
+        return Some(LspLocation::new(uri, range));
     }
 
     // LocationLink: { targetUri, targetRange, targetSelectionRange?, originSelectionRange? }
@@ -56,10 +81,7 @@ fn location_from_value(value: &Value) -> Option<LspLocation> {
         value.get("targetRange").and_then(range_from_value)?
     };
 
-    Some(LspLocation {
-        uri: target_uri.to_string(),
-        range,
-    })
+    Some(LspLocation::new(target_uri, range))
 }
 
 /// Normalize a "go to" LSP result (Location | Location[] | LocationLink | LocationLink[]).
@@ -89,6 +111,7 @@ mod tests {
         let locs = locations_from_value(&v);
         assert_eq!(locs.len(), 1);
         assert_eq!(locs[0].uri, "file:///a.rs");
+        assert!(locs[0].path.as_ref().is_some_and(|p| p.ends_with("a.rs")));
         assert_eq!(locs[0].range.start.line, 1);
     }
 
@@ -104,4 +127,18 @@ mod tests {
         assert_eq!(locs[0].range.start.line, 2);
         assert_eq!(locs[0].range.end.character, 8);
     }
+
+    #[test]
+    fn test_location_to_symbol_location() {
+        let v = json!([{
+            "uri": "file:///b.rs",
+            "range": { "start": { "line": 3, "character": 4 }, "end": { "line": 3, "character": 9 } }
+        }]);
+        let loc = locations_from_value(&v).remove(0).symbol_location();
+        assert_eq!(loc.uri, "file:///b.rs");
+        assert_eq!(
+            loc.range,
+            Utf16Range::new(Utf16Position::new(3, 4), Utf16Position::new(3, 9))
+        );
+    }
 }
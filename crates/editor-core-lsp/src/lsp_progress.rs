@@ -0,0 +1,175 @@
+//! Work-done progress (`$/progress` with `WorkDoneProgressBegin/Report/End` payloads).
+//!
+//! Servers report long-running work (indexing, `cargo check`, …) as a `begin` notification, any
+//! number of `report`s and a final `end`, all keyed by a progress token. [`ProgressTracker`]
+//! folds those notifications into one [`ProgressItem`] per token, which is what a status bar
+//! wants to show. `$/progress` notifications carrying partial results (e.g. for
+//! [`crate::WorkspaceSymbolQuery`]) are not work-done progress and are ignored.
+
+use crate::lsp_events::LspProgressParams;
+use serde_json::Value;
+
+/// A parsed work-done progress payload (the `value` of a `$/progress` notification).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkDoneProgress {
+    /// `WorkDoneProgressBegin`.
+    Begin {
+        /// Operation title, e.g. `"Indexing"`.
+        title: String,
+        /// Optional detail message.
+        message: Option<String>,
+        /// Optional percentage (0-100).
+        percentage: Option<u32>,
+        /// Whether the operation can be cancelled.
+        cancellable: bool,
+    },
+    /// `WorkDoneProgressReport`.
+    Report {
+        /// Optional detail message (replaces the previous one).
+        message: Option<String>,
+        /// Optional percentage (0-100).
+        percentage: Option<u32>,
+        /// Whether the operation can be cancelled (if changed).
+        cancellable: Option<bool>,
+    },
+    /// `WorkDoneProgressEnd`.
+    End {
+        /// Optional final message.
+        message: Option<String>,
+    },
+}
+
+impl WorkDoneProgress {
+    /// Parse a work-done progress payload (`kind` is `begin`, `report` or `end`).
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let message = value
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let percentage = value
+            .get("percentage")
+            .and_then(Value::as_u64)
+            .map(|p| p.min(100) as u32);
+        let cancellable = value.get("cancellable").and_then(Value::as_bool);
+        match value.get("kind")?.as_str()? {
+            "begin" => Some(Self::Begin {
+                title: value.get("title")?.as_str()?.to_string(),
+                message,
+                percentage,
+                cancellable: cancellable.unwrap_or(false),
+            }),
+            "report" => Some(Self::Report {
+                message,
+                percentage,
+                cancellable,
+            }),
+            "end" => Some(Self::End { message }),
+            _ => None,
+        }
+    }
+}
+
+/// The state of one work-done progress token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressItem {
+    /// Progress token.
+    pub token: Value,
+    /// Operation title (from `begin`).
+    pub title: String,
+    /// Latest detail message.
+    pub message: Option<String>,
+    /// Latest percentage (0-100), if the server reports one.
+    pub percentage: Option<u32>,
+    /// Whether the operation can be cancelled.
+    pub cancellable: bool,
+    /// `true` once `end` arrived.
+    pub done: bool,
+}
+
+/// Aggregates work-done progress notifications by token.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressTracker {
+    /// Active operations, in `begin` order.
+    active: Vec<ProgressItem>,
+}
+
+impl ProgressTracker {
+    /// Create an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a `$/progress` notification.
+    ///
+    /// Returns the updated item for work-done progress (with `done` set for `end`), or `None` if
+    /// the payload is not work-done progress or reports/ends a token that never began.
+    pub fn handle_progress(&mut self, params: &LspProgressParams) -> Option<ProgressItem> {
+        let progress = WorkDoneProgress::from_value(&params.value)?;
+        let index = self
+            .active
+            .iter()
+            .position(|item| item.token == params.token);
+        match progress {
+            WorkDoneProgress::Begin {
+                title,
+                message,
+                percentage,
+                cancellable,
+            } => {
+                let item = ProgressItem {
+                    token: params.token.clone(),
+                    title,
+                    message,
+                    percentage,
+                    cancellable,
+                    done: false,
+                };
+                match index {
+                    Some(index) => self.active[index] = item.clone(),
+                    None => self.active.push(item.clone()),
+                }
+                Some(item)
+            }
+            WorkDoneProgress::Report {
+                message,
+                percentage,
+                cancellable,
+            } => {
+                let item = &mut self.active[index?];
+                if message.is_some() {
+                    item.message = message;
+                }
+                if percentage.is_some() {
+                    item.percentage = percentage;
+                }
+                if let Some(cancellable) = cancellable {
+                    item.cancellable = cancellable;
+                }
+                Some(item.clone())
+            }
+            WorkDoneProgress::End { message } => {
+                let mut item = self.active.remove(index?);
+                if message.is_some() {
+                    item.message = message;
+                }
+                item.done = true;
+                Some(item)
+            }
+        }
+    }
+
+    /// Operations that began and have not ended yet, in `begin` order.
+    pub fn active_progress(&self) -> Vec<ProgressItem> {
+        self.active.clone()
+    }
+
+    /// Returns `true` if no operation is in progress.
+    pub fn is_idle(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    /// Forget all operations (e.g. after the server exited).
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+}
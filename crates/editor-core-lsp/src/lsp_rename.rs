@@ -0,0 +1,61 @@
+//! Helpers for rename refactoring (`textDocument/prepareRename` / `textDocument/rename`).
+//!
+//! `prepareRename` results come in three shapes:
+//! - `Range`: the range of the symbol to rename
+//! - `{ range, placeholder }`: the range plus the text to pre-fill in the rename prompt
+//! - `{ defaultBehavior: true }`: rename is valid, the client picks the range itself
+//!
+//! A `null` result means the position cannot be renamed.
+
+use crate::lsp_locations::range_from_value;
+use crate::lsp_text_edits::char_offsets_for_lsp_range;
+use editor_core::LineIndex;
+use serde_json::Value;
+
+/// A normalized `textDocument/prepareRename` result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrepareRenameResult {
+    /// The symbol range, as `(start, end)` char offsets.
+    Range {
+        /// Range to rename.
+        range: (usize, usize),
+        /// Suggested initial text for the rename prompt, if the server provided one.
+        placeholder: Option<String>,
+    },
+    /// The server accepts a rename here but leaves the range to the client (usually the word at
+    /// the cursor).
+    DefaultBehavior,
+}
+
+/// Normalize a `textDocument/prepareRename` result.
+///
+/// Returns `None` for `null` (rename not possible) or an unrecognized shape. Ranges are converted
+/// from UTF-16 positions to char offsets using `line_index`.
+pub fn prepare_rename_from_value(
+    line_index: &LineIndex,
+    value: &Value,
+) -> Option<PrepareRenameResult> {
+    if value
+        .get("defaultBehavior")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return Some(PrepareRenameResult::DefaultBehavior);
+    }
+
+    let (range, placeholder) = match value.get("range") {
+        Some(range) => (
+            range_from_value(range)?,
+            value
+                .get("placeholder")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        ),
+        None => (range_from_value(value)?, None),
+    };
+
+    Some(PrepareRenameResult::Range {
+        range: char_offsets_for_lsp_range(line_index, &range),
+        placeholder,
+    })
+}
@@ -0,0 +1,425 @@
+//! Several language servers attached to one document.
+//!
+//! Some setups run more than one server per language (e.g. a language server plus a linter).
+//! [`LspSessionGroup`] owns a set of [`LspSession`]s for the same document and polls them as a
+//! single [`DocumentProcessor`]:
+//!
+//! - **Diagnostics** are keyed by server. Each session writes through its own
+//!   [`crate::LspSessionOutput`]: diagnostics are tagged with the session's provider name
+//!   ([`editor_core::Diagnostic::provider`]) and underlined in the session's own style layer, so
+//!   one server's diagnostics can be replaced or cleared without wiping the other's.
+//! - **Document sync** (`didChange` / `didSave`) is fanned out to every session.
+//! - **Requests** are routed per feature: [`LspSessionGroup::route_mut`] picks the session that
+//!   serves e.g. completion or formatting (an explicit [`LspSessionGroup::set_route`], else the
+//!   first session whose server advertises the feature).
+//! - **Failures are isolated**: a session whose connection breaks is detached, what it wrote to
+//!   the document is cleared and the failure is reported by [`LspSessionGroup::take_failures`];
+//!   the other sessions keep running.
+//!
+//! # Overlapping semantic tokens
+//!
+//! Semantic token style ids are relative to each server's legend, so tokens from two servers
+//! covering the same text cannot be combined meaningfully. The group therefore lets only one
+//! session highlight: the primary ([`LspSessionGroup::set_primary`]) if its server supports
+//! semantic tokens, otherwise the first attached session whose server does. The other sessions
+//! get `semantic_tokens_layer = None` and never request tokens. Folding ranges come from the
+//! first attached session whose server supports them.
+
+use crate::editor::{LspContentChange, LspSession};
+use crate::lsp_events::LspEvent;
+use editor_core::processing::{DocumentProcessor, ProcessingEdit};
+use editor_core::{EditorStateManager, StyleLayerId};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A request feature, used to route requests within a [`LspSessionGroup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LspFeature {
+    /// `textDocument/completion`
+    Completion,
+    /// `textDocument/hover`
+    Hover,
+    /// `textDocument/signatureHelp`
+    SignatureHelp,
+    /// `textDocument/definition`
+    Definition,
+    /// `textDocument/references`
+    References,
+    /// `textDocument/documentHighlight`
+    DocumentHighlight,
+    /// `textDocument/documentSymbol`
+    DocumentSymbols,
+    /// `workspace/symbol`
+    WorkspaceSymbols,
+    /// `textDocument/codeAction`
+    CodeAction,
+    /// `textDocument/codeLens`
+    CodeLens,
+    /// `textDocument/documentLink`
+    DocumentLinks,
+    /// `textDocument/formatting`
+    Formatting,
+    /// `textDocument/rangeFormatting`
+    RangeFormatting,
+    /// `textDocument/onTypeFormatting`
+    OnTypeFormatting,
+    /// `textDocument/rename`
+    Rename,
+    /// `textDocument/inlayHint`
+    InlayHints,
+    /// `textDocument/semanticTokens/*`
+    SemanticTokens,
+    /// `textDocument/foldingRange`
+    FoldingRange,
+    /// `textDocument/diagnostic` (pull diagnostics)
+    PullDiagnostics,
+}
+
+impl LspFeature {
+    /// The `ServerCapabilities` field advertising this feature.
+    pub fn capability_key(self) -> &'static str {
+        match self {
+            Self::Completion => "completionProvider",
+            Self::Hover => "hoverProvider",
+            Self::SignatureHelp => "signatureHelpProvider",
+            Self::Definition => "definitionProvider",
+            Self::References => "referencesProvider",
+            Self::DocumentHighlight => "documentHighlightProvider",
+            Self::DocumentSymbols => "documentSymbolProvider",
+            Self::WorkspaceSymbols => "workspaceSymbolProvider",
+            Self::CodeAction => "codeActionProvider",
+            Self::CodeLens => "codeLensProvider",
+            Self::DocumentLinks => "documentLinkProvider",
+            Self::Formatting => "documentFormattingProvider",
+            Self::RangeFormatting => "documentRangeFormattingProvider",
+            Self::OnTypeFormatting => "documentOnTypeFormattingProvider",
+            Self::Rename => "renameProvider",
+            Self::InlayHints => "inlayHintProvider",
+            Self::SemanticTokens => "semanticTokensProvider",
+            Self::FoldingRange => "foldingRangeProvider",
+            Self::PullDiagnostics => "diagnosticProvider",
+        }
+    }
+
+    /// Returns `true` if `capabilities` (a `ServerCapabilities` object) advertises the feature.
+    pub fn is_supported_by(self, capabilities: &Value) -> bool {
+        capabilities
+            .get(self.capability_key())
+            .is_some_and(|value| !value.is_null() && *value != Value::Bool(false))
+    }
+}
+
+/// A session that failed and was detached from a [`LspSessionGroup`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspSessionFailure {
+    /// Provider name of the failed session.
+    pub provider: String,
+    /// Error that made the session fail.
+    pub reason: String,
+}
+
+struct Member {
+    session: LspSession,
+    /// Semantic tokens layer requested at attach time (used while this session highlights).
+    semantic_tokens_layer: Option<StyleLayerId>,
+    /// Whether folding ranges were requested at attach time.
+    folding_ranges: bool,
+}
+
+/// Language server sessions sharing one document, polled together.
+#[derive(Default)]
+pub struct LspSessionGroup {
+    members: Vec<Member>,
+    primary: Option<String>,
+    routes: HashMap<LspFeature, String>,
+    failures: Vec<LspSessionFailure>,
+    /// Edits clearing state of failed sessions or moved layers, returned by the next poll.
+    pending_edits: Vec<ProcessingEdit>,
+}
+
+impl LspSessionGroup {
+    /// Create an empty group.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach a session.
+    ///
+    /// The session's [`crate::LspSessionOutput`] must name a provider and a diagnostics layer
+    /// that no attached session uses. Whether it provides semantic tokens and folding ranges is
+    /// decided by the group (see the module docs).
+    pub fn attach(&mut self, mut session: LspSession) -> Result<(), String> {
+        let mut output = session.output().clone();
+        let Some(provider) = output.provider.as_deref() else {
+            return Err("LSP session 未设置 provider".to_string());
+        };
+        for other in self.sessions().map(LspSession::output) {
+            if other.provider.as_deref() == Some(provider) {
+                return Err(format!("LSP provider 重复: {}", provider));
+            }
+            if other.diagnostics_layer == output.diagnostics_layer {
+                return Err(format!(
+                    "LSP diagnostics layer 冲突: {:?}",
+                    output.diagnostics_layer
+                ));
+            }
+        }
+
+        let semantic_tokens_layer = output.semantic_tokens_layer.take();
+        let folding_ranges = std::mem::replace(&mut output.folding_ranges, false);
+        session.set_output(output);
+        self.members.push(Member {
+            session,
+            semantic_tokens_layer,
+            folding_ranges,
+        });
+        self.assign_outputs();
+        Ok(())
+    }
+
+    /// Detach the session of `provider`.
+    ///
+    /// Returns the session and the edits that clear what it wrote to the document; the other
+    /// sessions' state is untouched.
+    pub fn detach(&mut self, provider: &str) -> Option<(LspSession, Vec<ProcessingEdit>)> {
+        let index = self.position(provider)?;
+        let member = self.members.remove(index);
+        let edits = member.session.output().clear_edits();
+        self.assign_outputs();
+        Some((member.session, edits))
+    }
+
+    /// Take semantic tokens from `provider`'s session (if its server supports them) instead of
+    /// the first capable session.
+    pub fn set_primary(&mut self, provider: &str) -> Result<(), String> {
+        if self.position(provider).is_none() {
+            return Err(format!("LSP provider 不存在: {}", provider));
+        }
+        self.primary = Some(provider.to_string());
+        self.assign_outputs();
+        Ok(())
+    }
+
+    /// The primary provider set by [`set_primary`](Self::set_primary).
+    pub fn primary(&self) -> Option<&str> {
+        self.primary.as_deref()
+    }
+
+    /// Route `feature` requests to `provider`'s session, whatever the servers advertise.
+    pub fn set_route(&mut self, feature: LspFeature, provider: &str) -> Result<(), String> {
+        if self.position(provider).is_none() {
+            return Err(format!("LSP provider 不存在: {}", provider));
+        }
+        self.routes.insert(feature, provider.to_string());
+        Ok(())
+    }
+
+    /// The session serving `feature`: the explicit route while its session is attached,
+    /// otherwise the first session whose server advertises the feature.
+    pub fn route(&self, feature: LspFeature) -> Option<&LspSession> {
+        let index = self.route_index(feature)?;
+        Some(&self.members[index].session)
+    }
+
+    /// The session serving `feature` (mutable, to send the request).
+    pub fn route_mut(&mut self, feature: LspFeature) -> Option<&mut LspSession> {
+        let index = self.route_index(feature)?;
+        Some(&mut self.members[index].session)
+    }
+
+    /// Returns `true` if any attached server advertises `feature`.
+    pub fn supports(&self, feature: LspFeature) -> bool {
+        self.sessions()
+            .any(|session| feature.is_supported_by(session.server_capabilities()))
+    }
+
+    /// The union of the servers' capabilities; a field advertised by several servers is taken
+    /// from the first attached one.
+    pub fn capabilities(&self) -> Value {
+        let mut union = serde_json::Map::new();
+        for session in self.sessions() {
+            if let Some(capabilities) = session.server_capabilities().as_object() {
+                for (key, value) in capabilities {
+                    union.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+        Value::Object(union)
+    }
+
+    /// Send one `textDocument/didChange` change to every session.
+    pub fn did_change(&mut self, change: LspContentChange) {
+        self.did_change_many(vec![change]);
+    }
+
+    /// Send `textDocument/didChange` to every session.
+    ///
+    /// A session that fails to send is detached (see [`take_failures`](Self::take_failures)).
+    pub fn did_change_many(&mut self, changes: Vec<LspContentChange>) {
+        self.for_each_session(|session| session.did_change_many(changes.clone()));
+    }
+
+    /// Send `textDocument/didSave` to every session.
+    ///
+    /// A session that fails to send is detached (see [`take_failures`](Self::take_failures)).
+    pub fn did_save(&mut self, text: Option<String>) {
+        self.for_each_session(|session| session.did_save(text.clone()));
+    }
+
+    /// Sessions that failed since the last call. They are already detached, and the edits
+    /// clearing their state are returned by the next poll.
+    pub fn take_failures(&mut self) -> Vec<LspSessionFailure> {
+        std::mem::take(&mut self.failures)
+    }
+
+    /// The session of `provider`.
+    pub fn session(&self, provider: &str) -> Option<&LspSession> {
+        let index = self.position(provider)?;
+        Some(&self.members[index].session)
+    }
+
+    /// The session of `provider` (mutable).
+    pub fn session_mut(&mut self, provider: &str) -> Option<&mut LspSession> {
+        let index = self.position(provider)?;
+        Some(&mut self.members[index].session)
+    }
+
+    /// Attached sessions, in attach order.
+    pub fn sessions(&self) -> impl Iterator<Item = &LspSession> + '_ {
+        self.members.iter().map(|m| &m.session)
+    }
+
+    /// Attached sessions, in attach order (mutable).
+    pub fn sessions_mut(&mut self) -> impl Iterator<Item = &mut LspSession> + '_ {
+        self.members.iter_mut().map(|m| &mut m.session)
+    }
+
+    /// Number of attached sessions.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns `true` if no session is attached.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Drain the queued events of all sessions, each paired with its session's provider.
+    pub fn drain_events(&mut self) -> Vec<(String, LspEvent)> {
+        let mut out = Vec::new();
+        for session in self.sessions_mut() {
+            let provider = provider_of(session).to_string();
+            out.extend(
+                session
+                    .drain_events()
+                    .into_iter()
+                    .map(|event| (provider.clone(), event)),
+            );
+        }
+        out
+    }
+
+    fn position(&self, provider: &str) -> Option<usize> {
+        self.sessions()
+            .position(|s| s.output().provider.as_deref() == Some(provider))
+    }
+
+    fn route_index(&self, feature: LspFeature) -> Option<usize> {
+        self.routes
+            .get(&feature)
+            .and_then(|provider| self.position(provider))
+            .or_else(|| {
+                self.sessions()
+                    .position(|s| feature.is_supported_by(s.server_capabilities()))
+            })
+    }
+
+    /// Run `f` on every session, detaching the ones that fail.
+    fn for_each_session(&mut self, mut f: impl FnMut(&mut LspSession) -> Result<(), String>) {
+        let mut index = 0;
+        while index < self.members.len() {
+            match f(&mut self.members[index].session) {
+                Ok(()) => index += 1,
+                Err(reason) => self.fail(index, reason),
+            }
+        }
+    }
+
+    fn fail(&mut self, index: usize, reason: String) {
+        let member = self.members.remove(index);
+        self.pending_edits
+            .extend(member.session.output().clear_edits());
+        self.failures.push(LspSessionFailure {
+            provider: provider_of(&member.session).to_string(),
+            reason,
+        });
+        self.assign_outputs();
+    }
+
+    /// Decide which session highlights and which provides folding ranges.
+    fn assign_outputs(&mut self) {
+        let highlights =
+            |m: &Member| m.semantic_tokens_layer.is_some() && m.session.supports_semantic_tokens();
+        let highlighter = self
+            .primary
+            .as_deref()
+            .and_then(|primary| self.position(primary))
+            .filter(|&index| highlights(&self.members[index]))
+            .or_else(|| self.members.iter().position(highlights));
+        let folder = self
+            .members
+            .iter()
+            .position(|m| m.folding_ranges && m.session.supports_folding_range());
+
+        for (index, member) in self.members.iter_mut().enumerate() {
+            let mut output = member.session.output().clone();
+            let layer = member
+                .semantic_tokens_layer
+                .filter(|_| highlighter == Some(index));
+            if let Some(old) = output.semantic_tokens_layer
+                && layer != Some(old)
+            {
+                self.pending_edits
+                    .push(ProcessingEdit::ClearStyleLayer { layer: old });
+            }
+            let starts_highlighting = layer.is_some() && layer != output.semantic_tokens_layer;
+            output.semantic_tokens_layer = layer;
+            output.folding_ranges = folder == Some(index);
+            member.session.set_output(output);
+            if starts_highlighting {
+                member.session.schedule_refresh(Duration::ZERO);
+            }
+        }
+    }
+}
+
+impl DocumentProcessor for LspSessionGroup {
+    type Error = String;
+
+    /// Poll every session; the edits are concatenated in attach order.
+    ///
+    /// A session that fails is detached and its state cleared instead of failing the whole
+    /// poll (see [`LspSessionGroup::take_failures`]).
+    fn process(&mut self, state: &EditorStateManager) -> Result<Vec<ProcessingEdit>, Self::Error> {
+        let mut polled = Vec::new();
+        let mut index = 0;
+        while index < self.members.len() {
+            match self.members[index].session.poll_edits(state) {
+                Ok(edits) => {
+                    polled.extend(edits);
+                    index += 1;
+                }
+                Err(reason) => self.fail(index, reason),
+            }
+        }
+        let mut edits = std::mem::take(&mut self.pending_edits);
+        edits.extend(polled);
+        Ok(edits)
+    }
+}
+
+/// Provider name of an attached session ([`LspSessionGroup::attach`] guarantees one).
+fn provider_of(session: &LspSession) -> &str {
+    session.output().provider.as_deref().unwrap_or_default()
+}
@@ -0,0 +1,184 @@
+//! Signature help helpers (`textDocument/signatureHelp`).
+//!
+//! [`signature_help_from_value`] decodes a `SignatureHelp` result. Parameter labels may be given
+//! either as a substring of the signature label or as `[start, end]` UTF-16 offsets into it; both
+//! are resolved to char offsets within the signature label, so hosts can emphasize the active
+//! parameter without caring about the encoding.
+
+use crate::lsp_hover::{HoverBlock, hover_block_from_value};
+use crate::lsp_sync::LspCoordinateConverter;
+use serde_json::Value;
+
+/// One parameter of a signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterInformation {
+    /// Parameter label text.
+    pub label: String,
+    /// `(start, end)` char offsets of the parameter within the signature label, if it could be
+    /// located.
+    pub label_range: Option<(usize, usize)>,
+    /// Parameter documentation.
+    pub documentation: Option<HoverBlock>,
+}
+
+/// One callable signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureInformation {
+    /// Signature label (e.g. `fn add(a: i32, b: i32) -> i32`).
+    pub label: String,
+    /// Signature documentation.
+    pub documentation: Option<HoverBlock>,
+    /// Parameters, in order.
+    pub parameters: Vec<ParameterInformation>,
+    /// Per-signature active parameter (overrides [`SignatureHelp::active_parameter`]).
+    pub active_parameter: Option<usize>,
+}
+
+/// A normalized signature help result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureHelp {
+    /// Signatures (never empty).
+    pub signatures: Vec<SignatureInformation>,
+    /// Index of the active signature (always a valid index into `signatures`).
+    pub active_signature: usize,
+    /// Active parameter of the active signature, if the server provided one.
+    pub active_parameter: Option<usize>,
+}
+
+impl SignatureHelp {
+    /// The active signature.
+    pub fn active(&self) -> &SignatureInformation {
+        &self.signatures[self.active_signature]
+    }
+
+    /// The active parameter of the active signature, if any.
+    ///
+    /// The signature's own `activeParameter` wins over the top-level one; a missing value means
+    /// the first parameter, as in the spec.
+    pub fn active_parameter_info(&self) -> Option<&ParameterInformation> {
+        let signature = self.active();
+        let index = signature
+            .active_parameter
+            .or(self.active_parameter)
+            .unwrap_or(0);
+        signature.parameters.get(index)
+    }
+
+    /// `(start, end)` char offsets of the active parameter within the active signature label.
+    pub fn active_parameter_range(&self) -> Option<(usize, usize)> {
+        self.active_parameter_info()?.label_range
+    }
+}
+
+fn documentation_from_value(value: Option<&Value>) -> Option<HoverBlock> {
+    let value = value?;
+    match value.as_str() {
+        // Unlike hover's `MarkedString`, a bare string here is plain text.
+        Some(text) if text.trim().is_empty() => None,
+        Some(text) => Some(HoverBlock::PlainText(text.to_string())),
+        None => hover_block_from_value(value),
+    }
+}
+
+fn index_from_value(value: Option<&Value>) -> Option<usize> {
+    value
+        .and_then(Value::as_u64)
+        .and_then(|index| usize::try_from(index).ok())
+}
+
+fn parameter_from_value(
+    signature_label: &str,
+    search_from: &mut usize,
+    value: &Value,
+) -> Option<ParameterInformation> {
+    let label = value.get("label")?;
+    let (label, label_range) = if let Some(text) = label.as_str() {
+        // String form: a substring of the signature label. Search after the previous parameter
+        // so repeated names (`a: T, b: T`) land on the right occurrence.
+        let byte_start = signature_label
+            .char_indices()
+            .nth(*search_from)
+            .map_or(signature_label.len(), |(byte, _)| byte);
+        let range = (!text.is_empty())
+            .then(|| signature_label[byte_start..].find(text))
+            .flatten()
+            .map(|byte| {
+                let start = *search_from + signature_label[byte_start..][..byte].chars().count();
+                (start, start + text.chars().count())
+            });
+        (text.to_string(), range)
+    } else {
+        // Offset form: `[start, end]` in UTF-16 code units of the signature label.
+        let offsets = label.as_array()?;
+        let [start, end] = offsets.as_slice() else {
+            return None;
+        };
+        let start = usize::try_from(start.as_u64()?).ok()?;
+        let end = usize::try_from(end.as_u64()?).ok()?;
+        let start = LspCoordinateConverter::utf16_to_char_offset(signature_label, start);
+        let end = LspCoordinateConverter::utf16_to_char_offset(signature_label, end).max(start);
+        let text: String = signature_label
+            .chars()
+            .skip(start)
+            .take(end - start)
+            .collect();
+        (text, Some((start, end)))
+    };
+
+    if let Some((_, end)) = label_range {
+        *search_from = end;
+    }
+    Some(ParameterInformation {
+        label,
+        label_range,
+        documentation: documentation_from_value(value.get("documentation")),
+    })
+}
+
+fn signature_from_value(value: &Value) -> Option<SignatureInformation> {
+    let label = value.get("label")?.as_str()?.to_string();
+    let mut search_from = 0;
+    let parameters = value
+        .get("parameters")
+        .and_then(Value::as_array)
+        .map(|params| {
+            params
+                .iter()
+                .filter_map(|param| parameter_from_value(&label, &mut search_from, param))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(SignatureInformation {
+        documentation: documentation_from_value(value.get("documentation")),
+        parameters,
+        active_parameter: index_from_value(value.get("activeParameter")),
+        label,
+    })
+}
+
+/// Normalize a `textDocument/signatureHelp` result.
+///
+/// Returns `None` for a `null` result or when there are no signatures. An out-of-range
+/// `activeSignature` falls back to the first signature.
+pub fn signature_help_from_value(value: &Value) -> Option<SignatureHelp> {
+    let signatures: Vec<SignatureInformation> = value
+        .get("signatures")?
+        .as_array()?
+        .iter()
+        .filter_map(signature_from_value)
+        .collect();
+    if signatures.is_empty() {
+        return None;
+    }
+
+    let active_signature = index_from_value(value.get("activeSignature"))
+        .filter(|index| *index < signatures.len())
+        .unwrap_or(0);
+
+    Some(SignatureHelp {
+        signatures,
+        active_signature,
+        active_parameter: index_from_value(value.get("activeParameter")),
+    })
+}
@@ -40,6 +40,13 @@ fn char_offsets_for_lsp_range(line_index: &LineIndex, range: &LspRange) -> (usiz
     (start.min(end), start.max(end))
 }
 
+fn utf16_range_from_lsp(range: &LspRange) -> Utf16Range {
+    Utf16Range::new(
+        Utf16Position::new(range.start.line, range.start.character),
+        Utf16Position::new(range.end.line, range.end.character),
+    )
+}
+
 fn parse_document_symbol(line_index: &LineIndex, value: &Value) -> Option<DocumentSymbol> {
     let name = value.get("name")?.as_str()?.to_string();
     let detail = value
@@ -72,6 +79,8 @@ fn parse_document_symbol(line_index: &LineIndex, value: &Value) -> Option<Docume
         kind: SymbolKind::from_lsp_kind(kind),
         range: SymbolRange::new(start, end),
         selection_range: SymbolRange::new(sel_start, sel_end),
+        utf16_range: Some(utf16_range_from_lsp(&range)),
+        utf16_selection_range: Some(utf16_range_from_lsp(&selection_range)),
         children,
         data_json: Some(value.to_string()),
     })
@@ -95,6 +104,8 @@ fn parse_symbol_information_as_document_symbol(
         kind: SymbolKind::from_lsp_kind(kind),
         range: SymbolRange::new(start, end),
         selection_range: SymbolRange::new(start, end),
+        utf16_range: Some(utf16_range_from_lsp(&range)),
+        utf16_selection_range: Some(utf16_range_from_lsp(&range)),
         children: Vec::new(),
         data_json: Some(value.to_string()),
     })
@@ -124,6 +135,14 @@ pub fn lsp_document_symbols_to_outline(line_index: &LineIndex, result: &Value) -
     DocumentOutline::new(symbols)
 }
 
+/// Decode a `textDocument/documentSymbol` result into a document outline.
+///
+/// Same as [`lsp_document_symbols_to_outline`], with the argument order of the other
+/// `*_from_value` helpers. Each symbol carries both char-offset and UTF-16 ranges.
+pub fn document_symbols_from_value(result: &Value, line_index: &LineIndex) -> DocumentOutline {
+    lsp_document_symbols_to_outline(line_index, result)
+}
+
 /// Convert document symbols into a processing edit that replaces the current outline.
 pub fn lsp_document_symbols_to_processing_edit(
     line_index: &LineIndex,
@@ -141,7 +160,7 @@ fn parse_utf16_position(value: &Value) -> Option<Utf16Position> {
     })
 }
 
-fn parse_utf16_range(value: &Value) -> Option<Utf16Range> {
+pub(crate) fn parse_utf16_range(value: &Value) -> Option<Utf16Range> {
     Some(Utf16Range::new(
         parse_utf16_position(value.get("start")?)?,
         parse_utf16_position(value.get("end")?)?,
@@ -150,56 +169,65 @@ fn parse_utf16_range(value: &Value) -> Option<Utf16Range> {
 
 fn parse_symbol_location(value: &Value) -> Option<SymbolLocation> {
     let uri = value.get("uri")?.as_str()?.to_string();
-    let range = parse_utf16_range(value.get("range")?)?;
+    // `WorkspaceSymbol.location` may be `{ uri }` only, to be completed by
+    // `workspaceSymbol/resolve`; keep such symbols with an empty range at the start of the file.
+    let range = match value.get("range") {
+        Some(range) => parse_utf16_range(range)?,
+        None => Utf16Range::new(Utf16Position::new(0, 0), Utf16Position::new(0, 0)),
+    };
     Some(SymbolLocation { uri, range })
 }
 
+/// Parse one `workspace/symbol` result item (`SymbolInformation` or `WorkspaceSymbol`).
+///
+/// The raw item is kept in [`WorkspaceSymbol::data_json`], so it can be sent back for
+/// `workspaceSymbol/resolve`.
+pub fn workspace_symbol_from_value(item: &Value) -> Option<WorkspaceSymbol> {
+    let name = item.get("name").and_then(Value::as_str).unwrap_or("");
+    if name.is_empty() {
+        return None;
+    }
+
+    let kind = item.get("kind").and_then(Value::as_u64).unwrap_or(0) as u32;
+    let detail = item
+        .get("detail")
+        .and_then(Value::as_str)
+        .map(|s| s.to_string());
+    let container_name = item
+        .get("containerName")
+        .and_then(Value::as_str)
+        .map(|s| s.to_string());
+    let location = parse_symbol_location(item.get("location")?)?;
+
+    Some(WorkspaceSymbol {
+        name: name.to_string(),
+        detail,
+        kind: SymbolKind::from_lsp_kind(kind),
+        location,
+        container_name,
+        data_json: Some(item.to_string()),
+    })
+}
+
+/// Returns `true` if a workspace symbol only has a `location.uri` and needs
+/// `workspaceSymbol/resolve` before its range is known.
+pub fn workspace_symbol_needs_resolve(symbol: &WorkspaceSymbol) -> bool {
+    symbol
+        .data_json
+        .as_deref()
+        .and_then(|raw| serde_json::from_str::<Value>(raw).ok())
+        .and_then(|raw| raw.get("location").cloned())
+        .is_some_and(|location| location.get("range").is_none())
+}
+
 /// Convert an LSP `workspace/symbol` result payload into workspace symbols.
 ///
 /// This supports `SymbolInformation[]` (LSP 3.16) and the newer `WorkspaceSymbol[]`-shaped
-/// payloads where `location` is `Location`.
+/// payloads where `location` is `Location` or `{ uri }` (see
+/// [`workspace_symbol_needs_resolve`]).
 pub fn lsp_workspace_symbols_to_results(result: &Value) -> Vec<WorkspaceSymbol> {
     let Some(arr) = result.as_array() else {
         return Vec::new();
     };
-
-    let mut out = Vec::<WorkspaceSymbol>::with_capacity(arr.len());
-    for item in arr {
-        let name = item
-            .get("name")
-            .and_then(Value::as_str)
-            .unwrap_or("")
-            .to_string();
-        if name.is_empty() {
-            continue;
-        }
-
-        let kind = item.get("kind").and_then(Value::as_u64).unwrap_or(0) as u32;
-        let detail = item
-            .get("detail")
-            .and_then(Value::as_str)
-            .map(|s| s.to_string());
-        let container_name = item
-            .get("containerName")
-            .and_then(Value::as_str)
-            .map(|s| s.to_string());
-
-        let Some(location_value) = item.get("location") else {
-            continue;
-        };
-        let Some(location) = parse_symbol_location(location_value) else {
-            continue;
-        };
-
-        out.push(WorkspaceSymbol {
-            name,
-            detail,
-            kind: SymbolKind::from_lsp_kind(kind),
-            location,
-            container_name,
-            data_json: Some(item.to_string()),
-        });
-    }
-
-    out
+    arr.iter().filter_map(workspace_symbol_from_value).collect()
 }
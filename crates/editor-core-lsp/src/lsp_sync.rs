@@ -58,6 +58,12 @@ pub struct TextChange {
 /// LSP coordinate converter
 ///
 /// Handles conversions between byte offsets, character offsets, and LSP Position (UTF-16)
+///
+/// Characters outside the Basic Multilingual Plane (emoji, CJK extension B, …) are one `char`
+/// but two UTF-16 code units (a surrogate pair). Conversions round-trip exactly for offsets on
+/// character boundaries; out-of-range offsets clamp to the end of the text, and a UTF-16 offset
+/// pointing between the two halves of a surrogate pair rounds down to the start of that
+/// character.
 pub struct LspCoordinateConverter;
 
 impl LspCoordinateConverter {
@@ -67,24 +73,28 @@ impl LspCoordinateConverter {
     }
 
     /// Convert character offset to UTF-16 code unit offset
+    ///
+    /// Offsets past the end of `text` clamp to its UTF-16 length.
     pub fn char_offset_to_utf16(text: &str, char_offset: usize) -> usize {
         text.chars().take(char_offset).map(|c| c.len_utf16()).sum()
     }
 
     /// Convert UTF-16 code unit offset to character offset
+    ///
+    /// Offsets past the end of `text` clamp to its character count. An offset inside a surrogate
+    /// pair rounds down to the character the pair encodes, so it never moves past that
+    /// character.
     pub fn utf16_to_char_offset(text: &str, utf16_offset: usize) -> usize {
         let mut current_utf16 = 0;
-        let mut char_count = 0;
 
-        for ch in text.chars() {
-            if current_utf16 >= utf16_offset {
-                break;
-            }
+        for (char_count, ch) in text.chars().enumerate() {
             current_utf16 += ch.len_utf16();
-            char_count += 1;
+            if current_utf16 > utf16_offset {
+                return char_count;
+            }
         }
 
-        char_count
+        text.chars().count()
     }
 
     /// Convert line and column (character offset) to LSP Position
@@ -94,6 +104,8 @@ impl LspCoordinateConverter {
     }
 
     /// Convert LSP Position to character offset
+    ///
+    /// Clamps like [`utf16_to_char_offset`](Self::utf16_to_char_offset).
     pub fn lsp_to_char_offset(line_text: &str, character: u32) -> usize {
         Self::utf16_to_char_offset(line_text, character as usize)
     }
@@ -352,23 +364,213 @@ impl SemanticToken {
     }
 }
 
+/// One splice of a `semanticTokens/full/delta` response (`SemanticTokensEdit`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticTokensEdit {
+    /// Start index into the previous raw `data` array.
+    pub start: usize,
+    /// Number of `u32`s to remove at `start`.
+    pub delete_count: usize,
+    /// `u32`s to insert at `start`.
+    pub data: Vec<u32>,
+}
+
 /// Semantic tokens manager
 ///
-/// Converts semantic tokens returned by LSP into a format usable by Interval Tree
+/// Caches the raw semantic token `data` of a document (plus the server's `resultId`), so that
+/// `semanticTokens/full/delta` responses can be applied on top of the previous result, and
+/// converts the tokens into a format usable by Interval Tree
+///
+/// `semanticTokens/range` results (e.g. for the viewport) are kept separately, keyed by the line
+/// span they cover, and overlay the full data until a full result for the same document version
+/// arrives.
 pub struct SemanticTokensManager {
-    /// Current tokens
-    tokens: Vec<SemanticToken>,
+    /// Current raw token data (5 `u32`s per token)
+    data: Vec<u32>,
+    /// `resultId` of the response `data` came from
+    result_id: Option<String>,
+    /// Document version `data` belongs to
+    version: Option<i32>,
+    /// Range results as `(start_line, end_line, intervals)`, end line exclusive
+    ranges: Vec<(usize, usize, Vec<Interval>)>,
+    /// Document version the range results belong to
+    ranges_version: Option<i32>,
 }
 
 impl SemanticTokensManager {
     /// Create an empty semantic tokens manager.
     pub fn new() -> Self {
-        Self { tokens: Vec::new() }
+        Self {
+            data: Vec::new(),
+            result_id: None,
+            version: None,
+            ranges: Vec::new(),
+            ranges_version: None,
+        }
     }
 
     /// Update tokens
+    ///
+    /// This forgets the `resultId`, so the next request has to be a full one.
     pub fn update_tokens(&mut self, tokens: Vec<SemanticToken>) {
-        self.tokens = tokens;
+        self.data = tokens
+            .iter()
+            .flat_map(|t| {
+                [
+                    t.delta_line,
+                    t.delta_start,
+                    t.length,
+                    t.token_type,
+                    t.token_modifiers,
+                ]
+            })
+            .collect();
+        self.result_id = None;
+    }
+
+    /// Replace the cache with a full `semanticTokens/full` response.
+    ///
+    /// A full result supersedes any range results.
+    pub fn set_full(&mut self, data: Vec<u32>, result_id: Option<String>) {
+        self.data = data;
+        self.result_id = result_id;
+        self.clear_ranges();
+    }
+
+    /// Record the document version the full data belongs to.
+    pub fn set_version(&mut self, version: Option<i32>) {
+        self.version = version;
+    }
+
+    /// Document version the full data belongs to, if known.
+    pub fn version(&self) -> Option<i32> {
+        self.version
+    }
+
+    /// Merge a `semanticTokens/range` result covering lines `start_line..end_line`.
+    ///
+    /// Earlier range results overlapping the span are replaced; results from another document
+    /// version are dropped. Returns `false` (and ignores the result) if full data for `version` is
+    /// already cached: the full result wins over a range result that arrives late.
+    pub fn merge_range(
+        &mut self,
+        version: i32,
+        start_line: usize,
+        end_line: usize,
+        intervals: Vec<Interval>,
+    ) -> bool {
+        if self.version == Some(version) {
+            return false;
+        }
+        if self.ranges_version != Some(version) {
+            self.ranges.clear();
+            self.ranges_version = Some(version);
+        }
+        self.ranges
+            .retain(|(start, end, _)| *end <= start_line || end_line <= *start);
+        self.ranges.push((start_line, end_line, intervals));
+        true
+    }
+
+    /// Line spans (`start_line..end_line`) currently covered by range results.
+    pub fn range_spans(&self) -> Vec<(usize, usize)> {
+        self.ranges
+            .iter()
+            .map(|(start, end, _)| (*start, *end))
+            .collect()
+    }
+
+    /// Drop all range results.
+    pub fn clear_ranges(&mut self) {
+        self.ranges.clear();
+        self.ranges_version = None;
+    }
+
+    /// Style intervals for the cached tokens, with range results overlaid.
+    ///
+    /// Full-data intervals that start inside a range span are replaced by that range's
+    /// intervals. Undecodable full data (e.g. stale against `line_index`) is treated as empty.
+    pub fn to_intervals<F>(&self, line_index: &LineIndex, style_resolver: F) -> Vec<Interval>
+    where
+        F: Fn(u32, u32) -> StyleId,
+    {
+        let mut intervals = semantic_tokens_to_intervals(&self.data, line_index, &style_resolver)
+            .unwrap_or_default();
+        if self.ranges.is_empty() {
+            return intervals;
+        }
+
+        let line_count = line_index.line_count();
+        let line_start = |line: usize| {
+            if line < line_count {
+                line_index.position_to_char_offset(line, 0)
+            } else {
+                line_index.char_count()
+            }
+        };
+        let spans: Vec<(usize, usize)> = self
+            .ranges
+            .iter()
+            .map(|(start, end, _)| (line_start(*start), line_start(*end)))
+            .collect();
+        intervals.retain(|interval| {
+            !spans
+                .iter()
+                .any(|(start, end)| *start <= interval.start && interval.start < *end)
+        });
+        for (_, _, range_intervals) in &self.ranges {
+            intervals.extend(range_intervals.iter().cloned());
+        }
+        intervals.sort_by_key(|interval| (interval.start, interval.end));
+        intervals
+    }
+
+    /// Apply a `semanticTokens/full/delta` response to the cached data.
+    ///
+    /// Edit indices refer to the previous data array; edits may arrive in any order but must not
+    /// overlap. On error the cache is cleared, so the next request falls back to a full one.
+    pub fn apply_delta(
+        &mut self,
+        edits: &[SemanticTokensEdit],
+        result_id: Option<String>,
+    ) -> Result<(), SemanticTokensError> {
+        let mut sorted: Vec<&SemanticTokensEdit> = edits.iter().collect();
+        sorted.sort_by_key(|edit| edit.start);
+
+        let mut data = Vec::with_capacity(self.data.len());
+        let mut copied = 0usize;
+        for edit in sorted {
+            let end = edit.start.saturating_add(edit.delete_count);
+            if edit.start < copied || end > self.data.len() {
+                self.clear();
+                return Err(SemanticTokensError::InvalidDeltaEdit {
+                    start: edit.start,
+                    delete_count: edit.delete_count,
+                });
+            }
+            data.extend_from_slice(&self.data[copied..edit.start]);
+            data.extend_from_slice(&edit.data);
+            copied = end;
+        }
+        data.extend_from_slice(&self.data[copied..]);
+
+        if !data.len().is_multiple_of(5) {
+            self.clear();
+            return Err(SemanticTokensError::InvalidDataLength(data.len()));
+        }
+        self.data = data;
+        self.result_id = result_id;
+        Ok(())
+    }
+
+    /// Current raw token data (e.g. for [`semantic_tokens_to_intervals`]).
+    pub fn data(&self) -> &[u32] {
+        &self.data
+    }
+
+    /// `resultId` of the cached data, to send as `previousResultId` in a delta request.
+    pub fn result_id(&self) -> Option<&str> {
+        self.result_id.as_deref()
     }
 
     /// Convert relative offset tokens to absolute positions
@@ -379,15 +581,16 @@ impl SemanticTokensManager {
         let mut current_line = 0;
         let mut current_start = 0;
 
-        for token in &self.tokens {
-            if token.delta_line > 0 {
-                current_line += token.delta_line;
-                current_start = token.delta_start;
+        for token in self.data.chunks_exact(5) {
+            let (delta_line, delta_start) = (token[0], token[1]);
+            if delta_line > 0 {
+                current_line += delta_line;
+                current_start = delta_start;
             } else {
-                current_start += token.delta_start;
+                current_start += delta_start;
             }
 
-            result.push((current_line, current_start, token.length, token.token_type));
+            result.push((current_line, current_start, token[2], token[3]));
         }
 
         result
@@ -395,7 +598,10 @@ impl SemanticTokensManager {
 
     /// Clear tokens
     pub fn clear(&mut self) {
-        self.tokens.clear();
+        self.data.clear();
+        self.result_id = None;
+        self.version = None;
+        self.clear_ranges();
     }
 }
 
@@ -414,6 +620,13 @@ pub enum SemanticTokensError {
     InvalidLine(u32),
     /// Token UTF-16 range calculation overflow
     Utf16Overflow,
+    /// A delta edit is out of range of the previous data, or overlaps another edit
+    InvalidDeltaEdit {
+        /// Edit start index
+        start: usize,
+        /// Edit delete count
+        delete_count: usize,
+    },
 }
 
 impl std::fmt::Display for SemanticTokensError {
@@ -430,6 +643,14 @@ impl std::fmt::Display for SemanticTokensError {
                 write!(f, "Semantic token line out of range: {}", line)
             }
             SemanticTokensError::Utf16Overflow => write!(f, "Semantic token UTF-16 range overflow"),
+            SemanticTokensError::InvalidDeltaEdit {
+                start,
+                delete_count,
+            } => write!(
+                f,
+                "Invalid semantic tokens delta edit (start {}, deleteCount {})",
+                start, delete_count
+            ),
         }
     }
 }
@@ -693,6 +914,99 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_utf16_offsets_clamp_and_split_surrogate_pairs_round_down() {
+        // 😀 is one char and two UTF-16 units (units 1..3).
+        let text = "a😀b";
+        let expected = [0, 1, 1, 2, 3, 3, 3];
+        for (utf16, char_offset) in expected.into_iter().enumerate() {
+            assert_eq!(
+                LspCoordinateConverter::utf16_to_char_offset(text, utf16),
+                char_offset,
+                "utf16 offset {}",
+                utf16
+            );
+        }
+        assert_eq!(LspCoordinateConverter::char_offset_to_utf16(text, 2), 3);
+        assert_eq!(LspCoordinateConverter::char_offset_to_utf16(text, 99), 4);
+        assert_eq!(LspCoordinateConverter::utf16_to_char_offset("", 5), 0);
+    }
+
+    #[test]
+    fn test_roundtrip_conversion_fuzz_astral_plane() {
+        // ASCII, BMP CJK, emoji and a CJK extension B ideograph (astral plane).
+        const ALPHABET: [char; 8] = ['a', 'Z', ' ', '你', '好', '👋', '😀', '𠀀'];
+        let mut seed = 0x2545_f491_u32;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        for _ in 0..200 {
+            let len = (next() % 24) as usize;
+            let text: String = (0..len)
+                .map(|_| ALPHABET[next() as usize % ALPHABET.len()])
+                .collect();
+            let chars: Vec<char> = text.chars().collect();
+
+            // Every char boundary round-trips, and the UTF-16 offset is the encoded prefix length.
+            for char_offset in 0..=chars.len() {
+                let utf16 = LspCoordinateConverter::char_offset_to_utf16(&text, char_offset);
+                let prefix: String = chars[..char_offset].iter().collect();
+                assert_eq!(utf16, prefix.encode_utf16().count(), "text {:?}", text);
+                assert_eq!(
+                    LspCoordinateConverter::utf16_to_char_offset(&text, utf16),
+                    char_offset,
+                    "text {:?}, char offset {}",
+                    text,
+                    char_offset
+                );
+            }
+
+            // Every UTF-16 offset (including split surrogate pairs) maps to the char containing it.
+            let total = LspCoordinateConverter::utf8_to_utf16_len(&text);
+            for utf16 in 0..=total + 1 {
+                let char_offset = LspCoordinateConverter::utf16_to_char_offset(&text, utf16);
+                let start = LspCoordinateConverter::char_offset_to_utf16(&text, char_offset);
+                assert!(
+                    start <= utf16.min(total),
+                    "text {:?}, utf16 {}",
+                    text,
+                    utf16
+                );
+                if char_offset < chars.len() {
+                    assert!(utf16 < start + chars[char_offset].len_utf16());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_positions_roundtrip_past_astral_characters() {
+        let text = "fn 你好() {}\nlet 😀 = \"𠀀👋\";\n// ok 👋👋 done\n";
+        let line_index = editor_core::LineIndex::from_text(text);
+
+        for offset in 0..=text.chars().count() {
+            let (line, column) = line_index.char_offset_to_position(offset);
+            let line_text = line_index.get_line_text(line).unwrap_or_default();
+            let pos = LspCoordinateConverter::position_to_lsp(&line_text, line, column);
+            let back = LspCoordinateConverter::lsp_to_char_offset(&line_text, pos.character);
+            assert_eq!(
+                line_index.position_to_char_offset(pos.line as usize, back),
+                offset,
+                "offset {} -> {:?}",
+                offset,
+                pos
+            );
+        }
+
+        // Past the two emoji on line 2, columns and UTF-16 units differ by two.
+        let pos = LspCoordinateConverter::position_to_lsp("// ok 👋👋 done", 2, 9);
+        assert_eq!(pos.character, 11);
+    }
+
     #[test]
     fn test_encode_decode_semantic_style_id() {
         let style_id = encode_semantic_style_id(42, 0xBEEF);
@@ -71,6 +71,19 @@ pub fn percent_decode_path(path: &str) -> String {
     String::from_utf8_lossy(&out).to_string()
 }
 
+/// Normalize the percent-encoding of a `file://` URI to the form produced by
+/// [`path_to_file_uri`].
+///
+/// Servers do not always encode URIs the same way as the client (`%3a` vs `%3A`, or raw
+/// non-ASCII characters), so this is useful when matching server URIs against open documents.
+/// Other URIs are returned unchanged.
+pub fn normalize_file_uri(uri: &str) -> String {
+    match uri.strip_prefix("file://") {
+        Some(path) => format!("file://{}", percent_encode_path(&percent_decode_path(path))),
+        None => uri.to_string(),
+    }
+}
+
 /// Convert a `file://` URI back into a local filesystem path.
 ///
 /// This is intentionally minimal and is primarily intended to round-trip URIs created by
@@ -104,6 +117,15 @@ mod tests {
         assert_eq!(percent_decode_path(&encoded), input);
     }
 
+    #[test]
+    fn test_normalize_file_uri() {
+        assert_eq!(
+            normalize_file_uri("file:///c%3a/caf\u{e9} x.rs"),
+            "file:///c%3A/caf%C3%A9%20x.rs"
+        );
+        assert_eq!(normalize_file_uri("untitled:1"), "untitled:1");
+    }
+
     #[test]
     fn test_file_uri_roundtrip() {
         let path = Path::new("/tmp/hello world.txt");
@@ -0,0 +1,159 @@
+//! File watching and configuration (`workspace/didChangeWatchedFiles`, `workspace/configuration`).
+//!
+//! Servers ask the client to watch files by dynamically registering
+//! `workspace/didChangeWatchedFiles` (`client/registerCapability`) with a list of glob
+//! watchers. The session records those as [`LspFileWatcher`]s; the host runs its own file
+//! watcher and reports matches with [`crate::LspSession::notify_watched_files_changed`].
+//!
+//! Servers pull settings with `workspace/configuration` requests, one [`LspConfigurationItem`]
+//! per requested section.
+
+use serde_json::Value;
+
+/// `FileChangeType` of a `workspace/didChangeWatchedFiles` change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileChangeType {
+    /// The file was created.
+    Created,
+    /// The file was changed.
+    Changed,
+    /// The file was deleted.
+    Deleted,
+}
+
+impl FileChangeType {
+    /// The LSP integer value (`1`, `2` or `3`).
+    pub fn to_lsp(self) -> u32 {
+        match self {
+            Self::Created => 1,
+            Self::Changed => 2,
+            Self::Deleted => 3,
+        }
+    }
+}
+
+/// A glob watcher registered by the server for `workspace/didChangeWatchedFiles`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspFileWatcher {
+    /// Id of the registration the watcher belongs to (used by `client/unregisterCapability`).
+    pub registration_id: String,
+    /// Glob pattern, e.g. `**/*.rs`.
+    pub glob_pattern: String,
+    /// Base folder URI for a `RelativePattern`; `None` means relative to the workspace folders.
+    pub base_uri: Option<String>,
+    /// Bitmask of the changes to report (`1` create, `2` change, `4` delete).
+    pub kind: u32,
+}
+
+impl LspFileWatcher {
+    /// Whether changes of type `change` should be reported for this watcher.
+    pub fn watches(&self, change: FileChangeType) -> bool {
+        let bit = match change {
+            FileChangeType::Created => 1,
+            FileChangeType::Changed => 2,
+            FileChangeType::Deleted => 4,
+        };
+        self.kind & bit != 0
+    }
+}
+
+/// File watchers from the `registrations` of `client/registerCapability` params, keeping only
+/// `workspace/didChangeWatchedFiles` registrations.
+pub fn file_watchers_from_registrations(params: &Value) -> Vec<LspFileWatcher> {
+    let Some(registrations) = params.get("registrations").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for registration in registrations {
+        if registration.get("method").and_then(Value::as_str)
+            != Some("workspace/didChangeWatchedFiles")
+        {
+            continue;
+        }
+        let id = registration
+            .get("id")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let watchers = registration
+            .get("registerOptions")
+            .and_then(|options| options.get("watchers"))
+            .and_then(Value::as_array);
+        for watcher in watchers.into_iter().flatten() {
+            let kind = watcher
+                .get("kind")
+                .and_then(Value::as_u64)
+                .map_or(7, |kind| kind as u32);
+            let (glob_pattern, base_uri) = match watcher.get("globPattern") {
+                Some(Value::String(pattern)) => (pattern.clone(), None),
+                Some(relative) => {
+                    let Some(pattern) = relative.get("pattern").and_then(Value::as_str) else {
+                        continue;
+                    };
+                    // `baseUri` is a `WorkspaceFolder` or a plain URI.
+                    let base_uri = relative.get("baseUri").and_then(|base| {
+                        base.as_str()
+                            .or_else(|| base.get("uri").and_then(Value::as_str))
+                            .map(str::to_string)
+                    });
+                    (pattern.to_string(), base_uri)
+                }
+                None => continue,
+            };
+            out.push(LspFileWatcher {
+                registration_id: id.to_string(),
+                glob_pattern,
+                base_uri,
+                kind,
+            });
+        }
+    }
+    out
+}
+
+/// Registration ids from `client/unregisterCapability` params (`unregisterations`, sic).
+pub fn unregistration_ids(params: &Value) -> Vec<String> {
+    params
+        .get("unregisterations")
+        .or_else(|| params.get("unregistrations"))
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.get("id").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// One item of a `workspace/configuration` request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LspConfigurationItem {
+    /// Scope (a document or folder URI) the settings are requested for.
+    pub scope_uri: Option<String>,
+    /// Settings section, e.g. `rust-analyzer`; `None` asks for all settings.
+    pub section: Option<String>,
+}
+
+/// Parse the `items` of `workspace/configuration` params.
+pub fn configuration_items_from_value(params: &Value) -> Vec<LspConfigurationItem> {
+    params
+        .get("items")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| LspConfigurationItem {
+                    scope_uri: item
+                        .get("scopeUri")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    section: item
+                        .get("section")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,131 @@
+//! Streaming `workspace/symbol` queries.
+//!
+//! Workspace symbol searches can be slow on large projects. [`WorkspaceSymbolQuery`] sends the
+//! request with a `partialResultToken`, so servers that support partial results stream batches
+//! of symbols through `$/progress` before the final response. Like
+//! [`crate::CompletionSession`], the query is fed by the host: pass every [`LspEvent`] from
+//! [`crate::LspSession::drain_events`] to [`WorkspaceSymbolQuery::handle_event`], and render
+//! [`WorkspaceSymbolQuery::take_new_symbols`] as they arrive.
+//!
+//! Dropping an unfinished query cancels the request (`$/cancelRequest`).
+
+use crate::lsp_client::LspCancelHandle;
+use crate::lsp_events::{LspEvent, LspNotification};
+use crate::lsp_symbols::lsp_workspace_symbols_to_results;
+use editor_core::WorkspaceSymbol;
+use serde_json::Value;
+
+/// An in-flight `workspace/symbol` request and the symbols received so far.
+#[derive(Debug)]
+pub struct WorkspaceSymbolQuery {
+    request_id: u64,
+    partial_result_token: Value,
+    symbols: Vec<WorkspaceSymbol>,
+    taken: usize,
+    done: bool,
+    error: Option<String>,
+    canceller: Option<LspCancelHandle>,
+}
+
+impl WorkspaceSymbolQuery {
+    /// Track the `workspace/symbol` request `request_id`, sent with `partial_result_token`.
+    ///
+    /// `canceller` (if any) is used to cancel the request when the query is cancelled or
+    /// dropped before it finishes.
+    pub fn new(
+        request_id: u64,
+        partial_result_token: Value,
+        canceller: Option<LspCancelHandle>,
+    ) -> Self {
+        Self {
+            request_id,
+            partial_result_token,
+            symbols: Vec::new(),
+            taken: 0,
+            done: false,
+            error: None,
+            canceller,
+        }
+    }
+
+    /// JSON-RPC id of the request.
+    pub fn request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    /// The `partialResultToken` sent with the request.
+    pub fn partial_result_token(&self) -> &Value {
+        &self.partial_result_token
+    }
+
+    /// All symbols received so far, in arrival order.
+    pub fn symbols(&self) -> &[WorkspaceSymbol] {
+        &self.symbols
+    }
+
+    /// Symbols received since the previous call.
+    pub fn take_new_symbols(&mut self) -> Vec<WorkspaceSymbol> {
+        let new = self.symbols[self.taken..].to_vec();
+        self.taken = self.symbols.len();
+        new
+    }
+
+    /// Returns `true` once the final response arrived (or the query was cancelled).
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Error message of a failed request.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Feed an event to the query.
+    ///
+    /// Returns `true` if the event belonged to this query: a `$/progress` notification carrying
+    /// partial results for its token, or the final response.
+    pub fn handle_event(&mut self, event: &LspEvent) -> bool {
+        if self.done {
+            return false;
+        }
+        match event {
+            LspEvent::Notification(LspNotification::Progress(progress))
+                if progress.token == self.partial_result_token =>
+            {
+                self.symbols
+                    .extend(lsp_workspace_symbols_to_results(&progress.value));
+                true
+            }
+            LspEvent::Response(response) if response.id == self.request_id => {
+                if let Some(error) = &response.error {
+                    self.error = Some(error.message.clone());
+                } else if let Some(result) = &response.result {
+                    // With partial results the final response is usually empty.
+                    self.symbols
+                        .extend(lsp_workspace_symbols_to_results(result));
+                }
+                self.done = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancel the request (`$/cancelRequest`) unless it already finished.
+    pub fn cancel(&mut self) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        if let Some(canceller) = &self.canceller {
+            // A closed connection has nothing left to cancel.
+            let _ = canceller.cancel();
+        }
+    }
+}
+
+impl Drop for WorkspaceSymbolQuery {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -9,9 +9,13 @@
 //! - `WorkspaceEdit` payloads can be applied across multiple open documents
 
 use crate::editor::{LspContentChange, LspDocument, LspSession, LspSessionStartOptions};
+use crate::lsp_code_actions::{CodeActionItem, LspCommand};
 use crate::lsp_events::LspNotification;
 use crate::lsp_sync::{DeltaCalculator, TextChange};
-use crate::lsp_text_edits::{LspTextEdit, char_offsets_for_lsp_range, workspace_edit_text_edits};
+use crate::lsp_text_edits::{
+    LspTextEdit, char_offsets_for_lsp_range, workspace_edit_text_edits_for_uri,
+};
+use crate::lsp_uri::normalize_file_uri;
 use editor_core::{BufferId, LineIndex, TextDelta, TextEditSpec, Workspace};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -21,8 +25,12 @@ use std::collections::HashMap;
 pub struct ApplyWorkspaceEditResult {
     /// Documents that were successfully edited.
     pub applied: Vec<AppliedWorkspaceEditDocument>,
-    /// URIs that had edits but were not open in the workspace.
-    pub skipped_uris: Vec<String>,
+    /// Documents left untouched because the version requested by `documentChanges` did not match
+    /// the version tracked by the LSP session.
+    pub version_mismatches: Vec<WorkspaceEditVersionMismatch>,
+    /// Documents that had edits but are not open in the workspace. These need external
+    /// application by the host (e.g. editing the file on disk, or opening it first).
+    pub external: Vec<ExternalWorkspaceEditDocument>,
 }
 
 /// Per-document result for applying a `WorkspaceEdit`.
@@ -36,6 +44,35 @@ pub struct AppliedWorkspaceEditDocument {
     pub lsp_changes: Vec<LspContentChange>,
 }
 
+/// Result of applying a code action across a workspace.
+#[derive(Debug, Clone)]
+pub struct AppliedCodeAction {
+    /// Result of applying the action's edit, if it had one.
+    pub edit: Option<ApplyWorkspaceEditResult>,
+    /// Command the host should run next with [`LspSession::execute_command`], if any.
+    pub command: Option<LspCommand>,
+}
+
+/// A document whose `WorkspaceEdit` entry was rejected because of a stale version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceEditVersionMismatch {
+    /// Document URI.
+    pub uri: String,
+    /// Version the edit was computed against.
+    pub expected_version: i32,
+    /// Version currently tracked by the LSP session.
+    pub current_version: i32,
+}
+
+/// Edits for a document that is not open in the workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalWorkspaceEditDocument {
+    /// Document URI.
+    pub uri: String,
+    /// Edits to apply, in server order.
+    pub edits: Vec<LspTextEdit>,
+}
+
 /// A small helper that wires an [`LspSession`] to an [`editor_core::Workspace`].
 pub struct LspWorkspaceSync {
     session: LspSession,
@@ -196,7 +233,11 @@ impl LspWorkspaceSync {
                 format!("Workspace buffer not found (id={}): {:?}", id.get(), err)
             })?;
             let line_index = LineIndex::from_text(&text);
-            let edits = crate::editor::lsp_diagnostics_to_processing_edits(&line_index, &diags);
+            let edits = crate::editor::lsp_diagnostics_to_processing_edits_for(
+                &line_index,
+                &diags,
+                self.session.output(),
+            );
             workspace
                 .apply_processing_edits(id, edits)
                 .map_err(|err| format!("apply diagnostics edits 失败: {:?}", err))?;
@@ -241,75 +282,202 @@ impl LspWorkspaceSync {
 
     /// Apply an LSP `WorkspaceEdit` to all matching open documents in the workspace.
     ///
-    /// This is a best-effort helper:
-    /// - text edits are applied for any `uri` that is already open in the workspace
-    /// - unknown URIs are reported in [`ApplyWorkspaceEditResult::skipped_uris`]
+    /// See [`apply_workspace_edit_to_workspace`]; versions are checked against the documents
+    /// tracked by this session, and the sync state of edited documents is kept up to date.
     pub fn apply_workspace_edit(
         &mut self,
         workspace: &mut Workspace,
         workspace_edit: &Value,
     ) -> Result<ApplyWorkspaceEditResult, String> {
-        let by_uri = workspace_edit_text_edits(workspace_edit);
-
-        let mut applied = Vec::<AppliedWorkspaceEditDocument>::new();
-        let mut skipped = Vec::<String>::new();
-
-        for (uri, edits) in by_uri {
-            let Some(id) = workspace.buffer_id_for_uri(&uri) else {
-                skipped.push(uri);
-                continue;
-            };
-            let text = workspace.buffer_text(id).map_err(|err| {
-                format!("Workspace buffer not found (id={}): {:?}", id.get(), err)
-            })?;
-            let line_index = LineIndex::from_text(&text);
-
-            let lsp_changes = lsp_changes_for_text_edits(&line_index, &edits);
-
-            let mut specs: Vec<TextEditSpec> = edits
-                .iter()
-                .map(|edit| {
-                    let (start, end) = char_offsets_for_lsp_range(&line_index, &edit.range);
-                    TextEditSpec {
-                        start,
-                        end,
-                        text: edit.new_text.clone(),
-                    }
-                })
-                .collect();
-            let mut changed_char_ranges: Vec<(usize, usize)> =
-                specs.iter().map(|e| (e.start, e.end)).collect();
-
-            // Match the application order (descending start offsets) for highlighting stability.
-            changed_char_ranges.sort_by_key(|(start, _)| std::cmp::Reverse(*start));
-            specs.sort_by_key(|e| std::cmp::Reverse(e.start));
-
-            workspace
-                .apply_text_edits(vec![(id, specs)])
-                .map_err(|err| format!("apply workspace edit 失败: {:?}", err))?;
+        let session = &self.session;
+        let result = apply_workspace_edit_to_workspace(workspace, workspace_edit, |uri| {
+            session.document_for_uri(uri).map(|doc| doc.version)
+        })?;
 
-            // Keep our incremental calculator in sync with the applied edit.
-            if let Some(calc) = self.calculators.get_mut(&uri) {
-                for change in &lsp_changes {
+        // Keep our incremental calculators in sync with the applied edits.
+        for doc in &result.applied {
+            if let Some(calc) = self.calculators.get_mut(&doc.uri) {
+                for change in &doc.lsp_changes {
                     calc.apply_change(&TextChange {
                         range: change.range,
                         text: change.text.clone(),
                     });
                 }
             }
+        }
+
+        Ok(result)
+    }
+
+    /// Apply a code action across the workspace.
+    ///
+    /// The action is resolved first if it has no edit yet (see
+    /// [`LspSession::apply_code_action`]); its edit goes through
+    /// [`LspWorkspaceSync::apply_workspace_edit`].
+    pub fn apply_code_action(
+        &mut self,
+        workspace: &mut Workspace,
+        item: &CodeActionItem,
+    ) -> Result<AppliedCodeAction, String> {
+        let (edit, command) = self.session.code_action_parts(item)?;
+        let edit = match edit {
+            Some(edit) => Some(self.apply_workspace_edit(workspace, &edit)?),
+            None => None,
+        };
+        Ok(AppliedCodeAction { edit, command })
+    }
+}
+
+/// Look up the open buffer for a server `uri`, comparing `file://` URIs in normalized form on
+/// both sides (buffers may have been opened with a non-canonical uri too).
+fn buffer_id_for_lsp_uri(workspace: &Workspace, uri: &str) -> Option<BufferId> {
+    if let Some(id) = workspace.buffer_id_for_uri(uri) {
+        return Some(id);
+    }
+    let normalized = normalize_file_uri(uri);
+    workspace
+        .buffer_uris()
+        .find(|(open_uri, _)| normalize_file_uri(open_uri) == normalized)
+        .map(|(_, id)| id)
+}
+
+/// Apply an LSP `WorkspaceEdit` to the open documents of a workspace.
+///
+/// This is a best-effort helper, applied document by document:
+/// - text edits (from `changes` and `documentChanges`) are applied to any `uri` that is open in
+///   the workspace, one undo step per document; `file://` URIs also match when they only differ
+///   in percent-encoding, on either side (see [`normalize_file_uri`])
+/// - when a `documentChanges` entry names a version and `document_version` knows the document,
+///   a mismatch leaves that document untouched and is reported in
+///   [`ApplyWorkspaceEditResult::version_mismatches`]
+/// - edits for URIs that are not open are returned in [`ApplyWorkspaceEditResult::external`]
+///
+/// `document_version` returns the current LSP version of a document (typically
+/// [`LspSession::document_for_uri`]). Resource operations (`create`, `rename`, `delete`) are
+/// ignored.
+pub fn apply_workspace_edit_to_workspace(
+    workspace: &mut Workspace,
+    workspace_edit: &Value,
+    document_version: impl Fn(&str) -> Option<i32>,
+) -> Result<ApplyWorkspaceEditResult, String> {
+    let mut result = ApplyWorkspaceEditResult {
+        applied: Vec::new(),
+        version_mismatches: Vec::new(),
+        external: Vec::new(),
+    };
+
+    for (uri, expected_version) in workspace_edit_documents(workspace_edit) {
+        let edits = workspace_edit_text_edits_for_uri(workspace_edit, &uri);
+        if edits.is_empty() {
+            continue;
+        }
+        let Some(id) = buffer_id_for_lsp_uri(workspace, &uri) else {
+            result
+                .external
+                .push(ExternalWorkspaceEditDocument { uri, edits });
+            continue;
+        };
+        // Report (and look up versions) by the buffer's own uri, which is what the session tracks.
+        let uri = workspace
+            .buffer_metadata(id)
+            .and_then(|meta| meta.uri.clone())
+            .unwrap_or(uri);
+        if let Some(expected_version) = expected_version
+            && let Some(current_version) = document_version(&uri)
+            && current_version != expected_version
+        {
+            result
+                .version_mismatches
+                .push(WorkspaceEditVersionMismatch {
+                    uri,
+                    expected_version,
+                    current_version,
+                });
+            continue;
+        }
+
+        let text = workspace
+            .buffer_text(id)
+            .map_err(|err| format!("Workspace buffer not found (id={}): {:?}", id.get(), err))?;
+        let line_index = LineIndex::from_text(&text);
+
+        let lsp_changes = lsp_changes_for_text_edits(&line_index, &edits);
+
+        let mut specs: Vec<TextEditSpec> = edits
+            .iter()
+            .map(|edit| {
+                let (start, end) = char_offsets_for_lsp_range(&line_index, &edit.range);
+                TextEditSpec {
+                    start,
+                    end,
+                    text: edit.new_text.clone(),
+                }
+            })
+            .collect();
+        let mut changed_char_ranges: Vec<(usize, usize)> =
+            specs.iter().map(|e| (e.start, e.end)).collect();
+
+        // Match the application order (descending start offsets) for highlighting stability.
+        changed_char_ranges.sort_by_key(|(start, _)| std::cmp::Reverse(*start));
+        specs.sort_by_key(|e| std::cmp::Reverse(e.start));
+
+        workspace
+            .apply_text_edits(vec![(id, specs)])
+            .map_err(|err| format!("apply workspace edit 失败: {:?}", err))?;
+
+        result.applied.push(AppliedWorkspaceEditDocument {
+            uri,
+            changed_char_ranges,
+            lsp_changes,
+        });
+    }
+
+    Ok(result)
+}
+
+/// Documents referenced by a `WorkspaceEdit` (in order of appearance), with the version requested
+/// by `documentChanges` if any.
+fn workspace_edit_documents(workspace_edit: &Value) -> Vec<(String, Option<i32>)> {
+    let mut out = Vec::<(String, Option<i32>)>::new();
+    let mut add = |uri: &str, version: Option<i32>| match out
+        .iter_mut()
+        .find(|(existing, _)| existing == uri)
+    {
+        Some((_, existing_version)) => {
+            if existing_version.is_none() {
+                *existing_version = version;
+            }
+        }
+        None => out.push((uri.to_string(), version)),
+    };
 
-            applied.push(AppliedWorkspaceEditDocument {
-                uri,
-                changed_char_ranges,
-                lsp_changes,
-            });
+    if let Some(changes) = workspace_edit.get("changes").and_then(Value::as_object) {
+        for uri in changes.keys() {
+            add(uri, None);
         }
+    }
 
-        Ok(ApplyWorkspaceEditResult {
-            applied,
-            skipped_uris: skipped,
-        })
+    if let Some(document_changes) = workspace_edit
+        .get("documentChanges")
+        .and_then(Value::as_array)
+    {
+        for change in document_changes {
+            // TextDocumentEdit: { textDocument: { uri, version? }, edits: [...] }
+            let Some(text_document) = change.get("textDocument") else {
+                continue;
+            };
+            let Some(uri) = text_document.get("uri").and_then(Value::as_str) else {
+                continue;
+            };
+            let version = text_document
+                .get("version")
+                .and_then(Value::as_i64)
+                .and_then(|version| i32::try_from(version).ok());
+            add(uri, version);
+        }
     }
+
+    out
 }
 
 fn position_for_char_offset(calc: &DeltaCalculator, mut offset: usize) -> (usize, usize) {
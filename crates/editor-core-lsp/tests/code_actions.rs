@@ -0,0 +1,158 @@
+use editor_core::{Diagnostic, DiagnosticRange, DiagnosticSeverity, LineIndex};
+use editor_core_lsp::{
+    CodeActionItem, LspCommand, LspDiagnosticSeverity, code_action_context,
+    code_action_items_from_value, code_actions_from_value, preferred_quick_fix,
+};
+use serde_json::json;
+
+fn diagnostic(start: usize, end: usize, severity: DiagnosticSeverity, code: &str) -> Diagnostic {
+    Diagnostic {
+        range: DiagnosticRange::new(start, end),
+        severity: Some(severity),
+        code: Some(code.to_string()),
+        source: Some("test".to_string()),
+        provider: None,
+        message: format!("problem {}", code),
+        code_description_href: None,
+        tags: Vec::new(),
+        related: Vec::new(),
+        related_information_json: None,
+        data_json: Some(r#"{"fix":1}"#.to_string()),
+    }
+}
+
+#[test]
+fn test_code_action_context_keeps_overlapping_diagnostics() {
+    // Line 1 starts at char 11; the emoji takes two UTF-16 code units.
+    let line_index = LineIndex::from_text("let 😀 = 1;\nfoo(bar);");
+    let diagnostics = vec![
+        diagnostic(0, 3, DiagnosticSeverity::Warning, "W1"),
+        diagnostic(11, 14, DiagnosticSeverity::Error, "308"),
+        diagnostic(15, 18, DiagnosticSeverity::Hint, "H1"),
+    ];
+
+    // A caret right after `foo` touches only the second diagnostic.
+    let context = code_action_context(&line_index, 14, 14, &diagnostics, Some(&["quickfix"]));
+    assert_eq!(
+        context,
+        json!({
+            "diagnostics": [{
+                "range": {
+                    "start": { "line": 1, "character": 0 },
+                    "end": { "line": 1, "character": 3 }
+                },
+                "message": "problem 308",
+                "severity": 1,
+                "code": 308,
+                "source": "test",
+                "data": { "fix": 1 }
+            }],
+            "triggerKind": 1,
+            "only": ["quickfix"]
+        })
+    );
+
+    let context = code_action_context(&line_index, 0, 18, &diagnostics, None);
+    let diags = context["diagnostics"].as_array().unwrap();
+    assert_eq!(diags.len(), 3);
+    assert_eq!(diags[0]["code"], json!("W1"));
+    assert_eq!(diags[0]["severity"], json!(2));
+    assert!(context.get("only").is_none());
+}
+
+#[test]
+fn test_code_action_items_mix_commands_and_actions() {
+    let items = code_action_items_from_value(&json!([
+        { "title": "Run test", "command": "test.run", "arguments": [1] },
+        { "title": "Extract function", "kind": "refactor.extract", "data": 5 },
+        {
+            "title": "Import `HashMap`",
+            "kind": "quickfix",
+            "edit": { "changes": {} },
+            "command": { "title": "", "command": "cursor.move" }
+        },
+        { "title": "Remove unused", "kind": "quickfix", "isPreferred": true, "disabled": { "reason": "busy" } },
+        { "title": "Add `mut`", "kind": "quickfix.add", "isPreferred": true },
+    ]));
+    assert_eq!(items.len(), 5);
+
+    assert_eq!(
+        items[0],
+        CodeActionItem::Command(LspCommand {
+            title: "Run test".to_string(),
+            command: "test.run".to_string(),
+            arguments: vec![json!(1)],
+        })
+    );
+    let CodeActionItem::Action(import) = &items[2] else {
+        panic!("expected a code action");
+    };
+    assert_eq!(import.edit, Some(json!({ "changes": {} })));
+    assert_eq!(import.command.as_ref().unwrap().command, "cursor.move");
+
+    let CodeActionItem::Action(extract) = &items[1] else {
+        panic!("expected a code action");
+    };
+    assert_eq!(extract.edit, None);
+    assert_eq!(extract.raw["data"], json!(5));
+    assert!(!items[1].is_quick_fix());
+
+    // The disabled preferred fix is skipped in favour of the enabled preferred one.
+    assert!(items[3].is_disabled());
+    assert_eq!(preferred_quick_fix(&items).unwrap().title(), "Add `mut`");
+    assert_eq!(
+        preferred_quick_fix(&items[..3]).unwrap().title(),
+        "Import `HashMap`"
+    );
+    assert!(code_action_items_from_value(&json!(null)).is_empty());
+}
+
+#[test]
+fn test_code_actions_flat_list_with_diagnostics_and_resolve() {
+    let actions = code_actions_from_value(&json!([
+        { "title": "Run test", "command": "test.run" },
+        {
+            "title": "Remove unused import",
+            "kind": "quickfix",
+            "diagnostics": [{
+                "range": {
+                    "start": { "line": 0, "character": 4 },
+                    "end": { "line": 0, "character": 9 }
+                },
+                "severity": 2,
+                "code": "unused_imports",
+                "message": "unused import"
+            }],
+            "data": { "id": 1 }
+        },
+        {
+            "title": "Fill match arms",
+            "kind": "quickfix",
+            "edit": { "changes": {} },
+            "disabled": { "reason": "no match" }
+        },
+        {
+            "title": "Organize imports",
+            "kind": "source.organizeImports",
+            "command": { "title": "Organize", "command": "imports.organize" }
+        },
+    ]));
+    assert_eq!(actions.len(), 4);
+
+    assert_eq!(actions[0].command.as_ref().unwrap().command, "test.run");
+    assert_eq!(actions[0].kind, None);
+    assert!(!actions[0].needs_resolve());
+
+    let unused = &actions[1];
+    assert_eq!(unused.diagnostics.len(), 1);
+    assert_eq!(
+        unused.diagnostics[0].severity,
+        Some(LspDiagnosticSeverity::Warning)
+    );
+    assert_eq!(unused.diagnostics[0].range.end.character, 9);
+    assert!(unused.needs_resolve());
+
+    assert_eq!(actions[2].disabled_reason.as_deref(), Some("no match"));
+    assert!(!actions[2].needs_resolve());
+    assert!(!actions[3].needs_resolve());
+}
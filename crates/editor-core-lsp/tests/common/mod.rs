@@ -0,0 +1,143 @@
+//! Fake language servers shared by the integration tests.
+//!
+//! The "servers" are small `sh` scripts that write canned JSON-RPC frames; some also record what
+//! the client sends to a log file. Transport-agnostic managers are driven by [`FakeClient`]
+//! instead. Each test file uses a subset of these helpers.
+
+#![allow(dead_code)]
+
+use editor_core_lsp::{
+    LspDocument, LspRequestSender, LspRestartPolicy, LspSession, LspSessionStartOptions,
+};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Records requests instead of talking to a server.
+#[derive(Default)]
+pub struct FakeClient {
+    /// Id of the last request sent.
+    pub next_id: u64,
+    /// `(id, method, params)` of every request sent.
+    pub requests: Vec<(u64, String, Value)>,
+    /// Ids passed to `cancel_request`.
+    pub canceled: Vec<u64>,
+}
+
+impl LspRequestSender for FakeClient {
+    fn send_request(&mut self, method: &str, params: Value) -> Result<u64, String> {
+        self.next_id += 1;
+        self.requests
+            .push((self.next_id, method.to_string(), params));
+        Ok(self.next_id)
+    }
+
+    fn cancel_request(&mut self, request_id: u64) -> Result<(), String> {
+        self.canceled.push(request_id);
+        Ok(())
+    }
+}
+
+/// Frame JSON-RPC messages the way a server writes them to stdout.
+pub fn frame(messages: &[Value]) -> String {
+    messages
+        .iter()
+        .map(|message| {
+            let body = message.to_string();
+            format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+        })
+        .collect()
+}
+
+/// Parse every framed message in `raw`, e.g. a log of what the client wrote.
+pub fn unframe(raw: &str) -> Vec<Value> {
+    raw.split("Content-Length:")
+        .filter_map(|part| part.split_once("\r\n\r\n"))
+        .filter_map(|(_, body)| serde_json::from_str(body).ok())
+        .collect()
+}
+
+/// The response to `initialize` (request id 1) advertising `capabilities`.
+pub fn initialize_response(capabilities: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": 1, "result": { "capabilities": capabilities } })
+}
+
+/// A "server" that echoes every message back. A session started on it gets its own
+/// `initialize` request as the answer, so the server advertises no capabilities.
+pub fn echo_command() -> Command {
+    let mut cmd = Command::new("cat");
+    cmd.stderr(Stdio::null());
+    cmd
+}
+
+/// A "server" that waits `delay_secs`, writes `output` (e.g. from [`frame`]) and then ignores
+/// its input.
+pub fn scripted_command(delay_secs: &str, output: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg("sleep \"$1\"; printf '%s' \"$2\"; cat > /dev/null")
+        .arg("sh")
+        .arg(delay_secs)
+        .arg(output)
+        .stderr(Stdio::null());
+    cmd
+}
+
+/// A "server" that writes `messages`, records what the client sends to `log`, and writes each
+/// `(marker, reply)` reply once `marker` shows up in the log.
+pub fn scripted_replies_command(
+    messages: &[Value],
+    log: &Path,
+    replies: &[(&str, Value)],
+) -> Command {
+    let script = "printf '%s' \"$1\"; log=$2; shift 2; exec 3<&0; cat <&3 > \"$log\" & \
+        while [ $# -gt 0 ]; do \
+          until grep -qF -- \"$1\" \"$log\" 2>/dev/null; do sleep 0.01; done; \
+          printf '%s' \"$2\"; shift 2; \
+        done; wait";
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(script)
+        .arg("sh")
+        .arg(frame(messages))
+        .arg(log);
+    for (marker, reply) in replies {
+        cmd.arg(marker).arg(frame(std::slice::from_ref(reply)));
+    }
+    cmd.stderr(Stdio::null());
+    cmd
+}
+
+/// A log file in the temp dir for `name`, removed if a previous run left it behind.
+pub fn log_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "editor-core-lsp-{}-{}.log",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Options starting `cmd` for the Rust document `file:///a.rs` containing `text`.
+pub fn start_options(cmd: Command, text: &str) -> LspSessionStartOptions {
+    LspSessionStartOptions {
+        cmd,
+        workspace_folders: Vec::new(),
+        initialize_params: json!({}),
+        initialize_timeout: Duration::from_secs(5),
+        document: LspDocument {
+            uri: "file:///a.rs".to_string(),
+            language_id: "rust".to_string(),
+            version: 1,
+        },
+        initial_text: text.to_string(),
+        restart_policy: LspRestartPolicy::default(),
+    }
+}
+
+/// Start a session with [`start_options`].
+pub fn start(cmd: Command, text: &str) -> LspSession {
+    LspSession::start(start_options(cmd, text)).expect("start session")
+}
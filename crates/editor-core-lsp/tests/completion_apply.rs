@@ -1,4 +1,7 @@
-use editor_core::{Command, CursorCommand, EditCommand, EditorStateManager, Position};
+use editor_core::{
+    Command, CompletionAcceptMode, CursorCommand, EditCommand, EditorStateManager, Position,
+    completion_edit_at_caret,
+};
 use editor_core_lsp::{CompletionTextEditMode, apply_completion_item};
 use serde_json::json;
 
@@ -41,6 +44,58 @@ fn test_apply_completion_item_groups_edits_into_single_undo_step() {
     assert_eq!(state.editor().get_text(), original);
 }
 
+#[test]
+fn test_apply_snippet_completion_returns_tabstops_in_tab_order() {
+    let mut state = EditorStateManager::new("fn main() {\n    fo\n}\n", 80);
+    let item = json!({
+        "label": "for",
+        "insertTextFormat": 2,
+        "textEdit": {
+            "range": { "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 6 } },
+            "newText": "for ${1:item} in ${2:${3:iter}.rev()} {\n\t$0\n}"
+        },
+        "additionalTextEdits": [
+            {
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+                "newText": "use std::io;\n"
+            }
+        ]
+    });
+
+    let tabstops =
+        apply_completion_item(&mut state, &item, CompletionTextEditMode::Insert).unwrap();
+    let text = state.editor().get_text();
+    assert_eq!(
+        text,
+        "use std::io;\nfn main() {\n    for item in iter.rev() {\n\t\n}\n}\n"
+    );
+
+    // Offsets are post-edit: the import shifts the snippet by 13 chars.
+    let stops: Vec<(usize, usize, usize)> =
+        tabstops.iter().map(|t| (t.index, t.start, t.end)).collect();
+    assert_eq!(
+        stops,
+        vec![(1, 33, 37), (2, 41, 51), (3, 41, 45), (0, 55, 55)]
+    );
+    let slice = |start: usize, end: usize| {
+        text.chars()
+            .skip(start)
+            .take(end - start)
+            .collect::<String>()
+    };
+    assert_eq!(slice(33, 37), "item");
+    assert_eq!(slice(41, 51), "iter.rev()");
+
+    // Plain-text items have no tabstops.
+    let tabstops = apply_completion_item(
+        &mut state,
+        &json!({ "label": "x", "insertText": "$1" }),
+        CompletionTextEditMode::Insert,
+    )
+    .unwrap();
+    assert!(tabstops.is_empty());
+}
+
 #[test]
 fn test_apply_completion_item_falls_back_to_insert_text_over_selection() {
     let mut state = EditorStateManager::new("hello world\n", 80);
@@ -60,3 +115,44 @@ fn test_apply_completion_item_falls_back_to_insert_text_over_selection() {
     apply_completion_item(&mut state, &item, CompletionTextEditMode::Insert).unwrap();
     assert_eq!(state.editor().get_text(), "hello earth\n");
 }
+
+#[test]
+fn test_apply_completion_item_without_text_edit_matches_buffer_word_completion() {
+    let at_caret = || {
+        let mut state = EditorStateManager::new("x.get_value()\n", 80);
+        state
+            .execute(Command::Cursor(CursorCommand::MoveTo {
+                line: 0,
+                column: 5,
+            }))
+            .unwrap();
+        state
+    };
+
+    for (mode, accept, expected) in [
+        (
+            CompletionTextEditMode::Insert,
+            CompletionAcceptMode::Insert,
+            "x.get_name_value()\n",
+        ),
+        (
+            CompletionTextEditMode::Replace,
+            CompletionAcceptMode::Replace,
+            "x.get_name()\n",
+        ),
+    ] {
+        let mut lsp = at_caret();
+        apply_completion_item(&mut lsp, &json!({ "label": "get_name" }), mode).unwrap();
+
+        let mut words = at_caret();
+        let edit = completion_edit_at_caret(words.editor(), "get_name", accept);
+        words
+            .execute(Command::Edit(EditCommand::ApplyTextEdits {
+                edits: vec![edit],
+            }))
+            .unwrap();
+
+        assert_eq!(lsp.editor().get_text(), expected);
+        assert_eq!(words.editor().get_text(), expected);
+    }
+}
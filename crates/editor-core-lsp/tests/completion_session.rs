@@ -0,0 +1,168 @@
+mod common;
+
+use common::FakeClient;
+use editor_core::{Command, CursorCommand, EditCommand, EditorStateManager};
+use editor_core_lsp::{
+    CompletionSession, CompletionTextEditMode, LspResponse, completion_item_label,
+};
+use serde_json::{Value, json};
+
+fn response(id: u64, method: &str, result: Value) -> LspResponse {
+    LspResponse {
+        id,
+        method: method.to_string(),
+        result: Some(result),
+        error: None,
+    }
+}
+
+fn labels(session: &CompletionSession) -> Vec<&str> {
+    session.items().map(completion_item_label).collect()
+}
+
+fn type_text(state: &mut EditorStateManager, text: &str) {
+    state
+        .execute(Command::Edit(EditCommand::InsertText {
+            text: text.to_string(),
+        }))
+        .unwrap();
+}
+
+fn state_at(text: &str, line: usize, column: usize) -> EditorStateManager {
+    let mut state = EditorStateManager::new(text, 80);
+    state
+        .execute(Command::Cursor(CursorCommand::MoveTo { line, column }))
+        .unwrap();
+    state
+}
+
+#[test]
+fn test_trigger_filters_locally_and_accepts() {
+    let mut state = state_at("let 😀 = pr", 0, 10);
+    let mut client = FakeClient::default();
+    let mut session = CompletionSession::new("file:///a.rs");
+
+    let id = session.trigger(&mut client, &state).unwrap();
+    let (_, method, params) = &client.requests[0];
+    assert_eq!(method, "textDocument/completion");
+    // The emoji takes two UTF-16 code units.
+    assert_eq!(params["position"], json!({ "line": 0, "character": 11 }));
+    assert_eq!(params["context"]["triggerKind"], json!(1));
+    assert_eq!(session.prefix(), "pr");
+
+    assert!(session.handle_response(&response(
+        id,
+        "textDocument/completion",
+        json!([
+            { "label": "print" },
+            { "label": "println" },
+            { "label": "parse_range", "sortText": "0" },
+            { "label": "Vec" },
+        ]),
+    )));
+    assert!(!session.is_incomplete());
+    assert_eq!(labels(&session), vec!["print", "println", "parse_range"]);
+
+    // Typing narrows the list without another request.
+    type_text(&mut state, "intl");
+    assert!(session.update(&mut client, &state).unwrap());
+    assert_eq!(client.requests.len(), 1);
+    assert_eq!(labels(&session), vec!["println"]);
+
+    session
+        .accept(&mut state, 0, CompletionTextEditMode::Insert)
+        .unwrap();
+    assert_eq!(state.editor().get_text(), "let 😀 = println");
+    assert!(!session.is_active());
+}
+
+#[test]
+fn test_incomplete_lists_are_re_requested() {
+    let mut state = state_at("fo", 0, 2);
+    let mut client = FakeClient::default();
+    let mut session = CompletionSession::new("file:///a.rs");
+
+    let first = session.trigger(&mut client, &state).unwrap();
+    type_text(&mut state, "o");
+    session.update(&mut client, &state).unwrap();
+    // No list has arrived yet, so there is nothing incomplete to re-request.
+    assert_eq!(client.requests.len(), 1);
+
+    session.handle_response(&response(
+        first,
+        "textDocument/completion",
+        json!({ "isIncomplete": true, "items": [{ "label": "foo" }, { "label": "fob" }] }),
+    ));
+    assert!(session.is_incomplete());
+    assert_eq!(labels(&session), vec!["foo"]);
+
+    type_text(&mut state, "b");
+    session.update(&mut client, &state).unwrap();
+    assert_eq!(client.requests.len(), 2);
+    let (second, _, params) = &client.requests[1];
+    assert_eq!(params["context"]["triggerKind"], json!(3));
+    assert_eq!(session.pending_request(), Some(*second));
+
+    // Responses to superseded requests are ignored.
+    assert!(!session.handle_response(&response(first, "textDocument/completion", json!([]))));
+    session.handle_response(&response(
+        *second,
+        "textDocument/completion",
+        json!({ "isIncomplete": false, "items": [{ "label": "foobar", "filterText": "foob" }] }),
+    ));
+    assert_eq!(labels(&session), vec!["foobar"]);
+}
+
+#[test]
+fn test_superseded_pending_request_is_canceled() {
+    let mut state = state_at("fo", 0, 2);
+    let mut client = FakeClient::default();
+    let mut session = CompletionSession::new("file:///a.rs");
+
+    let first = session.trigger(&mut client, &state).unwrap();
+    assert!(client.canceled.is_empty());
+    type_text(&mut state, "o");
+    let second = session.trigger(&mut client, &state).unwrap();
+    assert_eq!(client.canceled, vec![first]);
+    assert_eq!(session.pending_request(), Some(second));
+
+    // Once answered, nothing is left to cancel.
+    session.handle_response(&response(second, "textDocument/completion", json!([])));
+    session.trigger(&mut client, &state).unwrap();
+    assert_eq!(client.canceled, vec![first]);
+}
+
+#[test]
+fn test_resolve_replaces_item_and_leaving_word_closes() {
+    let mut state = state_at("x.le", 0, 4);
+    let mut client = FakeClient::default();
+    let mut session = CompletionSession::new("file:///a.rs");
+
+    let id = session.trigger(&mut client, &state).unwrap();
+    session.handle_response(&response(
+        id,
+        "textDocument/completion",
+        json!([{ "label": "len", "data": 7 }]),
+    ));
+
+    let resolve_id = session.resolve(&mut client, 0).unwrap();
+    let (_, method, params) = &client.requests[1];
+    assert_eq!(method, "completionItem/resolve");
+    assert_eq!(params["data"], json!(7));
+
+    assert!(session.handle_response(&response(
+        resolve_id,
+        "completionItem/resolve",
+        json!({ "label": "len", "detail": "fn len(&self) -> usize", "insertText": "len()" }),
+    )));
+    assert_eq!(
+        session.item(0).unwrap()["detail"],
+        json!("fn len(&self) -> usize")
+    );
+
+    // Typing a non-word character ends the session.
+    type_text(&mut state, "(");
+    assert!(!session.update(&mut client, &state).unwrap());
+    assert!(!session.is_active());
+    assert!(session.is_empty());
+}
@@ -0,0 +1,279 @@
+mod common;
+
+use common::FakeClient;
+use editor_core::intervals::Interval;
+use editor_core::processing::ProcessingEdit;
+use editor_core::{DOCUMENT_HIGHLIGHT_READ_STYLE_ID, LineIndex};
+use editor_core_lsp::{
+    CursorHighlightManager, LspEvent, LspResponse, LspServerRestart, LspTimedOutRequest,
+};
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+
+const TEXT: &str = "let foo = 1;\nfoo + foo\nbar\n";
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+impl FakeClient {
+    /// Positions of the `textDocument/documentHighlight` requests, in order.
+    fn positions(&self) -> Vec<Value> {
+        self.requests
+            .iter()
+            .inspect(|(_, method, _)| assert_eq!(method, "textDocument/documentHighlight"))
+            .map(|(_, _, params)| params["position"].clone())
+            .collect()
+    }
+}
+
+fn highlight(line: u32, start: u32, end: u32) -> Value {
+    json!({
+        "range": {
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": end }
+        },
+        "kind": 2
+    })
+}
+
+fn response(id: u64, result: Value) -> LspEvent {
+    LspEvent::Response(LspResponse {
+        id,
+        method: "textDocument/documentHighlight".to_string(),
+        result: Some(result),
+        error: None,
+    })
+}
+
+fn foo_response(id: u64) -> LspEvent {
+    response(
+        id,
+        json!([highlight(0, 4, 7), highlight(1, 0, 3), highlight(1, 6, 9)]),
+    )
+}
+
+fn manager() -> CursorHighlightManager {
+    let mut manager = CursorHighlightManager::new("file:///a.rs");
+    manager.set_debounce(DEBOUNCE);
+    manager
+}
+
+fn replaced_intervals(edit: Option<ProcessingEdit>) -> Vec<Interval> {
+    match edit {
+        Some(ProcessingEdit::ReplaceStyleLayer { intervals, .. }) => intervals,
+        other => panic!("unexpected edit: {:?}", other),
+    }
+}
+
+fn is_clear(edit: Option<ProcessingEdit>) -> bool {
+    matches!(edit, Some(ProcessingEdit::ClearStyleLayer { .. }))
+}
+
+fn foo_highlights() -> Vec<Interval> {
+    vec![
+        Interval::new(4, 7, DOCUMENT_HIGHLIGHT_READ_STYLE_ID),
+        Interval::new(13, 16, DOCUMENT_HIGHLIGHT_READ_STYLE_ID),
+        Interval::new(19, 22, DOCUMENT_HIGHLIGHT_READ_STYLE_ID),
+    ]
+}
+
+#[test]
+fn test_cursor_highlights_are_debounced_and_cleared() {
+    let line_index = LineIndex::from_text(TEXT);
+    let mut client = FakeClient::default();
+    let mut highlights = manager();
+    let t0 = Instant::now();
+
+    // Nothing to request before the cursor is reported.
+    assert_eq!(
+        highlights.update(&mut client, &line_index, t0).unwrap(),
+        None
+    );
+
+    highlights.notify_cursor_moved(0, t0);
+    let t1 = t0 + Duration::from_millis(80);
+    assert_eq!(
+        highlights.update(&mut client, &line_index, t1).unwrap(),
+        None
+    );
+    // Moving again restarts the debounce: only the resting position is requested.
+    highlights.notify_cursor_moved(5, t1);
+    let t2 = t1 + Duration::from_millis(80);
+    assert_eq!(
+        highlights.update(&mut client, &line_index, t2).unwrap(),
+        None
+    );
+    assert!(client.requests.is_empty());
+
+    let id = highlights
+        .update(&mut client, &line_index, t1 + DEBOUNCE)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        client.positions(),
+        vec![json!({ "line": 0, "character": 5 })]
+    );
+    assert_eq!(
+        client.requests[0].2["textDocument"],
+        json!({ "uri": "file:///a.rs" })
+    );
+    assert_eq!(highlights.pending_request(), Some(id));
+
+    assert!(highlights.handle_event(&foo_response(id)));
+    assert_eq!(
+        replaced_intervals(highlights.take_processing_edit(&line_index)),
+        foo_highlights()
+    );
+    assert_eq!(highlights.ranges(), &[(4, 7), (13, 16), (19, 22)]);
+    assert!(highlights.take_processing_edit(&line_index).is_none());
+
+    // Moving within a highlighted range keeps the highlights and sends nothing.
+    let t3 = t2 + Duration::from_secs(1);
+    highlights.notify_cursor_moved(16, t3);
+    let later = t3 + Duration::from_secs(1);
+    assert_eq!(
+        highlights.update(&mut client, &line_index, later).unwrap(),
+        None
+    );
+    assert!(highlights.take_processing_edit(&line_index).is_none());
+    assert_eq!(client.requests.len(), 1);
+
+    // Leaving them clears them at once.
+    highlights.notify_cursor_moved(24, later);
+    assert!(highlights.ranges().is_empty());
+    assert!(highlights.has_highlights());
+    assert!(is_clear(highlights.take_processing_edit(&line_index)));
+    assert!(!highlights.has_highlights());
+}
+
+#[test]
+fn test_stale_cursor_highlight_responses_are_dropped() {
+    let line_index = LineIndex::from_text(TEXT);
+    let mut client = FakeClient::default();
+    let mut highlights = manager();
+    let t0 = Instant::now();
+
+    // Request 1 (for `bar`) goes out...
+    highlights.notify_cursor_moved(23, t0);
+    let first = highlights
+        .update(&mut client, &line_index, t0 + DEBOUNCE)
+        .unwrap()
+        .unwrap();
+    // ...but the cursor moves before its answer is read: the answer is stale.
+    let t1 = t0 + Duration::from_millis(200);
+    highlights.notify_cursor_moved(5, t1);
+    assert!(highlights.handle_event(&response(first, json!([highlight(2, 0, 3)]))));
+    assert!(highlights.take_processing_edit(&line_index).is_none());
+    assert!(highlights.ranges().is_empty());
+
+    let second = highlights
+        .update(&mut client, &line_index, t1 + DEBOUNCE)
+        .unwrap()
+        .unwrap();
+    // A response to a request that is no longer pending is not ours.
+    assert!(!highlights.handle_event(&foo_response(first)));
+    assert!(highlights.handle_event(&foo_response(second)));
+    assert_eq!(
+        replaced_intervals(highlights.take_processing_edit(&line_index)),
+        foo_highlights()
+    );
+
+    // An edit clears the shown highlights...
+    highlights.document_changed();
+    assert!(highlights.ranges().is_empty());
+    assert!(is_clear(highlights.take_processing_edit(&line_index)));
+
+    // ...and makes the answer to a request sent before it stale.
+    let t2 = t1 + Duration::from_secs(1);
+    highlights.notify_cursor_moved(5, t2);
+    let third = highlights
+        .update(&mut client, &line_index, t2 + DEBOUNCE)
+        .unwrap()
+        .unwrap();
+    highlights.document_changed();
+    assert!(highlights.handle_event(&foo_response(third)));
+    assert!(highlights.take_processing_edit(&line_index).is_none());
+    assert!(highlights.ranges().is_empty());
+    // The edit forgot the cursor: nothing is requested until it is reported again.
+    let later = t2 + Duration::from_secs(10);
+    assert_eq!(
+        highlights.update(&mut client, &line_index, later).unwrap(),
+        None
+    );
+    assert_eq!(client.requests.len(), 3);
+}
+
+#[test]
+fn test_superseded_request_is_canceled() {
+    let line_index = LineIndex::from_text(TEXT);
+    let mut client = FakeClient::default();
+    let mut highlights = manager();
+    let t0 = Instant::now();
+
+    highlights.notify_cursor_moved(23, t0);
+    let first = highlights
+        .update(&mut client, &line_index, t0 + DEBOUNCE)
+        .unwrap()
+        .unwrap();
+    let t1 = t0 + Duration::from_secs(1);
+    highlights.notify_cursor_moved(5, t1);
+    let second = highlights
+        .update(&mut client, &line_index, t1 + DEBOUNCE)
+        .unwrap()
+        .unwrap();
+    assert_eq!(client.canceled, vec![first]);
+    assert_eq!(highlights.pending_request(), Some(second));
+}
+
+#[test]
+fn test_timeout_and_restart_forget_the_pending_request() {
+    let line_index = LineIndex::from_text(TEXT);
+    let mut client = FakeClient::default();
+    let mut highlights = manager();
+    let t0 = Instant::now();
+
+    highlights.notify_cursor_moved(5, t0);
+    let first = highlights
+        .update(&mut client, &line_index, t0 + DEBOUNCE)
+        .unwrap()
+        .unwrap();
+
+    // A timed-out request is forgotten without asking again.
+    let other = LspEvent::RequestTimedOut(LspTimedOutRequest {
+        id: first + 100,
+        method: "textDocument/hover".to_string(),
+        timeout: Duration::from_secs(5),
+    });
+    assert!(!highlights.handle_event(&other));
+    assert!(
+        highlights.handle_event(&LspEvent::RequestTimedOut(LspTimedOutRequest {
+            id: first,
+            method: "textDocument/documentHighlight".to_string(),
+            timeout: Duration::from_secs(5),
+        }))
+    );
+    assert_eq!(highlights.pending_request(), None);
+    let t1 = t0 + Duration::from_secs(1);
+    assert_eq!(
+        highlights.update(&mut client, &line_index, t1).unwrap(),
+        None
+    );
+
+    // After a restart the new server is asked at once, without waiting for the debounce.
+    assert!(
+        highlights.handle_event(&LspEvent::ServerRestarted(LspServerRestart {
+            attempt: 1,
+            reason: "exited".to_string(),
+            abandoned_requests: Vec::new(),
+            closed_documents: Vec::new(),
+        }))
+    );
+    let second = highlights.update(&mut client, &line_index, t1).unwrap();
+    assert_eq!(second, Some(first + 1));
+    assert!(client.canceled.is_empty());
+    assert_eq!(
+        client.positions(),
+        vec![
+            json!({ "line": 0, "character": 5 }),
+            json!({ "line": 0, "character": 5 })
+        ]
+    );
+}
@@ -1,5 +1,8 @@
 use editor_core::processing::ProcessingEdit;
-use editor_core::{DiagnosticSeverity, LineIndex, StyleLayerId};
+use editor_core::{
+    DIAGNOSTIC_UNNECESSARY_STYLE_ID, Diagnostic, DiagnosticRange, DiagnosticSeverity,
+    DiagnosticTag, EditorStateManager, LineIndex, StyleLayerId, Utf16Position, Utf16Range,
+};
 use editor_core_lsp::{
     LspDiagnostic, LspDiagnosticSeverity, LspPosition, LspPublishDiagnosticsParams, LspRange,
     lsp_diagnostics_to_processing_edits,
@@ -29,6 +32,8 @@ fn test_lsp_diagnostics_to_processing_edits_utf16_ranges() {
         code: Some(json!(123)),
         source: Some("unit-test".to_string()),
         message: "emoji".to_string(),
+        code_description_href: None,
+        tags: Vec::new(),
         related_information: Some(json!([{ "note": "x" }])),
         data: Some(json!({ "k": 1 })),
     };
@@ -39,7 +44,7 @@ fn test_lsp_diagnostics_to_processing_edits_utf16_ranges() {
         version: Some(1),
     };
 
-    let edits = lsp_diagnostics_to_processing_edits(&line_index, &params);
+    let edits = lsp_diagnostics_to_processing_edits(&line_index, &params, "rust-analyzer");
     assert_eq!(edits.len(), 2);
 
     match &edits[0] {
@@ -54,9 +59,14 @@ fn test_lsp_diagnostics_to_processing_edits_utf16_ranges() {
     }
 
     match &edits[1] {
-        ProcessingEdit::ReplaceDiagnostics { diagnostics } => {
+        ProcessingEdit::ReplaceProviderDiagnostics {
+            provider,
+            diagnostics,
+        } => {
+            assert_eq!(provider, "rust-analyzer");
             assert_eq!(diagnostics.len(), 1);
             let diag = &diagnostics[0];
+            assert_eq!(diag.provider.as_deref(), Some("rust-analyzer"));
             assert_eq!(diag.range.start, 1);
             assert_eq!(diag.range.end, 2);
             assert_eq!(diag.severity, Some(DiagnosticSeverity::Error));
@@ -82,3 +92,139 @@ fn test_lsp_diagnostics_to_processing_edits_utf16_ranges() {
         other => panic!("unexpected edit: {:?}", other),
     }
 }
+
+#[test]
+fn test_lsp_diagnostic_tags_and_related_locations() {
+    let line_index = LineIndex::from_text("use std::io;\n");
+    let diagnostic = LspDiagnostic::from_value(&json!({
+        "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 11 } },
+        "severity": 4,
+        "code": "unused_imports",
+        "codeDescription": { "href": "https://example.com/unused_imports" },
+        "message": "unused import",
+        "tags": [1, 7],
+        "relatedInformation": [
+            {
+                "location": {
+                    "uri": "file:///src/main.rs",
+                    "range": { "start": { "line": 2, "character": 0 }, "end": { "line": 2, "character": 3 } }
+                },
+                "message": "first imported here"
+            },
+            { "message": "no location" }
+        ]
+    }))
+    .unwrap();
+    // Unknown tag values are ignored.
+    assert_eq!(diagnostic.tags, vec![DiagnosticTag::Unnecessary]);
+
+    let params = LspPublishDiagnosticsParams {
+        uri: "file:///src/lib.rs".to_string(),
+        diagnostics: vec![diagnostic],
+        version: None,
+    };
+    let edits = lsp_diagnostics_to_processing_edits(&line_index, &params, "rust-analyzer");
+
+    let ProcessingEdit::ReplaceStyleLayer { intervals, .. } = &edits[0] else {
+        panic!("expected a style layer edit");
+    };
+    let styles: Vec<u32> = intervals.iter().map(|i| i.style_id).collect();
+    assert_eq!(
+        styles,
+        vec![0x0400_0000 | 4, DIAGNOSTIC_UNNECESSARY_STYLE_ID]
+    );
+
+    let ProcessingEdit::ReplaceProviderDiagnostics { diagnostics, .. } = &edits[1] else {
+        panic!("expected a diagnostics edit");
+    };
+    let diag = &diagnostics[0];
+    assert_eq!(diag.severity, Some(DiagnosticSeverity::Hint));
+    assert_eq!(diag.tags, vec![DiagnosticTag::Unnecessary]);
+    assert_eq!(
+        diag.code_description_href.as_deref(),
+        Some("https://example.com/unused_imports")
+    );
+    assert_eq!(diag.related.len(), 1);
+    assert_eq!(diag.related[0].message, "first imported here");
+    assert_eq!(diag.related[0].uri, "file:///src/main.rs");
+    assert_eq!(
+        diag.related[0].range,
+        Utf16Range::new(Utf16Position::new(2, 0), Utf16Position::new(2, 3))
+    );
+    assert!(diag.related_information_json.is_some());
+}
+
+#[test]
+fn test_lsp_publish_keeps_other_providers_diagnostics() {
+    let mut manager = EditorStateManager::new("let teh = 1;\n", 80);
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceProviderDiagnostics {
+        provider: "spell".to_string(),
+        diagnostics: vec![Diagnostic {
+            range: DiagnosticRange::new(4, 7),
+            severity: Some(DiagnosticSeverity::Information),
+            code: None,
+            source: None,
+            provider: None,
+            message: "unknown word: teh".to_string(),
+            code_description_href: None,
+            tags: Vec::new(),
+            related: Vec::new(),
+            related_information_json: None,
+            data_json: None,
+        }],
+    }]);
+
+    let publish = |messages: &[&str]| {
+        LspPublishDiagnosticsParams {
+        uri: "file:///a.rs".to_string(),
+        diagnostics: messages
+            .iter()
+            .map(|message| {
+                LspDiagnostic::from_value(&json!({
+                    "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 7 } },
+                    "severity": 2,
+                    "message": message,
+                }))
+                .unwrap()
+            })
+            .collect(),
+        version: None,
+    }
+    };
+    let messages = |manager: &EditorStateManager| -> Vec<(Option<String>, String)> {
+        let mut messages: Vec<_> = manager
+            .editor()
+            .diagnostics()
+            .iter()
+            .map(|d| (d.provider.clone(), d.message.clone()))
+            .collect();
+        messages.sort();
+        messages
+    };
+
+    let line_index = manager.editor().line_index.clone();
+    let edits = lsp_diagnostics_to_processing_edits(
+        &line_index,
+        &publish(&["unused variable"]),
+        "rust-analyzer",
+    );
+    manager.apply_processing_edits(edits);
+    assert_eq!(
+        messages(&manager),
+        vec![
+            (
+                Some("rust-analyzer".to_string()),
+                "unused variable".to_string()
+            ),
+            (Some("spell".to_string()), "unknown word: teh".to_string()),
+        ]
+    );
+
+    // The server clears its diagnostics: the spell-checker's remain.
+    let edits = lsp_diagnostics_to_processing_edits(&line_index, &publish(&[]), "rust-analyzer");
+    manager.apply_processing_edits(edits);
+    assert_eq!(
+        messages(&manager),
+        vec![(Some("spell".to_string()), "unknown word: teh".to_string())]
+    );
+}
@@ -0,0 +1,109 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::log_path;
+use editor_core::processing::ProcessingEdit;
+use editor_core::{EditorStateManager, FoldingMergePolicy, LineIndex};
+use editor_core_lsp::{LspDocument, LspSession, LspSessionStartOptions};
+use serde_json::{Value, json};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A server with `capabilities` that answers the first `textDocument/foldingRange` request (id 2)
+/// with `folding_ranges` once the request shows up in `log`.
+fn start(text: &str, capabilities: Value, folding_ranges: Value, log: &Path) -> LspSession {
+    let cmd = common::scripted_replies_command(
+        &[common::initialize_response(capabilities)],
+        log,
+        &[(
+            "\"id\":2,",
+            json!({ "jsonrpc": "2.0", "id": 2, "result": folding_ranges }),
+        )],
+    );
+    let mut session = LspSession::start(LspSessionStartOptions {
+        document: LspDocument {
+            uri: "file:///a".to_string(),
+            language_id: "plaintext".to_string(),
+            version: 1,
+        },
+        ..common::start_options(cmd, text)
+    })
+    .unwrap();
+    session.set_folding_merge_policy(FoldingMergePolicy::IndentationFallback { min_lines: 2 });
+    session
+}
+
+/// Poll until the session replaces the folding regions, and apply them.
+fn poll_folds(session: &mut LspSession, state: &mut EditorStateManager) -> Vec<(usize, usize)> {
+    let line_index = LineIndex::from_text(&state.editor().get_text());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        assert!(Instant::now() < deadline, "no folding regions");
+        let edits = session.poll_edits_with_line_index(&line_index).unwrap();
+        if edits
+            .iter()
+            .any(|edit| matches!(edit, ProcessingEdit::ReplaceFoldingRegions { .. }))
+        {
+            state.apply_processing_edits(edits);
+            return state
+                .editor()
+                .folding_manager
+                .derived_regions()
+                .iter()
+                .map(|r| (r.start_line, r.end_line))
+                .collect();
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn test_indentation_folds_without_server_folding_ranges() {
+    let text = "server:\n  host: localhost\n  ports:\n    - 80\n    - 443\n\nlogging: debug\n";
+    let log = log_path("folding-fallback-yaml");
+    let mut session = start(text, json!({}), Value::Null, &log);
+    assert!(!session.supports_folding_range());
+
+    let mut state = EditorStateManager::new(text, 80);
+    assert_eq!(poll_folds(&mut session, &mut state), vec![(0, 4), (2, 4)]);
+
+    // Without the fallback the regions are cleared, as before.
+    session.set_folding_merge_policy(FoldingMergePolicy::ProviderOnly);
+    session.schedule_refresh(Duration::ZERO);
+    let edits = session
+        .poll_edits_with_line_index(&LineIndex::from_text(text))
+        .unwrap();
+    assert!(
+        edits
+            .iter()
+            .any(|edit| matches!(edit, ProcessingEdit::ClearFoldingRegions))
+    );
+    let _ = std::fs::remove_file(&log);
+}
+
+#[test]
+fn test_server_folding_ranges_take_precedence_over_indentation() {
+    let text = "fn main() {\n    let v = vec![\n        1,\n        2,\n    ];\n    if true {\n        call();\n    }\n}\n/// doc\n/// more\nstruct S;\n";
+    let log = log_path("folding-fallback-rust");
+    let mut session = start(
+        text,
+        json!({ "foldingRangeProvider": true }),
+        json!([
+            { "startLine": 0, "endLine": 7 },
+            { "startLine": 1, "endLine": 4 },
+            { "startLine": 9, "endLine": 10, "kind": "comment" },
+        ]),
+        &log,
+    );
+
+    let mut state = EditorStateManager::new(text, 80);
+    // Indentation finds (0, 7), (1, 3) and (5, 6): the first two start where server ranges do.
+    assert_eq!(
+        poll_folds(&mut session, &mut state),
+        vec![(0, 7), (1, 4), (5, 6), (9, 10)]
+    );
+    let regions = state.editor().folding_manager.derived_regions();
+    assert_eq!(regions[3].placeholder, "/*...*/");
+    let _ = std::fs::remove_file(&log);
+}
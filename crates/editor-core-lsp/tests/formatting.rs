@@ -0,0 +1,172 @@
+use editor_core::{
+    Command, CursorCommand, EditCommand, EditorStateManager, LineIndex, Position, TextEditSpec,
+    ViewCommand,
+};
+use editor_core_lsp::{
+    LspFormattingOptions, LspPosition, LspRange, LspTextEdit, apply_formatting_edits,
+    format_edits_to_text_edit_specs,
+};
+use serde_json::json;
+
+fn edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> LspTextEdit {
+    LspTextEdit {
+        range: LspRange::new(
+            LspPosition::new(start.0, start.1),
+            LspPosition::new(end.0, end.1),
+        ),
+        new_text: new_text.to_string(),
+    }
+}
+
+fn state_at(text: &str, line: usize, column: usize) -> EditorStateManager {
+    let mut state = EditorStateManager::new(text, 80);
+    state
+        .execute(Command::Cursor(CursorCommand::MoveTo { line, column }))
+        .unwrap();
+    state
+}
+
+fn undo(state: &mut EditorStateManager) {
+    state.execute(Command::Edit(EditCommand::Undo)).unwrap();
+}
+
+#[test]
+fn test_insert_only_and_delete_only_edits() {
+    let mut state = state_at("fn main(){\nlet x=1;\n}", 1, 4);
+    let changed = apply_formatting_edits(
+        &mut state,
+        &[
+            edit((1, 0), (1, 0), "    "),
+            edit((0, 9), (0, 9), " "),
+            edit((1, 5), (1, 5), " "),
+            edit((1, 6), (1, 6), " "),
+        ],
+    )
+    .unwrap();
+    assert_eq!(state.editor().get_text(), "fn main() {\n    let x = 1;\n}");
+    assert_eq!(changed, vec![(9, 9), (11, 11), (16, 16), (17, 17)]);
+    assert_eq!(state.editor().cursor_position(), Position::new(1, 4));
+
+    undo(&mut state);
+    assert_eq!(state.editor().get_text(), "fn main(){\nlet x=1;\n}");
+
+    // Delete-only: trailing whitespace and a blank line; the cursor line gets clamped.
+    let mut state = state_at("a  \n\n\nb\t", 3, 2);
+    apply_formatting_edits(
+        &mut state,
+        &[
+            edit((3, 1), (3, 2), ""),
+            edit((0, 1), (0, 3), ""),
+            edit((1, 0), (2, 0), ""),
+        ],
+    )
+    .unwrap();
+    assert_eq!(state.editor().get_text(), "a\n\nb");
+    assert_eq!(state.editor().cursor_position(), Position::new(2, 1));
+}
+
+#[test]
+fn test_multiline_replacement_with_cjk_is_one_undo_step() {
+    let text = "let 名前 = [\n    \"東京\",\"大阪\"\n];";
+    let mut state = state_at(text, 1, 6);
+
+    // CJK chars are one UTF-16 code unit each, so line 0 ends at character 10.
+    apply_formatting_edits(
+        &mut state,
+        &[edit((0, 10), (2, 0), "\n    \"東京\",\n    \"大阪\",\n")],
+    )
+    .unwrap();
+    assert_eq!(
+        state.editor().get_text(),
+        "let 名前 = [\n    \"東京\",\n    \"大阪\",\n];"
+    );
+    assert_eq!(state.editor().cursor_position(), Position::new(1, 6));
+
+    undo(&mut state);
+    assert_eq!(state.editor().get_text(), text);
+}
+
+#[test]
+fn test_overlapping_and_out_of_order_edits_are_merged() {
+    let mut state = state_at("abcdef", 0, 6);
+    apply_formatting_edits(
+        &mut state,
+        &[
+            edit((0, 4), (0, 6), "EF"),
+            // Two inserts at the same position keep server order.
+            edit((0, 0), (0, 0), "1"),
+            edit((0, 0), (0, 0), "2"),
+            // Overlaps the next edit.
+            edit((0, 1), (0, 3), "X"),
+            edit((0, 2), (0, 4), "Y"),
+        ],
+    )
+    .unwrap();
+    assert_eq!(state.editor().get_text(), "12aXYEF");
+    assert_eq!(state.editor().cursor_position(), Position::new(0, 6));
+
+    assert!(apply_formatting_edits(&mut state, &[]).unwrap().is_empty());
+}
+
+#[test]
+fn test_formatting_options_follow_tab_settings() {
+    let mut state = EditorStateManager::new("", 80);
+    state
+        .execute(Command::View(ViewCommand::SetTabWidth { width: 2 }))
+        .unwrap();
+    assert_eq!(
+        LspFormattingOptions::from_state_manager(&state).to_value(),
+        json!({ "tabSize": 2, "insertSpaces": false })
+    );
+}
+
+#[test]
+fn test_format_response_to_specs_handles_eof_inserts_and_order() {
+    let text = "a\nb";
+    let line_index = LineIndex::from_text(text);
+    let response = json!([
+        // A final newline inserted "after the last line" lands at EOF.
+        { "range": { "start": { "line": 2, "character": 0 }, "end": { "line": 2, "character": 0 } }, "newText": "\n" },
+        { "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 1, "character": 0 } }, "newText": "  " },
+        { "range": { "start": { "line": 0, "character": 1 }, "end": { "line": 0, "character": 1 } }, "newText": ";" },
+    ]);
+    let specs = format_edits_to_text_edit_specs(&response, &line_index).unwrap();
+    assert_eq!(
+        specs,
+        vec![
+            TextEditSpec {
+                start: 1,
+                end: 1,
+                text: ";".to_string()
+            },
+            TextEditSpec {
+                start: 2,
+                end: 2,
+                text: "  ".to_string()
+            },
+            TextEditSpec {
+                start: 3,
+                end: 3,
+                text: "\n".to_string()
+            },
+        ]
+    );
+
+    // One command: one undo step and one text delta for resync.
+    let mut state = EditorStateManager::new(text, 80);
+    state
+        .execute(Command::Edit(EditCommand::ApplyTextEdits { edits: specs }))
+        .unwrap();
+    assert_eq!(state.editor().get_text(), "a;\n  b\n");
+    assert_eq!(state.last_text_delta().unwrap().edits.len(), 3);
+    undo(&mut state);
+    assert_eq!(state.editor().get_text(), text);
+
+    assert!(
+        format_edits_to_text_edit_specs(&json!(null), &line_index)
+            .unwrap()
+            .is_empty()
+    );
+    assert!(format_edits_to_text_edit_specs(&json!({}), &line_index).is_err());
+    assert!(format_edits_to_text_edit_specs(&json!([{ "newText": "x" }]), &line_index).is_err());
+}
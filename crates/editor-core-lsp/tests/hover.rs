@@ -0,0 +1,113 @@
+use editor_core::processing::ProcessingEdit;
+use editor_core::{DecorationKind, DecorationLayerId, DecorationPlacement, LineIndex};
+use editor_core_lsp::{
+    HoverBlock, hover_from_value, lsp_hover_to_decoration, lsp_hover_to_processing_edit,
+};
+use serde_json::{Value, json};
+
+#[test]
+fn test_hover_markup_content_maps_range_to_char_offsets() {
+    let line_index = LineIndex::from_text("let 👋 = x;\n");
+
+    // "let 👋 = x": the emoji spans utf16 columns 4..6, so `x` is at utf16 9..10.
+    let hover = hover_from_value(
+        &line_index,
+        &json!({
+            "contents": { "kind": "markdown", "value": "```rust\nlet x: u8\n```" },
+            "range": { "start": { "line": 0, "character": 9 }, "end": { "line": 0, "character": 10 } }
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(
+        hover.contents,
+        vec![HoverBlock::Markdown("```rust\nlet x: u8\n```".to_string())]
+    );
+    assert_eq!(hover.range, Some((8, 9)));
+    assert_eq!(hover.plain_text(), "let x: u8");
+}
+
+#[test]
+fn test_hover_marked_strings_and_empty_results() {
+    let line_index = LineIndex::from_text("x");
+
+    let hover = hover_from_value(
+        &line_index,
+        &json!({
+            "contents": [
+                { "language": "python", "value": "def f() -> int" },
+                "Returns *one*.",
+                "",
+            ]
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        hover.contents,
+        vec![
+            HoverBlock::Code {
+                language: "python".to_string(),
+                value: "def f() -> int".to_string(),
+            },
+            HoverBlock::Markdown("Returns *one*.".to_string()),
+        ]
+    );
+    assert_eq!(hover.range, None);
+    assert_eq!(hover.plain_text(), "def f() -> int\n\nReturns one.");
+
+    let plain = hover_from_value(
+        &line_index,
+        &json!({ "contents": { "kind": "plaintext", "value": "a *b*" } }),
+    )
+    .unwrap();
+    assert_eq!(plain.plain_text(), "a *b*");
+
+    assert_eq!(hover_from_value(&line_index, &Value::Null), None);
+    assert_eq!(
+        hover_from_value(&line_index, &json!({ "contents": [] })),
+        None
+    );
+    assert_eq!(
+        hover_from_value(
+            &line_index,
+            &json!({ "contents": { "kind": "markdown", "value": " " } })
+        ),
+        None
+    );
+}
+
+#[test]
+fn test_hover_to_decorations() {
+    let line_index = LineIndex::from_text("a\nlet value = 1;");
+    let hover = hover_from_value(
+        &line_index,
+        &json!({
+            "contents": ["```rust\nlet value: i32\n```", "The value."],
+            "range": { "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 9 } }
+        }),
+    )
+    .unwrap();
+
+    let after =
+        lsp_hover_to_decoration(&line_index, &hover, 7, DecorationPlacement::After).unwrap();
+    assert_eq!((after.range.start, after.range.end), (11, 11));
+    assert_eq!(after.kind, DecorationKind::InlayHint);
+    assert_eq!(after.text.as_deref(), Some("let value: i32 ⏐ The value."));
+    assert_eq!(
+        after.tooltip.as_deref(),
+        Some("let value: i32\n\nThe value.")
+    );
+
+    let above =
+        lsp_hover_to_decoration(&line_index, &hover, 7, DecorationPlacement::AboveLine).unwrap();
+    assert_eq!((above.range.start, above.range.end), (2, 2));
+    assert_eq!(above.kind, DecorationKind::CodeLens);
+
+    let ProcessingEdit::ReplaceDecorations { layer, decorations } =
+        lsp_hover_to_processing_edit(&line_index, None, 7, DecorationPlacement::After)
+    else {
+        panic!("expected a decorations edit");
+    };
+    assert_eq!(layer, DecorationLayerId::HOVER);
+    assert!(decorations.is_empty());
+}
@@ -0,0 +1,272 @@
+mod common;
+
+use common::FakeClient;
+use editor_core::{LineIndex, ProcessingEdit};
+use editor_core_lsp::{
+    InlayHintManager, LspEvent, LspLocation, LspResponse, LspServerRequest, LspServerRestart,
+    LspTimedOutRequest,
+};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+fn response(id: u64, method: &str, result: Value) -> LspEvent {
+    LspEvent::Response(LspResponse {
+        id,
+        method: method.to_string(),
+        result: Some(result),
+        error: None,
+    })
+}
+
+fn manager() -> InlayHintManager {
+    let mut manager = InlayHintManager::new("file:///a.rs");
+    manager.set_debounce(Duration::ZERO);
+    manager
+}
+
+fn decoration_count(edit: Option<ProcessingEdit>) -> usize {
+    match edit {
+        Some(ProcessingEdit::ReplaceDecorations { decorations, .. }) => decorations.len(),
+        other => panic!("unexpected edit: {:?}", other),
+    }
+}
+
+#[test]
+fn test_edit_invalidates_cached_hints_and_drops_stale_responses() {
+    let line_index = LineIndex::from_text("let a = 1;\nlet b = a;\n");
+    let mut client = FakeClient::default();
+    let mut hints = manager();
+
+    let first = hints.update(&mut client, &line_index, 1, 0..2).unwrap();
+    assert_eq!(first, Some(1));
+    let (_, method, params) = &client.requests[0];
+    assert_eq!(method, "textDocument/inlayHint");
+    assert_eq!(
+        params["range"],
+        json!({ "start": { "line": 0, "character": 0 }, "end": { "line": 2, "character": 0 } })
+    );
+    // Same version and range: nothing to do while the request is in flight.
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..2).unwrap(),
+        None
+    );
+
+    assert!(hints.handle_event(&response(
+        1,
+        "textDocument/inlayHint",
+        json!([{ "position": { "line": 0, "character": 5 }, "label": ": i32" }]),
+    )));
+    assert_eq!(hints.hints().len(), 1);
+    assert_eq!(hints.hints_range(), Some((1, 0..2)));
+    assert_eq!(decoration_count(hints.take_processing_edit(&line_index)), 1);
+    assert!(hints.take_processing_edit(&line_index).is_none());
+
+    // An edit bumps the version: the cached hints are gone and new ones are requested.
+    let second = hints.update(&mut client, &line_index, 2, 0..2).unwrap();
+    assert!(hints.hints().is_empty());
+    assert_eq!(second, Some(2));
+    // A third version before the answer supersedes (and cancels) the second request.
+    let third = hints.update(&mut client, &line_index, 3, 0..2).unwrap();
+    assert_eq!(third, Some(3));
+    assert_eq!(client.canceled, vec![2]);
+
+    // The answer to the canceled request is not ours anymore.
+    assert!(!hints.handle_event(&response(2, "textDocument/inlayHint", json!([]))));
+    assert!(hints.take_processing_edit(&line_index).is_none());
+
+    assert!(hints.handle_event(&response(
+        3,
+        "textDocument/inlayHint",
+        json!([
+            { "position": { "line": 0, "character": 5 }, "label": ": i32" },
+            { "position": { "line": 1, "character": 5 }, "label": ": i32" },
+        ]),
+    )));
+    assert_eq!(hints.hints_range(), Some((3, 0..2)));
+    assert_eq!(decoration_count(hints.take_processing_edit(&line_index)), 2);
+}
+
+#[test]
+fn test_edit_debounces_re_request() {
+    let line_index = LineIndex::from_text("fn main() {}\n");
+    let mut client = FakeClient::default();
+    let mut hints = InlayHintManager::new("file:///a.rs");
+    assert_eq!(hints.debounce(), Duration::from_millis(200));
+    hints.set_debounce(Duration::from_millis(50));
+
+    // The first request is not an edit and goes out at once.
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..1).unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        hints.update(&mut client, &line_index, 2, 0..1).unwrap(),
+        None
+    );
+
+    // The answer for version 1 arrives after the edit: it is dropped.
+    assert!(hints.handle_event(&response(
+        1,
+        "textDocument/inlayHint",
+        json!([{ "position": { "line": 0, "character": 2 }, "label": "x" }]),
+    )));
+    assert!(hints.hints().is_empty());
+    assert!(hints.take_processing_edit(&line_index).is_none());
+
+    assert_eq!(
+        hints.update(&mut client, &line_index, 2, 0..1).unwrap(),
+        None
+    );
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(
+        hints.update(&mut client, &line_index, 2, 0..1).unwrap(),
+        Some(2)
+    );
+}
+
+#[test]
+fn test_refresh_triggers_re_request() {
+    let line_index = LineIndex::from_text("let a = 1;\n");
+    let mut client = FakeClient::default();
+    let mut hints = manager();
+
+    hints.update(&mut client, &line_index, 1, 0..1).unwrap();
+    hints.handle_event(&response(1, "textDocument/inlayHint", json!([])));
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..1).unwrap(),
+        None
+    );
+
+    // Auto-answered refresh, as reported by `LspSession`.
+    assert!(hints.handle_event(&LspEvent::InlayHintRefresh));
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..1).unwrap(),
+        Some(2)
+    );
+    hints.handle_event(&response(2, "textDocument/inlayHint", json!([])));
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..1).unwrap(),
+        None
+    );
+
+    // Deferred refresh request, answered by the host.
+    assert!(
+        hints.handle_event(&LspEvent::DeferredRequest(LspServerRequest {
+            id: 9,
+            method: "workspace/inlayHint/refresh".to_string(),
+            params: Value::Null,
+        }))
+    );
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..1).unwrap(),
+        Some(3)
+    );
+    assert_eq!(client.requests.len(), 3);
+}
+
+#[test]
+fn test_timeout_and_restart_trigger_re_request() {
+    let line_index = LineIndex::from_text("let a = 1;\n");
+    let mut client = FakeClient::default();
+    let mut hints = manager();
+
+    hints.update(&mut client, &line_index, 1, 0..1).unwrap();
+    // Another request's timeout is not ours.
+    assert!(
+        !hints.handle_event(&LspEvent::RequestTimedOut(LspTimedOutRequest {
+            id: 7,
+            method: "textDocument/hover".to_string(),
+            timeout: Duration::from_secs(1),
+        }))
+    );
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..1).unwrap(),
+        None
+    );
+
+    assert!(
+        hints.handle_event(&LspEvent::RequestTimedOut(LspTimedOutRequest {
+            id: 1,
+            method: "textDocument/inlayHint".to_string(),
+            timeout: Duration::from_secs(1),
+        }))
+    );
+    assert_eq!(hints.pending_request(), None);
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..1).unwrap(),
+        Some(2)
+    );
+    // The timed-out request was already canceled by the client.
+    assert!(client.canceled.is_empty());
+
+    assert!(
+        hints.handle_event(&LspEvent::ServerRestarted(LspServerRestart {
+            attempt: 1,
+            reason: "server exited".to_string(),
+            abandoned_requests: vec![2],
+            closed_documents: Vec::new(),
+        }))
+    );
+    assert_eq!(
+        hints.update(&mut client, &line_index, 1, 0..1).unwrap(),
+        Some(3)
+    );
+}
+
+#[test]
+fn test_resolve_keeps_label_part_locations() {
+    let line_index = LineIndex::from_text("let v = Vec::new();\n");
+    let mut client = FakeClient::default();
+    let mut hints = manager();
+
+    hints.update(&mut client, &line_index, 1, 0..1).unwrap();
+    hints.handle_event(&response(
+        1,
+        "textDocument/inlayHint",
+        json!([{
+            "position": { "line": 0, "character": 5 },
+            "label": [{ "value": ": " }, { "value": "Vec<u8>" }],
+            "data": 42
+        }]),
+    ));
+    hints.take_processing_edit(&line_index);
+    assert_eq!(hints.label_parts(0)[1].location, None);
+
+    let id = hints.resolve_hint(&mut client, 0).unwrap();
+    let (_, method, params) = client.requests.last().unwrap();
+    assert_eq!(method, "inlayHint/resolve");
+    assert_eq!(params["data"], json!(42));
+    assert!(hints.resolve_hint(&mut client, 5).is_err());
+
+    assert!(hints.handle_event(&response(
+        id,
+        "inlayHint/resolve",
+        json!({
+            "position": { "line": 0, "character": 5 },
+            "label": [
+                { "value": ": " },
+                {
+                    "value": "Vec<u8>",
+                    "tooltip": { "kind": "markdown", "value": "A vector" },
+                    "location": {
+                        "uri": "file:///std/vec.rs",
+                        "range": {
+                            "start": { "line": 3, "character": 11 },
+                            "end": { "line": 3, "character": 14 }
+                        }
+                    }
+                }
+            ]
+        }),
+    )));
+    let parts = hints.label_parts(0);
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[1].value, "Vec<u8>");
+    assert_eq!(parts[1].tooltip.as_deref(), Some("A vector"));
+    let location: &LspLocation = parts[1].location.as_ref().unwrap();
+    assert_eq!(location.uri, "file:///std/vec.rs");
+    assert_eq!(location.range.start.line, 3);
+    assert_eq!(location.range.start.character, 11);
+    // The resolved hint is republished.
+    assert_eq!(decoration_count(hints.take_processing_edit(&line_index)), 1);
+}
@@ -0,0 +1,106 @@
+use editor_core_lsp::{LspProgressParams, ProgressItem, ProgressTracker};
+use serde_json::{Value, json};
+
+fn progress(token: Value, value: Value) -> LspProgressParams {
+    LspProgressParams { token, value }
+}
+
+fn summary(tracker: &ProgressTracker) -> Vec<(String, Option<u32>)> {
+    tracker
+        .active_progress()
+        .into_iter()
+        .map(|item| (item.title, item.percentage))
+        .collect()
+}
+
+#[test]
+fn test_interleaved_progress_tokens() {
+    let mut tracker = ProgressTracker::new();
+    let index = json!("rustAnalyzer/Indexing");
+    let check = json!(7);
+
+    let begun = tracker
+        .handle_progress(&progress(
+            index.clone(),
+            json!({ "kind": "begin", "title": "Indexing", "percentage": 0, "cancellable": false }),
+        ))
+        .unwrap();
+    assert_eq!(
+        begun,
+        ProgressItem {
+            token: index.clone(),
+            title: "Indexing".to_string(),
+            message: None,
+            percentage: Some(0),
+            cancellable: false,
+            done: false,
+        }
+    );
+    tracker.handle_progress(&progress(
+        check.clone(),
+        json!({ "kind": "begin", "title": "cargo check", "message": "0/3" }),
+    ));
+    tracker.handle_progress(&progress(
+        index.clone(),
+        json!({ "kind": "report", "message": "12/40 (core)", "percentage": 30 }),
+    ));
+    // A report without a percentage keeps the previous one.
+    let reported = tracker
+        .handle_progress(&progress(
+            index.clone(),
+            json!({ "kind": "report", "message": "17/40 (std)" }),
+        ))
+        .unwrap();
+    assert_eq!(reported.message.as_deref(), Some("17/40 (std)"));
+    assert_eq!(reported.percentage, Some(30));
+    tracker.handle_progress(&progress(
+        check.clone(),
+        json!({ "kind": "report", "message": "2/3", "percentage": 66 }),
+    ));
+    assert_eq!(
+        summary(&tracker),
+        vec![
+            ("Indexing".to_string(), Some(30)),
+            ("cargo check".to_string(), Some(66)),
+        ]
+    );
+
+    let ended = tracker
+        .handle_progress(&progress(check.clone(), json!({ "kind": "end" })))
+        .unwrap();
+    assert!(ended.done);
+    assert_eq!(ended.title, "cargo check");
+    assert_eq!(ended.message.as_deref(), Some("2/3"));
+    assert_eq!(summary(&tracker), vec![("Indexing".to_string(), Some(30))]);
+
+    tracker.handle_progress(&progress(
+        index,
+        json!({ "kind": "end", "message": "done" }),
+    ));
+    assert!(tracker.is_idle());
+}
+
+#[test]
+fn test_non_work_done_progress_is_ignored() {
+    let mut tracker = ProgressTracker::new();
+    // Partial results (e.g. workspace symbols) and reports for unknown tokens.
+    assert!(
+        tracker
+            .handle_progress(&progress(json!("symbols/1"), json!([{ "name": "foo" }])))
+            .is_none()
+    );
+    assert!(
+        tracker
+            .handle_progress(&progress(
+                json!(1),
+                json!({ "kind": "report", "percentage": 5 })
+            ))
+            .is_none()
+    );
+    assert!(
+        tracker
+            .handle_progress(&progress(json!(1), json!({ "kind": "end" })))
+            .is_none()
+    );
+    assert!(tracker.is_idle());
+}
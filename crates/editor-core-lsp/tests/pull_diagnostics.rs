@@ -0,0 +1,134 @@
+use editor_core::LineIndex;
+use editor_core::processing::ProcessingEdit;
+use editor_core_lsp::{
+    DocumentDiagnosticReport, DocumentDiagnostics, LspDiagnostic, LspSessionOutput,
+    document_diagnostic_response_from_value,
+};
+use serde_json::{Value, json};
+
+fn diagnostic(line: u32, start: u32, end: u32, message: &str) -> Value {
+    json!({
+        "range": {
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": end }
+        },
+        "severity": 1,
+        "message": message
+    })
+}
+
+fn report(value: Value) -> DocumentDiagnosticReport {
+    DocumentDiagnosticReport::from_value(&value).unwrap()
+}
+
+fn messages(diagnostics: &[LspDiagnostic]) -> Vec<&str> {
+    diagnostics.iter().map(|d| d.message.as_str()).collect()
+}
+
+fn replaced_diagnostic_ranges(edits: &[ProcessingEdit]) -> Vec<(usize, usize)> {
+    edits
+        .iter()
+        .find_map(|edit| match edit {
+            ProcessingEdit::ReplaceDiagnostics { diagnostics } => Some(
+                diagnostics
+                    .iter()
+                    .map(|d| (d.range.start, d.range.end))
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_unchanged_report_keeps_diagnostics_and_full_report_replaces_them() {
+    let mut diagnostics = DocumentDiagnostics::new();
+    assert!(diagnostics.apply_pull_report(&report(json!({
+        "kind": "full",
+        "resultId": "1",
+        "items": [diagnostic(0, 0, 3, "a"), diagnostic(1, 0, 1, "b")]
+    }))));
+    assert_eq!(diagnostics.pull_result_id(), Some("1"));
+    assert_eq!(messages(diagnostics.pulled()), vec!["a", "b"]);
+
+    assert!(
+        !diagnostics.apply_pull_report(&report(json!({ "kind": "unchanged", "resultId": "2" })))
+    );
+    assert_eq!(diagnostics.pull_result_id(), Some("2"));
+    assert_eq!(messages(diagnostics.pulled()), vec!["a", "b"]);
+
+    assert!(diagnostics.apply_pull_report(&report(json!({
+        "kind": "full",
+        "resultId": "3",
+        "items": [diagnostic(1, 0, 1, "c")]
+    }))));
+    assert_eq!(messages(diagnostics.pulled()), vec!["c"]);
+
+    // An unchanged report needs a result id; unknown kinds are rejected.
+    assert!(DocumentDiagnosticReport::from_value(&json!({ "kind": "unchanged" })).is_none());
+    assert!(DocumentDiagnosticReport::from_value(&json!({ "kind": "partial" })).is_none());
+}
+
+#[test]
+fn test_push_and_pull_merge_without_duplicates() {
+    let line_index = LineIndex::from_text("let x = 1;\nfoo();\n");
+    let mut diagnostics = DocumentDiagnostics::new();
+    let pushed: Vec<LspDiagnostic> = [
+        diagnostic(0, 4, 5, "unused"),
+        diagnostic(1, 0, 3, "undefined"),
+    ]
+    .iter()
+    .filter_map(LspDiagnostic::from_value)
+    .collect();
+    diagnostics.set_pushed(pushed);
+
+    diagnostics.apply_pull_report(&report(json!({
+        "kind": "full",
+        "items": [diagnostic(1, 0, 3, "undefined"), diagnostic(1, 3, 5, "arity")]
+    })));
+    assert_eq!(
+        messages(&diagnostics.merged()),
+        vec!["unused", "undefined", "arity"]
+    );
+    assert_eq!(
+        replaced_diagnostic_ranges(&diagnostics.to_processing_edits(
+            "file:///a.rs",
+            &line_index,
+            &LspSessionOutput::default(),
+        )),
+        vec![(4, 5), (11, 14), (14, 16)]
+    );
+
+    // A new push only replaces the pushed set.
+    diagnostics.set_pushed(Vec::new());
+    assert_eq!(messages(&diagnostics.merged()), vec!["undefined", "arity"]);
+}
+
+#[test]
+fn test_related_document_reports() {
+    let response = document_diagnostic_response_from_value(&json!({
+        "kind": "unchanged",
+        "resultId": "7",
+        "relatedDocuments": {
+            "file:///b.rs": { "kind": "full", "items": [diagnostic(0, 0, 1, "b")] },
+            "file:///a.rs": { "kind": "unchanged", "resultId": "4" }
+        }
+    }))
+    .unwrap();
+    assert_eq!(
+        response.report,
+        DocumentDiagnosticReport::Unchanged {
+            result_id: "7".to_string()
+        }
+    );
+    let uris: Vec<&str> = response
+        .related_documents
+        .iter()
+        .map(|(uri, _)| uri.as_str())
+        .collect();
+    assert_eq!(uris, vec!["file:///a.rs", "file:///b.rs"]);
+    let DocumentDiagnosticReport::Full { items, .. } = &response.related_documents[1].1 else {
+        panic!("expected a full report");
+    };
+    assert_eq!(messages(items), vec!["b"]);
+}
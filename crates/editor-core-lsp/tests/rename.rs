@@ -0,0 +1,181 @@
+use editor_core::{LineIndex, OpenBufferResult, Workspace};
+use editor_core_lsp::{
+    PrepareRenameResult, WorkspaceEditVersionMismatch, apply_workspace_edit_to_workspace,
+    prepare_rename_from_value,
+};
+use serde_json::{Value, json};
+
+fn edit(line: u32, start: u32, end: u32, new_text: &str) -> Value {
+    json!({
+        "range": {
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": end }
+        },
+        "newText": new_text
+    })
+}
+
+#[test]
+fn test_prepare_rename_result_shapes() {
+    // The emoji takes two UTF-16 code units, so `x` is at utf16 6..7.
+    let line_index = LineIndex::from_text("let 😀 x = 1;");
+    let range =
+        json!({ "start": { "line": 0, "character": 7 }, "end": { "line": 0, "character": 8 } });
+
+    assert_eq!(
+        prepare_rename_from_value(&line_index, &range),
+        Some(PrepareRenameResult::Range {
+            range: (6, 7),
+            placeholder: None
+        })
+    );
+    assert_eq!(
+        prepare_rename_from_value(&line_index, &json!({ "range": range, "placeholder": "x" })),
+        Some(PrepareRenameResult::Range {
+            range: (6, 7),
+            placeholder: Some("x".to_string())
+        })
+    );
+    assert_eq!(
+        prepare_rename_from_value(&line_index, &json!({ "defaultBehavior": true })),
+        Some(PrepareRenameResult::DefaultBehavior)
+    );
+    assert_eq!(prepare_rename_from_value(&line_index, &Value::Null), None);
+}
+
+#[test]
+fn test_multi_file_rename_applies_open_buffers_and_reports_the_rest() {
+    let mut ws = Workspace::new();
+    let OpenBufferResult { buffer_id: lib, .. } = ws
+        .open_buffer(
+            Some("file:///src/lib.rs".to_string()),
+            "fn foo() {}\nfn bar() { foo() }",
+            80,
+        )
+        .unwrap();
+    let OpenBufferResult {
+        buffer_id: main, ..
+    } = ws
+        .open_buffer(Some("file:///src/main.rs".to_string()), "use lib::foo;", 80)
+        .unwrap();
+    let OpenBufferResult {
+        buffer_id: stale, ..
+    } = ws
+        .open_buffer(Some("file:///src/stale.rs".to_string()), "foo", 80)
+        .unwrap();
+
+    // Canned `textDocument/rename` response.
+    let rename = json!({
+        "documentChanges": [
+            {
+                "textDocument": { "uri": "file:///src/lib.rs", "version": 3 },
+                "edits": [edit(0, 3, 6, "baz"), edit(1, 11, 14, "baz")]
+            },
+            {
+                "textDocument": { "uri": "file:///src/main.rs", "version": null },
+                "edits": [edit(0, 9, 12, "baz")]
+            },
+            {
+                "textDocument": { "uri": "file:///src/stale.rs", "version": 1 },
+                "edits": [edit(0, 0, 3, "baz")]
+            },
+            {
+                "textDocument": { "uri": "file:///tests/it.rs", "version": 0 },
+                "edits": [edit(4, 8, 11, "baz")]
+            }
+        ]
+    });
+
+    let result = apply_workspace_edit_to_workspace(&mut ws, &rename, |uri| match uri {
+        "file:///src/lib.rs" => Some(3),
+        "file:///src/stale.rs" => Some(2),
+        _ => None,
+    })
+    .unwrap();
+
+    assert_eq!(
+        ws.buffer_text(lib).unwrap(),
+        "fn baz() {}\nfn bar() { baz() }"
+    );
+    assert_eq!(ws.buffer_text(main).unwrap(), "use lib::baz;");
+    assert_eq!(ws.buffer_text(stale).unwrap(), "foo");
+
+    let applied: Vec<&str> = result.applied.iter().map(|d| d.uri.as_str()).collect();
+    assert_eq!(applied, vec!["file:///src/lib.rs", "file:///src/main.rs"]);
+    assert_eq!(
+        result.applied[0].changed_char_ranges,
+        vec![(23, 26), (3, 6)]
+    );
+
+    assert_eq!(
+        result.version_mismatches,
+        vec![WorkspaceEditVersionMismatch {
+            uri: "file:///src/stale.rs".to_string(),
+            expected_version: 1,
+            current_version: 2,
+        }]
+    );
+
+    assert_eq!(result.external.len(), 1);
+    assert_eq!(result.external[0].uri, "file:///tests/it.rs");
+    assert_eq!(result.external[0].edits[0].new_text, "baz");
+}
+
+#[test]
+fn test_rename_changes_map_to_buffers_despite_uri_encoding() {
+    let mut ws = Workspace::new();
+    let OpenBufferResult { buffer_id: a, .. } = ws
+        .open_buffer(
+            Some("file:///proj/caf%C3%A9%20a.rs".to_string()),
+            "let total = 1;\nprint(total);",
+            80,
+        )
+        .unwrap();
+    let OpenBufferResult { buffer_id: b, .. } = ws
+        .open_buffer(Some("file:///proj/b.rs".to_string()), "use a::total;", 80)
+        .unwrap();
+
+    // `changes` form; the server spells the first uri without percent-encoding.
+    let rename = json!({
+        "changes": {
+            "file:///proj/café a.rs": [edit(0, 4, 9, "sum"), edit(1, 6, 11, "sum")],
+            "file:///proj/b.rs": [edit(0, 7, 12, "sum")]
+        }
+    });
+
+    let result = apply_workspace_edit_to_workspace(&mut ws, &rename, |_| None).unwrap();
+    assert_eq!(ws.buffer_text(a).unwrap(), "let sum = 1;\nprint(sum);");
+    assert_eq!(ws.buffer_text(b).unwrap(), "use a::sum;");
+    assert!(result.external.is_empty());
+
+    let mut applied: Vec<&str> = result.applied.iter().map(|d| d.uri.as_str()).collect();
+    applied.sort();
+    assert_eq!(
+        applied,
+        vec!["file:///proj/b.rs", "file:///proj/caf%C3%A9%20a.rs"]
+    );
+}
+
+#[test]
+fn test_rename_changes_map_to_buffers_opened_with_non_canonical_uris() {
+    let mut ws = Workspace::new();
+    // The host opened the buffer with a lowercase escape; the server sends the canonical form.
+    let OpenBufferResult { buffer_id, .. } = ws
+        .open_buffer(
+            Some("file:///c%3a/proj/caf%c3%a9.rs".to_string()),
+            "let total = 1;",
+            80,
+        )
+        .unwrap();
+
+    let rename = json!({
+        "changes": {
+            "file:///c%3A/proj/caf%C3%A9.rs": [edit(0, 4, 9, "sum")]
+        }
+    });
+
+    let result = apply_workspace_edit_to_workspace(&mut ws, &rename, |_| None).unwrap();
+    assert_eq!(ws.buffer_text(buffer_id).unwrap(), "let sum = 1;");
+    assert!(result.external.is_empty());
+    assert_eq!(result.applied[0].uri, "file:///c%3a/proj/caf%c3%a9.rs");
+}
@@ -0,0 +1,196 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{echo_command, frame, initialize_response, scripted_command};
+use editor_core::EditorStateManager;
+use editor_core_lsp::{
+    CANCELED_RESPONSE_GRACE, LspClient, LspEvent, LspInbound, LspRequestTimeouts,
+    LspTimedOutRequest,
+};
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+
+/// A client whose "server" echoes every message back, so responses sent with
+/// [`LspClient::respond`] come back as responses to our own request ids.
+fn echo_client() -> LspClient {
+    LspClient::spawn(echo_command(), Vec::new()).expect("spawn cat")
+}
+
+fn response(id: u64, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn recv(client: &LspClient) -> Option<Value> {
+    match client.recv_timeout(Duration::from_millis(500))? {
+        LspInbound::Message(msg) => Some(msg),
+        LspInbound::IoError(err) => panic!("io error: {err}"),
+    }
+}
+
+#[test]
+fn test_late_response_to_canceled_request_is_dropped() {
+    let mut client = echo_client();
+    let slow = client
+        .request("workspace/symbol", json!({ "query": "" }))
+        .unwrap();
+    let fast = client.request("textDocument/hover", json!({})).unwrap();
+    assert_ne!(slow, fast);
+    assert_eq!(recv(&client).unwrap()["id"], json!(slow));
+    assert_eq!(recv(&client).unwrap()["id"], json!(fast));
+
+    client.cancel_request(slow).unwrap();
+    assert!(client.is_canceled(slow));
+    let cancel = recv(&client).unwrap();
+    assert_eq!(cancel["method"], json!("$/cancelRequest"));
+    assert_eq!(cancel["params"], json!({ "id": slow }));
+
+    // The server answers both; only the live request's response is delivered.
+    client.respond(slow, json!("stale")).unwrap();
+    client.respond(fast, json!("fresh")).unwrap();
+    let msg = recv(&client).unwrap();
+    assert_eq!(msg["id"], json!(fast));
+    assert_eq!(msg["result"], json!("fresh"));
+    assert!(!client.is_canceled(slow));
+
+    // Cancel handles share the same bookkeeping.
+    let later = client
+        .request("workspace/symbol", json!({ "query": "x" }))
+        .unwrap();
+    recv(&client).unwrap();
+    client.cancel_handle(later).cancel().unwrap();
+    recv(&client).unwrap();
+    assert!(
+        client
+            .wait_for_response(later, Duration::from_millis(100))
+            .is_err()
+    );
+    client.respond(later, Value::Null).unwrap();
+    assert!(recv(&client).is_none());
+}
+
+#[test]
+fn test_request_deadlines_depend_on_the_method() {
+    let mut client = LspClient::spawn(scripted_command("0", ""), Vec::new()).unwrap();
+    client.set_request_timeouts(
+        LspRequestTimeouts::new()
+            .with_method("textDocument/completion", Duration::from_millis(20))
+            .with_method("textDocument/rename", Duration::from_secs(60)),
+    );
+    assert_eq!(
+        client.request_timeouts().timeout_for("textDocument/hover"),
+        None
+    );
+
+    let completion = client
+        .request("textDocument/completion", json!({}))
+        .unwrap();
+    let rename = client.request("textDocument/rename", json!({})).unwrap();
+    let hover = client.request("textDocument/hover", json!({})).unwrap();
+    assert_eq!(client.in_flight_requests(), vec![completion, rename, hover]);
+    assert!(client.expire_requests(Instant::now()).unwrap().is_empty());
+
+    std::thread::sleep(Duration::from_millis(40));
+    assert_eq!(
+        client.expire_requests(Instant::now()).unwrap(),
+        vec![LspTimedOutRequest {
+            id: completion,
+            method: "textDocument/completion".to_string(),
+            timeout: Duration::from_millis(20),
+        }]
+    );
+    assert!(client.is_canceled(completion));
+    assert_eq!(
+        client.in_flight_method(rename).as_deref(),
+        Some("textDocument/rename")
+    );
+
+    // Shutdown-style cancellation of everything still in flight.
+    assert_eq!(client.cancel_all().unwrap(), vec![rename, hover]);
+    assert!(client.in_flight_requests().is_empty());
+    assert!(client.is_canceled(hover));
+
+    // The server never answers: the canceled ids are forgotten after the grace period.
+    client
+        .expire_requests(Instant::now() + CANCELED_RESPONSE_GRACE / 2)
+        .unwrap();
+    assert!(client.is_canceled(hover));
+    client
+        .expire_requests(Instant::now() + CANCELED_RESPONSE_GRACE)
+        .unwrap();
+    assert!(!client.is_canceled(completion));
+    assert!(!client.is_canceled(hover));
+
+    // Killing the server forgets them right away.
+    let id = client.request("textDocument/hover", json!({})).unwrap();
+    client.cancel_request(id).unwrap();
+    assert!(client.is_canceled(id));
+    client.kill().unwrap();
+    assert!(!client.is_canceled(id));
+}
+
+#[test]
+fn test_delayed_response_after_timeout_is_dropped() {
+    // The server answers both requests, but only after 300ms.
+    let cmd = scripted_command(
+        "0.3",
+        &frame(&[response(1, json!("late")), response(2, json!("on time"))]),
+    );
+    let mut client = LspClient::spawn(cmd, Vec::new()).unwrap();
+    client.set_request_timeouts(
+        LspRequestTimeouts::new()
+            .with_default(Duration::from_secs(60))
+            .with_method("workspace/symbol", Duration::from_millis(50)),
+    );
+    let slow = client.request("workspace/symbol", json!({})).unwrap();
+    let fast = client.request("textDocument/hover", json!({})).unwrap();
+    assert_eq!((slow, fast), (1, 2));
+
+    std::thread::sleep(Duration::from_millis(80));
+    let expired = client.expire_requests(Instant::now()).unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].id, slow);
+
+    let msg = match client.recv_timeout(Duration::from_secs(2)) {
+        Some(LspInbound::Message(msg)) => msg,
+        other => panic!("unexpected inbound: {other:?}"),
+    };
+    assert_eq!(msg["id"], json!(fast));
+    assert_eq!(msg["result"], json!("on time"));
+    assert!(client.recv_timeout(Duration::from_millis(100)).is_none());
+    assert!(client.in_flight_requests().is_empty());
+
+    // Ids keep increasing, so a late answer can never be mistaken for a newer request.
+    assert_eq!(client.request("workspace/symbol", json!({})).unwrap(), 3);
+}
+
+#[test]
+fn test_session_reports_timed_out_requests_and_cancels_all_pending() {
+    let cmd = scripted_command("0", &frame(&[initialize_response(json!({}))]));
+    let mut session = common::start(cmd, "");
+    session.set_request_timeouts(
+        LspRequestTimeouts::new().with_method("textDocument/hover", Duration::from_millis(20)),
+    );
+
+    let hover = session.request("textDocument/hover", json!({})).unwrap();
+    let rename = session.request("textDocument/rename", json!({})).unwrap();
+    std::thread::sleep(Duration::from_millis(40));
+
+    let state = EditorStateManager::new("", 80);
+    session.poll_edits(&state).unwrap();
+    let timed_out: Vec<LspTimedOutRequest> = session
+        .drain_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            LspEvent::RequestTimedOut(request) => Some(request),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(timed_out.len(), 1);
+    assert_eq!(timed_out[0].id, hover);
+    assert_eq!(timed_out[0].method, "textDocument/hover");
+
+    assert_eq!(session.cancel_all_pending().unwrap(), vec![rename]);
+    assert!(session.client().in_flight_requests().is_empty());
+    assert!(session.cancel_all_pending().unwrap().is_empty());
+}
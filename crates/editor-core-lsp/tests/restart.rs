@@ -0,0 +1,147 @@
+#![cfg(unix)]
+
+mod common;
+
+use editor_core::intervals::Interval;
+use editor_core::processing::ProcessingEdit;
+use editor_core::{EditorStateManager, StyleLayerId};
+use editor_core_lsp::{
+    LspContentChange, LspEvent, LspPosition, LspRange, LspRestartPolicy, LspServerRestart,
+    LspSession, LspSessionStartOptions,
+};
+use serde_json::json;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+fn start(cmd: Command, restart_policy: LspRestartPolicy) -> LspSession {
+    LspSession::start(LspSessionStartOptions {
+        restart_policy,
+        ..common::start_options(cmd, "let x = 1;\n")
+    })
+    .expect("start session")
+}
+
+/// A "server" that echoes every message back and exits after `lifetime_secs`.
+fn short_lived(lifetime_secs: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg("exec timeout \"$1\" cat")
+        .arg("sh")
+        .arg(lifetime_secs)
+        .stderr(Stdio::null());
+    cmd
+}
+
+/// Replace the character at `column` of the first line.
+fn replace_char(column: u32, text: &str) -> LspContentChange {
+    LspContentChange {
+        range: LspRange::new(LspPosition::new(0, column), LspPosition::new(0, column + 1)),
+        text: text.to_string(),
+    }
+}
+
+fn restarts(session: &mut LspSession) -> Vec<LspServerRestart> {
+    session
+        .drain_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            LspEvent::ServerRestarted(restart) => Some(restart),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_manual_restart_abandons_requests_and_keeps_the_document() {
+    let mut cmd = Command::new("cat");
+    cmd.stderr(Stdio::null());
+    let mut session = start(cmd, LspRestartPolicy::default());
+    session.did_change(replace_char(4, "y")).unwrap();
+    let version = session.document().version;
+    let hover = session.request("textDocument/hover", json!({})).unwrap();
+
+    session.restart("let y = 1;\n").unwrap();
+    let events = restarts(&mut session);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].attempt, 0);
+    assert_eq!(events[0].abandoned_requests, vec![hover]);
+    assert!(events[0].closed_documents.is_empty());
+
+    assert_eq!(session.document().version, version);
+    assert!(!session.client().in_flight_requests().contains(&hover));
+    // Ids continue after the restart, so answers from the old server cannot match new requests.
+    assert!(session.request("textDocument/hover", json!({})).unwrap() > hover);
+}
+
+#[test]
+fn test_crashed_server_is_restarted_and_highlights_survive() {
+    let marker =
+        std::env::temp_dir().join(format!("editor-core-lsp-restart-{}", std::process::id()));
+    let _ = std::fs::remove_file(&marker);
+    // The first server dies after 300ms; the respawned one (marker exists) stays up.
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg("if [ -e \"$1\" ]; then exec cat; fi; touch \"$1\"; exec timeout 0.3 cat")
+        .arg("sh")
+        .arg(&marker)
+        .stderr(Stdio::null());
+    let policy = LspRestartPolicy::new(2, Duration::from_millis(20), Duration::from_secs(1));
+    let mut session = start(cmd, policy);
+
+    let mut manager = EditorStateManager::new("let x = 1;\n", 80);
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceStyleLayer {
+        layer: StyleLayerId::SEMANTIC_TOKENS,
+        intervals: vec![Interval::new(0, 3, 1)],
+    }]);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut seen = Vec::new();
+    while seen.is_empty() {
+        assert!(Instant::now() < deadline, "server was not restarted");
+        session.poll(&mut manager).unwrap();
+        seen = restarts(&mut session);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let _ = std::fs::remove_file(&marker);
+
+    assert_eq!(seen[0].attempt, 1);
+    assert!(seen[0].reason.contains("LSP 连接已断开"));
+    assert!(!session.is_restarting());
+    assert!(
+        manager
+            .editor()
+            .style_layers
+            .contains_key(&StyleLayerId::SEMANTIC_TOKENS)
+    );
+
+    // The new server keeps running.
+    std::thread::sleep(Duration::from_millis(400));
+    session.poll(&mut manager).unwrap();
+    assert!(restarts(&mut session).is_empty());
+    session.did_change(replace_char(8, "2")).unwrap();
+}
+
+#[test]
+fn test_restart_policy_gives_up_after_max_attempts() {
+    let policy = LspRestartPolicy::new(1, Duration::from_millis(10), Duration::from_millis(10));
+    assert_eq!(policy.backoff(1), Duration::from_millis(10));
+    assert_eq!(
+        LspRestartPolicy::new(5, Duration::from_millis(100), Duration::from_millis(250)).backoff(3),
+        Duration::from_millis(250)
+    );
+
+    let mut session = start(short_lived("0.2"), policy);
+    let mut manager = EditorStateManager::new("let x = 1;\n", 80);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut restarted = 0;
+    let err = loop {
+        assert!(Instant::now() < deadline, "session never gave up");
+        match session.poll(&mut manager) {
+            Ok(()) => restarted += restarts(&mut session).len(),
+            Err(err) => break err,
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(restarted, 1);
+    assert!(err.contains("LSP 连接已断开"));
+}
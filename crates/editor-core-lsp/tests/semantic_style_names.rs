@@ -0,0 +1,35 @@
+use editor_core::intervals::StyleRegistry;
+use editor_core_lsp::SemanticTokensLegend;
+
+fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec!["variable".to_string(), "function".to_string()],
+        token_modifiers: vec!["declaration".to_string(), "static".to_string()],
+    }
+}
+
+#[test]
+fn test_legend_style_names() {
+    let legend = legend();
+    assert_eq!(legend.style_name(1, 0), "semantic:function");
+    assert_eq!(
+        legend.style_name(1, 0b11),
+        "semantic:function.declaration.static"
+    );
+    assert_eq!(legend.style_name(0, 0b10), "semantic:variable.static");
+    // Indices outside the legend are kept as numbers.
+    assert_eq!(legend.style_name(7, 0b100), "semantic:7.2");
+}
+
+#[test]
+fn test_legend_style_names_map_to_shared_registry_ids() {
+    let legend = legend();
+    let registry = StyleRegistry::new();
+    let function = registry.style_id(&legend.style_name(1, 0));
+    assert_eq!(registry.style_id("semantic:function"), function);
+    assert_ne!(registry.style_id(&legend.style_name(1, 1)), function);
+    assert_eq!(
+        registry.name_for_style_id(function).as_deref(),
+        Some("semantic:function")
+    );
+}
@@ -0,0 +1,107 @@
+use editor_core::LineIndex;
+use editor_core_lsp::{
+    SemanticTokensEdit, SemanticTokensError, SemanticTokensManager, semantic_tokens_to_intervals,
+};
+
+fn intervals(data: &[u32], line_index: &LineIndex) -> Vec<(usize, usize, u32)> {
+    semantic_tokens_to_intervals(data, line_index, |token_type, _| token_type)
+        .unwrap()
+        .into_iter()
+        .map(|interval| (interval.start, interval.end, interval.style_id))
+        .collect()
+}
+
+fn edit(start: usize, delete_count: usize, data: &[u32]) -> SemanticTokensEdit {
+    SemanticTokensEdit {
+        start,
+        delete_count,
+        data: data.to_vec(),
+    }
+}
+
+#[test]
+fn test_delta_sequence_matches_full_decode() {
+    // "fn 😀() {}\nlet x = 1;\nlet y = 2;" — the emoji takes two UTF-16 code units.
+    let line_index = LineIndex::from_text("fn 😀() {}\nlet x = 1;\nlet y = 2;");
+    let mut manager = SemanticTokensManager::new();
+    manager.set_full(
+        vec![
+            0, 0, 2, 1, 0, // fn
+            0, 3, 2, 2, 0, // 😀
+            1, 0, 3, 1, 0, // let
+            0, 4, 1, 3, 0, // x
+        ],
+        Some("1".to_string()),
+    );
+
+    // Append the second `let y`, then retype `x` and `😀` in a single out-of-order delta.
+    manager
+        .apply_delta(
+            &[edit(20, 0, &[1, 0, 3, 1, 0, 0, 4, 1, 3, 0])],
+            Some("2".to_string()),
+        )
+        .unwrap();
+    manager
+        .apply_delta(
+            &[edit(15, 5, &[0, 4, 1, 4, 0]), edit(8, 1, &[5])],
+            Some("3".to_string()),
+        )
+        .unwrap();
+    assert_eq!(manager.result_id(), Some("3"));
+
+    let full = vec![
+        0, 0, 2, 1, 0, //
+        0, 3, 2, 5, 0, //
+        1, 0, 3, 1, 0, //
+        0, 4, 1, 4, 0, //
+        1, 0, 3, 1, 0, //
+        0, 4, 1, 3, 0, //
+    ];
+    assert_eq!(manager.data(), full.as_slice());
+    assert_eq!(
+        intervals(manager.data(), &line_index),
+        vec![
+            (0, 2, 1),
+            (3, 4, 5),
+            (10, 13, 1),
+            (14, 15, 4),
+            (21, 24, 1),
+            (25, 26, 3),
+        ]
+    );
+
+    // Deleting everything leaves an empty token stream.
+    manager
+        .apply_delta(&[edit(0, full.len(), &[])], None)
+        .unwrap();
+    assert!(manager.data().is_empty());
+    assert_eq!(manager.result_id(), None);
+}
+
+#[test]
+fn test_invalid_delta_clears_cache() {
+    let data = vec![0, 0, 2, 1, 0, 1, 0, 3, 1, 0];
+
+    let mut manager = SemanticTokensManager::new();
+    manager.set_full(data.clone(), Some("1".to_string()));
+    assert_eq!(
+        manager.apply_delta(&[edit(0, 5, &[]), edit(3, 1, &[])], Some("2".to_string())),
+        Err(SemanticTokensError::InvalidDeltaEdit {
+            start: 3,
+            delete_count: 1
+        })
+    );
+    assert!(manager.data().is_empty());
+    assert_eq!(manager.result_id(), None);
+
+    manager.set_full(data.clone(), Some("1".to_string()));
+    assert!(manager.apply_delta(&[edit(8, 5, &[])], None).is_err());
+    assert_eq!(manager.result_id(), None);
+
+    manager.set_full(data, Some("1".to_string()));
+    assert_eq!(
+        manager.apply_delta(&[edit(10, 0, &[1, 0])], None),
+        Err(SemanticTokensError::InvalidDataLength(12))
+    );
+    assert_eq!(manager.result_id(), None);
+}
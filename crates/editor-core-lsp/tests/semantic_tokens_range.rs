@@ -0,0 +1,76 @@
+use editor_core::LineIndex;
+use editor_core::intervals::Interval;
+use editor_core_lsp::{SemanticTokensManager, semantic_tokens_to_intervals};
+
+fn decode(data: &[u32], line_index: &LineIndex) -> Vec<Interval> {
+    semantic_tokens_to_intervals(data, line_index, |token_type, _| token_type).unwrap()
+}
+
+fn spans(manager: &SemanticTokensManager, line_index: &LineIndex) -> Vec<(usize, usize, u32)> {
+    manager
+        .to_intervals(line_index, |token_type, _| token_type)
+        .into_iter()
+        .map(|interval| (interval.start, interval.end, interval.style_id))
+        .collect()
+}
+
+// Four lines of `let x;`: six chars plus a newline each.
+const TEXT: &str = "let a;\nlet b;\nlet c;\nlet d;";
+
+#[test]
+fn test_range_result_then_full_result() {
+    let line_index = LineIndex::from_text(TEXT);
+    let mut manager = SemanticTokensManager::new();
+
+    // Viewport covers lines 1..3; range data is encoded from the start of the document.
+    let range = decode(&[1, 0, 3, 1, 0, 0, 4, 1, 2, 0, 1, 0, 3, 1, 0], &line_index);
+    assert!(manager.merge_range(1, 1, 3, range));
+    assert_eq!(
+        spans(&manager, &line_index),
+        vec![(7, 10, 1), (11, 12, 2), (14, 17, 1)]
+    );
+
+    // A second viewport range for the same version adds to the first one.
+    let range = decode(&[3, 4, 1, 3, 0], &line_index);
+    assert!(manager.merge_range(1, 3, 4, range));
+    assert_eq!(manager.range_spans(), vec![(1, 3), (3, 4)]);
+    assert_eq!(spans(&manager, &line_index).len(), 4);
+
+    // The full result replaces all range results.
+    let full = vec![0, 0, 3, 5, 0, 1, 0, 3, 5, 0, 1, 0, 3, 5, 0, 1, 0, 3, 5, 0];
+    manager.set_full(full, Some("1".to_string()));
+    manager.set_version(Some(1));
+    assert!(manager.range_spans().is_empty());
+    assert_eq!(
+        spans(&manager, &line_index),
+        vec![(0, 3, 5), (7, 10, 5), (14, 17, 5), (21, 24, 5)]
+    );
+}
+
+#[test]
+fn test_stale_range_result_after_full_result_is_ignored() {
+    let line_index = LineIndex::from_text(TEXT);
+    let mut manager = SemanticTokensManager::new();
+    manager.set_full(vec![0, 0, 3, 5, 0, 3, 0, 3, 5, 0], None);
+    manager.set_version(Some(2));
+
+    // The range request for version 2 was sent before the full one but answered after it.
+    let late = decode(&[1, 0, 3, 1, 0], &line_index);
+    assert!(!manager.merge_range(2, 0, 4, late));
+    assert_eq!(spans(&manager, &line_index), vec![(0, 3, 5), (21, 24, 5)]);
+
+    // After an edit (version 3) a range result overlays the stale full data on its lines only,
+    // and a range result for an older version is dropped once a newer one lands.
+    assert!(manager.merge_range(3, 0, 4, Vec::new()));
+    let range = decode(&[1, 4, 1, 2, 0], &line_index);
+    assert!(manager.merge_range(4, 1, 2, range));
+    assert_eq!(manager.range_spans(), vec![(1, 2)]);
+    assert_eq!(
+        spans(&manager, &line_index),
+        vec![(0, 3, 5), (11, 12, 2), (21, 24, 5)]
+    );
+
+    manager.clear();
+    assert!(manager.to_intervals(&line_index, |t, _| t).is_empty());
+    assert_eq!(manager.version(), None);
+}
@@ -0,0 +1,373 @@
+#[cfg(unix)]
+mod common;
+
+use editor_core::processing::ProcessingEdit;
+use editor_core::{EditorStateManager, LineIndex, StyleLayerId};
+use editor_core_lsp::{
+    LspDiagnostic, LspFeature, LspPublishDiagnosticsParams, LspSession, LspSessionGroup,
+    LspSessionOutput, lsp_diagnostics_to_processing_edits_for,
+};
+use serde_json::json;
+use std::time::Duration;
+
+fn publish(message: &str, character: u32) -> LspPublishDiagnosticsParams {
+    let diagnostic = LspDiagnostic::from_value(&json!({
+        "range": {
+            "start": { "line": 0, "character": character },
+            "end": { "line": 0, "character": character + 1 }
+        },
+        "severity": 2,
+        "source": "shared-tool",
+        "message": message
+    }))
+    .unwrap();
+    LspPublishDiagnosticsParams {
+        uri: "file:///a.rs".to_string(),
+        diagnostics: vec![diagnostic],
+        version: None,
+    }
+}
+
+#[test]
+fn test_two_providers_keep_separate_diagnostics_and_layers() {
+    let text = "let x = 1;\n";
+    let line_index = LineIndex::from_text(text);
+    let server = LspSessionOutput::for_provider(
+        "rust-analyzer",
+        StyleLayerId::SEMANTIC_TOKENS,
+        StyleLayerId::DIAGNOSTICS,
+    );
+    let linter =
+        LspSessionOutput::for_provider("linter", StyleLayerId::new(101), StyleLayerId::new(102));
+
+    let mut manager = EditorStateManager::new(text, 80);
+    let server_edits =
+        lsp_diagnostics_to_processing_edits_for(&line_index, &publish("unused", 4), &server);
+    assert!(matches!(
+        &server_edits[0],
+        ProcessingEdit::ReplaceStyleLayer { layer, .. } if *layer == StyleLayerId::DIAGNOSTICS
+    ));
+    manager.apply_processing_edits(server_edits);
+    let linter_edits =
+        lsp_diagnostics_to_processing_edits_for(&line_index, &publish("style", 0), &linter);
+    assert!(matches!(
+        &linter_edits[0],
+        ProcessingEdit::ReplaceStyleLayer { layer, .. } if *layer == StyleLayerId::new(102)
+    ));
+    manager.apply_processing_edits(linter_edits);
+
+    let providers: Vec<Option<&str>> = manager
+        .editor()
+        .diagnostics()
+        .iter()
+        .map(|d| d.provider.as_deref())
+        .collect();
+    assert_eq!(providers, vec![Some("rust-analyzer"), Some("linter")]);
+    // The server-reported `source` is kept as-is.
+    assert!(
+        manager
+            .editor()
+            .diagnostics()
+            .iter()
+            .all(|d| d.source.as_deref() == Some("shared-tool"))
+    );
+
+    // Clearing the linter leaves the language server's diagnostics and underlines alone.
+    manager.apply_processing_edits(linter.clear_edits());
+    assert_eq!(manager.editor().diagnostics().len(), 1);
+    assert_eq!(manager.editor().diagnostics()[0].message, "unused");
+    assert!(
+        manager
+            .editor()
+            .style_layers
+            .contains_key(&StyleLayerId::DIAGNOSTICS)
+    );
+    assert!(
+        !manager
+            .editor()
+            .style_layers
+            .contains_key(&StyleLayerId::new(102))
+    );
+}
+
+#[test]
+fn test_default_output_replaces_all_diagnostics() {
+    let edits = lsp_diagnostics_to_processing_edits_for(
+        &LineIndex::from_text("ab"),
+        &publish("x", 0),
+        &LspSessionOutput::default(),
+    );
+    assert!(matches!(
+        edits.as_slice(),
+        [
+            ProcessingEdit::ReplaceStyleLayer { .. },
+            ProcessingEdit::ReplaceDiagnostics { .. }
+        ]
+    ));
+    assert_eq!(
+        LspSessionOutput::default().clear_edits().len(),
+        4,
+        "semantic tokens, diagnostics layer, diagnostics, folding"
+    );
+}
+
+/// A session whose "server" echoes messages back (see [`common::echo_command`]).
+#[cfg(unix)]
+fn echo_session(output: LspSessionOutput) -> LspSession {
+    let mut session = common::start(common::echo_command(), "let x = 1;\n");
+    session.set_output(output);
+    session
+}
+
+#[cfg(unix)]
+#[test]
+fn test_group_attach_validates_providers_and_detach_clears() {
+    let mut group = LspSessionGroup::new();
+    assert!(
+        group
+            .attach(echo_session(LspSessionOutput::default()))
+            .is_err()
+    );
+
+    group
+        .attach(echo_session(LspSessionOutput::for_provider(
+            "server",
+            StyleLayerId::SEMANTIC_TOKENS,
+            StyleLayerId::DIAGNOSTICS,
+        )))
+        .unwrap();
+    let duplicate =
+        LspSessionOutput::for_provider("server", StyleLayerId::new(101), StyleLayerId::new(102));
+    assert!(group.attach(echo_session(duplicate)).is_err());
+    let same_layer =
+        LspSessionOutput::for_provider("linter", StyleLayerId::new(101), StyleLayerId::DIAGNOSTICS);
+    assert!(group.attach(echo_session(same_layer)).is_err());
+    group
+        .attach(echo_session(LspSessionOutput::for_provider(
+            "linter",
+            StyleLayerId::new(101),
+            StyleLayerId::new(102),
+        )))
+        .unwrap();
+    assert_eq!(group.len(), 2);
+
+    // Neither echo server supports semantic tokens or folding ranges.
+    let output = group.session("linter").unwrap().output();
+    assert_eq!(output.semantic_tokens_layer, None);
+    assert!(!output.folding_ranges);
+
+    let (_, edits) = group.detach("linter").unwrap();
+    assert!(matches!(
+        edits.as_slice(),
+        [
+            ProcessingEdit::ClearStyleLayer { layer },
+            ProcessingEdit::ClearProviderDiagnostics { provider }
+        ] if *layer == StyleLayerId::new(102) && provider == "linter"
+    ));
+    assert!(group.session("linter").is_none());
+    assert_eq!(group.len(), 1);
+}
+
+#[cfg(unix)]
+fn publish_notification(diagnostics: serde_json::Value) -> serde_json::Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": "file:///a.rs", "diagnostics": diagnostics }
+    })
+}
+
+#[cfg(unix)]
+fn warning(start: u32, end: u32, message: &str) -> serde_json::Value {
+    json!({
+        "range": {
+            "start": { "line": 0, "character": start },
+            "end": { "line": 0, "character": end }
+        },
+        "severity": 2,
+        "message": message
+    })
+}
+
+/// A session whose "server" writes `output` (already framed) and then ignores its input.
+#[cfg(unix)]
+fn scripted_session(output: &str, session_output: LspSessionOutput) -> LspSession {
+    let mut session = common::start(common::scripted_command("0", output), "let x = 1;\n");
+    session.set_output(session_output);
+    session
+}
+
+/// Process the group until `done` holds for the editor state (or give up after a few seconds).
+#[cfg(unix)]
+fn process_until(
+    group: &mut LspSessionGroup,
+    manager: &mut EditorStateManager,
+    done: impl Fn(&EditorStateManager, &LspSessionGroup) -> bool,
+) {
+    use editor_core::processing::DocumentProcessor;
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    while !done(manager, group) {
+        assert!(std::time::Instant::now() < deadline, "timed out");
+        let edits = group.process(manager).expect("group poll never fails");
+        manager.apply_processing_edits(edits);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(unix)]
+fn messages_of(manager: &EditorStateManager, provider: &str) -> Vec<String> {
+    manager
+        .editor()
+        .diagnostics()
+        .iter()
+        .filter(|d| d.provider.as_deref() == Some(provider))
+        .map(|d| d.message.clone())
+        .collect()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_group_merges_overlapping_diagnostics_and_routes_features() {
+    let legend =
+        json!({ "legend": { "tokenTypes": ["keyword"], "tokenModifiers": [] }, "full": true });
+    // The server publishes twice; only its latest set may survive, next to the linter's.
+    let server = common::frame(&[
+        common::initialize_response(
+            json!({ "completionProvider": {}, "semanticTokensProvider": legend }),
+        ),
+        publish_notification(json!([warning(4, 5, "stale")])),
+        publish_notification(json!([warning(4, 5, "unused variable")])),
+    ]);
+    let linter = common::frame(&[
+        common::initialize_response(json!({
+            "documentFormattingProvider": true,
+            "completionProvider": false,
+            "semanticTokensProvider": legend
+        })),
+        publish_notification(json!([
+            warning(0, 9, "prefer const"),
+            warning(4, 9, "name")
+        ])),
+    ]);
+
+    let mut group = LspSessionGroup::new();
+    group
+        .attach(scripted_session(
+            &server,
+            LspSessionOutput::for_provider(
+                "server",
+                StyleLayerId::SEMANTIC_TOKENS,
+                StyleLayerId::DIAGNOSTICS,
+            ),
+        ))
+        .unwrap();
+    group
+        .attach(scripted_session(
+            &linter,
+            LspSessionOutput::for_provider(
+                "linter",
+                StyleLayerId::new(101),
+                StyleLayerId::new(102),
+            ),
+        ))
+        .unwrap();
+
+    // Semantic tokens come from the first capable session until another one is made primary.
+    let highlighter = |group: &LspSessionGroup| {
+        group
+            .sessions()
+            .filter(|s| s.output().semantic_tokens_layer.is_some())
+            .map(|s| s.output().provider.clone().unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(highlighter(&group), vec!["server"]);
+    group.set_primary("linter").unwrap();
+    assert_eq!(highlighter(&group), vec!["linter"]);
+    assert!(group.set_primary("missing").is_err());
+
+    // Per-feature routing over the union of capabilities.
+    let provider = |session: Option<&LspSession>| session.and_then(|s| s.output().provider.clone());
+    assert_eq!(
+        provider(group.route(LspFeature::Completion)).as_deref(),
+        Some("server")
+    );
+    assert_eq!(
+        provider(group.route(LspFeature::Formatting)).as_deref(),
+        Some("linter")
+    );
+    assert!(group.route(LspFeature::Rename).is_none());
+    assert!(group.supports(LspFeature::Formatting));
+    let capabilities = group.capabilities();
+    assert_eq!(capabilities["completionProvider"], json!({}));
+    assert_eq!(capabilities["documentFormattingProvider"], json!(true));
+    group.set_route(LspFeature::Completion, "linter").unwrap();
+    assert_eq!(
+        provider(group.route(LspFeature::Completion)).as_deref(),
+        Some("linter")
+    );
+
+    let mut manager = EditorStateManager::new("let x = 1;\n", 80);
+    process_until(&mut group, &mut manager, |manager, _| {
+        messages_of(manager, "server") == ["unused variable"]
+            && messages_of(manager, "linter").len() == 2
+    });
+    assert_eq!(messages_of(&manager, "linter"), ["prefer const", "name"]);
+    let layers = &manager.editor().style_layers;
+    assert!(layers.contains_key(&StyleLayerId::DIAGNOSTICS));
+    assert!(layers.contains_key(&StyleLayerId::new(102)));
+
+    // Sync is fanned out to both servers.
+    group.did_save(None);
+    assert!(group.take_failures().is_empty());
+    assert_eq!(group.len(), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_group_detaches_failed_session_and_keeps_the_others() {
+    let healthy = common::frame(&[
+        common::initialize_response(json!({})),
+        publish_notification(json!([warning(4, 5, "unused variable")])),
+    ]);
+    // A broken frame after `initialize` makes the connection fail.
+    let broken =
+        common::frame(&[common::initialize_response(json!({}))]) + "Content-Length: 5\r\n\r\nnope!";
+
+    let mut group = LspSessionGroup::new();
+    group
+        .attach(scripted_session(
+            &broken,
+            LspSessionOutput::for_provider(
+                "broken",
+                StyleLayerId::new(101),
+                StyleLayerId::new(102),
+            ),
+        ))
+        .unwrap();
+    group
+        .attach(scripted_session(
+            &healthy,
+            LspSessionOutput::for_provider(
+                "healthy",
+                StyleLayerId::SEMANTIC_TOKENS,
+                StyleLayerId::DIAGNOSTICS,
+            ),
+        ))
+        .unwrap();
+
+    let mut manager = EditorStateManager::new("let x = 1;\n", 80);
+    process_until(&mut group, &mut manager, |manager, group| {
+        group.len() == 1 && !messages_of(manager, "healthy").is_empty()
+    });
+
+    let failures = group.take_failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].provider, "broken");
+    assert!(group.session("broken").is_none());
+    assert!(group.take_failures().is_empty());
+
+    group.did_save(Some("let x = 1;\n".to_string()));
+    assert_eq!(group.len(), 1);
+    assert_eq!(messages_of(&manager, "healthy"), ["unused variable"]);
+}
@@ -0,0 +1,62 @@
+use editor_core_lsp::{HoverBlock, signature_help_from_value};
+use serde_json::json;
+
+#[test]
+fn test_signature_help_resolves_string_and_offset_labels() {
+    let help = signature_help_from_value(&json!({
+        "signatures": [
+            {
+                "label": "fn add(a: i32, b: i32) -> i32",
+                "documentation": "Adds two numbers.",
+                // Repeated type text: the second `i32` must not match the first one.
+                "parameters": [
+                    { "label": "a: i32" },
+                    { "label": "i32", "documentation": { "kind": "markdown", "value": "**rhs**" } }
+                ]
+            },
+            {
+                // The emoji takes two UTF-16 code units, so `y` is at utf16 10..11 but char 9..10.
+                "label": "fn f(😀x, y)",
+                "parameters": [{ "label": [5, 8] }, { "label": [10, 11] }],
+                "activeParameter": 1
+            }
+        ],
+        "activeSignature": 1,
+        "activeParameter": 0
+    }))
+    .unwrap();
+
+    let add = &help.signatures[0];
+    assert_eq!(
+        add.documentation,
+        Some(HoverBlock::PlainText("Adds two numbers.".to_string()))
+    );
+    assert_eq!(add.parameters[0].label_range, Some((7, 13)));
+    assert_eq!(add.parameters[1].label_range, Some((18, 21)));
+    assert_eq!(
+        add.parameters[1].documentation,
+        Some(HoverBlock::Markdown("**rhs**".to_string()))
+    );
+
+    // The per-signature active parameter wins over the top-level one.
+    assert_eq!(help.active().label, "fn f(😀x, y)");
+    assert_eq!(help.active().parameters[0].label, "😀x");
+    assert_eq!(help.active_parameter_info().unwrap().label, "y");
+    assert_eq!(help.active_parameter_range(), Some((9, 10)));
+}
+
+#[test]
+fn test_signature_help_defaults_and_empty_results() {
+    let help = signature_help_from_value(&json!({
+        "signatures": [{ "label": "print(value)", "parameters": [{ "label": "value" }, { "label": "missing" }] }],
+        "activeSignature": 7
+    }))
+    .unwrap();
+    assert_eq!(help.active_signature, 0);
+    assert_eq!(help.active_parameter, None);
+    assert_eq!(help.active_parameter_range(), Some((6, 11)));
+    assert_eq!(help.signatures[0].parameters[1].label_range, None);
+
+    assert!(signature_help_from_value(&json!(null)).is_none());
+    assert!(signature_help_from_value(&json!({ "signatures": [] })).is_none());
+}
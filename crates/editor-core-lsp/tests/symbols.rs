@@ -1,8 +1,8 @@
-use editor_core::{LineIndex, Utf16Position, Utf16Range};
+use editor_core::{EditorStateManager, LineIndex, Utf16Position, Utf16Range};
 use editor_core::{ProcessingEdit, SymbolKind};
 use editor_core_lsp::{
-    lsp_document_symbols_to_outline, lsp_document_symbols_to_processing_edit,
-    lsp_workspace_symbols_to_results,
+    document_symbols_from_value, lsp_document_symbols_to_outline,
+    lsp_document_symbols_to_processing_edit, lsp_workspace_symbols_to_results,
 };
 use serde_json::json;
 
@@ -67,3 +67,105 @@ fn test_workspace_symbols_parse_location_utf16_ranges() {
         Utf16Range::new(Utf16Position::new(10, 2), Utf16Position::new(10, 5))
     );
 }
+
+fn range(start: (u32, u32), end: (u32, u32)) -> serde_json::Value {
+    json!({
+        "start": { "line": start.0, "character": start.1 },
+        "end": { "line": end.0, "character": end.1 }
+    })
+}
+
+fn char_offset(text: &str, needle: &str) -> usize {
+    text[..text.find(needle).unwrap()].chars().count()
+}
+
+#[test]
+fn test_document_symbols_nested_impl_blocks_from_rust_analyzer() {
+    let text = "struct Point {\n    x: i32,\n}\n\nimpl Point {\n    fn new() -> Self {\n        Point { x: 0 }\n    }\n}\n\nmod geo {\n    impl super::Point {\n        /* 📏 */ fn norm(&self) -> i32 { self.x }\n    }\n}\n";
+    let mut state = EditorStateManager::new(text, 80);
+
+    // Canned rust-analyzer `textDocument/documentSymbol` response. The emoji on line 12 takes two
+    // UTF-16 code units, so `norm` is at utf16 20..24.
+    let result = json!([
+        {
+            "name": "Point", "kind": 23,
+            "range": range((0, 0), (2, 1)), "selectionRange": range((0, 7), (0, 12)),
+            "children": [
+                { "name": "x", "detail": "i32", "kind": 8,
+                  "range": range((1, 4), (1, 10)), "selectionRange": range((1, 4), (1, 5)) }
+            ]
+        },
+        {
+            "name": "impl Point", "kind": 19,
+            "range": range((4, 0), (8, 1)), "selectionRange": range((4, 5), (4, 10)),
+            "children": [
+                { "name": "new", "detail": "fn() -> Self", "kind": 12,
+                  "range": range((5, 4), (7, 5)), "selectionRange": range((5, 7), (5, 10)) }
+            ]
+        },
+        {
+            "name": "geo", "kind": 2,
+            "range": range((10, 0), (14, 1)), "selectionRange": range((10, 4), (10, 7)),
+            "children": [
+                {
+                    "name": "impl super::Point", "kind": 19,
+                    "range": range((11, 4), (13, 5)), "selectionRange": range((11, 9), (11, 22)),
+                    "children": [
+                        { "name": "norm", "detail": "fn(&self) -> i32", "kind": 6,
+                          "range": range((12, 17), (12, 49)), "selectionRange": range((12, 20), (12, 24)) }
+                    ]
+                }
+            ]
+        }
+    ]);
+
+    let outline = document_symbols_from_value(&result, &state.editor().line_index);
+    let names: Vec<&str> = outline
+        .flatten_preorder()
+        .iter()
+        .map(|sym| sym.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "Point",
+            "x",
+            "impl Point",
+            "new",
+            "geo",
+            "impl super::Point",
+            "norm"
+        ]
+    );
+
+    let geo = &outline.symbols[2];
+    assert_eq!(geo.kind, SymbolKind::Module);
+    let impl_block = &geo.children[0];
+    assert_eq!(impl_block.kind, SymbolKind::Object);
+    let norm = &impl_block.children[0];
+    assert_eq!(norm.kind, SymbolKind::Method);
+    assert_eq!(norm.detail.as_deref(), Some("fn(&self) -> i32"));
+    let norm_start = char_offset(text, "norm");
+    assert_eq!(norm.selection_range.start, norm_start);
+    assert_eq!(norm.selection_range.end, norm_start + 4);
+    assert_eq!(norm.range.start, char_offset(text, "fn norm"));
+    assert_eq!(norm.range.end, char_offset(text, "self.x }") + 8);
+    assert_eq!(
+        norm.utf16_selection_range,
+        Some(Utf16Range::new(
+            Utf16Position::new(12, 20),
+            Utf16Position::new(12, 24)
+        ))
+    );
+    assert_eq!(
+        outline.symbols[1].children[0].selection_range.start,
+        char_offset(text, "new")
+    );
+
+    state.apply_processing_edits(vec![ProcessingEdit::ReplaceDocumentSymbols {
+        symbols: outline,
+    }]);
+    let found = state.editor().document_symbols.find_by_name("norm");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].range.start, char_offset(text, "fn norm"));
+}
@@ -0,0 +1,181 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{log_path, unframe};
+use editor_core::EditorStateManager;
+use editor_core_lsp::{
+    FileChangeType, LspDocument, LspEvent, LspFileWatcher, LspSession, LspSessionStartOptions,
+};
+use serde_json::{Value, json};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A server that writes `first`, then `second` once the client has sent
+/// `workspace/didChangeConfiguration`, and records everything the client sends to `log`.
+fn start(first: &[Value], second: &Value, log: &Path) -> LspSession {
+    let cmd = common::scripted_replies_command(
+        first,
+        log,
+        &[("workspace/didChangeConfiguration", second.clone())],
+    );
+    LspSession::start(LspSessionStartOptions {
+        document: LspDocument {
+            uri: "file:///ws/src/main.rs".to_string(),
+            language_id: "rust".to_string(),
+            version: 1,
+        },
+        ..common::start_options(cmd, "")
+    })
+    .unwrap()
+}
+
+fn poll_until(session: &mut LspSession, done: impl Fn(&[LspEvent]) -> bool) -> Vec<LspEvent> {
+    let mut state = EditorStateManager::new("", 80);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut events = Vec::new();
+    while !done(&events) {
+        assert!(Instant::now() < deadline, "timed out; events: {events:?}");
+        session.poll(&mut state).unwrap();
+        events.extend(session.drain_events());
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    events
+}
+
+fn watcher_events(events: &[LspEvent]) -> usize {
+    events
+        .iter()
+        .filter(|event| matches!(event, LspEvent::FileWatchersChanged))
+        .count()
+}
+
+#[test]
+fn test_dynamic_watcher_registration_and_configuration_pull() {
+    let log = log_path("watched-files");
+
+    let first = [
+        common::initialize_response(json!({})),
+        json!({
+            "jsonrpc": "2.0", "id": 7, "method": "client/registerCapability",
+            "params": { "registrations": [
+                {
+                    "id": "watch-1",
+                    "method": "workspace/didChangeWatchedFiles",
+                    "registerOptions": { "watchers": [
+                        { "globPattern": "**/*.rs" },
+                        {
+                            "globPattern": { "baseUri": "file:///ws", "pattern": "Cargo.toml" },
+                            "kind": 2
+                        }
+                    ] }
+                },
+                { "id": "fmt", "method": "textDocument/formatting" }
+            ] }
+        }),
+        json!({
+            "jsonrpc": "2.0", "id": 8, "method": "workspace/configuration",
+            "params": { "items": [
+                { "section": "rust-analyzer" },
+                { "scopeUri": "file:///ws/src/main.rs", "section": "other" }
+            ] }
+        }),
+    ];
+    let second = json!({
+        "jsonrpc": "2.0", "id": 9, "method": "client/unregisterCapability",
+        "params": { "unregisterations": [
+            { "id": "watch-1", "method": "workspace/didChangeWatchedFiles" }
+        ] }
+    });
+    let mut session = start(&first, &second, &log);
+    session.set_configuration_provider(|item| match item.section.as_deref() {
+        Some("rust-analyzer") => json!({ "checkOnSave": true }),
+        _ => Value::Null,
+    });
+
+    poll_until(&mut session, |events| watcher_events(events) == 1);
+    assert_eq!(
+        session.watched_globs(),
+        &[
+            LspFileWatcher {
+                registration_id: "watch-1".to_string(),
+                glob_pattern: "**/*.rs".to_string(),
+                base_uri: None,
+                kind: 7,
+            },
+            LspFileWatcher {
+                registration_id: "watch-1".to_string(),
+                glob_pattern: "Cargo.toml".to_string(),
+                base_uri: Some("file:///ws".to_string()),
+                kind: 2,
+            },
+        ]
+    );
+    assert!(session.watched_globs()[1].watches(FileChangeType::Changed));
+    assert!(!session.watched_globs()[1].watches(FileChangeType::Deleted));
+
+    session
+        .notify_watched_files_changed(vec![
+            ("/ws/src/lib.rs", FileChangeType::Created),
+            ("/ws/Cargo.toml", FileChangeType::Changed),
+        ])
+        .unwrap();
+    session
+        .notify_configuration_changed(json!({ "rust-analyzer": {} }))
+        .unwrap();
+
+    poll_until(&mut session, |events| watcher_events(events) == 1);
+    assert!(session.watched_globs().is_empty());
+
+    // Everything the client sent.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let sent = loop {
+        let sent = unframe(&std::fs::read_to_string(&log).unwrap_or_default());
+        // The reply to the unregistration (id 9) can be written after the notifications.
+        if sent
+            .iter()
+            .any(|msg| msg["method"] == "workspace/didChangeConfiguration")
+            && sent
+                .iter()
+                .any(|msg| msg["id"] == json!(9) && msg.get("method").is_none())
+        {
+            break sent;
+        }
+        assert!(Instant::now() < deadline, "client messages not recorded");
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    let _ = std::fs::remove_file(&log);
+
+    let response = |id: u64| {
+        sent.iter()
+            .find(|msg| msg["id"] == json!(id) && msg.get("method").is_none())
+            .unwrap_or_else(|| panic!("no response to {id}: {sent:?}"))
+            .clone()
+    };
+    assert_eq!(response(7)["result"], Value::Null);
+    assert_eq!(
+        response(8)["result"],
+        json!([{ "checkOnSave": true }, null])
+    );
+    assert_eq!(response(9)["result"], Value::Null);
+
+    let watched = sent
+        .iter()
+        .find(|msg| msg["method"] == "workspace/didChangeWatchedFiles")
+        .unwrap();
+    assert_eq!(
+        watched["params"]["changes"],
+        json!([
+            { "uri": "file:///ws/src/lib.rs", "type": 1 },
+            { "uri": "file:///ws/Cargo.toml", "type": 2 },
+        ])
+    );
+    let configuration = sent
+        .iter()
+        .find(|msg| msg["method"] == "workspace/didChangeConfiguration")
+        .unwrap();
+    assert_eq!(
+        configuration["params"],
+        json!({ "settings": { "rust-analyzer": {} } })
+    );
+}
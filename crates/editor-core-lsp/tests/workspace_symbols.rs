@@ -0,0 +1,124 @@
+use editor_core::SymbolKind;
+use editor_core_lsp::{
+    LspCancelHandle, LspEvent, LspNotification, LspOutbound, LspProgressParams, LspResponse,
+    WorkspaceSymbolQuery, workspace_symbol_needs_resolve,
+};
+use serde_json::{Value, json};
+use std::sync::mpsc;
+
+const TOKEN: &str = "editor-core/workspaceSymbol/1";
+
+fn progress(token: &str, value: Value) -> LspEvent {
+    LspEvent::Notification(LspNotification::Progress(LspProgressParams {
+        token: json!(token),
+        value,
+    }))
+}
+
+fn response(id: u64, result: Value) -> LspEvent {
+    LspEvent::Response(LspResponse {
+        id,
+        method: "workspace/symbol".to_string(),
+        result: Some(result),
+        error: None,
+    })
+}
+
+fn query(request_id: u64) -> (WorkspaceSymbolQuery, mpsc::Receiver<LspOutbound>) {
+    let (tx, rx) = mpsc::channel();
+    let query = WorkspaceSymbolQuery::new(
+        request_id,
+        json!(TOKEN),
+        Some(LspCancelHandle::new(tx, request_id)),
+    );
+    (query, rx)
+}
+
+#[test]
+fn test_partial_results_stream_before_final_response() {
+    let (mut query, rx) = query(7);
+
+    assert!(query.handle_event(&progress(
+        TOKEN,
+        json!([{
+            "name": "Parser",
+            "kind": 5,
+            "location": {
+                "uri": "file:///src/parser.rs",
+                "range": {
+                    "start": { "line": 3, "character": 11 },
+                    "end": { "line": 3, "character": 17 }
+                }
+            }
+        }])
+    )));
+    let first = query.take_new_symbols();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].kind, SymbolKind::Class);
+    assert_eq!(first[0].location.range.start.character, 11);
+    assert!(!workspace_symbol_needs_resolve(&first[0]));
+
+    // Another server's progress, and the newer `{ uri }`-only location shape.
+    assert!(!query.handle_event(&progress("other", json!([{ "name": "x" }]))));
+    assert!(query.handle_event(&progress(
+        TOKEN,
+        json!([
+            { "name": "parse", "kind": 12, "containerName": "parser", "location": { "uri": "file:///src/parser.rs" } },
+            { "name": "", "kind": 12, "location": { "uri": "file:///src/lib.rs" } },
+        ])
+    )));
+    let second = query.take_new_symbols();
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].name, "parse");
+    assert_eq!(second[0].container_name.as_deref(), Some("parser"));
+    assert!(workspace_symbol_needs_resolve(&second[0]));
+    assert!(query.take_new_symbols().is_empty());
+
+    // With partial results the final response is empty.
+    assert!(!query.handle_event(&response(8, json!([]))));
+    assert!(!query.is_done());
+    assert!(query.handle_event(&response(7, json!([]))));
+    assert!(query.is_done());
+    assert_eq!(query.error(), None);
+    assert_eq!(query.symbols().len(), 2);
+
+    // A finished query has nothing to cancel.
+    drop(query);
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn test_final_response_without_partial_results() {
+    let (mut query, _rx) = query(3);
+    query.handle_event(&response(
+        3,
+        json!([{
+            "name": "main",
+            "kind": 12,
+            "location": {
+                "uri": "file:///src/main.rs",
+                "range": {
+                    "start": { "line": 0, "character": 3 },
+                    "end": { "line": 0, "character": 7 }
+                }
+            }
+        }]),
+    ));
+    assert!(query.is_done());
+    assert_eq!(query.take_new_symbols()[0].name, "main");
+    // Events after the final response are ignored.
+    assert!(!query.handle_event(&progress(TOKEN, json!([{ "name": "late" }]))));
+}
+
+#[test]
+fn test_dropping_unfinished_query_cancels_request() {
+    let (mut query, rx) = query(11);
+    query.handle_event(&progress(TOKEN, json!([])));
+    drop(query);
+
+    let LspOutbound::Message(message) = rx.try_recv().unwrap();
+    assert_eq!(message["method"], json!("$/cancelRequest"));
+    assert_eq!(message["params"], json!({ "id": 11 }));
+    assert!(message.get("id").is_none());
+    assert!(rx.try_recv().is_err());
+}
@@ -1,22 +1,49 @@
 use crate::sublime_syntax::{
-    SublimeHighlightResult, SublimeScopeMapper, SublimeSyntax, SublimeSyntaxError,
-    SublimeSyntaxSet, highlight_document,
+    LineStates, SublimeScopeMapper, SublimeSyntax, SublimeSyntaxError, SublimeSyntaxSet,
+    default_fold_scopes, highlight_document_with_states, rehighlight_dirty_lines,
 };
-use editor_core::EditorStateManager;
-use editor_core::intervals::StyleLayerId;
+use editor_core::delta::TextDelta;
+use editor_core::intervals::{FoldRegion, StyleLayerId};
 use editor_core::processing::{DocumentProcessor, ProcessingEdit};
+use editor_core::{EditorStateManager, LineIndex};
+use std::collections::HashSet;
+use std::ops::Range;
 use std::sync::Arc;
 
 /// A stateful `.sublime-syntax` document processor.
 ///
 /// This owns a [`SublimeScopeMapper`] so callers can map `StyleId -> scope` for theming.
+///
+/// The processor caches the context stack at the start of every line. After an edit (taken from
+/// [`EditorStateManager::last_text_delta`], or from deltas queued with
+/// [`push_text_delta`](Self::push_text_delta)), only the edited lines are highlighted again,
+/// starting from the cached stack of the first one and stopping at the first line after them
+/// whose stack did not change; the result is emitted as
+/// [`ProcessingEdit::ReplaceStyleLayerRange`]. The whole document is highlighted again when the
+/// edits cannot be matched to the document the processor saw last.
+///
+/// Fold regions come from contexts whose meta scope matches one of the
+/// [`fold_scopes`](Self::fold_scopes) and that span 2+ lines. With
+/// [`preserve_collapsed_folds`](Self::preserve_collapsed_folds), a region stays collapsed when a
+/// collapsed region of the editor started on the same line with the same scope, even if its end
+/// line moved.
 #[derive(Debug)]
 pub struct SublimeProcessor {
     syntax: Arc<SublimeSyntax>,
     syntax_set: SublimeSyntaxSet,
     /// Maps Sublime scopes to `StyleId` values (and back) for theming.
     pub scope_mapper: SublimeScopeMapper,
+    fold_scopes: Vec<String>,
     preserve_collapsed_folds: bool,
+    line_states: LineStates,
+    /// The document as of the last highlight (`None` before the first one).
+    line_index: Option<LineIndex>,
+    last_processed_version: Option<u64>,
+    /// Deltas queued by [`SublimeProcessor::push_text_delta`], applied by the next `process()`.
+    pending_deltas: Vec<TextDelta>,
+    /// The last delta applied, so a stale `last_text_delta()` is not applied twice.
+    last_applied_delta: Option<TextDelta>,
+    last_highlighted_lines: Range<usize>,
 }
 
 impl SublimeProcessor {
@@ -26,7 +53,14 @@ impl SublimeProcessor {
             syntax,
             syntax_set,
             scope_mapper: SublimeScopeMapper::new(),
+            fold_scopes: default_fold_scopes(),
             preserve_collapsed_folds: true,
+            line_states: LineStates::default(),
+            line_index: None,
+            last_processed_version: None,
+            pending_deltas: Vec::new(),
+            last_applied_delta: None,
+            last_highlighted_lines: 0..0,
         }
     }
 
@@ -41,10 +75,29 @@ impl SublimeProcessor {
     }
 
     /// Mutably access the current syntax set (used to add/update syntaxes).
+    ///
+    /// The next `process()` highlights the whole document again.
     pub fn syntax_set_mut(&mut self) -> &mut SublimeSyntaxSet {
+        self.line_index = None;
+        self.last_processed_version = None;
         &mut self.syntax_set
     }
 
+    /// Scope selectors whose multi-line contexts become fold regions (defaults to
+    /// [`DEFAULT_FOLD_SCOPES`](crate::DEFAULT_FOLD_SCOPES)).
+    pub fn fold_scopes(&self) -> &[String] {
+        &self.fold_scopes
+    }
+
+    /// Set the scope selectors whose multi-line contexts become fold regions.
+    ///
+    /// The next `process()` highlights the whole document again.
+    pub fn set_fold_scopes(&mut self, fold_scopes: Vec<String>) {
+        self.fold_scopes = fold_scopes;
+        self.line_index = None;
+        self.last_processed_version = None;
+    }
+
     /// Returns whether fold replacement preserves the collapsed state for existing regions.
     pub fn preserve_collapsed_folds(&self) -> bool {
         self.preserve_collapsed_folds
@@ -55,17 +108,111 @@ impl SublimeProcessor {
         self.preserve_collapsed_folds = preserve;
     }
 
-    fn highlight(
+    /// Queue a text delta for the next `process()` call.
+    ///
+    /// Queued deltas must be pushed in order, starting from the document the processor last saw;
+    /// when any are queued, `process()` applies them instead of
+    /// [`EditorStateManager::last_text_delta`].
+    pub fn push_text_delta(&mut self, delta: TextDelta) {
+        self.pending_deltas.push(delta);
+    }
+
+    /// Lines highlighted by the last `process()` call (empty if it did no work).
+    pub fn last_highlighted_lines(&self) -> Range<usize> {
+        self.last_highlighted_lines.clone()
+    }
+
+    /// Record `deltas` in the line state cache. Returns `false` if they do not apply to the
+    /// document the processor saw last.
+    fn apply_text_deltas(&mut self, deltas: &[TextDelta]) -> bool {
+        let Some(line_index) = self.line_index.as_mut() else {
+            return false;
+        };
+        for delta in deltas {
+            if line_index.char_count() != delta.before_char_count {
+                return false;
+            }
+            for edit in &delta.edits {
+                if edit.end() > line_index.char_count() {
+                    return false;
+                }
+                let (start_line, _) = line_index.char_offset_to_position(edit.start);
+                let removed_lines = edit.deleted_text.matches('\n').count();
+                let added_lines = edit.inserted_text.matches('\n').count();
+                self.line_states
+                    .apply_edit(start_line, removed_lines, added_lines);
+                line_index.delete(edit.start, edit.deleted_len());
+                line_index.insert(edit.start, &edit.inserted_text);
+            }
+            if line_index.char_count() != delta.after_char_count {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Fold regions for the current folds, collapsed where `collapsed` (start line and scope of
+    /// the previously collapsed regions) has a match.
+    fn fold_edit(&self, collapsed: &HashSet<(usize, String)>) -> ProcessingEdit {
+        let regions = self
+            .line_states
+            .fold_regions()
+            .iter()
+            .map(|fold| {
+                let mut region = fold.region.clone();
+                region.is_collapsed =
+                    collapsed.contains(&(fold.region.start_line, fold.scope.clone()));
+                region
+            })
+            .collect();
+        ProcessingEdit::ReplaceFoldingRegions {
+            regions,
+            preserve_collapsed: self.preserve_collapsed_folds,
+        }
+    }
+
+    /// Start line and scope of the folds from the last highlight that are collapsed in `state`.
+    fn collapsed_folds(&self, state: &EditorStateManager) -> HashSet<(usize, String)> {
+        if !self.preserve_collapsed_folds {
+            return HashSet::new();
+        }
+        let collapsed_starts: HashSet<usize> = state
+            .editor()
+            .folding_manager
+            .derived_regions()
+            .iter()
+            .filter(|r| r.is_collapsed)
+            .map(|r| r.start_line)
+            .collect();
+        self.line_states
+            .fold_regions()
+            .iter()
+            .filter(|fold| collapsed_starts.contains(&fold.region.start_line))
+            .map(|fold| (fold.region.start_line, fold.scope.clone()))
+            .collect()
+    }
+
+    fn highlight_full(
         &mut self,
-        state: &EditorStateManager,
-    ) -> Result<SublimeHighlightResult, SublimeSyntaxError> {
-        let line_index = &state.editor().line_index;
-        highlight_document(
+        line_index: &LineIndex,
+        collapsed: &HashSet<(usize, String)>,
+    ) -> Result<Vec<ProcessingEdit>, SublimeSyntaxError> {
+        let result = highlight_document_with_states(
             self.syntax.clone(),
             line_index,
             Some(&mut self.syntax_set),
             &mut self.scope_mapper,
-        )
+            &self.fold_scopes,
+            &mut self.line_states,
+        )?;
+        self.last_highlighted_lines = 0..line_index.line_count();
+        Ok(vec![
+            ProcessingEdit::ReplaceStyleLayer {
+                layer: StyleLayerId::SUBLIME_SYNTAX,
+                intervals: result.intervals,
+            },
+            self.fold_edit(collapsed),
+        ])
     }
 }
 
@@ -73,16 +220,99 @@ impl DocumentProcessor for SublimeProcessor {
     type Error = SublimeSyntaxError;
 
     fn process(&mut self, state: &EditorStateManager) -> Result<Vec<ProcessingEdit>, Self::Error> {
-        let result = self.highlight(state)?;
-        Ok(vec![
-            ProcessingEdit::ReplaceStyleLayer {
-                layer: StyleLayerId::SUBLIME_SYNTAX,
-                intervals: result.intervals,
-            },
-            ProcessingEdit::ReplaceFoldingRegions {
-                regions: result.fold_regions,
-                preserve_collapsed: self.preserve_collapsed_folds,
-            },
-        ])
+        let version = state.version();
+        if self.last_processed_version == Some(version) {
+            self.last_highlighted_lines = 0..0;
+            return Ok(Vec::new());
+        }
+
+        let mut deltas = std::mem::take(&mut self.pending_deltas);
+        if deltas.is_empty()
+            && let Some(delta) = state.last_text_delta()
+            && self.last_applied_delta.as_ref() != Some(delta)
+        {
+            deltas.push(delta.clone());
+        }
+
+        let line_index = &state.editor().line_index;
+        let incremental = if deltas.is_empty() {
+            // No new edit: the version changed for another reason (cursor, styles, ...).
+            self.line_index
+                .as_ref()
+                .is_some_and(|previous| previous.get_text() == line_index.get_text())
+        } else {
+            self.apply_text_deltas(&deltas)
+                && self
+                    .line_index
+                    .as_ref()
+                    .is_some_and(|previous| previous.line_count() == line_index.line_count())
+        };
+        if let Some(delta) = deltas.pop() {
+            self.last_applied_delta = Some(delta);
+        }
+        // Folds of the last highlight, moved by the edits, that are collapsed in the editor.
+        let collapsed = self.collapsed_folds(state);
+
+        let edits = if incremental {
+            let previous_folds = self.line_states.fold_regions().to_vec();
+            match rehighlight_dirty_lines(
+                self.syntax.clone(),
+                line_index,
+                Some(&mut self.syntax_set),
+                &mut self.scope_mapper,
+                &self.fold_scopes,
+                &mut self.line_states,
+            )? {
+                Some(update) => {
+                    let start = line_index.position_to_char_offset(update.lines.start, 0);
+                    let end = if update.lines.end < line_index.line_count() {
+                        line_index.position_to_char_offset(update.lines.end, 0)
+                    } else {
+                        line_index.char_count()
+                    };
+                    self.last_highlighted_lines = update.lines;
+                    let mut edits = vec![ProcessingEdit::ReplaceStyleLayerRange {
+                        layer: StyleLayerId::SUBLIME_SYNTAX,
+                        start,
+                        end,
+                        intervals: update.intervals,
+                    }];
+                    let folds = self.line_states.fold_regions();
+                    if !same_regions(
+                        folds.iter().map(|f| &f.region),
+                        previous_folds.iter().map(|f| &f.region),
+                    ) || !same_regions(
+                        folds.iter().map(|f| &f.region),
+                        state.editor().folding_manager.derived_regions(),
+                    ) {
+                        edits.push(self.fold_edit(&collapsed));
+                    }
+                    edits
+                }
+                None => {
+                    self.last_highlighted_lines = 0..0;
+                    Vec::new()
+                }
+            }
+        } else {
+            self.highlight_full(line_index, &collapsed)?
+        };
+
+        self.line_index = Some(line_index.clone());
+        self.last_processed_version = Some(version);
+        Ok(edits)
     }
 }
+
+fn same_regions<'a>(
+    a: impl IntoIterator<Item = &'a FoldRegion>,
+    b: impl IntoIterator<Item = &'a FoldRegion>,
+) -> bool {
+    let lines = |regions: Vec<&FoldRegion>| {
+        let mut lines: Vec<_> = regions.iter().map(|r| (r.start_line, r.end_line)).collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    };
+    lines(a.into_iter().collect()) == lines(b.into_iter().collect())
+}
@@ -3,6 +3,7 @@ use crate::sublime_syntax::definition::{
     RawContextPattern, SyntaxDefinition,
 };
 use crate::sublime_syntax::error::SublimeSyntaxError;
+use editor_core::pattern::PatternError;
 use onig::Regex;
 use regex::Regex as RustRegex;
 use std::collections::{HashMap, HashSet};
@@ -27,13 +28,15 @@ pub struct SublimeSyntax {
     pub hidden_file_extensions: Vec<String>,
     /// Optional `first_line_match` regex source.
     pub first_line_match: Option<String>,
+    /// Compiled `first_line_match`, used by [`SublimeSyntaxSet::find_by_first_line`](crate::SublimeSyntaxSet::find_by_first_line).
+    pub first_line_regex: Option<Arc<Regex>>,
     /// Whether a `prototype` context exists.
     pub prototype_exists: bool,
     /// Compiled contexts keyed by context name.
     pub contexts: HashMap<String, CompiledContext>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// A compiled Sublime context (meta + pattern list).
 pub struct CompiledContext {
     /// Meta scope(s) applied while this context is active.
@@ -55,7 +58,7 @@ pub struct CompiledContext {
 }
 
 #[allow(clippy::large_enum_variant)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// A compiled pattern inside a context.
 pub enum CompiledPattern {
     /// A regex match pattern.
@@ -64,7 +67,7 @@ pub enum CompiledPattern {
     Include(CompiledIncludePattern),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// A compiled `include:` pattern.
 pub struct CompiledIncludePattern {
     /// Scope of the syntax that produced this pattern.
@@ -89,6 +92,8 @@ pub struct CompiledMatchPattern {
     pub scope: Vec<String>,
     /// Per-capture scopes keyed by capture group index.
     pub captures: HashMap<u32, Vec<String>>,
+    /// Scope-clearing directive applied to the matched text before `scope`.
+    pub clear_scopes: Option<ClearScopes>,
 
     /// Action taken when this pattern matches.
     pub action: MatchAction,
@@ -133,15 +138,40 @@ pub enum MatchAction {
         /// Original escape regex source string.
         escape_source: String,
         /// Compiled escape regex.
+        ///
+        /// When the escape has backreferences (see `escape_backrefs`), this is compiled with
+        /// them removed and the engine recompiles the source with the embed match's captures.
         escape: Arc<Regex>,
+        /// Whether the escape refers to captures of the embed match (`\1` .. `\9`), as
+        /// Markdown fences do to close on the same fence.
+        escape_backrefs: bool,
         /// Per-capture scopes for the escape match.
         escape_captures: HashMap<u32, Vec<String>>,
         /// Prototype patterns applied while embedded.
         with_prototype: Vec<CompiledPattern>,
     },
+    /// Record a branch point and push the first of several alternative contexts.
+    ///
+    /// A later [`MatchAction::Fail`] naming the branch point rewinds highlighting to this match
+    /// and retries it with the next alternative.
+    Branch {
+        /// Pop this many contexts before pushing the alternative.
+        pop_before: usize,
+        /// Name of the branch point (`branch_point`).
+        branch_point: String,
+        /// Alternative contexts, tried in order (`branch`).
+        branches: Vec<ContextSpec>,
+        /// Prototype patterns applied while the alternative is active.
+        with_prototype: Vec<CompiledPattern>,
+    },
+    /// Rewind to the named branch point and retry its next alternative (`fail`).
+    Fail {
+        /// Name of the branch point to rewind to.
+        branch_point: String,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// A specification of contexts to push/set.
 pub enum ContextPush {
     /// A single context.
@@ -150,7 +180,7 @@ pub enum ContextPush {
     Many(Vec<ContextSpec>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// A context reference used in stack operations.
 pub enum ContextSpec {
     /// Push a named context.
@@ -169,6 +199,99 @@ pub enum ContextSpec {
     },
 }
 
+// Compiled regexes are compared by their source.
+impl PartialEq for CompiledMatchPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.origin_scope == other.origin_scope
+            && self.regex_source == other.regex_source
+            && self.scope == other.scope
+            && self.captures == other.captures
+            && self.clear_scopes == other.clear_scopes
+            && self.action == other.action
+    }
+}
+
+impl PartialEq for MatchAction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::None, Self::None) => true,
+            (Self::Pop { count: a }, Self::Pop { count: b }) => a == b,
+            (
+                Self::Push {
+                    pop_before: a_pop,
+                    push: a_push,
+                    with_prototype: a_proto,
+                },
+                Self::Push {
+                    pop_before: b_pop,
+                    push: b_push,
+                    with_prototype: b_proto,
+                },
+            )
+            | (
+                Self::Set {
+                    pop_before: a_pop,
+                    set: a_push,
+                    with_prototype: a_proto,
+                },
+                Self::Set {
+                    pop_before: b_pop,
+                    set: b_push,
+                    with_prototype: b_proto,
+                },
+            ) => a_pop == b_pop && a_push == b_push && a_proto == b_proto,
+            (
+                Self::Embed {
+                    pop_before: a_pop,
+                    embed: a_embed,
+                    embed_scope: a_scope,
+                    escape_source: a_escape,
+                    escape_backrefs: a_backrefs,
+                    escape_captures: a_captures,
+                    with_prototype: a_proto,
+                    ..
+                },
+                Self::Embed {
+                    pop_before: b_pop,
+                    embed: b_embed,
+                    embed_scope: b_scope,
+                    escape_source: b_escape,
+                    escape_backrefs: b_backrefs,
+                    escape_captures: b_captures,
+                    with_prototype: b_proto,
+                    ..
+                },
+            ) => {
+                a_pop == b_pop
+                    && a_embed == b_embed
+                    && a_scope == b_scope
+                    && a_escape == b_escape
+                    && a_backrefs == b_backrefs
+                    && a_captures == b_captures
+                    && a_proto == b_proto
+            }
+            (
+                Self::Branch {
+                    pop_before: a_pop,
+                    branch_point: a_name,
+                    branches: a_branches,
+                    with_prototype: a_proto,
+                },
+                Self::Branch {
+                    pop_before: b_pop,
+                    branch_point: b_name,
+                    branches: b_branches,
+                    with_prototype: b_proto,
+                },
+            ) => {
+                a_pop == b_pop && a_name == b_name && a_branches == b_branches && a_proto == b_proto
+            }
+            (Self::Fail { branch_point: a }, Self::Fail { branch_point: b }) => a == b,
+            _ => false,
+        }
+    }
+}
+
 impl SublimeSyntax {
     /// Compile a parsed [`SyntaxDefinition`] into a [`SublimeSyntax`].
     pub fn compile(mut definition: SyntaxDefinition) -> Result<Self, SublimeSyntaxError> {
@@ -183,6 +306,13 @@ impl SublimeSyntax {
             definition.first_line_match = Some(substitute_variables(first, &realized_vars)?);
         }
 
+        let first_line_regex = definition
+            .first_line_match
+            .as_deref()
+            .map(compile_regex)
+            .transpose()?
+            .map(Arc::new);
+
         let prototype_exists = definition.contexts.contains_key("prototype");
 
         let mut contexts = HashMap::new();
@@ -199,6 +329,7 @@ impl SublimeSyntax {
             file_extensions: definition.file_extensions,
             hidden_file_extensions: definition.hidden_file_extensions,
             first_line_match: definition.first_line_match,
+            first_line_regex,
             prototype_exists,
             contexts,
         })
@@ -289,20 +420,44 @@ impl ContextMetaCollector {
     }
 }
 
+// Oniguruma error codes that indicate a resource/complexity limit rather than a syntax error.
+const ONIGERR_MEMORY: i32 = -5;
+const ONIGERR_PARSE_DEPTH_LIMIT_OVER: i32 = -16;
+const ONIGERR_TOO_BIG_NUMBER_FOR_REPEAT_RANGE: i32 = -201;
+const ONIGERR_TOO_MANY_MULTI_BYTE_RANGES: i32 = -205;
+const ONIGERR_TOO_MANY_CAPTURES: i32 = -210;
+
+pub(crate) fn compile_regex(source: &str) -> Result<Regex, SublimeSyntaxError> {
+    Regex::new(source).map_err(|e| {
+        let too_complex = matches!(
+            e.code(),
+            ONIGERR_MEMORY
+                | ONIGERR_PARSE_DEPTH_LIMIT_OVER
+                | ONIGERR_TOO_BIG_NUMBER_FOR_REPEAT_RANGE
+                | ONIGERR_TOO_MANY_MULTI_BYTE_RANGES
+                | ONIGERR_TOO_MANY_CAPTURES
+        );
+        if too_complex {
+            SublimeSyntaxError::PatternTooComplex {
+                pattern: source.to_string(),
+                error: PatternError::EngineLimit(e.description().to_string()),
+            }
+        } else {
+            SublimeSyntaxError::RegexCompile {
+                pattern: source.to_string(),
+                message: e.to_string(),
+            }
+        }
+    })
+}
+
 fn compile_match(
     pattern: &MatchPattern,
     variables: &HashMap<String, String>,
     origin_scope: &str,
 ) -> Result<CompiledMatchPattern, SublimeSyntaxError> {
-    if pattern.branch.is_some() || pattern.fail.is_some() || pattern.branch_point.is_some() {
-        return Err(SublimeSyntaxError::Unsupported("branch/fail"));
-    }
-
     let regex_source = substitute_variables(&pattern.regex, variables)?;
-    let regex = Regex::new(&regex_source).map_err(|e| SublimeSyntaxError::RegexCompile {
-        pattern: regex_source.clone(),
-        message: e.to_string(),
-    })?;
+    let regex = compile_regex(&regex_source)?;
 
     let mut captures = HashMap::new();
     for (idx, spec) in &pattern.captures {
@@ -329,7 +484,36 @@ fn compile_match(
         Some(PopAction::Count(n)) => *n,
     };
 
-    let action = if let Some(embed) = &pattern.embed {
+    let action = if let Some(branches) = &pattern.branch {
+        let Some(branch_point) = &pattern.branch_point else {
+            return Err(SublimeSyntaxError::MissingField(
+                "branch_point (required by branch)",
+            ));
+        };
+        if branches.is_empty() {
+            return Err(SublimeSyntaxError::MissingField("branch"));
+        }
+        MatchAction::Branch {
+            pop_before,
+            branch_point: branch_point.clone(),
+            branches: branches
+                .iter()
+                .map(|name| ContextSpec::Named {
+                    origin_scope: origin_scope.to_string(),
+                    name: name.clone(),
+                })
+                .collect(),
+            with_prototype,
+        }
+    } else if pattern.branch_point.is_some() {
+        return Err(SublimeSyntaxError::MissingField(
+            "branch (required by branch_point)",
+        ));
+    } else if let Some(branch_point) = &pattern.fail {
+        MatchAction::Fail {
+            branch_point: branch_point.clone(),
+        }
+    } else if let Some(embed) = &pattern.embed {
         let Some(escape) = &pattern.escape else {
             return Err(SublimeSyntaxError::MissingField(
                 "escape (required by embed)",
@@ -337,11 +521,12 @@ fn compile_match(
         };
 
         let escape_source = substitute_variables(escape, variables)?;
-        let escape_regex =
-            Regex::new(&escape_source).map_err(|e| SublimeSyntaxError::RegexCompile {
-                pattern: escape_source.clone(),
-                message: e.to_string(),
-            })?;
+        let escape_backrefs = has_backrefs(&escape_source);
+        let escape_regex = if escape_backrefs {
+            compile_regex(&resolve_backrefs(&escape_source, &[]))?
+        } else {
+            compile_regex(&escape_source)?
+        };
 
         MatchAction::Embed {
             pop_before,
@@ -349,6 +534,7 @@ fn compile_match(
             embed_scope: split_scopes(pattern.embed_scope.as_deref()),
             escape_source,
             escape: Arc::new(escape_regex),
+            escape_backrefs,
             escape_captures,
             with_prototype,
         }
@@ -381,6 +567,7 @@ fn compile_match(
         regex: Arc::new(regex),
         scope: split_scopes(pattern.scope.as_deref()),
         captures,
+        clear_scopes: pattern.clear_scopes.clone(),
         action,
     })
 }
@@ -501,6 +688,49 @@ fn realize_one_variable(
     Ok(out)
 }
 
+/// Whether `source` contains a backreference `\1` .. `\9` (outside of an escaped backslash).
+fn has_backrefs(source: &str) -> bool {
+    let mut chars = source.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' && chars.next().is_some_and(|next| matches!(next, '1'..='9')) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Replace backreferences `\1` .. `\9` in `source` with the literal (escaped) text of
+/// `captures[n]`; missing or unmatched groups become empty.
+pub(crate) fn resolve_backrefs(source: &str, captures: &[Option<String>]) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some(digit @ '1'..='9') => {
+                let index = digit as usize - '0' as usize;
+                if let Some(Some(text)) = captures.get(index) {
+                    for ch in text.chars() {
+                        if "\\^$.|?*+()[]{}#- \t".contains(ch) {
+                            out.push('\\');
+                        }
+                        out.push(ch);
+                    }
+                }
+            }
+            Some(next) => {
+                out.push('\\');
+                out.push(next);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
 fn substitute_variables(
     text: &str,
     vars: &HashMap<String, String>,
@@ -135,9 +135,9 @@ pub struct MetaPattern {
     pub comment: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[serde(untagged)]
-/// Controls how many scopes are cleared when entering a context.
+/// Controls how many scopes are cleared when entering a context (or for a single match).
 pub enum ClearScopes {
     /// Clear a fixed number of scopes.
     Count(usize),
@@ -204,15 +204,19 @@ pub struct MatchPattern {
     pub with_prototype: Option<Vec<RawContextPattern>>,
 
     #[serde(default)]
-    /// Branch target context names (advanced Sublime feature).
+    /// Scopes removed from the stack for the matched text only.
+    pub clear_scopes: Option<ClearScopes>,
+
+    #[serde(default)]
+    /// Alternative contexts to try in order, rewound to by `fail` (requires `branch_point`).
     pub branch: Option<Vec<String>>,
 
     #[serde(default)]
-    /// Branch point name (advanced Sublime feature).
+    /// Name of the branch point recorded by `branch`.
     pub branch_point: Option<String>,
 
     #[serde(default)]
-    /// Fail target (advanced Sublime feature).
+    /// Branch point to rewind to, retrying its next alternative.
     pub fail: Option<String>,
 
     #[serde(default)]
@@ -1,6 +1,6 @@
 use crate::sublime_syntax::compiler::{
     CompiledContext, CompiledIncludePattern, CompiledMatchPattern, CompiledPattern, ContextPush,
-    ContextSpec, MatchAction, SublimeSyntax,
+    ContextSpec, MatchAction, SublimeSyntax, compile_regex, resolve_backrefs,
 };
 use crate::sublime_syntax::definition::ClearScopes;
 use crate::sublime_syntax::error::SublimeSyntaxError;
@@ -10,6 +10,7 @@ use editor_core::LineIndex;
 use editor_core::intervals::{FoldRegion, Interval, StyleId};
 use onig::{Region, SearchOptions};
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::Arc;
 
 #[derive(Debug, Default)]
@@ -17,12 +18,45 @@ use std::sync::Arc;
 pub struct SublimeHighlightResult {
     /// Style intervals in character offsets.
     pub intervals: Vec<Interval>,
-    /// Fold regions inferred from multi-line contexts.
+    /// Fold regions inferred from multi-line fold-scope contexts.
     pub fold_regions: Vec<FoldRegion>,
 }
 
+impl SublimeHighlightResult {
+    fn new(intervals: Vec<Interval>, folds: &[ScopedFold]) -> Self {
+        Self {
+            intervals,
+            fold_regions: folds.iter().map(|fold| fold.region.clone()).collect(),
+        }
+    }
+}
+
+/// Scopes that produce fold regions by default (see [`highlight_document`]).
+///
+/// A selector matches a scope equal to it or starting with it followed by `.`, so `meta.block`
+/// matches `meta.block.rust`.
+pub const DEFAULT_FOLD_SCOPES: &[&str] = &[
+    "meta.block",
+    "meta.braces",
+    "meta.brackets",
+    "meta.function",
+    "meta.class",
+    "meta.mapping",
+    "meta.sequence",
+    "comment.block",
+    "string",
+];
+
+/// [`DEFAULT_FOLD_SCOPES`] as owned strings.
+pub fn default_fold_scopes() -> Vec<String> {
+    DEFAULT_FOLD_SCOPES.iter().map(|s| s.to_string()).collect()
+}
+
 /// Highlights a document and derives fold regions from multi-line contexts.
 ///
+/// A context folds when one of its meta scopes matches [`DEFAULT_FOLD_SCOPES`] and it spans 2+
+/// lines; a `set` to a context with the same scope continues the region.
+///
 /// - Intervals are in **char offsets**, consistent with `LineIndex` and the rest of editor-core.
 /// - The returned intervals are non-overlapping within this result (best-effort).
 pub fn highlight_document(
@@ -32,54 +66,355 @@ pub fn highlight_document(
     scope_mapper: &mut SublimeScopeMapper,
 ) -> Result<SublimeHighlightResult, SublimeSyntaxError> {
     let mut syntax_set = syntax_set;
-    let mut engine = Highlighter::new(syntax, scope_mapper);
-    engine.highlight(line_index, &mut syntax_set)
+    let fold_scopes = default_fold_scopes();
+    let mut engine = Highlighter::new(syntax, scope_mapper, &fold_scopes);
+    let intervals = engine.highlight(line_index, &mut syntax_set, None)?;
+    Ok(SublimeHighlightResult::new(intervals, &engine.fold_regions))
+}
+
+/// A fold region with the scope of the context it was derived from.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScopedFold {
+    pub region: FoldRegion,
+    pub scope: String,
+}
+
+/// Context stack at the start of a line.
+type LineStack = Arc<Vec<ContextFrame>>;
+
+/// Highlighter state at the start of every line of the last highlighted document, used to
+/// re-highlight only the lines affected by edits (see [`rehighlight_dirty_lines`]).
+#[derive(Debug, Default)]
+pub(crate) struct LineStates {
+    /// Context stack at the start of each line; `None` where highlighting cannot resume because
+    /// a `branch_point` could still be rewound to. Consecutive equal stacks share one `Arc`.
+    stacks: Vec<Option<LineStack>>,
+    /// Fold regions of the document, in the order their contexts were popped.
+    fold_regions: Vec<ScopedFold>,
+    /// Lines edited since the last highlight.
+    dirty: Option<Range<usize>>,
+}
+
+impl LineStates {
+    /// Fold regions of the document as of the last highlight.
+    pub(crate) fn fold_regions(&self) -> &[ScopedFold] {
+        &self.fold_regions
+    }
+
+    /// Record an edit that replaced lines `start_line..=start_line + removed_lines` with
+    /// `added_lines + 1` lines.
+    ///
+    /// Line numbers after the edit (in cached stacks and fold regions) are shifted; lines that
+    /// were removed map to `start_line`. Since the same mapping applies to everything kept from
+    /// the last highlight, re-highlighting can resume on a shifted stack and keep the results
+    /// after it.
+    pub(crate) fn apply_edit(
+        &mut self,
+        start_line: usize,
+        removed_lines: usize,
+        added_lines: usize,
+    ) {
+        let map_line = |line: usize| {
+            if line <= start_line {
+                line
+            } else if line > start_line + removed_lines {
+                line + added_lines - removed_lines
+            } else {
+                start_line
+            }
+        };
+
+        let removed = (start_line + 1).min(self.stacks.len())
+            ..(start_line + 1 + removed_lines).min(self.stacks.len());
+        self.stacks
+            .splice(removed, std::iter::repeat_n(None, added_lines));
+        let mut last: Option<(LineStack, LineStack)> = None;
+        for stack in self
+            .stacks
+            .iter_mut()
+            .skip(start_line + added_lines + 1)
+            .flatten()
+        {
+            if let Some((old, new)) = &last
+                && Arc::ptr_eq(old, stack)
+            {
+                *stack = new.clone();
+                continue;
+            }
+            let old = stack.clone();
+            if stack
+                .iter()
+                .any(|f| map_line(f.entered_at_line) != f.entered_at_line)
+            {
+                for frame in Arc::make_mut(stack) {
+                    frame.entered_at_line = map_line(frame.entered_at_line);
+                }
+            }
+            last = Some((old, stack.clone()));
+        }
+
+        for ScopedFold { region, .. } in &mut self.fold_regions {
+            region.start_line = map_line(region.start_line);
+            region.end_line = map_line(region.end_line);
+        }
+
+        let edited = start_line..start_line + added_lines + 1;
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => {
+                map_line(dirty.start).min(edited.start)..map_line(dirty.end).max(edited.end)
+            }
+            None => edited,
+        });
+    }
+}
+
+/// Lines re-highlighted by [`rehighlight_dirty_lines`].
+#[derive(Debug, Default)]
+pub(crate) struct LineRangeHighlight {
+    /// The re-highlighted lines.
+    pub lines: Range<usize>,
+    /// Style intervals of `lines` (char offsets); nothing outside them changed.
+    pub intervals: Vec<Interval>,
+}
+
+/// Highlights a document like [`highlight_document`], recording the state at the start of
+/// every line in `states`.
+pub(crate) fn highlight_document_with_states(
+    syntax: Arc<SublimeSyntax>,
+    line_index: &LineIndex,
+    syntax_set: Option<&mut SublimeSyntaxSet>,
+    scope_mapper: &mut SublimeScopeMapper,
+    fold_scopes: &[String],
+    states: &mut LineStates,
+) -> Result<SublimeHighlightResult, SublimeSyntaxError> {
+    let mut syntax_set = syntax_set;
+    let mut engine = Highlighter::new(syntax, scope_mapper, fold_scopes);
+    let intervals = engine.highlight(line_index, &mut syntax_set, Some(&mut states.stacks))?;
+    states.fold_regions = std::mem::take(&mut engine.fold_regions);
+    states.dirty = None;
+    Ok(SublimeHighlightResult::new(intervals, &states.fold_regions))
+}
+
+/// Re-highlights the lines edited since `states` was recorded (see [`LineStates::apply_edit`]).
+///
+/// Highlighting resumes at the closest line at or before the first edited line whose start
+/// state is cached, and stops at the first line after the edited ones whose start state is
+/// unchanged. Returns `None` when no line was edited. If `states` does not match the document
+/// (e.g. an edit was not recorded), the whole document is highlighted again.
+pub(crate) fn rehighlight_dirty_lines(
+    syntax: Arc<SublimeSyntax>,
+    line_index: &LineIndex,
+    syntax_set: Option<&mut SublimeSyntaxSet>,
+    scope_mapper: &mut SublimeScopeMapper,
+    fold_scopes: &[String],
+    states: &mut LineStates,
+) -> Result<Option<LineRangeHighlight>, SublimeSyntaxError> {
+    let line_count = line_index.line_count();
+    if states.stacks.len() != line_count {
+        let result = highlight_document_with_states(
+            syntax,
+            line_index,
+            syntax_set,
+            scope_mapper,
+            fold_scopes,
+            states,
+        )?;
+        return Ok(Some(LineRangeHighlight {
+            lines: 0..line_count,
+            intervals: result.intervals,
+        }));
+    }
+    let Some(dirty) = states.dirty.take() else {
+        return Ok(None);
+    };
+
+    let first_line = states.stacks[..=dirty.start.min(line_count - 1)]
+        .iter()
+        .rposition(Option::is_some)
+        .unwrap_or(0);
+    let mut syntax_set = syntax_set;
+    let mut engine = Highlighter::new(syntax, scope_mapper, fold_scopes);
+    match &states.stacks[first_line] {
+        Some(stack) => engine.context_stack = stack.as_ref().clone(),
+        None => {
+            let intervals =
+                engine.highlight(line_index, &mut syntax_set, Some(&mut states.stacks))?;
+            states.fold_regions = std::mem::take(&mut engine.fold_regions);
+            return Ok(Some(LineRangeHighlight {
+                lines: 0..line_count,
+                intervals,
+            }));
+        }
+    }
+
+    let (intervals, end_line) = engine.highlight_lines(
+        line_index,
+        &mut syntax_set,
+        first_line,
+        Some(&mut states.stacks),
+        dirty.end,
+    )?;
+
+    // Folds closed before `first_line` or (when highlighting stopped early) from `end_line` on
+    // are unchanged.
+    let old_folds = std::mem::take(&mut states.fold_regions);
+    let (before, after): (Vec<_>, Vec<_>) = old_folds
+        .into_iter()
+        .filter(|fold| fold.region.end_line < first_line || end_line < line_count)
+        .partition(|fold| fold.region.end_line < first_line);
+    states.fold_regions = before;
+    states.fold_regions.append(&mut engine.fold_regions);
+    states.fold_regions.extend(
+        after
+            .into_iter()
+            .filter(|fold| fold.region.end_line >= end_line),
+    );
+
+    Ok(Some(LineRangeHighlight {
+        lines: first_line..end_line,
+        intervals,
+    }))
+}
+
+/// Text (in char offsets) with the full scope stack it was highlighted with, innermost last.
+#[derive(Debug, Clone)]
+pub(crate) struct ScopeSpan {
+    pub start: usize,
+    pub end: usize,
+    pub scopes: Vec<String>,
+}
+
+/// Highlights a document and returns the scope stack of every span of text, including the
+/// spans that only carry the base scope.
+pub(crate) fn highlight_scope_stacks(
+    syntax: Arc<SublimeSyntax>,
+    line_index: &LineIndex,
+    syntax_set: Option<&mut SublimeSyntaxSet>,
+) -> Result<Vec<ScopeSpan>, SublimeSyntaxError> {
+    let mut syntax_set = syntax_set;
+    let mut scope_mapper = SublimeScopeMapper::new();
+    let mut engine = Highlighter::new(syntax, &mut scope_mapper, &[]);
+    engine.scope_spans = Some(Vec::new());
+    engine.highlight(line_index, &mut syntax_set, None)?;
+    Ok(engine.scope_spans.take().unwrap_or_default())
 }
 
+/// How many lines after a `branch_point` a `fail` can still rewind to it (as in Sublime Text).
+const BRANCH_LOOKAHEAD_LINES: usize = 128;
+
+/// Rewinds allowed per document line before highlighting gives up.
+const MAX_REWINDS_PER_LINE: usize = 32;
+
 struct Highlighter<'a> {
     root_syntax: Arc<SublimeSyntax>,
     scope_mapper: &'a mut SublimeScopeMapper,
     pattern_cache: PatternCache,
     context_stack: Vec<ContextFrame>,
-    fold_regions: Vec<FoldRegion>,
+    /// Selectors of the context scopes that fold.
+    fold_scopes: &'a [String],
+    fold_regions: Vec<ScopedFold>,
+    /// Active branch points, innermost last.
+    branch_points: Vec<BranchPoint>,
+    rewinds: usize,
+    /// Every emitted span with its scope stack, when requested.
+    scope_spans: Option<Vec<ScopeSpan>>,
 }
 
 impl<'a> Highlighter<'a> {
-    fn new(syntax: Arc<SublimeSyntax>, scope_mapper: &'a mut SublimeScopeMapper) -> Self {
+    fn new(
+        syntax: Arc<SublimeSyntax>,
+        scope_mapper: &'a mut SublimeScopeMapper,
+        fold_scopes: &'a [String],
+    ) -> Self {
         Self {
             root_syntax: syntax,
             scope_mapper,
             pattern_cache: PatternCache::default(),
             context_stack: Vec::new(),
+            fold_scopes,
             fold_regions: Vec::new(),
+            branch_points: Vec::new(),
+            rewinds: 0,
+            scope_spans: None,
         }
     }
 
+    /// Highlight the whole document, recording the state at the start of every line in
+    /// `stacks` when given. Fold regions are left in `fold_regions`.
     fn highlight(
         &mut self,
         line_index: &LineIndex,
         syntax_set: &mut Option<&mut SublimeSyntaxSet>,
-    ) -> Result<SublimeHighlightResult, SublimeSyntaxError> {
+        mut stacks: Option<&mut Vec<Option<LineStack>>>,
+    ) -> Result<Vec<Interval>, SublimeSyntaxError> {
         if !self.root_syntax.contexts.contains_key("main") {
             return Err(SublimeSyntaxError::MissingField("contexts.main"));
         }
 
-        self.context_stack.push(ContextFrame::named(
+        self.context_stack = vec![ContextFrame::named(
             self.root_syntax.clone(),
             "main".to_string(),
             0,
-        ));
+        )];
+        if let Some(stacks) = stacks.as_deref_mut() {
+            stacks.clear();
+            stacks.resize(line_index.line_count(), None);
+        }
+        self.fold_regions.clear();
+        let (intervals, _) = self.highlight_lines(line_index, syntax_set, 0, stacks, usize::MAX)?;
+        Ok(intervals)
+    }
 
-        let base_scope = self.root_syntax.scope.clone();
+    /// Highlight from the start of `first_line`, with `context_stack` as the stack there.
+    ///
+    /// With `stacks` (one entry per line), the stack at the start of every highlighted line is
+    /// recorded, and highlighting stops at the first line from `resync_from` on whose stack
+    /// equals the recorded one: the lines after it would highlight as before. Returns the
+    /// intervals and the line highlighting stopped at (the line count at the end of the
+    /// document, where the remaining contexts are closed).
+    fn highlight_lines(
+        &mut self,
+        line_index: &LineIndex,
+        syntax_set: &mut Option<&mut SublimeSyntaxSet>,
+        first_line: usize,
+        mut stacks: Option<&mut Vec<Option<LineStack>>>,
+        resync_from: usize,
+    ) -> Result<(Vec<Interval>, usize), SublimeSyntaxError> {
         let mut intervals = Vec::<Interval>::new();
 
         let line_count = line_index.line_count();
-        for line in 0..line_count {
+        let max_rewinds = (line_count + 1).saturating_mul(MAX_REWINDS_PER_LINE);
+        let mut line = first_line;
+        // Where to continue after rewinding to a branch point: `(byte, char)` within `line`.
+        let mut resume_at: Option<(usize, usize)> = None;
+        'lines: while line < line_count {
+            if resume_at.is_none()
+                && let Some(stacks) = stacks.as_deref_mut()
+            {
+                // A pending branch point may rewind past the line start: not a resumable state.
+                let stack = self
+                    .branch_points
+                    .is_empty()
+                    .then_some(self.context_stack.as_slice());
+                if line > first_line
+                    && line >= resync_from
+                    && stack.is_some()
+                    && stacks[line].as_ref().map(|s| s.as_slice()) == stack
+                {
+                    return Ok((intervals, line));
+                }
+                stacks[line] = stack.map(|stack| match line.checked_sub(1).map(|l| &stacks[l]) {
+                    Some(Some(previous)) if previous.as_slice() == stack => previous.clone(),
+                    _ => Arc::new(stack.to_vec()),
+                });
+            }
+
             let line_text = line_index.get_line_text(line).unwrap_or_default();
             let line_start_offset = line_index.position_to_char_offset(line, 0);
+            self.branch_points
+                .retain(|bp| line.saturating_sub(bp.line) <= BRANCH_LOOKAHEAD_LINES);
 
-            let mut pos_byte = 0usize;
-            let mut pos_char = 0usize;
+            let (mut pos_byte, mut pos_char) = resume_at.take().unwrap_or((0, 0));
             let line_len_bytes = line_text.len();
 
             // Prevent infinite loops with zero-width matches.
@@ -97,13 +432,12 @@ impl<'a> Highlighter<'a> {
 
                 let Some(found) = self.find_next_match(&line_text, pos_byte, syntax_set)? else {
                     let end_char = pos_char + line_text[pos_byte..].chars().count();
-                    let style = self.best_style_for_content();
-                    self.emit_segment(
+                    let scopes = self.content_scopes();
+                    self.emit_scopes(
                         &mut intervals,
                         line_start_offset + pos_char,
                         line_start_offset + end_char,
-                        style,
-                        base_scope.as_str(),
+                        scopes,
                     );
                     break;
                 };
@@ -112,39 +446,120 @@ impl<'a> Highlighter<'a> {
                 if found.start_byte > pos_byte {
                     let segment_chars = line_text[pos_byte..found.start_byte].chars().count();
                     let end_char = pos_char + segment_chars;
-                    let style = self.best_style_for_content();
-                    self.emit_segment(
+                    let scopes = self.content_scopes();
+                    self.emit_scopes(
                         &mut intervals,
                         line_start_offset + pos_char,
                         line_start_offset + end_char,
-                        style,
-                        base_scope.as_str(),
+                        scopes,
                     );
                     pos_char = end_char;
                     pos_byte = found.start_byte;
                 }
 
+                if let MatchAction::Fail { branch_point } = &found.pattern.action
+                    && let Some(rewind) = self.rewind_to_branch_point(
+                        branch_point,
+                        &mut intervals,
+                        line_index,
+                        syntax_set,
+                    )?
+                {
+                    self.rewinds += 1;
+                    if self.rewinds > max_rewinds {
+                        return Err(SublimeSyntaxError::Unsupported(
+                            "highlighting exceeded branch rewind limit",
+                        ));
+                    }
+                    line = rewind.line;
+                    resume_at = Some((rewind.byte, rewind.char));
+                    continue 'lines;
+                }
+
+                // State to restore if a later `fail` rewinds to this branch point.
+                let branch_state =
+                    matches!(found.pattern.action, MatchAction::Branch { .. }).then(|| {
+                        BranchState {
+                            context_stack: self.context_stack.clone(),
+                            intervals_len: intervals.len(),
+                            last_interval_end: intervals.last().map(|i| i.end),
+                            scope_spans_len: self.scope_spans.as_ref().map_or(0, Vec::len),
+                            fold_regions_len: self.fold_regions.len(),
+                            start_char: pos_char,
+                        }
+                    });
+
+                // An escape leaves the embed first: its match is scoped by the outer contexts.
+                let stack_len_before = self.context_stack.len();
+                if found.is_escape {
+                    self.apply_action(&found.pattern, &[], line, syntax_set)?;
+                }
+                // Captures are only needed by escapes that refer back to the embed match.
+                let captures = match &found.pattern.action {
+                    MatchAction::Embed {
+                        escape_backrefs: true,
+                        ..
+                    } => match_captures(&found.pattern.regex, &line_text, found.start_byte),
+                    _ => Vec::new(),
+                };
+
+                // Text that pushes contexts is scoped by them (their `meta_scope` and
+                // `clear_scopes`, not their `meta_content_scope`), so push before emitting it.
+                let pushed = if found.is_escape {
+                    None
+                } else {
+                    pushed_context_count(&found.pattern.action)
+                };
+                if pushed.is_some() {
+                    self.apply_action(&found.pattern, &captures, line, syntax_set)?;
+                }
+                let stack_len = self.context_stack.len();
+                let meta_only_from = match (&found.pattern.action, pushed) {
+                    (_, Some(count)) => stack_len - count,
+                    // Text that pops is scoped by the popped contexts' `meta_scope` only.
+                    (MatchAction::Pop { count }, None) if !found.is_escape => {
+                        stack_len - (*count).min(stack_len - 1)
+                    }
+                    _ => stack_len,
+                };
+
                 // Emit match region (may be empty for lookaheads).
                 if found.end_byte > found.start_byte {
                     let match_chars = line_text[found.start_byte..found.end_byte].chars().count();
                     let end_char = pos_char + match_chars;
 
-                    let style = self.best_style_for_match(&found.pattern);
-                    self.emit_segment(
+                    let scopes = self.match_scopes(&found.pattern, meta_only_from);
+                    self.emit_scopes(
                         &mut intervals,
                         line_start_offset + pos_char,
                         line_start_offset + end_char,
-                        style,
-                        base_scope.as_str(),
+                        scopes,
                     );
 
                     pos_char = end_char;
                     pos_byte = found.end_byte;
                 }
 
-                let stack_len_before = self.context_stack.len();
-                self.apply_action(found.pattern.action.clone(), line, syntax_set)?;
+                if !found.is_escape && pushed.is_none() {
+                    self.apply_action(&found.pattern, &captures, line, syntax_set)?;
+                }
                 let stack_len_after = self.context_stack.len();
+                // A branch point lives as long as the alternative it pushed.
+                self.branch_points.retain(|bp| bp.depth <= stack_len_after);
+                if let (Some(state), MatchAction::Branch { branch_point, .. }) =
+                    (branch_state, &found.pattern.action)
+                {
+                    self.branch_points.push(BranchPoint {
+                        name: branch_point.clone(),
+                        pattern: found.pattern.clone(),
+                        line,
+                        end_byte: pos_byte,
+                        end_char: pos_char,
+                        alternative: 0,
+                        depth: stack_len_after,
+                        state,
+                    });
+                }
 
                 // If this is a zero-width match and the stack didn't change, we must
                 // ensure progress to avoid an infinite loop. At end-of-line we can
@@ -170,6 +585,7 @@ impl<'a> Highlighter<'a> {
                     }
                 }
             }
+            line += 1;
         }
 
         // Close any remaining contexts at EOF for folding purposes.
@@ -178,10 +594,32 @@ impl<'a> Highlighter<'a> {
             self.pop_one_context(last_line);
         }
 
-        Ok(SublimeHighlightResult {
-            intervals,
-            fold_regions: std::mem::take(&mut self.fold_regions),
-        })
+        Ok((intervals, line_count))
+    }
+
+    /// Emit `start..end` with the scope stack `scopes`, styled by its innermost scope.
+    fn emit_scopes(
+        &mut self,
+        intervals: &mut Vec<Interval>,
+        start: usize,
+        end: usize,
+        scopes: Vec<String>,
+    ) {
+        if start >= end {
+            return;
+        }
+
+        let root_syntax = self.root_syntax.clone();
+        let style = self.scope_mapper.style_id_for_scope(
+            scopes
+                .last()
+                .map(String::as_str)
+                .unwrap_or(&root_syntax.scope),
+        );
+        if let Some(spans) = self.scope_spans.as_mut() {
+            spans.push(ScopeSpan { start, end, scopes });
+        }
+        self.emit_segment(intervals, start, end, style, &root_syntax.scope);
     }
 
     fn emit_segment(
@@ -242,6 +680,7 @@ impl<'a> Highlighter<'a> {
                         start_byte: start,
                         end_byte: end,
                         pattern,
+                        is_escape: false,
                     });
                 }
                 Some(existing) => {
@@ -250,6 +689,7 @@ impl<'a> Highlighter<'a> {
                             start_byte: start,
                             end_byte: end,
                             pattern,
+                            is_escape: false,
                         });
                     } else if start == existing.start_byte {
                         // Tie-break by definition order: since we iterate in order,
@@ -259,6 +699,40 @@ impl<'a> Highlighter<'a> {
             }
         }
 
+        // Escapes of active embeds take precedence over every pattern inside the embed; an
+        // outer embed's escape wins ties with inner ones, as it ends them too.
+        let mut escape: Option<FoundMatch> = None;
+        let stack_len = self.context_stack.len();
+        for (idx, frame) in self.context_stack.iter().enumerate() {
+            let Some(embed) = &frame.embed else {
+                continue;
+            };
+            let Some((start, end)) = search_first(&embed.escape.regex, line_text, from_byte)?
+            else {
+                continue;
+            };
+            if escape.as_ref().is_some_and(|e| e.start_byte <= start) {
+                continue;
+            }
+            let mut pattern = embed.escape.clone();
+            pattern.action = MatchAction::Pop {
+                count: stack_len - idx,
+            };
+            escape = Some(FoundMatch {
+                start_byte: start,
+                end_byte: end,
+                pattern,
+                is_escape: true,
+            });
+        }
+        if let Some(escape) = escape
+            && best
+                .as_ref()
+                .is_none_or(|b| escape.start_byte <= b.start_byte)
+        {
+            return Ok(Some(escape));
+        }
+
         Ok(best)
     }
 
@@ -372,6 +846,22 @@ impl<'a> Highlighter<'a> {
     ) -> Result<(), SublimeSyntaxError> {
         let target = include.include.as_str();
 
+        // Resolve against the syntax that defined the include: `with_prototype` patterns are
+        // injected into contexts of other syntaxes.
+        let syntax = if include.origin_scope == syntax.scope {
+            syntax.clone()
+        } else if include.origin_scope == self.root_syntax.scope {
+            self.root_syntax.clone()
+        } else {
+            syntax_set
+                .as_deref()
+                .and_then(|set| set.get_by_scope(&include.origin_scope))
+                .ok_or_else(|| {
+                    SublimeSyntaxError::UnknownSyntaxReference(include.origin_scope.clone())
+                })?
+        };
+        let syntax = &syntax;
+
         // External syntax include: include the referenced syntax's main context.
         if is_external_syntax_reference(target) {
             let Some(set) = syntax_set.as_deref_mut() else {
@@ -404,42 +894,34 @@ impl<'a> Highlighter<'a> {
         Ok(())
     }
 
-    fn best_style_for_content(&mut self) -> StyleId {
-        let scopes = compute_scopes(
-            &self.context_stack,
-            ScopeMode::Content,
-            None,
-            &self.root_syntax.scope,
-        );
-        let best = scopes
-            .last()
-            .map(|s| s.as_str())
-            .unwrap_or(&self.root_syntax.scope);
-        self.scope_mapper.style_id_for_scope(best)
+    fn content_scopes(&self) -> Vec<String> {
+        let frames = &self.context_stack;
+        compute_scopes(frames, frames.len(), None, &self.root_syntax.scope)
     }
 
-    fn best_style_for_match(&mut self, pattern: &CompiledMatchPattern) -> StyleId {
-        let scopes = compute_scopes(
+    /// Scopes of text matched by `pattern`; frames from `meta_only_from` up contribute their
+    /// `meta_scope` but not their `meta_content_scope`.
+    fn match_scopes(&self, pattern: &CompiledMatchPattern, meta_only_from: usize) -> Vec<String> {
+        compute_scopes(
             &self.context_stack,
-            ScopeMode::Match,
-            Some(&pattern.scope),
+            meta_only_from,
+            Some(pattern),
             &self.root_syntax.scope,
-        );
-        let best = scopes
-            .last()
-            .map(|s| s.as_str())
-            .unwrap_or(&self.root_syntax.scope);
-        self.scope_mapper.style_id_for_scope(best)
+        )
     }
 
+    /// Apply the stack action of `pattern`. `captures` are the texts of the match's capture
+    /// groups, used to resolve backreferences in an embed's escape.
     fn apply_action(
         &mut self,
-        action: MatchAction,
+        pattern: &CompiledMatchPattern,
+        captures: &[Option<String>],
         line: usize,
         syntax_set: &mut Option<&mut SublimeSyntaxSet>,
     ) -> Result<(), SublimeSyntaxError> {
-        match action {
-            MatchAction::None => Ok(()),
+        match pattern.action.clone() {
+            // Reached only when no branch point could be rewound to.
+            MatchAction::None | MatchAction::Fail { .. } => Ok(()),
             MatchAction::Pop { count } => {
                 for _ in 0..count {
                     self.pop_one_context(line);
@@ -450,6 +932,23 @@ impl<'a> Highlighter<'a> {
                 pop_before,
                 push,
                 with_prototype,
+            } => self.pop_and_push(pop_before, push, with_prototype, line, syntax_set),
+            MatchAction::Branch {
+                pop_before,
+                mut branches,
+                with_prototype,
+                ..
+            } => self.pop_and_push(
+                pop_before,
+                ContextPush::One(branches.swap_remove(0)),
+                with_prototype,
+                line,
+                syntax_set,
+            ),
+            MatchAction::Set {
+                pop_before,
+                set,
+                with_prototype,
             } => {
                 let mut inherited = self
                     .context_stack
@@ -460,13 +959,46 @@ impl<'a> Highlighter<'a> {
                 for _ in 0..pop_before {
                     self.pop_one_context(line);
                 }
-                self.push_contexts(push, inherited, line, syntax_set)
+                let folds_len = self.fold_regions.len();
+                let replaced = self
+                    .context_stack
+                    .last()
+                    .filter(|_| self.context_stack.len() > 1)
+                    .and_then(|frame| {
+                        Some((self.fold_scope(frame)?.to_string(), frame.entered_at_line))
+                    });
+                self.pop_one_context(line);
+                self.push_contexts(set, inherited, line, syntax_set)?;
+
+                // Setting a context with the same fold scope continues the replaced region.
+                if let Some((scope, entered_at_line)) = replaced
+                    && let Some(frame) = self.context_stack.last()
+                    && self.fold_scope(frame) == Some(scope.as_str())
+                {
+                    self.fold_regions.truncate(folds_len);
+                    if let Some(frame) = self.context_stack.last_mut() {
+                        frame.entered_at_line = entered_at_line;
+                    }
+                }
+                Ok(())
             }
-            MatchAction::Set {
+            MatchAction::Embed {
                 pop_before,
-                set,
+                embed,
+                embed_scope,
+                escape_source,
+                escape,
+                escape_backrefs,
+                escape_captures,
                 with_prototype,
             } => {
+                let (escape_source, escape) = if escape_backrefs {
+                    let source = resolve_backrefs(&escape_source, captures);
+                    let regex = Arc::new(compile_regex(&source)?);
+                    (source, regex)
+                } else {
+                    (escape_source, escape)
+                };
                 let mut inherited = self
                     .context_stack
                     .last()
@@ -476,13 +1008,133 @@ impl<'a> Highlighter<'a> {
                 for _ in 0..pop_before {
                     self.pop_one_context(line);
                 }
-                self.pop_one_context(line);
-                self.push_contexts(set, inherited, line, syntax_set)
+                self.push_context_spec(
+                    ContextSpec::Named {
+                        origin_scope: pattern.origin_scope.clone(),
+                        name: embed,
+                    },
+                    inherited,
+                    line,
+                    syntax_set,
+                )?;
+
+                // The escape is matched like a pattern whose scope is its whole-match capture.
+                let escape = CompiledMatchPattern {
+                    origin_scope: pattern.origin_scope.clone(),
+                    regex_source: escape_source,
+                    regex: escape,
+                    scope: escape_captures.get(&0).cloned().unwrap_or_default(),
+                    captures: escape_captures,
+                    clear_scopes: None,
+                    action: MatchAction::None,
+                };
+                if let Some(frame) = self.context_stack.last_mut() {
+                    frame.embed = Some(EmbedFrame {
+                        embed_scope,
+                        escape,
+                    });
+                }
+                Ok(())
             }
-            MatchAction::Embed { .. } => Err(SublimeSyntaxError::Unsupported("embed")),
         }
     }
 
+    /// Pop `pop_before` contexts and push `push`, which inherits the current `with_prototype`
+    /// patterns plus `with_prototype`.
+    fn pop_and_push(
+        &mut self,
+        pop_before: usize,
+        push: ContextPush,
+        with_prototype: Vec<CompiledPattern>,
+        line: usize,
+        syntax_set: &mut Option<&mut SublimeSyntaxSet>,
+    ) -> Result<(), SublimeSyntaxError> {
+        let mut inherited = self
+            .context_stack
+            .last()
+            .map(|f| f.injected_patterns.clone())
+            .unwrap_or_default();
+        inherited.extend(with_prototype);
+        for _ in 0..pop_before {
+            self.pop_one_context(line);
+        }
+        self.push_contexts(push, inherited, line, syntax_set)
+    }
+
+    /// Rewind to the innermost active branch point named `name`: restore the state from before
+    /// its match, re-emit the match and push the next alternative.
+    ///
+    /// Returns where to continue highlighting, or `None` when the `fail` has no effect: no such
+    /// branch point is active, or its last alternative is the one failing (the branch point is
+    /// then discarded and highlighting continues in that alternative).
+    fn rewind_to_branch_point(
+        &mut self,
+        name: &str,
+        intervals: &mut Vec<Interval>,
+        line_index: &LineIndex,
+        syntax_set: &mut Option<&mut SublimeSyntaxSet>,
+    ) -> Result<Option<Rewind>, SublimeSyntaxError> {
+        let Some(index) = self.branch_points.iter().rposition(|bp| bp.name == name) else {
+            return Ok(None);
+        };
+        // Branch points inside the failed alternative are discarded with it.
+        self.branch_points.truncate(index + 1);
+        let bp = &self.branch_points[index];
+        let MatchAction::Branch {
+            pop_before,
+            branches,
+            with_prototype,
+            ..
+        } = bp.pattern.action.clone()
+        else {
+            return Ok(None);
+        };
+        let alternative = bp.alternative + 1;
+        let Some(spec) = branches.get(alternative).cloned() else {
+            self.branch_points.pop();
+            return Ok(None);
+        };
+
+        self.context_stack = bp.state.context_stack.clone();
+        self.fold_regions.truncate(bp.state.fold_regions_len);
+        intervals.truncate(bp.state.intervals_len);
+        if let (Some(last), Some(end)) = (intervals.last_mut(), bp.state.last_interval_end) {
+            last.end = end;
+        }
+        if let Some(spans) = self.scope_spans.as_mut() {
+            spans.truncate(bp.state.scope_spans_len);
+        }
+
+        let line = bp.line;
+        let line_start_offset = line_index.position_to_char_offset(line, 0);
+        let (start_char, end_char, end_byte) = (bp.state.start_char, bp.end_char, bp.end_byte);
+        let pattern = bp.pattern.clone();
+        self.pop_and_push(
+            pop_before,
+            ContextPush::One(spec),
+            with_prototype,
+            line,
+            syntax_set,
+        )?;
+        let depth = self.context_stack.len();
+        let scopes = self.match_scopes(&pattern, depth - 1);
+        self.emit_scopes(
+            intervals,
+            line_start_offset + start_char,
+            line_start_offset + end_char,
+            scopes,
+        );
+
+        let bp = &mut self.branch_points[index];
+        bp.alternative = alternative;
+        bp.depth = depth;
+        Ok(Some(Rewind {
+            line,
+            byte: end_byte,
+            char: end_char,
+        }))
+    }
+
     fn push_contexts(
         &mut self,
         push: ContextPush,
@@ -581,21 +1233,52 @@ impl<'a> Highlighter<'a> {
             Err(_) => return,
         };
 
-        if !ctx.meta_scope.is_empty() && line > frame.entered_at_line {
-            self.fold_regions
-                .push(FoldRegion::new(frame.entered_at_line, line));
+        if line > frame.entered_at_line
+            && let Some(scope) = fold_scope_of(ctx, self.fold_scopes)
+        {
+            self.fold_regions.push(ScopedFold {
+                region: FoldRegion::new(frame.entered_at_line, line),
+                scope: scope.to_string(),
+            });
         }
     }
+
+    /// The first meta scope of `frame` matching a fold scope selector.
+    fn fold_scope<'f>(&self, frame: &'f ContextFrame) -> Option<&'f str> {
+        fold_scope_of(frame.context().ok()?, self.fold_scopes)
+    }
 }
 
-#[derive(Debug)]
-struct ContextFrame {
+#[derive(Debug, Clone)]
+pub(crate) struct ContextFrame {
     syntax: Arc<SublimeSyntax>,
     context_name: String,
     is_inline: bool,
     inline_context: Option<CompiledContext>,
     injected_patterns: Vec<CompiledPattern>,
     entered_at_line: usize,
+    /// Set on the bottom frame of an `embed`; the embed ends when its escape matches.
+    embed: Option<EmbedFrame>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct EmbedFrame {
+    /// Scopes applied to everything inside the embed (`embed_scope`).
+    embed_scope: Vec<String>,
+    /// The `escape` regex as a match pattern (scoped by `escape_captures[0]`).
+    escape: CompiledMatchPattern,
+}
+
+impl PartialEq for ContextFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.entered_at_line == other.entered_at_line
+            && self.is_inline == other.is_inline
+            && self.context_name == other.context_name
+            && Arc::ptr_eq(&self.syntax, &other.syntax)
+            && self.embed == other.embed
+            && self.inline_context == other.inline_context
+            && self.injected_patterns == other.injected_patterns
+    }
 }
 
 impl ContextFrame {
@@ -607,6 +1290,7 @@ impl ContextFrame {
             inline_context: None,
             injected_patterns: Vec::new(),
             entered_at_line,
+            embed: None,
         }
     }
 
@@ -623,6 +1307,7 @@ impl ContextFrame {
             inline_context: None,
             injected_patterns,
             entered_at_line,
+            embed: None,
         }
     }
 
@@ -639,6 +1324,7 @@ impl ContextFrame {
             inline_context: Some(context),
             injected_patterns,
             entered_at_line,
+            embed: None,
         }
     }
 
@@ -690,11 +1376,51 @@ impl ContextFrameSnapshot {
     }
 }
 
+/// A match with `branch_point` that a later `fail` can rewind to.
+#[derive(Debug)]
+struct BranchPoint {
+    name: String,
+    /// The branching match, whose action lists the alternatives.
+    pattern: CompiledMatchPattern,
+    /// Line of the match and the position just after it.
+    line: usize,
+    end_byte: usize,
+    end_char: usize,
+    /// Index of the alternative being tried.
+    alternative: usize,
+    /// Stack depth right after pushing the alternative; popping below it ends the branch.
+    depth: usize,
+    state: BranchState,
+}
+
+/// Highlighter state from just before a branching match.
+#[derive(Debug)]
+struct BranchState {
+    context_stack: Vec<ContextFrame>,
+    intervals_len: usize,
+    /// End of the last interval, which later segments may have extended in place.
+    last_interval_end: Option<usize>,
+    scope_spans_len: usize,
+    fold_regions_len: usize,
+    /// Char offset of the match within its line.
+    start_char: usize,
+}
+
+/// Where highlighting continues after a rewind.
+#[derive(Debug)]
+struct Rewind {
+    line: usize,
+    byte: usize,
+    char: usize,
+}
+
 #[derive(Debug)]
 struct FoundMatch {
     start_byte: usize,
     end_byte: usize,
     pattern: CompiledMatchPattern,
+    /// The escape of an active embed (its action pops the embed).
+    is_escape: bool,
 }
 
 #[derive(Debug, Default)]
@@ -813,48 +1539,97 @@ fn search_first(
     Ok(region.pos(0))
 }
 
-#[derive(Debug, Clone, Copy)]
-enum ScopeMode {
-    Content,
-    Match,
+/// Texts of the capture groups of `regex` matching at `from` (index 0 is the whole match).
+fn match_captures(regex: &Arc<onig::Regex>, text: &str, from: usize) -> Vec<Option<String>> {
+    let mut region = Region::new();
+    if regex
+        .search_with_options(
+            text,
+            from,
+            text.len(),
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+        )
+        .is_none()
+    {
+        return Vec::new();
+    }
+    (0..region.len())
+        .map(|index| {
+            region
+                .pos(index)
+                .map(|(start, end)| text[start..end].to_string())
+        })
+        .collect()
+}
+
+/// Number of contexts pushed by `action`, or `None` if it does not push any.
+fn pushed_context_count(action: &MatchAction) -> Option<usize> {
+    match action {
+        MatchAction::Push { push: contexts, .. } | MatchAction::Set { set: contexts, .. } => {
+            Some(match contexts {
+                ContextPush::One(_) => 1,
+                ContextPush::Many(specs) => specs.len(),
+            })
+        }
+        MatchAction::Branch { .. } => Some(1),
+        _ => None,
+    }
 }
 
+/// The first of `ctx`'s meta scopes matching one of the `selectors` (see
+/// [`DEFAULT_FOLD_SCOPES`]).
+fn fold_scope_of<'c>(ctx: &'c CompiledContext, selectors: &[String]) -> Option<&'c str> {
+    ctx.meta_scope
+        .iter()
+        .chain(&ctx.meta_content_scope)
+        .map(String::as_str)
+        .find(|scope| {
+            selectors.iter().any(|selector| {
+                scope
+                    .strip_prefix(selector.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+        })
+}
+
+/// The scope stack from the context `frames`: frames from `meta_only_from` up contribute their
+/// `meta_scope` only. A `pattern` adds its own scope (after its `clear_scopes`).
 fn compute_scopes(
     frames: &[ContextFrame],
-    mode: ScopeMode,
-    match_scope: Option<&Vec<String>>,
+    meta_only_from: usize,
+    pattern: Option<&CompiledMatchPattern>,
     base_scope: &str,
 ) -> Vec<String> {
     let mut stack: Vec<String> = Vec::new();
     stack.push(base_scope.to_string());
 
-    let top_idx = frames.len().saturating_sub(1);
     for (idx, frame) in frames.iter().enumerate() {
         let Ok(ctx) = frame.context() else {
             continue;
         };
 
-        apply_clear_scopes(&mut stack, ctx);
+        if let Some(embed) = &frame.embed {
+            stack.extend(embed.embed_scope.iter().cloned());
+        }
+        apply_clear_scopes(&mut stack, ctx.clear_scopes.as_ref());
         stack.extend(ctx.meta_scope.iter().cloned());
 
-        let include_content_scopes = match mode {
-            ScopeMode::Content => true,
-            ScopeMode::Match => idx != top_idx,
-        };
-        if include_content_scopes {
+        if idx < meta_only_from {
             stack.extend(ctx.meta_content_scope.iter().cloned());
         }
     }
 
-    if let (ScopeMode::Match, Some(scope)) = (mode, match_scope) {
-        stack.extend(scope.iter().cloned());
+    if let Some(pattern) = pattern {
+        apply_clear_scopes(&mut stack, pattern.clear_scopes.as_ref());
+        stack.extend(pattern.scope.iter().cloned());
     }
 
     stack
 }
 
-fn apply_clear_scopes(stack: &mut Vec<String>, ctx: &CompiledContext) {
-    let Some(clear) = &ctx.clear_scopes else {
+fn apply_clear_scopes(stack: &mut Vec<String>, clear_scopes: Option<&ClearScopes>) {
+    let Some(clear) = clear_scopes else {
         return;
     };
 
@@ -1,3 +1,4 @@
+use editor_core::pattern::PatternError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -44,6 +45,16 @@ pub enum SublimeSyntaxError {
         message: String,
     },
 
+    #[error("pattern '{pattern}': {error}")]
+    /// A regex pattern exceeded the regex engine's size/complexity limits
+    /// ([`PatternError::EngineLimit`]).
+    PatternTooComplex {
+        /// The regex pattern string.
+        pattern: String,
+        /// The shared pattern error.
+        error: PatternError,
+    },
+
     #[error("unsupported feature: {0}")]
     /// A feature from the Sublime syntax format is not implemented.
     Unsupported(&'static str),
@@ -9,6 +9,7 @@ mod engine;
 mod error;
 mod scope;
 mod set;
+mod syntax_test;
 
 pub use compiler::{
     CompiledContext, CompiledIncludePattern, CompiledMatchPattern, CompiledPattern, ContextPush,
@@ -18,7 +19,11 @@ pub use definition::{
     CaptureSpec, ClearScopes, ContextReference, Extends, MatchPattern, MetaPattern, PopAction,
     RawContextPattern, SyntaxDefinition,
 };
-pub use engine::{SublimeHighlightResult, highlight_document};
+pub use engine::{
+    DEFAULT_FOLD_SCOPES, SublimeHighlightResult, default_fold_scopes, highlight_document,
+};
+pub(crate) use engine::{LineStates, highlight_document_with_states, rehighlight_dirty_lines};
 pub use error::SublimeSyntaxError;
 pub use scope::SublimeScopeMapper;
-pub use set::SublimeSyntaxSet;
+pub use set::{SublimeSyntaxLoadReport, SublimeSyntaxSet};
+pub use syntax_test::{SyntaxTest, SyntaxTestFailure, SyntaxTestReport};
@@ -1,14 +1,26 @@
-use editor_core::intervals::StyleId;
+use editor_core::intervals::{StyleId, StyleRegistry};
 use std::collections::HashMap;
 
 /// A simple scope-to-`StyleId` mapper for `.sublime-syntax` scopes.
 ///
 /// Consumers are expected to keep a mapper instance around so `StyleId`s stay
-/// stable across re-highlighting passes.
+/// stable across re-highlighting passes: a scope keeps the `StyleId` it was first
+/// given for the lifetime of the mapper, and IDs are never reused.
+///
+/// Hosts can precompute a style table from [`iter`](Self::iter) instead of looking
+/// up the scope of every cell; since IDs are allocated in order, new entries can be
+/// appended with `iter().skip(known)`.
+///
+/// A mapper created with [`with_registry`](Self::with_registry) allocates its IDs from a
+/// shared [`StyleRegistry`] under the names `scope:<scope>` instead.
 #[derive(Debug, Default)]
 pub struct SublimeScopeMapper {
     scope_to_id: HashMap<String, StyleId>,
-    id_to_scope: Vec<String>,
+    /// Allocated `(StyleId, scope)` pairs, in allocation order.
+    scopes: Vec<(StyleId, String)>,
+    registry: Option<StyleRegistry>,
+    /// Index into `scopes` of registry-allocated IDs (which are not dense).
+    registry_index: HashMap<StyleId, usize>,
 }
 
 impl SublimeScopeMapper {
@@ -18,32 +30,83 @@ impl SublimeScopeMapper {
     /// tokens, simple regex highlighting).
     pub const BASE: StyleId = 0x0300_0000;
 
+    /// Prefix of the names this mapper registers in a [`StyleRegistry`].
+    pub const REGISTRY_PREFIX: &'static str = "scope:";
+
     /// Create a new scope mapper.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Create a scope mapper allocating `StyleId`s from `registry` (as `scope:<scope>`).
+    pub fn with_registry(registry: StyleRegistry) -> Self {
+        Self {
+            registry: Some(registry),
+            ..Self::default()
+        }
+    }
+
+    /// The registry IDs are allocated from, if any.
+    pub fn registry(&self) -> Option<&StyleRegistry> {
+        self.registry.as_ref()
+    }
+
     /// Get (or allocate) a stable `StyleId` for a Sublime scope string.
     pub fn style_id_for_scope(&mut self, scope: &str) -> StyleId {
         if let Some(&id) = self.scope_to_id.get(scope) {
             return id;
         }
 
-        // Keep IDs dense for fast reverse lookup. 0 is unused within this range.
-        let idx = self.id_to_scope.len() as u32 + 1;
-        let id = Self::BASE | idx;
+        let id = match &self.registry {
+            Some(registry) => {
+                let id = registry.style_id(&format!("{}{scope}", Self::REGISTRY_PREFIX));
+                self.registry_index.insert(id, self.scopes.len());
+                id
+            }
+            // Keep IDs dense for fast reverse lookup. 0 is unused within this range.
+            None => Self::BASE | (self.scopes.len() as u32 + 1),
+        };
 
-        self.id_to_scope.push(scope.to_string());
+        self.scopes.push((id, scope.to_string()));
         self.scope_to_id.insert(scope.to_string(), id);
         id
     }
 
     /// Return the original scope string for a previously allocated `StyleId`.
     pub fn scope_for_style_id(&self, style_id: StyleId) -> Option<&str> {
-        if style_id & 0xFF00_0000 != Self::BASE {
-            return None;
-        }
-        let idx = (style_id & 0x00FF_FFFF).saturating_sub(1) as usize;
-        self.id_to_scope.get(idx).map(|s| s.as_str())
+        let idx = if self.registry.is_some() {
+            *self.registry_index.get(&style_id)?
+        } else {
+            if style_id & 0xFF00_0000 != Self::BASE {
+                return None;
+            }
+            (style_id & 0x00FF_FFFF).checked_sub(1)? as usize
+        };
+        self.scopes.get(idx).map(|(_, scope)| scope.as_str())
+    }
+
+    /// Look up the scopes of several `StyleId`s at once (see [`scope_for_style_id`](Self::scope_for_style_id)).
+    pub fn scopes_for_style_ids(&self, style_ids: &[StyleId]) -> Vec<Option<&str>> {
+        style_ids
+            .iter()
+            .map(|&style_id| self.scope_for_style_id(style_id))
+            .collect()
+    }
+
+    /// Iterate over all allocated `(StyleId, scope)` pairs, in allocation order.
+    pub fn iter(&self) -> impl Iterator<Item = (StyleId, &str)> + '_ {
+        self.scopes
+            .iter()
+            .map(|(style_id, scope)| (*style_id, scope.as_str()))
+    }
+
+    /// Number of allocated scopes.
+    pub fn len(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Returns `true` if no scope has been allocated yet.
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
     }
 }
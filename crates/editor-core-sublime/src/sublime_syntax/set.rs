@@ -6,9 +6,20 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Debug, Default)]
+/// The outcome of [`SublimeSyntaxSet::load_from_directory`].
+pub struct SublimeSyntaxLoadReport {
+    /// Syntaxes that compiled successfully, in path order.
+    pub loaded: Vec<Arc<SublimeSyntax>>,
+    /// Files that failed to load, with the reason.
+    pub errors: Vec<(PathBuf, SublimeSyntaxError)>,
+}
+
+#[derive(Debug, Default, Clone)]
 /// A collection of compiled Sublime syntax definitions, with support for resolving `extends` and `include`.
 pub struct SublimeSyntaxSet {
     search_paths: Vec<PathBuf>,
+    /// Every loaded syntax in load order (one entry per scope; reloading replaces it in place).
+    syntaxes: Vec<Arc<SublimeSyntax>>,
     compiled_by_scope: HashMap<String, Arc<SublimeSyntax>>,
     compiled_by_reference: HashMap<String, Arc<SublimeSyntax>>,
     merged_definition_cache: HashMap<String, SyntaxDefinition>,
@@ -30,6 +41,86 @@ impl SublimeSyntaxSet {
         self.compiled_by_scope.get(scope).cloned()
     }
 
+    /// All loaded syntaxes, in load order.
+    pub fn syntaxes(&self) -> &[Arc<SublimeSyntax>] {
+        &self.syntaxes
+    }
+
+    /// Find the first loaded syntax listing `extension` (with or without a leading `.`) in its
+    /// `file_extensions` or `hidden_file_extensions`. Matching is ASCII case-insensitive.
+    ///
+    /// Sublime also lists whole file names here (e.g. `Makefile`), so a file name works too.
+    pub fn find_by_extension(&self, extension: &str) -> Option<Arc<SublimeSyntax>> {
+        let extension = extension.strip_prefix('.').unwrap_or(extension);
+        self.syntaxes
+            .iter()
+            .find(|syntax| {
+                syntax
+                    .file_extensions
+                    .iter()
+                    .chain(&syntax.hidden_file_extensions)
+                    .any(|ext| ext.eq_ignore_ascii_case(extension))
+            })
+            .cloned()
+    }
+
+    /// Find the first loaded syntax whose `name` matches (ASCII case-insensitive).
+    pub fn find_by_name(&self, name: &str) -> Option<Arc<SublimeSyntax>> {
+        self.syntaxes
+            .iter()
+            .find(|syntax| {
+                syntax
+                    .name
+                    .as_deref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+    }
+
+    /// Find the first loaded syntax whose `first_line_match` matches `line`
+    /// (e.g. a `#!/usr/bin/env python` shebang).
+    pub fn find_by_first_line(&self, line: &str) -> Option<Arc<SublimeSyntax>> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        self.syntaxes
+            .iter()
+            .find(|syntax| {
+                syntax
+                    .first_line_regex
+                    .as_ref()
+                    .is_some_and(|re| re.find(line).is_some())
+            })
+            .cloned()
+    }
+
+    /// Loads every `.sublime-syntax` file under `dir` (recursively, in path order).
+    ///
+    /// A file that fails to parse or compile is recorded in the report and does not stop the
+    /// others; only failing to read `dir` itself is an error. `dir` is also added as a search
+    /// path. Cross-syntax `include: scope:...` references are resolved against the set when
+    /// highlighting, so files may reference each other regardless of load order.
+    pub fn load_from_directory(
+        &mut self,
+        dir: impl AsRef<Path>,
+    ) -> Result<SublimeSyntaxLoadReport, SublimeSyntaxError> {
+        let dir = dir.as_ref();
+        let mut paths = Vec::new();
+        collect_syntax_files(dir, &mut paths)?;
+        paths.sort();
+
+        if !self.search_paths.iter().any(|p| p == dir) {
+            self.add_search_path(dir);
+        }
+
+        let mut report = SublimeSyntaxLoadReport::default();
+        for path in paths {
+            match self.load_from_path(&path) {
+                Ok(syntax) => report.loaded.push(syntax),
+                Err(err) => report.errors.push((path, err)),
+            }
+        }
+        Ok(report)
+    }
+
     /// Loads a syntax from a YAML string and returns the compiled result.
     ///
     /// If the syntax uses `extends: ...`, it will be resolved via `search_paths`.
@@ -37,8 +128,7 @@ impl SublimeSyntaxSet {
         let definition: SyntaxDefinition = serde_yaml::from_str(yaml)?;
         let resolved = self.resolve_inheritance(definition, &mut Vec::new())?;
         let compiled = Arc::new(SublimeSyntax::compile(resolved)?);
-        self.compiled_by_scope
-            .insert(compiled.scope.clone(), compiled.clone());
+        self.register(&compiled);
         Ok(compiled)
     }
 
@@ -56,8 +146,7 @@ impl SublimeSyntaxSet {
 
         self.compiled_by_reference
             .insert(reference, compiled.clone());
-        self.compiled_by_scope
-            .insert(compiled.scope.clone(), compiled.clone());
+        self.register(&compiled);
         Ok(compiled)
     }
 
@@ -93,9 +182,21 @@ impl SublimeSyntaxSet {
             .insert(reference.to_string(), compiled.clone());
         self.compiled_by_reference
             .insert(path.to_string_lossy().to_string(), compiled.clone());
+        self.register(&compiled);
+        Ok(compiled)
+    }
+
+    fn register(&mut self, compiled: &Arc<SublimeSyntax>) {
+        match self
+            .syntaxes
+            .iter_mut()
+            .find(|syntax| syntax.scope == compiled.scope)
+        {
+            Some(existing) => *existing = compiled.clone(),
+            None => self.syntaxes.push(compiled.clone()),
+        }
         self.compiled_by_scope
             .insert(compiled.scope.clone(), compiled.clone());
-        Ok(compiled)
     }
 
     fn resolve_reference_to_path(&self, reference: &str) -> Option<PathBuf> {
@@ -211,6 +312,18 @@ impl SublimeSyntaxSet {
     }
 }
 
+fn collect_syntax_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), SublimeSyntaxError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_syntax_files(&path, out)?;
+        } else if path.extension().is_some_and(|ext| ext == "sublime-syntax") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
 fn inheritance_directives(
     patterns: &[RawContextPattern],
 ) -> Result<(bool, bool), SublimeSyntaxError> {
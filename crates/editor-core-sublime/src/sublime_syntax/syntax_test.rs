@@ -0,0 +1,242 @@
+use crate::sublime_syntax::compiler::SublimeSyntax;
+use crate::sublime_syntax::engine::{ScopeSpan, highlight_scope_stacks};
+use crate::sublime_syntax::error::SublimeSyntaxError;
+use crate::sublime_syntax::set::SublimeSyntaxSet;
+use editor_core::LineIndex;
+use std::fmt;
+use std::sync::Arc;
+
+/// A Sublime Text syntax test (a `syntax_test_*` file).
+///
+/// The first line names the syntax under test after the file's comment token:
+///
+/// ```text
+/// // SYNTAX TEST "Packages/Rust/Rust.sublime-syntax"
+/// let v: Vec<u8> = Vec::new();
+/// //     ^^^ support.type
+/// // <- storage.modifier
+/// ```
+///
+/// Lines starting with the comment token followed by `^` assert the scope of the columns under
+/// the carets, on the closest line above that is not an assertion; `<-` asserts the column of
+/// the comment token itself. The rest of the line is a scope selector: space-separated scope
+/// prefixes that must appear in order, `-` to exclude scopes, and `|` / `,` / `&` to combine
+/// selectors (groups in parentheses are not supported).
+#[derive(Debug, Clone)]
+pub struct SyntaxTest {
+    /// The syntax reference from the header (e.g. `Packages/Rust/Rust.sublime-syntax`).
+    pub syntax_path: String,
+    text: String,
+    assertions: Vec<ScopeAssertion>,
+}
+
+#[derive(Debug, Clone)]
+struct ScopeAssertion {
+    line: usize,
+    columns: std::ops::Range<usize>,
+    selector: String,
+}
+
+/// Outcome of [`SyntaxTest::run`].
+#[derive(Debug, Clone, Default)]
+pub struct SyntaxTestReport {
+    /// Number of assertion lines checked.
+    pub assertions: usize,
+    /// One entry per column whose scopes did not match its selector.
+    pub failures: Vec<SyntaxTestFailure>,
+}
+
+impl SyntaxTestReport {
+    /// Returns `true` if every assertion held.
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A column whose scopes did not match the asserted selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxTestFailure {
+    /// Line of the tested text (0-based).
+    pub line: usize,
+    /// Column of the tested text, in chars (0-based).
+    pub column: usize,
+    /// The asserted selector.
+    pub selector: String,
+    /// The actual scope stack at that position, innermost last.
+    pub scopes: Vec<String>,
+}
+
+impl fmt::Display for SyntaxTestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: expected `{}`, found `{}`",
+            self.line + 1,
+            self.column + 1,
+            self.selector,
+            self.scopes.join(" ")
+        )
+    }
+}
+
+impl SyntaxTest {
+    /// Parse a syntax test file: its `SYNTAX TEST` header and assertion lines.
+    pub fn parse(source: &str) -> Result<Self, SublimeSyntaxError> {
+        let lines: Vec<&str> = source.lines().collect();
+        let header = lines.first().copied().unwrap_or_default();
+        let Some(marker) = header.find("SYNTAX TEST") else {
+            return Err(SublimeSyntaxError::MissingField("SYNTAX TEST header"));
+        };
+        let comment_start = header[..marker].trim();
+        if comment_start.is_empty() {
+            return Err(SublimeSyntaxError::MissingField(
+                "comment token before SYNTAX TEST",
+            ));
+        }
+        let rest = &header[marker + "SYNTAX TEST".len()..];
+        let Some((_, quoted)) = rest.split_once('"') else {
+            return Err(SublimeSyntaxError::MissingField("SYNTAX TEST syntax path"));
+        };
+        let Some((syntax_path, comment_end)) = quoted.split_once('"') else {
+            return Err(SublimeSyntaxError::MissingField("SYNTAX TEST syntax path"));
+        };
+        let comment_end = comment_end.trim();
+
+        let mut assertions = Vec::new();
+        let mut tested_line = 0usize;
+        for (line_number, line) in lines.iter().enumerate().skip(1) {
+            match parse_assertion(line, comment_start, comment_end) {
+                Some((columns, selector)) => assertions.push(ScopeAssertion {
+                    line: tested_line,
+                    columns,
+                    selector,
+                }),
+                None => tested_line = line_number,
+            }
+        }
+
+        Ok(Self {
+            syntax_path: syntax_path.to_string(),
+            text: lines.join("\n"),
+            assertions,
+        })
+    }
+
+    /// Highlight the test file with `syntax` and check every assertion.
+    pub fn run(
+        &self,
+        syntax: Arc<SublimeSyntax>,
+        syntax_set: Option<&mut SublimeSyntaxSet>,
+    ) -> Result<SyntaxTestReport, SublimeSyntaxError> {
+        let base_scope = syntax.scope.clone();
+        let line_index = LineIndex::from_text(&self.text);
+        let spans = highlight_scope_stacks(syntax, &line_index, syntax_set)?;
+
+        let mut report = SyntaxTestReport {
+            assertions: self.assertions.len(),
+            failures: Vec::new(),
+        };
+        for assertion in &self.assertions {
+            let line_len = line_index
+                .get_line_text(assertion.line)
+                .map_or(0, |text| text.chars().count());
+            for column in assertion.columns.clone() {
+                let scopes = if column < line_len {
+                    let offset = line_index.position_to_char_offset(assertion.line, column);
+                    scopes_at(&spans, offset).unwrap_or_else(|| vec![base_scope.clone()])
+                } else {
+                    Vec::new()
+                };
+                if !selector_matches(&assertion.selector, &scopes) {
+                    report.failures.push(SyntaxTestFailure {
+                        line: assertion.line,
+                        column,
+                        selector: assertion.selector.clone(),
+                        scopes,
+                    });
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// The asserted columns and selector of an assertion line, or `None` for any other line.
+fn parse_assertion(
+    line: &str,
+    comment_start: &str,
+    comment_end: &str,
+) -> Option<(std::ops::Range<usize>, String)> {
+    let indent = line.len() - line.trim_start().len();
+    let after_comment = line[indent..].strip_prefix(comment_start)?;
+    let body = after_comment.trim_start();
+
+    let (columns, selector) = if let Some(selector) = body.strip_prefix("<-") {
+        let column = line[..indent].chars().count();
+        (column..column + 1, selector)
+    } else if body.starts_with('^') {
+        let carets = body.chars().take_while(|&ch| ch == '^').count();
+        let column = line[..line.len() - body.len()].chars().count();
+        (column..column + carets, &body[carets..])
+    } else {
+        return None;
+    };
+
+    let selector = selector.trim();
+    let selector = if comment_end.is_empty() {
+        selector
+    } else {
+        selector
+            .strip_suffix(comment_end)
+            .unwrap_or(selector)
+            .trim()
+    };
+    Some((columns, selector.to_string()))
+}
+
+fn scopes_at(spans: &[ScopeSpan], offset: usize) -> Option<Vec<String>> {
+    let index = spans.partition_point(|span| span.end <= offset);
+    spans
+        .get(index)
+        .filter(|span| span.start <= offset)
+        .map(|span| span.scopes.clone())
+}
+
+/// Whether `scopes` (outermost first) match a scope selector.
+fn selector_matches(selector: &str, scopes: &[String]) -> bool {
+    selector.split(['|', ',']).any(|alternative| {
+        alternative
+            .split('&')
+            .all(|conjunct| conjunct_matches(conjunct, scopes))
+    })
+}
+
+/// A path of scope prefixes, optionally followed by `- path` exclusions.
+fn conjunct_matches(conjunct: &str, scopes: &[String]) -> bool {
+    let mut paths: Vec<(bool, Vec<&str>)> = vec![(true, Vec::new())];
+    for token in conjunct.split_whitespace() {
+        if token == "-" {
+            paths.push((false, Vec::new()));
+        } else if let Some(excluded) = token.strip_prefix('-') {
+            paths.push((false, vec![excluded]));
+        } else if let Some((_, path)) = paths.last_mut() {
+            path.push(token);
+        }
+    }
+
+    paths
+        .iter()
+        .all(|(included, path)| path_matches(path, scopes) == *included)
+}
+
+fn path_matches(path: &[&str], scopes: &[String]) -> bool {
+    let mut remaining = scopes.iter();
+    path.iter()
+        .all(|prefix| remaining.any(|scope| scope_has_prefix(scope, prefix)))
+}
+
+fn scope_has_prefix(scope: &str, prefix: &str) -> bool {
+    scope
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
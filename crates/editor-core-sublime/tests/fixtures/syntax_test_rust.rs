@@ -0,0 +1,23 @@
+// SYNTAX TEST "Packages/Rust/Rust.sublime-syntax"
+let v: Vec<Vec<u8>> = Vec::new();
+// <- storage.modifier.rust
+//     ^^^ source.rust support.type.rust - meta.generic
+//        ^ meta.generic.rust punctuation.definition.generic.begin.rust
+//         ^^^ meta.generic.rust support.type.rust
+//            ^ meta.generic.rust meta.generic.rust punctuation.definition.generic.begin.rust
+//             ^^ meta.generic.rust meta.generic.rust storage.type.rust
+//               ^ meta.generic.rust meta.generic.rust punctuation.definition.generic.end.rust
+//                ^ meta.generic.rust punctuation.definition.generic.end.rust - meta.generic meta.generic
+//                  ^ keyword.operator.rust - meta.generic
+//                    ^^^ variable.other.rust
+
+let ok = a < b;
+//       ^ variable.other.rust - support
+//         ^ keyword.operator.comparison.rust - meta.generic
+//           ^ variable.other.rust
+
+let s = "a\"b"; /* block
+//      ^ string.quoted.double.rust punctuation.definition.string.begin.rust
+//        ^^ string.quoted.double.rust constant.character.escape.rust
+   comment */
+// <- comment.block.rust
@@ -0,0 +1,91 @@
+use editor_core::{Command, EditCommand, EditorStateManager};
+use editor_core_sublime::{SublimeProcessor, SublimeSyntaxSet};
+
+const JSON: &str = r#"{
+  "name": "demo",
+  "items": [
+    1,
+    {
+      "a": true,
+      "b": [2, 3]
+    }
+  ],
+  /* multi-line
+     comment */
+  "empty": {}
+}
+"#;
+
+fn json_processor() -> SublimeProcessor {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let syntax = syntax_set
+        .load_from_str(include_str!("fixtures/JSON.sublime-syntax"))
+        .expect("compile JSON syntax");
+    SublimeProcessor::new(syntax, syntax_set)
+}
+
+/// Derived folds of `state` as `(start_line, end_line, is_collapsed)`.
+fn folds(state: &EditorStateManager) -> Vec<(usize, usize, bool)> {
+    let mut folds: Vec<_> = state
+        .editor()
+        .folding_manager
+        .derived_regions()
+        .iter()
+        .map(|r| (r.start_line, r.end_line, r.is_collapsed))
+        .collect();
+    folds.sort();
+    folds
+}
+
+#[test]
+fn test_json_objects_and_arrays_fold_and_nest() {
+    let mut state = EditorStateManager::new(JSON, 80);
+    let mut processor = json_processor();
+    state.apply_processor(&mut processor).expect("process");
+
+    // Single-line `[2, 3]` and `{}` do not fold; nested regions lie inside their parents.
+    assert_eq!(
+        folds(&state),
+        vec![(0, 12, false), (2, 8, false), (4, 7, false), (9, 10, false)]
+    );
+
+    processor.set_fold_scopes(vec!["meta.sequence".to_string()]);
+    state.apply_processor(&mut processor).expect("process");
+    assert_eq!(folds(&state), vec![(2, 8, false)]);
+}
+
+#[test]
+fn test_collapsed_fold_survives_rehighlight() {
+    let mut state = EditorStateManager::new(JSON, 80);
+    let mut processor = json_processor();
+    state.apply_processor(&mut processor).expect("process");
+
+    assert!(
+        state
+            .editor_mut()
+            .folding_manager
+            .toggle_region_starting_at_line(4)
+    );
+
+    // Growing the collapsed object moves its end line; it is matched by start line and scope.
+    let offset = state.editor().line_index.position_to_char_offset(6, 0);
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: "      \"c\": null,\n".to_string(),
+        }))
+        .expect("insert");
+    state.apply_processor(&mut processor).expect("process");
+    assert_eq!(
+        folds(&state),
+        vec![(0, 13, false), (2, 9, false), (4, 8, true), (10, 11, false)]
+    );
+
+    // A full re-highlight keeps it collapsed too.
+    processor.set_fold_scopes(processor.fold_scopes().to_vec());
+    state.apply_processor(&mut processor).expect("process");
+    assert_eq!(
+        folds(&state),
+        vec![(0, 13, false), (2, 9, false), (4, 8, true), (10, 11, false)]
+    );
+}
@@ -0,0 +1,214 @@
+use editor_core::intervals::StyleLayerId;
+use editor_core::processing::{DocumentProcessor, ProcessingEdit};
+use editor_core::{Command, EditCommand, EditorStateManager, LineIndex};
+use editor_core_sublime::{
+    SublimeProcessor, SublimeScopeMapper, SublimeSyntaxSet, highlight_document,
+};
+
+fn large_toml(sections: usize) -> String {
+    let mut text = String::new();
+    for i in 0..sections {
+        text.push_str(&format!(
+            "[section{i}]\nname = \"value {i}\" # comment\nnumbers = [\n  1,\n  {i},\n]\n\n"
+        ));
+    }
+    text
+}
+
+fn toml_processor() -> SublimeProcessor {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let syntax = syntax_set
+        .load_from_str(include_str!("fixtures/TOML.sublime-syntax"))
+        .expect("compile TOML syntax");
+    SublimeProcessor::new(syntax, syntax_set)
+}
+
+/// The style layer and derived folds of `state`, as `(start, end, scope)` and line pairs.
+type Highlights = (Vec<(usize, usize, String)>, Vec<(usize, usize)>);
+
+fn current_highlights(state: &EditorStateManager, processor: &SublimeProcessor) -> Highlights {
+    let editor = state.editor();
+    let intervals = editor
+        .style_layers
+        .get(&StyleLayerId::SUBLIME_SYNTAX)
+        .map(|tree| {
+            tree.query_range(0, usize::MAX)
+                .into_iter()
+                .map(|i| {
+                    let scope = processor.scope_mapper.scope_for_style_id(i.style_id);
+                    (i.start, i.end, scope.unwrap_or_default().to_string())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut folds: Vec<_> = editor
+        .folding_manager
+        .derived_regions()
+        .iter()
+        .map(|r| (r.start_line, r.end_line))
+        .collect();
+    folds.sort();
+    folds.dedup();
+    (intervals, folds)
+}
+
+fn full_highlights(state: &EditorStateManager, processor: &SublimeProcessor) -> Highlights {
+    let line_index = LineIndex::from_text(&state.editor().get_text());
+    let mut mapper = SublimeScopeMapper::new();
+    let result = highlight_document(processor.syntax().clone(), &line_index, None, &mut mapper)
+        .expect("highlight");
+    let intervals = result
+        .intervals
+        .iter()
+        .map(|i| {
+            let scope = mapper.scope_for_style_id(i.style_id);
+            (i.start, i.end, scope.unwrap_or_default().to_string())
+        })
+        .collect();
+    let mut folds: Vec<_> = result
+        .fold_regions
+        .iter()
+        .map(|r| (r.start_line, r.end_line))
+        .collect();
+    folds.sort();
+    folds.dedup();
+    (intervals, folds)
+}
+
+fn assert_same(a: Highlights, b: Highlights) {
+    for (x, y) in a.0.iter().zip(&b.0) {
+        assert_eq!(x, y);
+    }
+    assert_eq!(a, b);
+}
+
+fn insert(state: &mut EditorStateManager, line: usize, column: usize, text: &str) {
+    let offset = state
+        .editor()
+        .line_index
+        .position_to_char_offset(line, column);
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: text.to_string(),
+        }))
+        .expect("insert");
+}
+
+fn delete(state: &mut EditorStateManager, line: usize, column: usize, length: usize) {
+    let start = state
+        .editor()
+        .line_index
+        .position_to_char_offset(line, column);
+    state
+        .execute(Command::Edit(EditCommand::Delete { start, length }))
+        .expect("delete");
+}
+
+#[test]
+fn test_edit_in_large_toml_rehighlights_bounded_lines() {
+    let mut state = EditorStateManager::new(&large_toml(500), 80);
+    let mut processor = toml_processor();
+    state.apply_processor(&mut processor).expect("process");
+    assert_eq!(processor.last_highlighted_lines(), 0..3501);
+
+    // Line 1751 is `name = "value 250" # comment`; edit inside the string.
+    insert(&mut state, 1751, 10, "X");
+    let edits = processor.process(&state).expect("process");
+    assert!(matches!(
+        edits.as_slice(),
+        [ProcessingEdit::ReplaceStyleLayerRange { .. }]
+    ));
+    state.apply_processing_edits(edits);
+    let lines = processor.last_highlighted_lines();
+    assert!(lines.contains(&1751), "{lines:?}");
+    assert!(lines.len() <= 3, "{lines:?}");
+    assert_same(
+        current_highlights(&state, &processor),
+        full_highlights(&state, &processor),
+    );
+
+    // Joining two lines and splitting one keeps the rest of the document as it was.
+    delete(&mut state, 1752, 11, 1);
+    state.apply_processor(&mut processor).expect("process");
+    assert!(processor.last_highlighted_lines().len() <= 3);
+    insert(&mut state, 1752, 11, "\n");
+    state.apply_processor(&mut processor).expect("process");
+    assert!(processor.last_highlighted_lines().len() <= 3);
+    assert_same(
+        current_highlights(&state, &processor),
+        full_highlights(&state, &processor),
+    );
+
+    // No edit: nothing to do.
+    assert!(processor.process(&state).expect("process").is_empty());
+}
+
+#[test]
+fn test_multi_line_string_edits_match_full_highlight() {
+    let mut state = EditorStateManager::new(&large_toml(40), 80);
+    let mut processor = toml_processor();
+    state.apply_processor(&mut processor).expect("process");
+
+    // Opening a multi-line string restyles everything up to the next `"""`.
+    insert(&mut state, 141, 0, "text = \"\"\"\n");
+    state.apply_processor(&mut processor).expect("process");
+    assert_eq!(processor.last_highlighted_lines().end, 282);
+    assert_same(
+        current_highlights(&state, &processor),
+        full_highlights(&state, &processor),
+    );
+
+    // Closing it resumes from the cached state of the edited line.
+    insert(&mut state, 150, 0, "\"\"\"\n");
+    state.apply_processor(&mut processor).expect("process");
+    assert_eq!(processor.last_highlighted_lines(), 150..283);
+    assert_same(
+        current_highlights(&state, &processor),
+        full_highlights(&state, &processor),
+    );
+
+    // Removing a whole array (its fold goes away) and an edit spanning several lines.
+    delete(
+        &mut state,
+        2,
+        0,
+        "numbers = [\n  1,\n  0,\n]\n".chars().count(),
+    );
+    state.apply_processor(&mut processor).expect("process");
+    assert!(processor.last_highlighted_lines().len() <= 3);
+    insert(&mut state, 10, 0, "x = [\n  1,\n]\n");
+    state.apply_processor(&mut processor).expect("process");
+    assert_same(
+        current_highlights(&state, &processor),
+        full_highlights(&state, &processor),
+    );
+}
+
+#[test]
+fn test_branch_rewinds_match_full_highlight_after_edits() {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let syntax = syntax_set
+        .load_from_str(include_str!("fixtures/Rust.sublime-syntax"))
+        .expect("compile Rust syntax");
+    let mut processor = SublimeProcessor::new(syntax, syntax_set);
+
+    let text = "fn main() {\n    let a = b < c;\n    let v: Vec<u8> = x;\n}\n".repeat(20);
+    let mut state = EditorStateManager::new(&text, 80);
+    state.apply_processor(&mut processor).expect("process");
+
+    // Turn a comparison into generics and back.
+    insert(&mut state, 41, 17, ">");
+    state.apply_processor(&mut processor).expect("process");
+    assert_same(
+        current_highlights(&state, &processor),
+        full_highlights(&state, &processor),
+    );
+    delete(&mut state, 41, 17, 1);
+    state.apply_processor(&mut processor).expect("process");
+    assert!(processor.last_highlighted_lines().len() <= 3);
+    assert_same(
+        current_highlights(&state, &processor),
+        full_highlights(&state, &processor),
+    );
+}
@@ -0,0 +1,92 @@
+use editor_core::LineIndex;
+use editor_core::intervals::StyleRegistry;
+use editor_core_sublime::{SublimeScopeMapper, SublimeSyntaxSet, highlight_document};
+
+#[test]
+fn test_style_ids_are_stable_across_re_highlights() {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let syntax = syntax_set
+        .load_from_str(include_str!("fixtures/TOML.sublime-syntax"))
+        .expect("compile TOML syntax");
+    let mut mapper = SublimeScopeMapper::new();
+
+    let first_text = "title = \"x\" # comment\n[server]\nport = 80\n";
+    let first = highlight_document(
+        syntax.clone(),
+        &LineIndex::from_text(first_text),
+        Some(&mut syntax_set),
+        &mut mapper,
+    )
+    .expect("highlight");
+    let known: Vec<(u32, String)> = mapper
+        .iter()
+        .map(|(id, scope)| (id, scope.to_string()))
+        .collect();
+    assert_eq!(known.len(), mapper.len());
+
+    // Re-highlighting an edited document allocates new scopes after the known ones.
+    let edited = "enabled = true\ntitle = \"x\" # comment\n[server]\nport = 80\n";
+    highlight_document(
+        syntax.clone(),
+        &LineIndex::from_text(edited),
+        Some(&mut syntax_set),
+        &mut mapper,
+    )
+    .expect("highlight");
+    assert!(mapper.len() > known.len());
+    for ((id, scope), (known_id, known_scope)) in mapper.iter().zip(&known) {
+        assert_eq!((id, scope), (*known_id, known_scope.as_str()));
+    }
+
+    let again = highlight_document(
+        syntax,
+        &LineIndex::from_text(first_text),
+        Some(&mut syntax_set),
+        &mut mapper,
+    )
+    .expect("highlight");
+    assert_eq!(again.intervals, first.intervals);
+}
+
+#[test]
+fn test_batch_reverse_lookup() {
+    let mut mapper = SublimeScopeMapper::new();
+    assert!(mapper.is_empty());
+    let keyword = mapper.style_id_for_scope("keyword.control");
+    let string = mapper.style_id_for_scope("string.quoted");
+    assert_eq!(mapper.style_id_for_scope("keyword.control"), keyword);
+
+    assert_eq!(
+        mapper.scopes_for_style_ids(&[string, 7, keyword, SublimeScopeMapper::BASE | 99]),
+        vec![Some("string.quoted"), None, Some("keyword.control"), None]
+    );
+    assert_eq!(
+        mapper.iter().collect::<Vec<_>>(),
+        vec![(keyword, "keyword.control"), (string, "string.quoted")]
+    );
+}
+
+#[test]
+fn test_mapper_with_registry_allocates_scope_names() {
+    let registry = StyleRegistry::new();
+    let string = registry.style_id("string");
+    let mut mapper = SublimeScopeMapper::with_registry(registry.clone());
+
+    let comment = mapper.style_id_for_scope("comment.line");
+    assert_ne!(comment, string);
+    assert_eq!(mapper.style_id_for_scope("comment.line"), comment);
+    assert_eq!(
+        registry.name_for_style_id(comment).as_deref(),
+        Some("scope:comment.line")
+    );
+    assert_eq!(mapper.scope_for_style_id(comment), Some("comment.line"));
+    assert_eq!(mapper.scope_for_style_id(string), None);
+    assert_eq!(
+        mapper.iter().collect::<Vec<_>>(),
+        vec![(comment, "comment.line")]
+    );
+
+    // A second mapper on the same registry gets the same id for the same scope.
+    let mut other = SublimeScopeMapper::with_registry(registry);
+    assert_eq!(other.style_id_for_scope("comment.line"), comment);
+}
@@ -0,0 +1,163 @@
+use editor_core::LineIndex;
+use editor_core::intervals::Interval;
+use editor_core_sublime::{
+    SublimeScopeMapper, SublimeSyntaxError, SublimeSyntaxSet, highlight_document,
+};
+
+const RUST: &str = include_str!("fixtures/Rust.sublime-syntax");
+
+/// Highlight `text` and return the scope of each char (`None` for the base scope).
+fn char_scopes(syntax_yaml: &str, text: &str) -> Vec<Option<String>> {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let syntax = syntax_set.load_from_str(syntax_yaml).expect("compile");
+    let line_index = LineIndex::from_text(text);
+    let mut mapper = SublimeScopeMapper::new();
+    let result = highlight_document(syntax, &line_index, Some(&mut syntax_set), &mut mapper)
+        .expect("highlight");
+    scopes_from_intervals(text, &result.intervals, &mapper)
+}
+
+fn scopes_from_intervals(
+    text: &str,
+    intervals: &[Interval],
+    mapper: &SublimeScopeMapper,
+) -> Vec<Option<String>> {
+    (0..text.chars().count())
+        .map(|offset| {
+            intervals
+                .iter()
+                .find(|i| i.start <= offset && offset < i.end)
+                .and_then(|i| mapper.scope_for_style_id(i.style_id))
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// The scope of the first char of `needle` (its `nth` occurrence) in `text`.
+fn scope_of<'a>(
+    text: &str,
+    scopes: &'a [Option<String>],
+    needle: &str,
+    nth: usize,
+) -> Option<&'a str> {
+    let (byte, _) = text.match_indices(needle).nth(nth).expect("needle in text");
+    scopes[text[..byte].chars().count()].as_deref()
+}
+
+#[test]
+fn test_nested_generics_close_with_generic_punctuation() {
+    let text = "let v: Vec<Vec<u8>> = Vec::new();\n";
+    let scopes = char_scopes(RUST, text);
+    let at = |needle: &str, nth: usize| scope_of(text, &scopes, needle, nth);
+
+    assert_eq!(at("Vec", 0), Some("support.type.rust"));
+    assert_eq!(at("Vec", 1), Some("support.type.rust"));
+    assert_eq!(
+        at("<", 0),
+        Some("punctuation.definition.generic.begin.rust")
+    );
+    assert_eq!(
+        at("<", 1),
+        Some("punctuation.definition.generic.begin.rust")
+    );
+    assert_eq!(at("u8", 0), Some("storage.type.rust"));
+    // `>>` closes two argument lists rather than being a shift operator.
+    assert_eq!(at(">", 0), Some("punctuation.definition.generic.end.rust"));
+    assert_eq!(at(">", 1), Some("punctuation.definition.generic.end.rust"));
+    assert_eq!(at("=", 0), Some("keyword.operator.rust"));
+    assert_eq!(at("Vec", 2), Some("variable.other.rust"));
+}
+
+#[test]
+fn test_failed_generic_branch_falls_back_to_comparison() {
+    let text = "let ok = a < b;\nlet shifted = a >> 2;\n";
+    let scopes = char_scopes(RUST, text);
+    let at = |needle: &str, nth: usize| scope_of(text, &scopes, needle, nth);
+
+    assert_eq!(at("a <", 0), Some("variable.other.rust"));
+    assert_eq!(at("<", 0), Some("keyword.operator.comparison.rust"));
+    assert_eq!(at("b;", 0), Some("variable.other.rust"));
+    // Nothing from the abandoned branch is left behind.
+    assert_eq!(at(" b", 0), None);
+    assert_eq!(at(">>", 0), Some("keyword.operator.bitwise.rust"));
+    assert_eq!(at("let", 1), Some("storage.modifier.rust"));
+}
+
+#[test]
+fn test_rewind_crosses_lines() {
+    let text = "let ok = a <\n    b;\nlet v: Option<\n    u8> = None;\n";
+    let scopes = char_scopes(RUST, text);
+    let at = |needle: &str, nth: usize| scope_of(text, &scopes, needle, nth);
+
+    assert_eq!(at("a <", 0), Some("variable.other.rust"));
+    assert_eq!(at("<", 0), Some("keyword.operator.comparison.rust"));
+    assert_eq!(at("b;", 0), Some("variable.other.rust"));
+
+    assert_eq!(at("Option", 0), Some("support.type.rust"));
+    assert_eq!(
+        at("<", 1),
+        Some("punctuation.definition.generic.begin.rust")
+    );
+    assert_eq!(at("    u8", 0), Some("meta.generic.rust"));
+    assert_eq!(at(">", 0), Some("punctuation.definition.generic.end.rust"));
+}
+
+const ALTERNATIVES: &str = r#"
+name: Alternatives
+scope: source.alt
+contexts:
+  main:
+    - match: '(?=\w)'
+      branch_point: word
+      branch: [digits, letters, anything]
+    - match: '!'
+      fail: word
+  digits:
+    - match: '\d+'
+      scope: constant.numeric.alt
+      pop: true
+    - match: '(?=\S)'
+      fail: word
+  letters:
+    - match: '[a-z]+'
+      scope: variable.alt
+      pop: true
+    - match: '(?=\S)'
+      fail: word
+  anything:
+    - match: '\w+'
+      scope: string.alt
+    - match: '(?=\S)'
+      fail: word
+    - match: '$'
+      pop: true
+"#;
+
+#[test]
+fn test_alternatives_are_tried_in_order() {
+    let text = "12 ab X! C";
+    let scopes = char_scopes(ALTERNATIVES, text);
+    let at = |needle: &str| scope_of(text, &scopes, needle, 0);
+
+    assert_eq!(at("12"), Some("constant.numeric.alt"));
+    assert_eq!(at("ab"), Some("variable.alt"));
+    // The last alternative failing has no effect: it stays in place.
+    assert_eq!(at("X"), Some("string.alt"));
+    assert_eq!(at("C"), Some("string.alt"));
+    // After the branch is popped, `fail` no longer refers to it.
+    assert_eq!(char_scopes(ALTERNATIVES, "1!")[1], None);
+}
+
+#[test]
+fn test_branch_requires_branch_point() {
+    let yaml = r#"
+name: Broken
+scope: source.broken
+contexts:
+  main:
+    - match: 'x'
+      branch: [main]
+"#;
+    let err = SublimeSyntaxSet::new().load_from_str(yaml).unwrap_err();
+    assert!(matches!(err, SublimeSyntaxError::MissingField(_)));
+}
@@ -0,0 +1,108 @@
+use editor_core::LineIndex;
+use editor_core_sublime::{
+    SublimeScopeMapper, SublimeSyntax, SublimeSyntaxSet, highlight_document,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const CALC: &str = r#"
+name: Calc
+scope: source.calc
+file_extensions: [calc]
+first_line_match: '^#!.*\bcalc\b'
+contexts:
+  main:
+    - match: '\d+'
+      scope: constant.numeric.calc
+"#;
+
+// Sorts before `Calc.sublime-syntax`, so it is loaded before the syntax it embeds.
+const TEMPLATE: &str = r#"
+name: Template
+scope: text.template
+file_extensions: [tmpl]
+hidden_file_extensions: [Tmplfile]
+contexts:
+  main:
+    - match: '<%'
+      embed: scope:source.calc
+      escape: '%>'
+"#;
+
+const BROKEN: &str = r#"
+name: Broken
+scope: source.broken
+contexts:
+  other: []
+"#;
+
+fn syntax_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "editor-core-sublime-directory-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("nested")).expect("create syntax dir");
+    std::fs::write(dir.join("A-Template.sublime-syntax"), TEMPLATE).expect("write");
+    std::fs::write(dir.join("nested/Calc.sublime-syntax"), CALC).expect("write");
+    std::fs::write(dir.join("Broken.sublime-syntax"), BROKEN).expect("write");
+    std::fs::write(dir.join("README.md"), "not a syntax").expect("write");
+    dir
+}
+
+#[test]
+fn test_load_from_directory_collects_errors_and_finds_syntaxes() {
+    let dir = syntax_dir();
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let report = syntax_set.load_from_directory(&dir).expect("read dir");
+
+    let loaded: Vec<_> = report.loaded.iter().map(|s| s.scope.as_str()).collect();
+    assert_eq!(loaded, ["text.template", "source.calc"]);
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].0.ends_with("Broken.sublime-syntax"));
+    assert_eq!(syntax_set.syntaxes().len(), 2);
+
+    let scope = |s: Option<Arc<SublimeSyntax>>| s.map(|s| s.scope.clone());
+    assert_eq!(
+        scope(syntax_set.find_by_extension("calc")).as_deref(),
+        Some("source.calc")
+    );
+    assert_eq!(
+        scope(syntax_set.find_by_extension(".TMPL")).as_deref(),
+        Some("text.template")
+    );
+    assert_eq!(
+        scope(syntax_set.find_by_extension("tmplfile")).as_deref(),
+        Some("text.template")
+    );
+    assert!(syntax_set.find_by_extension("rs").is_none());
+    assert_eq!(
+        scope(syntax_set.find_by_name("template")).as_deref(),
+        Some("text.template")
+    );
+    assert_eq!(
+        scope(syntax_set.find_by_first_line("#!/usr/bin/env calc\n")).as_deref(),
+        Some("source.calc")
+    );
+    assert!(syntax_set.find_by_first_line("#!/bin/sh").is_none());
+
+    // The template's `embed: scope:source.calc` resolves against the loaded set.
+    let template = syntax_set.find_by_name("Template").expect("template");
+    let text = "<% 42 %>\n";
+    let line_index = LineIndex::from_text(text);
+    let mut mapper = SublimeScopeMapper::new();
+    let result = highlight_document(template, &line_index, Some(&mut syntax_set), &mut mapper)
+        .expect("highlight");
+    assert!(result.intervals.iter().any(|i| {
+        i.start == 3
+            && i.end == 5
+            && mapper.scope_for_style_id(i.style_id) == Some("constant.numeric.calc")
+    }));
+
+    // Reloading replaces syntaxes by scope instead of duplicating them.
+    syntax_set.load_from_directory(&dir).expect("read dir");
+    assert_eq!(syntax_set.syntaxes().len(), 2);
+
+    assert!(syntax_set.load_from_directory(dir.join("missing")).is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
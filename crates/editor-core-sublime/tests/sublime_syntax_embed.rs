@@ -0,0 +1,170 @@
+use editor_core::LineIndex;
+use editor_core_sublime::{SublimeScopeMapper, SublimeSyntaxSet, highlight_document};
+
+const CALC: &str = r#"
+name: Calc
+scope: source.calc
+contexts:
+  main:
+    - match: '\d+'
+      scope: constant.numeric.calc
+    - match: '"'
+      push: string
+  string:
+    - meta_scope: string.quoted.calc
+    - match: '"'
+      pop: true
+"#;
+
+const TEMPLATE: &str = r#"
+name: Template
+scope: text.template
+contexts:
+  main:
+    - match: '<%'
+      scope: punctuation.section.embedded.begin.template
+      embed: scope:source.calc
+      embed_scope: meta.embedded.calc
+      escape: '%>'
+      escape_captures:
+        0: punctuation.section.embedded.end.template
+      with_prototype:
+        - match: '#.*?(?=%>|$)'
+          scope: comment.line.template
+    - match: '\bif\b'
+      scope: keyword.control.template
+"#;
+
+/// The scope of the interval covering `needle` (first occurrence, plus `skip` chars).
+fn scope_at<'a>(
+    text: &str,
+    intervals: &[editor_core::intervals::Interval],
+    mapper: &'a SublimeScopeMapper,
+    needle: &str,
+    skip: usize,
+) -> Option<&'a str> {
+    let byte = text.find(needle).expect("needle in text");
+    let offset = text[..byte].chars().count() + skip;
+    intervals
+        .iter()
+        .find(|i| i.start <= offset && offset < i.end)
+        .and_then(|i| mapper.scope_for_style_id(i.style_id))
+}
+
+#[test]
+fn test_embed_switches_scopes_and_escape_restores_them() {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    syntax_set.load_from_str(CALC).expect("compile calc");
+    let template = syntax_set
+        .load_from_str(TEMPLATE)
+        .expect("compile template");
+
+    let text = "if 1 <% 42 \"if %>\" # if <% 5\nif 7 %> if 8\n";
+    let line_index = LineIndex::from_text(text);
+    let mut mapper = SublimeScopeMapper::new();
+    let result = highlight_document(template, &line_index, Some(&mut syntax_set), &mut mapper)
+        .expect("highlight");
+    let at = |needle: &str, skip: usize| scope_at(text, &result.intervals, &mapper, needle, skip);
+
+    // Outside the embed: template patterns, numbers are plain text.
+    assert_eq!(at("if 1", 0), Some("keyword.control.template"));
+    assert_eq!(at("1 <%", 0), None);
+    assert_eq!(
+        at("<%", 0),
+        Some("punctuation.section.embedded.begin.template")
+    );
+
+    // Inside: the embedded syntax's patterns, under `embed_scope`.
+    assert_eq!(at("42", 0), Some("constant.numeric.calc"));
+    assert_eq!(at(" 42", 0), Some("meta.embedded.calc"));
+    // The escape has precedence over the embedded string context.
+    assert_eq!(at("\"if", 1), Some("string.quoted.calc"));
+    assert_eq!(
+        at("%>\"", 0),
+        Some("punctuation.section.embedded.end.template")
+    );
+
+    // After the escape the template scopes are restored.
+    assert_eq!(at("\" # if", 0), None);
+    assert_eq!(at("# if", 2), Some("keyword.control.template"));
+
+    // An embed spans lines until its escape.
+    let second_line = "if 7 %> if 8";
+    assert_eq!(at(second_line, 0), Some("meta.embedded.calc"));
+    assert_eq!(at(second_line, 3), Some("constant.numeric.calc"));
+    assert_eq!(at("if 8", 0), Some("keyword.control.template"));
+    assert_eq!(at("8\n", 0), None);
+}
+
+#[test]
+fn test_with_prototype_applies_inside_embed() {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    syntax_set.load_from_str(CALC).expect("compile calc");
+    let template = syntax_set
+        .load_from_str(TEMPLATE)
+        .expect("compile template");
+
+    let text = "<% 1 # note 2 %> if\n";
+    let line_index = LineIndex::from_text(text);
+    let mut mapper = SublimeScopeMapper::new();
+    let result = highlight_document(template, &line_index, Some(&mut syntax_set), &mut mapper)
+        .expect("highlight");
+    let at = |needle: &str, skip: usize| scope_at(text, &result.intervals, &mapper, needle, skip);
+
+    assert_eq!(at("1 #", 0), Some("constant.numeric.calc"));
+    // The comment stops at the escape, so the embed still ends there.
+    assert_eq!(at("# note 2", 7), Some("comment.line.template"));
+    assert_eq!(
+        at("%>", 0),
+        Some("punctuation.section.embedded.end.template")
+    );
+    assert_eq!(at(" if", 1), Some("keyword.control.template"));
+}
+
+const MARKDOWN: &str = r#"
+name: Markdown
+scope: text.html.markdown
+contexts:
+  main:
+    - match: '^\s*(`{3,}|~{3,})\s*calc\s*$'
+      scope: meta.code-fence.begin.markdown
+      embed: scope:source.calc
+      embed_scope: markup.raw.code-fence.calc.markdown
+      escape: '^\s*\1\s*$'
+      escape_captures:
+        0: meta.code-fence.end.markdown
+    - match: '\*\*\w+\*\*'
+      scope: markup.bold.markdown
+"#;
+
+#[test]
+fn test_markdown_fence_escape_matches_the_opening_fence() {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    syntax_set.load_from_str(CALC).expect("compile calc");
+    let markdown = syntax_set
+        .load_from_str(MARKDOWN)
+        .expect("compile markdown");
+
+    let text = "**a** 0\n````calc\n1 **b**\n```\n2\n````\n**c** 3\n";
+    let line_index = LineIndex::from_text(text);
+    let mut mapper = SublimeScopeMapper::new();
+    let result = highlight_document(markdown, &line_index, Some(&mut syntax_set), &mut mapper)
+        .expect("highlight");
+    let at = |needle: &str, skip: usize| scope_at(text, &result.intervals, &mapper, needle, skip);
+
+    assert_eq!(at("**a**", 0), Some("markup.bold.markdown"));
+    assert_eq!(at("0\n", 0), None);
+    assert_eq!(at("````calc", 0), Some("meta.code-fence.begin.markdown"));
+
+    // Inside the fence: calc scopes; markdown patterns don't apply.
+    assert_eq!(at("1 **b**", 0), Some("constant.numeric.calc"));
+    assert_eq!(at("**b**", 0), Some("markup.raw.code-fence.calc.markdown"));
+    // A shorter fence doesn't close a longer one.
+    assert_eq!(at("```\n2", 0), Some("markup.raw.code-fence.calc.markdown"));
+    assert_eq!(at("2\n", 0), Some("constant.numeric.calc"));
+
+    // The matching fence pops back to markdown.
+    assert_eq!(at("````\n**c**", 0), Some("meta.code-fence.end.markdown"));
+    assert_eq!(at("**c**", 0), Some("markup.bold.markdown"));
+    assert_eq!(at("3\n", 0), None);
+}
@@ -0,0 +1,104 @@
+use editor_core_sublime::{SublimeSyntaxSet, SyntaxTest};
+use std::path::{Path, PathBuf};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn fixture_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+        .expect("read fixtures")
+        .map(|entry| entry.expect("fixture entry").path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Runs every `syntax_test_*` fixture against the fixture syntax named in its header (by file
+/// name: `Packages/Rust/Rust.sublime-syntax` is `fixtures/Rust.sublime-syntax`).
+#[test]
+fn test_syntax_test_files_pass() {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    for path in fixture_paths() {
+        if path.extension().is_some_and(|ext| ext == "sublime-syntax") {
+            syntax_set.load_from_path(&path).expect("compile fixture");
+        }
+    }
+
+    let mut ran = 0;
+    for path in fixture_paths() {
+        let is_test = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("syntax_test_"));
+        if !is_test {
+            continue;
+        }
+
+        let source = std::fs::read_to_string(&path).expect("read syntax test");
+        let test = SyntaxTest::parse(&source).expect("parse syntax test");
+        let file_name = Path::new(&test.syntax_path)
+            .file_name()
+            .expect("syntax file name");
+        let syntax_path = fixtures_dir().join(file_name);
+        let syntax = syntax_set
+            .load_by_reference(&syntax_path.to_string_lossy())
+            .expect("syntax under test");
+
+        let report = test.run(syntax, Some(&mut syntax_set)).expect("highlight");
+        assert!(report.assertions > 0, "{}: no assertions", path.display());
+        let failures: Vec<String> = report.failures.iter().map(|f| f.to_string()).collect();
+        assert!(
+            report.passed(),
+            "{}:\n{}",
+            path.display(),
+            failures.join("\n")
+        );
+        ran += 1;
+    }
+    assert!(ran >= 2);
+}
+
+const WORDS: &str = r#"
+name: Words
+scope: source.words
+contexts:
+  main:
+    - match: '\d+'
+      scope: constant.numeric.words
+    - match: '\('
+      push: group
+  group:
+    - meta_scope: meta.group.words
+    - match: '\)'
+      pop: true
+"#;
+
+#[test]
+fn test_syntax_test_reports_failing_columns() {
+    let source = "# SYNTAX TEST \"Words.sublime-syntax\"\n\
+                  12 (ab)\n\
+                  # <- constant.numeric.words\n\
+                  # ^^^ meta.group | constant\n\
+                  #    ^ - meta.group\n";
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let syntax = syntax_set.load_from_str(WORDS).expect("compile");
+    let test = SyntaxTest::parse(source).expect("parse");
+    assert_eq!(test.syntax_path, "Words.sublime-syntax");
+
+    let report = test.run(syntax, Some(&mut syntax_set)).expect("highlight");
+    assert_eq!(report.assertions, 3);
+    // Column 2 is the space before the group, and column 5 is inside it.
+    let failed: Vec<(usize, usize)> = report.failures.iter().map(|f| (f.line, f.column)).collect();
+    assert_eq!(failed, vec![(1, 2), (1, 5)]);
+    assert_eq!(report.failures[0].scopes, vec!["source.words".to_string()]);
+    assert_eq!(
+        report.failures[1].to_string(),
+        "2:6: expected `- meta.group`, found `source.words meta.group.words`"
+    );
+}
+
+#[test]
+fn test_syntax_test_requires_header() {
+    assert!(SyntaxTest::parse("let x = 1;\n// <- keyword\n").is_err());
+}
@@ -1,5 +1,7 @@
-use editor_core::LineIndex;
-use editor_core_sublime::{SublimeScopeMapper, SublimeSyntaxSet, highlight_document};
+use editor_core::{LineIndex, PatternError};
+use editor_core_sublime::{
+    SublimeScopeMapper, SublimeSyntaxError, SublimeSyntaxSet, highlight_document,
+};
 
 #[test]
 fn test_sublime_syntax_toml_highlight_and_folding() {
@@ -61,3 +63,25 @@ world
         "expected fold region for multi-line basic string (lines 6..=9)"
     );
 }
+
+#[test]
+fn test_sublime_syntax_reports_too_complex_pattern() {
+    let nested = format!("{}a{}", "(".repeat(5000), ")".repeat(5000));
+    let yaml = format!(
+        "%YAML 1.2\n---\nname: Deep\nscope: source.deep\ncontexts:\n  main:\n    - match: '{}'\n      scope: keyword.deep\n",
+        nested
+    );
+
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let err = syntax_set.load_from_str(&yaml).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            SublimeSyntaxError::PatternTooComplex {
+                error: PatternError::EngineLimit(_),
+                ..
+            }
+        ),
+        "unexpected error: {err}"
+    );
+}
@@ -0,0 +1,65 @@
+use editor_core::LineIndex;
+use editor_core_sublime::{
+    SublimeScopeMapper, SublimeSyntaxError, SublimeSyntaxSet, highlight_document,
+};
+
+fn syntax_with(variables: &str, pattern: &str) -> String {
+    format!(
+        "%YAML 1.2\n---\nname: Vars\nscope: source.vars\nvariables:\n{}contexts:\n  main:\n    - match: '{}'\n      scope: entity.name.vars\n",
+        variables, pattern
+    )
+}
+
+#[test]
+fn test_variables_expand_recursively_in_match_patterns() {
+    let yaml = syntax_with(
+        "  ident_start: '[A-Za-z_]'\n  ident: '{{ident_start}}[A-Za-z0-9_]*'\n",
+        "\\b{{ident}}\\b",
+    );
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let syntax = syntax_set.load_from_str(&yaml).expect("compile");
+
+    let text = "42 foo_1 9";
+    let line_index = LineIndex::from_text(text);
+    let mut mapper = SublimeScopeMapper::new();
+    let result = highlight_document(syntax, &line_index, Some(&mut syntax_set), &mut mapper)
+        .expect("highlight");
+
+    let style = mapper.style_id_for_scope("entity.name.vars");
+    let spans: Vec<(usize, usize)> = result
+        .intervals
+        .iter()
+        .filter(|i| i.style_id == style)
+        .map(|i| (i.start, i.end))
+        .collect();
+    assert_eq!(spans, vec![(3, 8)]);
+}
+
+#[test]
+fn test_undefined_and_circular_variables_are_errors() {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let err = syntax_set
+        .load_from_str(&syntax_with("  a: 'x'\n", "{{missing}}"))
+        .unwrap_err();
+    assert!(
+        matches!(&err, SublimeSyntaxError::UnknownVariable(name) if name == "missing"),
+        "unexpected error: {err}"
+    );
+
+    let err = syntax_set
+        .load_from_str(&syntax_with("  a: '{{b}}'\n  b: 'x{{a}}'\n", "{{a}}"))
+        .unwrap_err();
+    assert!(
+        matches!(err, SublimeSyntaxError::CircularVariableReference(_)),
+        "unexpected error: {err}"
+    );
+
+    // A variable referencing an undefined one fails even if no pattern uses it.
+    let err = syntax_set
+        .load_from_str(&syntax_with("  a: '{{nope}}'\n", "x"))
+        .unwrap_err();
+    assert!(
+        matches!(&err, SublimeSyntaxError::UnknownVariable(name) if name == "nope"),
+        "unexpected error: {err}"
+    );
+}
@@ -0,0 +1,158 @@
+use crate::TreeSitterError;
+use editor_core::{IndentProvider, LineIndex};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor};
+
+/// An [`IndentProvider`] driven by a Tree-sitter indents query (`indents.scm`).
+///
+/// The query marks nodes with two captures:
+///
+/// - `@indent`: lines inside the node are indented one level deeper than the line the node
+///   starts on.
+/// - `@outdent`: a node (typically a closing bracket) that, when it is the first text after the
+///   cursor, moves the new line back one level.
+///
+/// The level of a new line is the number of distinct lines on which `@indent` nodes enclosing the
+/// cursor start (so `foo({` on one line still indents a single level). A node enclosing the
+/// cursor either ends after it or is left unclosed (its last token is missing). Opening brackets
+/// that error recovery left unmatched inside an `ERROR` node count as well, as when typing `{` at
+/// the end of a document. The provider returns one `'\t'` per level; the executor
+/// re-expresses it according to its [`editor_core::TabKeyBehavior`].
+///
+/// The document is parsed on each request, so this suits interactive typing rather than bulk
+/// edits.
+pub struct TreeSitterIndentProvider {
+    parser: Mutex<Parser>,
+    query: Query,
+    indent_capture: Option<u32>,
+    outdent_capture: Option<u32>,
+}
+
+impl TreeSitterIndentProvider {
+    /// Create a provider for `language` from the source of an indents query.
+    pub fn new(language: Language, indents_query: &str) -> Result<Self, TreeSitterError> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&language)
+            .map_err(|e| TreeSitterError::Language(e.to_string()))?;
+        let query = Query::new(&language, indents_query)
+            .map_err(|e| TreeSitterError::Query(e.to_string()))?;
+        let indent_capture = query.capture_index_for_name("indent");
+        if indent_capture.is_none() {
+            return Err(TreeSitterError::Query(
+                "indents query has no @indent capture".to_string(),
+            ));
+        }
+        let outdent_capture = query.capture_index_for_name("outdent");
+        Ok(Self {
+            parser: Mutex::new(parser),
+            query,
+            indent_capture,
+            outdent_capture,
+        })
+    }
+
+    /// Indentation level of a new line inserted at byte offset `cursor` of `text`.
+    ///
+    /// Returns `None` when `text` could not be parsed or `cursor` is inside a character.
+    pub fn indent_level(&self, text: &str, cursor: usize) -> Option<usize> {
+        let cursor = cursor.min(text.len());
+        if !text.is_char_boundary(cursor) {
+            return None;
+        }
+        let tree = self.parser.lock().ok()?.parse(text, None)?;
+
+        // First non-whitespace byte after the cursor on the same line.
+        let rest = &text[cursor..];
+        let rest = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        let next_token = rest
+            .find(|ch: char| ch != ' ' && ch != '\t' && ch != '\r')
+            .map(|offset| cursor + offset);
+
+        let mut query_cursor = QueryCursor::new();
+        let range_end = next_token.unwrap_or(cursor).max(cursor) + 1;
+        query_cursor.set_byte_range(cursor.saturating_sub(1)..range_end.min(text.len()));
+
+        let mut indent_rows = BTreeSet::new();
+        let mut outdent = false;
+        let mut captures = query_cursor.captures(&self.query, tree.root_node(), text.as_bytes());
+        while let Some((m, index)) = captures.next() {
+            let capture = m.captures[*index];
+            let node = capture.node;
+            if Some(capture.index) == self.indent_capture {
+                if node.start_byte() < cursor
+                    && (node.end_byte() > cursor
+                        || (node.end_byte() == cursor && ends_unclosed(node)))
+                {
+                    indent_rows.insert(node.start_position().row);
+                }
+            } else if Some(capture.index) == self.outdent_capture
+                && Some(node.start_byte()) == next_token
+                && !node.is_missing()
+            {
+                outdent = true;
+            }
+        }
+
+        // Error recovery leaves an unclosed `{` as a bare token inside an ERROR node rather than
+        // a node missing its closer.
+        let mut node = tree
+            .root_node()
+            .descendant_for_byte_range(cursor.saturating_sub(1), cursor);
+        while let Some(current) = node {
+            if current.is_error() && current.start_byte() < cursor {
+                indent_rows.extend(unclosed_bracket_rows(current, cursor));
+            }
+            node = current.parent();
+        }
+
+        Some(indent_rows.len().saturating_sub(usize::from(outdent)))
+    }
+}
+
+impl IndentProvider for TreeSitterIndentProvider {
+    fn indent_for_new_line(&self, line_index: &LineIndex, cursor_offset: usize) -> Option<String> {
+        let text = line_index.get_text();
+        let cursor = line_index.char_offset_to_byte_offset(cursor_offset);
+        self.indent_level(&text, cursor)
+            .map(|level| "\t".repeat(level))
+    }
+}
+
+/// Whether the last token of `node` was inserted by error recovery.
+fn ends_unclosed(node: Node) -> bool {
+    let mut node = node;
+    while let Some(last) = node
+        .child_count()
+        .checked_sub(1)
+        .and_then(|i| node.child(i as u32))
+    {
+        if last.is_missing() {
+            return true;
+        }
+        node = last;
+    }
+    false
+}
+
+/// Rows of the opening brackets among the children of `error` before `cursor` that are not
+/// closed before it.
+fn unclosed_bracket_rows(error: Node, cursor: usize) -> Vec<usize> {
+    let mut open = Vec::new();
+    let mut walker = error.walk();
+    for child in error.children(&mut walker) {
+        if child.start_byte() >= cursor {
+            break;
+        }
+        match child.kind() {
+            "{" | "(" | "[" => open.push(child.start_position().row),
+            "}" | ")" | "]" => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    open
+}
@@ -5,10 +5,22 @@
 //!
 //! - highlight intervals (a style layer)
 //! - fold regions (derived folding)
+//! - highlights for embedded languages (injections)
+//! - a document outline (symbols from a tags query)
+//! - scope-aware highlighting of local variables (locals query)
+//!
+//! [`TreeSitterIndentProvider`] computes the indentation of new lines from an indents query, for
+//! `CommandExecutor::set_indent_provider`.
 //!
 //! Output is expressed as `editor_core::ProcessingEdit` values, so it composes with other derived
 //! state producers like LSP or `.sublime-syntax`.
 
+mod indent;
 mod processor;
 
-pub use processor::{TreeSitterProcessor, TreeSitterProcessorConfig, TreeSitterUpdateMode};
+pub use indent::TreeSitterIndentProvider;
+pub use processor::{
+    TreeSitterError, TreeSitterInjection, TreeSitterInjectionLanguage, TreeSitterProcessor,
+    TreeSitterProcessorConfig, TreeSitterUpdateMode, input_edit_for_delta_edit,
+    symbol_kind_for_tag,
+};
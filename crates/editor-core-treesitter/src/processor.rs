@@ -1,10 +1,15 @@
-use editor_core::delta::TextDelta;
+use editor_core::delta::{TextDelta, TextDeltaEdit};
 use editor_core::intervals::{FoldRegion, Interval, StyleId, StyleLayerId};
 use editor_core::processing::{DocumentProcessor, ProcessingEdit};
-use editor_core::{EditorStateManager, LineIndex};
-use std::collections::BTreeMap;
+use editor_core::{
+    DocumentOutline, DocumentSymbol, EditorStateManager, LineIndex, SymbolKind, SymbolRange,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use streaming_iterator::StreamingIterator;
-use tree_sitter::{InputEdit, Parser, Point, Query, QueryCursor, Tree};
+use tree_sitter::{InputEdit, Node, Parser, Point, Query, QueryCursor, Tree};
 
 /// Errors produced by [`TreeSitterProcessor`].
 #[derive(Debug)]
@@ -52,11 +57,42 @@ pub struct TreeSitterProcessorConfig {
     /// Optional folding query (`.scm`). Each capture becomes a fold candidate.
     pub folds_query: Option<String>,
     /// Mapping from capture name (e.g. `"comment"`) to an `editor-core` `StyleId`.
+    ///
+    /// Dotted names fall back to their parents: `@keyword.control.rust` uses the style of
+    /// `keyword.control`, then `keyword`, when it has no entry of its own.
     pub capture_styles: BTreeMap<String, StyleId>,
+    /// Style for captures with no mapping (not even through a parent name).
+    ///
+    /// Captures starting with `_` are never styled by default; by convention they only serve
+    /// predicates. `None` (the default) leaves unmapped captures unstyled.
+    pub default_capture_style: Option<StyleId>,
     /// Target style layer id to replace.
     pub style_layer: StyleLayerId,
     /// Whether to preserve the collapsed state for existing fold regions on replacement.
     pub preserve_collapsed_folds: bool,
+    /// Optional injections query (`.scm`) marking regions written in another language.
+    ///
+    /// Uses the usual Tree-sitter conventions: `@injection.content` captures the embedded region
+    /// and the language name comes from an `@injection.language` capture (its text) or a
+    /// `(#set! injection.language "name")` property.
+    pub injections_query: Option<String>,
+    /// Grammars available to injections, keyed by lowercase language name.
+    pub injection_languages: BTreeMap<String, TreeSitterInjectionLanguage>,
+    /// Optional tags query (`tags.scm`) used to build a document outline.
+    ///
+    /// Uses the Tree-sitter tags conventions: a `@definition.<kind>` capture spans a symbol and
+    /// the `@name` capture of the same match names it (`@reference.*` captures are ignored).
+    /// See [`symbol_kind_for_tag`] for how kinds map to [`SymbolKind`].
+    pub tags_query: Option<String>,
+    /// Whether `process()` emits the outline as [`ProcessingEdit::ReplaceDocumentSymbols`].
+    pub emit_outline: bool,
+    /// Optional locals query (`locals.scm`) resolving references to their definitions.
+    ///
+    /// Uses the Tree-sitter locals conventions: `@local.scope` nodes nest scopes,
+    /// `@local.definition` (or `@local.definition.<kind>`) captures name a definition in the
+    /// innermost scope, and a `@local.reference` whose text names a definition made earlier in an
+    /// enclosing scope takes the style the highlights query gave that definition.
+    pub locals_query: Option<String>,
 }
 
 impl TreeSitterProcessorConfig {
@@ -65,14 +101,21 @@ impl TreeSitterProcessorConfig {
     /// By default:
     /// - `style_layer` is [`StyleLayerId::TREE_SITTER`]
     /// - `preserve_collapsed_folds` is `true`
+    /// - `emit_outline` is `true` (only relevant with a tags query)
     pub fn new(language: tree_sitter::Language, highlights_query: impl Into<String>) -> Self {
         Self {
             language,
             highlights_query: highlights_query.into(),
             folds_query: None,
             capture_styles: BTreeMap::new(),
+            default_capture_style: None,
             style_layer: StyleLayerId::TREE_SITTER,
             preserve_collapsed_folds: true,
+            injections_query: None,
+            injection_languages: BTreeMap::new(),
+            tags_query: None,
+            emit_outline: true,
+            locals_query: None,
         }
     }
 
@@ -107,27 +150,471 @@ impl TreeSitterProcessorConfig {
         self
     }
 
+    /// Add capture name → style id mappings from any map or iterator (e.g. a `HashMap` built
+    /// from a theme).
+    pub fn with_capture_styles<I, S>(mut self, styles: I) -> Self
+    where
+        I: IntoIterator<Item = (S, StyleId)>,
+        S: Into<String>,
+    {
+        self.capture_styles.extend(
+            styles
+                .into_iter()
+                .map(|(name, style_id)| (name.into(), style_id)),
+        );
+        self
+    }
+
+    /// Style unmapped captures with `style_id` (see
+    /// [`TreeSitterProcessorConfig::default_capture_style`]).
+    pub fn with_default_capture_style(mut self, style_id: StyleId) -> Self {
+        self.default_capture_style = Some(style_id);
+        self
+    }
+
+    /// The style a capture named `name` resolves to: its own mapping, the closest dotted parent's
+    /// mapping, or the default style.
+    pub fn capture_style(&self, name: &str) -> Option<StyleId> {
+        resolve_capture_style(&self.capture_styles, self.default_capture_style, name)
+    }
+
     /// Control whether fold replacement preserves collapsed state.
     pub fn set_preserve_collapsed_folds(&mut self, preserve: bool) {
         self.preserve_collapsed_folds = preserve;
     }
+
+    /// Set an injections query (see [`TreeSitterProcessorConfig::injections_query`]).
+    pub fn with_injections_query(mut self, injections_query: impl Into<String>) -> Self {
+        self.injections_query = Some(injections_query.into());
+        self
+    }
+
+    /// Register the grammar used for regions injected as `name` (matched case-insensitively).
+    pub fn with_injection_language(
+        mut self,
+        name: impl Into<String>,
+        language: TreeSitterInjectionLanguage,
+    ) -> Self {
+        self.injection_languages
+            .insert(name.into().to_lowercase(), language);
+        self
+    }
+
+    /// Set a tags query (see [`TreeSitterProcessorConfig::tags_query`]).
+    pub fn with_tags_query(mut self, tags_query: impl Into<String>) -> Self {
+        self.tags_query = Some(tags_query.into());
+        self
+    }
+
+    /// Set a locals query (see [`TreeSitterProcessorConfig::locals_query`]).
+    pub fn with_locals_query(mut self, locals_query: impl Into<String>) -> Self {
+        self.locals_query = Some(locals_query.into());
+        self
+    }
+
+    /// Control whether `process()` emits the outline (it is kept either way, see
+    /// [`TreeSitterProcessor::outline`]).
+    pub fn set_emit_outline(&mut self, emit: bool) {
+        self.emit_outline = emit;
+    }
+}
+
+/// The [`SymbolKind`] for a tags `@definition.<kind>` capture.
+///
+/// `kind` is the part after `definition.`; dotted suffixes are ignored (`function.async` is a
+/// function). Besides the [`SymbolKind`] names, `trait` maps to `Interface`, `impl` /
+/// `implementation` to `Object`, `macro` to `Function`, `type` to `TypeParameter` and `variant`
+/// to `EnumMember`. Anything else is a `Variable`.
+pub fn symbol_kind_for_tag(kind: &str) -> SymbolKind {
+    match kind.split('.').next().unwrap_or(kind) {
+        "module" => SymbolKind::Module,
+        "namespace" => SymbolKind::Namespace,
+        "package" => SymbolKind::Package,
+        "class" => SymbolKind::Class,
+        "struct" => SymbolKind::Struct,
+        "method" => SymbolKind::Method,
+        "function" | "macro" => SymbolKind::Function,
+        "constructor" => SymbolKind::Constructor,
+        "property" => SymbolKind::Property,
+        "field" => SymbolKind::Field,
+        "enum" => SymbolKind::Enum,
+        "enum_member" | "variant" => SymbolKind::EnumMember,
+        "interface" | "trait" => SymbolKind::Interface,
+        "impl" | "implementation" => SymbolKind::Object,
+        "constant" => SymbolKind::Constant,
+        "type" => SymbolKind::TypeParameter,
+        _ => SymbolKind::Variable,
+    }
+}
+
+/// A grammar used to highlight regions embedded in the host document (e.g. a fenced code block
+/// in Markdown).
+///
+/// Injected highlights are written to the host's style layer. Capture names resolve like the
+/// host's (dotted parents, then the host's default style). Injected grammars do not run
+/// injections of their own.
+#[derive(Debug, Clone)]
+pub struct TreeSitterInjectionLanguage {
+    /// Tree-sitter language.
+    pub language: tree_sitter::Language,
+    /// Syntax highlighting query (`.scm`).
+    pub highlights_query: String,
+    /// Mapping from capture name to an `editor-core` `StyleId`.
+    pub capture_styles: BTreeMap<String, StyleId>,
+}
+
+impl TreeSitterInjectionLanguage {
+    /// Create an injection grammar from a language + highlights query.
+    pub fn new(language: tree_sitter::Language, highlights_query: impl Into<String>) -> Self {
+        Self {
+            language,
+            highlights_query: highlights_query.into(),
+            capture_styles: BTreeMap::new(),
+        }
+    }
+
+    /// Add a set of capture name → style id mappings.
+    pub fn with_simple_capture_styles<const N: usize>(
+        mut self,
+        styles: [(&'static str, StyleId); N],
+    ) -> Self {
+        for (name, style_id) in styles {
+            self.capture_styles.insert(name.to_string(), style_id);
+        }
+        self
+    }
+}
+
+/// A region of the document highlighted by an injected grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeSitterInjection {
+    /// Lowercase language name.
+    pub language: String,
+    /// Character range in the host document.
+    pub range: Range<usize>,
+}
+
+/// Convert one [`TextDeltaEdit`] into a Tree-sitter [`InputEdit`].
+///
+/// `line_index` must describe the document *before* the edit (`edit.start` is a character
+/// offset into it). Tree-sitter positions are byte based: `column` counts UTF-8 bytes from the
+/// start of the row.
+pub fn input_edit_for_delta_edit(line_index: &LineIndex, edit: &TextDeltaEdit) -> InputEdit {
+    let start_byte = line_index.char_offset_to_byte_offset(edit.start);
+    let (row, column) = line_index.char_offset_to_line_byte_column(edit.start);
+    let start_position = Point { row, column };
+    InputEdit {
+        start_byte,
+        old_end_byte: start_byte.saturating_add(edit.deleted_text.len()),
+        new_end_byte: start_byte.saturating_add(edit.inserted_text.len()),
+        start_position,
+        old_end_position: advance_point(start_position, &edit.deleted_text),
+        new_end_position: advance_point(start_position, &edit.inserted_text),
+    }
+}
+
+fn advance_point(mut point: Point, text: &str) -> Point {
+    let mut parts = text.split('\n');
+    let Some(first) = parts.next() else {
+        return point;
+    };
+
+    point.column = point.column.saturating_add(first.len());
+    for part in parts {
+        point.row = point.row.saturating_add(1);
+        point.column = part.len();
+    }
+
+    point
+}
+
+fn resolve_capture_style(
+    capture_styles: &BTreeMap<String, StyleId>,
+    default_style: Option<StyleId>,
+    name: &str,
+) -> Option<StyleId> {
+    let mut key = name;
+    loop {
+        if let Some(style_id) = capture_styles.get(key) {
+            return Some(*style_id);
+        }
+        match key.rfind('.') {
+            Some(dot) => key = &key[..dot],
+            None => break,
+        }
+    }
+    if name.starts_with('_') {
+        None
+    } else {
+        default_style
+    }
+}
+
+/// Compiled form of a [`TreeSitterInjectionLanguage`].
+struct InjectionGrammar {
+    parser: Parser,
+    highlight_query: Query,
+    highlight_capture_styles: Vec<Option<StyleId>>,
+}
+
+/// One edit applied since the last update, used to move cached results to the new document.
+#[derive(Debug, Clone, Copy)]
+struct EditShift {
+    /// Character offset of the edit.
+    start: usize,
+    /// End of the deleted text (characters, before the edit).
+    old_end: usize,
+    /// End of the inserted text (characters, after the edit).
+    new_end: usize,
+    start_line: usize,
+    old_end_line: usize,
+    new_end_line: usize,
+}
+
+impl EditShift {
+    /// Where character `offset` (before the edit) ends up; offsets inside the deleted text
+    /// collapse to the end of the inserted text.
+    fn map(&self, offset: usize) -> usize {
+        if offset <= self.start {
+            offset
+        } else if offset >= self.old_end {
+            offset - self.old_end + self.new_end
+        } else {
+            self.new_end
+        }
+    }
+
+    /// Move `intervals` past the edit, dropping the ones overlapping the deleted text.
+    fn shift_intervals(&self, intervals: &mut Vec<Interval>) {
+        intervals.retain_mut(|interval| {
+            if interval.end <= self.start {
+                true
+            } else if interval.start >= self.old_end {
+                interval.start = interval.start - self.old_end + self.new_end;
+                interval.end = interval.end - self.old_end + self.new_end;
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    /// Move `regions` past the edited lines, dropping the ones overlapping them.
+    fn shift_folds(&self, regions: &mut Vec<FoldRegion>) {
+        regions.retain_mut(|region| {
+            if region.end_line < self.start_line {
+                true
+            } else if region.start_line > self.old_end_line {
+                region.start_line = region.start_line - self.old_end_line + self.new_end_line;
+                region.end_line = region.end_line - self.old_end_line + self.new_end_line;
+                true
+            } else {
+                false
+            }
+        });
+    }
+}
+
+/// A `@local.scope` being walked by [`TreeSitterProcessor::resolve_locals`].
+struct LocalScope<'a> {
+    end_byte: usize,
+    definitions: Vec<(&'a str, Option<StyleId>)>,
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Highlights of one injected region from a previous update, reused while its text is unchanged.
+struct CachedInjection {
+    language: String,
+    text: String,
+    /// Intervals relative to the start of the region (in characters).
+    intervals: Vec<Interval>,
+}
+
+fn compile_highlights(
+    language: &tree_sitter::Language,
+    highlights_query: &str,
+    capture_styles: &BTreeMap<String, StyleId>,
+    default_style: Option<StyleId>,
+) -> Result<(Query, Vec<Option<StyleId>>), TreeSitterError> {
+    let query = Query::new(language, highlights_query)
+        .map_err(|e| TreeSitterError::Query(e.to_string()))?;
+    let styles = query
+        .capture_names()
+        .iter()
+        .map(|name| resolve_capture_style(capture_styles, default_style, name))
+        .collect::<Vec<_>>();
+    Ok((query, styles))
+}
+
+/// Highlight intervals (unsorted) for the captures of `query` under `root`, limited to nodes
+/// intersecting `byte_range` when given.
+fn highlight_intervals(
+    query: &Query,
+    capture_styles: &[Option<StyleId>],
+    root: Node<'_>,
+    byte_range: Option<Range<usize>>,
+    text: &str,
+    line_index: &LineIndex,
+    out: &mut Vec<Interval>,
+) {
+    let mut cursor = QueryCursor::new();
+    if let Some(byte_range) = byte_range {
+        cursor.set_byte_range(byte_range);
+    }
+    let mut matches = cursor.matches(query, root, text.as_bytes());
+    while let Some(m) = matches.next() {
+        for capture in m.captures {
+            let idx = capture.index as usize;
+            let Some(style_id) = capture_styles.get(idx).and_then(|x| *x) else {
+                continue;
+            };
+
+            let node = capture.node;
+            let start_byte = node.start_byte();
+            let end_byte = node.end_byte();
+            if end_byte <= start_byte {
+                continue;
+            }
+
+            let start = line_index.byte_offset_to_char_offset(start_byte);
+            let end = line_index.byte_offset_to_char_offset(end_byte);
+            if end <= start {
+                continue;
+            }
+
+            out.push(Interval::new(start, end, style_id));
+        }
+    }
+}
+
+/// Push the parts of `interval` outside `ranges` (sorted, non-overlapping) to `out`.
+fn push_outside_ranges(interval: &Interval, ranges: &[Range<usize>], out: &mut Vec<Interval>) {
+    let mut start = interval.start;
+    for range in ranges {
+        if range.end <= start {
+            continue;
+        }
+        if range.start >= interval.end {
+            break;
+        }
+        if range.start > start {
+            out.push(Interval::new(start, range.start, interval.style_id));
+        }
+        start = start.max(range.end);
+    }
+    if start < interval.end {
+        out.push(Interval::new(start, interval.end, interval.style_id));
+    }
+}
+
+/// Nest `symbols` (sorted by start, then longest first) under the symbols containing them.
+fn nest_symbols(symbols: Vec<DocumentSymbol>) -> Vec<DocumentSymbol> {
+    let mut roots = Vec::new();
+    // Open ancestors of the next symbol, outermost first.
+    let mut open: Vec<DocumentSymbol> = Vec::new();
+    for symbol in symbols {
+        while let Some(parent) = open.last()
+            && symbol.range.end > parent.range.end
+        {
+            let done = open.pop().expect("checked");
+            match open.last_mut() {
+                Some(grandparent) => grandparent.children.push(done),
+                None => roots.push(done),
+            }
+        }
+        open.push(symbol);
+    }
+    while let Some(done) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => parent.children.push(done),
+            None => roots.push(done),
+        }
+    }
+    roots
 }
 
 /// An incremental Tree-sitter based document processor.
 ///
-/// This processor tracks a parse tree and updates it based on `TextDelta` edits when available.
-/// It then produces highlighting and folding edits in `editor-core`'s derived-state format.
+/// This processor tracks a parse tree and updates it based on `TextDelta` edits when available:
+/// each edit is applied to the old tree with [`Tree::edit`] before re-parsing, so Tree-sitter
+/// reuses the unchanged subtrees. It then produces highlighting and folding edits in
+/// `editor-core`'s derived-state format.
+///
+/// After an incremental parse, highlights and folds are only queried again around the edits
+/// (the top-level nodes and lines they touch, plus Tree-sitter's changed ranges); the rest of
+/// the previous result is moved past the edits. When the deltas do not account for every text
+/// version since the last update (an edit was missed, see
+/// [`EditorStateManager::text_version`]), the processor falls back to a full parse.
+///
+/// Deltas come from [`EditorStateManager::last_text_delta`], which only holds the latest edit.
+/// Hosts that may run several edits between two `process()` calls can forward every delta
+/// (e.g. from a [`EditorStateManager::subscribe`] callback) with
+/// [`push_text_delta`](Self::push_text_delta) to stay incremental.
+///
+/// # Injections
+///
+/// With an injections query, each injected region whose language is registered is parsed on its
+/// own (with [`Parser::set_included_ranges`], so node offsets are already host offsets) and
+/// highlighted with that language's query. The injected grammar owns its region: host intervals
+/// are clipped to the parts outside it, and the injected intervals fill it. A region whose
+/// language is not registered keeps the host highlighting. A region whose language and text are
+/// the same as in the previous update reuses its highlights (moved to its new offset); only
+/// changed regions are re-parsed.
+///
+/// # Outline
+///
+/// With a tags query, every update rebuilds [`outline`](TreeSitterProcessor::outline) from the
+/// (incrementally re-parsed) tree: definitions become [`DocumentSymbol`]s with character-offset
+/// ranges, nested by containment. The outline is emitted as
+/// [`ProcessingEdit::ReplaceDocumentSymbols`] after a full parse and whenever it changed.
+///
+/// # Locals
+///
+/// With a locals query, resolved references replace their host highlights with their
+/// definition's style. Scopes are resolved per top-level node of the tree (definitions do not
+/// reach other top-level nodes), and a node whose text is unchanged since the previous update
+/// reuses its resolved references, so an edit only rebuilds the nodes it touches.
 pub struct TreeSitterProcessor {
     config: TreeSitterProcessorConfig,
     parser: Parser,
     highlight_query: Query,
     highlight_capture_styles: Vec<Option<StyleId>>,
     fold_query: Option<Query>,
+    injection_query: Option<Query>,
+    injection_grammars: BTreeMap<String, InjectionGrammar>,
+    last_injections: Vec<TreeSitterInjection>,
+    injection_cache: Vec<CachedInjection>,
+    last_reparsed_injections: Vec<Range<usize>>,
+    tags_query: Option<Query>,
+    outline: DocumentOutline,
+    locals_query: Option<Query>,
+    /// Resolved references of each top-level node, keyed by the hash of its text, relative to
+    /// the start of the node (in characters).
+    locals_cache: HashMap<u64, Vec<Interval>>,
+    last_rebuilt_locals: Vec<Range<usize>>,
     tree: Option<Tree>,
     text: String,
     line_index: LineIndex,
     last_processed_version: Option<u64>,
+    /// The text version `text` mirrors.
+    last_text_version: Option<u64>,
     last_update_mode: TreeSitterUpdateMode,
+    /// Deltas queued by [`TreeSitterProcessor::push_text_delta`], applied by the next `process()`.
+    pending_deltas: Vec<TextDelta>,
+    /// The last delta applied, so a stale `last_text_delta()` is not applied twice.
+    last_applied_delta: Option<TextDelta>,
+    last_changed_ranges: Vec<Range<usize>>,
+    /// Edits applied since the last update, for moving `host_intervals` and `fold_regions`.
+    pending_shifts: Vec<EditShift>,
+    /// Host highlights of the last update (before locals and injections), sorted.
+    host_intervals: Vec<Interval>,
+    /// Fold regions of the last update, sorted.
+    fold_regions: Vec<FoldRegion>,
 }
 
 impl TreeSitterProcessor {
@@ -138,13 +625,12 @@ impl TreeSitterProcessor {
             .set_language(&config.language)
             .map_err(|e| TreeSitterError::Language(e.to_string()))?;
 
-        let highlight_query = Query::new(&config.language, &config.highlights_query)
-            .map_err(|e| TreeSitterError::Query(e.to_string()))?;
-        let highlight_capture_styles = highlight_query
-            .capture_names()
-            .iter()
-            .map(|name| config.capture_styles.get(*name).copied())
-            .collect::<Vec<_>>();
+        let (highlight_query, highlight_capture_styles) = compile_highlights(
+            &config.language,
+            &config.highlights_query,
+            &config.capture_styles,
+            config.default_capture_style,
+        )?;
 
         let fold_query = match config.folds_query.as_deref() {
             Some(q) if !q.trim().is_empty() => Some(
@@ -154,17 +640,86 @@ impl TreeSitterProcessor {
             _ => None,
         };
 
+        let injection_query = match config.injections_query.as_deref() {
+            Some(q) if !q.trim().is_empty() => {
+                let query = Query::new(&config.language, q)
+                    .map_err(|e| TreeSitterError::Query(e.to_string()))?;
+                if query.capture_index_for_name("injection.content").is_none() {
+                    return Err(TreeSitterError::Query(
+                        "injections query has no @injection.content capture".to_string(),
+                    ));
+                }
+                Some(query)
+            }
+            _ => None,
+        };
+
+        let tags_query = match config.tags_query.as_deref() {
+            Some(q) if !q.trim().is_empty() => Some(
+                Query::new(&config.language, q)
+                    .map_err(|e| TreeSitterError::Query(e.to_string()))?,
+            ),
+            _ => None,
+        };
+
+        let locals_query = match config.locals_query.as_deref() {
+            Some(q) if !q.trim().is_empty() => Some(
+                Query::new(&config.language, q)
+                    .map_err(|e| TreeSitterError::Query(e.to_string()))?,
+            ),
+            _ => None,
+        };
+
+        let mut injection_grammars = BTreeMap::new();
+        for (name, injection) in &config.injection_languages {
+            let mut parser = Parser::new();
+            parser
+                .set_language(&injection.language)
+                .map_err(|e| TreeSitterError::Language(format!("{name}: {e}")))?;
+            let (highlight_query, highlight_capture_styles) = compile_highlights(
+                &injection.language,
+                &injection.highlights_query,
+                &injection.capture_styles,
+                config.default_capture_style,
+            )?;
+            injection_grammars.insert(
+                name.to_lowercase(),
+                InjectionGrammar {
+                    parser,
+                    highlight_query,
+                    highlight_capture_styles,
+                },
+            );
+        }
+
         Ok(Self {
             config,
             parser,
             highlight_query,
             highlight_capture_styles,
             fold_query,
+            injection_query,
+            injection_grammars,
+            last_injections: Vec::new(),
+            injection_cache: Vec::new(),
+            last_reparsed_injections: Vec::new(),
+            tags_query,
+            outline: DocumentOutline::default(),
+            locals_query,
+            locals_cache: HashMap::new(),
+            last_rebuilt_locals: Vec::new(),
             tree: None,
             text: String::new(),
             line_index: LineIndex::new(),
             last_processed_version: None,
+            last_text_version: None,
             last_update_mode: TreeSitterUpdateMode::FullReparse,
+            pending_deltas: Vec::new(),
+            last_applied_delta: None,
+            last_changed_ranges: Vec::new(),
+            pending_shifts: Vec::new(),
+            host_intervals: Vec::new(),
+            fold_regions: Vec::new(),
         })
     }
 
@@ -173,29 +728,53 @@ impl TreeSitterProcessor {
         self.last_update_mode
     }
 
-    fn sync_from_state_full(&mut self, state: &EditorStateManager) {
-        self.text = state.editor().get_text();
-        self.line_index = LineIndex::from_text(&self.text);
+    /// Character ranges (in the current document) whose syntax changed in the last `process()`.
+    ///
+    /// For an incremental update these are Tree-sitter's [`Tree::changed_ranges`] between the
+    /// edited old tree and the new one; a full parse reports the whole document and a skipped
+    /// update reports nothing.
+    pub fn last_changed_ranges(&self) -> &[Range<usize>] {
+        &self.last_changed_ranges
     }
 
-    fn point_for_char_offset(&self, char_offset: usize) -> Point {
-        let (row, col) = self.line_index.char_offset_to_line_byte_column(char_offset);
-        Point { row, column: col }
+    /// Regions highlighted by an injected grammar in the last update, in document order.
+    pub fn last_injections(&self) -> &[TreeSitterInjection] {
+        &self.last_injections
     }
 
-    fn advance_point(mut point: Point, text: &str) -> Point {
-        let mut parts = text.split('\n');
-        let Some(first) = parts.next() else {
-            return point;
-        };
+    /// The document outline built from the tags query as of the last update (empty without one).
+    pub fn outline(&self) -> &DocumentOutline {
+        &self.outline
+    }
 
-        point.column = point.column.saturating_add(first.len());
-        for part in parts {
-            point.row = point.row.saturating_add(1);
-            point.column = part.len();
-        }
+    /// Top-level nodes (character ranges) whose locals were resolved again in the last update.
+    ///
+    /// The other top-level nodes reused their references from the previous update.
+    pub fn last_rebuilt_locals(&self) -> &[Range<usize>] {
+        &self.last_rebuilt_locals
+    }
+
+    /// Injected regions (character ranges) that were re-parsed in the last update.
+    ///
+    /// The other regions of [`last_injections`](Self::last_injections) reused the highlights
+    /// of an unchanged region from the previous update.
+    pub fn last_reparsed_injections(&self) -> &[Range<usize>] {
+        &self.last_reparsed_injections
+    }
 
-        point
+    /// Queue a text delta for the next `process()` call.
+    ///
+    /// Queued deltas must be pushed in order, starting from the document the processor last saw;
+    /// when any are queued, `process()` applies them instead of
+    /// [`EditorStateManager::last_text_delta`].
+    pub fn push_text_delta(&mut self, delta: TextDelta) {
+        self.pending_deltas.push(delta);
+    }
+
+    fn sync_from_state_full(&mut self, state: &EditorStateManager) {
+        self.text = state.editor().get_text();
+        self.line_index = LineIndex::from_text(&self.text);
+        self.pending_shifts.clear();
     }
 
     fn apply_text_delta_incremental(&mut self, delta: &TextDelta) -> Result<(), TreeSitterError> {
@@ -207,39 +786,40 @@ impl TreeSitterProcessor {
         }
 
         for edit in &delta.edits {
-            let start_char = edit.start;
-            let deleted_chars = edit.deleted_text.chars().count();
-
-            let start_byte = self.line_index.char_offset_to_byte_offset(start_char);
-            let old_end_byte = start_byte.saturating_add(edit.deleted_text.len());
-            let new_end_byte = start_byte.saturating_add(edit.inserted_text.len());
+            let input_edit = input_edit_for_delta_edit(&self.line_index, edit);
+            let bytes = input_edit.start_byte..input_edit.old_end_byte;
 
-            let Some(old_slice) = self.text.get(start_byte..old_end_byte) else {
+            let Some(old_slice) = self.text.get(bytes.clone()) else {
                 return Err(TreeSitterError::DeltaMismatch);
             };
             if old_slice != edit.deleted_text {
                 return Err(TreeSitterError::DeltaMismatch);
             }
 
-            let start_position = self.point_for_char_offset(start_char);
-            let old_end_position = Self::advance_point(start_position, &edit.deleted_text);
-            let new_end_position = Self::advance_point(start_position, &edit.inserted_text);
-
             if let Some(tree) = self.tree.as_mut() {
-                tree.edit(&InputEdit {
-                    start_byte,
-                    old_end_byte,
-                    new_end_byte,
-                    start_position,
-                    old_end_position,
-                    new_end_position,
-                });
+                tree.edit(&input_edit);
             }
 
-            self.text
-                .replace_range(start_byte..old_end_byte, &edit.inserted_text);
-            self.line_index.delete(start_char, deleted_chars);
-            self.line_index.insert(start_char, &edit.inserted_text);
+            let line_of = |line_index: &LineIndex, offset| {
+                line_index.char_offset_to_line_byte_column(offset).0
+            };
+            let old_end = edit.start + edit.deleted_len();
+            let start_line = line_of(&self.line_index, edit.start);
+            let old_end_line = line_of(&self.line_index, old_end);
+
+            self.text.replace_range(bytes, &edit.inserted_text);
+            self.line_index.delete(edit.start, edit.deleted_len());
+            self.line_index.insert(edit.start, &edit.inserted_text);
+
+            let new_end = edit.start + edit.inserted_text.chars().count();
+            self.pending_shifts.push(EditShift {
+                start: edit.start,
+                old_end,
+                new_end,
+                start_line,
+                old_end_line,
+                new_end_line: line_of(&self.line_index, new_end),
+            });
         }
 
         if self.line_index.char_count() != delta.after_char_count {
@@ -249,51 +829,529 @@ impl TreeSitterProcessor {
         Ok(())
     }
 
+    /// Apply `deltas` in order; on success the edited tree is ready for an incremental parse.
+    fn apply_text_deltas(
+        &mut self,
+        deltas: &[TextDelta],
+        state: &EditorStateManager,
+    ) -> Result<(), TreeSitterError> {
+        // Every text version carries at most one delta, so fewer deltas than versions since the
+        // last update means an edit was missed.
+        let expected = self
+            .last_text_version
+            .and_then(|version| version.checked_add(deltas.len() as u64));
+        if expected != Some(state.text_version()) {
+            return Err(TreeSitterError::DeltaMismatch);
+        }
+        for delta in deltas {
+            self.apply_text_delta_incremental(delta)?;
+        }
+        if self.line_index.char_count() != state.editor().char_count() {
+            return Err(TreeSitterError::DeltaMismatch);
+        }
+        Ok(())
+    }
+
     fn parse(&mut self) -> Option<Tree> {
         self.parser.parse(&self.text, self.tree.as_ref())
     }
 
-    fn collect_highlight_intervals(&self, tree: &Tree) -> Vec<Interval> {
-        let mut cursor = QueryCursor::new();
+    fn changed_char_ranges(&self, old_tree: &Tree, new_tree: &Tree) -> Vec<Range<usize>> {
+        old_tree
+            .changed_ranges(new_tree)
+            .map(|range| {
+                self.line_index.byte_offset_to_char_offset(range.start_byte)
+                    ..self.line_index.byte_offset_to_char_offset(range.end_byte)
+            })
+            .collect()
+    }
+
+    /// Bring `host_intervals` and `fold_regions` up to date with `tree`.
+    ///
+    /// With `incremental`, the pending edit shifts move the previous results and only the
+    /// regions around the edits are queried again; otherwise everything is queried.
+    fn update_host_results(&mut self, tree: &Tree, incremental: bool) {
+        let shifts = std::mem::take(&mut self.pending_shifts);
+        if !incremental {
+            self.host_intervals.clear();
+            highlight_intervals(
+                &self.highlight_query,
+                &self.highlight_capture_styles,
+                tree.root_node(),
+                None,
+                &self.text,
+                &self.line_index,
+                &mut self.host_intervals,
+            );
+            self.fold_regions = self.collect_fold_regions(tree, None);
+        } else {
+            for shift in &shifts {
+                shift.shift_intervals(&mut self.host_intervals);
+                shift.shift_folds(&mut self.fold_regions);
+            }
+
+            let dirty = self.dirty_byte_ranges(tree, &shifts);
+            let dirty_chars: Vec<Range<usize>> = dirty
+                .iter()
+                .map(|bytes| {
+                    self.line_index.byte_offset_to_char_offset(bytes.start)
+                        ..self.line_index.byte_offset_to_char_offset(bytes.end)
+                })
+                .collect();
+            let line_of = |offset| self.line_index.char_offset_to_line_byte_column(offset).0;
+            let dirty_lines: Vec<Range<usize>> = dirty_chars
+                .iter()
+                .map(|chars| line_of(chars.start)..line_of(chars.end).max(line_of(chars.start) + 1))
+                .collect();
+            self.host_intervals.retain(|interval| {
+                !dirty_chars
+                    .iter()
+                    .any(|chars| interval.start < chars.end && chars.start < interval.end)
+            });
+            self.fold_regions.retain(|region| {
+                !dirty_lines
+                    .iter()
+                    .any(|lines| region.start_line < lines.end && lines.start <= region.end_line)
+            });
+
+            for bytes in dirty {
+                highlight_intervals(
+                    &self.highlight_query,
+                    &self.highlight_capture_styles,
+                    tree.root_node(),
+                    Some(bytes.clone()),
+                    &self.text,
+                    &self.line_index,
+                    &mut self.host_intervals,
+                );
+                let regions = self.collect_fold_regions(tree, Some(bytes));
+                self.fold_regions.extend(regions);
+            }
+        }
+
+        self.host_intervals
+            .sort_by_key(|i| (i.start, i.end, i.style_id));
+        self.host_intervals
+            .dedup_by(|a, b| a.start == b.start && a.end == b.end && a.style_id == b.style_id);
+        self.fold_regions
+            .sort_by_key(|r| (r.start_line, r.end_line));
+        self.fold_regions
+            .dedup_by(|a, b| a.start_line == b.start_line && a.end_line == b.end_line);
+    }
+
+    /// Byte ranges (sorted, disjoint) of the current document to query again after `shifts`:
+    /// the inserted text and Tree-sitter's changed ranges, widened to the top-level nodes they
+    /// touch and to whole lines.
+    fn dirty_byte_ranges(&self, tree: &Tree, shifts: &[EditShift]) -> Vec<Range<usize>> {
+        let mut chars = Vec::<Range<usize>>::new();
+        for shift in shifts {
+            for range in &mut chars {
+                *range = shift.map(range.start)..shift.map(range.end);
+            }
+            chars.push(shift.start..shift.new_end);
+        }
+        chars.extend(self.last_changed_ranges.iter().cloned());
+
+        let whole_lines = |bytes: Range<usize>| {
+            let line_index = &self.line_index;
+            let start_char = line_index.byte_offset_to_char_offset(bytes.start);
+            let end_char = line_index.byte_offset_to_char_offset(bytes.end);
+            let (start_line, _) = line_index.char_offset_to_line_byte_column(start_char);
+            let (end_line, _) = line_index.char_offset_to_line_byte_column(end_char);
+            let start = line_index
+                .char_offset_to_byte_offset(line_index.position_to_char_offset(start_line, 0));
+            let end = if end_line + 1 < line_index.line_count() {
+                line_index
+                    .char_offset_to_byte_offset(line_index.position_to_char_offset(end_line + 1, 0))
+            } else {
+                self.text.len()
+            };
+            start..end
+        };
+
         let root = tree.root_node();
-        let mut out = Vec::<Interval>::new();
+        let mut ranges: Vec<Range<usize>> = chars
+            .into_iter()
+            .map(|range| {
+                let mut bytes = whole_lines(
+                    self.line_index.char_offset_to_byte_offset(range.start)
+                        ..self.line_index.char_offset_to_byte_offset(range.end),
+                );
+                // Top-level nodes containing or touching the range.
+                let mut cursor = root.walk();
+                if cursor
+                    .goto_first_child_for_byte(bytes.start.saturating_sub(1))
+                    .is_some()
+                {
+                    loop {
+                        let node = cursor.node();
+                        if node.start_byte() > bytes.end {
+                            break;
+                        }
+                        bytes.start = bytes.start.min(node.start_byte());
+                        bytes.end = bytes.end.max(node.end_byte());
+                        if !cursor.goto_next_sibling() {
+                            break;
+                        }
+                    }
+                }
+                whole_lines(bytes)
+            })
+            .collect();
 
-        let mut matches = cursor.matches(&self.highlight_query, root, self.text.as_bytes());
-        while let Some(m) = matches.next() {
-            for capture in m.captures {
-                let idx = capture.index as usize;
-                let Some(style_id) = self.highlight_capture_styles.get(idx).and_then(|x| *x) else {
-                    continue;
-                };
+        ranges.sort_by_key(|range| range.start);
+        let mut merged = Vec::<Range<usize>>::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
 
-                let node = capture.node;
-                let start_byte = node.start_byte();
-                let end_byte = node.end_byte();
-                if end_byte <= start_byte {
-                    continue;
+    fn collect_highlight_intervals(&mut self, tree: &Tree) -> Vec<Interval> {
+        let mut host = self.host_intervals.clone();
+        self.apply_locals(tree, &mut host);
+
+        let mut injected = Vec::<Interval>::new();
+        self.last_injections.clear();
+        self.last_reparsed_injections.clear();
+        let mut previous = std::mem::take(&mut self.injection_cache);
+        for (language, range) in self.collect_injection_regions(tree) {
+            let Some(grammar) = self.injection_grammars.get_mut(&language) else {
+                continue;
+            };
+            let char_range = self.line_index.byte_offset_to_char_offset(range.start_byte)
+                ..self.line_index.byte_offset_to_char_offset(range.end_byte);
+            let Some(region_text) = self.text.get(range.start_byte..range.end_byte) else {
+                continue;
+            };
+
+            let cached = previous
+                .iter()
+                .position(|cached| cached.language == language && cached.text == region_text)
+                .map(|index| previous.swap_remove(index));
+            let cached = match cached {
+                Some(cached) => cached,
+                None => {
+                    if grammar.parser.set_included_ranges(&[range]).is_err() {
+                        continue;
+                    }
+                    let Some(injected_tree) = grammar.parser.parse(&self.text, None) else {
+                        continue;
+                    };
+                    let mut intervals = Vec::new();
+                    highlight_intervals(
+                        &grammar.highlight_query,
+                        &grammar.highlight_capture_styles,
+                        injected_tree.root_node(),
+                        None,
+                        &self.text,
+                        &self.line_index,
+                        &mut intervals,
+                    );
+                    for interval in &mut intervals {
+                        interval.start = interval.start.saturating_sub(char_range.start);
+                        interval.end = interval.end.saturating_sub(char_range.start);
+                    }
+                    self.last_reparsed_injections.push(char_range.clone());
+                    CachedInjection {
+                        language: language.clone(),
+                        text: region_text.to_string(),
+                        intervals,
+                    }
                 }
+            };
 
-                let start = self.line_index.byte_offset_to_char_offset(start_byte);
-                let end = self.line_index.byte_offset_to_char_offset(end_byte);
-                if end <= start {
-                    continue;
+            injected.extend(cached.intervals.iter().map(|interval| {
+                Interval::new(
+                    interval.start + char_range.start,
+                    interval.end + char_range.start,
+                    interval.style_id,
+                )
+            }));
+            self.injection_cache.push(cached);
+            self.last_injections.push(TreeSitterInjection {
+                language,
+                range: char_range,
+            });
+        }
+
+        let mut out = if self.last_injections.is_empty() {
+            host
+        } else {
+            let mut owned: Vec<Range<usize>> = self
+                .last_injections
+                .iter()
+                .map(|injection| injection.range.clone())
+                .collect();
+            owned.sort_by_key(|range| range.start);
+            let mut merged = Vec::<Range<usize>>::with_capacity(owned.len());
+            for range in owned {
+                match merged.last_mut() {
+                    Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                    _ => merged.push(range),
                 }
+            }
 
-                out.push(Interval::new(start, end, style_id));
+            let mut out = Vec::with_capacity(host.len() + injected.len());
+            for interval in &host {
+                push_outside_ranges(interval, &merged, &mut out);
             }
-        }
+            out.extend(injected);
+            out
+        };
 
         out.sort_by_key(|i| (i.start, i.end, i.style_id));
         out.dedup_by(|a, b| a.start == b.start && a.end == b.end && a.style_id == b.style_id);
         out
     }
 
-    fn collect_fold_regions(&self, tree: &Tree) -> Vec<FoldRegion> {
+    /// Replace the host highlights of resolved references with their definition's style.
+    fn apply_locals(&mut self, tree: &Tree, host: &mut Vec<Interval>) {
+        self.last_rebuilt_locals.clear();
+        if self.locals_query.is_none() {
+            return;
+        }
+
+        // The first highlight given to each exact range, as the style of a definition there.
+        let mut host_styles = HashMap::<(usize, usize), StyleId>::new();
+        for interval in host.iter() {
+            host_styles
+                .entry((interval.start, interval.end))
+                .or_insert(interval.style_id);
+        }
+
+        let mut previous = std::mem::take(&mut self.locals_cache);
+        let mut references = Vec::<Interval>::new();
+        let mut cursor = tree.root_node().walk();
+        for node in tree.root_node().children(&mut cursor) {
+            let Some(node_text) = self.text.get(node.start_byte()..node.end_byte()) else {
+                continue;
+            };
+            let hash = text_hash(node_text);
+            let start = self
+                .line_index
+                .byte_offset_to_char_offset(node.start_byte());
+            let resolved = match previous.remove(&hash) {
+                Some(resolved) => resolved,
+                None => {
+                    let mut resolved = self.resolve_locals(tree, node, &host_styles);
+                    for interval in &mut resolved {
+                        interval.start = interval.start.saturating_sub(start);
+                        interval.end = interval.end.saturating_sub(start);
+                    }
+                    self.last_rebuilt_locals
+                        .push(start..self.line_index.byte_offset_to_char_offset(node.end_byte()));
+                    resolved
+                }
+            };
+            references.extend(resolved.iter().map(|interval| {
+                Interval::new(
+                    interval.start + start,
+                    interval.end + start,
+                    interval.style_id,
+                )
+            }));
+            self.locals_cache.insert(hash, resolved);
+        }
+
+        if references.is_empty() {
+            return;
+        }
+        let replaced: HashSet<(usize, usize)> = references
+            .iter()
+            .map(|interval| (interval.start, interval.end))
+            .collect();
+        host.retain(|interval| !replaced.contains(&(interval.start, interval.end)));
+        host.extend(references);
+    }
+
+    /// References under `node` resolved to a styled definition (document character offsets).
+    fn resolve_locals(
+        &self,
+        tree: &Tree,
+        node: Node<'_>,
+        host_styles: &HashMap<(usize, usize), StyleId>,
+    ) -> Vec<Interval> {
+        let Some(query) = self.locals_query.as_ref() else {
+            return Vec::new();
+        };
+        let char_range = |node: Node<'_>| {
+            (
+                self.line_index
+                    .byte_offset_to_char_offset(node.start_byte()),
+                self.line_index.byte_offset_to_char_offset(node.end_byte()),
+            )
+        };
+
+        // The node itself acts as the outermost scope.
+        let mut scopes = vec![LocalScope {
+            end_byte: node.end_byte(),
+            definitions: Vec::new(),
+        }];
+        let mut definition_ranges = HashSet::<(usize, usize)>::new();
+        let mut out = Vec::new();
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(node.byte_range());
+        let mut captures = cursor.captures(query, tree.root_node(), self.text.as_bytes());
+        while let Some((m, index)) = captures.next() {
+            let capture = m.captures[*index];
+            let captured = capture.node;
+            while scopes.len() > 1
+                && scopes
+                    .last()
+                    .is_some_and(|scope| scope.end_byte <= captured.start_byte())
+            {
+                scopes.pop();
+            }
+
+            let name = query.capture_names()[capture.index as usize];
+            let Ok(text) = captured.utf8_text(self.text.as_bytes()) else {
+                continue;
+            };
+            if name == "local.scope" {
+                scopes.push(LocalScope {
+                    end_byte: captured.end_byte(),
+                    definitions: Vec::new(),
+                });
+            } else if name == "local.definition" || name.starts_with("local.definition.") {
+                let range = char_range(captured);
+                definition_ranges.insert(range);
+                if let Some(scope) = scopes.last_mut() {
+                    scope
+                        .definitions
+                        .push((text, host_styles.get(&range).copied()));
+                }
+            } else if name == "local.reference" {
+                let range = char_range(captured);
+                if definition_ranges.contains(&range) {
+                    continue;
+                }
+                let style = scopes.iter().rev().find_map(|scope| {
+                    scope
+                        .definitions
+                        .iter()
+                        .rev()
+                        .find(|(definition, _)| *definition == text)
+                        .map(|(_, style)| *style)
+                });
+                if let Some(Some(style_id)) = style {
+                    out.push(Interval::new(range.0, range.1, style_id));
+                }
+            }
+        }
+        out
+    }
+
+    /// Injected regions as (lowercase language name, host range), in document order.
+    fn collect_injection_regions(&self, tree: &Tree) -> Vec<(String, tree_sitter::Range)> {
+        let Some(query) = self.injection_query.as_ref() else {
+            return Vec::new();
+        };
+        let content_capture = query.capture_index_for_name("injection.content");
+        let language_capture = query.capture_index_for_name("injection.language");
+
+        let mut cursor = QueryCursor::new();
+        let mut regions = Vec::new();
+        let mut matches = cursor.matches(query, tree.root_node(), self.text.as_bytes());
+        while let Some(m) = matches.next() {
+            let mut language = query
+                .property_settings(m.pattern_index)
+                .iter()
+                .find(|property| &*property.key == "injection.language")
+                .and_then(|property| property.value.as_deref())
+                .map(str::to_string);
+            let mut content = None;
+            for capture in m.captures {
+                if Some(capture.index) == language_capture {
+                    language = capture
+                        .node
+                        .utf8_text(self.text.as_bytes())
+                        .ok()
+                        .map(|name| name.trim().to_string());
+                } else if Some(capture.index) == content_capture {
+                    content = Some(capture.node.range());
+                }
+            }
+            if let (Some(language), Some(range)) = (language, content)
+                && range.end_byte > range.start_byte
+            {
+                regions.push((language.to_lowercase(), range));
+            }
+        }
+
+        regions.sort_by_key(|(_, range)| (range.start_byte, range.end_byte));
+        regions
+    }
+
+    /// Symbols of the tags query, nested by containment.
+    fn collect_outline(&self, tree: &Tree) -> DocumentOutline {
+        let Some(query) = self.tags_query.as_ref() else {
+            return DocumentOutline::default();
+        };
+        let char_range = |node: Node<'_>| {
+            SymbolRange::new(
+                self.line_index
+                    .byte_offset_to_char_offset(node.start_byte()),
+                self.line_index.byte_offset_to_char_offset(node.end_byte()),
+            )
+        };
+
+        let mut cursor = QueryCursor::new();
+        let mut symbols = Vec::<DocumentSymbol>::new();
+        let mut matches = cursor.matches(query, tree.root_node(), self.text.as_bytes());
+        while let Some(m) = matches.next() {
+            let mut definition = None;
+            let mut name = None;
+            for capture in m.captures {
+                let capture_name = query.capture_names()[capture.index as usize];
+                if capture_name == "name" {
+                    name = Some(capture.node);
+                } else if let Some(kind) = capture_name.strip_prefix("definition.") {
+                    definition = Some((symbol_kind_for_tag(kind), capture.node));
+                }
+            }
+            let (Some((kind, node)), Some(name)) = (definition, name) else {
+                continue;
+            };
+            let Ok(name_text) = name.utf8_text(self.text.as_bytes()) else {
+                continue;
+            };
+            symbols.push(DocumentSymbol {
+                name: name_text.to_string(),
+                detail: None,
+                kind,
+                range: char_range(node),
+                selection_range: char_range(name),
+                utf16_range: None,
+                utf16_selection_range: None,
+                children: Vec::new(),
+                data_json: None,
+            });
+        }
+
+        symbols.sort_by_key(|symbol| (symbol.range.start, std::cmp::Reverse(symbol.range.end)));
+        symbols.dedup_by(|a, b| a.range == b.range && a.selection_range == b.selection_range);
+        DocumentOutline::new(nest_symbols(symbols))
+    }
+
+    /// Fold regions (unsorted) of nodes intersecting `byte_range` (all nodes without one).
+    fn collect_fold_regions(
+        &self,
+        tree: &Tree,
+        byte_range: Option<Range<usize>>,
+    ) -> Vec<FoldRegion> {
         let Some(query) = self.fold_query.as_ref() else {
             return Vec::new();
         };
 
         let mut cursor = QueryCursor::new();
+        if let Some(byte_range) = byte_range {
+            cursor.set_byte_range(byte_range);
+        }
         let root = tree.root_node();
         let mut regions = Vec::<FoldRegion>::new();
 
@@ -309,8 +1367,6 @@ impl TreeSitterProcessor {
             }
         }
 
-        regions.sort_by_key(|r| (r.start_line, r.end_line));
-        regions.dedup_by(|a, b| a.start_line == b.start_line && a.end_line == b.end_line);
         regions
     }
 }
@@ -325,14 +1381,36 @@ impl DocumentProcessor for TreeSitterProcessor {
             return Ok(Vec::new());
         }
 
+        let mut deltas = std::mem::take(&mut self.pending_deltas);
+        if deltas.is_empty()
+            && let Some(delta) = state.last_text_delta()
+            && self.last_applied_delta.as_ref() != Some(delta)
+        {
+            deltas.push(delta.clone());
+        }
+
         let update_mode = if self.tree.is_none() {
             self.sync_from_state_full(state);
             self.tree = self.parse();
             TreeSitterUpdateMode::Initial
-        } else if let Some(delta) = state.last_text_delta() {
-            match self.apply_text_delta_incremental(delta) {
+        } else if self.last_text_version == Some(state.text_version()) {
+            // No new edit: the version changed for another reason (cursor, styles, ...).
+            self.last_processed_version = Some(version);
+            self.last_update_mode = TreeSitterUpdateMode::Skipped;
+            self.last_changed_ranges.clear();
+            return Ok(Vec::new());
+        } else {
+            match self.apply_text_deltas(&deltas, state) {
                 Ok(()) => {
+                    // The old tree, already edited to match the new text.
+                    let old_tree = self.tree.clone();
                     self.tree = self.parse();
+                    self.last_changed_ranges = match (old_tree, self.tree.as_ref()) {
+                        (Some(old_tree), Some(new_tree)) => {
+                            self.changed_char_ranges(&old_tree, new_tree)
+                        }
+                        _ => Vec::new(),
+                    };
                     TreeSitterUpdateMode::Incremental
                 }
                 Err(_) => {
@@ -341,20 +1419,25 @@ impl DocumentProcessor for TreeSitterProcessor {
                     TreeSitterUpdateMode::FullReparse
                 }
             }
-        } else {
-            self.sync_from_state_full(state);
-            self.tree = self.parser.parse(&self.text, None);
-            TreeSitterUpdateMode::FullReparse
         };
+        self.last_text_version = Some(state.text_version());
+        if let Some(delta) = deltas.pop() {
+            self.last_applied_delta = Some(delta);
+        }
+        if update_mode != TreeSitterUpdateMode::Incremental {
+            let whole_document = 0..self.line_index.char_count();
+            self.last_changed_ranges = Vec::from([whole_document]);
+        }
 
-        let Some(tree) = self.tree.as_ref() else {
+        let Some(tree) = self.tree.clone() else {
             self.last_processed_version = Some(version);
             self.last_update_mode = update_mode;
             return Ok(Vec::new());
         };
 
-        let intervals = self.collect_highlight_intervals(tree);
-        let fold_regions = self.collect_fold_regions(tree);
+        self.update_host_results(&tree, update_mode == TreeSitterUpdateMode::Incremental);
+        let intervals = self.collect_highlight_intervals(&tree);
+        let fold_regions = self.fold_regions.clone();
 
         let mut edits = vec![ProcessingEdit::ReplaceStyleLayer {
             layer: self.config.style_layer,
@@ -368,6 +1451,19 @@ impl DocumentProcessor for TreeSitterProcessor {
             });
         }
 
+        if self.tags_query.is_some() {
+            let outline = self.collect_outline(&tree);
+            let changed = outline != self.outline;
+            self.outline = outline;
+            if self.config.emit_outline
+                && (changed || update_mode != TreeSitterUpdateMode::Incremental)
+            {
+                edits.push(ProcessingEdit::ReplaceDocumentSymbols {
+                    symbols: self.outline.clone(),
+                });
+            }
+        }
+
         self.last_processed_version = Some(version);
         self.last_update_mode = update_mode;
         Ok(edits)
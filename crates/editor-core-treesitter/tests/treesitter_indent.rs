@@ -0,0 +1,115 @@
+use editor_core::{
+    Command, CursorCommand, EditCommand, EditorStateManager, IndentProvider, LineIndex,
+    TabKeyBehavior, ViewCommand,
+};
+use editor_core_treesitter::TreeSitterIndentProvider;
+use tree_sitter_rust::LANGUAGE;
+
+const INDENTS_QUERY: &str = r#"
+[
+  (block)
+  (declaration_list)
+  (field_declaration_list)
+  (match_block)
+  (arguments)
+  (parameters)
+] @indent
+
+["}" ")" "]"] @outdent
+"#;
+
+fn provider() -> TreeSitterIndentProvider {
+    TreeSitterIndentProvider::new(LANGUAGE.into(), INDENTS_QUERY).unwrap()
+}
+
+/// Indentation level of a newline inserted at the `|` marker.
+fn level(marked: &str) -> Option<usize> {
+    let cursor = marked.find('|').unwrap();
+    let text = marked.replacen('|', "", 1);
+    provider().indent_level(&text, cursor)
+}
+
+#[test]
+fn test_indent_levels_follow_enclosing_nodes() {
+    // After `{`, closed or not.
+    assert_eq!(level("fn main() {|\n}\n"), Some(1));
+    assert_eq!(level("fn main() {|"), Some(1));
+    assert_eq!(level("fn main() {\n    let x = 1;|\n}\n"), Some(1));
+    assert_eq!(
+        level("fn main() {\n    match x {\n        1 => {}|\n    }\n}\n"),
+        Some(2)
+    );
+    // Brackets opened on the same line indent once.
+    assert_eq!(level("fn main() {\n    foo(bar(|\n}\n"), Some(2));
+    // Outside any block.
+    assert_eq!(level("fn main() {}\n|"), Some(0));
+    assert_eq!(level("fn main() {}|\n"), Some(0));
+}
+
+#[test]
+fn test_newline_before_closing_brace_does_not_double_indent() {
+    assert_eq!(
+        level("fn main() {\n    if x {\n        y();|}\n}\n"),
+        Some(1)
+    );
+    assert_eq!(level("fn main() {\n    foo(|)\n}\n"), Some(1));
+}
+
+#[test]
+fn test_indent_level_handles_non_ascii_text() {
+    assert_eq!(level("fn main() {\n    let s = \"é\";|\n}\n"), Some(1));
+    assert_eq!(level("fn main() {\n    let s = \"|é\";\n}\n"), Some(1));
+    // A byte offset inside `é` is not a cursor position.
+    let text = "fn main() {\n    let s = \"é\";\n}\n";
+    let inside = text.find('é').unwrap() + 1;
+    assert_eq!(provider().indent_level(text, inside), None);
+}
+
+fn newline_at(text: &str, line: usize, column: usize, behavior: TabKeyBehavior) -> String {
+    let mut state = EditorStateManager::new(text, 80);
+    state.set_indent_provider(Some(Box::new(provider())));
+    state
+        .execute(Command::View(ViewCommand::SetTabKeyBehavior { behavior }))
+        .unwrap();
+    state
+        .execute(Command::Cursor(CursorCommand::MoveTo { line, column }))
+        .unwrap();
+    state
+        .execute(Command::Edit(EditCommand::InsertNewline {
+            auto_indent: true,
+        }))
+        .unwrap();
+    state.editor().get_text()
+}
+
+#[test]
+fn test_insert_newline_uses_provider_and_tab_key_behavior() {
+    let text = "fn main() {\n}\n";
+    assert_eq!(
+        newline_at(text, 0, 11, TabKeyBehavior::Spaces),
+        "fn main() {\n    \n}\n"
+    );
+    let text = "fn main() {\n    let x = 1;\n}\n";
+    assert_eq!(
+        newline_at(text, 0, 11, TabKeyBehavior::Spaces),
+        "fn main() {\n    \n    let x = 1;\n}\n"
+    );
+    assert_eq!(
+        newline_at(text, 0, 11, TabKeyBehavior::Tab),
+        "fn main() {\n\t\n    let x = 1;\n}\n"
+    );
+
+    // Before a closing brace the new line takes the brace's level.
+    let text = "fn main() {\n    if x {\n        y();}\n}\n";
+    assert_eq!(
+        newline_at(text, 2, 12, TabKeyBehavior::Spaces),
+        "fn main() {\n    if x {\n        y();\n    }\n}\n"
+    );
+
+    let provider = provider();
+    let line_index = LineIndex::from_text("fn main() {\n");
+    assert_eq!(
+        provider.indent_for_new_line(&line_index, 11),
+        Some("\t".into())
+    );
+}
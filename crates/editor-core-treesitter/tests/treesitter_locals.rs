@@ -0,0 +1,120 @@
+use editor_core::intervals::Interval;
+use editor_core::{Command, DocumentProcessor, EditCommand, EditorStateManager, ProcessingEdit};
+use editor_core_treesitter::{
+    TreeSitterProcessor, TreeSitterProcessorConfig, TreeSitterUpdateMode,
+};
+use tree_sitter_rust::LANGUAGE;
+
+const PARAMETER: u32 = 1;
+const VARIABLE: u32 = 2;
+
+const HIGHLIGHTS_QUERY: &str = r#"
+(parameter pattern: (identifier) @variable.parameter)
+(identifier) @variable
+"#;
+
+const LOCALS_QUERY: &str = r#"
+(function_item) @local.scope
+(block) @local.scope
+(parameter pattern: (identifier) @local.definition)
+(let_declaration pattern: (identifier) @local.definition)
+(identifier) @local.reference
+"#;
+
+const SOURCE: &str = "fn scale(factor: i32) -> i32 {
+    let unrelated = 2;
+    factor * unrelated + factor
+}
+
+fn other(value: i32) -> i32 {
+    factor + value
+}
+";
+
+fn processor() -> TreeSitterProcessor {
+    let config = TreeSitterProcessorConfig::new(LANGUAGE.into(), HIGHLIGHTS_QUERY)
+        .with_simple_capture_styles([("variable.parameter", PARAMETER), ("variable", VARIABLE)])
+        .with_locals_query(LOCALS_QUERY);
+    TreeSitterProcessor::new(config).unwrap()
+}
+
+fn highlight_intervals(edits: Vec<ProcessingEdit>) -> Vec<Interval> {
+    edits
+        .into_iter()
+        .find_map(|edit| match edit {
+            ProcessingEdit::ReplaceStyleLayer { intervals, .. } => Some(intervals),
+            _ => None,
+        })
+        .expect("style layer edit")
+}
+
+/// The styles of the identifier starting at each occurrence of `name` in `text`.
+fn styles_of(intervals: &[Interval], text: &str, name: &str) -> Vec<Vec<u32>> {
+    text.match_indices(name)
+        .map(|(byte, _)| {
+            let start = text[..byte].chars().count();
+            let end = start + name.chars().count();
+            intervals
+                .iter()
+                .filter(|i| i.start == start && i.end == end)
+                .map(|i| i.style_id)
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_references_take_the_style_of_their_definition() {
+    let mut state = EditorStateManager::new(SOURCE, 80);
+    let mut processor = processor();
+    let intervals = highlight_intervals(processor.process(&state).unwrap());
+
+    // The parameter and its references share the parameter style; `factor` in `other` is not
+    // defined there and keeps the plain highlight.
+    assert_eq!(
+        styles_of(&intervals, SOURCE, "factor"),
+        vec![
+            vec![PARAMETER, VARIABLE],
+            vec![PARAMETER],
+            vec![PARAMETER],
+            vec![VARIABLE],
+        ]
+    );
+    assert_eq!(
+        styles_of(&intervals, SOURCE, "unrelated"),
+        vec![vec![VARIABLE], vec![VARIABLE]]
+    );
+    assert_eq!(
+        styles_of(&intervals, SOURCE, "value"),
+        vec![vec![PARAMETER, VARIABLE], vec![PARAMETER]]
+    );
+    assert_eq!(processor.last_rebuilt_locals().len(), 2);
+
+    // An edit in `other` only rebuilds the locals of `other`.
+    let offset = SOURCE.find("factor + value").unwrap();
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: "value * ".to_string(),
+        }))
+        .unwrap();
+    let intervals = highlight_intervals(processor.process(&state).unwrap());
+    assert_eq!(
+        processor.last_update_mode(),
+        TreeSitterUpdateMode::Incremental
+    );
+    let text = state.editor().get_text();
+    let other_start = text.find("fn other").unwrap();
+    assert_eq!(
+        processor.last_rebuilt_locals().to_vec(),
+        vec![other_start..text.len() - 1]
+    );
+    assert_eq!(
+        styles_of(&intervals, &text, "value"),
+        vec![vec![PARAMETER, VARIABLE], vec![PARAMETER], vec![PARAMETER]]
+    );
+    assert_eq!(
+        styles_of(&intervals, &text, "factor")[1..3],
+        [vec![PARAMETER], vec![PARAMETER]]
+    );
+}
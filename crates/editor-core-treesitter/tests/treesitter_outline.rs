@@ -0,0 +1,145 @@
+use editor_core::processing::ProcessingEdit;
+use editor_core::{
+    Command, DocumentOutline, DocumentProcessor, EditCommand, EditorStateManager, SymbolKind,
+};
+use editor_core_treesitter::{
+    TreeSitterProcessor, TreeSitterProcessorConfig, TreeSitterUpdateMode,
+};
+use tree_sitter_rust::LANGUAGE;
+
+const TAGS_QUERY: &str = r#"
+(mod_item name: (identifier) @name) @definition.module
+(struct_item name: (type_identifier) @name) @definition.struct
+(trait_item name: (type_identifier) @name) @definition.interface
+(impl_item type: (type_identifier) @name) @definition.impl
+(declaration_list (function_item name: (identifier) @name) @definition.method)
+(source_file (function_item name: (identifier) @name) @definition.function)
+(mod_item body: (declaration_list (function_item name: (identifier) @name) @definition.function))
+(call_expression function: (identifier) @name) @reference.call
+"#;
+
+const SOURCE: &str = "mod outer {
+    pub struct Point { x: i32 }
+
+    impl Point {
+        pub fn new() -> Self { Point { x: 0 } }
+        fn norm(&self) -> i32 { self.x }
+    }
+
+    mod inner {
+        fn helper() {}
+    }
+}
+
+fn main() {
+    helper();
+}
+";
+
+fn processor() -> TreeSitterProcessor {
+    let config = TreeSitterProcessorConfig::new(LANGUAGE.into(), "").with_tags_query(TAGS_QUERY);
+    TreeSitterProcessor::new(config).unwrap()
+}
+
+/// `(depth, kind, name)` of every symbol, in pre-order.
+fn shape(outline: &DocumentOutline) -> Vec<(usize, SymbolKind, String)> {
+    fn walk(
+        symbols: &[editor_core::DocumentSymbol],
+        depth: usize,
+        out: &mut Vec<(usize, SymbolKind, String)>,
+    ) {
+        for symbol in symbols {
+            out.push((depth, symbol.kind, symbol.name.clone()));
+            walk(&symbol.children, depth + 1, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(&outline.symbols, 0, &mut out);
+    out
+}
+
+fn outline_edit(edits: &[ProcessingEdit]) -> Option<&DocumentOutline> {
+    edits.iter().find_map(|edit| match edit {
+        ProcessingEdit::ReplaceDocumentSymbols { symbols } => Some(symbols),
+        _ => None,
+    })
+}
+
+#[test]
+fn test_outline_nests_modules_impls_and_methods() {
+    let mut state = EditorStateManager::new(SOURCE, 80);
+    let mut processor = processor();
+    let edits = processor.process(&state).unwrap();
+
+    let expected = vec![
+        (0, SymbolKind::Module, "outer".to_string()),
+        (1, SymbolKind::Struct, "Point".to_string()),
+        (1, SymbolKind::Object, "Point".to_string()),
+        (2, SymbolKind::Method, "new".to_string()),
+        (2, SymbolKind::Method, "norm".to_string()),
+        (1, SymbolKind::Module, "inner".to_string()),
+        (2, SymbolKind::Function, "helper".to_string()),
+        (0, SymbolKind::Function, "main".to_string()),
+    ];
+    assert_eq!(shape(processor.outline()), expected);
+    assert_eq!(outline_edit(&edits), Some(processor.outline()));
+
+    let main = &processor.outline().symbols[1];
+    let main_start = SOURCE.find("fn main").unwrap();
+    assert_eq!(main.range.start, main_start);
+    assert_eq!(main.range.end, SOURCE.len() - 1);
+    assert_eq!(main.selection_range.start, main_start + 3);
+    assert_eq!(main.selection_range.end, main_start + 7);
+
+    state.apply_processing_edits(edits);
+    assert_eq!(
+        state.editor().document_symbols.flatten_preorder().len(),
+        expected.len()
+    );
+}
+
+#[test]
+fn test_outline_follows_incremental_edits() {
+    let mut state = EditorStateManager::new(SOURCE, 80);
+    let mut processor = processor();
+    processor.process(&state).unwrap();
+
+    // Add a method to the impl block.
+    let offset = SOURCE.find("        fn norm").unwrap();
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: "        fn scale(&mut self) {}\n".to_string(),
+        }))
+        .unwrap();
+    let edits = processor.process(&state).unwrap();
+    assert_eq!(
+        processor.last_update_mode(),
+        TreeSitterUpdateMode::Incremental
+    );
+    let outline = outline_edit(&edits).expect("changed outline is emitted");
+    let names: Vec<&str> = outline.symbols[0].children[1]
+        .children
+        .iter()
+        .map(|symbol| symbol.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["new", "scale", "norm"]);
+    let main = &outline.symbols[1];
+    assert_eq!(
+        main.range.start,
+        state.editor().get_text().find("fn main").unwrap()
+    );
+
+    // A comment after the last symbol leaves the outline as it was.
+    let offset = state.editor().char_count();
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: "// end\n".to_string(),
+        }))
+        .unwrap();
+    let before = processor.outline().clone();
+    let edits = processor.process(&state).unwrap();
+    assert!(outline_edit(&edits).is_none());
+    assert_eq!(processor.outline(), &before);
+}
@@ -1,8 +1,15 @@
-use editor_core::intervals::StyleLayerId;
-use editor_core::{Command, DocumentProcessor, EditCommand, EditorStateManager, ProcessingEdit};
+use editor_core::delta::{TextDelta, TextDeltaEdit};
+use editor_core::intervals::{Interval, StyleLayerId};
+use editor_core::{
+    Command, CursorCommand, DocumentProcessor, EditCommand, EditorStateManager, LineIndex,
+    Position, ProcessingEdit,
+};
 use editor_core_treesitter::{
-    TreeSitterProcessor, TreeSitterProcessorConfig, TreeSitterUpdateMode,
+    TreeSitterInjection, TreeSitterInjectionLanguage, TreeSitterProcessor,
+    TreeSitterProcessorConfig, TreeSitterUpdateMode, input_edit_for_delta_edit,
 };
+use std::sync::{Arc, Mutex};
+use tree_sitter::Point;
 use tree_sitter_rust::LANGUAGE;
 
 fn rust_test_highlights_query() -> &'static str {
@@ -90,3 +97,462 @@ fn test_processor_uses_text_delta_incrementally() {
     );
     assert!(!edits.is_empty());
 }
+
+fn rust_test_processor() -> TreeSitterProcessor {
+    let config = TreeSitterProcessorConfig::new(LANGUAGE.into(), rust_test_highlights_query())
+        .with_folds_query(rust_test_folds_query())
+        .with_simple_capture_styles([("comment", 1), ("string", 2), ("type", 3), ("function", 4)]);
+    TreeSitterProcessor::new(config).unwrap()
+}
+
+fn highlight_intervals(edits: Vec<ProcessingEdit>) -> Vec<Interval> {
+    edits
+        .into_iter()
+        .find_map(|edit| match edit {
+            ProcessingEdit::ReplaceStyleLayer { intervals, .. } => Some(intervals),
+            _ => None,
+        })
+        .expect("highlight layer")
+}
+
+/// Many small functions, so a local edit touches a small part of the document.
+fn many_functions() -> String {
+    (0..50)
+        .map(|i| format!("// function {i}\nfn f{i}(a: i32) -> i32 {{\n    a + {i}\n}}\n\n"))
+        .collect()
+}
+
+fn insert(state: &mut EditorStateManager, offset: usize, text: &str) {
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: text.to_string(),
+        }))
+        .unwrap();
+}
+
+#[test]
+fn test_input_edit_uses_byte_offsets_and_byte_columns() {
+    // `你` and `好` are 3 bytes each in UTF-8.
+    let line_index = LineIndex::from_text("fn 你好() {}\nlet x;");
+
+    let edit = input_edit_for_delta_edit(
+        &line_index,
+        &TextDeltaEdit {
+            start: 4,
+            deleted_text: "好".to_string(),
+            inserted_text: "é\nz".to_string(),
+        },
+    );
+    assert_eq!(edit.start_byte, 6);
+    assert_eq!(edit.old_end_byte, 9);
+    assert_eq!(edit.new_end_byte, 6 + "é\nz".len());
+    assert_eq!(edit.start_position, Point { row: 0, column: 6 });
+    assert_eq!(edit.old_end_position, Point { row: 0, column: 9 });
+    assert_eq!(edit.new_end_position, Point { row: 1, column: 1 });
+
+    // An edit on the second line starts at byte column 4, row 1.
+    let edit = input_edit_for_delta_edit(
+        &line_index,
+        &TextDeltaEdit {
+            start: 15,
+            deleted_text: String::new(),
+            inserted_text: "y".to_string(),
+        },
+    );
+    assert_eq!(edit.start_byte, "fn 你好() {}\nlet ".len());
+    assert_eq!(edit.start_position, Point { row: 1, column: 4 });
+    assert_eq!(edit.new_end_position, Point { row: 1, column: 5 });
+}
+
+#[test]
+fn test_single_char_insert_reuses_the_old_tree() {
+    let text = many_functions();
+    let mut state = EditorStateManager::new(&text, 80);
+    let mut processor = rust_test_processor();
+    state.apply_processor(&mut processor).unwrap();
+    let whole_document = 0..text.chars().count();
+    assert_eq!(
+        processor.last_changed_ranges(),
+        std::slice::from_ref(&whole_document)
+    );
+
+    // Insert one character into the comment of the 26th function.
+    let offset = text.find("// function 25").unwrap() + "// func".len();
+    insert(&mut state, offset, "x");
+
+    let edits = processor.process(&state).unwrap();
+    assert_eq!(
+        processor.last_update_mode(),
+        TreeSitterUpdateMode::Incremental
+    );
+    // Only the edited function's neighbourhood is reported as changed.
+    let changed: usize = processor
+        .last_changed_ranges()
+        .iter()
+        .map(|r| r.len())
+        .sum();
+    assert!(
+        changed < 60,
+        "changed ranges: {:?}",
+        processor.last_changed_ranges()
+    );
+    assert!(
+        processor
+            .last_changed_ranges()
+            .iter()
+            .all(|r| r.start + 40 >= offset && r.end <= offset + 40)
+    );
+
+    // The incremental result matches a parse from scratch.
+    let mut fresh = rust_test_processor();
+    assert_eq!(
+        highlight_intervals(edits),
+        highlight_intervals(fresh.process(&state).unwrap())
+    );
+}
+
+#[test]
+fn test_one_char_edit_in_a_large_file_reparses_incrementally() {
+    let text: String = (0..20_000)
+        .map(|i| format!("// function {i}\nfn f{i}(a: i32) -> i32 {{\n    a + {i}\n}}\n\n"))
+        .collect();
+    assert!(text.len() > 1_000_000);
+    let mut state = EditorStateManager::new(&text, 80);
+    let mut processor = rust_test_processor();
+    processor.process(&state).unwrap();
+
+    let offset = text.find("// function 10000").unwrap() + "// func".len();
+    insert(&mut state, offset, "x");
+    let edits = processor.process(&state).unwrap();
+    assert_eq!(
+        processor.last_update_mode(),
+        TreeSitterUpdateMode::Incremental
+    );
+    // Only the edited comment is reported as changed, not the rest of the megabyte.
+    assert!(
+        processor
+            .last_changed_ranges()
+            .iter()
+            .all(|r| r.start + 40 >= offset && r.end <= offset + 40),
+        "changed ranges: {:?}",
+        processor.last_changed_ranges()
+    );
+
+    let mut fresh = rust_test_processor();
+    assert_eq!(
+        highlight_intervals(edits),
+        highlight_intervals(fresh.process(&state).unwrap())
+    );
+}
+
+#[test]
+fn test_cursor_move_after_edit_does_not_reapply_the_delta() {
+    let mut state = EditorStateManager::new(include_str!("fixtures/rust_sample.rs"), 80);
+    let mut processor = rust_test_processor();
+    state.apply_processor(&mut processor).unwrap();
+
+    insert(&mut state, 0, "x");
+    state.apply_processor(&mut processor).unwrap();
+    assert_eq!(
+        processor.last_update_mode(),
+        TreeSitterUpdateMode::Incremental
+    );
+
+    // The version moves, but `last_text_delta()` still holds the already applied insert.
+    state
+        .execute(Command::Cursor(CursorCommand::MoveTo {
+            line: 2,
+            column: 3,
+        }))
+        .unwrap();
+    assert!(state.last_text_delta().is_some());
+    assert!(processor.process(&state).unwrap().is_empty());
+    assert_eq!(processor.last_update_mode(), TreeSitterUpdateMode::Skipped);
+    assert!(processor.last_changed_ranges().is_empty());
+    assert_eq!(state.editor().cursor_position(), Position::new(2, 3));
+}
+
+#[test]
+fn test_pushed_deltas_keep_several_edits_incremental() {
+    let text = many_functions();
+    let mut state = EditorStateManager::new(&text, 80);
+    let mut processor = rust_test_processor();
+    state.apply_processor(&mut processor).unwrap();
+
+    let deltas: Arc<Mutex<Vec<TextDelta>>> = Arc::default();
+    let sink = deltas.clone();
+    state.subscribe(move |change| {
+        if let Some(delta) = change.text_delta.as_deref() {
+            sink.lock().unwrap().push(delta.clone());
+        }
+    });
+
+    // Two edits between polls: `last_text_delta()` alone only describes the second one.
+    insert(&mut state, text.find("a + 3").unwrap(), "1 + ");
+    let offset = state.editor().get_text().find("a + 40").unwrap();
+    insert(&mut state, offset, "// note\n");
+    for delta in deltas.lock().unwrap().drain(..) {
+        processor.push_text_delta(delta);
+    }
+
+    let edits = processor.process(&state).unwrap();
+    assert_eq!(
+        processor.last_update_mode(),
+        TreeSitterUpdateMode::Incremental
+    );
+    let mut fresh = rust_test_processor();
+    assert_eq!(
+        highlight_intervals(edits),
+        highlight_intervals(fresh.process(&state).unwrap())
+    );
+
+    // Without the pushed deltas the stale document is detected and fully re-parsed.
+    let mut unfed = rust_test_processor();
+    let mut other = EditorStateManager::new(&text, 80);
+    other.apply_processor(&mut unfed).unwrap();
+    insert(&mut other, text.find("a + 3").unwrap(), "1 + ");
+    insert(&mut other, offset, "// note\n");
+    other.apply_processor(&mut unfed).unwrap();
+    assert_eq!(unfed.last_update_mode(), TreeSitterUpdateMode::FullReparse);
+}
+
+fn fold_spans(edits: &[ProcessingEdit]) -> Vec<(usize, usize)> {
+    edits
+        .iter()
+        .find_map(|edit| match edit {
+            ProcessingEdit::ReplaceFoldingRegions { regions, .. } => {
+                Some(regions.iter().map(|r| (r.start_line, r.end_line)).collect())
+            }
+            _ => None,
+        })
+        .expect("folding edit")
+}
+
+#[test]
+fn test_incremental_highlights_and_folds_match_a_full_parse() {
+    let text = many_functions();
+    let mut state = EditorStateManager::new(&text, 80);
+    let mut processor = rust_test_processor();
+    state.apply_processor(&mut processor).unwrap();
+
+    let edit = |state: &mut EditorStateManager, find: &str, len: usize, replacement: &str| {
+        let current = state.editor().get_text();
+        let start = char_offset(&current, find);
+        state
+            .execute(Command::Edit(EditCommand::Replace {
+                start,
+                length: len,
+                text: replacement.to_string(),
+            }))
+            .unwrap();
+    };
+    let steps: [(&str, usize, &str); 5] = [
+        // New lines inside a body shift every later fold.
+        ("a + 10", 0, "let b = 1;\n    let c = \"two\";\n    "),
+        // Joining two functions onto one line.
+        ("}\n\n// function 20", 3, "} "),
+        // Deleting across a function boundary.
+        ("a + 30\n}", 30, ""),
+        // Opening a block comment changes the rest of the document.
+        ("// function 40", 0, "/* "),
+        ("// function 45", 0, "*/ "),
+    ];
+    for (find, len, replacement) in steps {
+        edit(&mut state, find, len, replacement);
+        let edits = processor.process(&state).unwrap();
+        assert_eq!(
+            processor.last_update_mode(),
+            TreeSitterUpdateMode::Incremental
+        );
+        let fresh = rust_test_processor().process(&state).unwrap();
+        assert_eq!(fold_spans(&edits), fold_spans(&fresh), "after {find:?}");
+        assert_eq!(
+            highlight_intervals(edits),
+            highlight_intervals(fresh),
+            "after {find:?}"
+        );
+    }
+
+    // A missed edit that keeps the length is caught by the text version.
+    edit(&mut state, "a + 2", 1, "b");
+    edit(&mut state, "a + 3", 1, "c");
+    let edits = processor.process(&state).unwrap();
+    assert_eq!(
+        processor.last_update_mode(),
+        TreeSitterUpdateMode::FullReparse
+    );
+    assert_eq!(
+        highlight_intervals(edits),
+        highlight_intervals(rust_test_processor().process(&state).unwrap())
+    );
+}
+
+/// Char offset of the first `needle` in `text`.
+fn char_offset(text: &str, needle: &str) -> usize {
+    text[..text.find(needle).unwrap()].chars().count()
+}
+
+fn styles_at(intervals: &[Interval], offset: usize) -> Vec<u32> {
+    let mut styles: Vec<u32> = intervals
+        .iter()
+        .filter(|interval| interval.start <= offset && offset < interval.end)
+        .map(|interval| interval.style_id)
+        .collect();
+    styles.sort_unstable();
+    styles
+}
+
+#[test]
+fn test_markdown_fenced_code_is_highlighted_by_the_injected_grammar() {
+    const LITERAL: u32 = 10;
+    const DELIMITER: u32 = 11;
+    const HEADING: u32 = 12;
+    const KEYWORD: u32 = 20;
+    const FUNCTION: u32 = 21;
+
+    let text = "# Überblick ✓\n\n```rust\nfn main() {}\n```\n\n```python\ndef f(): pass\n```\n";
+    let mut state = EditorStateManager::new(text, 80);
+
+    let rust = TreeSitterInjectionLanguage::new(
+        LANGUAGE.into(),
+        r#"
+        "fn" @keyword
+        (function_item name: (identifier) @function)
+        "#,
+    )
+    .with_simple_capture_styles([("keyword", KEYWORD), ("function", FUNCTION)]);
+    let config = TreeSitterProcessorConfig::new(
+        tree_sitter_md::LANGUAGE.into(),
+        r#"
+        (fenced_code_block) @literal
+        (fenced_code_block_delimiter) @delimiter
+        (atx_h1_marker) @heading
+        "#,
+    )
+    .with_simple_capture_styles([
+        ("literal", LITERAL),
+        ("delimiter", DELIMITER),
+        ("heading", HEADING),
+    ])
+    .with_injections_query(tree_sitter_md::INJECTION_QUERY_BLOCK)
+    .with_injection_language("Rust", rust);
+    let mut processor = TreeSitterProcessor::new(config).unwrap();
+
+    let intervals = highlight_intervals(processor.process(&state).unwrap());
+    let fn_start = char_offset(text, "fn main");
+    let main_start = char_offset(text, "main");
+    assert!(intervals.contains(&Interval::new(fn_start, fn_start + 2, KEYWORD)));
+    assert!(intervals.contains(&Interval::new(main_start, main_start + 4, FUNCTION)));
+    assert_eq!(
+        processor.last_injections(),
+        &[TreeSitterInjection {
+            language: "rust".to_string(),
+            range: fn_start..char_offset(text, "```\n\n```python"),
+        }]
+    );
+
+    // The injected grammar owns the fence content; the host keeps the fence itself.
+    assert_eq!(styles_at(&intervals, fn_start), vec![KEYWORD]);
+    assert_eq!(styles_at(&intervals, fn_start + 2), Vec::<u32>::new());
+    assert_eq!(
+        styles_at(&intervals, char_offset(text, "```rust")),
+        vec![LITERAL, DELIMITER]
+    );
+    assert_eq!(styles_at(&intervals, 0), vec![HEADING]);
+    // No grammar is registered for Python: the host highlighting stays.
+    assert_eq!(
+        styles_at(&intervals, char_offset(text, "def")),
+        vec![LITERAL]
+    );
+
+    // An edit inside the fence moves the injected highlights with it.
+    insert(&mut state, fn_start, "pub ");
+    let intervals = highlight_intervals(processor.process(&state).unwrap());
+    assert_eq!(
+        processor.last_update_mode(),
+        TreeSitterUpdateMode::Incremental
+    );
+    assert!(intervals.contains(&Interval::new(fn_start + 4, fn_start + 6, KEYWORD)));
+    assert_eq!(styles_at(&intervals, fn_start), Vec::<u32>::new());
+}
+
+#[test]
+fn test_only_changed_injected_regions_are_reparsed() {
+    const KEYWORD: u32 = 20;
+
+    let text = "```rust\nfn a() {}\n```\n\ntext\n\n```rust\nlet b = 1;\n```\n";
+    let mut state = EditorStateManager::new(text, 80);
+    let rust = TreeSitterInjectionLanguage::new(LANGUAGE.into(), r#"["fn" "let"] @keyword"#)
+        .with_simple_capture_styles([("keyword", KEYWORD)]);
+    let config =
+        TreeSitterProcessorConfig::new(tree_sitter_md::LANGUAGE.into(), "(paragraph) @text")
+            .with_injections_query(tree_sitter_md::INJECTION_QUERY_BLOCK)
+            .with_injection_language("rust", rust);
+    let mut processor = TreeSitterProcessor::new(config).unwrap();
+
+    processor.process(&state).unwrap();
+    assert_eq!(processor.last_reparsed_injections().len(), 2);
+
+    // Editing the first fence re-parses it; the second one only moves.
+    insert(&mut state, char_offset(text, "a()"), "xy");
+    let intervals = highlight_intervals(processor.process(&state).unwrap());
+    let edited = state.editor().get_text();
+    let injections = processor.last_injections().to_vec();
+    assert_eq!(injections.len(), 2);
+    assert_eq!(
+        processor.last_reparsed_injections(),
+        &[injections[0].range.clone()]
+    );
+    let let_start = char_offset(&edited, "let");
+    assert!(intervals.contains(&Interval::new(let_start, let_start + 3, KEYWORD)));
+
+    // An edit outside both fences re-parses neither.
+    insert(&mut state, char_offset(&edited, "text"), "more ");
+    let intervals = highlight_intervals(processor.process(&state).unwrap());
+    assert!(processor.last_reparsed_injections().is_empty());
+    let fn_start = char_offset(text, "fn");
+    assert!(intervals.contains(&Interval::new(fn_start, fn_start + 2, KEYWORD)));
+    assert!(intervals.contains(&Interval::new(let_start + 5, let_start + 8, KEYWORD)));
+}
+
+#[test]
+fn test_custom_capture_map_with_dotted_fallback_and_default_style() {
+    let text = "fn f() -> i32 {\n    if true {\n        return 1;\n    }\n    0\n}\n";
+    let state = EditorStateManager::new(text, 80);
+
+    let theme: std::collections::HashMap<String, u32> = [
+        ("keyword".to_string(), 1),
+        ("keyword.control".to_string(), 2),
+        ("type".to_string(), 3),
+    ]
+    .into_iter()
+    .collect();
+    let config = TreeSitterProcessorConfig::new(
+        LANGUAGE.into(),
+        r#"
+        "fn" @keyword
+        ["if" "return"] @keyword.control
+        (primitive_type) @type.builtin
+        (identifier) @variable
+        ((identifier) @_name (#eq? @_name "f"))
+        "#,
+    )
+    .with_capture_styles(theme)
+    .with_default_capture_style(9);
+    assert_eq!(config.capture_style("keyword.control.rust"), Some(2));
+    assert_eq!(config.capture_style("_name"), None);
+
+    let mut processor = TreeSitterProcessor::new(config).unwrap();
+    let intervals = highlight_intervals(processor.process(&state).unwrap());
+    assert_eq!(styles_at(&intervals, char_offset(text, "fn")), vec![1]);
+    assert_eq!(styles_at(&intervals, char_offset(text, "if")), vec![2]);
+    assert_eq!(styles_at(&intervals, char_offset(text, "return")), vec![2]);
+    assert_eq!(styles_at(&intervals, char_offset(text, "i32")), vec![3]);
+    // `@variable` has no mapping and gets the default; `@_name` stays unstyled.
+    assert_eq!(styles_at(&intervals, char_offset(text, "f()")), vec![9]);
+    assert_eq!(
+        styles_at(&intervals, char_offset(text, "true")),
+        Vec::<u32>::new()
+    );
+}
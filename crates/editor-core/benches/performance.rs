@@ -1,5 +1,9 @@
 use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
-use editor_core::{Command, CommandExecutor, EditCommand, EditorStateManager, ViewCommand};
+use editor_core::intervals::Interval;
+use editor_core::{
+    Command, CommandExecutor, EditCommand, EditorStateManager, IntervalTree, StyleLayerId,
+    ViewCommand,
+};
 
 fn large_text(line_count: usize) -> String {
     let mut out = String::with_capacity(line_count * 64);
@@ -64,10 +68,62 @@ fn bench_viewport_render_small_slice(c: &mut Criterion) {
     });
 }
 
+/// An executor over `large_text(50_000)` with one style per word in two overlapping layers.
+fn styled_executor() -> CommandExecutor {
+    let text = large_text(50_000);
+    let mut words = IntervalTree::new();
+    let mut lines = IntervalTree::new();
+    let mut offset = 0;
+    for line in text.split('\n') {
+        let mut word_start = offset;
+        for (i, ch) in line.chars().enumerate() {
+            if ch == ' ' {
+                words.insert(Interval::new(word_start, offset + i, (i % 7) as u32));
+                word_start = offset + i + 1;
+            }
+        }
+        let len = line.chars().count();
+        lines.insert(Interval::new(offset + 7, offset + len, 100));
+        offset += len + 1;
+    }
+
+    let mut executor = CommandExecutor::new(&text, 120);
+    let editor = executor.editor_mut();
+    editor
+        .style_layers
+        .insert(StyleLayerId::SIMPLE_SYNTAX, words);
+    editor
+        .style_layers
+        .insert(StyleLayerId::SEMANTIC_TOKENS, lines);
+    executor
+}
+
+fn bench_style_queries(c: &mut Criterion) {
+    let executor = styled_executor();
+    let editor = executor.editor();
+    let start = editor.line_index.position_to_char_offset(25_000, 0);
+    let end = editor.line_index.position_to_char_offset(25_060, 0);
+
+    c.bench_function("style_query/60_lines_per_char", |b| {
+        b.iter(|| {
+            for offset in start..end {
+                black_box(editor.styles_at_offset(offset));
+            }
+        })
+    });
+    c.bench_function("style_query/60_lines_runs", |b| {
+        b.iter(|| black_box(editor.style_runs(start, end)))
+    });
+    c.bench_function("styled_grid/60_lines", |b| {
+        b.iter(|| black_box(editor.get_headless_grid_styled(25_000, 60)))
+    });
+}
+
 criterion_group!(
     benches,
     bench_large_file_open,
     bench_typing_in_middle,
-    bench_viewport_render_small_slice
+    bench_viewport_render_small_slice,
+    bench_style_queries
 );
 criterion_main!(benches);
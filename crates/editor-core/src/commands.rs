@@ -33,28 +33,38 @@
 //! executor.execute_batch(commands).unwrap();
 //! ```
 
-use crate::decorations::{Decoration, DecorationLayerId, DecorationPlacement};
+use crate::decorations::{
+    Decoration, DecorationKind, DecorationLayerId, DecorationPlacement, UnderlineStyle,
+};
 use crate::delta::{TextDelta, TextDeltaEdit};
-use crate::diagnostics::Diagnostic;
-use crate::intervals::{FoldRegion, StyleId, StyleLayerId};
+use crate::diagnostics::{Diagnostic, DiagnosticSeverity};
+use crate::indent::{IndentSettings, reindent_leading_whitespace};
+use crate::intervals::{FoldRegion, StyleId, StyleLayerId, StylePriority, StyleRun};
 use crate::layout::{
-    WrapIndent, WrapMode, cell_width_at, char_width, visual_x_for_column,
+    WrapIndent, WrapMode, cell_width_at, char_width, directional_x_in_segment,
+    grapheme_cell_widths, grapheme_columns, grapheme_width_at, line_direction, visual_x_for_column,
     wrap_indent_cells_for_line_text,
 };
 use crate::line_ending::LineEnding;
-use crate::search::{CharIndex, SearchMatch, SearchOptions, find_all, find_next, find_prev};
+use crate::search::{
+    CharIndex, SearchMatch, SearchOptions, find_all, find_all_in_range, find_next,
+    find_next_in_range, find_prev,
+};
 use crate::snapshot::{
-    Cell, ComposedCell, ComposedCellSource, ComposedGrid, ComposedLine, ComposedLineKind,
-    HeadlessGrid, HeadlessLine, MinimapGrid, MinimapLine,
+    Cell, CellSource, ComposedCell, ComposedCellSource, ComposedGrid, ComposedLine,
+    ComposedLineKind, GutterFoldState, GutterLine, HeadlessGrid, HeadlessLine, MinimapCell,
+    MinimapData, MinimapGrid, MinimapLine, MinimapRow, SnapshotOptions, WhitespaceMode,
+    control_glyph, whitespace_glyph,
 };
+use crate::snippets::{SnippetSession, SnippetTabstopRange};
 use crate::{
     FOLD_PLACEHOLDER_STYLE_ID, FoldingManager, IntervalTree, LayoutEngine, LineIndex, PieceTable,
 };
-use editor_core_lang::CommentConfig;
-use regex::RegexBuilder;
+use editor_core_lang::{CommentConfig, LanguageConfig};
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
 use unicode_segmentation::UnicodeSegmentation;
 
 /// Position coordinates (line and column numbers)
@@ -116,6 +126,72 @@ pub enum TabKeyBehavior {
     Spaces,
 }
 
+/// Computes the indentation of a line opened by [`EditCommand::InsertNewline`].
+///
+/// When a provider is set (see [`CommandExecutor::set_indent_provider`]) and `auto_indent` is
+/// requested, the executor asks it for the leading whitespace of the new line. `None` falls back
+/// to copying the leading whitespace of the current line.
+///
+/// `cursor_offset` is the character offset where the line break is inserted; `line_index` is the
+/// document before the edit. The returned whitespace is re-expressed in the executor's
+/// [`TabKeyBehavior`] (tabs expand to `tab_width` spaces, or runs of `tab_width` spaces become
+/// tabs), so providers can return one `'\t'` per indent level.
+pub trait IndentProvider: Send {
+    /// Leading whitespace for a new line inserted at `cursor_offset`.
+    fn indent_for_new_line(&self, line_index: &LineIndex, cursor_offset: usize) -> Option<String>;
+}
+
+/// Controls how a multi-selection set is merged when it is normalized.
+///
+/// Normalization always sorts selections by position. The policy decides which neighbouring
+/// selections collapse into one:
+/// - `merge_overlapping`: ranges that share at least one character (or a caret strictly inside a
+///   range) are merged into their union.
+/// - `merge_touching`: selections where one ends exactly where the next starts are merged.
+/// - `merge_carets_at_same_position`: duplicate carets are collapsed into one.
+///
+/// Edit commands always merge overlapping ranges and duplicate carets before editing, since a
+/// character can only be edited once; the policy still applies to the resulting caret set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectionMergePolicy {
+    /// Merge selections whose ranges overlap.
+    pub merge_overlapping: bool,
+    /// Merge selections that touch end-to-start without overlapping.
+    pub merge_touching: bool,
+    /// Merge carets (empty selections) located at the same position.
+    pub merge_carets_at_same_position: bool,
+}
+
+impl Default for SelectionMergePolicy {
+    fn default() -> Self {
+        Self {
+            merge_overlapping: true,
+            merge_touching: false,
+            merge_carets_at_same_position: true,
+        }
+    }
+}
+
+impl SelectionMergePolicy {
+    /// Policy that merges overlapping, touching, and duplicate selections.
+    pub fn aggressive() -> Self {
+        Self {
+            merge_overlapping: true,
+            merge_touching: true,
+            merge_carets_at_same_position: true,
+        }
+    }
+
+    /// Policy used to prepare a selection set for editing (overlaps and duplicates are merged).
+    fn for_edit(self) -> Self {
+        Self {
+            merge_overlapping: true,
+            merge_carets_at_same_position: true,
+            ..self
+        }
+    }
+}
+
 /// A simple document text edit (character offsets, half-open).
 ///
 /// This is commonly used for applying a batch of "simultaneous" edits (e.g. rename, refactor, or
@@ -161,6 +237,48 @@ pub enum EditCommand {
         /// Text to insert/replace at each selection/caret.
         text: String,
     },
+    /// Paste and adjust indentation: like [`EditCommand::InsertText`], but a multi-line `text`
+    /// is re-indented to the line of each caret, in one undo step.
+    ///
+    /// The common leading indentation (in columns) of the pasted lines is stripped and each line
+    /// after the first gets the caret line's indentation instead, keeping its indentation relative
+    /// to the others; indentation is rebuilt in the current [`TabKeyBehavior`] and
+    /// whitespace-only lines become empty.
+    ///
+    /// The first line is inserted at the caret, keeping the caret's column. It counts towards
+    /// the common indentation only if it starts with whitespace (a copy that began at the start
+    /// of a line); otherwise it is assumed to start mid-line and is inserted as-is.
+    InsertTextReindented {
+        /// Text to insert/replace at each selection/caret.
+        text: String,
+    },
+    /// Like [`EditCommand::InsertText`], with auto-closing pairs (brackets, quotes).
+    ///
+    /// Only single-character `text` that is an opening or closing char of `pairs` is special;
+    /// anything else behaves exactly like `InsertText`. Each caret/selection is handled
+    /// independently, in one undo step:
+    /// - a non-empty selection is wrapped in the pair (and stays selected) when `text` opens it;
+    /// - typing a closing char right before the same char moves over it instead of inserting;
+    /// - typing an opening char inserts the pair with the caret in between, unless the next char
+    ///   is a word char (or, for pairs whose open and close chars are equal such as quotes, the
+    ///   previous char is).
+    InsertTextWithAutoPairs {
+        /// Text to insert/replace at each selection/caret.
+        text: String,
+        /// `(open, close)` character pairs, e.g. `('(', ')')` or `('"', '"')`.
+        pairs: Vec<(char, char)>,
+    },
+    /// Insert a snippet template (see [`crate::snippets`]) at each caret (or replace each
+    /// selection), in one undo step.
+    ///
+    /// The first tabstop is selected at every caret (mirrored tabstops as extra carets) and a
+    /// [`SnippetSession`] starts, navigated with [`CursorCommand::NextTabstop`] /
+    /// [`CursorCommand::PrevTabstop`]. A snippet without tabstops just leaves the carets at `$0`
+    /// (or after the inserted text).
+    InsertSnippet {
+        /// Snippet template, e.g. `"fn ${1:name}($2) {\n\t$0\n}"`.
+        template: String,
+    },
     /// Insert a tab at each caret (or replace each selection), using the current tab settings.
     ///
     /// - If `TabKeyBehavior::Tab`, inserts `'\t'`.
@@ -168,8 +286,9 @@ pub enum EditCommand {
     InsertTab,
     /// Insert a newline at each caret (or replace each selection).
     ///
-    /// If `auto_indent` is true, the inserted newline is followed by the leading whitespace
-    /// prefix of the current logical line.
+    /// If `auto_indent` is true, the inserted newline is followed by the indentation from the
+    /// executor's [`IndentProvider`], or else by the leading whitespace prefix of the current
+    /// logical line.
     InsertNewline {
         /// Whether to auto-indent the new line.
         auto_indent: bool,
@@ -206,8 +325,32 @@ pub enum EditCommand {
     ///
     /// This is a convenience alias for [`EditCommand::InsertNewline`] with `auto_indent: false`.
     SplitLine,
+    /// Insert a copy of each non-empty selection right after it and select the copy.
+    ///
+    /// Empty selections duplicate their line instead (as [`EditCommand::DuplicateLines`]) and
+    /// move to the copy. One undo step.
+    DuplicateSelection,
+    /// Swap the grapheme clusters on both sides of each caret (Emacs `C-t`) and move the caret
+    /// past them.
+    ///
+    /// At the start of a line the first two clusters are swapped, at the end of a line the last
+    /// two (the caret stays at the end); lines with fewer than two clusters are left alone.
+    /// Selections act at their caret (active end) and collapse to carets. One undo step.
+    TransposeChars,
+    /// Swap the word before each caret with the word after it (Emacs `M-t`) and move the caret
+    /// past the second word.
+    ///
+    /// Words are the UAX #29 word segments containing an alphanumeric `char` (the boundaries
+    /// [`CursorCommand::MoveWordLeft`] / [`CursorCommand::MoveWordRight`] stop at); the text
+    /// between the two words is kept. A caret inside a word swaps it with the next word; before
+    /// the first or after the last word of a line, the first or last two words are swapped.
+    /// Lines with fewer than two words are left alone. One undo step.
+    TransposeWords,
     /// Toggle comments for the selected line(s) or selection ranges, using a language-provided
     /// comment configuration.
+    ///
+    /// An empty `config` (no tokens, e.g. `CommentConfig::default()`) falls back to the comment
+    /// tokens of the executor's [`LanguageConfig`].
     ToggleComment {
         /// Comment tokens/config for the current language (data-driven).
         config: CommentConfig,
@@ -220,6 +363,17 @@ pub enum EditCommand {
         /// The edit list (character offsets, half-open).
         edits: Vec<TextEditSpec>,
     },
+    /// Convert the leading whitespace of every line from `from` to `to` indentation.
+    ///
+    /// - Leading whitespace is split into indent levels (`from.width` columns each) plus an
+    ///   alignment remainder, which is kept as spaces.
+    /// - Treated as a single undoable edit (no-op if nothing changes).
+    ReindentDocument {
+        /// Indentation the document currently uses.
+        from: IndentSettings,
+        /// Indentation to convert to.
+        to: IndentSettings,
+    },
     /// Smart backspace: if the caret is in leading whitespace, delete back to the previous tab stop.
     ///
     /// Otherwise, behaves like [`EditCommand::Backspace`].
@@ -234,6 +388,13 @@ pub enum EditCommand {
     DeleteWordForward,
     /// Backspace-like deletion: delete selection(s) if any, otherwise delete 1 char before each caret.
     Backspace,
+    /// Like [`EditCommand::Backspace`], but a caret between an empty pair (e.g. `(|)`) deletes
+    /// both characters.
+    DeletePairBackspace {
+        /// `(open, close)` character pairs, usually the same list as for
+        /// [`EditCommand::InsertTextWithAutoPairs`].
+        pairs: Vec<(char, char)>,
+    },
     /// Delete key-like deletion: delete selection(s) if any, otherwise delete 1 char after each caret.
     DeleteForward,
     /// Undo last edit operation (supports grouping)
@@ -246,6 +407,8 @@ pub enum EditCommand {
     ///
     /// - Honors `options` (case sensitivity / whole-word / regex).
     /// - Treated as a single undoable edit.
+    /// - In regex mode, `replacement` may reference captures (`$1`, `${name}`; `$$` for a
+    ///   literal `$`), expanded per match. Non-participating groups expand to nothing.
     ReplaceCurrent {
         /// Search query.
         query: String,
@@ -258,6 +421,8 @@ pub enum EditCommand {
     ///
     /// - Honors `options` (case sensitivity / whole-word / regex).
     /// - Treated as a single undoable edit.
+    /// - In regex mode, `replacement` may reference captures (`$1`, `${name}`; `$$` for a
+    ///   literal `$`), expanded per match. Non-participating groups expand to nothing.
     ReplaceAll {
         /// Search query.
         query: String,
@@ -266,6 +431,21 @@ pub enum EditCommand {
         /// Search options (case sensitivity, whole-word, regex).
         options: SearchOptions,
     },
+    /// Replace all occurrences of `query` inside a char-offset range ("Replace All in Selection").
+    ///
+    /// - Same matching/replacement rules as [`EditCommand::ReplaceAll`], but matches must lie
+    ///   entirely within the range; whole-word checks look at the characters just outside it.
+    /// - Afterwards the primary selection covers the scope with its new length.
+    ReplaceAllInRange {
+        /// Search query.
+        query: String,
+        /// Replacement text.
+        replacement: String,
+        /// Search options (case sensitivity, whole-word, regex).
+        options: SearchOptions,
+        /// Scope as `(start, end)` char offsets; `None` uses the primary selection.
+        range: Option<(usize, usize)>,
+    },
 }
 
 /// Cursor & selection commands
@@ -383,6 +563,70 @@ pub enum CursorCommand {
         /// Search options (case sensitivity, whole-word, regex).
         options: SearchOptions,
     },
+    /// Find the next occurrence of `query` inside a char-offset range and select it.
+    ///
+    /// Searches forward from the caret (or the range start, if the caret is before it); matches
+    /// follow the same scope rules as [`EditCommand::ReplaceAllInRange`].
+    FindNextInRange {
+        /// Search query.
+        query: String,
+        /// Search options (case sensitivity, whole-word, regex).
+        options: SearchOptions,
+        /// Scope as `(start, end)` char offsets; `None` uses the primary selection. Repeating
+        /// the command with `None` while its last match is selected keeps the original scope
+        /// and moves to the next match.
+        range: Option<(usize, usize)>,
+    },
+    /// Preview the nearest match of `query` at or after `from_offset` (search as you type).
+    ///
+    /// Unlike [`CursorCommand::FindNext`], the search starts from a fixed anchor instead of the
+    /// caret: a host keeps the caret it had when the search began as the origin and reruns this
+    /// on every keystroke. Cancelling (e.g. Escape) means restoring the selection it saved.
+    ///
+    /// - A match starting exactly at `from_offset` is found, so typing more of a query keeps
+    ///   the current match while it still matches.
+    /// - Without a match at or after `from_offset`, the search wraps around to the first match
+    ///   in the document (the result then starts before `from_offset`).
+    /// - The match becomes the primary selection (secondary selections are cleared) and
+    ///   [`CommandResult::SearchMatch`] is returned.
+    /// - For an empty query, or when nothing matches, the selection collapses to a caret at
+    ///   `from_offset` (clearing the previous preview) and [`CommandResult::SearchNotFound`] is
+    ///   returned. Neither is an error.
+    IncrementalFind {
+        /// Search query.
+        query: String,
+        /// Search options (case sensitivity, whole-word, regex).
+        options: SearchOptions,
+        /// Search origin as a char offset.
+        from_offset: usize,
+    },
+    /// Move the caret to the start of the next diagnostic (wrapping around the document).
+    ///
+    /// - Only diagnostics at or above `min_severity` are considered (missing severity counts as
+    ///   an error).
+    /// - Diagnostics sharing a start offset form a single stop.
+    /// - Returns [`CommandResult::Position`], or [`CommandResult::SearchNotFound`] if no
+    ///   diagnostic qualifies.
+    GoToNextDiagnostic {
+        /// Lowest severity to stop at.
+        min_severity: DiagnosticSeverity,
+    },
+    /// Move the caret to the start of the previous diagnostic (wrapping around the document).
+    ///
+    /// Same rules as [`CursorCommand::GoToNextDiagnostic`].
+    GoToPrevDiagnostic {
+        /// Lowest severity to stop at.
+        min_severity: DiagnosticSeverity,
+    },
+    /// Select the next tabstop of the active snippet session (all of its occurrences).
+    ///
+    /// Reaching `$0` places the carets there and ends the session. Fails if no snippet session
+    /// is active.
+    NextTabstop,
+    /// Select the previous tabstop of the active snippet session (stays on the first one).
+    ///
+    /// Fails if no snippet session is active.
+    PrevTabstop,
 }
 
 /// View commands
@@ -413,6 +657,12 @@ pub enum ViewCommand {
         /// Tab key behavior.
         behavior: TabKeyBehavior,
     },
+    /// Apply indentation settings (e.g. from [`detect_indentation`](crate::indent::detect_indentation)):
+    /// sets the tab width and [`TabKeyBehavior`] in one step.
+    ApplyIndentSettings {
+        /// Settings to apply (`width` must be greater than 0).
+        settings: IndentSettings,
+    },
     /// Scroll to specified line
     ScrollTo {
         /// Logical line index to scroll to.
@@ -448,6 +698,18 @@ pub enum StyleCommand {
         /// Style identifier.
         style_id: StyleId,
     },
+    /// Remove every style of a style layer inside a character range.
+    ///
+    /// Intervals straddling a boundary are clipped and intervals spanning the range are split
+    /// (see [`IntervalTree::remove_range`]).
+    ClearStyleRange {
+        /// The style layer to clear.
+        layer: StyleLayerId,
+        /// Range start offset in characters.
+        start: usize,
+        /// Range end offset in characters (exclusive).
+        end: usize,
+    },
     /// Fold code block
     Fold {
         /// Start logical line (inclusive).
@@ -567,6 +829,14 @@ enum TextBoundary {
     Word,
 }
 
+/// The most common style of `counts` (`(style, count)` pairs); ties go to the lower `StyleId`.
+fn dominant_style(counts: impl IntoIterator<Item = (StyleId, usize)>) -> Option<StyleId> {
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(style, _)| style)
+}
+
 fn byte_offset_for_char_column(text: &str, column: usize) -> usize {
     if column == 0 {
         return 0;
@@ -634,6 +904,268 @@ fn next_boundary_column(text: &str, column: usize, boundary: TextBoundary) -> us
     char_column_for_byte_offset(text, next)
 }
 
+/// Column ranges of the grapheme clusters [`EditCommand::TransposeChars`] swaps for a caret at
+/// `column` of `text`.
+fn transpose_chars_ranges(text: &str, column: usize) -> Option<(Range<usize>, Range<usize>)> {
+    let mut bounds = vec![0usize];
+    for grapheme in text.graphemes(true) {
+        bounds.push(bounds[bounds.len() - 1] + grapheme.chars().count());
+    }
+    let clusters = bounds.len() - 1;
+    if clusters < 2 {
+        return None;
+    }
+    // The boundary at (or before) the caret, moved inside the line's first/last pair.
+    let at = bounds.iter().filter(|&&bound| bound <= column).count() - 1;
+    let mid = at.clamp(1, clusters - 1);
+    Some((bounds[mid - 1]..bounds[mid], bounds[mid]..bounds[mid + 1]))
+}
+
+/// Column ranges of the words [`EditCommand::TransposeWords`] swaps for a caret at `column` of
+/// `text`.
+fn transpose_words_ranges(text: &str, column: usize) -> Option<(Range<usize>, Range<usize>)> {
+    let mut words: Vec<Range<usize>> = Vec::new();
+    let mut col = 0usize;
+    for segment in text.split_word_bounds() {
+        let len = segment.chars().count();
+        if segment.chars().any(char::is_alphanumeric) {
+            words.push(col..col + len);
+        }
+        col += len;
+    }
+    if words.len() < 2 {
+        return None;
+    }
+    let right = match words.iter().position(|word| word.end > column) {
+        // The caret is inside this word: swap it with the next one.
+        Some(idx) if words[idx].start < column => idx + 1,
+        Some(idx) => idx,
+        None => words.len(),
+    }
+    .clamp(1, words.len() - 1);
+    Some((words[right - 1].clone(), words[right].clone()))
+}
+
+/// Decides which document characters of a snapshot get substitute glyphs (see
+/// [`SnapshotOptions`]).
+struct RenderHints<'a> {
+    editor: &'a EditorCore,
+    options: SnapshotOptions,
+    /// Non-empty selection ranges, for [`WhitespaceMode::Selection`].
+    selected: Vec<SearchMatch>,
+    /// The last line looked up for [`WhitespaceMode::Boundary`]: (line start offset, chars).
+    line: Option<(usize, Vec<char>)>,
+}
+
+impl<'a> RenderHints<'a> {
+    fn new(editor: &'a EditorCore, options: SnapshotOptions) -> Self {
+        let selected = if options.show_whitespace == WhitespaceMode::Selection {
+            editor
+                .selection
+                .iter()
+                .chain(editor.secondary_selections.iter())
+                .filter(|selection| selection.start != selection.end)
+                .map(|selection| {
+                    let (min, max) = crate::selection_set::selection_min_max(selection);
+                    SearchMatch {
+                        start: editor
+                            .line_index
+                            .position_to_char_offset(min.line, min.column),
+                        end: editor
+                            .line_index
+                            .position_to_char_offset(max.line, max.column),
+                    }
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            editor,
+            options,
+            selected,
+            line: None,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.options.show_whitespace != WhitespaceMode::None || self.options.show_control_chars
+    }
+
+    /// Substitute glyph for the single-`char` cluster `ch` at document offset `offset`.
+    fn glyph(&mut self, ch: char, offset: usize) -> Option<char> {
+        if self.options.show_control_chars
+            && let Some(glyph) = control_glyph(ch)
+        {
+            return Some(glyph);
+        }
+        let glyph = whitespace_glyph(ch)?;
+        let show = match self.options.show_whitespace {
+            WhitespaceMode::None => false,
+            WhitespaceMode::All => true,
+            WhitespaceMode::Boundary => ch != ' ' || !self.is_inner_single_space(offset),
+            WhitespaceMode::Selection => self
+                .selected
+                .iter()
+                .any(|range| range.start <= offset && offset < range.end),
+        };
+        show.then_some(glyph)
+    }
+
+    /// Whether the space at `offset` sits between two non-whitespace characters of its line.
+    fn is_inner_single_space(&mut self, offset: usize) -> bool {
+        let (line, column) = self.editor.line_index.char_offset_to_position(offset);
+        let line_start = offset - column;
+        if self
+            .line
+            .as_ref()
+            .is_none_or(|(start, _)| *start != line_start)
+        {
+            let text = self
+                .editor
+                .line_index
+                .get_line_text(line)
+                .unwrap_or_default();
+            self.line = Some((line_start, text.chars().collect()));
+        }
+        let Some((_, chars)) = &self.line else {
+            return false;
+        };
+        let is_word = |c: Option<&char>| c.is_some_and(|c| !c.is_whitespace());
+        column > 0 && is_word(chars.get(column - 1)) && is_word(chars.get(column + 1))
+    }
+
+    /// Applies the hints to one cell, expanding a shown tab into an arrow plus padding cells.
+    fn push_cell(&mut self, cells: &mut Vec<Cell>, mut cell: Cell) {
+        let CellSource::Document { offset, .. } = cell.source else {
+            cells.push(cell);
+            return;
+        };
+        let glyph = if cell.combining.is_empty() {
+            self.glyph(cell.ch, offset)
+        } else {
+            None
+        };
+        let Some(glyph) = glyph else {
+            cells.push(cell);
+            return;
+        };
+        let padding = if cell.ch == '\t' {
+            cell.width.saturating_sub(1)
+        } else {
+            0
+        };
+        cell.ch = glyph;
+        cell.width -= padding;
+        cell.styles.push(crate::WHITESPACE_STYLE_ID);
+        let pad = Cell {
+            ch: ' ',
+            width: 1,
+            ..cell.clone()
+        };
+        cells.push(cell);
+        cells.extend(std::iter::repeat_n(pad, padding));
+    }
+
+    /// Composed-grid counterpart of [`Self::push_cell`].
+    fn push_composed_cell(&mut self, cells: &mut Vec<ComposedCell>, mut cell: ComposedCell) {
+        let ComposedCellSource::Document { offset } = cell.source else {
+            cells.push(cell);
+            return;
+        };
+        // Composed cells are one per `char`: a ZWNJ/ZWJ right after another document `char` is
+        // part of that `char`'s grapheme cluster, which styled grids keep in `combining`.
+        let joins_previous = matches!(cell.ch, '\u{200c}' | '\u{200d}')
+            && cells.last().is_some_and(|prev| {
+                prev.source
+                    == ComposedCellSource::Document {
+                        offset: offset.wrapping_sub(1),
+                    }
+            });
+        let glyph = if joins_previous {
+            None
+        } else {
+            self.glyph(cell.ch, offset)
+        };
+        let Some(glyph) = glyph else {
+            cells.push(cell);
+            return;
+        };
+        let padding = if cell.ch == '\t' {
+            cell.width.saturating_sub(1)
+        } else {
+            0
+        };
+        cell.ch = glyph;
+        cell.width -= padding;
+        cell.styles.push(crate::WHITESPACE_STYLE_ID);
+        let pad = ComposedCell {
+            ch: ' ',
+            width: 1,
+            ..cell.clone()
+        };
+        cells.push(cell);
+        cells.extend(std::iter::repeat_n(pad, padding));
+    }
+}
+
+/// Collects the cells of one visual row that intersect a horizontal window.
+///
+/// Cells are offered left to right with their width; only intersecting ones are materialized.
+/// A cell cut by either window edge (a wide character or a tab) becomes a blank padding cell
+/// covering its visible part, so the row's cells start at the window's left edge.
+struct RowClip {
+    x: usize,
+    start: usize,
+    end: usize,
+    first_char_offset: Option<usize>,
+}
+
+impl RowClip {
+    fn new(start: usize, width: usize) -> Self {
+        Self {
+            x: 0,
+            start,
+            end: start.saturating_add(width),
+            first_char_offset: None,
+        }
+    }
+
+    fn unbounded() -> Self {
+        Self::new(0, usize::MAX)
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.start > 0 || self.end < usize::MAX
+    }
+
+    /// Offer a cell of `width` cells; `make` is only called when it intersects the window.
+    fn push(&mut self, line: &mut HeadlessLine, width: usize, make: impl FnOnce() -> Option<Cell>) {
+        let (x, end) = (self.x, self.x.saturating_add(width));
+        self.x = end;
+        let visible = if width == 0 {
+            (self.start..self.end).contains(&x)
+        } else {
+            x < self.end && end > self.start
+        };
+        if !visible {
+            return;
+        }
+        let Some(mut cell) = make() else {
+            return;
+        };
+        if let CellSource::Document { offset, .. } = cell.source {
+            self.first_char_offset.get_or_insert(offset);
+        }
+        if x < self.start || end > self.end {
+            cell.ch = ' ';
+            cell.combining.clear();
+            cell.width = end.min(self.end) - x.max(self.start);
+        }
+        line.add_cell(cell);
+    }
+}
+
 #[derive(Debug, Clone)]
 struct TextEdit {
     start_before: usize,
@@ -868,6 +1400,8 @@ pub struct EditorCore {
     pub interval_tree: IntervalTree,
     /// Layered styles (for semantic highlighting/simple syntax highlighting, etc.)
     pub style_layers: BTreeMap<StyleLayerId, IntervalTree>,
+    /// Per-layer priority overrides (see [`StyleLayerId::default_priority`]).
+    pub style_layer_priorities: BTreeMap<StyleLayerId, StylePriority>,
     /// Derived diagnostics for this document (character-offset ranges + metadata).
     pub diagnostics: Vec<Diagnostic>,
     /// Derived decorations for this document (virtual text, links, etc.).
@@ -890,11 +1424,11 @@ pub struct EditorCore {
 impl EditorCore {
     /// Create a new Editor Core
     pub fn new(text: &str, viewport_width: usize) -> Self {
+        let line_index = LineIndex::from_text_preserving_line_endings(text);
         let normalized = crate::text::normalize_crlf_to_lf(text);
         let text = normalized.as_ref();
 
         let piece_table = PieceTable::new(text);
-        let line_index = LineIndex::from_text(text);
         let mut layout_engine = LayoutEngine::new(viewport_width);
 
         // Initialize layout engine to be consistent with initial text (including trailing empty line).
@@ -908,6 +1442,7 @@ impl EditorCore {
             layout_engine,
             interval_tree: IntervalTree::new(),
             style_layers: BTreeMap::new(),
+            style_layer_priorities: BTreeMap::new(),
             diagnostics: Vec::new(),
             decorations: BTreeMap::new(),
             document_symbols: crate::DocumentOutline::default(),
@@ -968,6 +1503,16 @@ impl EditorCore {
             .unwrap_or(&[])
     }
 
+    /// Recompute collapsed fold summaries from the folding manager's placeholder template.
+    ///
+    /// The executor does this after folding commands and edits; call it after collapsing
+    /// regions directly through [`FoldingManager`].
+    pub fn refresh_fold_summaries(&mut self) {
+        let line_index = &self.line_index;
+        self.folding_manager
+            .refresh_summaries(|line| line_index.get_line_text(line));
+    }
+
     /// Invalidate cached visual-row index (wrap/folding derived mapping).
     pub fn invalidate_visual_row_index_cache(&mut self) {
         *self.visual_row_index_cache.borrow_mut() = None;
@@ -1019,11 +1564,74 @@ impl EditorCore {
     /// Get styled headless grid snapshot (by visual line).
     ///
     /// - Supportsoft wrapping (based `layout_engine`)
-    /// - `Cell.styles` will `interval_tree` + `style_layers` merged from
+    /// - `Cell.styles` will `interval_tree` + `style_layers` merged from (one [`Self::style_runs`]
+    ///   walk per row)
     /// - Supportcode folding (based `folding_manager`)
+    /// - `HeadlessLine.background_style` comes from `DecorationKind::LineBackground` decorations
+    /// - `Cell.source` gives each cell's document offset and logical column (or marks wrap-indent
+    ///   and fold placeholder cells), for hit-testing without recomputing layout
     ///
     /// Note: This API is not responsible for mapping `StyleId` to specific colors.
     pub fn get_headless_grid_styled(&self, start_visual_row: usize, count: usize) -> HeadlessGrid {
+        self.headless_grid_styled(start_visual_row, count, RowClip::unbounded)
+    }
+
+    /// Get a styled snapshot clipped to a horizontal window (by visual line).
+    ///
+    /// Like [`Self::get_headless_grid_styled`], but each row only contains the cells
+    /// intersecting visual columns `x_start_cells..x_start_cells + width_cells`; this keeps
+    /// snapshots of very long lines (e.g. with [`WrapMode::None`]) bounded by the window width.
+    ///
+    /// A wide character or tab cut by a window edge is emitted as a blank padding cell covering
+    /// its visible part (keeping its styles and [`CellSource`]), so `cells` always start at the
+    /// window's left edge. [`HeadlessLine::clip_first_char_offset`] reports the first document
+    /// character in the window.
+    pub fn get_headless_grid_styled_clipped(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+        x_start_cells: usize,
+        width_cells: usize,
+    ) -> HeadlessGrid {
+        self.headless_grid_styled(start_visual_row, count, || {
+            RowClip::new(x_start_cells, width_cells)
+        })
+    }
+
+    /// Get a styled snapshot with rendering hints (by visual line).
+    ///
+    /// Like [`Self::get_headless_grid_styled`], but whitespace and control characters selected by
+    /// `options` are replaced with substitute glyphs styled [`crate::WHITESPACE_STYLE_ID`] (see
+    /// [`SnapshotOptions`]). Cell widths are unchanged: a tab becomes an arrow cell followed by
+    /// blank cells covering the rest of its width, all with the tab's [`CellSource`].
+    ///
+    /// [`WhitespaceMode::Selection`] uses this editor's current selections.
+    pub fn get_headless_grid_styled_with_options(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+        options: &SnapshotOptions,
+    ) -> HeadlessGrid {
+        let mut grid = self.get_headless_grid_styled(start_visual_row, count);
+        let mut hints = RenderHints::new(self, *options);
+        if !hints.is_active() {
+            return grid;
+        }
+        for line in &mut grid.lines {
+            let cells = std::mem::take(&mut line.cells);
+            for cell in cells {
+                hints.push_cell(&mut line.cells, cell);
+            }
+        }
+        grid
+    }
+
+    fn headless_grid_styled(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+        row_clip: impl Fn() -> RowClip,
+    ) -> HeadlessGrid {
         self.with_visual_row_index(|index| {
             let mut grid = HeadlessGrid::new(start_visual_row, count);
             if count == 0 {
@@ -1038,6 +1646,7 @@ impl EditorCore {
             let tab_width = self.layout_engine.tab_width();
             let end_visual = start_visual_row.saturating_add(count).min(total_visual);
             let regions = self.folding_manager.regions();
+            let line_backgrounds = self.line_background_by_line();
 
             let Some(mut span_idx) = index.span_index_for_visual_row(start_visual_row) else {
                 return grid;
@@ -1085,6 +1694,15 @@ impl EditorCore {
                 };
 
                 let mut headless_line = HeadlessLine::new(logical_line, visual_in_line > 0);
+                headless_line.background_style = line_backgrounds.get(&logical_line).copied();
+                let direction = line_direction(&line_text);
+                // Right-to-left cells are reordered after the fact, so clip them afterwards.
+                let window = row_clip();
+                let mut clip = if direction == crate::TextDirection::Rtl && window.is_bounded() {
+                    RowClip::unbounded()
+                } else {
+                    row_clip()
+                };
                 let mut segment_x_start_cells = 0usize;
                 if visual_in_line > 0 {
                     let indent_cells = wrap_indent_cells_for_line_text(
@@ -1095,23 +1713,60 @@ impl EditorCore {
                     );
                     segment_x_start_cells = indent_cells;
                     for _ in 0..indent_cells {
-                        headless_line.add_cell(Cell::new(' ', 1));
+                        clip.push(&mut headless_line, 1, || {
+                            Some(Cell::new(' ', 1).with_source(CellSource::WrapIndent))
+                        });
                     }
                 }
                 let mut x_in_line = visual_x_for_column(&line_text, segment_start_col, tab_width);
 
-                for (col, ch) in line_text
-                    .chars()
-                    .enumerate()
-                    .skip(segment_start_col)
-                    .take(segment_end_col.saturating_sub(segment_start_col))
+                // Widths first, so that only graphemes intersecting the window are styled.
+                let mut graphemes: Vec<(usize, &str, usize, usize)> = Vec::new();
+                let mut clip_x = clip.x;
+                for (col, _, grapheme) in grapheme_columns(&line_text)
+                    .skip_while(|(col, _, _)| *col < segment_start_col)
+                    .take_while(|(col, _, _)| *col < segment_end_col)
                 {
-                    let offset = line_start_offset + col;
-                    let styles = self.styles_at_offset(offset);
-                    let w = cell_width_at(ch, x_in_line, tab_width);
+                    if clip_x >= clip.end {
+                        break;
+                    }
+                    let w = grapheme_width_at(grapheme, x_in_line, tab_width);
+                    if clip_x.saturating_add(w) > clip.start || w == 0 {
+                        graphemes.push((col, grapheme, w, clip_x));
+                    }
                     x_in_line = x_in_line.saturating_add(w);
-                    headless_line.add_cell(Cell::with_styles(ch, w, styles));
+                    clip_x = clip_x.saturating_add(w);
+                }
+                let runs = match (graphemes.first(), graphemes.last()) {
+                    (Some((first, ..)), Some((last, grapheme, ..))) => self.style_runs(
+                        line_start_offset + first,
+                        line_start_offset + last + grapheme.chars().count(),
+                    ),
+                    _ => Vec::new(),
+                };
+                let mut run_idx = 0usize;
+
+                // One cell per grapheme cluster, styled by its first `char`.
+                for (col, grapheme, w, x) in graphemes {
+                    let offset = line_start_offset + col;
+                    while runs.get(run_idx).is_some_and(|run| run.end <= offset) {
+                        run_idx += 1;
+                    }
+                    clip.x = x;
+                    clip.push(&mut headless_line, w, || {
+                        let styles = runs
+                            .get(run_idx)
+                            .map(|run| run.styles.clone())
+                            .unwrap_or_default();
+                        Cell::from_grapheme(grapheme, w, styles).map(|cell| {
+                            cell.with_source(CellSource::Document {
+                                offset,
+                                column: col,
+                            })
+                        })
+                    });
                 }
+                clip.x = clip.x.max(clip_x);
 
                 headless_line.set_visual_metadata(
                     visual_in_line,
@@ -1119,33 +1774,52 @@ impl EditorCore {
                     line_start_offset.saturating_add(segment_end_col),
                     segment_x_start_cells,
                 );
+                headless_line.set_direction(direction);
                 headless_line.set_fold_placeholder_appended(false);
 
                 // For collapsed folding start line, append placeholder to the last segment.
                 if visual_in_line + 1 == layout.visual_line_count
                     && let Some(region) = Self::collapsed_region_starting_at(regions, logical_line)
-                    && !region.placeholder.is_empty()
+                    && !region.display_text().is_empty()
                 {
-                    if !headless_line.cells.is_empty() {
+                    if segment_x_start_cells > 0 || segment_end_col > segment_start_col {
                         x_in_line = x_in_line.saturating_add(char_width(' '));
-                        headless_line.add_cell(Cell::with_styles(
-                            ' ',
-                            char_width(' '),
-                            vec![FOLD_PLACEHOLDER_STYLE_ID],
-                        ));
+                        clip.push(&mut headless_line, char_width(' '), || {
+                            Some(
+                                Cell::with_styles(
+                                    ' ',
+                                    char_width(' '),
+                                    vec![FOLD_PLACEHOLDER_STYLE_ID],
+                                )
+                                .with_source(CellSource::FoldPlaceholder),
+                            )
+                        });
                     }
-                    for ch in region.placeholder.chars() {
+                    for ch in region.display_text().chars() {
                         let w = cell_width_at(ch, x_in_line, tab_width);
                         x_in_line = x_in_line.saturating_add(w);
-                        headless_line.add_cell(Cell::with_styles(
-                            ch,
-                            w,
-                            vec![FOLD_PLACEHOLDER_STYLE_ID],
-                        ));
+                        clip.push(&mut headless_line, w, || {
+                            Some(
+                                Cell::with_styles(ch, w, vec![FOLD_PLACEHOLDER_STYLE_ID])
+                                    .with_source(CellSource::FoldPlaceholder),
+                            )
+                        });
                     }
                     headless_line.set_fold_placeholder_appended(true);
                 }
 
+                if window.is_bounded() {
+                    if !clip.is_bounded() {
+                        // Re-clip the display-ordered cells of a right-to-left row.
+                        clip = window;
+                        for cell in std::mem::take(&mut headless_line.cells) {
+                            clip.push(&mut headless_line, cell.width, || Some(cell));
+                        }
+                    }
+                    headless_line.clip_x_start_cells = clip.start;
+                    headless_line.clip_first_char_offset = clip.first_char_offset;
+                }
+
                 grid.add_line(headless_line);
                 current_visual = current_visual.saturating_add(1);
                 visual_in_line = visual_in_line.saturating_add(1);
@@ -1236,17 +1910,18 @@ impl EditorCore {
                     );
                     total_cells = total_cells.saturating_add(indent_cells);
                 }
-                let mut x_in_line = visual_x_for_column(&line_text, segment_start_col, tab_width);
+                let widths = grapheme_cell_widths(&line_text, tab_width);
+                let mut x_in_line = widths.iter().take(segment_start_col).sum::<usize>();
 
-                for (col, ch) in line_text
+                for ((col, ch), &w) in line_text
                     .chars()
                     .enumerate()
+                    .zip(&widths)
                     .skip(segment_start_col)
                     .take(segment_end_col.saturating_sub(segment_start_col))
                 {
                     let offset = line_start_offset + col;
                     let styles = self.styles_at_offset(offset);
-                    let w = cell_width_at(ch, x_in_line, tab_width);
                     x_in_line = x_in_line.saturating_add(w);
                     total_cells = total_cells.saturating_add(w);
                     if !ch.is_whitespace() {
@@ -1261,13 +1936,13 @@ impl EditorCore {
                 let mut placeholder_appended = false;
                 if visual_in_line + 1 == layout.visual_line_count
                     && let Some(region) = Self::collapsed_region_starting_at(regions, logical_line)
-                    && !region.placeholder.is_empty()
+                    && !region.display_text().is_empty()
                 {
                     placeholder_appended = true;
                     if total_cells > 0 {
                         total_cells = total_cells.saturating_add(char_width(' '));
                     }
-                    for ch in region.placeholder.chars() {
+                    for ch in region.display_text().chars() {
                         let w = cell_width_at(ch, x_in_line, tab_width);
                         x_in_line = x_in_line.saturating_add(w);
                         total_cells = total_cells.saturating_add(w);
@@ -1309,42 +1984,424 @@ impl EditorCore {
         })
     }
 
-    /// Get a decoration-aware composed grid snapshot (by composed visual line).
-    ///
-    /// This is an **optional** snapshot path that injects:
-    /// - inline virtual text (`DecorationPlacement::{Before,After}`), e.g. inlay hints
-    /// - above-line virtual text (`DecorationPlacement::AboveLine`), e.g. code lens
+    /// Get a downscaled overview of the whole document (see [`MinimapData`]).
     ///
-    /// Notes:
-    /// - Wrapping is still computed from the underlying document text only.
-    /// - Virtual text can therefore extend past the viewport width; hosts may clip.
-    /// - Each [`ComposedCell`] carries its origin (`Document` vs `Virtual`) so hosts can map
-    ///   interactions back to document offsets without re-implementing layout.
-    pub fn get_headless_grid_composed(
-        &self,
-        start_visual_row: usize,
-        count: usize,
-    ) -> ComposedGrid {
-        let mut grid = ComposedGrid::new(start_visual_row, count);
-        if count == 0 {
-            return grid;
-        }
-
-        #[derive(Debug, Clone)]
-        struct VirtualText {
-            anchor: usize,
-            text: String,
-            styles: Vec<StyleId>,
-        }
+    /// Every `rows_per_cell` visible logical lines (at least 1) become one row of `cols`
+    /// columns. Only line text and style intervals are read (no layout or per-cell grid), so
+    /// this is cheap enough to recompute on scroll.
+    pub fn get_minimap(&self, rows_per_cell: usize, cols: usize) -> MinimapData {
+        let rows_per_cell = rows_per_cell.max(1);
+        let tab_width = self.layout_engine.tab_width();
+        let collapsed: Vec<&FoldRegion> = self
+            .folding_manager
+            .regions()
+            .iter()
+            .filter(|region| region.is_collapsed && region.end_line > region.start_line)
+            .collect();
 
-        // Collect virtual text decorations from all layers.
-        let mut inline_before: HashMap<usize, Vec<VirtualText>> = HashMap::new();
-        let mut inline_after: HashMap<usize, Vec<VirtualText>> = HashMap::new();
-        let mut above_by_line: BTreeMap<usize, Vec<VirtualText>> = BTreeMap::new();
+        let mut data = MinimapData {
+            rows_per_cell,
+            cols,
+            visible_lines: 0,
+            rows: Vec::new(),
+        };
+        let new_row = |start_line: usize| MinimapRow {
+            start_line,
+            end_line: start_line,
+            line_count: 0,
+            dominant_style: None,
+            indent: None,
+            cells: vec![MinimapCell::default(); cols],
+        };
+        let mut row: Option<MinimapRow> = None;
+        let mut row_styles: HashMap<StyleId, usize> = HashMap::new();
+        let mut cell_styles: Vec<Vec<(StyleId, usize)>> = vec![Vec::new(); cols];
+
+        let mut flush = |row: MinimapRow,
+                         row_styles: &mut HashMap<StyleId, usize>,
+                         cell_styles: &mut [Vec<(StyleId, usize)>]| {
+            let mut row = row;
+            row.dominant_style = dominant_style(row_styles.drain());
+            for (cell, styles) in row.cells.iter_mut().zip(cell_styles.iter_mut()) {
+                cell.style = dominant_style(styles.drain(..));
+            }
+            data.rows.push(row);
+        };
+
+        let mut next_region = 0usize;
+        let mut hidden_until: Option<usize> = None;
+        for line in 0..self.line_index.line_count() {
+            if hidden_until.is_some_and(|end| line <= end) {
+                continue;
+            }
+            while let Some(region) = collapsed.get(next_region)
+                && region.start_line <= line
+            {
+                if region.start_line == line {
+                    hidden_until = Some(hidden_until.unwrap_or(0).max(region.end_line));
+                }
+                next_region += 1;
+            }
+
+            let text = self.line_index.get_line_text(line).unwrap_or_default();
+            let line_start = self.line_index.position_to_char_offset(line, 0);
+            let line_end = line_start + text.chars().count();
+            let mut intervals = self.interval_tree.query_range(line_start, line_end);
+            for tree in self.style_layers.values() {
+                intervals.extend(tree.query_range(line_start, line_end));
+            }
+
+            let current = row.get_or_insert_with(|| new_row(line));
+            current.end_line = line;
+            current.line_count += 1;
+            let mut x = 0usize;
+            let mut line_indent = None;
+            let widths = grapheme_cell_widths(&text, tab_width);
+            for ((col, ch), &w) in text.chars().enumerate().zip(&widths) {
+                if !ch.is_whitespace() {
+                    line_indent.get_or_insert(x);
+                    let offset = line_start + col;
+                    let style = intervals
+                        .iter()
+                        .filter(|interval| interval.contains(offset))
+                        .map(|interval| interval.style_id)
+                        .min();
+                    if let Some(style) = style {
+                        *row_styles.entry(style).or_insert(0) += w;
+                    }
+                    let covered = x.min(cols)..x.saturating_add(w).min(cols);
+                    for (cell, styles) in current.cells[covered.clone()]
+                        .iter_mut()
+                        .zip(&mut cell_styles[covered])
+                    {
+                        cell.occupancy = cell.occupancy.saturating_add(1);
+                        if let Some(style) = style {
+                            match styles.iter_mut().find(|(s, _)| *s == style) {
+                                Some((_, count)) => *count += 1,
+                                None => styles.push((style, 1)),
+                            }
+                        }
+                    }
+                }
+                x = x.saturating_add(w);
+            }
+            if let Some(indent) = line_indent {
+                current.indent = Some(current.indent.map_or(indent, |i| i.min(indent)));
+            }
+
+            data.visible_lines += 1;
+            if current.line_count == rows_per_cell
+                && let Some(done) = row.take()
+            {
+                flush(done, &mut row_styles, &mut cell_styles);
+            }
+        }
+        if let Some(done) = row.take() {
+            flush(done, &mut row_styles, &mut cell_styles);
+        }
+
+        data
+    }
+
+    /// Get gutter metadata (by visual line).
+    ///
+    /// The result is aligned row-for-row with [`Self::get_headless_grid_styled`] for the same
+    /// `start_visual_row`/`count`: the first row of each logical line carries its line number,
+    /// fold marker, most severe diagnostic, and selection state; wrapped continuation rows are
+    /// empty apart from [`GutterLine::is_wrap_continuation`].
+    pub fn get_viewport_gutter(&self, start_visual_row: usize, count: usize) -> Vec<GutterLine> {
+        self.with_visual_row_index(|index| {
+            let mut gutter = Vec::new();
+            if count == 0 {
+                return gutter;
+            }
+
+            let total_visual = index.total_visual_lines();
+            if start_visual_row >= total_visual {
+                return gutter;
+            }
+
+            let end_visual = start_visual_row.saturating_add(count).min(total_visual);
+            let Some(mut span_idx) = index.span_index_for_visual_row(start_visual_row) else {
+                return gutter;
+            };
+            let mut visual_in_line =
+                start_visual_row.saturating_sub(index.spans[span_idx].start_visual_row);
+
+            let first_line = index.spans[span_idx].logical_line;
+            let last_line = index
+                .span_index_for_visual_row(end_visual.saturating_sub(1))
+                .map(|idx| index.spans[idx].logical_line)
+                .unwrap_or(first_line);
+            let severities = self.diagnostic_severity_by_line(first_line, last_line);
+            let selected = self.selected_lines(first_line, last_line);
+            let regions = self.folding_manager.regions();
+
+            let mut current_visual = start_visual_row;
+            while current_visual < end_visual && span_idx < index.spans.len() {
+                let span = index.spans[span_idx];
+                gutter.push(if visual_in_line == 0 {
+                    Self::gutter_line_for_logical(
+                        regions,
+                        &severities,
+                        &selected,
+                        span.logical_line,
+                    )
+                } else {
+                    GutterLine::wrap_continuation()
+                });
+
+                current_visual = current_visual.saturating_add(1);
+                visual_in_line = visual_in_line.saturating_add(1);
+                if visual_in_line >= span.visual_line_count {
+                    span_idx = span_idx.saturating_add(1);
+                    visual_in_line = 0;
+                }
+            }
+
+            gutter
+        })
+    }
+
+    /// Get gutter metadata aligned row-for-row with [`Self::get_headless_grid_composed`].
+    ///
+    /// Virtual rows (e.g. code lens above a line) produce empty gutter rows.
+    pub fn get_viewport_gutter_composed(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+    ) -> Vec<GutterLine> {
+        let grid = self.get_headless_grid_composed(start_visual_row, count);
+        let logical_lines = grid.lines.iter().filter_map(|line| match line.kind {
+            ComposedLineKind::Document { logical_line, .. } => Some(logical_line),
+            ComposedLineKind::VirtualAboveLine { .. } => None,
+        });
+        let first_line = logical_lines.clone().min().unwrap_or(0);
+        let last_line = logical_lines.max().unwrap_or(0);
+        let severities = self.diagnostic_severity_by_line(first_line, last_line);
+        let selected = self.selected_lines(first_line, last_line);
+        let regions = self.folding_manager.regions();
+
+        grid.lines
+            .iter()
+            .map(|line| match line.kind {
+                ComposedLineKind::Document {
+                    logical_line,
+                    visual_in_logical: 0,
+                } => Self::gutter_line_for_logical(regions, &severities, &selected, logical_line),
+                ComposedLineKind::Document { .. } => GutterLine::wrap_continuation(),
+                ComposedLineKind::VirtualAboveLine { .. } => GutterLine::empty(),
+            })
+            .collect()
+    }
+
+    fn gutter_line_for_logical(
+        regions: &[FoldRegion],
+        severities: &BTreeMap<usize, DiagnosticSeverity>,
+        selected: &BTreeSet<usize>,
+        logical_line: usize,
+    ) -> GutterLine {
+        let fold_state = if Self::collapsed_region_starting_at(regions, logical_line).is_some() {
+            GutterFoldState::Collapsed
+        } else if regions
+            .iter()
+            .any(|r| r.start_line == logical_line && r.end_line > logical_line)
+        {
+            GutterFoldState::Expanded
+        } else if regions
+            .iter()
+            .any(|r| !r.is_collapsed && r.start_line < logical_line && logical_line <= r.end_line)
+        {
+            GutterFoldState::Foldable
+        } else {
+            GutterFoldState::None
+        };
+
+        GutterLine {
+            logical_line: Some(logical_line),
+            fold_state,
+            diagnostic_severity: severities.get(&logical_line).copied(),
+            is_wrap_continuation: false,
+            is_selected: selected.contains(&logical_line),
+        }
+    }
+
+    /// Logical lines in `first_line..=last_line` touched by a caret or selection.
+    fn selected_lines(&self, first_line: usize, last_line: usize) -> BTreeSet<usize> {
+        let primary = self.selection.clone().unwrap_or(Selection {
+            start: self.cursor_position,
+            end: self.cursor_position,
+            direction: SelectionDirection::Forward,
+        });
+
+        let mut out = BTreeSet::new();
+        for selection in std::iter::once(&primary).chain(&self.secondary_selections) {
+            let (min, max) = crate::selection_set::selection_min_max(selection);
+            let last = if max.column == 0 && max.line > min.line {
+                max.line - 1
+            } else {
+                max.line
+            };
+            out.extend(min.line.max(first_line)..=last.min(last_line));
+        }
+        out
+    }
+
+    /// Most severe diagnostic per logical line, for lines in `first_line..=last_line`.
+    ///
+    /// Diagnostics without a severity are treated as errors (matching common LSP client behavior).
+    fn diagnostic_severity_by_line(
+        &self,
+        first_line: usize,
+        last_line: usize,
+    ) -> BTreeMap<usize, DiagnosticSeverity> {
+        let mut out: BTreeMap<usize, DiagnosticSeverity> = BTreeMap::new();
+        for diagnostic in &self.diagnostics {
+            let severity = diagnostic.severity.unwrap_or(DiagnosticSeverity::Error);
+            let start = diagnostic.range.start.min(diagnostic.range.end);
+            let end = diagnostic.range.start.max(diagnostic.range.end);
+            let start_line = self.line_index.char_offset_to_position(start).0;
+            let (mut end_line, end_col) = self.line_index.char_offset_to_position(end);
+            // A range ending at column 0 does not touch that line.
+            if end_line > start_line && end_col == 0 {
+                end_line -= 1;
+            }
+
+            for line in start_line.max(first_line)..=end_line.min(last_line) {
+                out.entry(line)
+                    .and_modify(|current| {
+                        if severity.rank() > current.rank() {
+                            *current = severity;
+                        }
+                    })
+                    .or_insert(severity);
+            }
+        }
+        out
+    }
+
+    /// Row background style per logical line from [`DecorationKind::LineBackground`] decorations.
+    ///
+    /// The first decoration in layer order wins when several cover the same line.
+    fn line_background_by_line(&self) -> BTreeMap<usize, StyleId> {
+        let mut out: BTreeMap<usize, StyleId> = BTreeMap::new();
+        for decorations in self.decorations.values() {
+            for deco in decorations {
+                let DecorationKind::LineBackground { style_id } = deco.kind else {
+                    continue;
+                };
+                let start = deco.range.start.min(deco.range.end);
+                let end = deco.range.start.max(deco.range.end);
+                let start_line = self.line_index.char_offset_to_position(start).0;
+                let (mut end_line, end_col) = self.line_index.char_offset_to_position(end);
+                // A range ending at column 0 does not touch that line.
+                if end_line > start_line && end_col == 0 {
+                    end_line -= 1;
+                }
+                for line in start_line..=end_line {
+                    out.entry(line).or_insert(style_id);
+                }
+            }
+        }
+        out
+    }
+
+    /// Get a decoration-aware composed grid snapshot (by composed visual line).
+    ///
+    /// This is an **optional** snapshot path that injects:
+    /// - inline virtual text (`DecorationPlacement::{Before,After}`), e.g. inlay hints
+    /// - above-line virtual text (`DecorationPlacement::AboveLine`), e.g. code lens
+    /// - styles of text-less range decorations (e.g. diagnostic underlines) on covered cells
+    /// - row backgrounds of [`DecorationKind::LineBackground`] decorations on document rows
+    ///
+    /// Notes:
+    /// - Wrapping is still computed from the underlying document text only.
+    /// - Virtual text can therefore extend past the viewport width; hosts may clip.
+    /// - Each [`ComposedCell`] carries its origin (`Document` vs `Virtual`) so hosts can map
+    ///   interactions back to document offsets without re-implementing layout.
+    pub fn get_headless_grid_composed(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+    ) -> ComposedGrid {
+        self.compose_grid(start_visual_row, count, false)
+    }
+
+    fn compose_grid(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+        include_selections: bool,
+    ) -> ComposedGrid {
+        let mut grid = ComposedGrid::new(start_visual_row, count);
+        if count == 0 {
+            return grid;
+        }
+
+        // Caret offsets (sorted) and non-empty selection ranges, for `include_selections`.
+        let mut caret_offsets: Vec<usize> = Vec::new();
+        let mut selected: Vec<(usize, usize)> = Vec::new();
+        if include_selections {
+            let primary = self.selection.clone().unwrap_or(Selection {
+                start: self.cursor_position,
+                end: self.cursor_position,
+                direction: SelectionDirection::Forward,
+            });
+            for selection in std::iter::once(&primary).chain(&self.secondary_selections) {
+                let (min, max) = crate::selection_set::selection_min_max(selection);
+                let start = self
+                    .line_index
+                    .position_to_char_offset(min.line, min.column);
+                let end = self
+                    .line_index
+                    .position_to_char_offset(max.line, max.column);
+                if start < end {
+                    selected.push((start, end));
+                }
+                caret_offsets.push(
+                    self.line_index
+                        .position_to_char_offset(selection.end.line, selection.end.column),
+                );
+            }
+            caret_offsets.sort_unstable();
+            caret_offsets.dedup();
+        }
+
+        #[derive(Debug, Clone)]
+        struct VirtualText {
+            anchor: usize,
+            text: String,
+            styles: Vec<StyleId>,
+        }
+
+        // Collect virtual text decorations from all layers.
+        let mut inline_before: HashMap<usize, Vec<VirtualText>> = HashMap::new();
+        let mut inline_after: HashMap<usize, Vec<VirtualText>> = HashMap::new();
+        let mut above_by_line: BTreeMap<usize, Vec<VirtualText>> = BTreeMap::new();
+
+        // Text-less range decorations (e.g. diagnostic underlines) style the covered cells.
+        let mut range_styles: Vec<(usize, usize, &[StyleId], Option<UnderlineStyle>)> = Vec::new();
 
         for decorations in self.decorations.values() {
             for deco in decorations {
+                if matches!(deco.kind, DecorationKind::LineBackground { .. }) {
+                    continue;
+                }
                 let Some(text) = deco.text.as_ref() else {
+                    let underline = match deco.kind {
+                        DecorationKind::Underline { style } => Some(style),
+                        _ => None,
+                    };
+                    if deco.range.start < deco.range.end
+                        && (!deco.styles.is_empty() || underline.is_some())
+                    {
+                        range_styles.push((
+                            deco.range.start,
+                            deco.range.end,
+                            &deco.styles,
+                            underline,
+                        ));
+                    }
                     continue;
                 };
                 if text.is_empty() {
@@ -1404,6 +2461,7 @@ impl EditorCore {
 
         let end_visual = start_visual_row.saturating_add(count).min(total_composed);
         let tab_width = self.layout_engine.tab_width();
+        let line_backgrounds = self.line_background_by_line();
 
         let mut current_visual = 0usize;
 
@@ -1429,6 +2487,7 @@ impl EditorCore {
                                 ch,
                                 width: w,
                                 styles: vt.styles.clone(),
+                                underline: None,
                                 source: ComposedCellSource::Virtual {
                                     anchor_offset: vt.anchor,
                                 },
@@ -1437,7 +2496,10 @@ impl EditorCore {
 
                         grid.lines.push(ComposedLine {
                             kind: ComposedLineKind::VirtualAboveLine { logical_line },
+                            background_style: None,
                             cells,
+                            caret_columns: Vec::new(),
+                            selection_columns: Vec::new(),
                         });
                     }
 
@@ -1455,6 +2517,13 @@ impl EditorCore {
                 .unwrap_or_default();
             let line_char_len = line_text.chars().count();
             let line_start_offset = self.line_index.position_to_char_offset(logical_line, 0);
+            let line_range_styles: Vec<&(usize, usize, &[StyleId], Option<UnderlineStyle>)> =
+                range_styles
+                    .iter()
+                    .filter(|(start, end, _, _)| {
+                        *start < line_start_offset + line_char_len && *end > line_start_offset
+                    })
+                    .collect();
 
             for visual_in_line in 0..layout.visual_line_count {
                 if current_visual >= end_visual {
@@ -1488,6 +2557,8 @@ impl EditorCore {
                 let segment_start_offset = line_start_offset + segment_start_col;
 
                 let mut cells: Vec<ComposedCell> = Vec::new();
+                let mut caret_columns: Vec<usize> = Vec::new();
+                let mut selection_columns: Vec<Range<usize>> = Vec::new();
 
                 let mut x_render = 0usize;
                 if visual_in_line > 0 {
@@ -1503,6 +2574,7 @@ impl EditorCore {
                             ch: ' ',
                             width: 1,
                             styles: Vec::new(),
+                            underline: None,
                             source: ComposedCellSource::Virtual {
                                 anchor_offset: segment_start_offset,
                             },
@@ -1510,7 +2582,7 @@ impl EditorCore {
                     }
                 }
 
-                let mut x_in_line = visual_x_for_column(&line_text, segment_start_col, tab_width);
+                let widths = grapheme_cell_widths(&line_text, tab_width);
 
                 let push_virtual = |anchor: usize,
                                     list: &[VirtualText],
@@ -1524,6 +2596,7 @@ impl EditorCore {
                                 ch,
                                 width: w,
                                 styles: vt.styles.clone(),
+                                underline: None,
                                 source: ComposedCellSource::Virtual {
                                     anchor_offset: anchor,
                                 },
@@ -1532,9 +2605,12 @@ impl EditorCore {
                     }
                 };
 
-                for (col, ch) in line_text
+                // Document cells stay one per `char`; the later `char`s of a grapheme cluster
+                // have width 0.
+                for ((col, ch), &w) in line_text
                     .chars()
                     .enumerate()
+                    .zip(&widths)
                     .skip(segment_start_col)
                     .take(segment_end_col.saturating_sub(segment_start_col))
                 {
@@ -1547,14 +2623,37 @@ impl EditorCore {
                         push_virtual(offset, list, &mut cells, &mut x_render);
                     }
 
-                    let styles = self.styles_at_offset(offset);
-                    let w = cell_width_at(ch, x_in_line, tab_width);
-                    x_in_line = x_in_line.saturating_add(w);
+                    if include_selections {
+                        if caret_offsets.binary_search(&offset).is_ok() {
+                            caret_columns.push(x_render);
+                        }
+                        if w > 0
+                            && selected
+                                .iter()
+                                .any(|&(start, end)| start <= offset && offset < end)
+                        {
+                            match selection_columns.last_mut() {
+                                Some(run) if run.end == x_render => run.end += w,
+                                _ => selection_columns.push(x_render..x_render + w),
+                            }
+                        }
+                    }
+
+                    let mut styles = self.styles_at_offset(offset);
+                    let mut underline = None;
+                    for (_, _, deco_styles, deco_underline) in line_range_styles
+                        .iter()
+                        .filter(|(start, end, _, _)| *start <= offset && offset < *end)
+                    {
+                        styles.extend_from_slice(deco_styles);
+                        underline = underline.or(*deco_underline);
+                    }
                     x_render = x_render.saturating_add(w);
                     cells.push(ComposedCell {
                         ch,
                         width: w,
                         styles,
+                        underline,
                         source: ComposedCellSource::Document { offset },
                     });
                 }
@@ -1568,10 +2667,13 @@ impl EditorCore {
                     if let Some(list) = inline_after.get(&eol_offset) {
                         push_virtual(eol_offset, list, &mut cells, &mut x_render);
                     }
+                    if caret_offsets.binary_search(&eol_offset).is_ok() {
+                        caret_columns.push(x_render);
+                    }
 
                     // For collapsed folding start line, append placeholder to the last segment.
                     if let Some(region) = Self::collapsed_region_starting_at(regions, logical_line)
-                        && !region.placeholder.is_empty()
+                        && !region.display_text().is_empty()
                     {
                         if !cells.is_empty() {
                             x_render = x_render.saturating_add(char_width(' '));
@@ -1579,18 +2681,20 @@ impl EditorCore {
                                 ch: ' ',
                                 width: char_width(' '),
                                 styles: vec![FOLD_PLACEHOLDER_STYLE_ID],
+                                underline: None,
                                 source: ComposedCellSource::Virtual {
                                     anchor_offset: eol_offset,
                                 },
                             });
                         }
-                        for ch in region.placeholder.chars() {
+                        for ch in region.display_text().chars() {
                             let w = cell_width_at(ch, x_render, tab_width);
                             x_render = x_render.saturating_add(w);
                             cells.push(ComposedCell {
                                 ch,
                                 width: w,
                                 styles: vec![FOLD_PLACEHOLDER_STYLE_ID],
+                                underline: None,
                                 source: ComposedCellSource::Virtual {
                                     anchor_offset: eol_offset,
                                 },
@@ -1604,7 +2708,10 @@ impl EditorCore {
                         logical_line,
                         visual_in_logical: visual_in_line,
                     },
+                    background_style: line_backgrounds.get(&logical_line).copied(),
                     cells,
+                    caret_columns,
+                    selection_columns,
                 });
 
                 current_visual = current_visual.saturating_add(1);
@@ -1614,6 +2721,33 @@ impl EditorCore {
         grid
     }
 
+    /// Get a composed snapshot with rendering hints (by composed visual line).
+    ///
+    /// Document cells of [`Self::get_headless_grid_composed`] get the substitute glyphs of
+    /// [`Self::get_headless_grid_styled_with_options`]; virtual text is left as-is. With
+    /// [`SnapshotOptions::include_selections`], each line also reports the visual columns of the
+    /// carets and selected cells, which hosts cannot derive from
+    /// [`Self::logical_position_to_visual`] once virtual text shifts the cells.
+    pub fn get_headless_grid_composed_with_options(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+        options: &SnapshotOptions,
+    ) -> ComposedGrid {
+        let mut grid = self.compose_grid(start_visual_row, count, options.include_selections);
+        let mut hints = RenderHints::new(self, *options);
+        if !hints.is_active() {
+            return grid;
+        }
+        for line in &mut grid.lines {
+            let cells = std::mem::take(&mut line.cells);
+            for cell in cells {
+                hints.push_composed_cell(&mut line.cells, cell);
+            }
+        }
+        grid
+    }
+
     /// Get total visual line count (considering soft wrapping + folding).
     pub fn visual_line_count(&self) -> usize {
         self.with_visual_row_index(|index| index.total_visual_lines())
@@ -1634,6 +2768,10 @@ impl EditorCore {
     }
 
     /// Convert logical coordinates (line, column) to visual coordinates (visual line number, in-line x cell offset), considering folding.
+    ///
+    /// On right-to-left lines (see [`line_direction`](crate::layout::line_direction)) the x
+    /// offset is mirrored within the row's text, matching the cell order of
+    /// [`get_headless_grid_styled`](Self::get_headless_grid_styled).
     pub fn logical_position_to_visual(
         &self,
         logical_line: usize,
@@ -1665,18 +2803,21 @@ impl EditorCore {
             }
         }
 
-        let seg_start_x_in_line = visual_x_for_column(&line_text, segment_start_col, tab_width);
-        let mut x_in_line = seg_start_x_in_line;
-        let mut x_in_segment = 0usize;
-        for ch in line_text
-            .chars()
-            .skip(segment_start_col)
-            .take(column.saturating_sub(segment_start_col))
-        {
-            let w = cell_width_at(ch, x_in_line, tab_width);
-            x_in_line = x_in_line.saturating_add(w);
-            x_in_segment = x_in_segment.saturating_add(w);
-        }
+        let x_in_segment = visual_x_for_column(&line_text, column, tab_width).saturating_sub(
+            visual_x_for_column(&line_text, segment_start_col, tab_width),
+        );
+
+        let segment_end_col = layout
+            .wrap_points
+            .get(wrapped_offset)
+            .map_or(line_char_len, |wp| wp.char_index);
+        let x_in_segment = directional_x_in_segment(
+            &line_text,
+            segment_start_col,
+            segment_end_col,
+            x_in_segment,
+            tab_width,
+        );
 
         let indent = if wrapped_offset == 0 {
             0
@@ -1699,6 +2840,7 @@ impl EditorCore {
     ///
     /// Difference from [`logical_position_to_visual`](Self::logical_position_to_visual) is that it allows `column`
     /// to exceed the line end: the exceeding part is treated as `' '` (width=1) virtual spaces, suitable for rectangular selection / column editing.
+    /// Text direction is ignored: columns are placed left to right.
     pub fn logical_position_to_visual_allow_virtual(
         &self,
         logical_line: usize,
@@ -1730,18 +2872,10 @@ impl EditorCore {
             }
         }
 
-        let seg_start_x_in_line = visual_x_for_column(&line_text, segment_start_col, tab_width);
-        let mut x_in_line = seg_start_x_in_line;
-        let mut x_in_segment = 0usize;
-        for ch in line_text
-            .chars()
-            .skip(segment_start_col)
-            .take(clamped_column.saturating_sub(segment_start_col))
-        {
-            let w = cell_width_at(ch, x_in_line, tab_width);
-            x_in_line = x_in_line.saturating_add(w);
-            x_in_segment = x_in_segment.saturating_add(w);
-        }
+        let x_in_segment =
+            visual_x_for_column(&line_text, clamped_column, tab_width).saturating_sub(
+                visual_x_for_column(&line_text, segment_start_col, tab_width),
+            );
 
         let x_in_segment = x_in_segment + column.saturating_sub(line_char_len);
 
@@ -1765,7 +2899,10 @@ impl EditorCore {
     /// Convert visual coordinates (global visual row + x in cells) back to logical `(line, column)`.
     ///
     /// - `visual_row` is the global visual row (after soft wrapping and folding).
-    /// - `x_in_cells` is the cell offset within that visual row (0-based).
+    /// - `x_in_cells` is the cell offset within that visual row (0-based). An x inside the wrap
+    ///   indent of a continuation row maps to the row's first character; an x past the end of a
+    ///   row maps to the line end on the last row of a line, and to the row's last character on
+    ///   rows continued by soft wrapping (so the caret stays on the row).
     ///
     /// Returns `None` if layout information is unavailable.
     pub fn visual_position_to_logical(
@@ -1818,26 +2955,39 @@ impl EditorCore {
             );
             x_in_cells.saturating_sub(indent)
         };
-        let seg_start_x_in_line = visual_x_for_column(&line_text, segment_start_col, tab_width);
-        let mut x_in_line = seg_start_x_in_line;
+        let x_in_cells = directional_x_in_segment(
+            &line_text,
+            segment_start_col,
+            segment_end_col,
+            x_in_cells,
+            tab_width,
+        );
+        // Later `char`s of a grapheme cluster have width 0, so the column never lands inside one.
         let mut x_in_segment = 0usize;
         let mut column = segment_start_col;
-
-        for (char_idx, ch) in line_text.chars().enumerate().skip(segment_start_col) {
-            if char_idx >= segment_end_col {
-                break;
-            }
-
-            let w = cell_width_at(ch, x_in_line, tab_width);
+        for &w in grapheme_cell_widths(&line_text, tab_width)
+            .get(segment_start_col..segment_end_col)
+            .unwrap_or_default()
+        {
             if x_in_segment.saturating_add(w) > x_in_cells {
                 break;
             }
 
-            x_in_line = x_in_line.saturating_add(w);
             x_in_segment = x_in_segment.saturating_add(w);
             column = column.saturating_add(1);
         }
 
+        // The wrap point itself is shown at the start of the next row, so an x past the end of a
+        // continued segment lands before its last grapheme instead.
+        if visual_in_logical < layout.wrap_points.len() && column >= segment_end_col {
+            column = grapheme_columns(&line_text)
+                .map(|(col, _, _)| col)
+                .take_while(|&col| col < segment_end_col)
+                .last()
+                .unwrap_or(segment_start_col)
+                .max(segment_start_col);
+        }
+
         Some(Position::new(logical_line, column))
     }
 
@@ -1901,7 +3051,8 @@ impl EditorCore {
         }
     }
 
-    fn styles_at_offset(&self, offset: usize) -> Vec<StyleId> {
+    /// Every style at `offset` (base `interval_tree` plus all style layers), sorted and deduped.
+    pub fn styles_at_offset(&self, offset: usize) -> Vec<StyleId> {
         let mut styles: Vec<StyleId> = self
             .interval_tree
             .query_point(offset)
@@ -1921,6 +3072,70 @@ impl EditorCore {
         styles.dedup();
         styles
     }
+
+    /// Style runs over the char range `start..end`, merged across the base `interval_tree` and
+    /// all style layers.
+    ///
+    /// Every offset of a run has the styles [`styles_at_offset`](Self::styles_at_offset) returns
+    /// for it; renderers walking a range should prefer this over querying each offset.
+    pub fn style_runs(&self, start: usize, end: usize) -> Vec<StyleRun> {
+        crate::intervals::merged_style_runs(
+            std::iter::once(&self.interval_tree).chain(self.style_layers.values()),
+            start,
+            end,
+        )
+    }
+
+    /// Priority of `layer` when resolving overlapping styles: the override set with
+    /// [`set_style_layer_priority`](Self::set_style_layer_priority), or the layer's default.
+    pub fn style_layer_priority(&self, layer: StyleLayerId) -> StylePriority {
+        self.style_layer_priorities
+            .get(&layer)
+            .copied()
+            .unwrap_or(layer.default_priority())
+    }
+
+    /// Override the priority of `layer` (`None` restores [`StyleLayerId::default_priority`]).
+    pub fn set_style_layer_priority(
+        &mut self,
+        layer: StyleLayerId,
+        priority: Option<StylePriority>,
+    ) {
+        match priority {
+            Some(priority) => {
+                self.style_layer_priorities.insert(layer, priority);
+            }
+            None => {
+                self.style_layer_priorities.remove(&layer);
+            }
+        }
+    }
+
+    /// The single winning style at `offset`, for renderers that need one style per cell.
+    ///
+    /// The interval from the highest-priority layer wins (the base `interval_tree` has
+    /// priority `0`). Within equal priority the innermost interval (latest start, then
+    /// earliest end) wins, and remaining ties go to the later layer / later interval.
+    /// [`styles_at_offset`](Self::styles_at_offset) still returns every style.
+    pub fn styles_at_offset_resolved(&self, offset: usize) -> Option<StyleId> {
+        let base = self
+            .interval_tree
+            .query_point(offset)
+            .into_iter()
+            .map(|interval| (0, interval));
+        let layered = self.style_layers.iter().flat_map(|(&layer, tree)| {
+            let priority = self.style_layer_priority(layer);
+            tree.query_point(offset)
+                .into_iter()
+                .map(move |interval| (priority, interval))
+        });
+
+        base.chain(layered)
+            .max_by_key(|(priority, interval)| {
+                (*priority, interval.start, std::cmp::Reverse(interval.end))
+            })
+            .map(|(_, interval)| interval.style_id)
+    }
 }
 
 /// Command executor
@@ -1969,12 +3184,23 @@ pub struct CommandExecutor {
     undo_redo: UndoRedoManager,
     /// Controls how [`EditCommand::InsertTab`] behaves.
     tab_key_behavior: TabKeyBehavior,
+    /// Controls how multi-selection sets are merged during normalization.
+    selection_merge_policy: SelectionMergePolicy,
     /// Preferred line ending for saving (internal storage is always LF).
     line_ending: LineEnding,
     /// Sticky x position for visual-row cursor movement (in cells).
     preferred_x_cells: Option<usize>,
     /// Structured delta for the last executed text modification (cleared on each `execute()` call).
     last_text_delta: Option<TextDelta>,
+    /// Tabstops of the snippet being filled in, if any.
+    snippet_session: Option<SnippetSession>,
+    /// Language settings used when commands are not given explicit configuration.
+    language_config: Option<LanguageConfig>,
+    /// Computes the indentation of new lines, when set.
+    indent_provider: Option<Box<dyn IndentProvider>>,
+    /// Scope of the last selection-scoped [`CursorCommand::FindNextInRange`] and the match it
+    /// selected, so repeating the command keeps searching the original selection.
+    find_in_range_scope: Option<(SearchMatch, SearchMatch)>,
 }
 
 impl CommandExecutor {
@@ -1985,9 +3211,14 @@ impl CommandExecutor {
             command_history: Vec::new(),
             undo_redo: UndoRedoManager::new(1000),
             tab_key_behavior: TabKeyBehavior::Tab,
+            selection_merge_policy: SelectionMergePolicy::default(),
             line_ending: LineEnding::detect_in_text(text),
             preferred_x_cells: None,
             last_text_delta: None,
+            snippet_session: None,
+            language_config: None,
+            indent_provider: None,
+            find_in_range_scope: None,
         }
     }
 
@@ -1999,6 +3230,9 @@ impl CommandExecutor {
     /// Execute command
     pub fn execute(&mut self, command: Command) -> Result<CommandResult, CommandError> {
         self.last_text_delta = None;
+        if matches!(command, Command::Edit(_)) {
+            self.find_in_range_scope = None;
+        }
 
         // Save command to history
         self.command_history.push(command.clone());
@@ -2027,13 +3261,37 @@ impl CommandExecutor {
             self.undo_redo.end_group();
         }
 
+        // Snippet tabstops follow ordinary edits; undo/redo ends the session instead.
+        let maps_snippet_session = match &command {
+            Command::Edit(EditCommand::Undo | EditCommand::Redo) => {
+                self.snippet_session = None;
+                false
+            }
+            Command::Edit(EditCommand::InsertSnippet { .. }) => false,
+            Command::Edit(_) => self.snippet_session.is_some(),
+            _ => false,
+        };
+
         // Execute command
-        match command {
+        let result = match command {
             Command::Edit(edit_cmd) => self.execute_edit(edit_cmd),
             Command::Cursor(cursor_cmd) => self.execute_cursor(cursor_cmd),
             Command::View(view_cmd) => self.execute_view(view_cmd),
             Command::Style(style_cmd) => self.execute_style(style_cmd),
+        };
+
+        if affects_visual_rows && result.is_ok() {
+            self.editor.refresh_fold_summaries();
+        }
+
+        if maps_snippet_session
+            && let (Some(session), Some(delta)) =
+                (self.snippet_session.as_mut(), self.last_text_delta.as_ref())
+        {
+            session.apply_delta(delta);
         }
+
+        result
     }
 
     /// Get the structured text delta produced by the last successful `execute()` call, if any.
@@ -2046,6 +3304,16 @@ impl CommandExecutor {
         self.last_text_delta.take()
     }
 
+    /// The active snippet session started by [`EditCommand::InsertSnippet`], if any.
+    pub fn snippet_session(&self) -> Option<&SnippetSession> {
+        self.snippet_session.as_ref()
+    }
+
+    /// End the active snippet session (e.g. when the user presses Escape).
+    pub fn end_snippet_session(&mut self) {
+        self.snippet_session = None;
+    }
+
     /// Batch execute commands (transactional)
     pub fn execute_batch(
         &mut self,
@@ -2091,6 +3359,42 @@ impl CommandExecutor {
         self.undo_redo.current_group_id()
     }
 
+    /// Language configuration used by commands that are not given explicit settings.
+    pub fn language_config(&self) -> Option<&LanguageConfig> {
+        self.language_config.as_ref()
+    }
+
+    /// Set (or clear) the language configuration.
+    ///
+    /// Indentation fields that are set are applied right away (tab width and
+    /// [`TabKeyBehavior`]).
+    pub fn set_language_config(&mut self, config: Option<LanguageConfig>) {
+        if let Some(config) = &config {
+            if let Some(width) = config.indent_width.filter(|&width| width > 0) {
+                self.editor.layout_engine.set_tab_width(width);
+                self.editor.invalidate_visual_row_index_cache();
+            }
+            if let Some(use_tabs) = config.use_tabs {
+                self.tab_key_behavior = if use_tabs {
+                    TabKeyBehavior::Tab
+                } else {
+                    TabKeyBehavior::Spaces
+                };
+            }
+        }
+        self.language_config = config;
+    }
+
+    /// Set (or clear) the provider consulted by auto-indenting [`EditCommand::InsertNewline`].
+    pub fn set_indent_provider(&mut self, provider: Option<Box<dyn IndentProvider>>) {
+        self.indent_provider = provider;
+    }
+
+    /// Whether an [`IndentProvider`] is set.
+    pub fn has_indent_provider(&self) -> bool {
+        self.indent_provider.is_some()
+    }
+
     /// Whether current state is at clean point (for dirty tracking)
     pub fn is_clean(&self) -> bool {
         self.undo_redo.is_clean()
@@ -2121,6 +3425,19 @@ impl CommandExecutor {
         self.tab_key_behavior = behavior;
     }
 
+    /// Get the policy used when normalizing multi-selection sets.
+    pub fn selection_merge_policy(&self) -> SelectionMergePolicy {
+        self.selection_merge_policy
+    }
+
+    /// Set the policy used when normalizing multi-selection sets.
+    ///
+    /// The new policy applies to subsequent commands; the current selection set is not
+    /// re-normalized.
+    pub fn set_selection_merge_policy(&mut self, policy: SelectionMergePolicy) {
+        self.selection_merge_policy = policy;
+    }
+
     /// Get the sticky x position (in cells) used by visual-row cursor movement.
     pub fn preferred_x_cells(&self) -> Option<usize> {
         self.preferred_x_cells
@@ -2159,7 +3476,16 @@ impl CommandExecutor {
                 query,
                 replacement,
                 options,
-            } => self.execute_replace_all_command(query, replacement, options),
+            } => self.execute_replace_all_command(query, replacement, options, None),
+            EditCommand::ReplaceAllInRange {
+                query,
+                replacement,
+                options,
+                range,
+            } => {
+                let scope = self.search_scope(range)?;
+                self.execute_replace_all_command(query, replacement, options, Some(scope))
+            }
             EditCommand::DeleteToPrevTabStop => self.execute_delete_to_prev_tab_stop_command(),
             EditCommand::DeleteGraphemeBack => {
                 self.execute_delete_by_boundary_command(false, TextBoundary::Grapheme)
@@ -2174,8 +3500,20 @@ impl CommandExecutor {
                 self.execute_delete_by_boundary_command(true, TextBoundary::Word)
             }
             EditCommand::Backspace => self.execute_backspace_command(),
+            EditCommand::DeletePairBackspace { pairs } => {
+                self.execute_delete_like_command(false, &pairs)
+            }
             EditCommand::DeleteForward => self.execute_delete_forward_command(),
-            EditCommand::InsertText { text } => self.execute_insert_text_command(text),
+            EditCommand::InsertText { text } => self.execute_insert_text_command(text, false),
+            EditCommand::InsertTextReindented { text } => {
+                self.execute_insert_text_command(text, true)
+            }
+            EditCommand::InsertTextWithAutoPairs { text, pairs } => {
+                self.execute_insert_text_with_auto_pairs_command(text, pairs)
+            }
+            EditCommand::InsertSnippet { template } => {
+                self.execute_insert_snippet_command(template)
+            }
             EditCommand::InsertTab => self.execute_insert_tab_command(),
             EditCommand::InsertNewline { auto_indent } => {
                 self.execute_insert_newline_command(auto_indent)
@@ -2183,13 +3521,21 @@ impl CommandExecutor {
             EditCommand::Indent => self.execute_indent_command(false),
             EditCommand::Outdent => self.execute_indent_command(true),
             EditCommand::DuplicateLines => self.execute_duplicate_lines_command(),
+            EditCommand::DuplicateSelection => self.execute_duplicate_selection_command(),
             EditCommand::DeleteLines => self.execute_delete_lines_command(),
             EditCommand::MoveLinesUp => self.execute_move_lines_command(true),
             EditCommand::MoveLinesDown => self.execute_move_lines_command(false),
             EditCommand::JoinLines => self.execute_join_lines_command(),
             EditCommand::SplitLine => self.execute_insert_newline_command(false),
+            EditCommand::TransposeChars => self.execute_transpose_command(false),
+            EditCommand::TransposeWords => self.execute_transpose_command(true),
             EditCommand::ToggleComment { config } => self.execute_toggle_comment_command(config),
-            EditCommand::ApplyTextEdits { edits } => self.execute_apply_text_edits_command(edits),
+            EditCommand::ApplyTextEdits { edits } => {
+                self.execute_apply_text_edits_command(edits, None)
+            }
+            EditCommand::ReindentDocument { from, to } => {
+                self.execute_reindent_document_command(from, to)
+            }
             EditCommand::Insert { offset, text } => self.execute_insert_command(offset, text),
             EditCommand::Delete { start, length } => self.execute_delete_command(start, length),
             EditCommand::Replace {
@@ -2294,7 +3640,12 @@ impl CommandExecutor {
         Ok(CommandResult::Success)
     }
 
-    fn execute_insert_text_command(&mut self, text: String) -> Result<CommandResult, CommandError> {
+    /// Insert `text` at every caret/selection; with `reindent`, as [`EditCommand::InsertTextReindented`].
+    fn execute_insert_text_command(
+        &mut self,
+        text: String,
+        reindent: bool,
+    ) -> Result<CommandResult, CommandError> {
         if text.is_empty() {
             return Ok(CommandResult::Success);
         }
@@ -2316,8 +3667,13 @@ impl CommandExecutor {
         selections.push(primary_selection);
         selections.extend(self.editor.secondary_selections.iter().cloned());
 
-        let (selections, primary_index) = crate::selection_set::normalize_selections(selections, 0);
+        let (selections, primary_index) = crate::selection_set::normalize_selections(
+            selections,
+            0,
+            self.selection_merge_policy.for_edit(),
+        );
 
+        let reindent = reindent && text.contains('\n');
         let text_char_len = text.chars().count();
 
         struct Op {
@@ -2344,7 +3700,6 @@ impl CommandExecutor {
             let end_offset = self.position_to_char_offset_clamped(range_end_pos);
 
             let delete_len = end_offset.saturating_sub(start_offset);
-            let insert_char_len = start_pad + text_char_len;
 
             let deleted_text = if delete_len == 0 {
                 String::new()
@@ -2352,11 +3707,29 @@ impl CommandExecutor {
                 self.editor.piece_table.get_range(start_offset, delete_len)
             };
 
+            let reindented = if reindent {
+                let line_text = self
+                    .editor
+                    .line_index
+                    .get_line_text(range_start_pos.line)
+                    .unwrap_or_default();
+                Some(self.reindent_pasted_text(&text, &Self::leading_whitespace_prefix(&line_text)))
+            } else {
+                None
+            };
+            let text = reindented.as_deref().unwrap_or(&text);
+            let insert_char_len = start_pad
+                + if reindented.is_some() {
+                    text.chars().count()
+                } else {
+                    text_char_len
+                };
+
             let mut insert_text = String::with_capacity(text.len() + start_pad);
             for _ in 0..start_pad {
                 insert_text.push(' ');
             }
-            insert_text.push_str(&text);
+            insert_text.push_str(text);
 
             ops.push(Op {
                 selection_index,
@@ -2391,29 +3764,7 @@ impl CommandExecutor {
         for &idx in &desc_indices {
             let op = &ops[idx];
 
-            let edit_line = self
-                .editor
-                .line_index
-                .char_offset_to_position(op.start_offset)
-                .0;
-            let deleted_newlines = op
-                .deleted_text
-                .as_bytes()
-                .iter()
-                .filter(|b| **b == b'\n')
-                .count();
-            let inserted_newlines = op
-                .insert_text
-                .as_bytes()
-                .iter()
-                .filter(|b| **b == b'\n')
-                .count();
-            let line_delta = inserted_newlines as isize - deleted_newlines as isize;
-            if line_delta != 0 {
-                self.editor
-                    .folding_manager
-                    .apply_line_delta(edit_line, line_delta);
-            }
+            self.update_folds_for_edit(op.start_offset, op.delete_len, &op.insert_text);
 
             if op.delete_len > 0 {
                 self.editor
@@ -2463,8 +3814,11 @@ impl CommandExecutor {
             });
         }
 
-        let (new_carets, new_primary_index) =
-            crate::selection_set::normalize_selections(new_carets, primary_index);
+        let (new_carets, new_primary_index) = crate::selection_set::normalize_selections(
+            new_carets,
+            primary_index,
+            self.selection_merge_policy,
+        );
         let primary = new_carets
             .get(new_primary_index)
             .cloned()
@@ -2527,6 +3881,332 @@ impl CommandExecutor {
         Ok(CommandResult::Success)
     }
 
+    fn execute_insert_text_with_auto_pairs_command(
+        &mut self,
+        text: String,
+        pairs: Vec<(char, char)>,
+    ) -> Result<CommandResult, CommandError> {
+        let mut chars = text.chars();
+        let (Some(ch), None) = (chars.next(), chars.next()) else {
+            return self.execute_insert_text_command(text, false);
+        };
+        let closing_for_ch = pairs
+            .iter()
+            .find(|(open, _)| *open == ch)
+            .map(|(_, close)| *close);
+        let ch_is_closing = pairs.iter().any(|(_, close)| *close == ch);
+        if closing_for_ch.is_none() && !ch_is_closing {
+            return self.execute_insert_text_command(text, false);
+        }
+
+        let before_char_count = self.editor.piece_table.char_count();
+        let before_selection = self.snapshot_selection_set();
+        let (selections, primary_index) = crate::selection_set::normalize_selections(
+            before_selection.selections.clone(),
+            before_selection.primary_index,
+            self.selection_merge_policy.for_edit(),
+        );
+
+        // At equal offsets, a wrap's closing char goes before anything inserted by the next
+        // selection.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        enum OpKind {
+            WrapEnd,
+            Insert,
+            WrapStart,
+        }
+
+        struct Op {
+            start_before: usize,
+            start_after: usize,
+            delete_len: usize,
+            deleted_text: String,
+            inserted_text: String,
+            sel_id: usize,
+            kind: OpKind,
+        }
+
+        // Where each selection ends up: either relative to one of its ops, or (type-over) a
+        // pre-edit offset mapped through all ops.
+        enum Target {
+            AfterInsert,
+            Wrapped,
+            TypeOver(usize),
+        }
+
+        let char_at = |offset: usize| -> Option<char> {
+            (offset < before_char_count)
+                .then(|| self.editor.piece_table.get_range(offset, 1).chars().next())
+                .flatten()
+        };
+
+        let mut ops: Vec<Op> = Vec::new();
+        let mut targets: Vec<(Target, SelectionDirection)> = Vec::with_capacity(selections.len());
+        for (sel_id, selection) in selections.iter().enumerate() {
+            let range = self.selection_char_range(selection);
+            let mut push = |start: usize, end: usize, inserted_text: String, kind: OpKind| {
+                ops.push(Op {
+                    start_before: start,
+                    start_after: start,
+                    delete_len: end - start,
+                    deleted_text: if end > start {
+                        self.editor.piece_table.get_range(start, end - start)
+                    } else {
+                        String::new()
+                    },
+                    inserted_text,
+                    sel_id,
+                    kind,
+                });
+            };
+
+            let target = if range.start < range.end {
+                if let Some(close) = closing_for_ch {
+                    push(range.start, range.start, ch.to_string(), OpKind::WrapStart);
+                    push(range.end, range.end, close.to_string(), OpKind::WrapEnd);
+                    Target::Wrapped
+                } else {
+                    push(range.start, range.end, ch.to_string(), OpKind::Insert);
+                    Target::AfterInsert
+                }
+            } else {
+                let caret = range.start;
+                let next = char_at(caret);
+                let prev = caret.checked_sub(1).and_then(char_at);
+                if ch_is_closing && next == Some(ch) {
+                    Target::TypeOver(caret + 1)
+                } else {
+                    let auto_close = closing_for_ch.filter(|&close| {
+                        // Don't pair before a word, nor quotes right after one (e.g. `don't`).
+                        !next.is_some_and(crate::text::is_word_char)
+                            && (close != ch || !prev.is_some_and(crate::text::is_word_char))
+                    });
+                    let mut inserted = ch.to_string();
+                    inserted.extend(auto_close);
+                    push(caret, caret, inserted, OpKind::Insert);
+                    Target::AfterInsert
+                }
+            };
+            targets.push((target, selection.direction));
+        }
+
+        ops.sort_by_key(|op| (op.start_before, op.kind));
+        let mut delta: i64 = 0;
+        for op in &mut ops {
+            op.start_after = (op.start_before as i64 + delta) as usize;
+            delta += op.inserted_text.chars().count() as i64 - op.delete_len as i64;
+        }
+
+        let mut new_ranges: Vec<(usize, usize)> = vec![(0, 0); targets.len()];
+        for op in &ops {
+            let range = &mut new_ranges[op.sel_id];
+            match op.kind {
+                OpKind::Insert => *range = (op.start_after + 1, op.start_after + 1),
+                OpKind::WrapStart => range.0 = op.start_after + 1,
+                OpKind::WrapEnd => range.1 = op.start_after,
+            }
+        }
+        for (sel_id, (target, _)) in targets.iter().enumerate() {
+            if let Target::TypeOver(offset) = *target {
+                let shift: i64 = ops
+                    .iter()
+                    .filter(|op| op.start_before < offset)
+                    .map(|op| op.inserted_text.chars().count() as i64 - op.delete_len as i64)
+                    .sum();
+                let caret = (offset as i64 + shift) as usize;
+                new_ranges[sel_id] = (caret, caret);
+            }
+        }
+
+        if !ops.is_empty() {
+            // Reverse so that, among ops sharing an offset, the later one is applied first.
+            let apply_ops: Vec<(usize, usize, &str)> = ops
+                .iter()
+                .rev()
+                .map(|op| (op.start_before, op.delete_len, op.inserted_text.as_str()))
+                .collect();
+            self.apply_text_ops(apply_ops)?;
+        }
+
+        let next_selections: Vec<Selection> = new_ranges
+            .iter()
+            .zip(&targets)
+            .map(|(&(start, end), (target, direction))| {
+                let (start_line, start_col) = self.editor.line_index.char_offset_to_position(start);
+                let (end_line, end_col) = self.editor.line_index.char_offset_to_position(end);
+                let (start, end) = (
+                    Position::new(start_line, start_col),
+                    Position::new(end_line, end_col),
+                );
+                match (target, direction) {
+                    (Target::Wrapped, SelectionDirection::Backward) => Selection {
+                        start: end,
+                        end: start,
+                        direction: SelectionDirection::Backward,
+                    },
+                    _ => Selection {
+                        start,
+                        end,
+                        direction: SelectionDirection::Forward,
+                    },
+                }
+            })
+            .collect();
+        self.execute_cursor(CursorCommand::SetSelections {
+            selections: next_selections,
+            primary_index,
+        })?;
+
+        if ops.is_empty() {
+            // Pure type-over: only the carets moved.
+            return Ok(CommandResult::Success);
+        }
+
+        let after_selection = self.snapshot_selection_set();
+
+        let edits: Vec<TextEdit> = ops
+            .into_iter()
+            .map(|op| TextEdit {
+                start_before: op.start_before,
+                start_after: op.start_after,
+                deleted_text: op.deleted_text,
+                inserted_text: op.inserted_text,
+            })
+            .collect();
+        let coalescible_insert = edits.iter().all(|e| e.deleted_text.is_empty());
+
+        // Descending offsets, in the same order the ops were applied.
+        let delta_edits: Vec<TextDeltaEdit> = edits
+            .iter()
+            .rev()
+            .map(|e| TextDeltaEdit {
+                start: e.start_before,
+                deleted_text: e.deleted_text.clone(),
+                inserted_text: e.inserted_text.clone(),
+            })
+            .collect();
+
+        let step = UndoStep {
+            group_id: 0,
+            edits,
+            before_selection,
+            after_selection,
+        };
+        let group_id = self.undo_redo.push_step(step, coalescible_insert);
+
+        self.last_text_delta = Some(TextDelta {
+            before_char_count,
+            after_char_count: self.editor.piece_table.char_count(),
+            edits: delta_edits,
+            undo_group_id: Some(group_id),
+        });
+
+        Ok(CommandResult::Success)
+    }
+
+    fn execute_insert_snippet_command(
+        &mut self,
+        template: String,
+    ) -> Result<CommandResult, CommandError> {
+        let template = crate::text::normalize_crlf_to_lf_string(template);
+        let snippet = crate::snippets::parse_snippet(&template);
+        self.snippet_session = None;
+
+        // A snippet is its own undo step, even when inserted right after typing.
+        self.undo_redo.end_group();
+        if !snippet.text.is_empty() {
+            self.execute_insert_text_command(snippet.text.clone(), false)?;
+        }
+        self.undo_redo.end_group();
+
+        // Where each copy of the snippet landed (ascending, post-edit offsets). Inserted text may
+        // be prefixed with virtual-space padding.
+        let snippet_len = snippet.text.chars().count();
+        let starts: Vec<usize> = match self.last_text_delta.as_ref() {
+            Some(delta) => {
+                let mut edits: Vec<&TextDeltaEdit> = delta.edits.iter().collect();
+                edits.sort_by_key(|e| e.start);
+                let mut shift: i64 = 0;
+                edits
+                    .into_iter()
+                    .map(|e| {
+                        let start = (e.start as i64 + shift) as usize;
+                        shift += e.inserted_len() as i64 - e.deleted_len() as i64;
+                        start + e.inserted_len() - snippet_len
+                    })
+                    .collect()
+            }
+            // Nothing was inserted: every (collapsed) caret is a copy of the empty snippet.
+            None => {
+                let mut starts: Vec<usize> = self
+                    .snapshot_selection_set()
+                    .selections
+                    .iter()
+                    .map(|sel| self.selection_char_range(sel).start)
+                    .collect();
+                starts.sort_unstable();
+                starts
+            }
+        };
+
+        match SnippetSession::new(&snippet, &starts) {
+            Ok(session) => {
+                let ranges = session.current().ranges.clone();
+                self.snippet_session = Some(session);
+                self.select_snippet_ranges(&ranges)?;
+            }
+            Err(final_ranges) => self.select_snippet_ranges(&final_ranges)?,
+        }
+        Ok(CommandResult::Success)
+    }
+
+    fn execute_tabstop_command(&mut self, forward: bool) -> Result<CommandResult, CommandError> {
+        let Some(session) = self.snippet_session.as_mut() else {
+            return Err(CommandError::Other("No active snippet session".to_string()));
+        };
+        session.step(forward);
+        let ranges = session.current().ranges.clone();
+        if session.is_at_final_tabstop() {
+            self.snippet_session = None;
+        }
+        self.select_snippet_ranges(&ranges)?;
+        Ok(CommandResult::Success)
+    }
+
+    /// Select `ranges` (forward selections), the first one being primary.
+    fn select_snippet_ranges(
+        &mut self,
+        ranges: &[SnippetTabstopRange],
+    ) -> Result<(), CommandError> {
+        if ranges.is_empty() {
+            return Ok(());
+        }
+        let char_count = self.editor.piece_table.char_count();
+        let selections: Vec<Selection> = ranges
+            .iter()
+            .map(|range| {
+                let (start_line, start_col) = self
+                    .editor
+                    .line_index
+                    .char_offset_to_position(range.start.min(char_count));
+                let (end_line, end_col) = self
+                    .editor
+                    .line_index
+                    .char_offset_to_position(range.end.min(char_count));
+                Selection {
+                    start: Position::new(start_line, start_col),
+                    end: Position::new(end_line, end_col),
+                    direction: SelectionDirection::Forward,
+                }
+            })
+            .collect();
+        self.execute_cursor(CursorCommand::SetSelections {
+            selections,
+            primary_index: 0,
+        })?;
+        Ok(())
+    }
+
     fn execute_insert_tab_command(&mut self) -> Result<CommandResult, CommandError> {
         let before_char_count = self.editor.piece_table.char_count();
         let before_selection = self.snapshot_selection_set();
@@ -2541,7 +4221,11 @@ impl CommandExecutor {
         selections.push(primary_selection);
         selections.extend(self.editor.secondary_selections.iter().cloned());
 
-        let (selections, primary_index) = crate::selection_set::normalize_selections(selections, 0);
+        let (selections, primary_index) = crate::selection_set::normalize_selections(
+            selections,
+            0,
+            self.selection_merge_policy.for_edit(),
+        );
 
         let tab_width = self.editor.layout_engine.tab_width();
 
@@ -2648,29 +4332,7 @@ impl CommandExecutor {
         for &idx in &desc_indices {
             let op = &ops[idx];
 
-            let edit_line = self
-                .editor
-                .line_index
-                .char_offset_to_position(op.start_offset)
-                .0;
-            let deleted_newlines = op
-                .deleted_text
-                .as_bytes()
-                .iter()
-                .filter(|b| **b == b'\n')
-                .count();
-            let inserted_newlines = op
-                .insert_text
-                .as_bytes()
-                .iter()
-                .filter(|b| **b == b'\n')
-                .count();
-            let line_delta = inserted_newlines as isize - deleted_newlines as isize;
-            if line_delta != 0 {
-                self.editor
-                    .folding_manager
-                    .apply_line_delta(edit_line, line_delta);
-            }
+            self.update_folds_for_edit(op.start_offset, op.delete_len, &op.insert_text);
 
             if op.delete_len > 0 {
                 self.editor
@@ -2720,8 +4382,11 @@ impl CommandExecutor {
             });
         }
 
-        let (new_carets, new_primary_index) =
-            crate::selection_set::normalize_selections(new_carets, primary_index);
+        let (new_carets, new_primary_index) = crate::selection_set::normalize_selections(
+            new_carets,
+            primary_index,
+            self.selection_merge_policy,
+        );
         let primary = new_carets
             .get(new_primary_index)
             .cloned()
@@ -2781,14 +4446,84 @@ impl CommandExecutor {
             undo_group_id: Some(group_id),
         });
 
-        Ok(CommandResult::Success)
+        Ok(CommandResult::Success)
+    }
+
+    fn leading_whitespace_prefix(line_text: &str) -> String {
+        line_text
+            .chars()
+            .take_while(|ch| *ch == ' ' || *ch == '\t')
+            .collect()
+    }
+
+    /// Width in columns of `indent` (spaces and tabs).
+    fn indent_columns(&self, indent: &str) -> usize {
+        let tab_width = self.editor.layout_engine.tab_width().max(1);
+        let mut columns = 0usize;
+        for ch in indent.chars() {
+            match ch {
+                '\t' => columns = (columns / tab_width + 1) * tab_width,
+                ' ' => columns += 1,
+                _ => {}
+            }
+        }
+        columns
+    }
+
+    /// Rewrite `indent` (spaces and tabs) in the current [`TabKeyBehavior`], keeping its width.
+    fn reindent_whitespace(&self, indent: &str) -> String {
+        self.indent_for_columns(self.indent_columns(indent))
     }
 
-    fn leading_whitespace_prefix(line_text: &str) -> String {
-        line_text
-            .chars()
-            .take_while(|ch| *ch == ' ' || *ch == '\t')
-            .collect()
+    /// Indentation `columns` wide in the current [`TabKeyBehavior`].
+    fn indent_for_columns(&self, columns: usize) -> String {
+        let tab_width = self.editor.layout_engine.tab_width().max(1);
+        match self.tab_key_behavior {
+            TabKeyBehavior::Tab => {
+                let mut out = "\t".repeat(columns / tab_width);
+                out.push_str(&" ".repeat(columns % tab_width));
+                out
+            }
+            TabKeyBehavior::Spaces => " ".repeat(columns),
+        }
+    }
+
+    /// Re-indent a multi-line paste to a caret line indented by `target_indent` (see
+    /// [`EditCommand::InsertTextReindented`]).
+    fn reindent_pasted_text(&self, text: &str, target_indent: &str) -> String {
+        let lines: Vec<&str> = text.split('\n').collect();
+        let indent_of = |line: &str| self.indent_columns(&Self::leading_whitespace_prefix(line));
+        let is_blank = |line: &str| line.chars().all(|ch| ch == ' ' || ch == '\t');
+
+        let first_is_indented = lines[0].starts_with([' ', '\t']) && !is_blank(lines[0]);
+        let common = lines
+            .iter()
+            .enumerate()
+            .filter(|&(idx, line)| (idx > 0 || first_is_indented) && !is_blank(line))
+            .map(|(_, line)| indent_of(line))
+            .min()
+            .unwrap_or(0);
+        let target = self.indent_columns(target_indent);
+
+        let mut out = String::with_capacity(text.len());
+        for (idx, line) in lines.iter().enumerate() {
+            if idx > 0 {
+                out.push('\n');
+            }
+            let body = line.trim_start_matches([' ', '\t']);
+            if idx == 0 {
+                if first_is_indented {
+                    out.push_str(&self.indent_for_columns(indent_of(line) - common));
+                    out.push_str(body);
+                } else {
+                    out.push_str(line);
+                }
+            } else if !body.is_empty() {
+                out.push_str(&self.indent_for_columns(target + indent_of(line) - common));
+                out.push_str(body);
+            }
+        }
+        out
     }
 
     fn indent_unit(&self) -> String {
@@ -2819,7 +4554,11 @@ impl CommandExecutor {
         selections.push(primary_selection);
         selections.extend(self.editor.secondary_selections.iter().cloned());
 
-        let (selections, primary_index) = crate::selection_set::normalize_selections(selections, 0);
+        let (selections, primary_index) = crate::selection_set::normalize_selections(
+            selections,
+            0,
+            self.selection_merge_policy.for_edit(),
+        );
 
         struct Op {
             selection_index: usize,
@@ -2853,7 +4592,13 @@ impl CommandExecutor {
                     .line_index
                     .get_line_text(range_start_pos.line)
                     .unwrap_or_default();
-                Self::leading_whitespace_prefix(&line_text)
+                self.indent_provider
+                    .as_ref()
+                    .and_then(|provider| {
+                        provider.indent_for_new_line(&self.editor.line_index, start_offset)
+                    })
+                    .map(|indent| self.reindent_whitespace(&indent))
+                    .unwrap_or_else(|| Self::leading_whitespace_prefix(&line_text))
             } else {
                 String::new()
             };
@@ -2938,8 +4683,11 @@ impl CommandExecutor {
             });
         }
 
-        let (new_carets, new_primary_index) =
-            crate::selection_set::normalize_selections(new_carets, primary_index);
+        let (new_carets, new_primary_index) = crate::selection_set::normalize_selections(
+            new_carets,
+            primary_index,
+            self.selection_merge_policy,
+        );
         let primary = new_carets
             .get(new_primary_index)
             .cloned()
@@ -3435,8 +5183,11 @@ impl CommandExecutor {
             });
         }
 
-        let (mapped, mapped_primary) =
-            crate::selection_set::normalize_selections(mapped, primary_index);
+        let (mapped, mapped_primary) = crate::selection_set::normalize_selections(
+            mapped,
+            primary_index,
+            self.selection_merge_policy,
+        );
         self.execute_cursor(CursorCommand::SetSelections {
             selections: mapped,
             primary_index: mapped_primary,
@@ -3811,8 +5562,11 @@ impl CommandExecutor {
             });
         }
 
-        let (mapped, mapped_primary) =
-            crate::selection_set::normalize_selections(mapped, primary_index);
+        let (mapped, mapped_primary) = crate::selection_set::normalize_selections(
+            mapped,
+            primary_index,
+            self.selection_merge_policy,
+        );
         self.execute_cursor(CursorCommand::SetSelections {
             selections: mapped,
             primary_index: mapped_primary,
@@ -4001,7 +5755,8 @@ impl CommandExecutor {
             });
         }
 
-        let (new_carets, primary_index) = crate::selection_set::normalize_selections(new_carets, 0);
+        let (new_carets, primary_index) =
+            crate::selection_set::normalize_selections(new_carets, 0, self.selection_merge_policy);
         self.execute_cursor(CursorCommand::SetSelections {
             selections: new_carets,
             primary_index,
@@ -4051,6 +5806,14 @@ impl CommandExecutor {
         &mut self,
         config: CommentConfig,
     ) -> Result<CommandResult, CommandError> {
+        let config = if !config.has_line() && !config.has_block() {
+            self.language_config
+                .as_ref()
+                .map(|language| language.comment.clone())
+                .unwrap_or(config)
+        } else {
+            config
+        };
         if !config.has_line() && !config.has_block() {
             return Err(CommandError::Other(
                 "ToggleComment requires at least one comment token".to_string(),
@@ -4118,9 +5881,174 @@ impl CommandExecutor {
         Ok(CommandResult::Success)
     }
 
+    fn execute_reindent_document_command(
+        &mut self,
+        from: IndentSettings,
+        to: IndentSettings,
+    ) -> Result<CommandResult, CommandError> {
+        if from.width == 0 || to.width == 0 {
+            return Err(CommandError::Other(
+                "Indent width must be greater than 0".to_string(),
+            ));
+        }
+
+        let line_index = &self.editor.line_index;
+        let mut edits: Vec<TextEditSpec> = Vec::new();
+        for line in 0..line_index.line_count() {
+            let line_text = line_index.get_line_text(line).unwrap_or_default();
+            if let Some((len, text)) = reindent_leading_whitespace(&line_text, from, to) {
+                let start = line_index.position_to_char_offset(line, 0);
+                edits.push(TextEditSpec {
+                    start,
+                    end: start + len,
+                    text,
+                });
+            }
+        }
+
+        self.execute_apply_text_edits_command(edits, None)
+    }
+
+    fn execute_duplicate_selection_command(&mut self) -> Result<CommandResult, CommandError> {
+        let before_selection = self.snapshot_selection_set();
+        if before_selection
+            .selections
+            .iter()
+            .all(|selection| selection.start == selection.end)
+        {
+            return self.execute_duplicate_lines_command();
+        }
+
+        // Text inserted at each offset; copies sharing an offset are concatenated in order.
+        let mut inserts: BTreeMap<usize, String> = BTreeMap::new();
+        // Per selection: insertion offset, chars inserted before its copy at that offset, and
+        // the new (anchor, active) relative to the copy's start.
+        let mut placements: Vec<(usize, usize, usize, usize)> = Vec::new();
+        // Carets share one copy per line: line -> (offset, chars before the copy).
+        let mut duplicated_lines: HashMap<usize, (usize, usize)> = HashMap::new();
+
+        for selection in &before_selection.selections {
+            let anchor = self.position_to_char_offset_clamped(selection.start);
+            let active = self.position_to_char_offset_clamped(selection.end);
+            if anchor != active {
+                let (start, end) = (anchor.min(active), anchor.max(active));
+                let text = self.editor.piece_table.get_range(start, end - start);
+                let len = end - start;
+                let insert = inserts.entry(end).or_default();
+                let before = insert.chars().count();
+                insert.push_str(&text);
+                let (new_anchor, new_active) = if anchor <= active { (0, len) } else { (len, 0) };
+                placements.push((end, before, new_anchor, new_active));
+                continue;
+            }
+
+            // Caret: copy its line below it (`"\n" + line` at the line end) and follow it.
+            let line = selection
+                .end
+                .line
+                .min(self.editor.line_index.line_count().saturating_sub(1));
+            let line_start = self.editor.line_index.position_to_char_offset(line, 0);
+            let column = active - line_start;
+            let (offset, before) = *duplicated_lines.entry(line).or_insert_with(|| {
+                let line_text = self
+                    .editor
+                    .line_index
+                    .get_line_text(line)
+                    .unwrap_or_default();
+                let offset = line_start + line_text.chars().count();
+                let insert = inserts.entry(offset).or_default();
+                let before = insert.chars().count();
+                insert.push('\n');
+                insert.push_str(&line_text);
+                (offset, before)
+            });
+            placements.push((offset, before, 1 + column, 1 + column));
+        }
+
+        // Post-edit start of each insertion: its offset plus everything inserted before it.
+        let mut shifted: HashMap<usize, usize> = HashMap::with_capacity(inserts.len());
+        let mut shift = 0usize;
+        for (&offset, text) in &inserts {
+            shifted.insert(offset, offset + shift);
+            shift += text.chars().count();
+        }
+        let selections = placements
+            .into_iter()
+            .map(|(offset, before, anchor, active)| {
+                let start = shifted[&offset] + before;
+                (start + anchor, start + active)
+            })
+            .collect();
+
+        let edits = inserts
+            .into_iter()
+            .map(|(offset, text)| TextEditSpec {
+                start: offset,
+                end: offset,
+                text,
+            })
+            .collect();
+        self.execute_apply_text_edits_command(
+            edits,
+            Some((selections, before_selection.primary_index)),
+        )
+    }
+
+    fn execute_transpose_command(&mut self, words: bool) -> Result<CommandResult, CommandError> {
+        let before_selection = self.snapshot_selection_set();
+        let line_count = self.editor.line_index.line_count();
+
+        let mut edits: Vec<TextEditSpec> = Vec::new();
+        let mut carets: Vec<(usize, usize)> = Vec::with_capacity(before_selection.selections.len());
+        for selection in &before_selection.selections {
+            let caret = selection.end;
+            let line = caret.line.min(line_count.saturating_sub(1));
+            let line_text = self
+                .editor
+                .line_index
+                .get_line_text(line)
+                .unwrap_or_default();
+            let ranges = if words {
+                transpose_words_ranges(&line_text, caret.column)
+            } else {
+                transpose_chars_ranges(&line_text, caret.column)
+            };
+            let Some((left, right)) = ranges else {
+                let offset = self.position_to_char_offset_clamped(caret);
+                carets.push((offset, offset));
+                continue;
+            };
+
+            let line_start = self.editor.line_index.position_to_char_offset(line, 0);
+            let slice = |range: Range<usize>| {
+                &line_text[byte_offset_for_char_column(&line_text, range.start)
+                    ..byte_offset_for_char_column(&line_text, range.end)]
+            };
+            let mut text = String::from(slice(right.clone()));
+            text.push_str(slice(left.end..right.start));
+            text.push_str(slice(left.clone()));
+            edits.push(TextEditSpec {
+                start: line_start + left.start,
+                end: line_start + right.end,
+                text,
+            });
+            // Transposing keeps the text length, so the offset is valid after the edit too.
+            carets.push((line_start + right.end, line_start + right.end));
+        }
+
+        // Carets on the same words produce the same (or overlapping) edits; keep the first.
+        edits.sort_by_key(|edit| edit.start);
+        edits.dedup_by(|later, kept| later.start < kept.end);
+
+        self.execute_apply_text_edits_command(edits, Some((carets, before_selection.primary_index)))
+    }
+
+    /// Apply `edits` as one undo step; `selections` (`(anchor, active)` offsets valid after the
+    /// edit, plus the primary index) replace the selections when given.
     fn execute_apply_text_edits_command(
         &mut self,
         mut edits: Vec<TextEditSpec>,
+        selections: Option<(Vec<(usize, usize)>, usize)>,
     ) -> Result<CommandResult, CommandError> {
         self.undo_redo.end_group();
 
@@ -4212,6 +6140,33 @@ impl CommandExecutor {
             .collect();
         self.apply_text_ops(apply_ops)?;
 
+        if let Some((offsets, primary_index)) = selections {
+            let position = |offset: usize| {
+                let (line, column) = self.editor.line_index.char_offset_to_position(offset);
+                Position::new(line, column)
+            };
+            let selections: Vec<Selection> = offsets
+                .into_iter()
+                .map(|(anchor, active)| {
+                    let (start, end) = (position(anchor), position(active));
+                    Selection {
+                        start,
+                        end,
+                        direction: crate::selection_set::selection_direction(start, end),
+                    }
+                })
+                .collect();
+            let (selections, primary_index) = crate::selection_set::normalize_selections(
+                selections,
+                primary_index,
+                self.selection_merge_policy,
+            );
+            self.execute_cursor(CursorCommand::SetSelections {
+                selections,
+                primary_index,
+            })?;
+        }
+
         let after_selection = self.snapshot_selection_set();
 
         let edits: Vec<TextEdit> = ops
@@ -4893,10 +6848,6 @@ impl CommandExecutor {
         Ok(CommandResult::Success)
     }
 
-    fn is_word_char(ch: char) -> bool {
-        ch == '_' || ch.is_alphanumeric()
-    }
-
     fn word_range_in_line(line_text: &str, column: usize) -> Option<(usize, usize)> {
         if line_text.is_empty() {
             return None;
@@ -4922,7 +6873,7 @@ impl CommandExecutor {
 
         let pick_part = |idx: usize, parts: &[(usize, usize, &str)]| -> Option<(usize, usize)> {
             let (s, e, text) = parts.get(idx)?;
-            if text.chars().any(Self::is_word_char) {
+            if text.chars().any(crate::text::is_word_char) {
                 Some((*s, *e))
             } else {
                 None
@@ -5354,8 +7305,8 @@ impl CommandExecutor {
         let before_char_count = self.editor.piece_table.char_count();
         let before_selection = self.snapshot_selection_set();
 
-        let affected_line = self.editor.line_index.char_offset_to_position(offset).0;
         let inserted_newlines = text.as_bytes().iter().filter(|b| **b == b'\n').count();
+        self.update_folds_for_edit(offset, 0, &text);
 
         // Execute insertion
         self.editor.piece_table.insert(offset, &text);
@@ -5364,9 +7315,6 @@ impl CommandExecutor {
         self.apply_text_change_to_line_index_and_layout(offset, "", &text);
 
         if inserted_newlines > 0 {
-            self.editor
-                .folding_manager
-                .apply_line_delta(affected_line, inserted_newlines as isize);
             self.editor
                 .folding_manager
                 .clamp_to_line_count(self.editor.line_index.line_count());
@@ -5446,7 +7394,7 @@ impl CommandExecutor {
             .iter()
             .filter(|b| **b == b'\n')
             .count();
-        let affected_line = self.editor.line_index.char_offset_to_position(start).0;
+        self.update_folds_for_edit(start, length, "");
 
         // Execute deletion
         self.editor.piece_table.delete(start, length);
@@ -5455,9 +7403,6 @@ impl CommandExecutor {
         self.apply_text_change_to_line_index_and_layout(start, &delta_deleted_text, "");
 
         if deleted_newlines > 0 {
-            self.editor
-                .folding_manager
-                .apply_line_delta(affected_line, -(deleted_newlines as isize));
             self.editor
                 .folding_manager
                 .clamp_to_line_count(self.editor.line_index.line_count());
@@ -5536,7 +7481,7 @@ impl CommandExecutor {
         let delta_deleted_text = deleted_text.clone();
         let delta_inserted_text = text.clone();
 
-        let affected_line = self.editor.line_index.char_offset_to_position(start).0;
+        self.update_folds_for_edit(start, length, &text);
         let deleted_newlines = deleted_text
             .as_bytes()
             .iter()
@@ -5571,9 +7516,6 @@ impl CommandExecutor {
         self.apply_text_change_to_line_index_and_layout(start, &deleted_text, &text);
 
         if line_delta != 0 {
-            self.editor
-                .folding_manager
-                .apply_line_delta(affected_line, line_delta);
             self.editor
                 .folding_manager
                 .clamp_to_line_count(self.editor.line_index.line_count());
@@ -5685,19 +7627,142 @@ impl CommandExecutor {
         })
     }
 
+    /// Resolve an explicit scope, or fall back to the primary selection.
+    fn search_scope(&self, range: Option<(usize, usize)>) -> Result<SearchMatch, CommandError> {
+        let Some((start, end)) = range else {
+            return self.primary_selection_char_range().ok_or_else(|| {
+                CommandError::Other("Search scope requires a non-empty selection".to_string())
+            });
+        };
+        if start > end || end > self.editor.piece_table.char_count() {
+            return Err(CommandError::InvalidRange { start, end });
+        }
+        Ok(SearchMatch { start, end })
+    }
+
+    fn execute_find_in_range_command(
+        &mut self,
+        query: String,
+        options: SearchOptions,
+        range: Option<(usize, usize)>,
+    ) -> Result<CommandResult, CommandError> {
+        let selection = self.primary_selection_char_range();
+        let (scope, from) = match (range, self.find_in_range_scope) {
+            // Repeated on the match it selected: keep the original scope and move past it.
+            (None, Some((scope, last))) if selection == Some(last) => (scope, last.end),
+            // Scoped to the selection itself: start at its beginning.
+            (None, _) => {
+                let scope = self.search_scope(None)?;
+                (scope, scope.start)
+            }
+            (Some(_), _) => {
+                let scope = self.search_scope(range)?;
+                let from = match selection {
+                    Some(selection) => selection.end,
+                    None => self.cursor_char_offset(),
+                };
+                (scope, from)
+            }
+        };
+        let text = self.editor.piece_table.get_text();
+
+        let found = find_next_in_range(&text, &query, options, scope, from)
+            .map_err(|err| CommandError::Other(err.to_string()))?;
+        let Some(m) = found else {
+            return Ok(CommandResult::SearchNotFound);
+        };
+
+        self.set_primary_selection_by_char_range(m);
+        self.find_in_range_scope = range.is_none().then_some((scope, m));
+
+        Ok(CommandResult::SearchMatch {
+            start: m.start,
+            end: m.end,
+        })
+    }
+
+    fn execute_incremental_find_command(
+        &mut self,
+        query: String,
+        options: SearchOptions,
+        from_offset: usize,
+    ) -> Result<CommandResult, CommandError> {
+        if from_offset > self.editor.piece_table.char_count() {
+            return Err(CommandError::InvalidOffset(from_offset));
+        }
+
+        let found = if query.is_empty() {
+            None
+        } else {
+            let text = self.editor.piece_table.get_text();
+            let find = |from: usize| {
+                find_next(&text, &query, options, from)
+                    .map_err(|err| CommandError::Other(err.to_string()))
+            };
+            match find(from_offset)? {
+                Some(m) => Some(m),
+                None => find(0)?,
+            }
+        };
+
+        let Some(m) = found else {
+            self.set_primary_selection_by_char_range(SearchMatch {
+                start: from_offset,
+                end: from_offset,
+            });
+            return Ok(CommandResult::SearchNotFound);
+        };
+
+        self.set_primary_selection_by_char_range(m);
+
+        Ok(CommandResult::SearchMatch {
+            start: m.start,
+            end: m.end,
+        })
+    }
+
+    fn execute_goto_diagnostic_command(
+        &mut self,
+        min_severity: DiagnosticSeverity,
+        forward: bool,
+    ) -> Result<CommandResult, CommandError> {
+        let char_count = self.editor.piece_table.char_count();
+        let from = self.cursor_char_offset();
+        let Some(diagnostic) = crate::diagnostics::next_diagnostic(
+            &self.editor.diagnostics,
+            from,
+            min_severity,
+            forward,
+        ) else {
+            return Ok(CommandResult::SearchNotFound);
+        };
+        let target = diagnostic
+            .range
+            .start
+            .min(diagnostic.range.end)
+            .min(char_count);
+
+        self.set_primary_selection_by_char_range(SearchMatch {
+            start: target,
+            end: target,
+        });
+        self.preferred_x_cells = None;
+
+        Ok(CommandResult::Position(self.editor.cursor_position))
+    }
+
     fn compile_user_regex(
         query: &str,
         options: SearchOptions,
     ) -> Result<regex::Regex, CommandError> {
-        RegexBuilder::new(query)
-            .case_insensitive(!options.case_sensitive)
-            .multi_line(true)
-            .build()
-            .map_err(|err| CommandError::Other(format!("Invalid regex: {}", err)))
+        let (pattern, pattern_options) = crate::search::search_pattern(query, options);
+        crate::pattern::compile_regex(&pattern, pattern_options)
+            .map_err(|err| CommandError::Other(err.to_string()))
     }
 
     fn regex_expand_replacement(
         re: &regex::Regex,
+        // May be a prefix of the document (scoped searches stop matching at the scope end).
         text: &str,
         index: &CharIndex,
         range: SearchMatch,
@@ -5814,6 +7879,7 @@ impl CommandExecutor {
         query: String,
         replacement: String,
         options: SearchOptions,
+        scope: Option<SearchMatch>,
     ) -> Result<CommandResult, CommandError> {
         if query.is_empty() {
             return Err(CommandError::Other("Search query is empty".to_string()));
@@ -5821,14 +7887,21 @@ impl CommandExecutor {
 
         let replacement = crate::text::normalize_crlf_to_lf_string(replacement);
         let text = self.editor.piece_table.get_text();
-        let matches =
-            find_all(&text, &query, options).map_err(|err| CommandError::Other(err.to_string()))?;
+        let matches = match scope {
+            Some(scope) => find_all_in_range(&text, &query, options, scope),
+            None => find_all(&text, &query, options),
+        }
+        .map_err(|err| CommandError::Other(err.to_string()))?;
         if matches.is_empty() {
             return Err(CommandError::Other("No match found".to_string()));
         }
         let match_count = matches.len();
 
         let index = CharIndex::new(&text);
+        let haystack = match scope {
+            Some(scope) => &text[..index.char_to_byte(scope.end)],
+            None => text.as_str(),
+        };
 
         struct Op {
             start_before: usize,
@@ -5851,7 +7924,7 @@ impl CommandExecutor {
                         .to_string()
                 };
                 let inserted_text =
-                    Self::regex_expand_replacement(&re, &text, &index, m, &replacement)?;
+                    Self::regex_expand_replacement(&re, haystack, &index, m, &replacement)?;
                 let inserted_text = crate::text::normalize_crlf_to_lf_string(inserted_text);
                 let inserted_len = inserted_text.chars().count();
                 ops.push(Op {
@@ -5906,7 +7979,13 @@ impl CommandExecutor {
             .collect();
         self.apply_text_ops(apply_ops)?;
 
-        if let Some(first) = ops.first() {
+        if let Some(scope) = scope {
+            let end = scope.end as i64 + delta;
+            self.set_primary_selection_by_char_range(SearchMatch {
+                start: scope.start,
+                end: end.max(scope.start as i64) as usize,
+            });
+        } else if let Some(first) = ops.first() {
             let caret_end = first.start_after + first.inserted_len;
             let select_end = if first.inserted_len == 0 {
                 first.start_after
@@ -5965,11 +8044,11 @@ impl CommandExecutor {
     }
 
     fn execute_backspace_command(&mut self) -> Result<CommandResult, CommandError> {
-        self.execute_delete_like_command(false)
+        self.execute_delete_like_command(false, &[])
     }
 
     fn execute_delete_forward_command(&mut self) -> Result<CommandResult, CommandError> {
-        self.execute_delete_like_command(true)
+        self.execute_delete_like_command(true, &[])
     }
 
     fn execute_delete_to_prev_tab_stop_command(&mut self) -> Result<CommandResult, CommandError> {
@@ -6108,22 +8187,7 @@ impl CommandExecutor {
                 continue;
             }
 
-            let edit_line = self
-                .editor
-                .line_index
-                .char_offset_to_position(op.start_offset)
-                .0;
-            let deleted_newlines = op
-                .deleted_text
-                .as_bytes()
-                .iter()
-                .filter(|b| **b == b'\n')
-                .count();
-            if deleted_newlines > 0 {
-                self.editor
-                    .folding_manager
-                    .apply_line_delta(edit_line, -(deleted_newlines as isize));
-            }
+            self.update_folds_for_edit(op.start_offset, op.delete_len, "");
 
             self.editor
                 .piece_table
@@ -6154,8 +8218,11 @@ impl CommandExecutor {
             });
         }
 
-        let (new_carets, new_primary_index) =
-            crate::selection_set::normalize_selections(new_carets, primary_index);
+        let (new_carets, new_primary_index) = crate::selection_set::normalize_selections(
+            new_carets,
+            primary_index,
+            self.selection_merge_policy,
+        );
         let primary = new_carets
             .get(new_primary_index)
             .cloned()
@@ -6370,8 +8437,11 @@ impl CommandExecutor {
             });
         }
 
-        let (new_carets, new_primary_index) =
-            crate::selection_set::normalize_selections(new_carets, primary_index);
+        let (new_carets, new_primary_index) = crate::selection_set::normalize_selections(
+            new_carets,
+            primary_index,
+            self.selection_merge_policy,
+        );
         let primary = new_carets
             .get(new_primary_index)
             .cloned()
@@ -6431,9 +8501,11 @@ impl CommandExecutor {
         Ok(CommandResult::Success)
     }
 
+    /// `pairs` only affects backward deletion: a caret between an empty pair deletes both chars.
     fn execute_delete_like_command(
         &mut self,
         forward: bool,
+        pairs: &[(char, char)],
     ) -> Result<CommandResult, CommandError> {
         // Any delete-like action should end an open insert coalescing group, even if it turns out
         // to be a no-op (e.g. backspace at the beginning of the document).
@@ -6481,6 +8553,8 @@ impl CommandExecutor {
                     }
                 } else if caret_offset == 0 {
                     (0, 0)
+                } else if self.is_between_pair(caret_offset, pairs) {
+                    (caret_offset - 1, caret_offset + 1)
                 } else {
                     (caret_offset - 1, caret_offset)
                 }
@@ -6557,8 +8631,11 @@ impl CommandExecutor {
             });
         }
 
-        let (new_carets, new_primary_index) =
-            crate::selection_set::normalize_selections(new_carets, primary_index);
+        let (new_carets, new_primary_index) = crate::selection_set::normalize_selections(
+            new_carets,
+            primary_index,
+            self.selection_merge_policy,
+        );
         let primary = new_carets
             .get(new_primary_index)
             .cloned()
@@ -6618,6 +8695,19 @@ impl CommandExecutor {
         Ok(CommandResult::Success)
     }
 
+    /// Whether `offset` sits between the open and close char of one of `pairs` (e.g. `(|)`).
+    fn is_between_pair(&self, offset: usize, pairs: &[(char, char)]) -> bool {
+        if pairs.is_empty() || offset == 0 || offset >= self.editor.piece_table.char_count() {
+            return false;
+        }
+        let around = self.editor.piece_table.get_range(offset - 1, 2);
+        let mut chars = around.chars();
+        let (Some(prev), Some(next)) = (chars.next(), chars.next()) else {
+            return false;
+        };
+        pairs.contains(&(prev, next))
+    }
+
     fn snapshot_selection_set(&self) -> SelectionSetSnapshot {
         let mut selections: Vec<Selection> =
             Vec::with_capacity(1 + self.editor.secondary_selections.len());
@@ -6630,7 +8720,8 @@ impl CommandExecutor {
         selections.push(primary);
         selections.extend(self.editor.secondary_selections.iter().cloned());
 
-        let (selections, primary_index) = crate::selection_set::normalize_selections(selections, 0);
+        let (selections, primary_index) =
+            crate::selection_set::normalize_selections(selections, 0, self.selection_merge_policy);
         SelectionSetSnapshot {
             selections,
             primary_index,
@@ -6713,28 +8804,12 @@ impl CommandExecutor {
                 });
             }
 
-            let edit_line = self.editor.line_index.char_offset_to_position(start).0;
             let deleted_text = if delete_len > 0 {
                 self.editor.piece_table.get_range(start, delete_len)
             } else {
                 String::new()
             };
-            let deleted_newlines = deleted_text
-                .as_bytes()
-                .iter()
-                .filter(|b| **b == b'\n')
-                .count();
-            let inserted_newlines = insert_text
-                .as_bytes()
-                .iter()
-                .filter(|b| **b == b'\n')
-                .count();
-            let line_delta = inserted_newlines as isize - deleted_newlines as isize;
-            if line_delta != 0 {
-                self.editor
-                    .folding_manager
-                    .apply_line_delta(edit_line, line_delta);
-            }
+            self.update_folds_for_edit(start, delete_len, insert_text);
 
             if delete_len > 0 {
                 self.editor.piece_table.delete(start, delete_len);
@@ -7204,8 +9279,11 @@ impl CommandExecutor {
                     }
                 }
 
-                let (selections, primary_index) =
-                    crate::selection_set::normalize_selections(selections, primary_index);
+                let (selections, primary_index) = crate::selection_set::normalize_selections(
+                    selections,
+                    primary_index,
+                    self.selection_merge_policy,
+                );
 
                 let primary = selections
                     .get(primary_index)
@@ -7246,8 +9324,11 @@ impl CommandExecutor {
                     });
                 }
 
-                let (selections, primary_index) =
-                    crate::selection_set::rect_selections(anchor, active);
+                let (selections, primary_index) = crate::selection_set::rect_selections(
+                    anchor,
+                    active,
+                    self.selection_merge_policy,
+                );
 
                 // Delegate to SetSelections so normalization rules are shared.
                 self.execute_cursor(CursorCommand::SetSelections {
@@ -7273,6 +9354,24 @@ impl CommandExecutor {
             CursorCommand::FindPrev { query, options } => {
                 self.execute_find_command(query, options, false)
             }
+            CursorCommand::FindNextInRange {
+                query,
+                options,
+                range,
+            } => self.execute_find_in_range_command(query, options, range),
+            CursorCommand::IncrementalFind {
+                query,
+                options,
+                from_offset,
+            } => self.execute_incremental_find_command(query, options, from_offset),
+            CursorCommand::GoToNextDiagnostic { min_severity } => {
+                self.execute_goto_diagnostic_command(min_severity, true)
+            }
+            CursorCommand::GoToPrevDiagnostic { min_severity } => {
+                self.execute_goto_diagnostic_command(min_severity, false)
+            }
+            CursorCommand::NextTabstop => self.execute_tabstop_command(true),
+            CursorCommand::PrevTabstop => self.execute_tabstop_command(false),
         }
     }
 
@@ -7312,6 +9411,21 @@ impl CommandExecutor {
                 self.tab_key_behavior = behavior;
                 Ok(CommandResult::Success)
             }
+            ViewCommand::ApplyIndentSettings { settings } => {
+                if settings.width == 0 {
+                    return Err(CommandError::Other(
+                        "Indent width must be greater than 0".to_string(),
+                    ));
+                }
+
+                self.editor.layout_engine.set_tab_width(settings.width);
+                self.tab_key_behavior = if settings.use_tabs {
+                    TabKeyBehavior::Tab
+                } else {
+                    TabKeyBehavior::Spaces
+                };
+                Ok(CommandResult::Success)
+            }
             ViewCommand::ScrollTo { line } => {
                 if line >= self.editor.line_index.line_count() {
                     return Err(CommandError::InvalidPosition { line, column: 0 });
@@ -7352,6 +9466,19 @@ impl CommandExecutor {
                 self.editor.interval_tree.remove(start, end, style_id);
                 Ok(CommandResult::Success)
             }
+            StyleCommand::ClearStyleRange { layer, start, end } => {
+                if start > end {
+                    return Err(CommandError::InvalidRange { start, end });
+                }
+
+                if let Some(tree) = self.editor.style_layers.get_mut(&layer) {
+                    tree.remove_range(start, end);
+                    if tree.is_empty() {
+                        self.editor.style_layers.remove(&layer);
+                    }
+                }
+                Ok(CommandResult::Success)
+            }
             StyleCommand::Fold {
                 start_line,
                 end_line,
@@ -7379,6 +9506,29 @@ impl CommandExecutor {
         }
     }
 
+    /// Update fold regions for replacing `delete_len` chars at `start` with `inserted`.
+    ///
+    /// Must be called before the edit is applied to the line index.
+    fn update_folds_for_edit(&mut self, start: usize, delete_len: usize, inserted: &str) {
+        let folds = &self.editor.folding_manager;
+        if folds.derived_regions().is_empty() && folds.user_regions().is_empty() {
+            return;
+        }
+        let inserted_newlines = inserted.as_bytes().iter().filter(|b| **b == b'\n').count();
+        let (start_line, start_column) = self.editor.line_index.char_offset_to_position(start);
+        let (end_line, end_column) = self
+            .editor
+            .line_index
+            .char_offset_to_position(start + delete_len);
+        self.editor.folding_manager.apply_edit(
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+            inserted_newlines,
+        );
+    }
+
     fn apply_text_change_to_line_index_and_layout(
         &mut self,
         start_offset: usize,
@@ -7656,6 +9806,83 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_clear_style_range_in_layer() {
+        let mut executor = CommandExecutor::new("Hello World", 80);
+        let layer = StyleLayerId::SEMANTIC_TOKENS;
+        let mut tree = IntervalTree::new();
+        tree.insert(crate::intervals::Interval::new(0, 11, 1));
+        tree.insert(crate::intervals::Interval::new(6, 11, 2));
+        executor.editor_mut().style_layers.insert(layer, tree);
+
+        executor
+            .execute(Command::Style(StyleCommand::ClearStyleRange {
+                layer,
+                start: 4,
+                end: 7,
+            }))
+            .unwrap();
+        assert_eq!(executor.editor().styles_at_offset(3), vec![1]);
+        assert!(executor.editor().styles_at_offset(5).is_empty());
+        assert_eq!(executor.editor().styles_at_offset(7), vec![1, 2]);
+
+        // Clearing everything drops the layer.
+        executor
+            .execute(Command::Style(StyleCommand::ClearStyleRange {
+                layer,
+                start: 0,
+                end: 11,
+            }))
+            .unwrap();
+        assert!(!executor.editor().style_layers.contains_key(&layer));
+
+        let result = executor.execute(Command::Style(StyleCommand::ClearStyleRange {
+            layer,
+            start: 5,
+            end: 2,
+        }));
+        assert!(matches!(result, Err(CommandError::InvalidRange { .. })));
+    }
+
+    #[test]
+    fn test_style_runs_match_styles_at_offset() {
+        let mut executor = CommandExecutor::new("fn main() { let x = 1; }", 80);
+        for (start, end, style_id) in [(0, 2, 1), (3, 7, 2), (10, 24, 3), (3, 5, 1)] {
+            executor
+                .execute(Command::Style(StyleCommand::AddStyle {
+                    start,
+                    end,
+                    style_id,
+                }))
+                .unwrap();
+        }
+        let mut layer = IntervalTree::new();
+        layer.insert(crate::intervals::Interval::new(4, 12, 1));
+        layer.insert(crate::intervals::Interval::new(16, 17, 9));
+        executor
+            .editor_mut()
+            .style_layers
+            .insert(StyleLayerId::SEMANTIC_TOKENS, layer);
+
+        let editor = executor.editor();
+        let runs = editor.style_runs(0, 24);
+        assert_eq!(runs.first().map(|r| r.start), Some(0));
+        assert_eq!(runs.last().map(|r| r.end), Some(24));
+        for pair in runs.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+            assert_ne!(pair[0].styles, pair[1].styles);
+        }
+        for run in &runs {
+            for offset in run.start..run.end {
+                assert_eq!(
+                    run.styles,
+                    editor.styles_at_offset(offset),
+                    "offset {offset}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_batch_execution() {
         let mut executor = CommandExecutor::new("", 80);
@@ -0,0 +1,222 @@
+//! Completion acceptance and filtering helpers.
+//!
+//! Completion sources (LSP, buffer words, snippets) only decide *what* to insert; the range an
+//! accepted item replaces is derived from the word around each caret. Keeping that logic here
+//! means every source edits the buffer the same way.
+
+use crate::commands::{EditorCore, Position, TextEditSpec};
+use crate::text::{word_end_column, word_start_column};
+
+/// How much of the word around the caret an accepted completion replaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompletionAcceptMode {
+    /// Replace only the typed prefix (word start → caret); text after the caret is kept.
+    #[default]
+    Insert,
+    /// Replace the whole word under the caret (word start → word end).
+    Replace,
+}
+
+/// Build the edit that accepts `new_text` at the primary caret.
+///
+/// - A non-empty primary selection is replaced as-is (regardless of `mode`).
+/// - Otherwise the edit starts at the beginning of the word ending at the caret, and ends at the
+///   caret ([`CompletionAcceptMode::Insert`]) or at the end of the word continuing after it
+///   ([`CompletionAcceptMode::Replace`]).
+pub fn completion_edit_at_caret(
+    editor: &EditorCore,
+    new_text: &str,
+    mode: CompletionAcceptMode,
+) -> TextEditSpec {
+    let (start, end) = match editor.selection() {
+        Some(sel) if sel.start != sel.end => (sel.start, sel.end),
+        _ => {
+            let caret = editor.cursor_position();
+            (caret, caret)
+        }
+    };
+    completion_edit_for_range(editor, start, end, new_text, mode)
+}
+
+/// Multi-caret variant of [`completion_edit_at_caret`]: one edit per caret/selection.
+///
+/// Edits are sorted by start offset (pre-edit coordinates, ready for
+/// [`EditCommand::ApplyTextEdits`](crate::EditCommand::ApplyTextEdits)). When two carets would
+/// touch the same word, only the first edit is kept so the batch never overlaps.
+pub fn completion_edits_at_carets(
+    editor: &EditorCore,
+    new_text: &str,
+    mode: CompletionAcceptMode,
+) -> Vec<TextEditSpec> {
+    let primary = match editor.selection() {
+        Some(sel) => (sel.start, sel.end),
+        None => {
+            let caret = editor.cursor_position();
+            (caret, caret)
+        }
+    };
+
+    let mut edits: Vec<TextEditSpec> = std::iter::once(primary)
+        .chain(
+            editor
+                .secondary_selections()
+                .iter()
+                .map(|sel| (sel.start, sel.end)),
+        )
+        .map(|(start, end)| completion_edit_for_range(editor, start, end, new_text, mode))
+        .collect();
+
+    edits.sort_by_key(|edit| (edit.start, edit.end));
+    let mut kept: Vec<TextEditSpec> = Vec::with_capacity(edits.len());
+    for edit in edits {
+        if let Some(prev) = kept.last()
+            && (edit.start < prev.end || (edit.start, edit.end) == (prev.start, prev.end))
+        {
+            continue;
+        }
+        kept.push(edit);
+    }
+    kept
+}
+
+fn completion_edit_for_range(
+    editor: &EditorCore,
+    a: Position,
+    b: Position,
+    new_text: &str,
+    mode: CompletionAcceptMode,
+) -> TextEditSpec {
+    let line_index = &editor.line_index;
+    let a = line_index.position_to_char_offset(a.line, a.column);
+    let b = line_index.position_to_char_offset(b.line, b.column);
+    if a != b {
+        return TextEditSpec {
+            start: a.min(b),
+            end: a.max(b),
+            text: new_text.to_string(),
+        };
+    }
+
+    let (line, column) = line_index.char_offset_to_position(a);
+    let line_text = line_index.get_line_text(line).unwrap_or_default();
+    let line_start = a - column;
+    let end_column = match mode {
+        CompletionAcceptMode::Insert => column,
+        CompletionAcceptMode::Replace => word_end_column(&line_text, column),
+    };
+
+    TextEditSpec {
+        start: line_start + word_start_column(&line_text, column),
+        end: line_start + end_column,
+        text: new_text.to_string(),
+    }
+}
+
+/// Score `candidate` against a typed `pattern` for completion filtering.
+///
+/// `pattern` must match as a case-insensitive subsequence of `candidate`, otherwise this
+/// returns `None`. Higher scores are better: consecutive matches, matches at word starts
+/// (start of text, after `_`/`-`/`.`/`:`/whitespace, or a lower→upper case change) and
+/// exact-case matches earn bonuses, and skipped characters cost a little. An empty pattern
+/// matches everything with score `0`.
+pub fn fuzzy_match_score(pattern: &str, candidate: &str) -> Option<i64> {
+    fuzzy_match_positions(pattern, candidate).map(|(score, _)| score)
+}
+
+/// Like [`fuzzy_match_score`], also returning the matched character indices of `candidate`
+/// (for highlighting the match).
+///
+/// Each pattern character scores `1`, plus `1` for an exact-case match and `8` at a word start.
+/// A match right after the previous one adds `5`; otherwise every skipped character costs `1`
+/// (at most `10` per gap, and characters before the first match count as skipped). The returned
+/// positions are the highest-scoring alignment (the leftmost one among equals).
+pub fn fuzzy_match_positions(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    const CONSECUTIVE: i64 = 5;
+    const MAX_GAP_PENALTY: usize = 10;
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let candidate: Vec<char> = candidate.chars().collect();
+    if pattern.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let n = candidate.len();
+    let at_word_start = |i: usize| match i.checked_sub(1).map(|i| candidate[i]) {
+        None => true,
+        Some(prev) => {
+            matches!(prev, '_' | '-' | '.' | ':')
+                || prev.is_whitespace()
+                || (prev.is_lowercase() && candidate[i].is_uppercase())
+        }
+    };
+    let gap_penalty = |skipped: usize| skipped.min(MAX_GAP_PENALTY) as i64;
+
+    // best[j][i]: best score of `pattern[..=j]` with `pattern[j]` matched at `candidate[i]`;
+    // from[j][i]: where `pattern[j - 1]` was matched for that score.
+    let mut best = vec![vec![None::<i64>; n]; pattern.len()];
+    let mut from = vec![vec![0usize; n]; pattern.len()];
+    for (j, &p) in pattern.iter().enumerate() {
+        // Best `best[j - 1][k]` over the `k` whose gap to the current `i` is capped.
+        let mut far: Option<(i64, usize)> = None;
+        for i in j..n {
+            if j > 0 && i > MAX_GAP_PENALTY {
+                let k = i - MAX_GAP_PENALTY - 1;
+                if let Some(prev) = best[j - 1][k]
+                    && far.is_none_or(|(s, _)| prev > s)
+                {
+                    far = Some((prev, k));
+                }
+            }
+            let c = candidate[i];
+            if !chars_eq_ignore_case(c, p) {
+                continue;
+            }
+            let mut score = 1 + i64::from(c == p);
+            if at_word_start(i) {
+                score += 8;
+            }
+            if j == 0 {
+                best[j][i] = Some(score - gap_penalty(i));
+                continue;
+            }
+            let mut previous = far.map(|(s, k)| (s - gap_penalty(MAX_GAP_PENALTY), k));
+            let near = (j - 1).max(i.saturating_sub(MAX_GAP_PENALTY));
+            for (k, prev) in best[j - 1][near..i].iter().enumerate() {
+                let (k, Some(prev)) = (near + k, *prev) else {
+                    continue;
+                };
+                let transition = if k + 1 == i {
+                    CONSECUTIVE
+                } else {
+                    -gap_penalty(i - k - 1)
+                };
+                if previous.is_none_or(|(s, _)| prev + transition > s) {
+                    previous = Some((prev + transition, k));
+                }
+            }
+            if let Some((prev, k)) = previous {
+                best[j][i] = Some(score + prev);
+                from[j][i] = k;
+            }
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut i, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(i, score)| score.map(|score| (i, score)))
+        .fold(None::<(usize, i64)>, |acc, (i, score)| match acc {
+            Some((_, s)) if s >= score => acc,
+            _ => Some((i, score)),
+        })?;
+    let mut positions = vec![0usize; pattern.len()];
+    for j in (0..pattern.len()).rev() {
+        positions[j] = i;
+        i = from[j][i];
+    }
+    Some((score, positions))
+}
+
+fn chars_eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
@@ -10,8 +10,16 @@
 //!
 //! Decorations are derived editor state. They typically originate from integrations via
 //! [`ProcessingEdit`](crate::processing::ProcessingEdit) and are rendered by the host.
+//!
+//! [`diagnostic_underline_decorations`] maps the document's diagnostics to underline decorations
+//! for [`DecorationLayerId::DIAGNOSTICS`].
 
-use crate::intervals::StyleId;
+use crate::diagnostics::{Diagnostic, DiagnosticSeverity, DiagnosticTag};
+use crate::intervals::{
+    DIAGNOSTIC_DEPRECATED_STYLE_ID, DIAGNOSTIC_ERROR_STYLE_ID, DIAGNOSTIC_HINT_STYLE_ID,
+    DIAGNOSTIC_INFORMATION_STYLE_ID, DIAGNOSTIC_UNNECESSARY_STYLE_ID, DIAGNOSTIC_WARNING_STYLE_ID,
+    StyleId,
+};
 
 /// A source/layer identifier for decorations.
 ///
@@ -28,6 +36,12 @@ impl DecorationLayerId {
     pub const DOCUMENT_LINKS: Self = Self(3);
     /// Decorations representing match highlights (search matches, bracket matches, etc.).
     pub const MATCH_HIGHLIGHTS: Self = Self(4);
+    /// Underline decorations derived from diagnostics (see [`diagnostic_underline_decorations`]).
+    pub const DIAGNOSTICS: Self = Self(5);
+    /// Whole-line background decorations (e.g. the current-line highlight).
+    pub const LINE_BACKGROUNDS: Self = Self(6);
+    /// Hover information rendered inline (e.g. LSP `textDocument/hover` at the caret).
+    pub const HOVER: Self = Self(7);
 
     /// Create a new layer id.
     pub fn new(id: u32) -> Self {
@@ -75,10 +89,40 @@ pub enum DecorationKind {
     DocumentLink,
     /// Highlight decoration (e.g. match/bracket highlights).
     Highlight,
+    /// Underline over the decoration range (e.g. diagnostic squiggles, spell-check).
+    ///
+    /// Text-less underline decorations set [`ComposedCell::underline`](crate::ComposedCell::underline)
+    /// on the covered cells of the composed snapshot.
+    Underline {
+        /// How the underline should be drawn.
+        style: UnderlineStyle,
+    },
+    /// Background for whole logical lines (e.g. current line, diagnostic lines, diff hunks).
+    ///
+    /// Every logical line the decoration range touches gets `style_id` as its row background
+    /// ([`HeadlessLine::background_style`](crate::HeadlessLine::background_style) /
+    /// [`ComposedLine::background_style`](crate::ComposedLine::background_style)), including all
+    /// soft-wrapped segments. An empty range marks the line containing it. When several
+    /// decorations cover a line, the first one in layer order wins.
+    LineBackground {
+        /// Style id used as the row background.
+        style_id: StyleId,
+    },
     /// A custom, integration-defined kind.
     Custom(u32),
 }
 
+/// How an underline decoration is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UnderlineStyle {
+    /// A solid straight line.
+    Straight,
+    /// A wavy line (squiggle), typically used for diagnostics.
+    Wavy,
+    /// A dotted line.
+    Dotted,
+}
+
 /// A single decoration item.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Decoration {
@@ -97,3 +141,120 @@ pub struct Decoration {
     /// Optional integration-specific payload (JSON text).
     pub data_json: Option<String>,
 }
+
+/// Returns the built-in underline style id for a diagnostic severity.
+///
+/// Diagnostics without a severity are treated as errors.
+pub fn diagnostic_style_id(severity: Option<DiagnosticSeverity>) -> StyleId {
+    match severity.unwrap_or(DiagnosticSeverity::Error) {
+        DiagnosticSeverity::Error => DIAGNOSTIC_ERROR_STYLE_ID,
+        DiagnosticSeverity::Warning => DIAGNOSTIC_WARNING_STYLE_ID,
+        DiagnosticSeverity::Information => DIAGNOSTIC_INFORMATION_STYLE_ID,
+        DiagnosticSeverity::Hint => DIAGNOSTIC_HINT_STYLE_ID,
+    }
+}
+
+/// Returns the built-in style id for a diagnostic tag.
+pub fn diagnostic_tag_style_id(tag: DiagnosticTag) -> StyleId {
+    match tag {
+        DiagnosticTag::Unnecessary => DIAGNOSTIC_UNNECESSARY_STYLE_ID,
+        DiagnosticTag::Deprecated => DIAGNOSTIC_DEPRECATED_STYLE_ID,
+    }
+}
+
+/// Convert diagnostics into underline decorations (for [`DecorationLayerId::DIAGNOSTICS`]).
+///
+/// Where diagnostics overlap, the overlapping part is underlined with the most severe
+/// diagnostic's style; each decoration's tooltip lists the messages of the diagnostics it
+/// covers. Empty ranges are skipped. The result is sorted by range and non-overlapping.
+///
+/// Tags of the covering diagnostics add [`diagnostic_tag_style_id`] styles after the underline
+/// style, so hosts can fade unused code or strike through deprecated code.
+pub fn diagnostic_underline_decorations(diagnostics: &[Diagnostic]) -> Vec<Decoration> {
+    let ranges: Vec<(usize, usize, &Diagnostic)> = diagnostics
+        .iter()
+        .map(|d| {
+            let start = d.range.start.min(d.range.end);
+            let end = d.range.start.max(d.range.end);
+            (start, end, d)
+        })
+        .filter(|(start, end, _)| start < end)
+        .collect();
+
+    let mut bounds: Vec<usize> = ranges
+        .iter()
+        .flat_map(|(start, end, _)| [*start, *end])
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    // Sweep over the elementary segments between range boundaries, tracking covering ranges.
+    let mut by_start: Vec<usize> = (0..ranges.len()).collect();
+    by_start.sort_by_key(|&i| ranges[i].0);
+    let mut next = 0usize;
+    let mut active: Vec<usize> = Vec::new();
+
+    // Merged underline spans: (start, end, styles, indices of covering diagnostics).
+    let mut spans: Vec<(usize, usize, Vec<StyleId>, Vec<usize>)> = Vec::new();
+    for window in bounds.windows(2) {
+        let (seg_start, seg_end) = (window[0], window[1]);
+        active.retain(|&i| ranges[i].1 > seg_start);
+        while next < by_start.len() && ranges[by_start[next]].0 <= seg_start {
+            active.push(by_start[next]);
+            next += 1;
+        }
+
+        let Some(severity) = active
+            .iter()
+            .map(|&i| ranges[i].2.severity.unwrap_or(DiagnosticSeverity::Error))
+            .max_by_key(|s| s.rank())
+        else {
+            continue;
+        };
+        let mut tags: Vec<DiagnosticTag> = active
+            .iter()
+            .flat_map(|&i| ranges[i].2.tags.iter().copied())
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        let styles: Vec<StyleId> = std::iter::once(diagnostic_style_id(Some(severity)))
+            .chain(tags.into_iter().map(diagnostic_tag_style_id))
+            .collect();
+
+        match spans.last_mut() {
+            Some((_, end, last_styles, covering))
+                if *end == seg_start && *last_styles == styles =>
+            {
+                *end = seg_end;
+                covering.extend_from_slice(&active);
+            }
+            _ => spans.push((seg_start, seg_end, styles, active.clone())),
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|(start, end, styles, mut covering)| {
+            // List each diagnostic once, in input order.
+            covering.sort_unstable();
+            covering.dedup();
+            let tooltip = covering
+                .iter()
+                .map(|&i| ranges[i].2.message.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            Decoration {
+                range: DecorationRange::new(start, end),
+                placement: DecorationPlacement::Before,
+                kind: DecorationKind::Underline {
+                    style: UnderlineStyle::Wavy,
+                },
+                text: None,
+                styles,
+                tooltip: Some(tooltip),
+                data_json: None,
+            }
+        })
+        .collect()
+}
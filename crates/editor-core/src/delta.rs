@@ -55,9 +55,328 @@ pub struct TextDelta {
     pub undo_group_id: Option<usize>,
 }
 
+/// Errors returned by [`TextDelta::apply_to`] and [`TextDelta::compose`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeltaError {
+    /// The target text does not have the delta's `before_char_count` characters.
+    CharCountMismatch {
+        /// Character count the delta expects (`before_char_count`).
+        expected: usize,
+        /// Character count of the target text.
+        actual: usize,
+    },
+    /// An edit's deleted range extends past the end of the text it is applied to.
+    EditOutOfBounds {
+        /// Index of the edit in [`TextDelta::edits`].
+        index: usize,
+    },
+    /// The text covered by an edit differs from its `deleted_text`.
+    DeletedTextMismatch {
+        /// Index of the edit in [`TextDelta::edits`].
+        index: usize,
+    },
+}
+
+impl std::fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CharCountMismatch { expected, actual } => write!(
+                f,
+                "Delta expects {} characters but the text has {}",
+                expected, actual
+            ),
+            Self::EditOutOfBounds { index } => write!(f, "Delta edit {} is out of bounds", index),
+            Self::DeletedTextMismatch { index } => {
+                write!(f, "Delta edit {} does not match the text it deletes", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DeltaError {}
+
 impl TextDelta {
     /// Returns `true` if this delta contains no edits.
     pub fn is_empty(&self) -> bool {
         self.edits.is_empty()
     }
+
+    /// Replay this delta onto `text`, turning the "before" document into the "after" document.
+    ///
+    /// This lets consumers that mirror the document (e.g. a background linter) stay in sync
+    /// without holding an [`EditorCore`](crate::EditorCore). `text` must have exactly
+    /// `before_char_count` characters, and each edit's `deleted_text` must match the text it
+    /// removes. On error, `text` is left unchanged.
+    pub fn apply_to(&self, text: &mut String) -> Result<(), DeltaError> {
+        let actual = text.chars().count();
+        if actual != self.before_char_count {
+            return Err(DeltaError::CharCountMismatch {
+                expected: self.before_char_count,
+                actual,
+            });
+        }
+
+        let mut out = text.clone();
+        for (index, edit) in self.edits.iter().enumerate() {
+            let start =
+                char_to_byte(&out, edit.start).ok_or(DeltaError::EditOutOfBounds { index })?;
+            let end = char_to_byte(&out[start..], edit.deleted_len())
+                .map(|len| start + len)
+                .ok_or(DeltaError::EditOutOfBounds { index })?;
+            if out[start..end] != edit.deleted_text {
+                return Err(DeltaError::DeletedTextMismatch { index });
+            }
+            out.replace_range(start..end, &edit.inserted_text);
+        }
+
+        *text = out;
+        Ok(())
+    }
+
+    /// The delta that undoes this one (turns the "after" document back into the "before" one).
+    ///
+    /// Edits are replayed in reverse order with deleted/inserted text swapped, so no document
+    /// access is needed.
+    pub fn invert(&self) -> TextDelta {
+        TextDelta {
+            before_char_count: self.after_char_count,
+            after_char_count: self.before_char_count,
+            edits: self
+                .edits
+                .iter()
+                .rev()
+                .map(|edit| TextDeltaEdit {
+                    start: edit.start,
+                    deleted_text: edit.inserted_text.clone(),
+                    inserted_text: edit.deleted_text.clone(),
+                })
+                .collect(),
+            undo_group_id: self.undo_group_id,
+        }
+    }
+
+    /// Merge this delta with `next` (which must apply to this delta's result) into one delta.
+    ///
+    /// The composed edits are non-overlapping, expressed in offsets of this delta's "before"
+    /// document and ordered by descending start (like the executor's own deltas), so they can be
+    /// applied in order. Changes that cancel out (e.g. text inserted by `self` and deleted by
+    /// `next`, or `self.compose(&self.invert())`) disappear.
+    pub fn compose(&self, next: &TextDelta) -> Result<TextDelta, DeltaError> {
+        if next.before_char_count != self.after_char_count {
+            return Err(DeltaError::CharCountMismatch {
+                expected: next.before_char_count,
+                actual: self.after_char_count,
+            });
+        }
+
+        let mut ops = Vec::new();
+        push_op(&mut ops, Op::Retain(self.before_char_count));
+        for delta in [self, next] {
+            for (index, edit) in delta.edits.iter().enumerate() {
+                ops = apply_edit_to_ops(&ops, edit, index)?;
+            }
+        }
+
+        Ok(TextDelta {
+            before_char_count: self.before_char_count,
+            after_char_count: output_len(&ops),
+            edits: ops_to_edits(&ops),
+            undo_group_id: if self.undo_group_id == next.undo_group_id {
+                next.undo_group_id
+            } else {
+                None
+            },
+        })
+    }
+}
+
+/// One step of a change, walking the "before" document from start to end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Op {
+    /// Keep this many characters.
+    Retain(usize),
+    /// Insert text at the current position.
+    Insert(String),
+    /// Remove this text (present at the current position).
+    Delete(String),
+}
+
+fn push_op(ops: &mut Vec<Op>, op: Op) {
+    match (ops.last_mut(), op) {
+        (_, Op::Retain(0)) => {}
+        (_, Op::Insert(text) | Op::Delete(text)) if text.is_empty() => {}
+        (Some(Op::Retain(last)), Op::Retain(n)) => *last += n,
+        (Some(Op::Insert(last)), Op::Insert(text)) => last.push_str(&text),
+        (Some(Op::Delete(last)), Op::Delete(text)) => last.push_str(&text),
+        (_, op) => ops.push(op),
+    }
+}
+
+/// Split `text` after `chars` characters.
+fn split_chars(text: &str, chars: usize) -> (&str, &str) {
+    text.split_at(char_to_byte(text, chars).unwrap_or(text.len()))
+}
+
+fn op_len(op: &Op) -> usize {
+    match op {
+        Op::Retain(n) => *n,
+        Op::Insert(text) | Op::Delete(text) => text.chars().count(),
+    }
+}
+
+/// Length of the document an op list produces.
+fn output_len(ops: &[Op]) -> usize {
+    ops.iter()
+        .filter(|op| !matches!(op, Op::Delete(_)))
+        .map(op_len)
+        .sum()
+}
+
+/// Split `op` after `n` characters; the tail is `None` if nothing is left.
+fn split_op(op: Op, n: usize) -> (Op, Option<Op>) {
+    let (head, tail) = match op {
+        Op::Retain(len) => (Op::Retain(n), Op::Retain(len - n)),
+        Op::Insert(text) => {
+            let (head, tail) = split_chars(&text, n);
+            (Op::Insert(head.to_string()), Op::Insert(tail.to_string()))
+        }
+        Op::Delete(text) => {
+            let (head, tail) = split_chars(&text, n);
+            (Op::Delete(head.to_string()), Op::Delete(tail.to_string()))
+        }
+    };
+    let tail = (op_len(&tail) > 0).then_some(tail);
+    (head, tail)
+}
+
+/// Apply the sequential edit `edits[index]` on top of `ops` (the changes made so far).
+///
+/// The edit's offsets refer to the document `ops` produces; the result still walks the original
+/// "before" document.
+fn apply_edit_to_ops(
+    ops: &[Op],
+    edit: &TextDeltaEdit,
+    index: usize,
+) -> Result<Vec<Op>, DeltaError> {
+    let current_len = output_len(ops);
+    let end = edit.end();
+    if end > current_len {
+        return Err(DeltaError::EditOutOfBounds { index });
+    }
+
+    let mut edit_ops = Vec::with_capacity(4);
+    push_op(&mut edit_ops, Op::Retain(edit.start));
+    push_op(&mut edit_ops, Op::Delete(edit.deleted_text.clone()));
+    push_op(&mut edit_ops, Op::Insert(edit.inserted_text.clone()));
+    push_op(&mut edit_ops, Op::Retain(current_len - end));
+
+    let mut out = Vec::with_capacity(ops.len() + 3);
+    let mut first = ops.iter().cloned();
+    let mut second = edit_ops.into_iter();
+    let mut a = first.next();
+    let mut b = second.next();
+    loop {
+        match (a.take(), b.take()) {
+            (None, None) => break,
+            (Some(Op::Delete(text)), rest) => {
+                push_op(&mut out, Op::Delete(text));
+                a = first.next();
+                b = rest;
+            }
+            (rest, Some(Op::Insert(text))) => {
+                push_op(&mut out, Op::Insert(text));
+                a = rest;
+                b = second.next();
+            }
+            (Some(x), Some(y)) => {
+                let n = op_len(&x).min(op_len(&y));
+                let (x, x_tail) = split_op(x, n);
+                let (y, y_tail) = split_op(y, n);
+                match (x, y) {
+                    (Op::Retain(n), Op::Retain(_)) => push_op(&mut out, Op::Retain(n)),
+                    (Op::Retain(_), Op::Delete(text)) => push_op(&mut out, Op::Delete(text)),
+                    (Op::Insert(text), Op::Retain(_)) => push_op(&mut out, Op::Insert(text)),
+                    // Text inserted earlier and deleted now cancels out.
+                    (Op::Insert(text), Op::Delete(deleted)) => {
+                        if text != deleted {
+                            return Err(DeltaError::DeletedTextMismatch { index });
+                        }
+                    }
+                    _ => unreachable!("deletes of `ops` and inserts of the edit are handled above"),
+                }
+                a = x_tail.or_else(|| first.next());
+                b = y_tail.or_else(|| second.next());
+            }
+            // Lengths were checked above.
+            _ => return Err(DeltaError::EditOutOfBounds { index }),
+        }
+    }
+    Ok(out)
+}
+
+/// Turn an op list into non-overlapping edits in descending start order.
+fn ops_to_edits(ops: &[Op]) -> Vec<TextDeltaEdit> {
+    let mut edits = Vec::new();
+    let mut pos = 0usize;
+    let mut pending: Option<TextDeltaEdit> = None;
+    for op in ops {
+        match op {
+            Op::Retain(n) => {
+                if let Some(edit) = pending.take() {
+                    pos += edit.deleted_len();
+                    edits.push(edit);
+                }
+                pos += n;
+            }
+            Op::Insert(text) | Op::Delete(text) => {
+                let edit = pending.get_or_insert_with(|| TextDeltaEdit {
+                    start: pos,
+                    deleted_text: String::new(),
+                    inserted_text: String::new(),
+                });
+                if matches!(op, Op::Insert(_)) {
+                    edit.inserted_text.push_str(text);
+                } else {
+                    edit.deleted_text.push_str(text);
+                }
+            }
+        }
+    }
+    edits.extend(pending);
+
+    edits
+        .into_iter()
+        .rev()
+        .filter_map(|edit| {
+            // Drop text that is deleted and re-inserted unchanged (e.g. `a` then `invert(a)`).
+            let deleted: Vec<char> = edit.deleted_text.chars().collect();
+            let inserted: Vec<char> = edit.inserted_text.chars().collect();
+            let prefix = deleted
+                .iter()
+                .zip(&inserted)
+                .take_while(|(a, b)| a == b)
+                .count();
+            let suffix = deleted[prefix..]
+                .iter()
+                .rev()
+                .zip(inserted[prefix..].iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let deleted_text: String = deleted[prefix..deleted.len() - suffix].iter().collect();
+            let inserted_text: String = inserted[prefix..inserted.len() - suffix].iter().collect();
+            (!deleted_text.is_empty() || !inserted_text.is_empty()).then(|| TextDeltaEdit {
+                start: edit.start + prefix,
+                deleted_text,
+                inserted_text,
+            })
+        })
+        .collect()
+}
+
+/// Byte offset of character offset `offset` in `text` (`text.len()` for the end).
+fn char_to_byte(text: &str, offset: usize) -> Option<usize> {
+    text.char_indices()
+        .map(|(byte, _)| byte)
+        .chain(std::iter::once(text.len()))
+        .nth(offset)
 }
@@ -5,6 +5,9 @@
 //! - problems panels / gutter markers
 //! - hover tooltips / inline messages
 //! - mapping diagnostics back to style layers (underlines)
+//! - next/previous problem navigation ([`next_diagnostic`])
+
+use crate::symbols::Utf16Range;
 
 /// A half-open character-offset range (`start..end`) in the document.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -35,6 +38,41 @@ pub enum DiagnosticSeverity {
     Hint,
 }
 
+impl DiagnosticSeverity {
+    /// Severity rank where higher is more severe (`Error` is the highest).
+    pub(crate) fn rank(self) -> u8 {
+        match self {
+            Self::Error => 3,
+            Self::Warning => 2,
+            Self::Information => 1,
+            Self::Hint => 0,
+        }
+    }
+}
+
+/// Extra diagnostic classification (LSP `DiagnosticTag`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiagnosticTag {
+    /// Unused or unnecessary code; hosts usually render it faded.
+    Unnecessary,
+    /// Deprecated code; hosts usually render it struck through.
+    Deprecated,
+}
+
+/// A secondary location for a diagnostic (LSP `DiagnosticRelatedInformation`), e.g.
+/// "first defined here".
+///
+/// The location may be in another document, so its range stays in LSP UTF-16 coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedDiagnostic {
+    /// Message for the related location.
+    pub message: String,
+    /// Document URI of the related location.
+    pub uri: String,
+    /// Range in that document (UTF-16 line/character).
+    pub range: Utf16Range,
+}
+
 /// A single diagnostic item for the current document.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Diagnostic {
@@ -46,10 +84,102 @@ pub struct Diagnostic {
     pub code: Option<String>,
     /// Optional diagnostic source (e.g. `"rust-analyzer"`).
     pub source: Option<String>,
+    /// Which integration produced the diagnostic, e.g. one name per language server.
+    ///
+    /// Unlike `source` (the tool name a server reports, which varies per diagnostic), this is
+    /// set by [`ProcessingEdit::ReplaceProviderDiagnostics`](crate::ProcessingEdit::ReplaceProviderDiagnostics)
+    /// so one provider's diagnostics can be replaced without touching the others.
+    pub provider: Option<String>,
+    /// Link to documentation for `code` (LSP `codeDescription.href`).
+    pub code_description_href: Option<String>,
     /// Diagnostic message.
     pub message: String,
+    /// Tags (unnecessary / deprecated code).
+    pub tags: Vec<DiagnosticTag>,
+    /// Related locations.
+    pub related: Vec<RelatedDiagnostic>,
     /// Optional related information payload, encoded as JSON text (if provided by an integration).
     pub related_information_json: Option<String>,
     /// Optional extra data payload, encoded as JSON text (if provided by an integration).
     pub data_json: Option<String>,
 }
+
+impl Diagnostic {
+    /// Severity used for filtering and sorting; a missing severity counts as an error.
+    pub fn effective_severity(&self) -> DiagnosticSeverity {
+        self.severity.unwrap_or(DiagnosticSeverity::Error)
+    }
+
+    /// The range with `start <= end`.
+    fn ordered_range(&self) -> (usize, usize) {
+        (
+            self.range.start.min(self.range.end),
+            self.range.start.max(self.range.end),
+        )
+    }
+}
+
+/// Replace the diagnostics of `provider` in `diagnostics` with `replacement` (stamped with
+/// `provider`), keeping other providers' diagnostics in front.
+pub(crate) fn replace_provider_diagnostics(
+    diagnostics: &mut Vec<Diagnostic>,
+    provider: &str,
+    replacement: Vec<Diagnostic>,
+) {
+    diagnostics.retain(|diagnostic| diagnostic.provider.as_deref() != Some(provider));
+    diagnostics.extend(replacement.into_iter().map(|mut diagnostic| {
+        diagnostic.provider = Some(provider.to_string());
+        diagnostic
+    }));
+}
+
+/// Diagnostics whose range contains or touches `offset` (a caret right after a diagnostic still
+/// hits it), in their original order.
+pub fn diagnostics_at_offset(diagnostics: &[Diagnostic], offset: usize) -> Vec<&Diagnostic> {
+    diagnostics
+        .iter()
+        .filter(|d| {
+            let (start, end) = d.ordered_range();
+            start <= offset && offset <= end
+        })
+        .collect()
+}
+
+/// The next diagnostic at least as severe as `min_severity` that starts after `offset`
+/// (`forward`) or before it, wrapping around the document.
+///
+/// Among diagnostics starting at the same offset, the most severe one is returned.
+pub fn next_diagnostic(
+    diagnostics: &[Diagnostic],
+    offset: usize,
+    min_severity: DiagnosticSeverity,
+    forward: bool,
+) -> Option<&Diagnostic> {
+    let mut stops: Vec<&Diagnostic> = diagnostics
+        .iter()
+        .filter(|d| d.effective_severity().rank() >= min_severity.rank())
+        .collect();
+    stops.sort_by_key(|d| {
+        (
+            d.ordered_range().0,
+            std::cmp::Reverse(d.effective_severity().rank()),
+        )
+    });
+
+    let start = |d: &&Diagnostic| d.ordered_range().0;
+    if forward {
+        stops
+            .iter()
+            .find(|d| start(d) > offset)
+            .or(stops.first())
+            .copied()
+    } else {
+        // The first (most severe) diagnostic of the closest start before `offset`.
+        let before = stops
+            .iter()
+            .rev()
+            .find(|d| start(d) < offset)
+            .or(stops.last())?;
+        stops.iter().find(|d| start(d) == start(before)).copied()
+    }
+}
@@ -0,0 +1,189 @@
+//! Line-based text diffing.
+//!
+//! [`diff_lines`] compares two texts line by line (Myers' O(ND) algorithm) and reports the
+//! changed regions as [`DiffHunk`]s. Lines are split on `\n`, matching the editor's internal
+//! line model.
+
+use std::ops::Range;
+
+/// A contiguous block of changed lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    /// Changed line range in the old text (0-based, half-open; empty for pure insertions).
+    pub old_lines: Range<usize>,
+    /// Changed line range in the new text (0-based, half-open; empty for pure deletions).
+    pub new_lines: Range<usize>,
+    /// Lines removed from the old text (without line terminators).
+    pub removed: Vec<String>,
+    /// Lines inserted in the new text (without line terminators).
+    pub inserted: Vec<String>,
+}
+
+/// Diff `old` against `new`, returning the changed hunks in document order.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffHunk> {
+    let old_lines: Vec<&str> = old.split('\n').collect();
+    let new_lines: Vec<&str> = new.split('\n').collect();
+
+    // Trim the common prefix/suffix so the quadratic-in-D part only sees the changed middle.
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old_lines[prefix..old_lines.len() - suffix];
+    let b = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut hunks = Vec::new();
+    let (mut i0, mut j0) = (0usize, 0usize);
+    for (i, j) in common_lines(a, b)
+        .into_iter()
+        .chain(std::iter::once((a.len(), b.len())))
+    {
+        if i > i0 || j > j0 {
+            hunks.push(DiffHunk {
+                old_lines: prefix + i0..prefix + i,
+                new_lines: prefix + j0..prefix + j,
+                removed: a[i0..i].iter().map(|s| s.to_string()).collect(),
+                inserted: b[j0..j].iter().map(|s| s.to_string()).collect(),
+            });
+        }
+        (i0, j0) = (i + 1, j + 1);
+    }
+    hunks
+}
+
+/// Index pairs `(i, j)` with `a[i] == b[j]` forming a longest common subsequence.
+fn common_lines(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // `trace[d]` holds `v[-d..=d]` as it was before step `d`.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..trace.len() as isize).rev() {
+        let k = x - y;
+        let (start_x, start_y, prev_x, prev_y) = if d == 0 {
+            (0, 0, 0, 0)
+        } else {
+            let snap = &trace[d as usize];
+            let get = |k: isize| snap[(k + d) as usize];
+            let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            let prev_x = get(prev_k);
+            let start_x = if prev_k == k + 1 { prev_x } else { prev_x + 1 };
+            (start_x, start_x - k, prev_x, prev_x - prev_k)
+        };
+
+        while x > start_x && y > start_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+
+    pairs.reverse();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_texts_have_no_hunks() {
+        assert!(diff_lines("a\nb\n", "a\nb\n").is_empty());
+        assert!(diff_lines("", "").is_empty());
+    }
+
+    #[test]
+    fn test_replace_insert_delete() {
+        let hunks = diff_lines("a\nb\nc\nd\ne", "a\nB\nc\ne\nf");
+        assert_eq!(
+            hunks,
+            vec![
+                DiffHunk {
+                    old_lines: 1..2,
+                    new_lines: 1..2,
+                    removed: vec!["b".to_string()],
+                    inserted: vec!["B".to_string()],
+                },
+                DiffHunk {
+                    old_lines: 3..4,
+                    new_lines: 3..3,
+                    removed: vec!["d".to_string()],
+                    inserted: vec![],
+                },
+                DiffHunk {
+                    old_lines: 5..5,
+                    new_lines: 4..5,
+                    removed: vec![],
+                    inserted: vec!["f".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hunks_reconstruct_new_text() {
+        let old = "x\na\nb\nc\na\nb\nb\na\ny";
+        let new = "c\nb\na\nb\na\nc\nz";
+        let hunks = diff_lines(old, new);
+
+        let old_lines: Vec<&str> = old.split('\n').collect();
+        let mut rebuilt: Vec<String> = Vec::new();
+        let mut cursor = 0;
+        for hunk in &hunks {
+            rebuilt.extend(
+                old_lines[cursor..hunk.old_lines.start]
+                    .iter()
+                    .map(|s| s.to_string()),
+            );
+            rebuilt.extend(hunk.inserted.iter().cloned());
+            cursor = hunk.old_lines.end;
+        }
+        rebuilt.extend(old_lines[cursor..].iter().map(|s| s.to_string()));
+        assert_eq!(rebuilt.join("\n"), new);
+
+        let changed: usize = hunks
+            .iter()
+            .map(|h| h.removed.len() + h.inserted.len())
+            .sum();
+        // The longest common subsequence has 4 lines; a minimal script changes everything else.
+        assert_eq!(changed, old_lines.len() + new.split('\n').count() - 2 * 4);
+    }
+}
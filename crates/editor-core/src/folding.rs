@@ -0,0 +1,224 @@
+//! Folding region providers and merging.
+//!
+//! [`regions_from_indentation`] derives fold regions from leading whitespace, for documents
+//! whose language has no better source (or as a fallback next to one, see
+//! [`FoldingMergePolicy`]). [`carry_collapsed_state`] keeps folds collapsed when a provider
+//! replaces its regions.
+
+use crate::LineIndex;
+use crate::indent::IndentSettings;
+use crate::intervals::FoldRegion;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// How a folding provider's regions are combined with indentation-based regions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FoldingMergePolicy {
+    /// Use the provider's regions only; without a provider there is no folding.
+    #[default]
+    ProviderOnly,
+    /// Provider regions take precedence; [`regions_from_indentation`] fills in what they don't
+    /// cover (everything, when there is no provider).
+    IndentationFallback {
+        /// Minimum region size in lines, passed to [`regions_from_indentation`].
+        min_lines: usize,
+    },
+}
+
+impl FoldingMergePolicy {
+    /// Combine `provider` regions with the fallback regions of this policy.
+    pub fn merge(&self, provider: Vec<FoldRegion>, line_index: &LineIndex) -> Vec<FoldRegion> {
+        match *self {
+            Self::ProviderOnly => provider,
+            Self::IndentationFallback { min_lines } => {
+                merge_fold_regions(provider, regions_from_indentation(line_index, min_lines))
+            }
+        }
+    }
+}
+
+/// Fold regions implied by indentation.
+///
+/// A non-blank line followed by more indented lines starts a region that ends at the last
+/// non-blank line before indentation returns to its level (trailing blank lines stay visible).
+/// Tabs advance to the next multiple of the default indent width. Regions spanning fewer than
+/// `min_lines` lines (the start line included) are skipped.
+pub fn regions_from_indentation(line_index: &LineIndex, min_lines: usize) -> Vec<FoldRegion> {
+    let tab_width = IndentSettings::default().width;
+    let mut regions = Vec::new();
+    // Candidate region starts: `(line, indent)`, indents strictly increasing.
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut last_non_blank = 0usize;
+
+    let close = |start: usize, end: usize, regions: &mut Vec<FoldRegion>| {
+        if end > start && end - start + 1 >= min_lines {
+            regions.push(FoldRegion::new(start, end));
+        }
+    };
+
+    for line in 0..line_index.line_count() {
+        let text = line_index.get_line_text(line).unwrap_or_default();
+        if text.trim().is_empty() {
+            continue;
+        }
+        let indent = indent_columns(&text, tab_width);
+        while let Some(&(start, start_indent)) = open.last() {
+            if start_indent < indent {
+                break;
+            }
+            open.pop();
+            close(start, last_non_blank, &mut regions);
+        }
+        open.push((line, indent));
+        last_non_blank = line;
+    }
+    while let Some((start, _)) = open.pop() {
+        close(start, last_non_blank, &mut regions);
+    }
+
+    regions.sort_by_key(|r| (r.start_line, r.end_line));
+    regions
+}
+
+fn indent_columns(text: &str, tab_width: usize) -> usize {
+    let mut columns = 0usize;
+    for ch in text.chars() {
+        match ch {
+            ' ' => columns += 1,
+            '\t' => columns = (columns / tab_width + 1) * tab_width,
+            _ => break,
+        }
+    }
+    columns
+}
+
+/// Merge a provider's regions with `fallback` regions.
+///
+/// All `provider` regions are kept. A fallback region is added unless it starts on the same
+/// line as a provider region or partially overlaps one (regions nesting inside or around
+/// provider regions fill in structure the provider didn't report).
+pub fn merge_fold_regions(provider: Vec<FoldRegion>, fallback: Vec<FoldRegion>) -> Vec<FoldRegion> {
+    let starts: HashSet<usize> = provider.iter().map(|r| r.start_line).collect();
+    let crosses = |a: &FoldRegion, b: &FoldRegion| {
+        (a.start_line < b.start_line && b.start_line <= a.end_line && a.end_line < b.end_line)
+            || (b.start_line < a.start_line
+                && a.start_line <= b.end_line
+                && b.end_line < a.end_line)
+    };
+
+    let mut merged = provider;
+    let kept: Vec<FoldRegion> = fallback
+        .into_iter()
+        .filter(|region| {
+            !starts.contains(&region.start_line) && !merged.iter().any(|p| crosses(p, region))
+        })
+        .collect();
+    merged.extend(kept);
+    merged.sort_by_key(|r| (r.start_line, r.end_line));
+    merged
+}
+
+/// Collapse the regions of `new` that correspond to collapsed regions of `old`.
+///
+/// A region corresponds if it has the same start and end line. Otherwise (an edit moved lines
+/// under the old region), a new region whose end line has the same content is used, preferring
+/// one whose start line content matches too, then the nearest. Line content is read from
+/// `line_index`, the current document.
+pub fn carry_collapsed_state(old: &[FoldRegion], new: &mut [FoldRegion], line_index: &LineIndex) {
+    let exact: HashMap<(usize, usize), usize> = new
+        .iter()
+        .enumerate()
+        .map(|(index, r)| ((r.start_line, r.end_line), index))
+        .collect();
+    let mut shifted = Vec::new();
+    for region in old.iter().filter(|r| r.is_collapsed) {
+        match exact.get(&(region.start_line, region.end_line)) {
+            Some(&index) => new[index].is_collapsed = true,
+            None => shifted.push(region),
+        }
+    }
+    if shifted.is_empty() {
+        return;
+    }
+
+    let line_hash = |line: usize| {
+        let mut hasher = DefaultHasher::new();
+        line_index
+            .get_line_text(line)
+            .unwrap_or_default()
+            .trim()
+            .hash(&mut hasher);
+        hasher.finish()
+    };
+    let mut by_end: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, region) in new.iter().enumerate() {
+        if !region.is_collapsed {
+            by_end
+                .entry(line_hash(region.end_line))
+                .or_default()
+                .push(index);
+        }
+    }
+    for region in shifted {
+        let Some(candidates) = by_end.get_mut(&line_hash(region.end_line)) else {
+            continue;
+        };
+        let start_hash = line_hash(region.start_line);
+        let Some(best) = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, index)| {
+                let candidate = &new[**index];
+                (
+                    line_hash(candidate.start_line) != start_hash,
+                    candidate.start_line.abs_diff(region.start_line)
+                        + candidate.end_line.abs_diff(region.end_line),
+                )
+            })
+            .map(|(position, _)| position)
+        else {
+            continue;
+        };
+        let index = candidates.swap_remove(best);
+        new[index].is_collapsed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(regions: &[FoldRegion]) -> Vec<(usize, usize)> {
+        regions.iter().map(|r| (r.start_line, r.end_line)).collect()
+    }
+
+    #[test]
+    fn test_indentation_regions_skip_blank_lines_and_tabs() {
+        let text = "a:\n\tb: 1\n\n    c:\n        d\n\ne\n";
+        let line_index = LineIndex::from_text(text);
+        assert_eq!(
+            spans(&regions_from_indentation(&line_index, 2)),
+            vec![(0, 4), (3, 4)]
+        );
+        assert_eq!(
+            spans(&regions_from_indentation(&line_index, 3)),
+            vec![(0, 4)]
+        );
+    }
+
+    #[test]
+    fn test_merge_drops_fallback_regions_crossing_provider_regions() {
+        let provider = vec![FoldRegion::new(0, 5), FoldRegion::new(7, 9)];
+        let fallback = vec![
+            FoldRegion::new(0, 4),
+            FoldRegion::new(2, 3),
+            FoldRegion::new(4, 8),
+            FoldRegion::new(6, 10),
+        ];
+        assert_eq!(
+            spans(&merge_fold_regions(provider, fallback)),
+            vec![(0, 5), (2, 3), (6, 10), (7, 9)]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Indentation detection.
+//!
+//! [`detect_indentation`] guesses whether a document indents with tabs or spaces (and how many
+//! spaces per level) from its leading whitespace. Hosts typically run it once when a file is
+//! opened and apply the result with [`ViewCommand::ApplyIndentSettings`](crate::ViewCommand::ApplyIndentSettings).
+
+use crate::LineIndex;
+
+/// Only the first lines of a document are sampled (enough for a stable guess on huge files).
+const MAX_SAMPLE_LINES: usize = 10_000;
+
+/// Largest indent step considered when measuring space indentation.
+const MAX_INDENT_WIDTH: usize = 8;
+
+/// Indentation settings for a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentSettings {
+    /// Indent with tab characters (`true`) or spaces (`false`).
+    pub use_tabs: bool,
+    /// Indent width in columns (spaces per level, or the tab width when `use_tabs` is set).
+    pub width: usize,
+    /// How confident the detection was, as a percentage (`0..=100`).
+    ///
+    /// `0` means the document gave no evidence (e.g. it has no indented lines) and the settings
+    /// are defaults. Settings constructed by hand typically use `100`.
+    pub confidence: u8,
+}
+
+impl Default for IndentSettings {
+    fn default() -> Self {
+        Self {
+            use_tabs: false,
+            width: 4,
+            confidence: 0,
+        }
+    }
+}
+
+impl IndentSettings {
+    /// Settings for indenting with spaces, `width` per level.
+    pub fn spaces(width: usize) -> Self {
+        Self {
+            use_tabs: false,
+            width,
+            confidence: 100,
+        }
+    }
+
+    /// Settings for indenting with tabs displayed `width` columns wide.
+    pub fn tabs(width: usize) -> Self {
+        Self {
+            use_tabs: true,
+            width,
+            confidence: 100,
+        }
+    }
+}
+
+/// Detect the indentation style of a document.
+///
+/// Tabs vs spaces is decided by which kind of leading whitespace indents more lines. For spaces,
+/// the width is the most common indentation delta between consecutive non-blank lines, so
+/// occasional continuation/alignment indents (e.g. a 3-space outlier in a 4-space file) don't
+/// flip the result. Documents without indented lines yield [`IndentSettings::default`].
+pub fn detect_indentation(line_index: &LineIndex) -> IndentSettings {
+    let mut tab_lines = 0usize;
+    let mut space_lines = 0usize;
+    // `deltas[d]`: how often the space indentation changed by `d` columns between neighbours.
+    let mut deltas = [0usize; MAX_INDENT_WIDTH + 1];
+    let mut prev_spaces: Option<usize> = None;
+
+    for line in 0..line_index.line_count().min(MAX_SAMPLE_LINES) {
+        let text = line_index.get_line_text(line).unwrap_or_default();
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let leading: Vec<char> = text
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        if leading.first() == Some(&'\t') {
+            tab_lines += 1;
+        } else if !leading.is_empty() {
+            space_lines += 1;
+        }
+
+        if leading.contains(&'\t') {
+            // Tab-indented lines carry no information about space widths.
+            prev_spaces = None;
+            continue;
+        }
+
+        let spaces = leading.len();
+        if let Some(prev) = prev_spaces {
+            let delta = spaces.abs_diff(prev);
+            if delta <= MAX_INDENT_WIDTH {
+                deltas[delta] += 1;
+            }
+        }
+        prev_spaces = Some(spaces);
+    }
+
+    let indented = tab_lines + space_lines;
+    if indented == 0 {
+        return IndentSettings::default();
+    }
+
+    if tab_lines > space_lines {
+        return IndentSettings {
+            use_tabs: true,
+            width: IndentSettings::default().width,
+            confidence: percent(tab_lines, indented),
+        };
+    }
+
+    // Prefer common widths on ties; single-column deltas are alignment noise.
+    let width = [4, 2, 8, 3, 6, 5, 7]
+        .into_iter()
+        .max_by_key(|&w| (deltas[w], std::cmp::Reverse(preference(w))))
+        .filter(|&w| deltas[w] > 0)
+        .unwrap_or(IndentSettings::default().width);
+    let changes: usize = deltas[2..].iter().sum();
+    let width_share = if changes == 0 {
+        // Only flat runs (or 1-column steps): the style is clear but the width is a guess.
+        50
+    } else {
+        percent(deltas[width], changes)
+    };
+
+    IndentSettings {
+        use_tabs: false,
+        width,
+        confidence: (u32::from(percent(space_lines, indented)) * u32::from(width_share) / 100)
+            as u8,
+    }
+}
+
+fn preference(width: usize) -> usize {
+    match width {
+        4 => 0,
+        2 => 1,
+        8 => 2,
+        _ => 3 + width,
+    }
+}
+
+fn percent(part: usize, total: usize) -> u8 {
+    (part * 100 / total.max(1)).min(100) as u8
+}
+
+/// Convert leading whitespace of one line from `from` to `to` indentation.
+///
+/// Leading whitespace is measured in columns using `from.width` for tabs, split into whole
+/// indent levels plus a remainder (alignment), and rebuilt with `to`. Returns `None` if the line
+/// is unchanged.
+pub(crate) fn reindent_leading_whitespace(
+    line_text: &str,
+    from: IndentSettings,
+    to: IndentSettings,
+) -> Option<(usize, String)> {
+    let leading_len = line_text
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .count();
+    if leading_len == 0 {
+        return None;
+    }
+
+    let mut columns = 0usize;
+    for ch in line_text.chars().take(leading_len) {
+        columns = if ch == '\t' {
+            (columns / from.width + 1) * from.width
+        } else {
+            columns + 1
+        };
+    }
+
+    let levels = columns / from.width;
+    let remainder = columns % from.width;
+    let mut rebuilt = if to.use_tabs {
+        "\t".repeat(levels)
+    } else {
+        " ".repeat(levels * to.width)
+    };
+    rebuilt.push_str(&" ".repeat(remainder));
+
+    let current: String = line_text.chars().take(leading_len).collect();
+    (rebuilt != current).then_some((leading_len, rebuilt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reindent_leading_whitespace() {
+        let tabs = IndentSettings::tabs(4);
+        let two = IndentSettings::spaces(2);
+        let four = IndentSettings::spaces(4);
+
+        assert_eq!(
+            reindent_leading_whitespace("\t\tx", tabs, two),
+            Some((2, "    ".to_string()))
+        );
+        assert_eq!(
+            reindent_leading_whitespace("      x", four, tabs),
+            Some((6, "\t  ".to_string()))
+        );
+        // Tabs after spaces snap to the next tab stop.
+        assert_eq!(
+            reindent_leading_whitespace("  \tx", tabs, four),
+            Some((3, "    ".to_string()))
+        );
+        assert_eq!(reindent_leading_whitespace("    x", four, four), None);
+        assert_eq!(reindent_leading_whitespace("x", four, tabs), None);
+    }
+}
@@ -2,6 +2,9 @@
 //!
 //! Uses Interval Tree to manage style metadata and code folding.
 
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, PoisonError, RwLock};
+
 /// Style ID type
 pub type StyleId = u32;
 
@@ -17,6 +20,30 @@ pub const DOCUMENT_HIGHLIGHT_READ_STYLE_ID: StyleId = 0x0400_0002;
 /// Built-in style id for LSP `textDocument/documentHighlight` (kind: Write).
 pub const DOCUMENT_HIGHLIGHT_WRITE_STYLE_ID: StyleId = 0x0400_0003;
 
+/// Built-in style id for diagnostic underlines (severity: Error).
+pub const DIAGNOSTIC_ERROR_STYLE_ID: StyleId = 0x0500_0001;
+/// Built-in style id for diagnostic underlines (severity: Warning).
+pub const DIAGNOSTIC_WARNING_STYLE_ID: StyleId = 0x0500_0002;
+/// Built-in style id for diagnostic underlines (severity: Information).
+pub const DIAGNOSTIC_INFORMATION_STYLE_ID: StyleId = 0x0500_0003;
+/// Built-in style id for diagnostic underlines (severity: Hint).
+pub const DIAGNOSTIC_HINT_STYLE_ID: StyleId = 0x0500_0004;
+/// Built-in style id for code a diagnostic tags as unnecessary (e.g. unused; usually faded).
+pub const DIAGNOSTIC_UNNECESSARY_STYLE_ID: StyleId = 0x0500_0010;
+/// Built-in style id for code a diagnostic tags as deprecated (usually struck through).
+pub const DIAGNOSTIC_DEPRECATED_STYLE_ID: StyleId = 0x0500_0011;
+
+/// Built-in style id for search matches (see `EditorStateManager::set_search_highlight`).
+pub const SEARCH_MATCH_STYLE_ID: StyleId = 0x0600_0001;
+/// Built-in style id for the current search match (the one selected by the primary selection).
+pub const SEARCH_CURRENT_MATCH_STYLE_ID: StyleId = 0x0600_0002;
+
+/// Built-in style id for the current-line background (see `DecorationKind::LineBackground`).
+pub const CURRENT_LINE_STYLE_ID: StyleId = 0x0700_0001;
+/// Built-in style id for substitute glyphs of whitespace and control characters (see
+/// `SnapshotOptions`).
+pub const WHITESPACE_STYLE_ID: StyleId = 0x0700_0002;
+
 /// Style layer ID
 ///
 /// Used to distinguish style sources (e.g., LSP semantic highlighting, simple syntax highlighting, diagnostics, etc.),
@@ -49,6 +76,167 @@ impl StyleLayerId {
 
     /// Tree-sitter syntax highlighting style layer.
     pub const TREE_SITTER: Self = Self(6);
+
+    /// "Highlight all matches" layer maintained by `EditorStateManager::set_search_highlight`.
+    pub const SEARCH_MATCHES: Self = Self(7);
+
+    /// Default priority of this layer when overlapping styles are resolved to a single winner
+    /// (see `EditorCore::styles_at_offset_resolved`). Higher wins.
+    ///
+    /// Syntax layers rank below semantic tokens, which rank below overlays (diagnostics,
+    /// document highlights, search matches). Custom layers and the base `interval_tree`
+    /// default to `0`; override per layer with `EditorCore::set_style_layer_priority`.
+    pub const fn default_priority(self) -> StylePriority {
+        match self {
+            Self::SIMPLE_SYNTAX => 10,
+            Self::SUBLIME_SYNTAX => 20,
+            Self::TREE_SITTER => 30,
+            Self::SEMANTIC_TOKENS => 40,
+            Self::DIAGNOSTICS => 50,
+            Self::DOCUMENT_HIGHLIGHTS => 60,
+            Self::SEARCH_MATCHES => 70,
+            _ => 0,
+        }
+    }
+}
+
+/// Priority of a style layer; higher wins when overlapping styles are resolved to one.
+pub type StylePriority = i32;
+
+/// Names of the built-in style ids, pre-registered in every [`StyleRegistry`].
+pub const BUILTIN_STYLE_NAMES: &[(StyleId, &str)] = &[
+    (FOLD_PLACEHOLDER_STYLE_ID, "fold_placeholder"),
+    (DOCUMENT_HIGHLIGHT_TEXT_STYLE_ID, "document_highlight.text"),
+    (DOCUMENT_HIGHLIGHT_READ_STYLE_ID, "document_highlight.read"),
+    (
+        DOCUMENT_HIGHLIGHT_WRITE_STYLE_ID,
+        "document_highlight.write",
+    ),
+    (DIAGNOSTIC_ERROR_STYLE_ID, "diagnostic.error"),
+    (DIAGNOSTIC_WARNING_STYLE_ID, "diagnostic.warning"),
+    (DIAGNOSTIC_INFORMATION_STYLE_ID, "diagnostic.information"),
+    (DIAGNOSTIC_HINT_STYLE_ID, "diagnostic.hint"),
+    (DIAGNOSTIC_UNNECESSARY_STYLE_ID, "diagnostic.unnecessary"),
+    (DIAGNOSTIC_DEPRECATED_STYLE_ID, "diagnostic.deprecated"),
+    (SEARCH_MATCH_STYLE_ID, "search.match"),
+    (SEARCH_CURRENT_MATCH_STYLE_ID, "search.current_match"),
+    (CURRENT_LINE_STYLE_ID, "current_line"),
+    (WHITESPACE_STYLE_ID, "whitespace"),
+];
+
+/// Allocates `StyleId`s for human-readable style names, shared by highlight sources.
+///
+/// Producers register names such as `"string"`, `"scope:comment.line"` or
+/// `"semantic:function.declaration"` and get a stable `StyleId` back; a theme layer maps
+/// [`name_for_style_id`](Self::name_for_style_id) to colors instead of decoding each source's
+/// numbering. The registry owns allocation, so two names never share an id: allocated ids
+/// start at [`StyleRegistry::BASE`], and the built-in ids keep their constants (see
+/// [`BUILTIN_STYLE_NAMES`]).
+///
+/// Clones share the same registry, so the host can hand one to several producers (which may
+/// live in other threads) and keep one for theming.
+#[derive(Debug, Clone, Default)]
+pub struct StyleRegistry {
+    inner: Arc<RwLock<StyleRegistryInner>>,
+}
+
+#[derive(Debug)]
+struct StyleRegistryInner {
+    ids: HashMap<Arc<str>, StyleId>,
+    /// Allocated names; `names[i]` has id `BASE | (i + 1)`.
+    names: Vec<Arc<str>>,
+    builtin: HashMap<StyleId, Arc<str>>,
+}
+
+impl Default for StyleRegistryInner {
+    fn default() -> Self {
+        let mut inner = Self {
+            ids: HashMap::new(),
+            names: Vec::new(),
+            builtin: HashMap::new(),
+        };
+        for &(id, name) in BUILTIN_STYLE_NAMES {
+            let name: Arc<str> = Arc::from(name);
+            inner.ids.insert(name.clone(), id);
+            inner.builtin.insert(id, name);
+        }
+        inner
+    }
+}
+
+impl StyleRegistry {
+    /// Base prefix for registry-allocated `StyleId`s.
+    pub const BASE: StyleId = 0x0800_0000;
+
+    /// Create a registry holding only the built-in names.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get (or allocate) the `StyleId` for `name`.
+    pub fn style_id(&self, name: &str) -> StyleId {
+        if let Some(id) = self.get(name) {
+            return id;
+        }
+
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        // Another clone may have registered it in between.
+        if let Some(&id) = inner.ids.get(name) {
+            return id;
+        }
+        let id = Self::BASE | (inner.names.len() as u32 + 1);
+        let name: Arc<str> = Arc::from(name);
+        inner.names.push(name.clone());
+        inner.ids.insert(name, id);
+        id
+    }
+
+    /// The `StyleId` of `name`, if registered.
+    pub fn get(&self, name: &str) -> Option<StyleId> {
+        let inner = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        inner.ids.get(name).copied()
+    }
+
+    /// The name registered for `style_id` (built-in or allocated).
+    pub fn name_for_style_id(&self, style_id: StyleId) -> Option<Arc<str>> {
+        let inner = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(name) = inner.builtin.get(&style_id) {
+            return Some(name.clone());
+        }
+        if style_id & 0xFF00_0000 != Self::BASE {
+            return None;
+        }
+        let idx = (style_id & 0x00FF_FFFF).checked_sub(1)? as usize;
+        inner.names.get(idx).cloned()
+    }
+
+    /// All allocated `(StyleId, name)` pairs in allocation order (built-ins excluded).
+    ///
+    /// Ids are allocated in order, so a host can extend a style table with
+    /// `entries().into_iter().skip(known)`.
+    pub fn entries(&self) -> Vec<(StyleId, Arc<str>)> {
+        let inner = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        inner
+            .names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (Self::BASE | (idx as u32 + 1), name.clone()))
+            .collect()
+    }
+
+    /// Number of allocated names (built-ins excluded).
+    pub fn len(&self) -> usize {
+        self.inner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .names
+            .len()
+    }
+
+    /// Returns `true` if no name has been allocated yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// Interval structure
@@ -83,6 +271,75 @@ impl Interval {
     }
 }
 
+/// A range of offsets sharing one set of styles (see [`IntervalTree::style_runs`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleRun {
+    /// Start offset.
+    pub start: usize,
+    /// End offset (exclusive).
+    pub end: usize,
+    /// Styles covering the run, sorted and deduplicated (empty for unstyled text).
+    pub styles: Vec<StyleId>,
+}
+
+/// Merged style runs of several interval trees over `start..end`.
+///
+/// The runs cover `start..end` without gaps, and adjacent runs have different style sets. A
+/// run's styles are the ones every offset in it gets from querying each tree with
+/// [`IntervalTree::query_point`], sorted and deduplicated.
+pub fn merged_style_runs<'a, I>(trees: I, start: usize, end: usize) -> Vec<StyleRun>
+where
+    I: IntoIterator<Item = &'a IntervalTree>,
+{
+    if start >= end {
+        return Vec::new();
+    }
+
+    // `(offset, style, +1 | -1)`, with intervals clipped to the range.
+    let mut events: Vec<(usize, StyleId, isize)> = Vec::new();
+    for tree in trees {
+        for interval in tree.query_range(start, end) {
+            events.push((interval.start.max(start), interval.style_id, 1));
+            events.push((interval.end.min(end), interval.style_id, -1));
+        }
+    }
+    // Openings first, so an empty interval never stays active.
+    events.sort_unstable_by_key(|&(offset, _, delta)| (offset, -delta));
+
+    let mut runs: Vec<StyleRun> = Vec::new();
+    let mut active: BTreeMap<StyleId, usize> = BTreeMap::new();
+    let mut run_start = start;
+    let mut events = events.into_iter().peekable();
+    while run_start < end {
+        while let Some(&(offset, style_id, delta)) = events.peek() {
+            if offset > run_start {
+                break;
+            }
+            events.next();
+            if delta > 0 {
+                *active.entry(style_id).or_insert(0) += 1;
+            } else if let Some(count) = active.get_mut(&style_id) {
+                *count -= 1;
+                if *count == 0 {
+                    active.remove(&style_id);
+                }
+            }
+        }
+        let run_end = events.peek().map_or(end, |&(offset, _, _)| offset.min(end));
+        let styles: Vec<StyleId> = active.keys().copied().collect();
+        match runs.last_mut() {
+            Some(last) if last.styles == styles => last.end = run_end,
+            _ => runs.push(StyleRun {
+                start: run_start,
+                end: run_end,
+                styles,
+            }),
+        }
+        run_start = run_end;
+    }
+    runs
+}
+
 /// Interval tree - manages style intervals
 ///
 /// Uses a sorted vector with binary search for efficient interval queries.
@@ -145,6 +402,62 @@ impl IntervalTree {
         self.rebuild_prefix_max_end_from(pos);
     }
 
+    /// Replace every interval overlapping `start..end` with `intervals`.
+    ///
+    /// Intervals crossing `start` or `end` are removed as a whole, so `intervals` should cover
+    /// their parts outside the range too. Empty intervals are skipped.
+    pub fn replace_range<I>(&mut self, start: usize, end: usize, intervals: I)
+    where
+        I: IntoIterator<Item = Interval>,
+    {
+        self.intervals.retain(|i| i.start >= end || i.end <= start);
+        self.intervals
+            .extend(intervals.into_iter().filter(|i| i.start < i.end));
+        self.intervals.sort_by_key(|i| i.start);
+        self.rebuild_prefix_max_end();
+    }
+
+    /// Remove every part of every interval that falls inside `start..end`.
+    ///
+    /// Intervals inside the range are deleted, intervals straddling one boundary are clipped to
+    /// their part outside the range, and an interval spanning the whole range is split in two.
+    /// Returns whether anything changed.
+    pub fn remove_range(&mut self, start: usize, end: usize) -> bool {
+        if start >= end {
+            return false;
+        }
+
+        let mut changed = false;
+        let mut tails = Vec::new();
+        self.intervals.retain_mut(|i| {
+            if i.end <= start || i.start >= end {
+                return true;
+            }
+            changed = true;
+            if i.end > end {
+                if i.start < start {
+                    tails.push(Interval::new(end, i.end, i.style_id));
+                    i.end = start;
+                } else {
+                    i.start = end;
+                }
+                true
+            } else if i.start < start {
+                i.end = start;
+                true
+            } else {
+                false
+            }
+        });
+
+        if changed {
+            self.intervals.extend(tails);
+            self.intervals.sort_by_key(|i| i.start);
+            self.rebuild_prefix_max_end();
+        }
+        changed
+    }
+
     /// Remove interval that exactly matches the specified interval
     pub fn remove(&mut self, start: usize, end: usize, style_id: StyleId) -> bool {
         if let Some(pos) = self
@@ -250,6 +563,13 @@ impl IntervalTree {
         result
     }
 
+    /// Style runs over `start..end`, walking the range once instead of querying every offset.
+    ///
+    /// See [`merged_style_runs`] to merge several trees.
+    pub fn style_runs(&self, start: usize, end: usize) -> Vec<StyleRun> {
+        merged_style_runs([self], start, end)
+    }
+
     /// Clear all intervals
     pub fn clear(&mut self) {
         self.intervals.clear();
@@ -339,6 +659,9 @@ pub struct FoldRegion {
     pub is_collapsed: bool,
     /// Placeholder text shown when folded (e.g., "[...]")
     pub placeholder: String,
+    /// Summary computed from the folding manager's placeholder template when the region was
+    /// collapsed (e.g. "{ … 12 lines }"); shown instead of `placeholder` when present.
+    pub summary: Option<String>,
 }
 
 impl FoldRegion {
@@ -349,6 +672,7 @@ impl FoldRegion {
             end_line,
             is_collapsed: false,
             placeholder: String::from("[...]"),
+            summary: None,
         }
     }
 
@@ -359,12 +683,14 @@ impl FoldRegion {
             end_line,
             is_collapsed: false,
             placeholder,
+            summary: None,
         }
     }
 
     /// Expand
     pub fn expand(&mut self) {
         self.is_collapsed = false;
+        self.summary = None;
     }
 
     /// Collapse
@@ -374,7 +700,16 @@ impl FoldRegion {
 
     /// Toggle fold state
     pub fn toggle(&mut self) {
-        self.is_collapsed = !self.is_collapsed;
+        if self.is_collapsed {
+            self.expand();
+        } else {
+            self.collapse();
+        }
+    }
+
+    /// Text shown after the start line when folded: the computed summary, else `placeholder`.
+    pub fn display_text(&self) -> &str {
+        self.summary.as_deref().unwrap_or(&self.placeholder)
     }
 
     /// Check if line number is within fold region
@@ -391,6 +726,8 @@ pub struct FoldingManager {
     user_regions: Vec<FoldRegion>,
     /// Cached merged view (sorted/deduplicated) used for rendering and coordinate mapping.
     merged_regions: Vec<FoldRegion>,
+    /// Template used to compute collapsed-region summaries (see [`Self::set_placeholder_template`]).
+    placeholder_template: Option<String>,
 }
 
 impl FoldingManager {
@@ -400,6 +737,7 @@ impl FoldingManager {
             derived_regions: Vec::new(),
             user_regions: Vec::new(),
             merged_regions: Vec::new(),
+            placeholder_template: None,
         }
     }
 
@@ -544,7 +882,7 @@ impl FoldingManager {
                         && best_source.is_some_and(|(prev_is_user, _)| !prev_is_user && is_user))
                 {
                     best_end = region.end_line;
-                    best_source = Some((is_user, i));
+                    best_source = Some((is_user, idx + i));
                 }
             }
         }
@@ -649,6 +987,24 @@ impl FoldingManager {
         self.replace_derived_regions(regions);
     }
 
+    /// Restore previously captured derived/user fold regions (e.g. after reloading a file).
+    ///
+    /// Regions that no longer fit in a document of `line_count` lines are dropped.
+    pub fn restore_regions(
+        &mut self,
+        mut derived: Vec<FoldRegion>,
+        mut user: Vec<FoldRegion>,
+        line_count: usize,
+    ) {
+        for regions in [&mut derived, &mut user] {
+            regions.retain(|r| r.end_line < line_count);
+            Self::normalize_regions(regions);
+        }
+        self.derived_regions = derived;
+        self.user_regions = user;
+        self.rebuild_merged_regions();
+    }
+
     /// Expand all folds
     pub fn expand_all(&mut self) {
         for region in &mut self.derived_regions {
@@ -671,6 +1027,93 @@ impl FoldingManager {
         self.rebuild_merged_regions();
     }
 
+    /// Get the placeholder template used for collapsed-region summaries, if any.
+    pub fn placeholder_template(&self) -> Option<&str> {
+        self.placeholder_template.as_deref()
+    }
+
+    /// Set the template used to summarize collapsed regions (`None` restores static placeholders).
+    ///
+    /// Supported fields:
+    /// - `{lines}`: number of lines hidden by the region (`end_line - start_line`)
+    /// - `{first_line}`: trimmed text of the first hidden line
+    /// - `{placeholder}`: the region's static `placeholder`
+    ///
+    /// Summaries are computed by [`Self::refresh_summaries`], which the command executor calls
+    /// after folding commands and edits. Hosts collapsing regions directly on the manager should
+    /// call [`crate::EditorCore::refresh_fold_summaries`] afterwards.
+    pub fn set_placeholder_template(&mut self, template: Option<String>) {
+        self.placeholder_template = template;
+        if self.placeholder_template.is_none() {
+            for region in self
+                .derived_regions
+                .iter_mut()
+                .chain(self.user_regions.iter_mut())
+            {
+                region.summary = None;
+            }
+            self.rebuild_merged_regions();
+        }
+    }
+
+    /// Recompute summaries of collapsed regions from the placeholder template.
+    ///
+    /// `line_text` returns the text of a logical line. Does nothing when no template is set.
+    pub fn refresh_summaries(&mut self, line_text: impl Fn(usize) -> Option<String>) {
+        let Some(template) = self.placeholder_template.as_deref() else {
+            return;
+        };
+        for region in self
+            .derived_regions
+            .iter_mut()
+            .chain(self.user_regions.iter_mut())
+        {
+            region.summary = region
+                .is_collapsed
+                .then(|| Self::expand_placeholder_template(template, region, &line_text));
+        }
+        self.rebuild_merged_regions();
+    }
+
+    fn expand_placeholder_template(
+        template: &str,
+        region: &FoldRegion,
+        line_text: &impl Fn(usize) -> Option<String>,
+    ) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let field = &rest[open..];
+            let Some(close) = field.find('}') else {
+                rest = field;
+                break;
+            };
+            match &field[1..close] {
+                "lines" => out.push_str(
+                    &region
+                        .end_line
+                        .saturating_sub(region.start_line)
+                        .to_string(),
+                ),
+                "first_line" => {
+                    let text = line_text(region.start_line + 1).unwrap_or_default();
+                    out.push_str(text.trim());
+                }
+                "placeholder" => out.push_str(&region.placeholder),
+                _ => {
+                    // Unknown fields (and literal braces) are kept as-is.
+                    out.push('{');
+                    rest = &field[1..];
+                    continue;
+                }
+            }
+            rest = &field[close + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
     /// Update fold regions to account for an edit that changes the number of logical lines.
     ///
     /// This is intended to keep **user folds** stable across newline insertions/deletions.
@@ -700,6 +1143,76 @@ impl FoldingManager {
         apply(&mut self.user_regions);
     }
 
+    /// Update fold regions for a text edit that replaces the range from
+    /// `(start_line, start_column)` to `(end_line, end_column)` (pre-edit, columns in
+    /// characters) with text containing `inserted_newlines` newlines.
+    ///
+    /// Unlike [`apply_line_delta`](Self::apply_line_delta), this follows the fold's lines
+    /// themselves:
+    /// - a region whose start (header) line is deleted is removed, while splitting the header
+    ///   line after its first character keeps the region in place;
+    /// - lines inserted or deleted inside a region adjust its `end_line` and keep its
+    ///   collapsed state;
+    /// - regions that did not cross before the edit do not cross after it.
+    pub fn apply_edit(
+        &mut self,
+        start_line: usize,
+        start_column: usize,
+        end_line: usize,
+        end_column: usize,
+        inserted_newlines: usize,
+    ) {
+        let deleted_lines = end_line.saturating_sub(start_line);
+        if deleted_lines == 0 && inserted_newlines == 0 {
+            return;
+        }
+
+        // The edited lines become: the text before the edit (on `start_line`), the inserted
+        // lines, then the text after the edit (on `start_line + inserted_newlines`).
+        let tail_line = start_line + inserted_newlines;
+        let map_start = |line: usize| -> Option<usize> {
+            if line < start_line {
+                Some(line)
+            } else if line > end_line {
+                Some(line - deleted_lines + inserted_newlines)
+            } else if line == start_line && start_column > 0 {
+                // The header keeps its first character(s) before the edit.
+                Some(start_line)
+            } else if line == end_line && (deleted_lines == 0 || end_column == 0) {
+                // The header follows the edit.
+                Some(tail_line)
+            } else {
+                None
+            }
+        };
+        let map_end = |line: usize| -> usize {
+            if line < start_line {
+                line
+            } else if line > end_line {
+                line - deleted_lines + inserted_newlines
+            } else if line == end_line && (deleted_lines > 0 || start_column == 0) {
+                tail_line
+            } else {
+                start_line
+            }
+        };
+
+        let apply = |regions: &mut Vec<FoldRegion>| {
+            regions.retain_mut(|region| {
+                let Some(start) = map_start(region.start_line) else {
+                    return false;
+                };
+                region.start_line = start;
+                region.end_line = map_end(region.end_line);
+                region.end_line > region.start_line
+            });
+        };
+
+        apply(&mut self.derived_regions);
+        apply(&mut self.user_regions);
+        self.rebuild_merged_regions();
+    }
+
     /// Clamp fold regions to the given `line_count` after a text edit, dropping invalid regions.
     pub fn clamp_to_line_count(&mut self, line_count: usize) {
         let max_line = line_count.saturating_sub(1);
@@ -751,6 +1264,147 @@ mod tests {
         assert_eq!(tree.len(), 3);
     }
 
+    #[test]
+    fn test_interval_tree_replace_range() {
+        let mut tree = IntervalTree::new();
+        tree.insert(Interval::new(0, 5, 1));
+        tree.insert(Interval::new(6, 12, 2));
+        tree.insert(Interval::new(12, 20, 3));
+        tree.insert(Interval::new(20, 25, 4));
+
+        // Intervals overlapping 10..20 go away, including the one crossing 10.
+        tree.replace_range(
+            10,
+            20,
+            vec![Interval::new(6, 10, 5), Interval::new(15, 15, 6)],
+        );
+
+        let styles: Vec<_> = tree
+            .query_range(0, 30)
+            .into_iter()
+            .map(|i| (i.start, i.end, i.style_id))
+            .collect();
+        assert_eq!(styles, vec![(0, 5, 1), (6, 10, 5), (20, 25, 4)]);
+        assert_eq!(tree.query_point(8).len(), 1);
+    }
+
+    #[test]
+    fn test_interval_tree_remove_range_clips_and_splits() {
+        let mut tree = IntervalTree::new();
+        tree.insert(Interval::new(0, 30, 1));
+        tree.insert(Interval::new(2, 12, 2));
+        tree.insert(Interval::new(12, 16, 3));
+        tree.insert(Interval::new(18, 25, 4));
+        tree.insert(Interval::new(25, 28, 5));
+
+        assert!(tree.remove_range(10, 20));
+
+        let styles: Vec<_> = tree
+            .query_range(0, 40)
+            .into_iter()
+            .map(|i| (i.start, i.end, i.style_id))
+            .collect();
+        // 1 is split, 2 and 4 are clipped on one side, 3 is inside and removed, 5 is untouched.
+        assert_eq!(
+            styles,
+            vec![
+                (0, 10, 1),
+                (2, 10, 2),
+                (20, 25, 4),
+                (20, 30, 1),
+                (25, 28, 5)
+            ]
+        );
+        assert!(tree.query_point(15).is_empty());
+        assert_eq!(tree.query_point(26).len(), 2);
+
+        assert!(!tree.remove_range(10, 20));
+        assert!(!tree.remove_range(5, 5));
+    }
+
+    #[test]
+    fn test_style_runs_merge_overlaps_and_tile_the_range() {
+        let mut tree = IntervalTree::new();
+        tree.insert(Interval::new(0, 10, 1));
+        tree.insert(Interval::new(5, 15, 2));
+        tree.insert(Interval::new(10, 12, 1));
+        tree.insert(Interval::new(20, 20, 3));
+
+        let runs: Vec<_> = tree
+            .style_runs(2, 25)
+            .into_iter()
+            .map(|run| (run.start, run.end, run.styles))
+            .collect();
+        // `1` continues across 10 via the adjacent interval; the empty interval adds nothing.
+        assert_eq!(
+            runs,
+            vec![
+                (2, 5, vec![1]),
+                (5, 12, vec![1, 2]),
+                (12, 15, vec![2]),
+                (15, 25, vec![]),
+            ]
+        );
+
+        let mut other = IntervalTree::new();
+        other.insert(Interval::new(13, 30, 2));
+        // Style `2` from both trees merges into one run.
+        assert_eq!(
+            merged_style_runs([&tree, &other], 12, 18),
+            vec![StyleRun {
+                start: 12,
+                end: 18,
+                styles: vec![2],
+            }]
+        );
+        assert!(tree.style_runs(5, 5).is_empty());
+    }
+
+    #[test]
+    fn test_style_registry_allocates_stable_ids_shared_by_clones() {
+        let registry = StyleRegistry::new();
+        let producer = registry.clone();
+
+        let string = producer.style_id("string");
+        let comment = registry.style_id("scope:comment.line");
+        assert_ne!(string, comment);
+        assert_eq!(registry.style_id("string"), string);
+        assert_eq!(registry.get("string"), Some(string));
+        assert_eq!(registry.get("keyword"), None);
+        assert_eq!(string & 0xFF00_0000, StyleRegistry::BASE);
+
+        assert_eq!(
+            producer.name_for_style_id(comment).as_deref(),
+            Some("scope:comment.line")
+        );
+        assert_eq!(registry.name_for_style_id(StyleRegistry::BASE | 99), None);
+        assert_eq!(registry.name_for_style_id(StyleRegistry::BASE), None);
+        assert_eq!(
+            registry
+                .entries()
+                .iter()
+                .map(|(id, name)| (*id, name.to_string()))
+                .collect::<Vec<_>>(),
+            vec![
+                (string, "string".to_string()),
+                (comment, "scope:comment.line".to_string())
+            ]
+        );
+
+        // Built-in ids keep their constants.
+        assert_eq!(
+            registry.style_id("fold_placeholder"),
+            FOLD_PLACEHOLDER_STYLE_ID
+        );
+        assert_eq!(
+            registry
+                .name_for_style_id(DIAGNOSTIC_ERROR_STYLE_ID)
+                .as_deref(),
+            Some("diagnostic.error")
+        );
+        assert_eq!(registry.len(), 2);
+    }
+
     #[test]
     fn test_interval_tree_query_point() {
         let mut tree = IntervalTree::new();
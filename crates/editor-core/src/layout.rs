@@ -3,7 +3,8 @@
 //! Calculates the visual representation of text given a container width.
 //! Computes character widths based on UAX #11 and implements headless reflow algorithm.
 
-use unicode_width::UnicodeWidthChar;
+use unicode_segmentation::{GraphemeIndices, UnicodeSegmentation};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Default tab width (in cells) used when a caller does not specify a tab width.
 pub const DEFAULT_TAB_WIDTH: usize = 4;
@@ -33,6 +34,16 @@ pub enum WrapIndent {
     FixedCells(usize),
 }
 
+/// Base (paragraph) direction of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDirection {
+    /// Left-to-right.
+    #[default]
+    Ltr,
+    /// Right-to-left (Hebrew, Arabic, ...).
+    Rtl,
+}
+
 /// Wrap point
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct WrapPoint {
@@ -154,30 +165,158 @@ pub fn cell_width_at(ch: char, cell_offset_in_line: usize, tab_width: usize) ->
     }
 }
 
+/// Calculate visual width (in cells) of a grapheme cluster at a specific cell offset within the
+/// line.
+///
+/// Single-`char` clusters follow [`cell_width_at`]; multi-`char` clusters (combining marks, ZWJ
+/// emoji sequences, flags) are measured as a whole, so `e` + U+0301 is 1 cell and
+/// 👨‍👩‍👧 is 2.
+pub fn grapheme_width_at(grapheme: &str, cell_offset_in_line: usize, tab_width: usize) -> usize {
+    let mut chars = grapheme.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => cell_width_at(ch, cell_offset_in_line, tab_width),
+        _ => UnicodeWidthStr::width(grapheme),
+    }
+}
+
+/// Iterate the grapheme clusters (UAX #29) of `line` as `(char_index, byte_offset, grapheme)`.
+pub(crate) fn grapheme_columns(line: &str) -> GraphemeColumns<'_> {
+    GraphemeColumns {
+        char_index: 0,
+        // Every ASCII `char` of a line is its own cluster (`"\r\n"` never occurs within a
+        // line), so segmentation can be skipped.
+        inner: if line.is_ascii() {
+            GraphemeColumnsInner::Ascii(line, 0)
+        } else {
+            GraphemeColumnsInner::Unicode(line.grapheme_indices(true))
+        },
+    }
+}
+
+/// Iterator returned by [`grapheme_columns`].
+pub(crate) struct GraphemeColumns<'a> {
+    char_index: usize,
+    inner: GraphemeColumnsInner<'a>,
+}
+
+enum GraphemeColumnsInner<'a> {
+    Ascii(&'a str, usize),
+    Unicode(GraphemeIndices<'a>),
+}
+
+impl<'a> Iterator for GraphemeColumns<'a> {
+    type Item = (usize, usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (byte_offset, grapheme) = match &mut self.inner {
+            GraphemeColumnsInner::Ascii(line, offset) => {
+                let start = *offset;
+                let grapheme = line.get(start..start + 1)?;
+                *offset += 1;
+                (start, grapheme)
+            }
+            GraphemeColumnsInner::Unicode(graphemes) => graphemes.next()?,
+        };
+        let char_index = self.char_index;
+        self.char_index += grapheme.chars().count();
+        Some((char_index, byte_offset, grapheme))
+    }
+}
+
+/// Cell width of each `char` of `line`, measured per grapheme cluster.
+///
+/// The first `char` of a cluster carries the width of the whole cluster (see
+/// [`grapheme_width_at`]) and the remaining `char`s of the cluster have width 0, so the result can
+/// be indexed by `char` column.
+pub fn grapheme_cell_widths(line: &str, tab_width: usize) -> Vec<usize> {
+    let mut widths = Vec::with_capacity(line.len());
+    for (_, grapheme, w) in grapheme_cells(line, tab_width) {
+        widths.push(w);
+        widths.extend(grapheme.chars().skip(1).map(|_| 0));
+    }
+    widths
+}
+
+/// Iterate the grapheme clusters of `line` as `(char_index, grapheme, cell_width)`.
+fn grapheme_cells(line: &str, tab_width: usize) -> impl Iterator<Item = (usize, &str, usize)> {
+    let mut x = 0usize;
+    grapheme_columns(line).map(move |(char_index, _, grapheme)| {
+        let w = grapheme_width_at(grapheme, x, tab_width);
+        x = x.saturating_add(w);
+        (char_index, grapheme, w)
+    })
+}
+
 /// Calculate total visual width of a string
 pub fn str_width(s: &str) -> usize {
-    s.chars().map(char_width).sum()
+    grapheme_columns(s)
+        .map(|(_, _, g)| grapheme_width_at(g, 0, 1))
+        .sum()
 }
 
 /// Calculate total visual width of a string, interpreting `'\t'` using `tab_width`.
 pub fn str_width_with_tab_width(s: &str, tab_width: usize) -> usize {
-    let mut x = 0usize;
-    for ch in s.chars() {
-        x = x.saturating_add(cell_width_at(ch, x, tab_width));
-    }
-    x
+    grapheme_cells(s, tab_width).map(|(_, _, w)| w).sum()
 }
 
 /// Calculate the visual cell offset from the start of the line to the given character column.
 ///
 /// - `column` is counted in `char` (not bytes).
 /// - `'\t'` is expanded using `tab_width` and the current cell offset.
+/// - Width is measured per grapheme cluster: a column inside a cluster maps to the cell after it.
 pub fn visual_x_for_column(line: &str, column: usize, tab_width: usize) -> usize {
-    let mut x = 0usize;
-    for ch in line.chars().take(column) {
-        x = x.saturating_add(cell_width_at(ch, x, tab_width));
+    grapheme_cells(line, tab_width)
+        .take_while(|(char_index, _, _)| *char_index < column)
+        .map(|(_, _, w)| w)
+        .sum()
+}
+
+/// Base direction of a line: the direction of its first strong (letter) character, as in the
+/// UAX #9 paragraph rules (P2/P3) with each line treated as a paragraph. Lines without letters
+/// are left-to-right.
+///
+/// Only the base direction is resolved: a right-to-left line is laid out entirely right to left,
+/// including embedded left-to-right runs (numbers, Latin words); mixed-direction reordering is not
+/// implemented.
+pub fn line_direction(line: &str) -> TextDirection {
+    line.chars()
+        .find(|ch| ch.is_alphabetic())
+        .map_or(TextDirection::Ltr, |ch| {
+            if is_rtl_char(ch) {
+                TextDirection::Rtl
+            } else {
+                TextDirection::Ltr
+            }
+        })
+}
+
+/// Whether `ch` belongs to a right-to-left script block (Hebrew, Arabic, Syriac, Thaana, NKo,
+/// Samaritan, Mandaic, their presentation forms, and the historic RTL blocks).
+fn is_rtl_char(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFC | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF
+    )
+}
+
+/// Map a cell offset within the segment `start_col..end_col` of `line` between logical order
+/// (from the segment start) and display order (from the segment's left edge).
+///
+/// Left-to-right lines are unchanged; right-to-left lines are mirrored within the segment's
+/// width. The mapping is its own inverse, so it serves both conversion directions.
+pub(crate) fn directional_x_in_segment(
+    line: &str,
+    start_col: usize,
+    end_col: usize,
+    x_in_segment: usize,
+    tab_width: usize,
+) -> usize {
+    if line_direction(line) == TextDirection::Ltr {
+        return x_in_segment;
     }
-    x
+    let width = visual_x_for_column(line, end_col, tab_width)
+        .saturating_sub(visual_x_for_column(line, start_col, tab_width));
+    width.saturating_sub(x_in_segment)
 }
 
 fn leading_whitespace_prefix_slice(line: &str) -> &str {
@@ -257,6 +396,10 @@ pub fn calculate_wrap_points_with_tab_width_mode_and_indent(
     if viewport_width == 0 {
         return Vec::new();
     }
+    // Outside tabs a grapheme is never wider than its UTF-8 length, so short lines fit.
+    if text.len() <= viewport_width && !text.contains('\t') {
+        return Vec::new();
+    }
 
     match wrap_mode {
         WrapMode::None => Vec::new(),
@@ -283,8 +426,9 @@ fn calculate_wrap_points_char_with_tab_width(
     let mut x_in_segment = 0usize;
     let mut x_in_line = 0usize;
 
-    for (char_index, (byte_offset, ch)) in text.char_indices().enumerate() {
-        let ch_width = cell_width_at(ch, x_in_line, tab_width);
+    // Grapheme clusters are never split across segments.
+    for (char_index, byte_offset, grapheme) in grapheme_columns(text) {
+        let ch_width = grapheme_width_at(grapheme, x_in_line, tab_width);
 
         // If adding this character would exceed the width limit
         if x_in_segment + ch_width > viewport_width {
@@ -305,10 +449,10 @@ fn calculate_wrap_points_char_with_tab_width(
         // If current width equals viewport width exactly, the next character should wrap
         if x_in_segment == viewport_width {
             // Check if there are more characters
-            if byte_offset + ch.len_utf8() < text.len() {
+            if byte_offset + grapheme.len() < text.len() {
                 wrap_points.push(WrapPoint {
-                    char_index: char_index + 1,
-                    byte_offset: byte_offset + ch.len_utf8(),
+                    char_index: char_index + grapheme.chars().count(),
+                    byte_offset: byte_offset + grapheme.len(),
                 });
                 x_in_segment = wrap_indent_cells;
             }
@@ -332,8 +476,8 @@ fn calculate_wrap_points_word_with_tab_width(
 
     let mut x_in_line = 0usize;
 
-    for (char_index, (byte_offset, ch)) in text.char_indices().enumerate() {
-        let ch_width = cell_width_at(ch, x_in_line, tab_width);
+    for (char_index, byte_offset, grapheme) in grapheme_columns(text) {
+        let ch_width = grapheme_width_at(grapheme, x_in_line, tab_width);
 
         loop {
             let segment_indent = if segment_start_char == 0 {
@@ -374,8 +518,12 @@ fn calculate_wrap_points_word_with_tab_width(
 
         x_in_line = x_in_line.saturating_add(ch_width);
 
-        if ch.is_whitespace() {
-            last_break = Some((char_index + 1, byte_offset + ch.len_utf8(), x_in_line));
+        if grapheme.chars().all(char::is_whitespace) {
+            last_break = Some((
+                char_index + grapheme.chars().count(),
+                byte_offset + grapheme.len(),
+                x_in_line,
+            ));
         }
     }
 
@@ -631,6 +779,9 @@ impl LayoutEngine {
     /// Return value:
     /// - `Some((visual_row, x))`: `visual_row` is the global visual row number, `x` is the cell offset within that visual row
     /// - `None`: Line number out of range
+    ///
+    /// On right-to-left lines (see [`line_direction`]) `x` is mirrored within the row's text, so
+    /// column 0 sits at the right edge of the segment.
     pub fn logical_position_to_visual(
         &self,
         logical_line: usize,
@@ -657,18 +808,21 @@ impl LayoutEngine {
         }
 
         // Calculate visual width from segment start to column, with tab expansion.
-        let seg_start_x_in_line = visual_x_for_column(line_text, segment_start_col, self.tab_width);
-        let mut x_in_line = seg_start_x_in_line;
-        let mut x_in_segment = 0usize;
-        for ch in line_text
-            .chars()
-            .skip(segment_start_col)
-            .take(column.saturating_sub(segment_start_col))
-        {
-            let w = cell_width_at(ch, x_in_line, self.tab_width);
-            x_in_line = x_in_line.saturating_add(w);
-            x_in_segment = x_in_segment.saturating_add(w);
-        }
+        let x_in_segment = visual_x_for_column(line_text, column, self.tab_width).saturating_sub(
+            visual_x_for_column(line_text, segment_start_col, self.tab_width),
+        );
+
+        let segment_end_col = layout
+            .wrap_points
+            .get(wrapped_offset)
+            .map_or(line_char_len, |wp| wp.char_index);
+        let x_in_segment = directional_x_in_segment(
+            line_text,
+            segment_start_col,
+            segment_end_col,
+            x_in_segment,
+            self.tab_width,
+        );
 
         let indent = if wrapped_offset == 0 {
             0
@@ -690,6 +844,8 @@ impl LayoutEngine {
     /// Difference from [`logical_position_to_visual`](Self::logical_position_to_visual):
     /// - `column` is not clamped to `line_char_len`
     /// - Excess portion is treated as virtual spaces of `' '` (width=1)
+    /// - Text direction is ignored: columns are placed left to right (rectangular selection
+    ///   operates on logical columns)
     pub fn logical_position_to_visual_allow_virtual(
         &self,
         logical_line: usize,
@@ -712,18 +868,10 @@ impl LayoutEngine {
             }
         }
 
-        let seg_start_x_in_line = visual_x_for_column(line_text, segment_start_col, self.tab_width);
-        let mut x_in_line = seg_start_x_in_line;
-        let mut x_in_segment = 0usize;
-        for ch in line_text
-            .chars()
-            .skip(segment_start_col)
-            .take(clamped_column.saturating_sub(segment_start_col))
-        {
-            let w = cell_width_at(ch, x_in_line, self.tab_width);
-            x_in_line = x_in_line.saturating_add(w);
-            x_in_segment = x_in_segment.saturating_add(w);
-        }
+        let x_in_segment =
+            visual_x_for_column(line_text, clamped_column, self.tab_width).saturating_sub(
+                visual_x_for_column(line_text, segment_start_col, self.tab_width),
+            );
 
         let indent = if wrapped_offset == 0 {
             0
@@ -965,4 +1113,20 @@ mod tests {
         // Visual line 3 -> logical line 2
         assert_eq!(engine.visual_to_logical_line(3), (2, 0));
     }
+
+    #[test]
+    fn test_rtl_line_positions_are_mirrored() {
+        let mut engine = LayoutEngine::new(10);
+        engine.from_lines(&["abc", "\tשלום"]);
+
+        assert_eq!(engine.logical_position_to_visual(0, 1), Some((0, 1)));
+        // Tab (4 cells) + 4 letters: the caret before the tab is at the right edge.
+        assert_eq!(engine.logical_position_to_visual(1, 0), Some((1, 8)));
+        assert_eq!(engine.logical_position_to_visual(1, 1), Some((1, 4)));
+        assert_eq!(engine.logical_position_to_visual(1, 5), Some((1, 0)));
+        assert_eq!(
+            engine.logical_position_to_visual_allow_virtual(1, 2),
+            Some((1, 5))
+        );
+    }
 }
@@ -106,6 +106,7 @@
 //! - [`line_index`] - Rope based line index
 //! - [`layout`] - soft wrappinglayout engine
 //! - [`intervals`] - Style interval tree andcode foldingmanagement
+//! - [`folding`] - Indentation-based fold regions and merging with provider regions
 //! - [`snapshot`] - Headless snapshot API (HeadlessGrid)
 //! - [`commands`] - Unified command interface
 //! - [`state`] - State management and query interface
@@ -121,65 +122,103 @@
 //!
 //! - UTF-8 internal encoding
 //! - Proper handling of CJK double-width characters
+//! - Display width measured per grapheme cluster: combining marks and ZWJ emoji sequences share one
+//!   [`Cell`] in the styled grid and never straddle a soft wrap
 //! - Grapheme/word-aware cursor + delete commands (UAX #29), while keeping `char`-indexed
 //!   coordinates at the API boundary
+//! - Per-line base direction ([`line_direction`]): right-to-left lines are mirrored in the styled
+//!   grid and in logical/visual position mapping (no mixed-direction reordering within a line)
 //! - via `editor-core-lsp` provides UTF-16 code unit coordinate conversion (for upper-layer protocols/integrations)
 //! - via `editor-core-sublime` provides `.sublime-syntax` syntax highlighting and folding (optional integration)
 
 pub mod commands;
+pub mod completion;
 pub mod decorations;
 pub mod delta;
 pub mod diagnostics;
+pub mod diff;
+pub mod folding;
+pub mod indent;
 pub mod intervals;
 pub mod layout;
 pub mod line_ending;
 pub mod line_index;
+pub mod pattern;
 pub mod processing;
 pub mod search;
 mod selection_set;
 pub mod snapshot;
+pub mod snippets;
 pub mod state;
 pub mod storage;
 pub mod symbols;
 mod text;
+pub mod theme;
 pub mod workspace;
 
 pub use commands::{
     Command, CommandError, CommandExecutor, CommandResult, CursorCommand, EditCommand, EditorCore,
-    Position, Selection, SelectionDirection, StyleCommand, TabKeyBehavior, TextEditSpec,
-    ViewCommand,
+    IndentProvider, Position, Selection, SelectionDirection, SelectionMergePolicy, StyleCommand,
+    TabKeyBehavior, TextEditSpec, ViewCommand,
+};
+pub use completion::{
+    CompletionAcceptMode, completion_edit_at_caret, completion_edits_at_carets,
+    fuzzy_match_positions, fuzzy_match_score,
 };
 pub use decorations::{
     Decoration, DecorationKind, DecorationLayerId, DecorationPlacement, DecorationRange,
+    UnderlineStyle, diagnostic_style_id, diagnostic_tag_style_id, diagnostic_underline_decorations,
+};
+pub use delta::{DeltaError, TextDelta, TextDeltaEdit};
+pub use diagnostics::{
+    Diagnostic, DiagnosticRange, DiagnosticSeverity, DiagnosticTag, RelatedDiagnostic,
+    diagnostics_at_offset, next_diagnostic,
+};
+pub use diff::{DiffHunk, diff_lines};
+pub use editor_core_lang::{CommentConfig, LanguageConfig};
+pub use folding::{
+    FoldingMergePolicy, carry_collapsed_state, merge_fold_regions, regions_from_indentation,
 };
-pub use delta::{TextDelta, TextDeltaEdit};
-pub use diagnostics::{Diagnostic, DiagnosticRange, DiagnosticSeverity};
-pub use editor_core_lang::CommentConfig;
+pub use indent::{IndentSettings, detect_indentation};
 pub use intervals::{
-    DOCUMENT_HIGHLIGHT_READ_STYLE_ID, DOCUMENT_HIGHLIGHT_TEXT_STYLE_ID,
-    DOCUMENT_HIGHLIGHT_WRITE_STYLE_ID, FOLD_PLACEHOLDER_STYLE_ID, FoldRegion, FoldingManager,
-    IntervalTree, StyleLayerId,
+    CURRENT_LINE_STYLE_ID, DIAGNOSTIC_DEPRECATED_STYLE_ID, DIAGNOSTIC_ERROR_STYLE_ID,
+    DIAGNOSTIC_HINT_STYLE_ID, DIAGNOSTIC_INFORMATION_STYLE_ID, DIAGNOSTIC_UNNECESSARY_STYLE_ID,
+    DIAGNOSTIC_WARNING_STYLE_ID, DOCUMENT_HIGHLIGHT_READ_STYLE_ID,
+    DOCUMENT_HIGHLIGHT_TEXT_STYLE_ID, DOCUMENT_HIGHLIGHT_WRITE_STYLE_ID, FOLD_PLACEHOLDER_STYLE_ID,
+    FoldRegion, FoldingManager, IntervalTree, SEARCH_CURRENT_MATCH_STYLE_ID, SEARCH_MATCH_STYLE_ID,
+    StyleLayerId, StylePriority, StyleRegistry, StyleRun, WHITESPACE_STYLE_ID,
 };
-pub use layout::{LayoutEngine, WrapIndent, WrapMode};
+pub use layout::{LayoutEngine, TextDirection, WrapIndent, WrapMode, line_direction};
 pub use line_ending::LineEnding;
 pub use line_index::LineIndex;
-pub use processing::{DocumentProcessor, ProcessingEdit};
-pub use search::{SearchError, SearchMatch, SearchOptions};
+pub use pattern::{PatternError, PatternLimits, PatternOptions};
+pub use processing::{
+    DocumentProcessor, PipelineError, ProcessingEdit, ProcessingEditBatch, ProcessorFailure,
+    ProcessorInput, ProcessorPipeline, ProcessorSpec, StaleBatchError, StaleBatchPolicy,
+};
+pub use search::{FindAllIter, SearchError, SearchMatch, SearchMatchCount, SearchOptions};
 pub use snapshot::{
-    Cell, ComposedCell, ComposedCellSource, ComposedGrid, ComposedLine, ComposedLineKind,
-    HeadlessGrid, HeadlessLine, MinimapGrid, MinimapLine, SnapshotGenerator,
+    Cell, CellSource, ComposedCell, ComposedCellSource, ComposedGrid, ComposedLine,
+    ComposedLineKind, GutterFoldState, GutterLine, HeadlessGrid, HeadlessLine, MinimapCell,
+    MinimapData, MinimapGrid, MinimapLine, MinimapRow, ResolvedCell, ResolvedGrid, ResolvedLine,
+    RowDiff, SnapshotGenerator, SnapshotOptions, WhitespaceMode,
+};
+pub use snippets::{
+    ParsedSnippet, SnippetSession, SnippetTabstop, SnippetTabstopGroup, SnippetTabstopRange,
+    parse_snippet,
 };
 pub use state::{
     CursorState, DecorationsState, DiagnosticsState, DocumentState, EditorState,
-    EditorStateManager, FoldingState, SmoothScrollState, StateChange, StateChangeCallback,
-    StateChangeType, StyleState, UndoRedoState, ViewportState,
+    EditorStateManager, FoldingState, PinId, SmoothScrollState, StateChange, StateChangeCallback,
+    StateChangeType, StyleState, UndoRedoState, VersionPin, ViewportState,
 };
 pub use storage::PieceTable;
 pub use symbols::{
-    DocumentOutline, DocumentSymbol, SymbolKind, SymbolLocation, SymbolRange, Utf16Position,
-    Utf16Range, WorkspaceSymbol,
+    DocumentOutline, DocumentSymbol, ScoredSymbol, SymbolKind, SymbolLocation, SymbolRange,
+    Utf16Position, Utf16Range, WorkspaceSymbol, filter_workspace_symbols,
 };
+pub use theme::{Rgb, TextAttributes, Theme, ThemeError, ThemeRule};
 pub use workspace::{
-    BufferId, BufferMetadata, OpenBufferResult, ViewId, ViewSmoothScrollState, Workspace,
-    WorkspaceError, WorkspaceSearchResult, WorkspaceViewportState,
+    BufferId, BufferMetadata, OpenBufferResult, ScrollLinkMode, ViewId, ViewSmoothScrollState,
+    Workspace, WorkspaceError, WorkspaceSearchResult, WorkspaceViewportState,
 };
@@ -2,7 +2,9 @@
 //!
 //! `editor-core` stores text internally using LF (`'\n'`) newlines.
 //! When opening a file that uses CRLF (`"\r\n"`), the content is normalized on load, but the
-//! preferred line ending can be tracked for saving.
+//! preferred line ending can be tracked for saving. Files that mix both are reported as
+//! [`LineEnding::Mixed`]; their per-line endings are kept by
+//! [`LineIndex`](crate::LineIndex) so they can be written back unchanged.
 
 /// The preferred newline sequence used when saving a document.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -11,25 +13,54 @@ pub enum LineEnding {
     Lf,
     /// Windows-style CRLF (`"\r\n"`).
     Crlf,
+    /// Both LF and CRLF occur in the source text.
+    ///
+    /// There is no single sequence to save with: the original ending of each line is used
+    /// instead (see [`LineIndex::get_text_with_original_endings`](crate::LineIndex::get_text_with_original_endings)).
+    Mixed,
 }
 
 impl LineEnding {
     /// Detect the dominant line ending from a source text.
     ///
-    /// Policy: if the input contains any CRLF (`"\r\n"`), returns [`LineEnding::Crlf`],
+    /// Policy: if the input contains both CRLF (`"\r\n"`) and bare LF newlines, returns
+    /// [`LineEnding::Mixed`]; if it contains only CRLF, returns [`LineEnding::Crlf`];
     /// otherwise [`LineEnding::Lf`].
     pub fn detect_in_text(text: &str) -> Self {
-        if text.contains("\r\n") {
-            Self::Crlf
-        } else {
-            Self::Lf
+        let bytes = text.as_bytes();
+        let mut has_crlf = false;
+        let mut has_lf = false;
+        for (i, &b) in bytes.iter().enumerate() {
+            if b != b'\n' {
+                continue;
+            }
+            if i > 0 && bytes[i - 1] == b'\r' {
+                has_crlf = true;
+            } else {
+                has_lf = true;
+            }
+            if has_crlf && has_lf {
+                return Self::Mixed;
+            }
+        }
+        if has_crlf { Self::Crlf } else { Self::Lf }
+    }
+
+    /// The newline sequence itself (`"\n"` for [`LineEnding::Mixed`]).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf | Self::Mixed => "\n",
+            Self::Crlf => "\r\n",
         }
     }
 
     /// Convert an LF-normalized text to this line ending for saving.
+    ///
+    /// [`LineEnding::Mixed`] leaves the text unchanged, since the ending of each line is not
+    /// known here.
     pub fn apply_to_text(self, text: &str) -> String {
         match self {
-            Self::Lf => text.to_string(),
+            Self::Lf | Self::Mixed => text.to_string(),
             Self::Crlf => text.replace('\n', "\r\n"),
         }
     }
@@ -2,6 +2,7 @@
 //!
 //! Provides efficient line indexing using Rope data structure, supporting O(log N) access and editing.
 
+use crate::line_ending::LineEnding;
 use crate::storage::Piece;
 use ropey::Rope;
 
@@ -50,22 +51,94 @@ impl Default for LineMetadata {
 /// Logical line index - implemented using Rope data structure
 ///
 /// Rope provides O(log N) line access, insertion, and deletion performance, suitable for large file editing
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct LineIndex {
     /// Rope data structure that automatically manages line indexing
     rope: Rope,
+    /// Original ending of each line break (entry `i` ends line `i`), when recorded.
+    ///
+    /// `None` means every line ends with LF, which is also what the rope stores.
+    line_endings: Option<Vec<LineEnding>>,
 }
 
 impl LineIndex {
     /// Create a new line index
     pub fn new() -> Self {
-        Self { rope: Rope::new() }
+        Self {
+            rope: Rope::new(),
+            line_endings: None,
+        }
     }
 
     /// Build line index from text
     pub fn from_text(text: &str) -> Self {
         Self {
             rope: Rope::from_str(text),
+            line_endings: None,
+        }
+    }
+
+    /// Build a line index from text that may contain CRLF newlines.
+    ///
+    /// The text is normalized to LF like everywhere else in the kernel, but the original ending
+    /// of each line is recorded so that [`line_ending_at`](Self::line_ending_at) and
+    /// [`get_text_with_original_endings`](Self::get_text_with_original_endings) can report it.
+    /// A lone `'\r'` is recorded as [`LineEnding::Lf`] and is not preserved.
+    pub fn from_text_preserving_line_endings(text: &str) -> Self {
+        if !text.contains('\r') {
+            return Self::from_text(text);
+        }
+
+        let original = Rope::from_str(text);
+        let line_endings = original
+            .lines()
+            .take(original.len_lines().saturating_sub(1))
+            .map(|line| {
+                let len = line.len_chars();
+                if len >= 2 && line.char(len - 2) == '\r' && line.char(len - 1) == '\n' {
+                    LineEnding::Crlf
+                } else {
+                    LineEnding::Lf
+                }
+            })
+            .collect();
+
+        Self {
+            rope: Rope::from_str(&crate::text::normalize_crlf_to_lf(text)),
+            line_endings: Some(line_endings),
+        }
+    }
+
+    /// Original ending of the specified line.
+    ///
+    /// Returns `None` for the last line (which has no ending) and for out-of-range lines.
+    /// Without recorded endings (see
+    /// [`from_text_preserving_line_endings`](Self::from_text_preserving_line_endings)) every
+    /// line reports [`LineEnding::Lf`].
+    pub fn line_ending_at(&self, line_number: usize) -> Option<LineEnding> {
+        if line_number + 1 >= self.rope.len_lines() {
+            return None;
+        }
+        Some(
+            self.line_endings
+                .as_ref()
+                .and_then(|endings| endings.get(line_number).copied())
+                .unwrap_or(LineEnding::Lf),
+        )
+    }
+
+    /// Summarize the recorded line endings: [`LineEnding::Mixed`] if both LF and CRLF lines
+    /// are present.
+    pub fn line_ending(&self) -> LineEnding {
+        let Some(endings) = self.line_endings.as_ref() else {
+            return LineEnding::Lf;
+        };
+        let has_crlf = endings.contains(&LineEnding::Crlf);
+        let has_lf = endings.contains(&LineEnding::Lf);
+        match (has_crlf, has_lf) {
+            (true, true) => LineEnding::Mixed,
+            (true, false) => LineEnding::Crlf,
+            _ => LineEnding::Lf,
         }
     }
 
@@ -76,7 +149,7 @@ impl LineIndex {
 
         // If not the first line, add a newline first
         if current_len > 0 {
-            self.rope.insert(current_len, "\n");
+            self.insert(current_len, "\n");
         }
 
         // Add line content (LineMetadata doesn't store actual text, using placeholder here)
@@ -97,8 +170,8 @@ impl LineIndex {
 
         // Insert new line content
         let placeholder = "x".repeat(line.char_count);
-        self.rope.insert(insert_pos, &placeholder);
-        self.rope.insert(insert_pos + line.char_count, "\n");
+        self.insert(insert_pos, &placeholder);
+        self.insert(insert_pos + line.char_count, "\n");
     }
 
     /// Delete the specified line
@@ -114,7 +187,7 @@ impl LineIndex {
             self.rope.len_chars()
         };
 
-        self.rope.remove(start_char..end_char);
+        self.delete(start_char, end_char - start_char);
     }
 
     /// Get metadata for the specified line number (simulated)
@@ -249,6 +322,22 @@ impl LineIndex {
         self.rope.byte_to_char(byte_offset)
     }
 
+    /// Convert a `(line, column)` position (column in chars) to a UTF-8 byte offset.
+    ///
+    /// The column is clamped to the line length (excluding the newline), like
+    /// [`position_to_char_offset`](Self::position_to_char_offset).
+    pub fn position_to_byte_offset(&self, line: usize, column: usize) -> usize {
+        self.rope
+            .char_to_byte(self.position_to_char_offset(line, column))
+    }
+
+    /// Convert a UTF-8 byte offset to a `(line, column)` position (column in chars).
+    ///
+    /// A byte offset inside a multi-byte character maps to the start of that character.
+    pub fn byte_offset_to_position(&self, byte_offset: usize) -> (usize, usize) {
+        self.char_offset_to_position(self.byte_offset_to_char_offset(byte_offset))
+    }
+
     /// Convert a character offset to `(line, byte_column)` where `byte_column` is measured in UTF-8 bytes.
     pub fn char_offset_to_line_byte_column(&self, char_offset: usize) -> (usize, usize) {
         let char_offset = char_offset.min(self.rope.len_chars());
@@ -263,7 +352,24 @@ impl LineIndex {
     /// Insert text (at specified character offset)
     pub fn insert(&mut self, char_offset: usize, text: &str) {
         let char_offset = char_offset.min(self.rope.len_chars());
+        let lines_before = self.rope.len_lines();
         self.rope.insert(char_offset, text);
+
+        if let Some(endings) = self.line_endings.as_mut() {
+            let added = self.rope.len_lines() - lines_before;
+            if added > 0 {
+                // The split line keeps its ending on its last piece; new breaks inherit it so
+                // that typing Enter inside a CRLF region stays CRLF.
+                let line = self.rope.char_to_line(char_offset);
+                let inherited = endings
+                    .get(line)
+                    .or(endings.last())
+                    .copied()
+                    .unwrap_or(LineEnding::Lf);
+                let at = line.min(endings.len());
+                endings.splice(at..at, std::iter::repeat_n(inherited, added));
+            }
+        }
     }
 
     /// Delete text range (character offset)
@@ -272,15 +378,54 @@ impl LineIndex {
         let end_char = (start_char + len_chars).min(self.rope.len_chars());
 
         if start_char < end_char {
+            let start_line = self.rope.char_to_line(start_char);
+            let lines_before = self.rope.len_lines();
             self.rope.remove(start_char..end_char);
+
+            if let Some(endings) = self.line_endings.as_mut() {
+                // The joined line keeps the ending of the last line in the range.
+                let removed = lines_before - self.rope.len_lines();
+                let start = start_line.min(endings.len());
+                let end = (start_line + removed).min(endings.len());
+                endings.drain(start..end);
+            }
         }
     }
 
+    /// Borrow the underlying rope.
+    pub(crate) fn rope(&self) -> &Rope {
+        &self.rope
+    }
+
     /// Get complete text
     pub fn get_text(&self) -> String {
         self.rope.to_string()
     }
 
+    /// Get complete text with each line terminated by its original ending.
+    ///
+    /// Identical to [`get_text`](Self::get_text) when no endings were recorded.
+    pub fn get_text_with_original_endings(&self) -> String {
+        let Some(endings) = self.line_endings.as_ref() else {
+            return self.get_text();
+        };
+
+        let mut text = String::with_capacity(self.rope.len_bytes() + endings.len());
+        for (line_number, line) in self.rope.lines().enumerate() {
+            match endings.get(line_number) {
+                Some(LineEnding::Crlf)
+                    if line.len_chars() > 0 && line.char(line.len_chars() - 1) == '\n' =>
+                {
+                    let content = line.slice(..line.len_chars() - 1);
+                    text.extend(content.chunks());
+                    text.push_str(LineEnding::Crlf.as_str());
+                }
+                _ => text.extend(line.chunks()),
+            }
+        }
+        text
+    }
+
     /// Get text of the specified line (excluding newline)
     pub fn get_line_text(&self, line_number: usize) -> Option<String> {
         if line_number >= self.rope.len_lines() {
@@ -465,4 +610,46 @@ mod tests {
             assert_eq!(line_start_byte + byte_col, byte_offset);
         }
     }
+
+    #[test]
+    fn test_position_byte_offset_roundtrip() {
+        let text = "a你好\n🌍b\ne\u{301}👨\u{200D}👩";
+        let index = LineIndex::from_text(text);
+
+        for char_offset in 0..=index.char_count() {
+            let (line, column) = index.char_offset_to_position(char_offset);
+            let byte_offset = index.position_to_byte_offset(line, column);
+            assert_eq!(
+                byte_offset,
+                text.char_indices()
+                    .nth(char_offset)
+                    .map_or(text.len(), |(b, _)| b)
+            );
+            assert_eq!(index.byte_offset_to_position(byte_offset), (line, column));
+        }
+
+        // Columns clamp to the line; bytes inside a char map to its start.
+        assert_eq!(index.position_to_byte_offset(0, 99), "a你好".len());
+        assert_eq!(index.byte_offset_to_position(2), (0, 1));
+        assert_eq!(index.byte_offset_to_position(usize::MAX), (2, 5));
+    }
+
+    #[test]
+    fn test_line_endings_track_multi_line_edits() {
+        let mut index = LineIndex::from_text_preserving_line_endings("a\r\nb\nc\r\nd");
+        assert_eq!(index.get_text(), "a\nb\nc\nd");
+        assert_eq!(index.line_ending(), LineEnding::Mixed);
+
+        // Remove "b\nc\r\n": the joined line keeps the CRLF that ended "c".
+        index.delete(2, 4);
+        assert_eq!(index.get_text_with_original_endings(), "a\r\nd");
+        assert_eq!(index.line_ending(), LineEnding::Crlf);
+
+        // Appending after the last line inherits the previous ending.
+        index.insert(index.char_count(), "\ne\nf");
+        assert_eq!(index.get_text_with_original_endings(), "a\r\nd\r\ne\r\nf");
+        assert_eq!(index.line_ending_at(3), None);
+
+        assert_eq!(LineEnding::detect_in_text("x\r\ny\n"), LineEnding::Mixed);
+    }
 }
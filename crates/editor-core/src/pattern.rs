@@ -0,0 +1,426 @@
+//! Compilation helpers for user-supplied patterns.
+//!
+//! User patterns (search queries, highlighting rules) can exceed the compilation limits of the
+//! `regex` crate, e.g. "match any of these 5000 words" alternations. This module wraps regex
+//! compilation so that:
+//!
+//! - size/complexity failures are reported as a typed [`PatternError::TooComplex`] with a
+//!   user-presentable message (or [`PatternError::EngineLimit`] for other regex engines, such as
+//!   the Oniguruma patterns of `.sublime-syntax` files)
+//! - callers can set per-call size limits ([`PatternLimits`])
+//! - a pattern that is a pure alternation of literals (`foo|bar|baz`) falls back to an in-crate
+//!   literal-set matcher producing the same matches the regex would
+
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Default compiled-program size limit used by the `regex` crate (in bytes).
+pub const DEFAULT_REGEX_SIZE_LIMIT: usize = 10 * (1 << 20);
+
+/// Default lazy-DFA cache size limit used by the `regex` crate (in bytes).
+pub const DEFAULT_REGEX_DFA_SIZE_LIMIT: usize = 2 * (1 << 20);
+
+/// How many times the size limit is doubled when measuring how much a too-complex pattern needs.
+///
+/// Each doubling is another compilation, so this bounds the extra time spent on a failing pattern.
+pub const ESTIMATE_DOUBLINGS: u32 = 2;
+
+/// Size limits applied when compiling a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternLimits {
+    /// Maximum size of the compiled program (bytes).
+    pub size_limit: usize,
+    /// Maximum size of the lazy DFA cache (bytes).
+    pub dfa_size_limit: usize,
+}
+
+impl Default for PatternLimits {
+    fn default() -> Self {
+        Self {
+            size_limit: DEFAULT_REGEX_SIZE_LIMIT,
+            dfa_size_limit: DEFAULT_REGEX_DFA_SIZE_LIMIT,
+        }
+    }
+}
+
+/// Options that control how a pattern is compiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PatternOptions {
+    /// If `true`, matching ignores case (Unicode simple case folding).
+    pub case_insensitive: bool,
+    /// If `true`, `^`/`$` match at line boundaries.
+    pub multi_line: bool,
+    /// Size limits for compilation.
+    pub limits: PatternLimits,
+}
+
+/// Pattern compilation errors.
+#[derive(Debug, Clone)]
+pub enum PatternError {
+    /// The pattern is not a valid regex.
+    Invalid(regex::Error),
+    /// The compiled pattern would exceed the configured size limit.
+    TooComplex {
+        /// The size limit that was exceeded (bytes).
+        limit: usize,
+        /// Lower bound on the compiled size (bytes): the largest limit, doubling from `limit` at
+        /// most [`ESTIMATE_DOUBLINGS`] times, that the pattern was measured not to fit in. Unless
+        /// every doubling failed, the pattern fits in twice this much.
+        estimate: usize,
+    },
+    /// A regex engine other than `regex` rejected the pattern for exceeding one of its own limits
+    /// (nesting depth, repeat count, number of captures, ...); holds the engine's message.
+    EngineLimit(String),
+}
+
+impl std::fmt::Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(err) => write!(f, "Invalid regex: {}", err),
+            Self::TooComplex { limit, estimate } => write!(
+                f,
+                "Pattern is too complex: it needs more than {} KiB but the limit is {} KiB; \
+                 try a simpler pattern or split the search",
+                estimate.div_ceil(1024),
+                limit.div_ceil(1024)
+            ),
+            Self::EngineLimit(message) => write!(
+                f,
+                "Pattern is too complex: {}; try a simpler pattern",
+                message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// A compiled pattern: either a regex, or a literal-set matcher used as a fallback.
+#[derive(Debug, Clone)]
+pub enum CompiledPattern {
+    /// A regular `regex::Regex`.
+    Regex(Regex),
+    /// A literal-set matcher for a pure alternation of literals that exceeded regex limits.
+    Literals(LiteralSet),
+}
+
+impl CompiledPattern {
+    /// Returns the underlying regex, if this pattern was compiled as one.
+    pub fn as_regex(&self) -> Option<&Regex> {
+        match self {
+            Self::Regex(re) => Some(re),
+            Self::Literals(_) => None,
+        }
+    }
+
+    /// Returns `true` if this pattern uses the literal-set fallback.
+    pub fn is_literal_fallback(&self) -> bool {
+        matches!(self, Self::Literals(_))
+    }
+
+    /// Find the first match starting at or after `start` (byte offsets).
+    pub fn find_at(&self, text: &str, start: usize) -> Option<Range<usize>> {
+        match self {
+            Self::Regex(re) => re.find_at(text, start).map(|m| m.range()),
+            Self::Literals(set) => set.find_at(text, start),
+        }
+    }
+
+    /// Iterate all non-overlapping matches in `text` (byte offsets).
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> PatternMatches<'r, 't> {
+        let inner = match self {
+            Self::Regex(re) => MatchesInner::Regex(re.find_iter(text)),
+            Self::Literals(set) => MatchesInner::Literals { set, text, pos: 0 },
+        };
+        PatternMatches { inner }
+    }
+}
+
+/// Iterator over the matches of a [`CompiledPattern`] (byte ranges).
+pub struct PatternMatches<'r, 't> {
+    inner: MatchesInner<'r, 't>,
+}
+
+enum MatchesInner<'r, 't> {
+    Regex(regex::Matches<'r, 't>),
+    Literals {
+        set: &'r LiteralSet,
+        text: &'t str,
+        pos: usize,
+    },
+}
+
+impl Iterator for PatternMatches<'_, '_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            MatchesInner::Regex(matches) => matches.next().map(|m| m.range()),
+            MatchesInner::Literals { set, text, pos } => {
+                let found = set.find_at(text, *pos)?;
+                *pos = found.end;
+                Some(found)
+            }
+        }
+    }
+}
+
+/// A leftmost-first multi-literal matcher.
+///
+/// Matches are identical to what `regex` reports for `lit0|lit1|...`: the leftmost match wins,
+/// and among literals matching at the same position the earliest one in the alternation wins.
+#[derive(Debug, Clone)]
+pub struct LiteralSet {
+    /// Trie over (case-folded) literal chars; node 0 is the root.
+    nodes: Vec<TrieNode>,
+    len: usize,
+    case_insensitive: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    children: HashMap<char, usize>,
+    /// Smallest alternation index of a literal ending at this node.
+    terminal: Option<usize>,
+}
+
+impl LiteralSet {
+    /// Build a matcher from literals in alternation order.
+    ///
+    /// Returns `None` if any literal is empty, or if `case_insensitive` is set and a literal is
+    /// not ASCII (the only case where folding can be reproduced exactly without Unicode tables).
+    pub fn new<I, S>(literals: I, case_insensitive: bool) -> Option<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut nodes = vec![TrieNode::default()];
+        let mut len = 0usize;
+        for (idx, literal) in literals.into_iter().enumerate() {
+            let literal = literal.as_ref();
+            if literal.is_empty() || (case_insensitive && !literal.is_ascii()) {
+                return None;
+            }
+            let mut node = 0usize;
+            for ch in literal.chars() {
+                let key = fold_char(ch, case_insensitive);
+                node = match nodes[node].children.get(&key) {
+                    Some(&next) => next,
+                    None => {
+                        let next = nodes.len();
+                        nodes.push(TrieNode::default());
+                        nodes[node].children.insert(key, next);
+                        next
+                    }
+                };
+            }
+            nodes[node].terminal.get_or_insert(idx);
+            len += 1;
+        }
+        if len == 0 {
+            return None;
+        }
+        Some(Self {
+            nodes,
+            len,
+            case_insensitive,
+        })
+    }
+
+    /// Number of literals in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set contains no literals.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Find the first match starting at or after `start` (byte offsets).
+    pub fn find_at(&self, text: &str, start: usize) -> Option<Range<usize>> {
+        let tail = text.get(start..)?;
+        for (rel, _) in tail.char_indices() {
+            let at = start + rel;
+            if let Some(len) = self.match_len_at(&text[at..]) {
+                return Some(at..at + len);
+            }
+        }
+        None
+    }
+
+    /// Length (bytes) of the highest-priority literal matching at the start of `text`.
+    fn match_len_at(&self, text: &str) -> Option<usize> {
+        let mut node = 0usize;
+        let mut best: Option<(usize, usize)> = None; // (literal index, byte len)
+        for (byte, ch) in text.char_indices() {
+            let Some(&next) = self.nodes[node]
+                .children
+                .get(&fold_char(ch, self.case_insensitive))
+            else {
+                break;
+            };
+            node = next;
+            if let Some(idx) = self.nodes[node].terminal
+                && best.is_none_or(|(best_idx, _)| idx < best_idx)
+            {
+                best = Some((idx, byte + ch.len_utf8()));
+            }
+        }
+        best.map(|(_, len)| len)
+    }
+}
+
+fn fold_char(ch: char, case_insensitive: bool) -> char {
+    if !case_insensitive {
+        return ch;
+    }
+    match ch {
+        // Non-ASCII characters whose simple case folding maps onto ASCII letters.
+        '\u{212A}' => 'k',
+        '\u{017F}' => 's',
+        _ => ch.to_ascii_lowercase(),
+    }
+}
+
+/// Compile `pattern` as a regex, mapping size-limit failures to [`PatternError::TooComplex`].
+pub fn compile_regex(pattern: &str, options: PatternOptions) -> Result<Regex, PatternError> {
+    build_regex(pattern, options, options.limits.size_limit).map_err(|err| match err {
+        regex::Error::CompiledTooBig(limit) => too_complex(pattern, options, limit),
+        other => PatternError::Invalid(other),
+    })
+}
+
+fn build_regex(
+    pattern: &str,
+    options: PatternOptions,
+    size_limit: usize,
+) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(options.case_insensitive)
+        .multi_line(options.multi_line)
+        .size_limit(size_limit)
+        .dfa_size_limit(options.limits.dfa_size_limit)
+        .build()
+}
+
+/// Measure how much a pattern that exceeded `limit` needs, by compiling it again with doubling
+/// limits, at most [`ESTIMATE_DOUBLINGS`] times.
+fn too_complex(pattern: &str, options: PatternOptions, limit: usize) -> PatternError {
+    let mut estimate = limit;
+    for _ in 0..ESTIMATE_DOUBLINGS {
+        let next = estimate.saturating_mul(2);
+        if !matches!(
+            build_regex(pattern, options, next),
+            Err(regex::Error::CompiledTooBig(_))
+        ) {
+            break;
+        }
+        estimate = next;
+    }
+    PatternError::TooComplex { limit, estimate }
+}
+
+/// Compile `pattern`, falling back to a [`LiteralSet`] when the regex is too large but the
+/// pattern is a pure alternation of literals.
+pub fn compile_pattern(
+    pattern: &str,
+    options: PatternOptions,
+) -> Result<CompiledPattern, PatternError> {
+    match build_regex(pattern, options, options.limits.size_limit) {
+        Ok(re) => Ok(CompiledPattern::Regex(re)),
+        // Try the fallback before measuring the regex: that takes further compilations.
+        Err(regex::Error::CompiledTooBig(limit)) => parse_literal_alternation(pattern)
+            .and_then(|literals| LiteralSet::new(literals, options.case_insensitive))
+            .map(CompiledPattern::Literals)
+            .ok_or_else(|| too_complex(pattern, options, limit)),
+        Err(err) => Err(PatternError::Invalid(err)),
+    }
+}
+
+/// Parse a pattern of the form `a|b|c` (optionally wrapped in `(?:...)`) into its literals.
+///
+/// Returns `None` if any alternative contains regex syntax other than escaped punctuation.
+pub fn parse_literal_alternation(pattern: &str) -> Option<Vec<String>> {
+    let inner = pattern
+        .strip_prefix("(?:")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or(pattern);
+
+    let mut literals = Vec::new();
+    let mut current = String::new();
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                let escaped = chars.next()?;
+                if !escaped.is_ascii_punctuation() {
+                    return None;
+                }
+                current.push(escaped);
+            }
+            '|' => {
+                if current.is_empty() {
+                    return None;
+                }
+                literals.push(std::mem::take(&mut current));
+            }
+            '.' | '+' | '*' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '^' | '$' => return None,
+            _ => current.push(ch),
+        }
+    }
+    if current.is_empty() {
+        return None;
+    }
+    literals.push(current);
+    Some(literals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_literal_alternation() {
+        assert_eq!(
+            parse_literal_alternation("foo|bar\\.baz"),
+            Some(vec!["foo".to_string(), "bar.baz".to_string()])
+        );
+        assert_eq!(
+            parse_literal_alternation("(?:a|b)"),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(parse_literal_alternation("a|b+"), None);
+        assert_eq!(parse_literal_alternation("a||b"), None);
+        assert_eq!(parse_literal_alternation("\\w|b"), None);
+    }
+
+    #[test]
+    fn test_literal_set_is_leftmost_first() {
+        let set = LiteralSet::new(["ab", "abc", "b"], false).unwrap();
+        let text = "xabcb";
+        let re = Regex::new("ab|abc|b").unwrap();
+
+        let expected: Vec<Range<usize>> = re.find_iter(text).map(|m| m.range()).collect();
+        let pattern = CompiledPattern::Literals(set);
+        let actual: Vec<Range<usize>> = pattern.find_iter(text).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_literal_set_case_insensitive_matches_regex_folding() {
+        let set = LiteralSet::new(["kiss"], true).unwrap();
+        let text = "KISS \u{212A}i\u{017F}s kiSs";
+        let re = RegexBuilder::new("kiss")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+
+        let expected: Vec<Range<usize>> = re.find_iter(text).map(|m| m.range()).collect();
+        let pattern = CompiledPattern::Literals(set);
+        let actual: Vec<Range<usize>> = pattern.find_iter(text).collect();
+        assert_eq!(actual, expected);
+        assert!(LiteralSet::new(["ä"], true).is_none());
+    }
+}
@@ -7,12 +7,19 @@
 //! External crates (`editor-core-*`) can produce [`ProcessingEdit`] values and apply them to an
 //! [`EditorStateManager`] via
 //! [`EditorStateManager::apply_processing_edits`](crate::EditorStateManager::apply_processing_edits).
+//!
+//! Hosts running several processors can register them in the manager's [`ProcessorPipeline`]
+//! instead, which orders them, skips the ones whose inputs did not change, and keeps two
+//! processors from writing the same style layer.
 
 use crate::EditorStateManager;
-use crate::decorations::{Decoration, DecorationLayerId};
-use crate::diagnostics::Diagnostic;
+use crate::decorations::{Decoration, DecorationLayerId, DecorationRange};
+use crate::delta::{TextDelta, TextDeltaEdit};
+use crate::diagnostics::{Diagnostic, DiagnosticRange};
 use crate::intervals::{FoldRegion, Interval, StyleLayerId};
 use crate::symbols::DocumentOutline;
+use std::fmt::Display;
+use std::ops::Range;
 
 /// A change to derived editor state (highlighting, folding, etc.).
 #[derive(Debug, Clone)]
@@ -24,6 +31,20 @@ pub enum ProcessingEdit {
         /// The full set of style intervals for the layer (char offsets, half-open).
         intervals: Vec<Interval>,
     },
+    /// Replace the intervals of a style layer within a range of the document, keeping the rest.
+    ///
+    /// Every existing interval overlapping `start..end` is removed (as a whole, including its
+    /// parts outside the range) before `intervals` are added.
+    ReplaceStyleLayerRange {
+        /// The style layer being updated.
+        layer: StyleLayerId,
+        /// Start of the replaced range (char offset).
+        start: usize,
+        /// End of the replaced range (char offset, exclusive).
+        end: usize,
+        /// The new style intervals for the range (char offsets, half-open).
+        intervals: Vec<Interval>,
+    },
     /// Clear a style layer.
     ClearStyleLayer {
         /// The style layer being cleared.
@@ -34,7 +55,8 @@ pub enum ProcessingEdit {
     /// This affects the **derived** fold set (from external providers), leaving user-created folds intact.
     ///
     /// If `preserve_collapsed` is true, regions that match an existing collapsed region
-    /// (`start_line`, `end_line`) will remain collapsed after replacement.
+    /// (`start_line`, `end_line`, or the content of those lines when they shifted) will remain
+    /// collapsed after replacement.
     ReplaceFoldingRegions {
         /// The complete set of folding regions.
         regions: Vec<FoldRegion>,
@@ -50,6 +72,21 @@ pub enum ProcessingEdit {
     },
     /// Clear all diagnostics.
     ClearDiagnostics,
+    /// Replace the diagnostics of one provider (e.g. one of several language servers attached
+    /// to the document), keeping the other providers' diagnostics.
+    ///
+    /// The new diagnostics get [`Diagnostic::provider`] set to `provider`.
+    ReplaceProviderDiagnostics {
+        /// Provider name.
+        provider: String,
+        /// Full diagnostic list of the provider.
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// Clear the diagnostics of one provider.
+    ClearProviderDiagnostics {
+        /// Provider name.
+        provider: String,
+    },
     /// Replace a decoration layer wholesale.
     ReplaceDecorations {
         /// Decoration layer being replaced.
@@ -82,3 +119,507 @@ pub trait DocumentProcessor {
     /// apply (e.g. via [`EditorStateManager::apply_processing_edits`](crate::EditorStateManager::apply_processing_edits)).
     fn process(&mut self, state: &EditorStateManager) -> Result<Vec<ProcessingEdit>, Self::Error>;
 }
+
+/// [`ProcessingEdit`]s computed for a given version of the document text, e.g. on a worker
+/// thread.
+///
+/// Apply it with
+/// [`EditorStateManager::apply_processing_batch`](crate::EditorStateManager::apply_processing_batch),
+/// which checks that the text has not changed since (or adjusts the edits if it has).
+#[derive(Debug, Clone)]
+pub struct ProcessingEditBatch {
+    /// The [`DocumentState::text_version`](crate::DocumentState::text_version) the edits were
+    /// computed for.
+    pub document_version: u64,
+    /// The edits.
+    pub edits: Vec<ProcessingEdit>,
+}
+
+impl ProcessingEditBatch {
+    /// Create a batch of edits computed for `document_version`.
+    pub fn new(document_version: u64, edits: Vec<ProcessingEdit>) -> Self {
+        Self {
+            document_version,
+            edits,
+        }
+    }
+
+    /// Map the batch's character offsets through `deltas` (the text changes made since
+    /// `document_version`, oldest first).
+    ///
+    /// Ranges keep covering the same text: they move with edits before them and shrink when
+    /// part of their text is deleted; ranges whose text is gone entirely are dropped. Edits that
+    /// are not expressed in character offsets (folding regions, which are line-based, and
+    /// document symbols, which also carry UTF-16 positions) are dropped as well.
+    pub(crate) fn shift(self, deltas: &[TextDelta]) -> Vec<ProcessingEdit> {
+        let edits: Vec<&TextDeltaEdit> = deltas.iter().flat_map(|delta| &delta.edits).collect();
+        let map = |start: usize, end: usize| map_range(&edits, start, end);
+        let intervals = |intervals: Vec<Interval>| -> Vec<Interval> {
+            intervals
+                .into_iter()
+                .filter_map(|interval| {
+                    let range = map(interval.start, interval.end)?;
+                    Some(Interval::new(range.start, range.end, interval.style_id))
+                })
+                .collect()
+        };
+
+        self.edits
+            .into_iter()
+            .filter_map(|edit| {
+                Some(match edit {
+                    ProcessingEdit::ReplaceStyleLayer {
+                        layer,
+                        intervals: list,
+                    } => ProcessingEdit::ReplaceStyleLayer {
+                        layer,
+                        intervals: intervals(list),
+                    },
+                    ProcessingEdit::ReplaceStyleLayerRange {
+                        layer,
+                        start,
+                        end,
+                        intervals: list,
+                    } => {
+                        // The replaced range may collapse to an insertion point but still applies.
+                        let start = map_offset(&edits, start, false);
+                        let end = map_offset(&edits, end, true).max(start);
+                        ProcessingEdit::ReplaceStyleLayerRange {
+                            layer,
+                            start,
+                            end,
+                            intervals: intervals(list),
+                        }
+                    }
+                    ProcessingEdit::ReplaceDiagnostics { diagnostics } => {
+                        ProcessingEdit::ReplaceDiagnostics {
+                            diagnostics: shift_diagnostics(diagnostics, map),
+                        }
+                    }
+                    ProcessingEdit::ReplaceProviderDiagnostics {
+                        provider,
+                        diagnostics,
+                    } => ProcessingEdit::ReplaceProviderDiagnostics {
+                        provider,
+                        diagnostics: shift_diagnostics(diagnostics, map),
+                    },
+                    ProcessingEdit::ReplaceDecorations { layer, decorations } => {
+                        ProcessingEdit::ReplaceDecorations {
+                            layer,
+                            decorations: decorations
+                                .into_iter()
+                                .filter_map(|mut decoration| {
+                                    let range = map(decoration.range.start, decoration.range.end)?;
+                                    decoration.range = DecorationRange::new(range.start, range.end);
+                                    Some(decoration)
+                                })
+                                .collect(),
+                        }
+                    }
+                    ProcessingEdit::ReplaceFoldingRegions { .. }
+                    | ProcessingEdit::ReplaceDocumentSymbols { .. } => return None,
+                    edit @ (ProcessingEdit::ClearStyleLayer { .. }
+                    | ProcessingEdit::ClearFoldingRegions
+                    | ProcessingEdit::ClearDiagnostics
+                    | ProcessingEdit::ClearProviderDiagnostics { .. }
+                    | ProcessingEdit::ClearDecorations { .. }
+                    | ProcessingEdit::ClearDocumentSymbols) => edit,
+                })
+            })
+            .collect()
+    }
+}
+
+fn shift_diagnostics(
+    diagnostics: Vec<Diagnostic>,
+    map: impl Fn(usize, usize) -> Option<Range<usize>>,
+) -> Vec<Diagnostic> {
+    diagnostics
+        .into_iter()
+        .filter_map(|mut diagnostic| {
+            let range = map(diagnostic.range.start, diagnostic.range.end)?;
+            diagnostic.range = DiagnosticRange::new(range.start, range.end);
+            Some(diagnostic)
+        })
+        .collect()
+}
+
+/// Map a character offset through sequential `edits`.
+///
+/// Offsets inside deleted text move to the edge of the replacement that keeps them within
+/// the surviving text: after it for a range start (`is_end == false`), before it for a range
+/// end. Text inserted exactly at an offset stays outside the range: a start moves past it, an
+/// end stays before it.
+fn map_offset(edits: &[&TextDeltaEdit], offset: usize, is_end: bool) -> usize {
+    edits.iter().fold(offset, |offset, edit| {
+        let deleted_end = edit.end();
+        let shifted_past = if is_end {
+            offset >= deleted_end && offset > edit.start
+        } else {
+            offset >= deleted_end
+        };
+        if shifted_past {
+            offset - edit.deleted_len() + edit.inserted_len()
+        } else if offset <= edit.start {
+            offset
+        } else if is_end {
+            edit.start
+        } else {
+            edit.start + edit.inserted_len()
+        }
+    })
+}
+
+/// Map a non-empty range through `edits`; `None` if none of its text is left.
+fn map_range(edits: &[&TextDeltaEdit], start: usize, end: usize) -> Option<Range<usize>> {
+    let start = map_offset(edits, start, false);
+    let end = map_offset(edits, end, true);
+    (start < end).then_some(start..end)
+}
+
+/// How [`EditorStateManager::apply_processing_batch`](crate::EditorStateManager::apply_processing_batch)
+/// treats a batch computed for an older version of the text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StaleBatchPolicy {
+    /// Reject the batch; the host recomputes it for the current text.
+    #[default]
+    Drop,
+    /// Map the batch's ranges through the text changes made since (see
+    /// [`EditorStateManager::delta_journal_since`](crate::EditorStateManager::delta_journal_since))
+    /// and apply it. Batches older than the delta journal are still rejected.
+    Shift,
+}
+
+/// Error returned by
+/// [`EditorStateManager::apply_processing_batch`](crate::EditorStateManager::apply_processing_batch)
+/// for a batch that does not match the current text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleBatchError {
+    /// The batch's [`ProcessingEditBatch::document_version`].
+    pub batch_version: u64,
+    /// The current [`DocumentState::text_version`](crate::DocumentState::text_version).
+    pub current_version: u64,
+}
+
+impl Display for StaleBatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Processing batch for text version {} does not apply to version {}",
+            self.batch_version, self.current_version
+        )
+    }
+}
+
+impl std::error::Error for StaleBatchError {}
+
+/// What the output of a processor in a [`ProcessorPipeline`] depends on.
+///
+/// [`ProcessorPipeline::run`] skips a processor when its input is the same as on its last
+/// successful run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProcessorInput {
+    /// The document text ([`DocumentState::text_version`](crate::DocumentState::text_version)).
+    #[default]
+    Document,
+    /// The document text and the visible rows
+    /// ([`ViewportState::visible_lines`](crate::ViewportState::visible_lines)), for processors
+    /// that only cover the viewport.
+    DocumentAndViewport,
+    /// Nothing the pipeline can see: run every time (e.g. to poll a language server).
+    Always,
+}
+
+/// How a processor is registered in a [`ProcessorPipeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessorSpec {
+    /// Unique name, used in [`ProcessorFailure`]s and by [`ProcessorPipeline::remove`].
+    pub name: String,
+    /// Style layers the processor writes. Style-layer edits to other layers are dropped (and
+    /// reported as a [`ProcessorFailure`]); other edits (folding, diagnostics, ...) are not checked.
+    pub output_layers: Vec<StyleLayerId>,
+    /// Processors run in ascending priority, in registration order among equal priorities.
+    pub priority: i32,
+    /// Whether the processor may share its output layers with other additive processors
+    /// (e.g. several processors each updating ranges of one layer).
+    pub additive: bool,
+    /// What the processor's output depends on.
+    pub input: ProcessorInput,
+}
+
+impl ProcessorSpec {
+    /// A spec with no output layers, priority `0` and [`ProcessorInput::Document`].
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            output_layers: Vec::new(),
+            priority: 0,
+            additive: false,
+            input: ProcessorInput::default(),
+        }
+    }
+
+    /// Set the style layers the processor writes.
+    pub fn with_layers(mut self, layers: impl IntoIterator<Item = StyleLayerId>) -> Self {
+        self.output_layers = layers.into_iter().collect();
+        self
+    }
+
+    /// Set the run order priority.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set what the processor's output depends on.
+    pub fn with_input(mut self, input: ProcessorInput) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// Mark the processor's output layers as shareable with other additive processors.
+    pub fn additive(mut self) -> Self {
+        self.additive = true;
+        self
+    }
+}
+
+/// Errors returned by [`ProcessorPipeline::register`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineError {
+    /// A processor with this name is already registered.
+    DuplicateName(String),
+    /// A processor claims a style layer that another processor already writes, and the two
+    /// are not both additive.
+    LayerConflict {
+        /// The contested layer.
+        layer: StyleLayerId,
+        /// Name of the processor being registered.
+        processor: String,
+        /// Name of the registered processor writing `layer`.
+        owner: String,
+    },
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateName(name) => write!(f, "Processor `{}` is already registered", name),
+            Self::LayerConflict {
+                layer,
+                processor,
+                owner,
+            } => write!(
+                f,
+                "Processor `{}` claims style layer {} owned by `{}`",
+                processor, layer.0, owner
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+/// A processor error collected by [`ProcessorPipeline::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessorFailure {
+    /// Name of the failed processor.
+    pub name: String,
+    /// The processor's error message.
+    pub message: String,
+}
+
+/// A [`DocumentProcessor`] with its error type erased to a message.
+trait PipelineProcessor {
+    fn process(&mut self, state: &EditorStateManager) -> Result<Vec<ProcessingEdit>, String>;
+}
+
+impl<P> PipelineProcessor for P
+where
+    P: DocumentProcessor,
+    P::Error: Display,
+{
+    fn process(&mut self, state: &EditorStateManager) -> Result<Vec<ProcessingEdit>, String> {
+        DocumentProcessor::process(self, state).map_err(|err| err.to_string())
+    }
+}
+
+/// Input seen by a processor's last successful run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InputKey {
+    text_version: u64,
+    visible_lines: Option<Range<usize>>,
+}
+
+struct PipelineEntry {
+    spec: ProcessorSpec,
+    processor: Box<dyn PipelineProcessor>,
+    last_input: Option<InputKey>,
+}
+
+/// An ordered set of [`DocumentProcessor`]s with declared output layers.
+///
+/// Every [`EditorStateManager`] owns one (see
+/// [`EditorStateManager::pipeline_mut`](crate::EditorStateManager::pipeline_mut)), run with
+/// [`EditorStateManager::run_pipeline`](crate::EditorStateManager::run_pipeline).
+#[derive(Default)]
+pub struct ProcessorPipeline {
+    entries: Vec<PipelineEntry>,
+}
+
+impl std::fmt::Debug for ProcessorPipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.entries.iter().map(|entry| &entry.spec))
+            .finish()
+    }
+}
+
+impl ProcessorPipeline {
+    /// Create an empty pipeline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `processor`, ordered by `spec.priority`.
+    ///
+    /// Fails if the name is taken, or if one of `spec.output_layers` is written by a registered
+    /// processor and the two are not both [`ProcessorSpec::additive`].
+    pub fn register<P>(&mut self, spec: ProcessorSpec, processor: P) -> Result<(), PipelineError>
+    where
+        P: DocumentProcessor + 'static,
+        P::Error: Display,
+    {
+        if self.contains(&spec.name) {
+            return Err(PipelineError::DuplicateName(spec.name));
+        }
+        for entry in &self.entries {
+            let shared = spec
+                .output_layers
+                .iter()
+                .find(|layer| entry.spec.output_layers.contains(layer));
+            if let Some(&layer) = shared
+                && !(spec.additive && entry.spec.additive)
+            {
+                return Err(PipelineError::LayerConflict {
+                    layer,
+                    processor: spec.name,
+                    owner: entry.spec.name.clone(),
+                });
+            }
+        }
+
+        let index = self
+            .entries
+            .partition_point(|entry| entry.spec.priority <= spec.priority);
+        self.entries.insert(
+            index,
+            PipelineEntry {
+                spec,
+                processor: Box::new(processor),
+                last_input: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Remove the processor named `name`; returns whether it was registered.
+    ///
+    /// Its output stays in the editor; clear its layers if needed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.spec.name != name);
+        self.entries.len() != before
+    }
+
+    /// Whether a processor named `name` is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.spec.name == name)
+    }
+
+    /// Specs of the registered processors, in run order.
+    pub fn specs(&self) -> impl Iterator<Item = &ProcessorSpec> {
+        self.entries.iter().map(|entry| &entry.spec)
+    }
+
+    /// Number of registered processors.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no processor is registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Make every processor run on the next [`Self::run`], even if its input is unchanged
+    /// (e.g. after a theme or configuration change).
+    pub fn invalidate(&mut self) {
+        for entry in &mut self.entries {
+            entry.last_input = None;
+        }
+    }
+
+    /// Run the processors in order against `state`, applying each one's edits before the next
+    /// one runs.
+    ///
+    /// Processors whose [`ProcessorInput`] did not change since their last successful run are
+    /// skipped. A failing processor does not stop the others: its error is collected (and it
+    /// runs again next time).
+    pub fn run(&mut self, state: &mut EditorStateManager) -> Vec<ProcessorFailure> {
+        let mut failures = Vec::new();
+        for entry in &mut self.entries {
+            let input = match entry.spec.input {
+                ProcessorInput::Document => Some(InputKey {
+                    text_version: state.get_document_state().text_version,
+                    visible_lines: None,
+                }),
+                ProcessorInput::DocumentAndViewport => Some(InputKey {
+                    text_version: state.get_document_state().text_version,
+                    visible_lines: Some(state.get_viewport_state().visible_lines),
+                }),
+                ProcessorInput::Always => None,
+            };
+            if input.is_some() && input == entry.last_input {
+                continue;
+            }
+
+            let mut edits = match entry.processor.process(state) {
+                Ok(edits) => edits,
+                Err(message) => {
+                    failures.push(ProcessorFailure {
+                        name: entry.spec.name.clone(),
+                        message,
+                    });
+                    continue;
+                }
+            };
+            let declared = &entry.spec.output_layers;
+            let undeclared = edits
+                .iter()
+                .filter_map(edit_style_layer)
+                .find(|layer| !declared.contains(layer));
+            if let Some(layer) = undeclared {
+                edits.retain(|edit| edit_style_layer(edit).is_none_or(|l| declared.contains(&l)));
+                failures.push(ProcessorFailure {
+                    name: entry.spec.name.clone(),
+                    message: format!("wrote undeclared style layer {}", layer.0),
+                });
+            }
+
+            state.apply_processing_edits(edits);
+            entry.last_input = input;
+        }
+        failures
+    }
+}
+
+/// The style layer a processing edit writes, if any.
+fn edit_style_layer(edit: &ProcessingEdit) -> Option<StyleLayerId> {
+    match edit {
+        ProcessingEdit::ReplaceStyleLayer { layer, .. }
+        | ProcessingEdit::ReplaceStyleLayerRange { layer, .. }
+        | ProcessingEdit::ClearStyleLayer { layer } => Some(*layer),
+        _ => None,
+    }
+}
@@ -4,20 +4,39 @@
 //! (not byte offsets) for all public inputs/outputs. It supports:
 //!
 //! - plain substring search (escaped and compiled into a regex)
-//! - regex search
+//! - regex search, over the whole text (so patterns may span line breaks)
 //! - optional whole-word matching
+//!
+//! Patterns are compiled via [`crate::pattern`], so oversized patterns report
+//! [`SearchError::TooComplex`] (or fall back to a literal-set matcher for pure alternations).
+//!
+//! For large documents, [`find_all_iter`] and [`find_all_limited`] search a [`LineIndex`]
+//! lazily, line by line, so hosts can cap the number of matches or stop after a time budget.
 
-use regex::{Regex, RegexBuilder};
+use crate::LineIndex;
+use crate::pattern::{CompiledPattern, PatternError, PatternLimits, PatternOptions};
+use regex_syntax::hir::{Class, Hir, HirKind, Look};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
 
 /// Options that control how search is performed.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SearchOptions {
     /// If `true`, performs a case-sensitive search.
     pub case_sensitive: bool,
-    /// If `true`, matches only whole words (ASCII-alphanumeric and `_`).
+    /// If `true`, matches only whole words: a match must start and end on a Unicode word boundary
+    /// (UAX #29, like the cursor word commands). `foo` does not match in `foobar` or `foo_bar`,
+    /// and each CJK ideograph is a word of its own; note that UAX #29 also keeps `foo.bar` and
+    /// `don't` together as single words.
     pub whole_word: bool,
     /// If `true`, treats the query as a regex pattern.
     pub regex: bool,
+    /// If `true`, `.` in a regex query also matches `\n` (like a leading `(?s)`), so patterns
+    /// such as `begin.*end` can span lines. Ignored for plain-text queries.
+    pub dot_matches_newline: bool,
+    /// Size limits used when compiling the query.
+    pub limits: PatternLimits,
 }
 
 impl Default for SearchOptions {
@@ -26,6 +45,8 @@ impl Default for SearchOptions {
             case_sensitive: true,
             whole_word: false,
             regex: false,
+            dot_matches_newline: false,
+            limits: PatternLimits::default(),
         }
     }
 }
@@ -56,12 +77,37 @@ impl SearchMatch {
 pub enum SearchError {
     /// The provided regex pattern failed to compile.
     InvalidRegex(regex::Error),
+    /// The compiled pattern would exceed the configured size limit.
+    TooComplex {
+        /// The size limit that was exceeded (bytes).
+        limit: usize,
+        /// Lower bound on the compiled size (bytes); see [`PatternError::TooComplex`].
+        estimate: usize,
+    },
+    /// The pattern exceeded a regex engine limit; see [`PatternError::EngineLimit`].
+    EngineLimit(String),
 }
 
 impl std::fmt::Display for SearchError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::InvalidRegex(err) => write!(f, "Invalid regex: {}", err),
+            Self::TooComplex { limit, estimate } => PatternError::TooComplex {
+                limit: *limit,
+                estimate: *estimate,
+            }
+            .fmt(f),
+            Self::EngineLimit(message) => PatternError::EngineLimit(message.clone()).fmt(f),
+        }
+    }
+}
+
+impl From<PatternError> for SearchError {
+    fn from(value: PatternError) -> Self {
+        match value {
+            PatternError::Invalid(err) => Self::InvalidRegex(err),
+            PatternError::TooComplex { limit, estimate } => Self::TooComplex { limit, estimate },
+            PatternError::EngineLimit(message) => Self::EngineLimit(message),
         }
     }
 }
@@ -103,48 +149,108 @@ impl CharIndex {
             Err(idx) => idx,
         }
     }
-
-    pub(crate) fn char_at(&self, text: &str, char_offset: usize) -> Option<char> {
-        if char_offset >= self.char_count() {
-            return None;
-        }
-        let start = self.char_to_byte[char_offset];
-        let end = self.char_to_byte[char_offset + 1];
-        text.get(start..end)?.chars().next()
-    }
 }
 
-fn compile_search_regex(query: &str, options: SearchOptions) -> Result<Regex, SearchError> {
-    let pattern = if options.regex {
+/// The regex pattern and compile options that `query` is searched with.
+///
+/// Shared with the replace commands, so a regex replacement expands the same matches that search
+/// found.
+pub(crate) fn search_pattern(query: &str, options: SearchOptions) -> (String, PatternOptions) {
+    let pattern = if options.regex && options.dot_matches_newline {
+        format!("(?s){query}")
+    } else if options.regex {
         query.to_string()
     } else {
         regex::escape(query)
     };
 
-    RegexBuilder::new(&pattern)
+    (
+        pattern,
+        PatternOptions {
+            case_insensitive: !options.case_sensitive,
+            multi_line: true,
+            limits: options.limits,
+        },
+    )
+}
+
+fn compile_search_regex(
+    query: &str,
+    options: SearchOptions,
+) -> Result<CompiledPattern, SearchError> {
+    let (pattern, pattern_options) = search_pattern(query, options);
+    Ok(crate::pattern::compile_pattern(&pattern, pattern_options)?)
+}
+
+/// Returns `true` if matches of `query` may depend on text outside a single `\n`-terminated line
+/// (the pattern can match a newline, or anchors to the start/end of the whole text).
+pub(crate) fn spans_lines(query: &str, options: SearchOptions) -> bool {
+    if !options.regex {
+        return query.contains('\n');
+    }
+
+    let hir = regex_syntax::ParserBuilder::new()
         .case_insensitive(!options.case_sensitive)
         .multi_line(true)
+        .dot_matches_new_line(options.dot_matches_newline)
         .build()
-        .map_err(SearchError::InvalidRegex)
+        .parse(query);
+    // If the pattern doesn't parse here it won't have compiled either; stay conservative.
+    hir.map_or(true, |hir| hir_spans_lines(&hir))
 }
 
-fn is_word_char(ch: char) -> bool {
-    ch == '_' || ch.is_alphanumeric()
-}
-
-fn is_whole_word(text: &str, index: &CharIndex, m: SearchMatch) -> bool {
-    if m.is_empty() {
-        return false;
+fn hir_spans_lines(hir: &Hir) -> bool {
+    match hir.kind() {
+        HirKind::Empty => false,
+        HirKind::Literal(lit) => lit.0.contains(&b'\n'),
+        HirKind::Class(Class::Unicode(class)) => class
+            .ranges()
+            .iter()
+            .any(|r| r.start() <= '\n' && '\n' <= r.end()),
+        HirKind::Class(Class::Bytes(class)) => class
+            .ranges()
+            .iter()
+            .any(|r| r.start() <= b'\n' && b'\n' <= r.end()),
+        HirKind::Look(look) => matches!(look, Look::Start | Look::End),
+        HirKind::Repetition(rep) => hir_spans_lines(&rep.sub),
+        HirKind::Capture(cap) => hir_spans_lines(&cap.sub),
+        HirKind::Concat(subs) | HirKind::Alternation(subs) => subs.iter().any(hir_spans_lines),
     }
+}
 
-    let before = if m.start == 0 {
-        None
-    } else {
-        index.char_at(text, m.start.saturating_sub(1))
-    };
-    let after = index.char_at(text, m.end);
+/// Word boundaries (UAX #29) of the `\n`-delimited lines around the last checked match, reused
+/// while the following matches stay on those lines.
+///
+/// `\n` is always a word boundary, so boundaries within a line do not depend on other lines.
+#[derive(Debug, Default)]
+struct WordBoundaries {
+    /// Byte range of the cached lines (without their delimiting `\n`s).
+    lines: Range<usize>,
+    /// Boundary byte offsets within `lines`, ascending.
+    bounds: Vec<usize>,
+}
 
-    !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+impl WordBoundaries {
+    /// Whether the byte range `start..end` of `text` is non-empty and starts and ends on word
+    /// boundaries.
+    fn is_whole_word(&mut self, text: &str, start: usize, end: usize) -> bool {
+        if start >= end {
+            return false;
+        }
+        if start < self.lines.start || end > self.lines.end {
+            let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+            self.bounds.clear();
+            self.bounds.extend(
+                text[line_start..line_end]
+                    .split_word_bound_indices()
+                    .map(|(b, _)| line_start + b),
+            );
+            self.bounds.push(line_end);
+            self.lines = line_start..line_end;
+        }
+        self.bounds.binary_search(&start).is_ok() && self.bounds.binary_search(&end).is_ok()
+    }
 }
 
 /// Find the next occurrence of `query` in `text`, searching forward from `from_char`.
@@ -164,6 +270,7 @@ pub fn find_next(
     let re = compile_search_regex(query, options)?;
     let index = CharIndex::new(text);
 
+    let mut words = WordBoundaries::default();
     let mut start_char = from_char.min(index.char_count());
     loop {
         let start_byte = index.char_to_byte(start_char);
@@ -171,8 +278,8 @@ pub fn find_next(
             return Ok(None);
         };
 
-        let start = index.byte_to_char(m.start());
-        let end = index.byte_to_char(m.end());
+        let start = index.byte_to_char(m.start);
+        let end = index.byte_to_char(m.end);
         let candidate = SearchMatch { start, end };
 
         if candidate.is_empty() {
@@ -183,7 +290,7 @@ pub fn find_next(
             continue;
         }
 
-        if options.whole_word && !is_whole_word(text, &index, candidate) {
+        if options.whole_word && !words.is_whole_word(text, m.start, m.end) {
             start_char = candidate.end;
             continue;
         }
@@ -194,6 +301,10 @@ pub fn find_next(
 
 /// Find the previous occurrence of `query` in `text`, searching backward from `from_char`.
 ///
+/// Returns the last match ending at or before `from_char`. Matches are taken from the whole
+/// text, so a match spanning `from_char` is skipped rather than cut short, and anchors or
+/// lookarounds see the text after `from_char`.
+///
 /// - Returns `Ok(None)` if no match is found (or if `query` is empty).
 /// - Match ranges are character offsets and are half-open (`[start, end)`).
 pub fn find_prev(
@@ -212,16 +323,23 @@ pub fn find_prev(
     let limit_char = from_char.min(index.char_count());
     let limit_byte = index.char_to_byte(limit_char);
 
+    let mut words = WordBoundaries::default();
     let mut last: Option<SearchMatch> = None;
-    for m in re.find_iter(&text[..limit_byte]) {
-        let start = index.byte_to_char(m.start());
-        let end = index.byte_to_char(m.end());
+    for m in re.find_iter(text) {
+        if m.end > limit_byte {
+            if m.start >= limit_byte {
+                break;
+            }
+            continue;
+        }
+        let start = index.byte_to_char(m.start);
+        let end = index.byte_to_char(m.end);
         let candidate = SearchMatch { start, end };
 
         if candidate.is_empty() {
             continue;
         }
-        if options.whole_word && !is_whole_word(text, &index, candidate) {
+        if options.whole_word && !words.is_whole_word(text, m.start, m.end) {
             continue;
         }
 
@@ -247,16 +365,17 @@ pub fn find_all(
     let re = compile_search_regex(query, options)?;
     let index = CharIndex::new(text);
 
+    let mut words = WordBoundaries::default();
     let mut matches: Vec<SearchMatch> = Vec::new();
     for m in re.find_iter(text) {
-        let start = index.byte_to_char(m.start());
-        let end = index.byte_to_char(m.end());
+        let start = index.byte_to_char(m.start);
+        let end = index.byte_to_char(m.end);
         let candidate = SearchMatch { start, end };
 
         if candidate.is_empty() {
             continue;
         }
-        if options.whole_word && !is_whole_word(text, &index, candidate) {
+        if options.whole_word && !words.is_whole_word(text, m.start, m.end) {
             continue;
         }
 
@@ -266,6 +385,327 @@ pub fn find_all(
     Ok(matches)
 }
 
+/// Find all occurrences of `query` that lie entirely within `range` (char offsets, half-open).
+///
+/// Matching stops at `range.end` (so a pattern never extends past the scope), while text before
+/// `range.start` still serves as context for anchors. Whole-word checks look at the characters
+/// just outside the range, so a word straddling a boundary is not matched.
+pub fn find_all_in_range(
+    text: &str,
+    query: &str,
+    options: SearchOptions,
+    range: SearchMatch,
+) -> Result<Vec<SearchMatch>, SearchError> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let re = compile_search_regex(query, options)?;
+    let index = CharIndex::new(text);
+
+    let mut words = WordBoundaries::default();
+    let mut matches = Vec::new();
+    let mut from = range.start;
+    while let Some(m) = next_match_in_range(&re, text, &index, &mut words, options, range, from) {
+        from = m.end;
+        matches.push(m);
+    }
+    Ok(matches)
+}
+
+/// Find the next occurrence of `query` within `range`, searching forward from `from_char`.
+///
+/// Scope rules are the same as [`find_all_in_range`].
+pub fn find_next_in_range(
+    text: &str,
+    query: &str,
+    options: SearchOptions,
+    range: SearchMatch,
+    from_char: usize,
+) -> Result<Option<SearchMatch>, SearchError> {
+    if query.is_empty() {
+        return Ok(None);
+    }
+
+    let re = compile_search_regex(query, options)?;
+    let index = CharIndex::new(text);
+    let from = from_char.max(range.start);
+    let mut words = WordBoundaries::default();
+    Ok(next_match_in_range(
+        &re, text, &index, &mut words, options, range, from,
+    ))
+}
+
+fn next_match_in_range(
+    re: &CompiledPattern,
+    text: &str,
+    index: &CharIndex,
+    words: &mut WordBoundaries,
+    options: SearchOptions,
+    range: SearchMatch,
+    from_char: usize,
+) -> Option<SearchMatch> {
+    let end_char = range.end.min(index.char_count());
+    let haystack = &text[..index.char_to_byte(end_char)];
+
+    let mut start_char = from_char;
+    while start_char <= end_char {
+        let m = re.find_at(haystack, index.char_to_byte(start_char))?;
+        let candidate = SearchMatch {
+            start: index.byte_to_char(m.start),
+            end: index.byte_to_char(m.end),
+        };
+
+        if candidate.is_empty() {
+            start_char = candidate.end + 1;
+            continue;
+        }
+
+        if options.whole_word && !words.is_whole_word(text, m.start, m.end) {
+            start_char = candidate.end;
+            continue;
+        }
+
+        return Some(candidate);
+    }
+    None
+}
+
+/// Lazily search a document for all occurrences of `query`.
+///
+/// Matches are identical to [`find_all`] over the same text, but the document is searched one
+/// `\n`-terminated line at a time, so callers can stop pulling at any point. Patterns that can
+/// match across lines (or use `\A`/`\z`) are searched over the whole text instead.
+///
+/// - Yields nothing if `query` is empty.
+/// - Match ranges are character offsets and are half-open (`[start, end)`).
+pub fn find_all_iter<'a>(
+    line_index: &'a LineIndex,
+    query: &str,
+    options: SearchOptions,
+) -> Result<FindAllIter<'a>, SearchError> {
+    find_all_iter_in_lines(line_index, query, options, 0..usize::MAX)
+}
+
+/// Like [`find_all_iter`], but only searches `lines` (see [`newline_aligned_lines`]).
+///
+/// Patterns that can match across lines still search the whole document.
+pub(crate) fn find_all_iter_in_lines<'a>(
+    line_index: &'a LineIndex,
+    query: &str,
+    options: SearchOptions,
+    lines: Range<usize>,
+) -> Result<FindAllIter<'a>, SearchError> {
+    let pattern = if query.is_empty() {
+        None
+    } else {
+        Some(compile_search_regex(query, options)?)
+    };
+    let whole_text = pattern.is_some() && spans_lines(query, options);
+    let lines = if whole_text {
+        0..usize::MAX
+    } else {
+        newline_aligned_lines(line_index, lines)
+    };
+
+    Ok(FindAllIter {
+        line_index,
+        line: lines.start,
+        end_line: lines.end,
+        whole_text,
+        pattern,
+        whole_word: options.whole_word,
+        words: WordBoundaries::default(),
+        segment: String::new(),
+        segment_char_start: 0,
+        next_segment_char_start: line_index.rope().line_to_char(lines.start),
+        byte_pos: None,
+        cursor: (0, 0),
+        deadline: None,
+        timed_out: false,
+    })
+}
+
+/// Expand a rope line range so it starts and ends on `\n` boundaries (rope lines may also break
+/// on `\r`, U+2028, etc.), clamped to the document.
+pub(crate) fn newline_aligned_lines(line_index: &LineIndex, lines: Range<usize>) -> Range<usize> {
+    let rope = line_index.rope();
+    let line_count = rope.len_lines();
+    let ends_with_newline = |line: usize| rope.line(line).chars().last() == Some('\n');
+
+    let mut start = lines.start.min(line_count.saturating_sub(1));
+    while start > 0 && !ends_with_newline(start - 1) {
+        start -= 1;
+    }
+    let mut end = lines.end.clamp(start, line_count);
+    while end > start && end < line_count && !ends_with_newline(end - 1) {
+        end += 1;
+    }
+    start..end
+}
+
+/// Iterator returned by [`find_all_iter`].
+pub struct FindAllIter<'a> {
+    line_index: &'a LineIndex,
+    /// Next rope line to load.
+    line: usize,
+    /// Rope line to stop loading segments at (exclusive).
+    end_line: usize,
+    pattern: Option<CompiledPattern>,
+    whole_word: bool,
+    /// Word boundaries within `segment`, for `whole_word`.
+    words: WordBoundaries,
+    whole_text: bool,
+    /// Text currently being searched (one or more lines, ending in `\n` unless last).
+    segment: String,
+    segment_char_start: usize,
+    next_segment_char_start: usize,
+    /// Byte position to resume searching `segment` from; `None` once it is exhausted.
+    byte_pos: Option<usize>,
+    /// `(byte, char)` offsets within `segment` used for incremental offset conversion.
+    cursor: (usize, usize),
+    deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+impl<'a> FindAllIter<'a> {
+    /// Stop yielding matches once `deadline` has passed (checked between lines and matches).
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns `true` if iteration stopped early because the deadline passed.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    fn load_next_segment(&mut self) -> bool {
+        let rope = self.line_index.rope();
+        let line_count = rope.len_lines();
+        if self.line >= line_count.min(self.end_line) {
+            return false;
+        }
+
+        self.segment.clear();
+        if self.whole_text {
+            self.segment = rope.to_string();
+            self.line = line_count;
+        } else {
+            // Rope lines also break on `\r`, U+2028, etc.; group them until a `\n` so segments
+            // match what the pattern sees in the full text.
+            while self.line < line_count {
+                for chunk in rope.line(self.line).chunks() {
+                    self.segment.push_str(chunk);
+                }
+                self.line += 1;
+                if self.segment.ends_with('\n') {
+                    break;
+                }
+            }
+        }
+
+        self.segment_char_start = self.next_segment_char_start;
+        self.next_segment_char_start += self.segment.chars().count();
+        self.byte_pos = Some(0);
+        self.cursor = (0, 0);
+        self.words = WordBoundaries::default();
+        true
+    }
+
+    fn char_offset(&mut self, byte: usize) -> usize {
+        let (cursor_byte, cursor_char) = self.cursor;
+        let char_in_segment = cursor_char + self.segment[cursor_byte..byte].chars().count();
+        self.cursor = (byte, char_in_segment);
+        self.segment_char_start + char_in_segment
+    }
+
+    fn past_deadline(&mut self) -> bool {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.timed_out = true;
+        }
+        self.timed_out
+    }
+}
+
+impl Iterator for FindAllIter<'_> {
+    type Item = SearchMatch;
+
+    fn next(&mut self) -> Option<SearchMatch> {
+        loop {
+            if self.past_deadline() {
+                return None;
+            }
+
+            let Some(byte_pos) = self.byte_pos else {
+                if self.pattern.is_none() || !self.load_next_segment() {
+                    return None;
+                }
+                continue;
+            };
+
+            let pattern = self.pattern.as_ref()?;
+            let Some(m) = pattern.find_at(&self.segment, byte_pos) else {
+                self.byte_pos = None;
+                continue;
+            };
+
+            if m.is_empty() {
+                self.byte_pos = self.segment[m.end..]
+                    .chars()
+                    .next()
+                    .map(|ch| m.end + ch.len_utf8());
+                continue;
+            }
+
+            self.byte_pos = Some(m.end);
+            // Segments are whole `\n`-delimited lines, so their word boundaries match the full
+            // text's.
+            if self.whole_word && !self.words.is_whole_word(&self.segment, m.start, m.end) {
+                continue;
+            }
+
+            let start = self.char_offset(m.start);
+            let end = self.char_offset(m.end);
+            return Some(SearchMatch { start, end });
+        }
+    }
+}
+
+/// Find occurrences of `query` in a document, stopping after `max_matches` matches or once
+/// `deadline` has elapsed.
+///
+/// Returns the matches found and whether the result was truncated (more matches exist, or the
+/// deadline passed before the whole document was searched).
+pub fn find_all_limited(
+    line_index: &LineIndex,
+    query: &str,
+    options: SearchOptions,
+    max_matches: usize,
+    deadline: Option<Duration>,
+) -> Result<(Vec<SearchMatch>, bool), SearchError> {
+    let mut iter = find_all_iter(line_index, query, options)?;
+    if let Some(deadline) = deadline {
+        iter = iter.with_deadline(Instant::now() + deadline);
+    }
+
+    let matches: Vec<SearchMatch> = iter.by_ref().take(max_matches).collect();
+    let truncated = iter.next().is_some() || iter.timed_out();
+    Ok((matches, truncated))
+}
+
+/// A (possibly truncated) count of matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatchCount {
+    /// Number of matches found.
+    pub count: usize,
+    /// `true` if the search stopped early, i.e. the real count is at least `count`.
+    pub truncated: bool,
+}
+
 /// Returns `true` if `range` exactly matches an occurrence of `query` in `text`.
 ///
 /// This is useful for checking whether a current selection/caret range corresponds to the
@@ -1,4 +1,4 @@
-use crate::commands::{Position, Selection, SelectionDirection};
+use crate::commands::{Position, Selection, SelectionDirection, SelectionMergePolicy};
 
 pub fn selection_direction(start: Position, end: Position) -> SelectionDirection {
     if start <= end {
@@ -21,24 +21,11 @@ pub fn selection_contains_position_inclusive(selection: &Selection, pos: Positio
     min_pos <= pos && pos <= max_pos
 }
 
-pub fn normalize_selections(
-    mut selections: Vec<Selection>,
-    primary_index: usize,
-) -> (Vec<Selection>, usize) {
-    if selections.is_empty() {
-        return (selections, 0);
-    }
-
-    let primary_active = selections
-        .get(primary_index)
-        .map(|s| s.end)
-        .unwrap_or(selections[0].end);
-
-    for sel in &mut selections {
-        sel.direction = selection_direction(sel.start, sel.end);
-    }
-
-    selections.sort_by(|a, b| {
+fn merge_sorted_pass(
+    mut tagged: Vec<(Selection, bool)>,
+    policy: SelectionMergePolicy,
+) -> Vec<(Selection, bool)> {
+    tagged.sort_by(|(a, _), (b, _)| {
         let (a_min, a_max) = selection_min_max(a);
         let (b_min, b_max) = selection_min_max(b);
         a_min
@@ -48,44 +35,113 @@ pub fn normalize_selections(
             .then_with(|| a.start.cmp(&b.start))
     });
 
-    // Merge overlapping selections (half-open-ish: do not merge when next starts exactly at current end).
-    let mut merged: Vec<Selection> = Vec::with_capacity(selections.len());
-    for sel in selections {
-        if merged.is_empty() {
-            merged.push(sel);
+    let mut merged: Vec<(Selection, bool)> = Vec::with_capacity(tagged.len());
+    for (sel, is_primary) in tagged {
+        let Some((last, last_is_primary)) = merged.last_mut() else {
+            merged.push((sel, is_primary));
             continue;
-        }
+        };
 
-        let last = merged.last_mut().expect("non-empty");
         let (last_min, last_max) = selection_min_max(last);
         let (sel_min, sel_max) = selection_min_max(&sel);
 
-        if sel_min < last_max {
-            // Merge to union range; canonicalize to Forward.
-            let new_min = last_min.min(sel_min);
-            let new_max = last_max.max(sel_max);
-            *last = Selection {
+        let should_merge = if sel_min < last_max {
+            policy.merge_overlapping
+        } else if sel_min == last_max {
+            if last_min == last_max && sel_min == sel_max {
+                policy.merge_carets_at_same_position
+            } else {
+                policy.merge_touching
+            }
+        } else {
+            false
+        };
+
+        if !should_merge {
+            merged.push((sel, is_primary));
+            continue;
+        }
+
+        // Keep the primary's direction when it takes part in the merge; otherwise keep the
+        // direction of the earliest non-empty selection.
+        let direction =
+            if (is_primary && !*last_is_primary && sel_min != sel_max) || last_min == last_max {
+                sel.direction
+            } else {
+                last.direction
+            };
+        let new_min = last_min.min(sel_min);
+        let new_max = last_max.max(sel_max);
+        *last = match direction {
+            SelectionDirection::Forward => Selection {
                 start: new_min,
                 end: new_max,
-                direction: SelectionDirection::Forward,
-            };
-        } else if sel_min == last_min && sel_max == last_max {
-            // Exact duplicate - drop.
-            continue;
-        } else {
-            merged.push(sel);
+                direction,
+            },
+            SelectionDirection::Backward => Selection {
+                start: new_max,
+                end: new_min,
+                direction,
+            },
+        };
+        *last_is_primary |= is_primary;
+    }
+
+    merged
+}
+
+/// Sort and merge a selection set according to `policy`.
+///
+/// Returns the normalized selections and the index of the selection that contains the input
+/// primary selection (`primary_index` is clamped into range).
+pub fn normalize_selections(
+    selections: Vec<Selection>,
+    primary_index: usize,
+    policy: SelectionMergePolicy,
+) -> (Vec<Selection>, usize) {
+    if selections.is_empty() {
+        return (selections, 0);
+    }
+
+    let primary_index = primary_index.min(selections.len() - 1);
+
+    // Tag each selection with whether it is the primary one, so the primary can be tracked
+    // through sorting and merging.
+    let mut merged: Vec<(Selection, bool)> = selections
+        .into_iter()
+        .enumerate()
+        .map(|(idx, mut sel)| {
+            sel.direction = selection_direction(sel.start, sel.end);
+            (sel, idx == primary_index)
+        })
+        .collect();
+
+    // A merge can turn a caret into a range, which may change how it relates to its neighbours,
+    // so repeat until a pass makes no further merges (keeps normalization idempotent).
+    loop {
+        let before = merged.len();
+        merged = merge_sorted_pass(merged, policy);
+        if merged.len() == before {
+            break;
         }
     }
 
     let new_primary_index = merged
         .iter()
-        .position(|s| selection_contains_position_inclusive(s, primary_active))
-        .unwrap_or_else(|| merged.len().saturating_sub(1));
+        .position(|(_, is_primary)| *is_primary)
+        .unwrap_or(0);
 
-    (merged, new_primary_index)
+    (
+        merged.into_iter().map(|(sel, _)| sel).collect(),
+        new_primary_index,
+    )
 }
 
-pub fn rect_selections(anchor: Position, active: Position) -> (Vec<Selection>, usize) {
+pub fn rect_selections(
+    anchor: Position,
+    active: Position,
+    policy: SelectionMergePolicy,
+) -> (Vec<Selection>, usize) {
     let start_line = anchor.line.min(active.line);
     let end_line = anchor.line.max(active.line);
 
@@ -109,5 +165,5 @@ pub fn rect_selections(anchor: Position, active: Position) -> (Vec<Selection>, u
         .position(|s| s.end.line == primary_active.line)
         .unwrap_or(0);
 
-    normalize_selections(selections, primary_index)
+    normalize_selections(selections, primary_index, policy)
 }
@@ -2,36 +2,99 @@
 //!
 //! Provides data structures needed by UI renderers, simulating "text grid" output.
 
+use crate::diagnostics::DiagnosticSeverity;
 use crate::intervals::StyleId;
 use crate::layout::{
-    DEFAULT_TAB_WIDTH, LayoutEngine, WrapIndent, WrapMode, cell_width_at, visual_x_for_column,
-    wrap_indent_cells_for_line_text,
+    DEFAULT_TAB_WIDTH, LayoutEngine, TextDirection, WrapIndent, WrapMode, grapheme_columns,
+    grapheme_width_at, line_direction, visual_x_for_column, wrap_indent_cells_for_line_text,
 };
+use crate::theme::{Rgb, TextAttributes, Theme};
+use std::collections::HashMap;
+use std::ops::Range;
 
 /// Cell (character) information
+///
+/// A cell holds one grapheme cluster: `ch` is its first `char` and `combining` holds the rest
+/// (combining marks, ZWJ emoji sequence members, variation selectors), so `e` + U+0301 and
+/// 👨‍👩‍👧 each occupy a single cell.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Cell {
-    /// Character content
+    /// Character content (first `char` of the grapheme cluster)
     pub ch: char,
+    /// Remaining `char`s of the grapheme cluster; empty for single-`char` clusters.
+    pub combining: String,
     /// Visual width (1 or 2 cells)
     pub width: usize,
     /// List of applied style IDs
     pub styles: Vec<StyleId>,
+    /// Where the cell comes from, for mapping a rendered cell back to the document.
+    pub source: CellSource,
+}
+
+/// The origin of a [`Cell`] (see [`ComposedCellSource`] for composed grids).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CellSource {
+    /// Not recorded (cells built with [`Cell::new`] and friends, without
+    /// [`Cell::with_source`]).
+    #[default]
+    Unknown,
+    /// A document grapheme cluster.
+    Document {
+        /// Character offset of the cluster's first `char` from the start of the document.
+        offset: usize,
+        /// Logical column (in `char`s) of the cluster's first `char` within its line.
+        column: usize,
+    },
+    /// Wrap-indent padding before the text of a wrapped segment.
+    WrapIndent,
+    /// Fold placeholder text (and the space before it) after a collapsed fold's first line.
+    FoldPlaceholder,
 }
 
 impl Cell {
     /// Create a cell without any styles applied.
     pub fn new(ch: char, width: usize) -> Self {
+        Self::with_styles(ch, width, Vec::new())
+    }
+
+    /// Create a cell with an explicit style list.
+    pub fn with_styles(ch: char, width: usize, styles: Vec<StyleId>) -> Self {
         Self {
             ch,
+            combining: String::new(),
             width,
-            styles: Vec::new(),
+            styles,
+            source: CellSource::Unknown,
         }
     }
 
-    /// Create a cell with an explicit style list.
-    pub fn with_styles(ch: char, width: usize, styles: Vec<StyleId>) -> Self {
-        Self { ch, width, styles }
+    /// Set where the cell comes from.
+    pub fn with_source(mut self, source: CellSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Create a cell for a whole grapheme cluster (see [`Cell::text`]).
+    ///
+    /// Returns `None` when `grapheme` is empty.
+    pub fn from_grapheme(grapheme: &str, width: usize, styles: Vec<StyleId>) -> Option<Self> {
+        let mut chars = grapheme.chars();
+        let ch = chars.next()?;
+        Some(Self {
+            ch,
+            combining: chars.collect(),
+            width,
+            styles,
+            source: CellSource::Unknown,
+        })
+    }
+
+    /// The grapheme cluster shown in this cell (`ch` followed by `combining`).
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.ch.len_utf8() + self.combining.len());
+        text.push(self.ch);
+        text.push_str(&self.combining);
+        text
     }
 }
 
@@ -54,6 +117,22 @@ pub struct HeadlessLine {
     pub segment_x_start_cells: usize,
     /// Whether a fold placeholder was appended to this segment.
     pub is_fold_placeholder_appended: bool,
+    /// Row background style from a [`DecorationKind::LineBackground`](crate::DecorationKind::LineBackground)
+    /// decoration covering this logical line, if any.
+    pub background_style: Option<StyleId>,
+    /// Base direction of the logical line (see [`line_direction`]).
+    ///
+    /// The text cells of a right-to-left segment are in display order (the first character of
+    /// the segment is the rightmost text cell); wrap-indent cells stay on the left.
+    pub direction: TextDirection,
+    /// Visual x (in cells) within the row at which `cells` start.
+    ///
+    /// Non-zero only for horizontally clipped snapshots (see
+    /// [`EditorCore::get_headless_grid_styled_clipped`](crate::EditorCore::get_headless_grid_styled_clipped)).
+    pub clip_x_start_cells: usize,
+    /// Document offset of the first character in the clipped window, including one cut by its
+    /// left edge. `None` for unclipped snapshots and for windows without document text.
+    pub clip_first_char_offset: Option<usize>,
     /// List of cells
     pub cells: Vec<Cell>,
 }
@@ -69,6 +148,10 @@ impl HeadlessLine {
             char_offset_end: 0,
             segment_x_start_cells: 0,
             is_fold_placeholder_appended: false,
+            background_style: None,
+            direction: TextDirection::Ltr,
+            clip_x_start_cells: 0,
+            clip_first_char_offset: None,
             cells: Vec::new(),
         }
     }
@@ -87,6 +170,19 @@ impl HeadlessLine {
         self.segment_x_start_cells = segment_x_start_cells;
     }
 
+    /// Set the base direction of this segment.
+    ///
+    /// Call after the segment's text cells were added in logical order (and after
+    /// [`set_visual_metadata`](Self::set_visual_metadata)): for [`TextDirection::Rtl`] the cells
+    /// after the wrap indent are reversed into display order.
+    pub fn set_direction(&mut self, direction: TextDirection) {
+        if direction == TextDirection::Rtl && self.direction == TextDirection::Ltr {
+            let indent = self.segment_x_start_cells.min(self.cells.len());
+            self.cells[indent..].reverse();
+        }
+        self.direction = direction;
+    }
+
     /// Mark whether this line has fold placeholder text appended.
     pub fn set_fold_placeholder_appended(&mut self, appended: bool) {
         self.is_fold_placeholder_appended = appended;
@@ -101,6 +197,21 @@ impl HeadlessLine {
     pub fn visual_width(&self) -> usize {
         self.cells.iter().map(|c| c.width).sum()
     }
+
+    /// The cell covering visual column `x` (in cells), if any.
+    ///
+    /// With [`Cell::source`] this maps a click at `x` back to the document.
+    pub fn cell_at_x(&self, x: usize) -> Option<&Cell> {
+        let mut cell_x = 0usize;
+        for cell in &self.cells {
+            let end = cell_x + cell.width.max(1);
+            if x < end {
+                return Some(cell);
+            }
+            cell_x = end;
+        }
+        None
+    }
 }
 
 /// Headless grid snapshot
@@ -133,6 +244,196 @@ impl HeadlessGrid {
     pub fn actual_line_count(&self) -> usize {
         self.lines.len()
     }
+
+    /// Rows of `self` that differ from `prev`, for repainting only what changed.
+    ///
+    /// Rows are compared by screen position (`lines[i]` of both grids), so a change of
+    /// `start_visual_row` (scrolling) reports exactly the rows whose displayed content changed.
+    /// Within a row, the common prefix of equal cells is skipped, and so is the common suffix
+    /// when both rows have the same visual width (otherwise the suffix moved). Rows only `prev`
+    /// has are reported with an empty cell span, to be cleared.
+    pub fn diff(&self, prev: &HeadlessGrid) -> Vec<RowDiff> {
+        let mut diffs = Vec::new();
+        for row in 0..self.lines.len().max(prev.lines.len()) {
+            match (self.lines.get(row), prev.lines.get(row)) {
+                (Some(new), Some(old)) => diffs.extend(row_diff(row, new, old)),
+                (Some(new), None) => diffs.push(RowDiff {
+                    row,
+                    cells: 0..new.cells.len(),
+                    columns: 0..new.visual_width(),
+                    full_row: true,
+                }),
+                (None, Some(old)) => diffs.push(RowDiff {
+                    row,
+                    cells: 0..0,
+                    columns: 0..old.visual_width(),
+                    full_row: true,
+                }),
+                (None, None) => {}
+            }
+        }
+        diffs
+    }
+
+    /// Render the grid as plain text, for tests and logging (not for display).
+    ///
+    /// See [`debug_string`] for the format.
+    pub fn to_debug_string(&self, with_styles: bool) -> String {
+        debug_string(
+            self.lines.iter().map(|line| {
+                line.cells.iter().map(|cell| {
+                    (
+                        cell.ch,
+                        cell.combining.as_str(),
+                        cell.width,
+                        cell.styles.as_slice(),
+                    )
+                })
+            }),
+            with_styles,
+        )
+    }
+
+    /// Resolve every cell's styles to final attributes with `theme`.
+    ///
+    /// Identical style lists are resolved once.
+    pub fn resolve_styles(&self, theme: &Theme) -> ResolvedGrid {
+        let mut cache: HashMap<&[StyleId], TextAttributes> = HashMap::new();
+        let lines = self
+            .lines
+            .iter()
+            .map(|line| ResolvedLine {
+                logical_line_index: line.logical_line_index,
+                is_wrapped_part: line.is_wrapped_part,
+                background: line
+                    .background_style
+                    .and_then(|style| theme.style_attributes(&[style]).bg),
+                cells: line
+                    .cells
+                    .iter()
+                    .map(|cell| ResolvedCell {
+                        ch: cell.ch,
+                        combining: cell.combining.clone(),
+                        width: cell.width,
+                        source: cell.source,
+                        attributes: *cache
+                            .entry(cell.styles.as_slice())
+                            .or_insert_with(|| theme.resolve(&cell.styles)),
+                    })
+                    .collect(),
+            })
+            .collect();
+        ResolvedGrid {
+            lines,
+            start_visual_row: self.start_visual_row,
+            count: self.count,
+        }
+    }
+}
+
+/// A [`Cell`] with its styles resolved by a [`Theme`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedCell {
+    /// Character content (first `char` of the grapheme cluster)
+    pub ch: char,
+    /// Remaining `char`s of the grapheme cluster.
+    pub combining: String,
+    /// Visual width (1 or 2 cells)
+    pub width: usize,
+    /// Where the cell comes from.
+    pub source: CellSource,
+    /// Final attributes ([`Theme::default`] overlaid with the cell's styles).
+    pub attributes: TextAttributes,
+}
+
+/// A [`HeadlessLine`] with its styles resolved by a [`Theme`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedLine {
+    /// Corresponding logical line index
+    pub logical_line_index: usize,
+    /// Whether this is a part created by wrapping (soft wrap)
+    pub is_wrapped_part: bool,
+    /// Row background color from the line's `background_style`, if the theme sets one.
+    pub background: Option<Rgb>,
+    /// Resolved cells
+    pub cells: Vec<ResolvedCell>,
+}
+
+/// A [`HeadlessGrid`] resolved by [`HeadlessGrid::resolve_styles`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedGrid {
+    /// Resolved visual lines
+    pub lines: Vec<ResolvedLine>,
+    /// Starting visual row number
+    pub start_visual_row: usize,
+    /// Number of lines requested
+    pub count: usize,
+}
+
+/// A changed row reported by [`HeadlessGrid::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowDiff {
+    /// Row index within the grid (`lines[row]`, i.e. screen row relative to the grid top).
+    pub row: usize,
+    /// Changed cells of the new row (indices into `lines[row].cells`); empty when the row was
+    /// removed.
+    pub cells: Range<usize>,
+    /// Visual columns to repaint: the changed cells of the new row, extended over the old row's
+    /// changed cells when those reached further.
+    pub columns: Range<usize>,
+    /// Whether the whole row must be repainted (it appeared, disappeared, or its background
+    /// style changed).
+    pub full_row: bool,
+}
+
+fn row_diff(row: usize, new: &HeadlessLine, old: &HeadlessLine) -> Option<RowDiff> {
+    let new_width = new.visual_width();
+    let old_width = old.visual_width();
+    if new.background_style != old.background_style {
+        return Some(RowDiff {
+            row,
+            cells: 0..new.cells.len(),
+            columns: 0..new_width.max(old_width),
+            full_row: true,
+        });
+    }
+
+    let prefix = new
+        .cells
+        .iter()
+        .zip(&old.cells)
+        .take_while(|(a, b)| same_appearance(a, b))
+        .count();
+    if prefix == new.cells.len() && prefix == old.cells.len() {
+        return None;
+    }
+    let suffix = if new_width == old_width {
+        new.cells[prefix..]
+            .iter()
+            .rev()
+            .zip(old.cells[prefix..].iter().rev())
+            .take_while(|(a, b)| same_appearance(a, b))
+            .count()
+    } else {
+        0
+    };
+
+    let start_column: usize = new.cells[..prefix].iter().map(|c| c.width).sum();
+    let suffix_width =
+        |cells: &[Cell]| -> usize { cells[cells.len() - suffix..].iter().map(|c| c.width).sum() };
+    let end_column =
+        (new_width - suffix_width(&new.cells)).max(old_width - suffix_width(&old.cells));
+    Some(RowDiff {
+        row,
+        cells: prefix..new.cells.len() - suffix,
+        columns: start_column..end_column,
+        full_row: false,
+    })
+}
+
+/// Whether two cells render the same; their sources may differ (e.g. after an edit above).
+fn same_appearance(a: &Cell, b: &Cell) -> bool {
+    a.ch == b.ch && a.combining == b.combining && a.width == b.width && a.styles == b.styles
 }
 
 /// A lightweight minimap summary for one visual line.
@@ -183,6 +484,177 @@ impl MinimapGrid {
     }
 }
 
+/// One column of a [`MinimapRow`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MinimapCell {
+    /// How many lines of the row have a non-whitespace character in this column.
+    pub occupancy: u16,
+    /// Most common style of those characters (see [`MinimapData`]).
+    pub style: Option<StyleId>,
+}
+
+/// One minimap row: up to `rows_per_cell` consecutive visible logical lines folded together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinimapRow {
+    /// First logical line of the row.
+    pub start_line: usize,
+    /// Last logical line of the row (inclusive). Lines hidden by folds in between are excluded
+    /// from the row's data.
+    pub end_line: usize,
+    /// Number of visible lines aggregated into this row.
+    pub line_count: usize,
+    /// Most common style over all the row's characters.
+    pub dominant_style: Option<StyleId>,
+    /// Smallest indentation (in cells) of the row's non-blank lines; `None` if all are blank.
+    pub indent: Option<usize>,
+    /// One entry per minimap column (`cols` entries).
+    pub cells: Vec<MinimapCell>,
+}
+
+/// A downscaled document overview for minimaps.
+///
+/// Unlike [`MinimapGrid`] (one summary per visual row of a viewport), this covers the whole
+/// document by logical lines (soft wrapping is ignored; lines hidden by collapsed folds are
+/// excluded) and folds every `rows_per_cell` visible lines into one [`MinimapRow`]. Each row
+/// has `cols` columns, one per text cell; text beyond `cols` cells is ignored.
+///
+/// Downscaling picks, for each bucket (a row, or a column within a row), the most common
+/// `StyleId` among its non-whitespace characters, counting only the highest-priority (lowest)
+/// style of each character, as [`MinimapLine::dominant_style`] does. Unstyled characters don't
+/// count; ties go to the lower `StyleId`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinimapData {
+    /// Visible lines per row.
+    pub rows_per_cell: usize,
+    /// Columns per row.
+    pub cols: usize,
+    /// Number of visible logical lines in the document.
+    pub visible_lines: usize,
+    /// Minimap rows, top to bottom.
+    pub rows: Vec<MinimapRow>,
+}
+
+/// Fold marker state shown in the gutter for a visual row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GutterFoldState {
+    /// No folding information for this row.
+    None,
+    /// The row lies inside the body of an expanded fold region (hosts may draw a fold guide).
+    Foldable,
+    /// A collapsed fold region starts on this row.
+    Collapsed,
+    /// An expanded fold region starts on this row.
+    Expanded,
+}
+
+/// Gutter metadata for one visual row (line number, fold marker, diagnostic marker).
+///
+/// Gutter snapshots are aligned row-for-row with the grid snapshot they were requested for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GutterLine {
+    /// Logical line index (0-based), or `None` for wrapped continuation rows and virtual lines.
+    pub logical_line: Option<usize>,
+    /// Fold marker state (always [`GutterFoldState::None`] when `logical_line` is `None`).
+    pub fold_state: GutterFoldState,
+    /// Most severe diagnostic touching this logical line (`None` on continuation/virtual rows).
+    pub diagnostic_severity: Option<DiagnosticSeverity>,
+    /// Whether this row continues a soft-wrapped line (as opposed to a virtual row).
+    pub is_wrap_continuation: bool,
+    /// Whether a caret or a selection touches this logical line (`false` on continuation/virtual
+    /// rows). A selection ending at column 0 does not mark its last line.
+    pub is_selected: bool,
+}
+
+impl GutterLine {
+    /// Create a gutter row without a line number or markers.
+    pub fn empty() -> Self {
+        Self {
+            logical_line: None,
+            fold_state: GutterFoldState::None,
+            diagnostic_severity: None,
+            is_wrap_continuation: false,
+            is_selected: false,
+        }
+    }
+
+    /// Create an empty gutter row for a wrapped continuation of a line.
+    pub fn wrap_continuation() -> Self {
+        Self {
+            is_wrap_continuation: true,
+            ..Self::empty()
+        }
+    }
+
+    /// The 1-based line number to display, if this row starts a logical line.
+    pub fn line_number(&self) -> Option<usize> {
+        self.logical_line.map(|line| line + 1)
+    }
+}
+
+/// Which whitespace characters a snapshot shows with substitute glyphs (see [`SnapshotOptions`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WhitespaceMode {
+    /// Render whitespace as-is.
+    #[default]
+    None,
+    /// Show every space, tab and no-break space.
+    All,
+    /// Like [`WhitespaceMode::All`], except single spaces between non-whitespace characters.
+    Boundary,
+    /// Show whitespace inside non-empty selections only.
+    Selection,
+}
+
+/// Rendering hints for styled and composed snapshots (see
+/// [`EditorCore::get_headless_grid_styled_with_options`](crate::EditorCore::get_headless_grid_styled_with_options)).
+///
+/// Substitute glyphs keep the width of the character they stand for and add
+/// [`WHITESPACE_STYLE_ID`](crate::WHITESPACE_STYLE_ID) to the cell's styles:
+///
+/// - space: `·`; no-break space (U+00A0, U+202F): `⍽`
+/// - tab: `→` in its first cell, followed by blank cells up to the tab's width
+/// - C0 controls and DEL: control pictures (`␀`..`␟`, `␡`); C1 controls: `�`
+/// - zero-width and bidi formatting characters (ZWSP, ZWNJ/ZWJ, LRM/RLM, ALM, embeddings,
+///   overrides, isolates, word joiner, BOM): `◦`, still zero cells wide, for hosts to draw as a
+///   marker between the neighbouring cells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SnapshotOptions {
+    /// Which whitespace to show.
+    pub show_whitespace: WhitespaceMode,
+    /// Whether to show control and invisible formatting characters.
+    pub show_control_chars: bool,
+    /// Whether composed snapshots report caret and selection columns per line (see
+    /// [`ComposedLine::caret_columns`]); styled snapshots ignore it.
+    pub include_selections: bool,
+}
+
+/// Substitute glyph for a space, tab or no-break space.
+pub(crate) fn whitespace_glyph(ch: char) -> Option<char> {
+    match ch {
+        ' ' => Some('·'),
+        '\t' => Some('→'),
+        '\u{a0}' | '\u{202f}' => Some('⍽'),
+        _ => None,
+    }
+}
+
+/// Substitute glyph for a control or invisible formatting character (tabs excluded).
+pub(crate) fn control_glyph(ch: char) -> Option<char> {
+    match ch {
+        '\t' => None,
+        '\u{0}'..='\u{1f}' => char::from_u32(0x2400 + ch as u32),
+        '\u{7f}' => Some('␡'),
+        '\u{80}'..='\u{9f}' => Some('\u{fffd}'),
+        '\u{61c}'
+        | '\u{200b}'..='\u{200f}'
+        | '\u{202a}'..='\u{202e}'
+        | '\u{2060}'
+        | '\u{2066}'..='\u{2069}'
+        | '\u{feff}' => Some('◦'),
+        _ => None,
+    }
+}
+
 /// A cell in a composed (decoration-aware) snapshot.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ComposedCell {
@@ -192,6 +664,9 @@ pub struct ComposedCell {
     pub width: usize,
     /// Style ids applied to this cell.
     pub styles: Vec<crate::intervals::StyleId>,
+    /// Underline to draw under this cell, from a covering underline decoration (the first one in
+    /// decoration layer order wins).
+    pub underline: Option<crate::decorations::UnderlineStyle>,
     /// Where this cell originated from (document text vs virtual text).
     pub source: ComposedCellSource,
 }
@@ -233,8 +708,20 @@ pub enum ComposedLineKind {
 pub struct ComposedLine {
     /// Line kind / anchor info.
     pub kind: ComposedLineKind,
+    /// Row background style from a [`DecorationKind::LineBackground`](crate::DecorationKind::LineBackground)
+    /// decoration (document rows only; virtual above-line rows have none).
+    pub background_style: Option<StyleId>,
     /// Rendered cells for this line.
     pub cells: Vec<ComposedCell>,
+    /// Visual columns of the carets on this row, in ascending order (only with
+    /// [`SnapshotOptions::include_selections`]).
+    ///
+    /// A caret sits before the document cell at its offset, after any inline virtual text
+    /// anchored there; at the end of a line it sits before the fold placeholder.
+    pub caret_columns: Vec<usize>,
+    /// Visual columns covered by selected document cells, one range per run of adjacent cells
+    /// (only with [`SnapshotOptions::include_selections`]). Virtual cells are never selected.
+    pub selection_columns: Vec<Range<usize>>,
 }
 
 /// A decoration-aware snapshot that can include virtual text (inlay hints, code lens, ...).
@@ -262,6 +749,83 @@ impl ComposedGrid {
     pub fn actual_line_count(&self) -> usize {
         self.lines.len()
     }
+
+    /// Render the grid as plain text, for tests and logging (not for display).
+    ///
+    /// Virtual cells render like document cells. See [`debug_string`] for the format.
+    pub fn to_debug_string(&self, with_styles: bool) -> String {
+        debug_string(
+            self.lines.iter().map(|line| {
+                line.cells
+                    .iter()
+                    .map(|cell| (cell.ch, "", cell.width, cell.styles.as_slice()))
+            }),
+            with_styles,
+        )
+    }
+}
+
+/// Render rows of `(ch, combining, width, styles)` cells as text, one `\n`-terminated line per
+/// row.
+///
+/// Each cell takes `width` columns: tabs are expanded to spaces, other grapheme clusters are
+/// written as-is. With `with_styles`, every row is followed by a legend line that marks each styled
+/// column with a letter naming its style set (`a` for the first distinct set in the grid, `b`
+/// for the next, ...; `*` once letters run out), and the letters are listed at the end as
+/// `a: 1, 2`. Trailing blanks are trimmed from legend lines only.
+fn debug_string<'a, R, C>(rows: R, with_styles: bool) -> String
+where
+    R: Iterator<Item = C>,
+    C: Iterator<Item = (char, &'a str, usize, &'a [StyleId])> + Clone,
+{
+    const MARKERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut out = String::new();
+    let mut style_sets: Vec<&'a [StyleId]> = Vec::new();
+
+    for cells in rows {
+        for (ch, combining, width, _) in cells.clone() {
+            if ch == '\t' {
+                out.extend(std::iter::repeat_n(' ', width));
+            } else {
+                out.push(ch);
+                out.push_str(combining);
+            }
+        }
+        out.push('\n');
+
+        if with_styles {
+            let legend_start = out.len();
+            for (_, _, width, styles) in cells {
+                let marker = if styles.is_empty() {
+                    ' '
+                } else {
+                    let index = match style_sets.iter().position(|set| *set == styles) {
+                        Some(index) => index,
+                        None => {
+                            style_sets.push(styles);
+                            style_sets.len() - 1
+                        }
+                    };
+                    MARKERS.get(index).map_or('*', |&b| b as char)
+                };
+                out.extend(std::iter::repeat_n(marker, width));
+            }
+            let trimmed = out[legend_start..].trim_end().len();
+            out.truncate(legend_start + trimmed);
+            out.push('\n');
+        }
+    }
+
+    for (index, styles) in style_sets.iter().enumerate().take(MARKERS.len()) {
+        out.push(MARKERS[index] as char);
+        out.push(':');
+        for (position, style) in styles.iter().enumerate() {
+            out.push_str(if position == 0 { " " } else { ", " });
+            out.push_str(&style.to_string());
+        }
+        out.push('\n');
+    }
+    out
 }
 
 /// Headless snapshot generator
@@ -441,20 +1005,24 @@ impl SnapshotGenerator {
                         );
                         segment_x_start_cells = indent_cells;
                         for _ in 0..indent_cells {
-                            headless_line.add_cell(Cell::new(' ', 1));
+                            headless_line
+                                .add_cell(Cell::new(' ', 1).with_source(CellSource::WrapIndent));
                         }
                     }
                     let seg_start_x_in_line =
                         visual_x_for_column(line_text, segment_start_col, self.tab_width);
                     let mut x_in_line = seg_start_x_in_line;
-                    for ch in line_text
-                        .chars()
-                        .skip(segment_start_col)
-                        .take(segment_end_col.saturating_sub(segment_start_col))
+                    for (col, _, grapheme) in grapheme_columns(line_text)
+                        .filter(|(col, _, _)| (segment_start_col..segment_end_col).contains(col))
                     {
-                        let w = cell_width_at(ch, x_in_line, self.tab_width);
+                        let w = grapheme_width_at(grapheme, x_in_line, self.tab_width);
                         x_in_line = x_in_line.saturating_add(w);
-                        headless_line.add_cell(Cell::new(ch, w));
+                        if let Some(cell) = Cell::from_grapheme(grapheme, w, Vec::new()) {
+                            headless_line.add_cell(cell.with_source(CellSource::Document {
+                                offset: line_start_offset + col,
+                                column: col,
+                            }));
+                        }
                     }
                     headless_line.set_visual_metadata(
                         visual_in_line,
@@ -462,6 +1030,7 @@ impl SnapshotGenerator {
                         line_start_offset.saturating_add(segment_end_col),
                         segment_x_start_cells,
                     );
+                    headless_line.set_direction(line_direction(line_text));
 
                     grid.add_line(headless_line);
                 }
@@ -527,6 +1096,43 @@ mod tests {
         assert_eq!(line.visual_width(), 4); // 1 + 1 + 2
     }
 
+    #[test]
+    fn test_resolve_styles() {
+        use crate::intervals::{CURRENT_LINE_STYLE_ID, SEARCH_MATCH_STYLE_ID};
+
+        let theme = Theme::from_toml_str(
+            "[default]\nfg = \"#ffffff\"\n[\"search.match\"]\nfg = \"#ff0000\"\nbold = true\n\
+             [current_line]\nbg = \"#202020\"\n",
+        )
+        .unwrap();
+        let mut line = HeadlessLine::new(3, false);
+        line.background_style = Some(CURRENT_LINE_STYLE_ID);
+        line.add_cell(Cell::new('a', 1));
+        line.add_cell(Cell::with_styles(
+            'b',
+            1,
+            vec![SEARCH_MATCH_STYLE_ID, 0x1234],
+        ));
+        let mut grid = HeadlessGrid::new(5, 1);
+        grid.add_line(line);
+
+        let resolved = grid.resolve_styles(&theme);
+        assert_eq!(resolved.start_visual_row, 5);
+        let line = &resolved.lines[0];
+        assert_eq!(line.logical_line_index, 3);
+        assert_eq!(line.background, Some(Rgb::new(0x20, 0x20, 0x20)));
+        assert_eq!(line.cells[0].attributes, theme.default);
+        assert_eq!(line.cells[1].ch, 'b');
+        assert_eq!(
+            line.cells[1].attributes,
+            TextAttributes {
+                fg: Some(Rgb::new(0xff, 0, 0)),
+                bold: Some(true),
+                ..TextAttributes::default()
+            }
+        );
+    }
+
     #[test]
     fn test_snapshot_generator_basic() {
         let text = "Hello\nWorld\nRust";
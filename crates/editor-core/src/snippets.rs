@@ -0,0 +1,404 @@
+//! Snippet templates and tabstop sessions.
+//!
+//! Templates use the TextMate/LSP snippet syntax subset that most completion items need:
+//!
+//! - `$1`, `${1}`: an empty tabstop
+//! - `${1:placeholder}`: a tabstop pre-filled with (and selecting) `placeholder`, which may
+//!   itself contain tabstops
+//! - `$0` / `${0}`: the final caret position (defaults to the end of the snippet)
+//! - `\$`, `\}`, `\\`: escaped literal characters
+//!
+//! A tabstop index used more than once is *mirrored*: every occurrence shares the placeholder
+//! text and is selected as a multi-cursor. Placeholders may nest (`${1:foo(${2:bar})}`): the
+//! outer tabstop selects the whole text, the inner one just its own part. Choices
+//! (`${1|a,b|}`) and variables (`$TM_FILENAME`) are not supported; they are inserted as literal
+//! text.
+//!
+//! [`EditCommand::InsertSnippet`](crate::EditCommand::InsertSnippet) inserts a template at every
+//! caret and starts a [`SnippetSession`], which the executor keeps in sync with later edits.
+
+use crate::delta::{TextDelta, TextDeltaEdit};
+
+/// A tabstop inside a parsed snippet, in character offsets relative to the snippet text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnippetTabstop {
+    /// Tabstop index (`0` is the final caret position).
+    pub index: usize,
+    /// Inclusive start character offset.
+    pub start: usize,
+    /// Exclusive end character offset (equal to `start` for empty tabstops).
+    pub end: usize,
+}
+
+/// A snippet template with its syntax removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedSnippet {
+    /// Text to insert.
+    pub text: String,
+    /// Tabstops in template order (always contains at least one `$0`).
+    pub tabstops: Vec<SnippetTabstop>,
+}
+
+impl ParsedSnippet {
+    /// Tabstops in navigation order: ascending index with `$0` last, occurrences of the same
+    /// index in template order (an enclosing placeholder before the tabstops nested in it).
+    pub fn tabstops_in_tab_order(&self) -> Vec<SnippetTabstop> {
+        let mut tabstops = self.tabstops.clone();
+        tabstops.sort_by_key(|t| (t.index == 0, t.index));
+        tabstops
+    }
+}
+
+/// A parsed template piece: literal text or a tabstop (with its placeholder, if any).
+enum Segment {
+    Text(String),
+    Tabstop {
+        index: usize,
+        placeholder: Option<Vec<Segment>>,
+    },
+}
+
+/// Parse a snippet template (see the [module docs](self) for the supported syntax).
+///
+/// Parsing never fails: anything that is not a valid tabstop is kept as literal text. If the
+/// template has no `$0`, an empty one is added at the end.
+pub fn parse_snippet(template: &str) -> ParsedSnippet {
+    let chars: Vec<char> = template.chars().collect();
+    let (segments, _) = parse_segments(&chars, 0, false);
+
+    // Mirrors without a placeholder take the text of the first occurrence that has one.
+    let mut placeholders: Vec<(usize, String)> = Vec::new();
+    collect_placeholders(&segments, &mut placeholders);
+
+    let mut out = ParsedSnippet {
+        text: String::new(),
+        tabstops: Vec::new(),
+    };
+    let mut len = 0usize;
+    emit_segments(&segments, &placeholders, &mut out, &mut len);
+
+    if !out.tabstops.iter().any(|t| t.index == 0) {
+        out.tabstops.push(SnippetTabstop {
+            index: 0,
+            start: len,
+            end: len,
+        });
+    }
+    out
+}
+
+/// Parse segments starting at `i`, up to the end of the template or (when `nested`) the `}`
+/// closing the enclosing placeholder.
+///
+/// Returns the segments and the position where parsing stopped.
+fn parse_segments(chars: &[char], mut i: usize, nested: bool) -> (Vec<Segment>, usize) {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut literal = String::new();
+    while i < chars.len() {
+        let ch = chars[i];
+        if nested && ch == '}' {
+            break;
+        }
+        if ch == '\\' && matches!(chars.get(i + 1), Some('$' | '}' | '\\')) {
+            literal.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if ch == '$'
+            && let Some((tabstop, next)) = parse_tabstop(chars, i)
+        {
+            if !literal.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut literal)));
+            }
+            segments.push(tabstop);
+            i = next;
+            continue;
+        }
+        literal.push(ch);
+        i += 1;
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Text(literal));
+    }
+    (segments, i)
+}
+
+/// Parse `$N`, `${N}` or `${N:placeholder}` at `chars[i]` (a `$`); placeholders may contain
+/// further tabstops.
+///
+/// Returns the tabstop and the position after it.
+fn parse_tabstop(chars: &[char], i: usize) -> Option<(Segment, usize)> {
+    let digits = |from: usize| {
+        chars[from..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+    let number = |from: usize, len: usize| -> Option<usize> {
+        chars[from..from + len]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    };
+
+    if chars.get(i + 1) != Some(&'{') {
+        let len = digits(i + 1);
+        if len == 0 {
+            return None;
+        }
+        let index = number(i + 1, len)?;
+        return Some((
+            Segment::Tabstop {
+                index,
+                placeholder: None,
+            },
+            i + 1 + len,
+        ));
+    }
+
+    let len = digits(i + 2);
+    if len == 0 {
+        return None;
+    }
+    let index = number(i + 2, len)?;
+    let after = i + 2 + len;
+    match chars.get(after) {
+        Some('}') => Some((
+            Segment::Tabstop {
+                index,
+                placeholder: None,
+            },
+            after + 1,
+        )),
+        Some(':') => {
+            let (placeholder, end) = parse_segments(chars, after + 1, true);
+            (chars.get(end) == Some(&'}')).then(|| {
+                (
+                    Segment::Tabstop {
+                        index,
+                        placeholder: Some(placeholder),
+                    },
+                    end + 1,
+                )
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Record the (flattened) placeholder text of the first occurrence of each tabstop index.
+fn collect_placeholders(segments: &[Segment], out: &mut Vec<(usize, String)>) {
+    for segment in segments {
+        if let Segment::Tabstop {
+            index,
+            placeholder: Some(children),
+        } = segment
+        {
+            // Nested placeholders first, so mirrors inside this one can be expanded.
+            collect_placeholders(children, out);
+            if !out.iter().any(|(i, _)| i == index) {
+                let mut text = String::new();
+                flatten_segments(children, out, &mut text);
+                out.push((*index, text));
+            }
+        }
+    }
+}
+
+/// The text `segments` expand to, using `placeholders` for mirrors.
+fn flatten_segments(segments: &[Segment], placeholders: &[(usize, String)], out: &mut String) {
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => out.push_str(text),
+            Segment::Tabstop {
+                placeholder: Some(children),
+                ..
+            } => flatten_segments(children, placeholders, out),
+            Segment::Tabstop {
+                index,
+                placeholder: None,
+            } => {
+                if let Some((_, text)) = placeholders.iter().find(|(i, _)| i == index) {
+                    out.push_str(text);
+                }
+            }
+        }
+    }
+}
+
+fn emit_segments(
+    segments: &[Segment],
+    placeholders: &[(usize, String)],
+    out: &mut ParsedSnippet,
+    len: &mut usize,
+) {
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => {
+                out.text.push_str(text);
+                *len += text.chars().count();
+            }
+            Segment::Tabstop { index, placeholder } => {
+                let slot = out.tabstops.len();
+                out.tabstops.push(SnippetTabstop {
+                    index: *index,
+                    start: *len,
+                    end: *len,
+                });
+                match placeholder {
+                    Some(children) => emit_segments(children, placeholders, out, len),
+                    None => {
+                        if let Some((_, text)) = placeholders.iter().find(|(i, _)| i == index) {
+                            out.text.push_str(text);
+                            *len += text.chars().count();
+                        }
+                    }
+                }
+                out.tabstops[slot].end = *len;
+            }
+        }
+    }
+}
+
+/// One occurrence of a tabstop in the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnippetTabstopRange {
+    /// Index of the caret the snippet was inserted at (carets in ascending document order).
+    pub caret: usize,
+    /// Inclusive start character offset in the document.
+    pub start: usize,
+    /// Exclusive end character offset in the document.
+    pub end: usize,
+}
+
+/// All occurrences of one tabstop index, across every caret the snippet was inserted at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnippetTabstopGroup {
+    /// Tabstop index.
+    pub index: usize,
+    /// Occurrences, ordered by caret and then by document offset.
+    pub ranges: Vec<SnippetTabstopRange>,
+}
+
+/// An active snippet: the tabstops still reachable with
+/// [`CursorCommand::NextTabstop`](crate::CursorCommand::NextTabstop) /
+/// [`CursorCommand::PrevTabstop`](crate::CursorCommand::PrevTabstop).
+///
+/// Ranges are char offsets in the current document; the executor shifts them through every
+/// text edit. Text typed at the edges of the current tabstop extends it, while other tabstops
+/// only move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnippetSession {
+    /// Groups in navigation order (ascending index, `$0` last).
+    groups: Vec<SnippetTabstopGroup>,
+    /// Index into `groups` of the selected tabstop.
+    current: usize,
+}
+
+impl SnippetSession {
+    /// Build a session for `snippet` inserted at `starts` (char offsets of each inserted copy).
+    ///
+    /// Returns the `$0` ranges instead when the snippet has no other tabstop to visit.
+    pub(crate) fn new(
+        snippet: &ParsedSnippet,
+        starts: &[usize],
+    ) -> Result<Self, Vec<SnippetTabstopRange>> {
+        let mut indices: Vec<usize> = snippet.tabstops.iter().map(|t| t.index).collect();
+        indices.sort_by_key(|&index| (index == 0, index));
+        indices.dedup();
+
+        let groups: Vec<SnippetTabstopGroup> = indices
+            .into_iter()
+            .map(|index| SnippetTabstopGroup {
+                index,
+                ranges: starts
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(caret, &base)| {
+                        snippet
+                            .tabstops
+                            .iter()
+                            .filter(move |t| t.index == index)
+                            .map(move |t| SnippetTabstopRange {
+                                caret,
+                                start: base + t.start,
+                                end: base + t.end,
+                            })
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        if groups.len() == 1 {
+            return Err(groups
+                .into_iter()
+                .next()
+                .map(|g| g.ranges)
+                .unwrap_or_default());
+        }
+        Ok(Self { groups, current: 0 })
+    }
+
+    /// All tabstop groups in navigation order (ascending index, `$0` last).
+    pub fn tabstops(&self) -> &[SnippetTabstopGroup] {
+        &self.groups
+    }
+
+    /// The currently selected tabstop group.
+    pub fn current(&self) -> &SnippetTabstopGroup {
+        &self.groups[self.current]
+    }
+
+    /// Index of the currently selected tabstop (e.g. `1` for `$1`).
+    pub fn current_index(&self) -> usize {
+        self.current().index
+    }
+
+    /// Whether the current tabstop is the final position (`$0`).
+    pub fn is_at_final_tabstop(&self) -> bool {
+        self.current + 1 == self.groups.len()
+    }
+
+    /// Move to the next (`forward`) or previous tabstop group, clamped to the ends.
+    pub(crate) fn step(&mut self, forward: bool) {
+        self.current = if forward {
+            (self.current + 1).min(self.groups.len() - 1)
+        } else {
+            self.current.saturating_sub(1)
+        };
+    }
+
+    /// Shift every range through `delta` (a change made while the session is active).
+    pub(crate) fn apply_delta(&mut self, delta: &TextDelta) {
+        for edit in &delta.edits {
+            for (group_index, group) in self.groups.iter_mut().enumerate() {
+                let is_current = group_index == self.current;
+                for range in &mut group.ranges {
+                    // The current tabstop grows with text typed at its edges; other tabstops
+                    // (and empty ones) are pushed along instead.
+                    let empty = range.start == range.end;
+                    let start = map_offset(range.start, edit, !is_current);
+                    let end = map_offset(range.end, edit, is_current || empty);
+                    range.start = start;
+                    range.end = end.max(start);
+                }
+            }
+        }
+    }
+}
+
+/// Map `offset` through `edit`; `stick_right` decides where an offset at the insertion point
+/// (or inside the deleted text) ends up.
+fn map_offset(offset: usize, edit: &TextDeltaEdit, stick_right: bool) -> usize {
+    let end = edit.end();
+    if offset < edit.start {
+        offset
+    } else if offset > end || (offset == end && end > edit.start) {
+        offset - edit.deleted_len() + edit.inserted_len()
+    } else if stick_right {
+        edit.start + edit.inserted_len()
+    } else {
+        edit.start
+    }
+}
@@ -34,17 +34,30 @@
 //! ```
 
 use crate::delta::TextDelta;
-use crate::intervals::{FoldRegion, Interval, StyleId, StyleLayerId};
-use crate::processing::{DocumentProcessor, ProcessingEdit};
-use crate::snapshot::{ComposedGrid, HeadlessGrid};
+use crate::intervals::{
+    FoldRegion, Interval, SEARCH_CURRENT_MATCH_STYLE_ID, SEARCH_MATCH_STYLE_ID, StyleId,
+    StyleLayerId, StylePriority,
+};
+use crate::processing::{
+    DocumentProcessor, ProcessingEdit, ProcessingEditBatch, ProcessorFailure, ProcessorPipeline,
+    StaleBatchError, StaleBatchPolicy,
+};
+use crate::search::{SearchError, SearchMatch, SearchMatchCount, SearchOptions};
+use crate::snapshot::{ComposedGrid, HeadlessGrid, SnapshotOptions};
+use crate::snippets::SnippetSession;
 use crate::{
     Command, CommandError, CommandExecutor, CommandResult, CursorCommand, Decoration,
-    DecorationLayerId, Diagnostic, EditCommand, EditorCore, LineEnding, Position, Selection,
-    SelectionDirection, StyleCommand, ViewCommand,
+    DecorationLayerId, Diagnostic, DiagnosticSeverity, EditCommand, EditorCore, IndentProvider,
+    LineEnding, Position, Selection, SelectionDirection, SelectionMergePolicy, StyleCommand,
+    ViewCommand,
 };
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Default number of deltas kept for [`EditorStateManager::delta_journal_since`].
+const DEFAULT_DELTA_JOURNAL_CAPACITY: usize = 64;
 
 /// Document state
 #[derive(Debug, Clone)]
@@ -59,6 +72,9 @@ pub struct DocumentState {
     pub is_modified: bool,
     /// Document version number (incremented after each modification)
     pub version: u64,
+    /// Document text version (incremented only by content changes, i.e.
+    /// [`StateChangeType::DocumentModified`]; styles, folding, etc. leave it unchanged).
+    pub text_version: u64,
 }
 
 /// Cursor state
@@ -87,6 +103,8 @@ pub struct ViewportState {
     pub height: Option<usize>,
     /// Current scroll position (visual line number)
     pub scroll_top: usize,
+    /// Horizontal scroll position (in cells), for clipped snapshots of unwrapped lines.
+    pub scroll_left: usize,
     /// Sub-row smooth-scroll offset (0..=65535, normalized).
     pub sub_row_offset: u16,
     /// Overscan rows used to compute `prefetch_lines`.
@@ -143,6 +161,17 @@ pub struct FoldingState {
 pub struct DiagnosticsState {
     /// Total number of diagnostics.
     pub diagnostics_count: usize,
+    /// Number of errors (including diagnostics without a severity).
+    pub error_count: usize,
+    /// Number of warnings.
+    pub warning_count: usize,
+    /// Number of information diagnostics.
+    pub information_count: usize,
+    /// Number of hints.
+    pub hint_count: usize,
+    /// Number of diagnostics per provider (see [`Diagnostic::provider`]); diagnostics without a
+    /// provider are not included.
+    pub provider_counts: BTreeMap<String, usize>,
 }
 
 /// Decorations state
@@ -159,6 +188,10 @@ pub struct DecorationsState {
 pub struct StyleState {
     /// Total number of style intervals
     pub style_count: usize,
+    /// Active search highlight query (see [`EditorStateManager::set_search_highlight`]).
+    pub search_query: Option<String>,
+    /// Number of highlighted search matches.
+    pub search_match_count: usize,
 }
 
 /// State change type
@@ -299,12 +332,16 @@ pub struct EditorStateManager {
     executor: CommandExecutor,
     /// State version number
     state_version: u64,
+    /// Version of the document text (see [`DocumentState::text_version`]).
+    text_version: u64,
     /// Whether document has been modified
     is_modified: bool,
     /// State change callback list
     callbacks: Vec<StateChangeCallback>,
     /// Current scroll position
     scroll_top: usize,
+    /// Horizontal scroll position (in cells).
+    scroll_left: usize,
     /// Sub-row smooth-scroll offset.
     scroll_sub_row_offset: u16,
     /// Overscan rows for prefetching.
@@ -313,6 +350,52 @@ pub struct EditorStateManager {
     viewport_height: Option<usize>,
     /// Structured text delta produced by the last document edit.
     last_text_delta: Option<Arc<TextDelta>>,
+    /// Deltas of the most recent text versions, oldest first (see
+    /// [`EditorStateManager::delta_journal_since`]).
+    delta_journal: VecDeque<Arc<TextDelta>>,
+    /// Maximum number of deltas kept in `delta_journal`.
+    delta_journal_capacity: usize,
+    /// How [`EditorStateManager::apply_processing_batch`] treats stale batches.
+    stale_batch_policy: StaleBatchPolicy,
+    /// Active "highlight all matches" query.
+    search_highlight: Option<SearchHighlight>,
+    /// Named content snapshots (see [`EditorStateManager::pin_version`]).
+    pins: Vec<VersionPin>,
+    /// Next pin id to hand out.
+    next_pin_id: u64,
+    /// Registered document processors (see [`EditorStateManager::run_pipeline`]).
+    pipeline: ProcessorPipeline,
+}
+
+/// Identifier of a pinned document version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PinId(pub u64);
+
+/// A labeled snapshot of the document content.
+///
+/// Pins are independent of undo/redo history: they hold the full text (shared via `Arc`, so
+/// cloning a pin is cheap) and stay valid across any later edits.
+#[derive(Debug, Clone)]
+pub struct VersionPin {
+    /// Pin identifier.
+    pub id: PinId,
+    /// User-facing label (e.g. `"before refactor"`).
+    pub label: String,
+    /// State version at the time the pin was taken.
+    pub state_version: u64,
+    /// Pinned document text.
+    pub text: Arc<str>,
+}
+
+/// State behind [`StyleLayerId::SEARCH_MATCHES`].
+#[derive(Debug, Clone)]
+struct SearchHighlight {
+    query: String,
+    options: SearchOptions,
+    /// Sorted, non-overlapping matches in the current document.
+    matches: Vec<SearchMatch>,
+    /// Index of the match equal to the primary selection, if any.
+    current: Option<usize>,
 }
 
 impl EditorStateManager {
@@ -321,13 +404,22 @@ impl EditorStateManager {
         Self {
             executor: CommandExecutor::new(text, viewport_width),
             state_version: 0,
+            text_version: 0,
             is_modified: false,
             callbacks: Vec::new(),
             scroll_top: 0,
+            scroll_left: 0,
             scroll_sub_row_offset: 0,
             overscan_rows: 0,
             viewport_height: None,
             last_text_delta: None,
+            delta_journal: VecDeque::new(),
+            delta_journal_capacity: DEFAULT_DELTA_JOURNAL_CAPACITY,
+            stale_batch_policy: StaleBatchPolicy::default(),
+            search_highlight: None,
+            pins: Vec::new(),
+            next_pin_id: 1,
+            pipeline: ProcessorPipeline::new(),
         }
     }
 
@@ -346,6 +438,11 @@ impl EditorStateManager {
         self.executor.editor_mut()
     }
 
+    /// Get the tab key behavior used by [`EditCommand::InsertTab`].
+    pub fn tab_key_behavior(&self) -> crate::TabKeyBehavior {
+        self.executor.tab_key_behavior()
+    }
+
     /// Get the preferred line ending for saving this document.
     pub fn line_ending(&self) -> LineEnding {
         self.executor.line_ending()
@@ -356,10 +453,41 @@ impl EditorStateManager {
         self.executor.set_line_ending(line_ending);
     }
 
+    /// The active snippet session, if any (see [`CommandExecutor::snippet_session`]).
+    pub fn snippet_session(&self) -> Option<&SnippetSession> {
+        self.executor.snippet_session()
+    }
+
+    /// End the active snippet session, if any.
+    pub fn end_snippet_session(&mut self) {
+        self.executor.end_snippet_session();
+    }
+
+    /// Get the policy used when normalizing multi-selection sets.
+    pub fn selection_merge_policy(&self) -> SelectionMergePolicy {
+        self.executor.selection_merge_policy()
+    }
+
+    /// Set the policy used when normalizing multi-selection sets.
+    pub fn set_selection_merge_policy(&mut self, policy: SelectionMergePolicy) {
+        self.executor.set_selection_merge_policy(policy);
+    }
+
+    /// Set (or clear) the provider consulted by auto-indenting newlines (see
+    /// [`CommandExecutor::set_indent_provider`]).
+    pub fn set_indent_provider(&mut self, provider: Option<Box<dyn IndentProvider>>) {
+        self.executor.set_indent_provider(provider);
+    }
+
     /// Get the current document text converted to the preferred line ending for saving.
+    ///
+    /// With [`LineEnding::Mixed`], each line keeps the ending it had when the document was
+    /// opened (see [`LineIndex::get_text_with_original_endings`](crate::LineIndex::get_text_with_original_endings)).
     pub fn get_text_for_saving(&self) -> String {
-        let text = self.editor().get_text();
-        self.line_ending().apply_to_text(&text)
+        match self.line_ending() {
+            LineEnding::Mixed => self.editor().line_index.get_text_with_original_endings(),
+            line_ending => line_ending.apply_to_text(&self.editor().get_text()),
+        }
     }
 
     /// Execute a command and automatically trigger state change notifications.
@@ -372,7 +500,11 @@ impl EditorStateManager {
         let change_type = Self::change_type_for_command(&command);
         let is_delete_like = matches!(
             &command,
-            Command::Edit(EditCommand::Backspace | EditCommand::DeleteForward)
+            Command::Edit(
+                EditCommand::Backspace
+                    | EditCommand::DeletePairBackspace { .. }
+                    | EditCommand::DeleteForward
+            )
         );
 
         // Detect changes for potential no-ops: when command execution succeeds but state doesn't change, version should not increment.
@@ -385,6 +517,7 @@ impl EditorStateManager {
         let result = self.executor.execute(command)?;
         let char_count_after = self.executor.editor().char_count();
         let delta_present = self.executor.last_text_delta().is_some();
+        let mut text_changed = false;
 
         if let Some(change_type) = change_type {
             let changed = match change_type {
@@ -421,22 +554,37 @@ impl EditorStateManager {
 
             if changed {
                 if matches!(change_type, StateChangeType::DocumentModified) {
+                    text_changed = true;
                     let is_modified = !self.executor.is_clean();
                     let delta = self.executor.take_last_text_delta().map(Arc::new);
                     self.last_text_delta = delta.clone();
+                    self.refresh_search_matches(delta.as_deref());
                     self.mark_modified_internal(change_type, Some(is_modified), delta);
                 } else {
                     self.mark_modified_internal(change_type, None, None);
                 }
+            } else if matches!(change_type, StateChangeType::DocumentModified)
+                && (self.executor.editor().cursor_position() != cursor_before
+                    || self.executor.editor().selection().cloned() != selection_before
+                    || self.executor.editor().secondary_selections() != secondary_before.as_slice())
+            {
+                // An edit command that only moved carets (e.g. auto-pair type-over).
+                self.mark_modified_internal(StateChangeType::SelectionChanged, None, None);
             }
         }
 
+        if self.search_highlight.is_some() {
+            self.sync_search_highlight_layer(text_changed);
+        }
+
         Ok(result)
     }
 
     fn change_type_for_command(command: &Command) -> Option<StateChangeType> {
         match command {
-            Command::Edit(EditCommand::InsertText { text }) if text.is_empty() => None,
+            Command::Edit(
+                EditCommand::InsertText { text } | EditCommand::InsertTextReindented { text },
+            ) if text.is_empty() => None,
             Command::Edit(EditCommand::Delete { length: 0, .. }) => None,
             Command::Edit(EditCommand::Replace {
                 length: 0, text, ..
@@ -455,7 +603,9 @@ impl EditorStateManager {
                 | CursorCommand::MoveGraphemeLeft
                 | CursorCommand::MoveGraphemeRight
                 | CursorCommand::MoveWordLeft
-                | CursorCommand::MoveWordRight,
+                | CursorCommand::MoveWordRight
+                | CursorCommand::GoToNextDiagnostic { .. }
+                | CursorCommand::GoToPrevDiagnostic { .. },
             ) => Some(StateChangeType::CursorMoved),
             Command::Cursor(
                 CursorCommand::SetSelection { .. }
@@ -472,22 +622,29 @@ impl EditorStateManager {
                 | CursorCommand::AddNextOccurrence { .. }
                 | CursorCommand::AddAllOccurrences { .. }
                 | CursorCommand::FindNext { .. }
-                | CursorCommand::FindPrev { .. },
+                | CursorCommand::FindPrev { .. }
+                | CursorCommand::FindNextInRange { .. }
+                | CursorCommand::IncrementalFind { .. }
+                | CursorCommand::NextTabstop
+                | CursorCommand::PrevTabstop,
             ) => Some(StateChangeType::SelectionChanged),
             Command::View(
                 ViewCommand::SetViewportWidth { .. }
                 | ViewCommand::SetWrapMode { .. }
                 | ViewCommand::SetWrapIndent { .. }
-                | ViewCommand::SetTabWidth { .. },
+                | ViewCommand::SetTabWidth { .. }
+                | ViewCommand::ApplyIndentSettings { .. },
             ) => Some(StateChangeType::ViewportChanged),
             Command::View(
                 ViewCommand::SetTabKeyBehavior { .. }
                 | ViewCommand::ScrollTo { .. }
                 | ViewCommand::GetViewport { .. },
             ) => None,
-            Command::Style(StyleCommand::AddStyle { .. } | StyleCommand::RemoveStyle { .. }) => {
-                Some(StateChangeType::StyleChanged)
-            }
+            Command::Style(
+                StyleCommand::AddStyle { .. }
+                | StyleCommand::RemoveStyle { .. }
+                | StyleCommand::ClearStyleRange { .. },
+            ) => Some(StateChangeType::StyleChanged),
             Command::Style(
                 StyleCommand::Fold { .. } | StyleCommand::Unfold { .. } | StyleCommand::UnfoldAll,
             ) => Some(StateChangeType::FoldingChanged),
@@ -499,6 +656,11 @@ impl EditorStateManager {
         self.state_version
     }
 
+    /// Get the document text version (see [`DocumentState::text_version`])
+    pub fn text_version(&self) -> u64 {
+        self.text_version
+    }
+
     /// Set viewport height
     pub fn set_viewport_height(&mut self, height: usize) {
         self.viewport_height = Some(height);
@@ -514,6 +676,28 @@ impl EditorStateManager {
         }
     }
 
+    /// Set horizontal scroll position (in cells).
+    pub fn set_scroll_left(&mut self, scroll_left: usize) {
+        let old_scroll = self.scroll_left;
+        self.scroll_left = scroll_left;
+
+        if old_scroll != scroll_left {
+            self.notify_change(StateChangeType::ViewportChanged);
+        }
+    }
+
+    /// Scroll horizontally just enough for visual column `x` (in cells) to be within the
+    /// viewport width, e.g. the cursor's x from
+    /// [`EditorCore::logical_position_to_visual`](crate::EditorCore::logical_position_to_visual).
+    pub fn reveal_x(&mut self, x: usize) {
+        let width = self.executor.editor().viewport_width.max(1);
+        if x < self.scroll_left {
+            self.set_scroll_left(x);
+        } else if x >= self.scroll_left.saturating_add(width) {
+            self.set_scroll_left(x + 1 - width);
+        }
+    }
+
     /// Set sub-row smooth-scroll offset (normalized 0..=65535).
     pub fn set_scroll_sub_row_offset(&mut self, sub_row_offset: u16) {
         let old = self.scroll_sub_row_offset;
@@ -584,6 +768,7 @@ impl EditorStateManager {
             byte_count: editor.get_text().len(),
             is_modified: self.is_modified,
             version: self.state_version,
+            text_version: self.text_version,
         }
     }
 
@@ -601,8 +786,11 @@ impl EditorStateManager {
         selections.push(primary);
         selections.extend(editor.secondary_selections().iter().cloned());
 
-        let (selections, primary_selection_index) =
-            crate::selection_set::normalize_selections(selections, 0);
+        let (selections, primary_selection_index) = crate::selection_set::normalize_selections(
+            selections,
+            0,
+            self.executor.selection_merge_policy(),
+        );
         let primary = selections
             .get(primary_selection_index)
             .cloned()
@@ -666,6 +854,7 @@ impl EditorStateManager {
             width: editor.viewport_width,
             height: self.viewport_height,
             scroll_top: clamped_top,
+            scroll_left: self.scroll_left,
             sub_row_offset: self.scroll_sub_row_offset,
             overscan_rows: self.overscan_rows,
             visible_lines,
@@ -711,15 +900,325 @@ impl EditorStateManager {
         let layered_count: usize = editor.style_layers.values().map(|t| t.len()).sum();
         StyleState {
             style_count: editor.interval_tree.len() + layered_count,
+            search_query: self.search_highlight.as_ref().map(|h| h.query.clone()),
+            search_match_count: self
+                .search_highlight
+                .as_ref()
+                .map_or(0, |h| h.matches.len()),
+        }
+    }
+
+    /// Pin the current document content under `label`.
+    pub fn pin_version(&mut self, label: &str) -> PinId {
+        let id = PinId(self.next_pin_id);
+        self.next_pin_id += 1;
+
+        // Share the allocation with an existing pin of identical content.
+        let current = self.editor().get_text();
+        let text = self
+            .pins
+            .iter()
+            .find(|p| *p.text == *current)
+            .map(|p| p.text.clone())
+            .unwrap_or_else(|| Arc::from(current));
+
+        self.pins.push(VersionPin {
+            id,
+            label: label.to_string(),
+            state_version: self.state_version,
+            text,
+        });
+        id
+    }
+
+    /// List pinned versions, oldest first.
+    pub fn list_pins(&self) -> &[VersionPin] {
+        &self.pins
+    }
+
+    /// Remove a pin. Returns `false` if it does not exist.
+    pub fn unpin(&mut self, pin: PinId) -> bool {
+        let before = self.pins.len();
+        self.pins.retain(|p| p.id != pin);
+        self.pins.len() != before
+    }
+
+    /// Total bytes of text held by pins, for memory accounting.
+    ///
+    /// Pins with identical content share one allocation and are counted once.
+    pub fn pinned_text_bytes(&self) -> usize {
+        let mut seen: HashSet<*const u8> = HashSet::new();
+        self.pins
+            .iter()
+            .filter(|p| seen.insert(p.text.as_ptr()))
+            .map(|p| p.text.len())
+            .sum()
+    }
+
+    /// Line diff from a pinned version to the current document (`None` if the pin is unknown).
+    pub fn diff_against_pin(&self, pin: PinId) -> Option<Vec<crate::DiffHunk>> {
+        let pinned = self.pins.iter().find(|p| p.id == pin)?;
+        Some(crate::diff_lines(&pinned.text, &self.editor().get_text()))
+    }
+
+    /// Restore the document to a pinned version.
+    ///
+    /// The restore is applied as a single edit (covering the changed span), so it is one undo
+    /// step and can itself be undone.
+    pub fn restore_pin(&mut self, pin: PinId) -> Result<(), CommandError> {
+        let Some(pinned) = self
+            .pins
+            .iter()
+            .find(|p| p.id == pin)
+            .map(|p| p.text.clone())
+        else {
+            return Err(CommandError::Other(format!("Unknown pin: {}", pin.0)));
+        };
+        let current = self.editor().get_text();
+        if *current == *pinned {
+            return Ok(());
+        }
+
+        let current_chars: Vec<char> = current.chars().collect();
+        let pinned_chars: Vec<char> = pinned.chars().collect();
+        let prefix = current_chars
+            .iter()
+            .zip(&pinned_chars)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = current_chars[prefix..]
+            .iter()
+            .rev()
+            .zip(pinned_chars[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let text: String = pinned_chars[prefix..pinned_chars.len() - suffix]
+            .iter()
+            .collect();
+        self.execute(Command::Edit(EditCommand::Replace {
+            start: prefix,
+            length: current_chars.len() - suffix - prefix,
+            text,
+        }))?;
+        Ok(())
+    }
+
+    /// Highlight all occurrences of a query in [`StyleLayerId::SEARCH_MATCHES`].
+    ///
+    /// Matches use [`SEARCH_MATCH_STYLE_ID`]; the match equal to the primary selection (e.g.
+    /// after `FindNext`/`FindPrev`) uses [`SEARCH_CURRENT_MATCH_STYLE_ID`]. The layer is kept up
+    /// to date across edits (only edited lines are re-searched when the pattern cannot span
+    /// lines). Passing `None` clears the layer.
+    pub fn set_search_highlight(
+        &mut self,
+        query: Option<(String, SearchOptions)>,
+    ) -> Result<(), SearchError> {
+        let Some((query, options)) = query else {
+            if self.search_highlight.take().is_some() {
+                self.clear_style_layer(StyleLayerId::SEARCH_MATCHES);
+            }
+            return Ok(());
+        };
+
+        let matches =
+            crate::search::find_all_iter(&self.executor.editor().line_index, &query, options)?
+                .collect();
+        self.search_highlight = Some(SearchHighlight {
+            query,
+            options,
+            matches,
+            current: None,
+        });
+        self.sync_search_highlight_layer(true);
+        self.mark_modified(StateChangeType::StyleChanged);
+        Ok(())
+    }
+
+    /// Matches currently highlighted by [`Self::set_search_highlight`].
+    pub fn search_highlight_matches(&self) -> &[SearchMatch] {
+        self.search_highlight
+            .as_ref()
+            .map_or(&[], |h| h.matches.as_slice())
+    }
+
+    /// Update search highlight matches after a document edit.
+    fn refresh_search_matches(&mut self, delta: Option<&TextDelta>) {
+        let Some(highlight) = self.search_highlight.as_mut() else {
+            return;
+        };
+        let line_index = &self.executor.editor().line_index;
+
+        let incremental =
+            delta.filter(|_| !crate::search::spans_lines(&highlight.query, highlight.options));
+        let Some(delta) = incremental else {
+            highlight.matches =
+                crate::search::find_all_iter(line_index, &highlight.query, highlight.options)
+                    .map(|iter| iter.collect())
+                    .unwrap_or_default();
+            return;
+        };
+
+        // Map surviving matches through the edits, tracking the edited (dirty) char ranges.
+        let mut matches = std::mem::take(&mut highlight.matches);
+        let mut dirty: Vec<(usize, usize)> = Vec::new();
+        for edit in &delta.edits {
+            let start = edit.start;
+            let deleted_end = edit.end();
+            let inserted_len = edit.inserted_len();
+            let shift = |offset: usize| offset + inserted_len - (deleted_end - start);
+
+            matches.retain_mut(|m| {
+                if m.end < start {
+                    true
+                } else if m.start > deleted_end {
+                    m.start = shift(m.start);
+                    m.end = shift(m.end);
+                    true
+                } else {
+                    false
+                }
+            });
+
+            let mut merged = (start, start + inserted_len);
+            dirty.retain_mut(|range| {
+                if range.1 < start {
+                    true
+                } else if range.0 > deleted_end {
+                    *range = (shift(range.0), shift(range.1));
+                    true
+                } else {
+                    merged.0 = merged.0.min(range.0);
+                    if range.1 > deleted_end {
+                        merged.1 = merged.1.max(shift(range.1));
+                    }
+                    false
+                }
+            });
+            dirty.push(merged);
+        }
+
+        // Re-search the whole lines touched by the edits.
+        let mut line_ranges: Vec<Range<usize>> = dirty
+            .iter()
+            .map(|&(start, end)| {
+                let start_line = line_index.char_offset_to_position(start).0;
+                let end_line = line_index.char_offset_to_position(end).0 + 1;
+                crate::search::newline_aligned_lines(line_index, start_line..end_line)
+            })
+            .collect();
+        line_ranges.sort_by_key(|r| r.start);
+
+        let mut merged_ranges: Vec<Range<usize>> = Vec::new();
+        for range in line_ranges {
+            match merged_ranges.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged_ranges.push(range),
+            }
+        }
+
+        for lines in merged_ranges {
+            let region_start = line_index.position_to_char_offset(lines.start, 0);
+            let region_end = if lines.end >= line_index.line_count() {
+                line_index.char_count()
+            } else {
+                line_index.position_to_char_offset(lines.end, 0)
+            };
+            matches.retain(|m| m.end <= region_start || m.start >= region_end);
+
+            if let Ok(found) = crate::search::find_all_iter_in_lines(
+                line_index,
+                &highlight.query,
+                highlight.options,
+                lines,
+            ) {
+                matches.extend(found);
+            }
+        }
+
+        matches.sort_unstable_by_key(|m| m.start);
+        highlight.matches = matches;
+    }
+
+    /// Rewrite [`StyleLayerId::SEARCH_MATCHES`] if the matches or the current match changed.
+    fn sync_search_highlight_layer(&mut self, matches_changed: bool) {
+        let Some(highlight) = self.search_highlight.as_mut() else {
+            return;
+        };
+
+        let editor = self.executor.editor();
+        let current = editor.selection().and_then(|selection| {
+            let a = editor
+                .line_index
+                .position_to_char_offset(selection.start.line, selection.start.column);
+            let b = editor
+                .line_index
+                .position_to_char_offset(selection.end.line, selection.end.column);
+            let (start, end) = (a.min(b), a.max(b));
+            highlight
+                .matches
+                .binary_search_by_key(&start, |m| m.start)
+                .ok()
+                .filter(|&i| highlight.matches[i].end == end)
+        });
+
+        if !matches_changed && current == highlight.current {
+            return;
+        }
+        highlight.current = current;
+
+        let intervals: Vec<Interval> = highlight
+            .matches
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let style = if Some(i) == current {
+                    SEARCH_CURRENT_MATCH_STYLE_ID
+                } else {
+                    SEARCH_MATCH_STYLE_ID
+                };
+                Interval::new(m.start, m.end, style)
+            })
+            .collect();
+
+        let editor = self.executor.editor_mut();
+        if intervals.is_empty() {
+            editor.style_layers.remove(&StyleLayerId::SEARCH_MATCHES);
+            return;
+        }
+        let tree = editor
+            .style_layers
+            .entry(StyleLayerId::SEARCH_MATCHES)
+            .or_default();
+        tree.clear();
+        for interval in intervals {
+            tree.insert(interval);
         }
     }
 
     /// Get diagnostics state.
     pub fn get_diagnostics_state(&self) -> DiagnosticsState {
         let editor = self.executor.editor();
-        DiagnosticsState {
+        let mut state = DiagnosticsState {
             diagnostics_count: editor.diagnostics.len(),
+            error_count: 0,
+            warning_count: 0,
+            information_count: 0,
+            hint_count: 0,
+            provider_counts: BTreeMap::new(),
+        };
+        for diagnostic in &editor.diagnostics {
+            if let Some(provider) = &diagnostic.provider {
+                *state.provider_counts.entry(provider.clone()).or_default() += 1;
+            }
+            match diagnostic.effective_severity() {
+                DiagnosticSeverity::Error => state.error_count += 1,
+                DiagnosticSeverity::Warning => state.warning_count += 1,
+                DiagnosticSeverity::Information => state.information_count += 1,
+                DiagnosticSeverity::Hint => state.hint_count += 1,
+            }
         }
+        state
     }
 
     /// Get decorations state.
@@ -732,6 +1231,29 @@ impl EditorStateManager {
         }
     }
 
+    /// Count occurrences of `query`, stopping after `max_matches` or once `deadline` elapses.
+    ///
+    /// When `truncated` is set, hosts should present the count as a lower bound (e.g. "1000+").
+    pub fn count_matches(
+        &self,
+        query: &str,
+        options: SearchOptions,
+        max_matches: usize,
+        deadline: Option<Duration>,
+    ) -> Result<SearchMatchCount, SearchError> {
+        let (matches, truncated) = crate::search::find_all_limited(
+            &self.executor.editor().line_index,
+            query,
+            options,
+            max_matches,
+            deadline,
+        )?;
+        Ok(SearchMatchCount {
+            count: matches.len(),
+            truncated,
+        })
+    }
+
     /// Get all styles within the specified range
     pub fn get_styles_in_range(&self, start: usize, end: usize) -> Vec<(usize, usize, StyleId)> {
         let editor = self.executor.editor();
@@ -777,6 +1299,25 @@ impl EditorStateManager {
         styles
     }
 
+    /// Get the single top-priority style at the specified position.
+    ///
+    /// See [`EditorCore::styles_at_offset_resolved`](crate::EditorCore::styles_at_offset_resolved).
+    pub fn get_style_at_resolved(&self, offset: usize) -> Option<StyleId> {
+        self.executor.editor().styles_at_offset_resolved(offset)
+    }
+
+    /// Override the priority of a style layer (`None` restores its default).
+    pub fn set_style_layer_priority(
+        &mut self,
+        layer: StyleLayerId,
+        priority: Option<StylePriority>,
+    ) {
+        self.executor
+            .editor_mut()
+            .set_style_layer_priority(layer, priority);
+        self.mark_modified(StateChangeType::StyleChanged);
+    }
+
     /// Replace all intervals in the specified style layer.
     ///
     /// Suitable for scenarios such as LSP semantic highlighting and simple syntax highlighting that require "full layer refresh".
@@ -802,6 +1343,26 @@ impl EditorStateManager {
         self.mark_modified(StateChangeType::StyleChanged);
     }
 
+    /// Replace the intervals of the specified style layer that overlap `start..end`.
+    ///
+    /// Used by processors that re-highlight only part of the document; see
+    /// [`IntervalTree::replace_range`](crate::intervals::IntervalTree::replace_range).
+    pub fn replace_style_layer_range(
+        &mut self,
+        layer: StyleLayerId,
+        start: usize,
+        end: usize,
+        intervals: Vec<Interval>,
+    ) {
+        let editor = self.executor.editor_mut();
+        let tree = editor.style_layers.entry(layer).or_default();
+        tree.replace_range(start, end, intervals);
+        if tree.is_empty() {
+            editor.style_layers.remove(&layer);
+        }
+        self.mark_modified(StateChangeType::StyleChanged);
+    }
+
     /// Clear the specified style layer.
     pub fn clear_style_layer(&mut self, layer: StyleLayerId) {
         let editor = self.executor.editor_mut();
@@ -816,6 +1377,45 @@ impl EditorStateManager {
         self.mark_modified(StateChangeType::DiagnosticsChanged);
     }
 
+    /// Diagnostics whose range contains or touches the char offset `offset` (see
+    /// [`crate::diagnostics_at_offset`]).
+    pub fn diagnostics_at_offset(&self, offset: usize) -> Vec<&Diagnostic> {
+        crate::diagnostics::diagnostics_at_offset(&self.editor().diagnostics, offset)
+    }
+
+    /// The first diagnostic at least as severe as `min_severity` starting after the char offset
+    /// `after_offset`, wrapping around to the start of the document.
+    ///
+    /// This is the query behind [`CursorCommand::GoToNextDiagnostic`](crate::CursorCommand::GoToNextDiagnostic),
+    /// for hosts that want to show the diagnostic without moving the caret.
+    pub fn next_diagnostic(
+        &self,
+        after_offset: usize,
+        min_severity: DiagnosticSeverity,
+    ) -> Option<&Diagnostic> {
+        crate::diagnostics::next_diagnostic(
+            &self.editor().diagnostics,
+            after_offset,
+            min_severity,
+            true,
+        )
+    }
+
+    /// The last diagnostic at least as severe as `min_severity` starting before the char offset
+    /// `before_offset`, wrapping around to the end of the document.
+    pub fn prev_diagnostic(
+        &self,
+        before_offset: usize,
+        min_severity: DiagnosticSeverity,
+    ) -> Option<&Diagnostic> {
+        crate::diagnostics::next_diagnostic(
+            &self.editor().diagnostics,
+            before_offset,
+            min_severity,
+            false,
+        )
+    }
+
     /// Clear all diagnostics.
     pub fn clear_diagnostics(&mut self) {
         let editor = self.executor.editor_mut();
@@ -823,6 +1423,34 @@ impl EditorStateManager {
         self.mark_modified(StateChangeType::DiagnosticsChanged);
     }
 
+    /// Replace the diagnostics of one provider, keeping the others (see
+    /// [`ProcessingEdit::ReplaceProviderDiagnostics`]).
+    pub fn replace_provider_diagnostics(&mut self, provider: &str, diagnostics: Vec<Diagnostic>) {
+        let editor = self.executor.editor_mut();
+        crate::diagnostics::replace_provider_diagnostics(
+            &mut editor.diagnostics,
+            provider,
+            diagnostics,
+        );
+        self.mark_modified(StateChangeType::DiagnosticsChanged);
+    }
+
+    /// Clear the diagnostics of one provider.
+    pub fn clear_provider_diagnostics(&mut self, provider: &str) {
+        self.replace_provider_diagnostics(provider, Vec::new());
+    }
+
+    /// Rebuild [`DecorationLayerId::DIAGNOSTICS`] underline decorations from the current
+    /// diagnostics (see [`crate::diagnostic_underline_decorations`]).
+    pub fn sync_diagnostic_decorations(&mut self) {
+        let decorations = crate::diagnostic_underline_decorations(&self.editor().diagnostics);
+        if decorations.is_empty() {
+            self.clear_decorations(DecorationLayerId::DIAGNOSTICS);
+        } else {
+            self.replace_decorations(DecorationLayerId::DIAGNOSTICS, decorations);
+        }
+    }
+
     /// Replace document symbols / outline wholesale.
     pub fn replace_document_symbols(&mut self, symbols: crate::DocumentOutline) {
         let editor = self.executor.editor_mut();
@@ -859,36 +1487,40 @@ impl EditorStateManager {
     /// Replace folding regions wholesale.
     ///
     /// If `preserve_collapsed` is true, any region that matches an existing collapsed region
-    /// (`start_line`, `end_line`) will remain collapsed after replacement.
+    /// (`start_line`, `end_line`, or else the content of those lines) will remain collapsed
+    /// after replacement (see [`crate::folding::carry_collapsed_state`]).
     pub fn replace_folding_regions(
         &mut self,
         mut regions: Vec<FoldRegion>,
         preserve_collapsed: bool,
     ) {
         if preserve_collapsed {
-            let collapsed: HashSet<(usize, usize)> = self
-                .editor()
-                .folding_manager
-                .derived_regions()
-                .iter()
-                .filter(|r| r.is_collapsed)
-                .map(|r| (r.start_line, r.end_line))
-                .collect();
-
-            for region in &mut regions {
-                if collapsed.contains(&(region.start_line, region.end_line)) {
-                    region.is_collapsed = true;
-                }
-            }
+            let editor = self.editor();
+            crate::folding::carry_collapsed_state(
+                editor.folding_manager.derived_regions(),
+                &mut regions,
+                &editor.line_index,
+            );
         }
 
         self.editor_mut()
             .folding_manager
             .replace_derived_regions(regions);
+        self.editor_mut().refresh_fold_summaries();
         self.editor_mut().invalidate_visual_row_index_cache();
         self.mark_modified(StateChangeType::FoldingChanged);
     }
 
+    /// Set the template used to summarize collapsed folds (`None` restores static placeholders).
+    ///
+    /// See [`crate::intervals::FoldingManager::set_placeholder_template`] for supported fields.
+    pub fn set_fold_placeholder_template(&mut self, template: Option<String>) {
+        let editor = self.editor_mut();
+        editor.folding_manager.set_placeholder_template(template);
+        editor.refresh_fold_summaries();
+        self.mark_modified(StateChangeType::FoldingChanged);
+    }
+
     /// Clear all *derived* folding regions (leaves user folds intact).
     pub fn clear_folding_regions(&mut self) {
         self.editor_mut().folding_manager.clear_derived_regions();
@@ -906,6 +1538,14 @@ impl EditorStateManager {
                 ProcessingEdit::ReplaceStyleLayer { layer, intervals } => {
                     self.replace_style_layer(layer, intervals);
                 }
+                ProcessingEdit::ReplaceStyleLayerRange {
+                    layer,
+                    start,
+                    end,
+                    intervals,
+                } => {
+                    self.replace_style_layer_range(layer, start, end, intervals);
+                }
                 ProcessingEdit::ClearStyleLayer { layer } => {
                     self.clear_style_layer(layer);
                 }
@@ -924,6 +1564,15 @@ impl EditorStateManager {
                 ProcessingEdit::ClearDiagnostics => {
                     self.clear_diagnostics();
                 }
+                ProcessingEdit::ReplaceProviderDiagnostics {
+                    provider,
+                    diagnostics,
+                } => {
+                    self.replace_provider_diagnostics(&provider, diagnostics);
+                }
+                ProcessingEdit::ClearProviderDiagnostics { provider } => {
+                    self.clear_provider_diagnostics(&provider);
+                }
                 ProcessingEdit::ReplaceDecorations { layer, decorations } => {
                     self.replace_decorations(layer, decorations);
                 }
@@ -950,6 +1599,92 @@ impl EditorStateManager {
         Ok(())
     }
 
+    /// Apply a batch of processing edits computed for an earlier (or the current) text version,
+    /// e.g. by a worker thread.
+    ///
+    /// A batch for the current [`DocumentState::text_version`] is applied as-is. A stale batch is
+    /// handled according to [`EditorStateManager::set_stale_batch_policy`]: rejected, or shifted
+    /// through the text changes made since (see [`ProcessingEditBatch`] for what shifting keeps).
+    /// Batches the delta journal cannot bring up to date, or for a future version, are rejected.
+    pub fn apply_processing_batch(
+        &mut self,
+        batch: ProcessingEditBatch,
+    ) -> Result<(), StaleBatchError> {
+        let error = StaleBatchError {
+            batch_version: batch.document_version,
+            current_version: self.text_version,
+        };
+        if batch.document_version == self.text_version {
+            self.apply_processing_edits(batch.edits);
+            return Ok(());
+        }
+        if self.stale_batch_policy == StaleBatchPolicy::Drop {
+            return Err(error);
+        }
+        let deltas = self
+            .delta_journal_since(batch.document_version)
+            .ok_or(error)?;
+        let edits = batch.shift(&deltas);
+        self.apply_processing_edits(edits);
+        Ok(())
+    }
+
+    /// How [`EditorStateManager::apply_processing_batch`] treats batches for older text versions.
+    pub fn stale_batch_policy(&self) -> StaleBatchPolicy {
+        self.stale_batch_policy
+    }
+
+    /// Set how [`EditorStateManager::apply_processing_batch`] treats batches for older text
+    /// versions (default: [`StaleBatchPolicy::Drop`]).
+    pub fn set_stale_batch_policy(&mut self, policy: StaleBatchPolicy) {
+        self.stale_batch_policy = policy;
+    }
+
+    /// The text deltas that turn text version `version` into the current text, oldest first.
+    ///
+    /// Returns an empty list for the current version, and `None` when `version` is in the future
+    /// or older than the journal (it keeps the last
+    /// [`delta_journal_capacity`](EditorStateManager::set_delta_journal_capacity) deltas, and is
+    /// reset by changes made without a delta, e.g. [`EditorStateManager::mark_modified`]).
+    pub fn delta_journal_since(&self, version: u64) -> Option<Vec<TextDelta>> {
+        let count = usize::try_from(self.text_version.checked_sub(version)?).ok()?;
+        let skip = self.delta_journal.len().checked_sub(count)?;
+        Some(
+            self.delta_journal
+                .iter()
+                .skip(skip)
+                .map(|delta| delta.as_ref().clone())
+                .collect(),
+        )
+    }
+
+    /// Set how many text deltas [`EditorStateManager::delta_journal_since`] keeps (default: 64).
+    pub fn set_delta_journal_capacity(&mut self, capacity: usize) {
+        self.delta_journal_capacity = capacity;
+        let excess = self.delta_journal.len().saturating_sub(capacity);
+        self.delta_journal.drain(..excess);
+    }
+
+    /// The registered document processors.
+    pub fn pipeline(&self) -> &ProcessorPipeline {
+        &self.pipeline
+    }
+
+    /// The registered document processors, e.g. to
+    /// [`register`](ProcessorPipeline::register) one.
+    pub fn pipeline_mut(&mut self) -> &mut ProcessorPipeline {
+        &mut self.pipeline
+    }
+
+    /// Run the registered processors (see [`ProcessorPipeline::run`]) and return the errors
+    /// of the ones that failed.
+    pub fn run_pipeline(&mut self) -> Vec<ProcessorFailure> {
+        let mut pipeline = std::mem::take(&mut self.pipeline);
+        let failures = pipeline.run(self);
+        self.pipeline = pipeline;
+        failures
+    }
+
     /// Get viewport content
     pub fn get_viewport_content(&self, start_row: usize, count: usize) -> HeadlessGrid {
         let editor = self.executor.editor();
@@ -978,6 +1713,39 @@ impl EditorStateManager {
             .get_headless_grid_styled(start_visual_row, count)
     }
 
+    /// Get styled viewport content with rendering hints (visible whitespace and control
+    /// characters, see [`SnapshotOptions`]).
+    pub fn get_viewport_content_styled_with_options(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+        options: &SnapshotOptions,
+    ) -> HeadlessGrid {
+        self.executor
+            .editor()
+            .get_headless_grid_styled_with_options(start_visual_row, count, options)
+    }
+
+    /// Get styled viewport content clipped to visual columns
+    /// `x_start_cells..x_start_cells + width_cells`.
+    ///
+    /// See [`EditorCore::get_headless_grid_styled_clipped`](crate::EditorCore::get_headless_grid_styled_clipped);
+    /// pass [`ViewportState::scroll_left`] as `x_start_cells` to follow horizontal scrolling.
+    pub fn get_viewport_content_styled_clipped(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+        x_start_cells: usize,
+        width_cells: usize,
+    ) -> HeadlessGrid {
+        self.executor.editor().get_headless_grid_styled_clipped(
+            start_visual_row,
+            count,
+            x_start_cells,
+            width_cells,
+        )
+    }
+
     /// Get lightweight minimap content (by visual line).
     pub fn get_minimap_content(&self, start_visual_row: usize, count: usize) -> crate::MinimapGrid {
         self.executor
@@ -985,6 +1753,33 @@ impl EditorStateManager {
             .get_minimap_grid(start_visual_row, count)
     }
 
+    /// Get a downscaled overview of the whole document (see [`crate::MinimapData`]).
+    pub fn get_minimap(&self, rows_per_cell: usize, cols: usize) -> crate::MinimapData {
+        self.executor.editor().get_minimap(rows_per_cell, cols)
+    }
+
+    /// Get gutter metadata aligned row-for-row with [`Self::get_viewport_content_styled`].
+    pub fn get_viewport_gutter(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+    ) -> Vec<crate::GutterLine> {
+        self.executor
+            .editor()
+            .get_viewport_gutter(start_visual_row, count)
+    }
+
+    /// Get gutter metadata aligned row-for-row with [`Self::get_viewport_content_composed`].
+    pub fn get_viewport_gutter_composed(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+    ) -> Vec<crate::GutterLine> {
+        self.executor
+            .editor()
+            .get_viewport_gutter_composed(start_visual_row, count)
+    }
+
     /// Get a decoration-aware composed viewport snapshot (by composed visual line).
     ///
     /// See [`EditorCore::get_headless_grid_composed`](crate::EditorCore::get_headless_grid_composed)
@@ -999,6 +1794,19 @@ impl EditorStateManager {
             .get_headless_grid_composed(start_visual_row, count)
     }
 
+    /// Get composed viewport content with rendering hints (visible whitespace and control
+    /// characters, see [`SnapshotOptions`]).
+    pub fn get_viewport_content_composed_with_options(
+        &self,
+        start_visual_row: usize,
+        count: usize,
+        options: &SnapshotOptions,
+    ) -> ComposedGrid {
+        self.executor
+            .editor()
+            .get_headless_grid_composed_with_options(start_visual_row, count, options)
+    }
+
     /// Get total visual line count under current wrap/folding state.
     pub fn total_visual_lines(&self) -> usize {
         self.executor.editor().visual_line_count()
@@ -1042,6 +1850,13 @@ impl EditorStateManager {
 
     /// Mark document as modified and increment version number
     pub fn mark_modified(&mut self, change_type: StateChangeType) {
+        if matches!(change_type, StateChangeType::DocumentModified)
+            && self.search_highlight.is_some()
+        {
+            // The edit is unknown here, so re-search the whole document.
+            self.refresh_search_matches(None);
+            self.sync_search_highlight_layer(true);
+        }
         self.mark_modified_internal(change_type, None, None);
     }
 
@@ -1057,6 +1872,19 @@ impl EditorStateManager {
         // Only mark as modified for document content changes
         if matches!(change_type, StateChangeType::DocumentModified) {
             self.is_modified = is_modified_override.unwrap_or(true);
+            self.text_version += 1;
+            match &delta {
+                Some(delta) => {
+                    if self.delta_journal.len() == self.delta_journal_capacity {
+                        self.delta_journal.pop_front();
+                    }
+                    if self.delta_journal_capacity > 0 {
+                        self.delta_journal.push_back(delta.clone());
+                    }
+                }
+                // The change is unknown, so older versions can no longer be mapped forward.
+                None => self.delta_journal.clear(),
+            }
         }
 
         let mut change = StateChange::new(change_type, old_version, self.state_version);
@@ -1373,6 +2201,40 @@ mod tests {
         assert_eq!(manager.get_styles_at(0), vec![1, 100]);
     }
 
+    #[test]
+    fn test_resolved_style_follows_layer_priority() {
+        let mut manager = EditorStateManager::new("let x = 1;", 80);
+        manager.replace_style_layer(
+            StyleLayerId::SEMANTIC_TOKENS,
+            vec![Interval::new(4, 5, 100)],
+        );
+        manager.replace_style_layer(
+            StyleLayerId::SUBLIME_SYNTAX,
+            vec![Interval::new(0, 10, 200), Interval::new(4, 6, 201)],
+        );
+        manager
+            .editor_mut()
+            .interval_tree
+            .insert(Interval::new(0, 10, 1));
+
+        // Semantic tokens outrank syntax; inside one layer the innermost interval wins.
+        assert_eq!(manager.get_style_at_resolved(4), Some(100));
+        assert_eq!(manager.get_style_at_resolved(5), Some(201));
+        assert_eq!(manager.get_style_at_resolved(0), Some(200));
+        assert_eq!(manager.get_style_at_resolved(10), None);
+        assert_eq!(manager.get_styles_at(4), vec![1, 100, 200, 201]);
+
+        let version = manager.version();
+        manager.set_style_layer_priority(StyleLayerId::SUBLIME_SYNTAX, Some(100));
+        assert!(manager.version() > version);
+        assert_eq!(manager.get_style_at_resolved(4), Some(201));
+
+        manager.set_style_layer_priority(StyleLayerId::SUBLIME_SYNTAX, None);
+        manager.set_style_layer_priority(StyleLayerId::SEMANTIC_TOKENS, Some(-1));
+        assert_eq!(manager.get_style_at_resolved(4), Some(201));
+        assert_eq!(manager.get_style_at_resolved(0), Some(200));
+    }
+
     #[test]
     fn test_viewport_content_styled_wraps_and_includes_styles() {
         let mut manager = EditorStateManager::new("abcdef", 3);
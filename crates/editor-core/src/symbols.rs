@@ -9,6 +9,8 @@
 //! - fuzzy search over symbols
 //! - navigation/jump lists
 
+use crate::completion::fuzzy_match_positions;
+
 /// A half-open character-offset range (`start..end`) in the document.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SymbolRange {
@@ -173,6 +175,10 @@ pub struct DocumentSymbol {
     pub range: SymbolRange,
     /// Selection span (character offsets).
     pub selection_range: SymbolRange,
+    /// Full symbol span in UTF-16 coordinates, if the provider reported one (e.g. LSP).
+    pub utf16_range: Option<Utf16Range>,
+    /// Selection span in UTF-16 coordinates, if the provider reported one (e.g. LSP).
+    pub utf16_selection_range: Option<Utf16Range>,
     /// Child symbols.
     pub children: Vec<DocumentSymbol>,
     /// Optional raw integration payload, encoded as JSON text.
@@ -239,6 +245,23 @@ impl DocumentOutline {
         }
         out
     }
+
+    /// The symbols enclosing character `offset`, from outermost to innermost (a breadcrumb).
+    ///
+    /// A symbol encloses `offset` when `range.start <= offset < range.end`. When several
+    /// siblings enclose it, the first one (in outline order) is followed.
+    pub fn symbol_path_at(&self, offset: usize) -> Vec<&DocumentSymbol> {
+        let mut path = Vec::new();
+        let mut level = &self.symbols;
+        while let Some(symbol) = level
+            .iter()
+            .find(|sym| sym.range.start <= offset && offset < sym.range.end)
+        {
+            path.push(symbol);
+            level = &symbol.children;
+        }
+        path
+    }
 }
 
 /// A workspace symbol (cross-file, usually flat).
@@ -257,3 +280,46 @@ pub struct WorkspaceSymbol {
     /// Optional raw integration payload, encoded as JSON text.
     pub data_json: Option<String>,
 }
+
+/// A workspace symbol matching a [`filter_workspace_symbols`] query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoredSymbol<'a> {
+    /// The matching symbol.
+    pub symbol: &'a WorkspaceSymbol,
+    /// Match score (higher is better), see [`fuzzy_match_positions`].
+    pub score: i64,
+    /// Matched character indices in `symbol.name`, for highlighting.
+    pub positions: Vec<usize>,
+}
+
+/// Fuzzy-filter `symbols` by name and rank them for a symbol picker.
+///
+/// A symbol matches when `query` is a case-insensitive subsequence of its name; it is scored by
+/// [`fuzzy_match_positions`] (contiguous matches and matches at word starts, including
+/// `camelCase` humps, rank higher). Results are sorted by descending score, then shorter names,
+/// then input order. An empty query keeps every symbol, in input order.
+pub fn filter_workspace_symbols<'a>(
+    symbols: &'a [WorkspaceSymbol],
+    query: &str,
+) -> Vec<ScoredSymbol<'a>> {
+    let mut scored: Vec<ScoredSymbol<'a>> = symbols
+        .iter()
+        .filter_map(|symbol| {
+            let (score, positions) = fuzzy_match_positions(query, &symbol.name)?;
+            Some(ScoredSymbol {
+                symbol,
+                score,
+                positions,
+            })
+        })
+        .collect();
+    if !query.is_empty() {
+        scored.sort_by_key(|scored| {
+            (
+                std::cmp::Reverse(scored.score),
+                scored.symbol.name.chars().count(),
+            )
+        });
+    }
+    scored
+}
@@ -28,3 +28,30 @@ pub(crate) fn split_lines_preserve_trailing(text: &str) -> Vec<String> {
     // line semantics (N newlines => N+1 lines), and keeps behavior consistent with Rope.
     text.split('\n').map(|line| line.to_string()).collect()
 }
+
+/// Identifier-like characters (used for word selection and completion).
+pub(crate) fn is_word_char(ch: char) -> bool {
+    ch == '_' || ch.is_alphanumeric()
+}
+
+/// Column where the word ending at `column` starts (`column` itself if no word char precedes it).
+pub(crate) fn word_start_column(line_text: &str, column: usize) -> usize {
+    let before: Vec<char> = line_text.chars().take(column).collect();
+    let trailing = before
+        .iter()
+        .rev()
+        .take_while(|&&ch| is_word_char(ch))
+        .count();
+    before.len() - trailing
+}
+
+/// Column where the word starting at `column` ends (`column` itself if no word char follows it).
+pub(crate) fn word_end_column(line_text: &str, column: usize) -> usize {
+    let column = column.min(line_text.chars().count());
+    column
+        + line_text
+            .chars()
+            .skip(column)
+            .take_while(|&ch| is_word_char(ch))
+            .count()
+}
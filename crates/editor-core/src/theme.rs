@@ -0,0 +1,576 @@
+//! Theme resolution: map style ids to concrete text attributes.
+//!
+//! A [`Theme`] holds rules keyed by style names (see [`StyleRegistry`]): `"string"`,
+//! `"scope:comment.line"`, `"semantic:function"`, `"search.match"`, ... A rule selector matches
+//! a name equal to it or extending it by dotted segments, so `scope:comment` matches
+//! `scope:comment.line.double-slash.rust`. When several rules match one style, the more specific
+//! selector (more segments) wins; equally specific rules apply in file order. A cell with several
+//! styles then stacks each style's attributes by precedence: editor decorations (search matches,
+//! diagnostics, ...) over highlighting, and among highlighting styles the more specific one on top.
+//!
+//! Themes are written in a small TOML subset (see [`Theme::from_toml_str`]); hosts resolve
+//! cells with [`Theme::resolve`] or a whole grid with
+//! [`HeadlessGrid::resolve_styles`](crate::HeadlessGrid::resolve_styles) and only need to
+//! convert [`Rgb`] to their toolkit's color type.
+
+use crate::intervals::{
+    BUILTIN_STYLE_NAMES, CURRENT_LINE_STYLE_ID, DIAGNOSTIC_DEPRECATED_STYLE_ID,
+    DIAGNOSTIC_ERROR_STYLE_ID, DIAGNOSTIC_HINT_STYLE_ID, DIAGNOSTIC_INFORMATION_STYLE_ID,
+    DIAGNOSTIC_UNNECESSARY_STYLE_ID, DIAGNOSTIC_WARNING_STYLE_ID, DOCUMENT_HIGHLIGHT_READ_STYLE_ID,
+    DOCUMENT_HIGHLIGHT_TEXT_STYLE_ID, DOCUMENT_HIGHLIGHT_WRITE_STYLE_ID, FOLD_PLACEHOLDER_STYLE_ID,
+    SEARCH_CURRENT_MATCH_STYLE_ID, SEARCH_MATCH_STYLE_ID, StyleId, StyleRegistry,
+    WHITESPACE_STYLE_ID,
+};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Built-in styles from lowest to highest precedence. Every other style (syntax, semantic
+/// tokens) ranks below all of them.
+const BUILTIN_PRECEDENCE: &[StyleId] = &[
+    CURRENT_LINE_STYLE_ID,
+    WHITESPACE_STYLE_ID,
+    FOLD_PLACEHOLDER_STYLE_ID,
+    DOCUMENT_HIGHLIGHT_TEXT_STYLE_ID,
+    DOCUMENT_HIGHLIGHT_READ_STYLE_ID,
+    DOCUMENT_HIGHLIGHT_WRITE_STYLE_ID,
+    DIAGNOSTIC_UNNECESSARY_STYLE_ID,
+    DIAGNOSTIC_DEPRECATED_STYLE_ID,
+    DIAGNOSTIC_HINT_STYLE_ID,
+    DIAGNOSTIC_INFORMATION_STYLE_ID,
+    DIAGNOSTIC_WARNING_STYLE_ID,
+    DIAGNOSTIC_ERROR_STYLE_ID,
+    SEARCH_MATCH_STYLE_ID,
+    SEARCH_CURRENT_MATCH_STYLE_ID,
+];
+
+/// A 24-bit RGB color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    /// Red component.
+    pub r: u8,
+    /// Green component.
+    pub g: u8,
+    /// Blue component.
+    pub b: u8,
+}
+
+impl Rgb {
+    /// Create a color from its components.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parse `#rrggbb` or `#rgb` (the `#` is optional).
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.is_ascii() {
+            return None;
+        }
+        let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+        match hex.len() {
+            6 => Some(Self::new(
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            )),
+            3 => {
+                let short = |i: usize| channel(&hex[i..i + 1]).map(|v| v * 0x11);
+                Some(Self::new(short(0)?, short(1)?, short(2)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Toolkit-independent text attributes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TextAttributes {
+    /// Foreground color (`None` keeps the host default).
+    pub fg: Option<Rgb>,
+    /// Background color (`None` keeps the host default).
+    pub bg: Option<Rgb>,
+    /// Bold text (`None` keeps the host default).
+    pub bold: Option<bool>,
+    /// Italic text (`None` keeps the host default).
+    pub italic: Option<bool>,
+    /// Underlined text (`None` keeps the host default).
+    pub underline: Option<bool>,
+    /// Struck-through text (`None` keeps the host default).
+    pub strikethrough: Option<bool>,
+}
+
+impl TextAttributes {
+    /// Apply `other` on top of `self`: each attribute `other` sets replaces the one in `self`, so
+    /// a rule can also turn a flag off (`bold = false`).
+    pub fn overlay(&mut self, other: &TextAttributes) {
+        fn set<T: Copy>(target: &mut Option<T>, value: Option<T>) {
+            if value.is_some() {
+                *target = value;
+            }
+        }
+        set(&mut self.fg, other.fg);
+        set(&mut self.bg, other.bg);
+        set(&mut self.bold, other.bold);
+        set(&mut self.italic, other.italic);
+        set(&mut self.underline, other.underline);
+        set(&mut self.strikethrough, other.strikethrough);
+    }
+}
+
+/// A theme rule: attributes for style names matching `selector`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeRule {
+    /// Style name selector, e.g. `scope:comment` or `semantic:function`.
+    pub selector: String,
+    /// Attributes applied to matching styles.
+    pub attributes: TextAttributes,
+}
+
+impl ThemeRule {
+    /// Number of dotted segments; a rule with more segments beats a less specific one.
+    pub fn specificity(&self) -> usize {
+        self.selector.split('.').count()
+    }
+
+    /// Whether `name` equals the selector or extends it by dotted segments.
+    pub fn matches(&self, name: &str) -> bool {
+        name.strip_prefix(self.selector.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    }
+}
+
+/// Theme loading errors.
+#[derive(Debug)]
+pub enum ThemeError {
+    /// The theme file could not be read.
+    Io(std::io::Error),
+    /// The theme text is malformed.
+    Parse {
+        /// 1-based line number.
+        line: usize,
+        /// What is wrong with the line.
+        message: String,
+    },
+}
+
+impl std::fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Failed to read theme: {}", err),
+            Self::Parse { line, message } => write!(f, "Theme line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+impl From<std::io::Error> for ThemeError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Maps style ids to [`TextAttributes`] through their registered names.
+///
+/// Without a registry only the built-in names ([`BUILTIN_STYLE_NAMES`]) resolve; producers must
+/// allocate their ids from the same [`StyleRegistry`] (see [`Theme::with_registry`]) for their
+/// styles to be themed.
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+    /// Attributes of text without any themed style (the `[default]` table).
+    pub default: TextAttributes,
+    rules: Vec<ThemeRule>,
+    registry: Option<StyleRegistry>,
+}
+
+impl Theme {
+    /// Create an empty theme.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve style ids through `registry`.
+    pub fn with_registry(mut self, registry: StyleRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Replace the registry used to name style ids.
+    pub fn set_registry(&mut self, registry: Option<StyleRegistry>) {
+        self.registry = registry;
+    }
+
+    /// The registry used to name style ids, if any.
+    pub fn registry(&self) -> Option<&StyleRegistry> {
+        self.registry.as_ref()
+    }
+
+    /// Append a rule (later rules win over equally specific earlier ones).
+    pub fn add_rule(&mut self, selector: impl Into<String>, attributes: TextAttributes) {
+        self.rules.push(ThemeRule {
+            selector: selector.into(),
+            attributes,
+        });
+    }
+
+    /// All rules, in file order.
+    pub fn rules(&self) -> &[ThemeRule] {
+        &self.rules
+    }
+
+    /// Parse a theme from a small TOML subset.
+    ///
+    /// Each table is a selector (quote names containing `:` or `.`); `[default]` sets
+    /// [`Theme::default`]. Keys are `fg`/`bg` (`"#rrggbb"` strings) and `bold`, `italic`,
+    /// `underline`, `strikethrough` (booleans):
+    ///
+    /// ```text
+    /// [default]
+    /// fg = "#d4d4d4"
+    ///
+    /// ["scope:comment"]
+    /// fg = "#6a9955"
+    /// italic = true
+    /// ```
+    pub fn from_toml_str(text: &str) -> Result<Self, ThemeError> {
+        let mut theme = Self::new();
+        // `None` while in `[default]`, otherwise the index of the current rule.
+        let mut current: Option<Option<usize>> = None;
+
+        for (idx, raw_line) in text.lines().enumerate() {
+            let error = |message: String| ThemeError::Parse {
+                line: idx + 1,
+                message,
+            };
+            let line = strip_comment(raw_line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("unterminated table header".to_string()))?
+                    .trim();
+                let selector = parse_key(header).map_err(error)?;
+                if selector == "default" {
+                    current = Some(None);
+                } else {
+                    theme.add_rule(selector, TextAttributes::default());
+                    current = Some(Some(theme.rules.len() - 1));
+                }
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(error(format!("expected `key = value`, got `{}`", line)));
+            };
+            let Some(target) = current else {
+                return Err(error("key outside of a table".to_string()));
+            };
+            let attributes = match target {
+                None => &mut theme.default,
+                Some(rule) => &mut theme.rules[rule].attributes,
+            };
+            let key = parse_key(key.trim()).map_err(error)?;
+            let value = value.trim();
+            match key.as_str() {
+                "fg" | "bg" => {
+                    let hex = parse_string(value).map_err(error)?;
+                    let color = Rgb::from_hex(&hex)
+                        .ok_or_else(|| error(format!("invalid color `{}`", hex)))?;
+                    if key == "fg" {
+                        attributes.fg = Some(color);
+                    } else {
+                        attributes.bg = Some(color);
+                    }
+                }
+                "bold" | "italic" | "underline" | "strikethrough" => {
+                    let flag = match value {
+                        "true" => true,
+                        "false" => false,
+                        _ => return Err(error(format!("expected a boolean, got `{}`", value))),
+                    };
+                    match key.as_str() {
+                        "bold" => attributes.bold = Some(flag),
+                        "italic" => attributes.italic = Some(flag),
+                        "underline" => attributes.underline = Some(flag),
+                        _ => attributes.strikethrough = Some(flag),
+                    }
+                }
+                _ => return Err(error(format!("unknown key `{}`", key))),
+            }
+        }
+
+        Ok(theme)
+    }
+
+    /// Read and parse a theme file (see [`Theme::from_toml_str`]).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ThemeError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// The name of `style_id`, from the registry or the built-in names.
+    pub fn style_name(&self, style_id: StyleId) -> Option<Arc<str>> {
+        match &self.registry {
+            Some(registry) => registry.name_for_style_id(style_id),
+            None => BUILTIN_STYLE_NAMES
+                .iter()
+                .find(|(id, _)| *id == style_id)
+                .map(|(_, name)| Arc::from(*name)),
+        }
+    }
+
+    /// Attributes the rules assign to `styles`, without [`Theme::default`].
+    ///
+    /// Each style first resolves on its own (its matching rules, least specific first). The
+    /// styles are then overlaid by precedence, so a built-in decoration such as
+    /// [`SEARCH_MATCH_STYLE_ID`] sets its attributes over syntax highlighting however specific the
+    /// syntax rule is; among other styles the one with the more specific rule goes on top. The
+    /// order of `styles` does not matter. Unnamed style ids and names no rule matches contribute
+    /// nothing.
+    pub fn style_attributes(&self, styles: &[StyleId]) -> TextAttributes {
+        let mut resolved: Vec<((usize, usize, usize, StyleId), TextAttributes)> = Vec::new();
+        for &style_id in styles {
+            let Some(name) = self.style_name(style_id) else {
+                continue;
+            };
+            let mut matches: Vec<(usize, usize)> = self
+                .rules
+                .iter()
+                .enumerate()
+                .filter(|(_, rule)| rule.matches(&name))
+                .map(|(idx, rule)| (rule.specificity(), idx))
+                .collect();
+            matches.sort_unstable();
+            let Some(&(specificity, last)) = matches.last() else {
+                continue;
+            };
+
+            let mut attributes = TextAttributes::default();
+            for (_, idx) in matches {
+                attributes.overlay(&self.rules[idx].attributes);
+            }
+            let rank = BUILTIN_PRECEDENCE
+                .iter()
+                .position(|&id| id == style_id)
+                .map_or(0, |pos| pos + 1);
+            resolved.push(((rank, specificity, last, style_id), attributes));
+        }
+        resolved.sort_unstable_by_key(|(key, _)| *key);
+
+        let mut attributes = TextAttributes::default();
+        for (_, style_attributes) in resolved {
+            attributes.overlay(&style_attributes);
+        }
+        attributes
+    }
+
+    /// Final attributes of a cell with `styles`: [`Theme::default`] overlaid with
+    /// [`Theme::style_attributes`].
+    pub fn resolve(&self, styles: &[StyleId]) -> TextAttributes {
+        let mut attributes = self.default;
+        attributes.overlay(&self.style_attributes(styles));
+        attributes
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (idx, ch) in line.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_key(key: &str) -> Result<String, String> {
+    if key.starts_with('"') {
+        return parse_string(key);
+    }
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-')
+    {
+        return Err(format!("invalid key `{}` (quote it)", key));
+    }
+    Ok(key.to_string())
+}
+
+fn parse_string(value: &str) -> Result<String, String> {
+    value
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .filter(|inner| !inner.contains('"') && !inner.contains('\\'))
+        .map(str::to_string)
+        .ok_or_else(|| format!("expected a plain quoted string, got `{}`", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intervals::SEARCH_MATCH_STYLE_ID;
+
+    const THEME: &str = r##"
+# Comments and blank lines are ignored.
+[default]
+fg = "#d4d4d4"
+
+["scope:comment"]
+fg = "#6a9955"
+italic = true
+
+["scope:comment.line"] # more specific
+fg = "#808080"
+
+[string]
+fg = "#ce9178"
+
+["search.match"]
+underline = true
+bg = "#333"
+"##;
+
+    #[test]
+    fn test_parse_theme() {
+        let theme = Theme::from_toml_str(THEME).unwrap();
+        assert_eq!(theme.default.fg, Some(Rgb::new(0xd4, 0xd4, 0xd4)));
+        assert_eq!(theme.rules().len(), 4);
+        assert_eq!(theme.rules()[1].selector, "scope:comment.line");
+        assert_eq!(
+            theme.rules()[3].attributes,
+            TextAttributes {
+                bg: Some(Rgb::new(0x33, 0x33, 0x33)),
+                underline: Some(true),
+                ..TextAttributes::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_report_lines() {
+        for (text, line) in [
+            ("fg = \"#fff\"", 1),
+            ("[a]\nfg = \"#ffff\"", 2),
+            ("[a]\nbold = yes", 2),
+            ("[a]\n\ncolor = \"#fff\"", 3),
+            ("[scope:comment]", 1),
+            ("[a", 1),
+        ] {
+            match Theme::from_toml_str(text) {
+                Err(ThemeError::Parse { line: got, .. }) => assert_eq!(got, line, "{text}"),
+                other => panic!("{text}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_more_specific_selector_wins() {
+        let registry = StyleRegistry::new();
+        let theme = Theme::from_toml_str(THEME)
+            .unwrap()
+            .with_registry(registry.clone());
+        let comment_line = registry.style_id("scope:comment.line.double-slash.rust");
+        let comment_block = registry.style_id("scope:comment.block.rust");
+        let string = registry.style_id("string");
+        let other = registry.style_id("scope:commentary");
+
+        let line = theme.resolve(&[comment_line]);
+        assert_eq!(line.fg, Some(Rgb::new(0x80, 0x80, 0x80)));
+        assert_eq!(line.italic, Some(true));
+        // Style order does not matter, only selector specificity.
+        assert_eq!(theme.resolve(&[comment_block, comment_line]), line);
+        assert_eq!(theme.resolve(&[comment_line, comment_block]), line);
+
+        assert_eq!(
+            theme.resolve(&[comment_block]).fg,
+            Some(Rgb::new(0x6a, 0x99, 0x55))
+        );
+        assert_eq!(
+            theme.resolve(&[string]).fg,
+            Some(Rgb::new(0xce, 0x91, 0x78))
+        );
+        assert_eq!(theme.resolve(&[other]), theme.default);
+        assert_eq!(theme.resolve(&[0x1234]), theme.default);
+    }
+
+    #[test]
+    fn test_more_specific_selector_clears_flags() {
+        let registry = StyleRegistry::new();
+        let theme = Theme::from_toml_str(
+            r#"
+[default]
+bold = true
+
+["scope:comment"]
+italic = true
+underline = true
+
+["scope:comment.line"]
+bold = false
+italic = false
+"#,
+        )
+        .unwrap()
+        .with_registry(registry.clone());
+
+        let block = theme.resolve(&[registry.style_id("scope:comment.block")]);
+        assert_eq!(block.bold, Some(true));
+        assert_eq!(block.italic, Some(true));
+
+        let line = theme.resolve(&[registry.style_id("scope:comment.line")]);
+        assert_eq!(line.bold, Some(false));
+        assert_eq!(line.italic, Some(false));
+        assert_eq!(line.underline, Some(true));
+        assert_eq!(line.strikethrough, None);
+    }
+
+    #[test]
+    fn test_decorations_override_more_specific_syntax_rules() {
+        let registry = StyleRegistry::new();
+        let theme = Theme::from_toml_str(
+            r##"
+["scope:comment.line.double-slash"]
+fg = "#808080"
+bg = "#101010"
+underline = false
+
+["search.match"]
+bg = "#333333"
+underline = true
+"##,
+        )
+        .unwrap()
+        .with_registry(registry.clone());
+        let comment = registry.style_id("scope:comment.line.double-slash.rust");
+
+        // The comment rule has more segments, but the search match still sets its background
+        // and underline; attributes it leaves unset come from the comment.
+        for styles in [
+            [comment, SEARCH_MATCH_STYLE_ID],
+            [SEARCH_MATCH_STYLE_ID, comment],
+        ] {
+            let attributes = theme.resolve(&styles);
+            assert_eq!(attributes.bg, Some(Rgb::new(0x33, 0x33, 0x33)));
+            assert_eq!(attributes.underline, Some(true));
+            assert_eq!(attributes.fg, Some(Rgb::new(0x80, 0x80, 0x80)));
+        }
+        assert_eq!(
+            theme.resolve(&[comment]).bg,
+            Some(Rgb::new(0x10, 0x10, 0x10))
+        );
+    }
+
+    #[test]
+    fn test_builtin_names_resolve_without_registry() {
+        let theme = Theme::from_toml_str(THEME).unwrap();
+        let attributes = theme.style_attributes(&[SEARCH_MATCH_STYLE_ID]);
+        assert_eq!(attributes.underline, Some(true));
+        assert_eq!(attributes.fg, None);
+        assert_eq!(theme.resolve(&[SEARCH_MATCH_STYLE_ID]).fg, theme.default.fg);
+    }
+}
@@ -21,7 +21,8 @@ use crate::delta::TextDelta;
 use crate::processing::ProcessingEdit;
 use crate::search::{SearchError, SearchMatch, SearchOptions, find_all};
 use crate::selection_set::selection_direction;
-use crate::{LineIndex, Position, Selection, TabKeyBehavior, ViewCommand};
+use crate::symbols::{SymbolLocation, Utf16Position};
+use crate::{LanguageConfig, LineIndex, Position, Selection, TabKeyBehavior, ViewCommand};
 use crate::{StateChange, StateChangeCallback, StateChangeType, WrapIndent, WrapMode};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Range;
@@ -68,6 +69,8 @@ impl ViewId {
 pub struct BufferMetadata {
     /// Optional buffer URI/path (host-provided).
     pub uri: Option<String>,
+    /// Language id from the buffer's [`LanguageConfig`], if one was set.
+    pub language_id: Option<String>,
 }
 
 /// Result of opening a buffer (a buffer always starts with a default view).
@@ -187,6 +190,13 @@ pub enum WorkspaceError {
         /// Error message.
         message: String,
     },
+    /// The host loader could not provide the text of a document to open.
+    LoadFailed {
+        /// Document uri.
+        uri: String,
+        /// Error message.
+        message: String,
+    },
 }
 
 /// Search matches for a single open buffer in a [`Workspace`].
@@ -200,6 +210,39 @@ pub struct WorkspaceSearchResult {
     pub matches: Vec<SearchMatch>,
 }
 
+/// How two views linked with [`Workspace::link_views`] keep their scroll positions in sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollLinkMode {
+    /// Scrolling either view scrolls the other.
+    Bidirectional,
+    /// Only the first view drives the second; the second can still scroll on its own.
+    Follow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ScrollLink {
+    leader: ViewId,
+    follower: ViewId,
+    mode: ScrollLinkMode,
+}
+
+impl ScrollLink {
+    fn involves(&self, view: ViewId) -> bool {
+        self.leader == view || self.follower == view
+    }
+
+    /// The view that should follow `source`, if `source` drives this link.
+    fn target_for(&self, source: ViewId) -> Option<ViewId> {
+        if source == self.leader {
+            Some(self.follower)
+        } else if source == self.follower && self.mode == ScrollLinkMode::Bidirectional {
+            Some(self.leader)
+        } else {
+            None
+        }
+    }
+}
+
 /// Smooth-scrolling state for a view.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ViewSmoothScrollState {
@@ -230,6 +273,35 @@ pub struct WorkspaceViewportState {
     pub prefetch_lines: Range<usize>,
 }
 
+fn utf16_to_position(line_index: &LineIndex, pos: Utf16Position) -> Position {
+    let line = (pos.line as usize).min(line_index.line_count().saturating_sub(1));
+    let line_text = line_index.get_line_text(line).unwrap_or_default();
+    // Positions inside a surrogate pair round down to the start of the character.
+    let mut units = 0usize;
+    let mut column = 0usize;
+    for ch in line_text.chars() {
+        units += ch.len_utf16();
+        if units > pos.character as usize {
+            break;
+        }
+        column += 1;
+    }
+    Position::new(line, column)
+}
+
+fn clamp_position(line_index: &LineIndex, pos: Position) -> Position {
+    let line = pos.line.min(line_index.line_count().saturating_sub(1));
+    let line_len = line_index
+        .position_to_char_offset(line, usize::MAX)
+        .saturating_sub(line_index.position_to_char_offset(line, 0));
+    Position::new(line, pos.column.min(line_len))
+}
+
+fn clamp_selection(line_index: &LineIndex, sel: &mut Selection) {
+    sel.start = clamp_position(line_index, sel.start);
+    sel.end = clamp_position(line_index, sel.end);
+}
+
 fn apply_char_offset_delta(mut offset: usize, delta: &TextDelta) -> usize {
     for edit in &delta.edits {
         let start = edit.start;
@@ -295,6 +367,8 @@ pub struct Workspace {
     next_view_id: u64,
     views: BTreeMap<ViewId, ViewEntry>,
     active_view: Option<ViewId>,
+
+    scroll_links: Vec<ScrollLink>,
 }
 
 impl std::fmt::Debug for Workspace {
@@ -370,7 +444,10 @@ impl Workspace {
         self.next_buffer_id = self.next_buffer_id.saturating_add(1);
 
         let executor = CommandExecutor::new(text, viewport_width);
-        let meta = BufferMetadata { uri: uri.clone() };
+        let meta = BufferMetadata {
+            uri: uri.clone(),
+            language_id: None,
+        };
         self.buffers.insert(
             buffer_id,
             BufferEntry {
@@ -411,6 +488,7 @@ impl Workspace {
             .collect();
         for view_id in views_to_remove {
             self.views.remove(&view_id);
+            self.scroll_links.retain(|link| !link.involves(view_id));
         }
 
         if self
@@ -424,21 +502,144 @@ impl Workspace {
     }
 
     /// Close a view. If it was the last view of its buffer, the buffer is also closed.
+    ///
+    /// This is [`Workspace::detach_view`] without the closed-buffer report.
     pub fn close_view(&mut self, id: ViewId) -> Result<(), WorkspaceError> {
+        self.detach_view(id).map(|_| ())
+    }
+
+    /// Remove a view from the workspace.
+    ///
+    /// Buffers are reference-counted by their views: the buffer stays open while any other view
+    /// still shows it, and is closed when its last view detaches. Returns the id of the buffer
+    /// that was closed, if any.
+    pub fn detach_view(&mut self, id: ViewId) -> Result<Option<BufferId>, WorkspaceError> {
         let Some(view) = self.views.remove(&id) else {
             return Err(WorkspaceError::ViewNotFound(id));
         };
+        self.scroll_links.retain(|link| !link.involves(id));
 
         if self.active_view == Some(id) {
             self.active_view = self.views.keys().next().copied();
         }
 
-        let still_has_views = self.views.values().any(|v| v.buffer == view.buffer);
-        if !still_has_views {
-            self.close_buffer(view.buffer)?;
+        self.close_buffer_if_unused(view.buffer)
+    }
+
+    /// Re-attach an existing view to another open buffer (e.g. a tab dragged into a split pane).
+    ///
+    /// - The view keeps its layout settings (width, wrapping, tab settings) and scroll link, but
+    ///   its cursor, selections and scroll position are reset to the start of the new buffer.
+    /// - The previous buffer is closed if this was its last view (see
+    ///   [`Workspace::detach_view`]); its id is returned in that case.
+    /// - Moving a view to the buffer it already shows is a no-op.
+    pub fn move_view_to_buffer(
+        &mut self,
+        view_id: ViewId,
+        buffer_id: BufferId,
+    ) -> Result<Option<BufferId>, WorkspaceError> {
+        if !self.buffers.contains_key(&buffer_id) {
+            return Err(WorkspaceError::BufferNotFound(buffer_id));
+        }
+        let Some(view) = self.views.get_mut(&view_id) else {
+            return Err(WorkspaceError::ViewNotFound(view_id));
+        };
+        if view.buffer == buffer_id {
+            return Ok(None);
+        }
+
+        let previous = std::mem::replace(&mut view.buffer, buffer_id);
+        view.core.cursor_position = Position::new(0, 0);
+        view.core.selection = None;
+        view.core.secondary_selections.clear();
+        view.core.preferred_x_cells = None;
+        view.scroll_top = 0;
+        view.scroll_sub_row_offset = 0;
+        view.last_text_delta = None;
+        Self::notify_view(view, StateChangeType::DocumentModified, None);
+
+        self.close_buffer_if_unused(previous)
+    }
+
+    fn close_buffer_if_unused(
+        &mut self,
+        buffer_id: BufferId,
+    ) -> Result<Option<BufferId>, WorkspaceError> {
+        if self.views.values().any(|v| v.buffer == buffer_id) {
+            return Ok(None);
         }
+        self.close_buffer(buffer_id)?;
+        Ok(Some(buffer_id))
+    }
 
-        Ok(())
+    /// Open (or focus) the document at `location` and put the cursor at the start of its range.
+    ///
+    /// - If a buffer with `location.uri` is already open, its view becomes active (the current
+    ///   active view if it shows that buffer, otherwise the buffer's first view).
+    /// - Otherwise `loader(uri)` is called for the document text and a new buffer is opened,
+    ///   using the active view's viewport width (or 80 cells when there is none).
+    /// - The UTF-16 range is converted using the target document's text. If the view has a
+    ///   viewport height and the target line is off-screen, it is scrolled to the top.
+    ///
+    /// Returns the view showing the location.
+    pub fn open_location<F>(
+        &mut self,
+        location: &SymbolLocation,
+        loader: F,
+    ) -> Result<ViewId, WorkspaceError>
+    where
+        F: FnOnce(&str) -> Result<String, String>,
+    {
+        let view_id = match self.buffer_id_for_uri(&location.uri) {
+            Some(buffer_id) => self
+                .active_view
+                .filter(|id| self.views.get(id).is_some_and(|v| v.buffer == buffer_id))
+                .or_else(|| {
+                    self.views
+                        .iter()
+                        .find(|(_, v)| v.buffer == buffer_id)
+                        .map(|(id, _)| *id)
+                })
+                .ok_or(WorkspaceError::BufferNotFound(buffer_id))?,
+            None => {
+                let text = loader(&location.uri).map_err(|message| WorkspaceError::LoadFailed {
+                    uri: location.uri.clone(),
+                    message,
+                })?;
+                let viewport_width = self
+                    .active_view
+                    .and_then(|id| self.views.get(&id))
+                    .map_or(80, |v| v.core.viewport_width);
+                self.open_buffer(Some(location.uri.clone()), &text, viewport_width)?
+                    .view_id
+            }
+        };
+        self.set_active_view(view_id)?;
+
+        let buffer_id = self.buffer_id_for_view(view_id)?;
+        let Some(buffer) = self.buffers.get(&buffer_id) else {
+            return Err(WorkspaceError::BufferNotFound(buffer_id));
+        };
+        let target = utf16_to_position(&buffer.executor.editor().line_index, location.range.start);
+        self.execute(
+            view_id,
+            Command::Cursor(CursorCommand::MoveTo {
+                line: target.line,
+                column: target.column,
+            }),
+        )?;
+
+        if let Some((row, _)) =
+            self.logical_to_visual_for_view(view_id, target.line, target.column)?
+            && let Some(view) = self.views.get_mut(&view_id)
+            && let Some(height) = view.viewport_height
+            && !(view.scroll_top..view.scroll_top + height).contains(&row)
+        {
+            view.scroll_top = row;
+            self.sync_linked_scroll(view_id)?;
+        }
+
+        Ok(view_id)
     }
 
     /// Create a new view into an existing buffer.
@@ -486,6 +687,13 @@ impl Workspace {
         self.uri_to_buffer.get(uri).copied()
     }
 
+    /// Iterate the uris of open buffers with their ids, in no particular order.
+    pub fn buffer_uris(&self) -> impl Iterator<Item = (&str, BufferId)> + '_ {
+        self.uri_to_buffer
+            .iter()
+            .map(|(uri, id)| (uri.as_str(), *id))
+    }
+
     /// Get a buffer's metadata.
     pub fn buffer_metadata(&self, id: BufferId) -> Option<&BufferMetadata> {
         self.buffers.get(&id).map(|e| &e.meta)
@@ -583,6 +791,58 @@ impl Workspace {
         Ok(())
     }
 
+    /// Set a buffer's language configuration.
+    ///
+    /// - The language id is recorded in [`BufferMetadata::language_id`].
+    /// - Commands that accept optional language settings (e.g. [`EditCommand::ToggleComment`]
+    ///   with an empty config) use this configuration for every view of the buffer.
+    /// - Indentation fields that are set update the tab width / Tab key behavior of all views of
+    ///   the buffer; views whose layout changed receive a `ViewportChanged` notification.
+    pub fn set_buffer_language(
+        &mut self,
+        id: BufferId,
+        config: LanguageConfig,
+    ) -> Result<(), WorkspaceError> {
+        let Some(entry) = self.buffers.get_mut(&id) else {
+            return Err(WorkspaceError::BufferNotFound(id));
+        };
+
+        entry.meta.language_id = Some(config.language_id.clone());
+        let tab_width = config.indent_width.filter(|&width| width > 0);
+        let tab_key_behavior = config.use_tabs.map(|use_tabs| {
+            if use_tabs {
+                TabKeyBehavior::Tab
+            } else {
+                TabKeyBehavior::Spaces
+            }
+        });
+        entry.executor.set_language_config(Some(config));
+
+        for view in self.views.values_mut() {
+            if view.buffer != id {
+                continue;
+            }
+            let before = view.core.clone();
+            if let Some(width) = tab_width {
+                view.core.tab_width = width;
+            }
+            if let Some(behavior) = tab_key_behavior {
+                view.core.tab_key_behavior = behavior;
+            }
+            if view.core != before {
+                Self::notify_view(view, StateChangeType::ViewportChanged, None);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get a buffer's language configuration, if one was set.
+    pub fn buffer_language(&self, id: BufferId) -> Option<&LanguageConfig> {
+        self.buffers
+            .get(&id)
+            .and_then(|entry| entry.executor.language_config())
+    }
+
     /// Get a view's current version (increments on view-local changes and buffer changes).
     pub fn view_version(&self, id: ViewId) -> Option<u64> {
         self.views.get(&id).map(|v| v.version)
@@ -645,7 +905,9 @@ impl Workspace {
 
     fn command_change_type(command: &Command) -> Option<StateChangeType> {
         match command {
-            Command::Edit(EditCommand::InsertText { text }) if text.is_empty() => None,
+            Command::Edit(
+                EditCommand::InsertText { text } | EditCommand::InsertTextReindented { text },
+            ) if text.is_empty() => None,
             Command::Edit(EditCommand::Delete { length: 0, .. }) => None,
             Command::Edit(EditCommand::Replace {
                 length: 0, text, ..
@@ -666,13 +928,17 @@ impl Workspace {
                 | CursorCommand::MoveWordLeft
                 | CursorCommand::MoveWordRight
                 | CursorCommand::FindNext { .. }
-                | CursorCommand::FindPrev { .. },
+                | CursorCommand::FindPrev { .. }
+                | CursorCommand::GoToNextDiagnostic { .. }
+                | CursorCommand::GoToPrevDiagnostic { .. },
             ) => Some(StateChangeType::CursorMoved),
             Command::Cursor(_) => Some(StateChangeType::SelectionChanged),
             Command::View(ViewCommand::ScrollTo { .. } | ViewCommand::GetViewport { .. }) => None,
             Command::View(_) => Some(StateChangeType::ViewportChanged),
             Command::Style(
-                crate::StyleCommand::AddStyle { .. } | crate::StyleCommand::RemoveStyle { .. },
+                crate::StyleCommand::AddStyle { .. }
+                | crate::StyleCommand::RemoveStyle { .. }
+                | crate::StyleCommand::ClearStyleRange { .. },
             ) => Some(StateChangeType::StyleChanged),
             Command::Style(
                 crate::StyleCommand::Fold { .. }
@@ -746,7 +1012,11 @@ impl Workspace {
         let change_type = if buffer_text_changed {
             StateChangeType::DocumentModified
         } else {
-            change_type.unwrap_or(StateChangeType::ViewportChanged)
+            match change_type {
+                // An edit command that only moved carets (e.g. auto-pair type-over).
+                Some(StateChangeType::DocumentModified) => StateChangeType::SelectionChanged,
+                other => other.unwrap_or(StateChangeType::ViewportChanged),
+            }
         };
 
         if buffer_text_changed || buffer_derived_changed {
@@ -806,6 +1076,14 @@ impl Workspace {
             Self::notify_view(view, change_type, None);
         }
 
+        // Re-wrapping/folding changes which visual row shows which line.
+        if matches!(
+            change_type,
+            StateChangeType::ViewportChanged | StateChangeType::FoldingChanged
+        ) {
+            self.sync_linked_scroll(view_id)?;
+        }
+
         Ok(result)
     }
 
@@ -832,7 +1110,7 @@ impl Workspace {
             return Err(WorkspaceError::ViewNotFound(view_id));
         };
         view.scroll_top = scroll_top;
-        Ok(())
+        self.sync_linked_scroll(view_id)
     }
 
     /// Set sub-row smooth-scroll offset for a view.
@@ -873,6 +1151,107 @@ impl Workspace {
         view.scroll_top = state.top_visual_row;
         view.scroll_sub_row_offset = state.sub_row_offset;
         view.overscan_rows = state.overscan_rows;
+        self.sync_linked_scroll(view_id)
+    }
+
+    /// Link the scroll positions of two views (e.g. side-by-side panes of the same buffer).
+    ///
+    /// - `b` is scrolled to match `a` immediately; afterwards every scroll of `a` (and, with
+    ///   [`ScrollLinkMode::Bidirectional`], of `b`) is mirrored onto the other view.
+    /// - Views are aligned by **logical position**, not raw visual row: the first logical
+    ///   line/column shown by the scrolled view is mapped into the other view's own wrapping
+    ///   and folding. With different viewport widths the two `scroll_top` values therefore
+    ///   differ, but both panes start at the same line.
+    /// - Re-wrapping or folding a linked view re-syncs its partner; text edits leave the link
+    ///   in place. Closing either view removes the link.
+    /// - A view has at most one link: linking replaces any existing link of `a` or `b`.
+    pub fn link_views(
+        &mut self,
+        a: ViewId,
+        b: ViewId,
+        mode: ScrollLinkMode,
+    ) -> Result<(), WorkspaceError> {
+        for id in [a, b] {
+            if !self.views.contains_key(&id) {
+                return Err(WorkspaceError::ViewNotFound(id));
+            }
+        }
+        if a == b {
+            return Ok(());
+        }
+
+        self.scroll_links
+            .retain(|link| !link.involves(a) && !link.involves(b));
+        self.scroll_links.push(ScrollLink {
+            leader: a,
+            follower: b,
+            mode,
+        });
+        self.sync_linked_scroll(a)
+    }
+
+    /// Remove the scroll link between `a` and `b` (in either order).
+    ///
+    /// Returns `false` if the views were not linked.
+    pub fn unlink_views(&mut self, a: ViewId, b: ViewId) -> bool {
+        let before = self.scroll_links.len();
+        self.scroll_links
+            .retain(|link| !(link.involves(a) && link.involves(b)));
+        self.scroll_links.len() != before
+    }
+
+    /// The view linked to `view` and the link mode, if any.
+    pub fn linked_view(&self, view: ViewId) -> Option<(ViewId, ScrollLinkMode)> {
+        self.scroll_links.iter().find_map(|link| {
+            if link.leader == view {
+                Some((link.follower, link.mode))
+            } else if link.follower == view {
+                Some((link.leader, link.mode))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Mirror `source`'s scroll position onto the view it drives (if any).
+    fn sync_linked_scroll(&mut self, source: ViewId) -> Result<(), WorkspaceError> {
+        let Some(target) = self
+            .scroll_links
+            .iter()
+            .find_map(|link| link.target_for(source))
+        else {
+            return Ok(());
+        };
+
+        let Some((scroll_top, sub_row_offset)) = self
+            .views
+            .get(&source)
+            .map(|v| (v.scroll_top, v.scroll_sub_row_offset))
+        else {
+            return Err(WorkspaceError::ViewNotFound(source));
+        };
+
+        let top = match self.visual_position_to_logical_for_view(source, scroll_top, 0)? {
+            Some(pos) => pos,
+            None => {
+                let (line, _) = self.visual_to_logical_for_view(source, scroll_top)?;
+                Position::new(line, 0)
+            }
+        };
+        let target_row = match self.logical_to_visual_for_view(target, top.line, top.column)? {
+            Some((row, _)) => row,
+            // Hidden in the target (e.g. inside a collapsed fold): use the line's visible row.
+            None => self
+                .logical_to_visual_for_view(target, top.line, 0)?
+                .map(|(row, _)| row)
+                .unwrap_or(0),
+        };
+
+        let Some(view) = self.views.get_mut(&target) else {
+            return Err(WorkspaceError::ViewNotFound(target));
+        };
+        view.scroll_top = target_row;
+        view.scroll_sub_row_offset = sub_row_offset;
         Ok(())
     }
 
@@ -1014,6 +1393,14 @@ impl Workspace {
         Ok(buffer.executor.editor().get_text())
     }
 
+    /// Whether a buffer is at its clean point (no unsaved changes).
+    pub fn is_buffer_clean(&self, buffer_id: BufferId) -> Result<bool, WorkspaceError> {
+        let Some(buffer) = self.buffers.get(&buffer_id) else {
+            return Err(WorkspaceError::BufferNotFound(buffer_id));
+        };
+        Ok(buffer.executor.is_clean())
+    }
+
     /// Get styled viewport content for a view (by visual line).
     pub fn get_viewport_content_styled(
         &mut self,
@@ -1070,6 +1457,61 @@ impl Workspace {
             .get_minimap_grid(start_visual_row, count))
     }
 
+    /// Get a downscaled overview of a view's document (see [`crate::MinimapData`]).
+    ///
+    /// Tabs are expanded with the view's tab width.
+    pub fn get_minimap(
+        &mut self,
+        view_id: ViewId,
+        rows_per_cell: usize,
+        cols: usize,
+    ) -> Result<crate::MinimapData, WorkspaceError> {
+        let Some(buffer_id) = self.views.get(&view_id).map(|v| v.buffer) else {
+            return Err(WorkspaceError::ViewNotFound(view_id));
+        };
+
+        let view_core = self
+            .views
+            .get(&view_id)
+            .map(|v| v.core.clone())
+            .ok_or(WorkspaceError::ViewNotFound(view_id))?;
+
+        let Some(buffer) = self.buffers.get_mut(&buffer_id) else {
+            return Err(WorkspaceError::BufferNotFound(buffer_id));
+        };
+
+        view_core.apply_to_executor(&mut buffer.executor);
+        Ok(buffer.executor.editor().get_minimap(rows_per_cell, cols))
+    }
+
+    /// Get gutter metadata for a view, aligned row-for-row with [`Self::get_viewport_content_styled`].
+    pub fn get_viewport_gutter(
+        &mut self,
+        view_id: ViewId,
+        start_visual_row: usize,
+        count: usize,
+    ) -> Result<Vec<crate::GutterLine>, WorkspaceError> {
+        let Some(buffer_id) = self.views.get(&view_id).map(|v| v.buffer) else {
+            return Err(WorkspaceError::ViewNotFound(view_id));
+        };
+
+        let view_core = self
+            .views
+            .get(&view_id)
+            .map(|v| v.core.clone())
+            .ok_or(WorkspaceError::ViewNotFound(view_id))?;
+
+        let Some(buffer) = self.buffers.get_mut(&buffer_id) else {
+            return Err(WorkspaceError::BufferNotFound(buffer_id));
+        };
+
+        view_core.apply_to_executor(&mut buffer.executor);
+        Ok(buffer
+            .executor
+            .editor()
+            .get_viewport_gutter(start_visual_row, count))
+    }
+
     /// Get a decoration-aware composed viewport snapshot for a view (by composed visual line).
     ///
     /// This snapshot can include virtual text (inlay hints, code lens) injected from the buffer's
@@ -1137,6 +1579,20 @@ impl Workspace {
                     }
                     style_changed = true;
                 }
+                ProcessingEdit::ReplaceStyleLayerRange {
+                    layer,
+                    start,
+                    end,
+                    intervals,
+                } => {
+                    let editor = buffer.executor.editor_mut();
+                    let tree = editor.style_layers.entry(layer).or_default();
+                    tree.replace_range(start, end, intervals);
+                    if tree.is_empty() {
+                        editor.style_layers.remove(&layer);
+                    }
+                    style_changed = true;
+                }
                 ProcessingEdit::ClearStyleLayer { layer } => {
                     buffer.executor.editor_mut().style_layers.remove(&layer);
                     style_changed = true;
@@ -1168,10 +1624,9 @@ impl Workspace {
                         .editor_mut()
                         .folding_manager
                         .replace_derived_regions(regions);
-                    buffer
-                        .executor
-                        .editor_mut()
-                        .invalidate_visual_row_index_cache();
+                    let editor = buffer.executor.editor_mut();
+                    editor.refresh_fold_summaries();
+                    editor.invalidate_visual_row_index_cache();
                     folding_changed = true;
                 }
                 ProcessingEdit::ClearFoldingRegions => {
@@ -1194,6 +1649,25 @@ impl Workspace {
                     buffer.executor.editor_mut().diagnostics.clear();
                     diagnostics_changed = true;
                 }
+                ProcessingEdit::ReplaceProviderDiagnostics {
+                    provider,
+                    diagnostics,
+                } => {
+                    crate::diagnostics::replace_provider_diagnostics(
+                        &mut buffer.executor.editor_mut().diagnostics,
+                        &provider,
+                        diagnostics,
+                    );
+                    diagnostics_changed = true;
+                }
+                ProcessingEdit::ClearProviderDiagnostics { provider } => {
+                    crate::diagnostics::replace_provider_diagnostics(
+                        &mut buffer.executor.editor_mut().diagnostics,
+                        &provider,
+                        Vec::new(),
+                    );
+                    diagnostics_changed = true;
+                }
                 ProcessingEdit::ReplaceDecorations {
                     layer,
                     mut decorations,
@@ -1276,6 +1750,146 @@ impl Workspace {
         Ok(out)
     }
 
+    /// Replace a buffer's contents with `new_text` (e.g. after the file changed on disk).
+    ///
+    /// - The replacement is a single undoable step, after which the buffer is marked clean.
+    /// - Views keep their cursor, selections and `scroll_top` where possible: positions are
+    ///   clamped to the new text rather than mapped through the edit.
+    /// - Fold regions (and their collapsed state) are kept unless they now extend past the end
+    ///   of the document.
+    /// - Every view of the buffer receives a `DocumentModified` notification.
+    pub fn reload_buffer(
+        &mut self,
+        buffer_id: BufferId,
+        new_text: &str,
+    ) -> Result<(), WorkspaceError> {
+        let Some(buffer) = self.buffers.get_mut(&buffer_id) else {
+            return Err(WorkspaceError::BufferNotFound(buffer_id));
+        };
+
+        let folding = &buffer.executor.editor().folding_manager;
+        let derived_folds = folding.derived_regions().to_vec();
+        let user_folds = folding.user_regions().to_vec();
+
+        let neutral = ViewCore {
+            cursor_position: Position::new(0, 0),
+            selection: None,
+            secondary_selections: Vec::new(),
+            viewport_width: buffer.executor.editor().viewport_width.max(1),
+            wrap_mode: buffer.executor.editor().layout_engine.wrap_mode(),
+            wrap_indent: buffer.executor.editor().layout_engine.wrap_indent(),
+            tab_width: buffer.executor.editor().layout_engine.tab_width(),
+            tab_key_behavior: buffer.executor.tab_key_behavior(),
+            preferred_x_cells: None,
+        };
+        neutral.apply_to_executor(&mut buffer.executor);
+
+        let char_count = buffer.executor.editor().char_count();
+        buffer
+            .executor
+            .execute(Command::Edit(EditCommand::ApplyTextEdits {
+                edits: vec![TextEditSpec {
+                    start: 0,
+                    end: char_count,
+                    text: new_text.to_string(),
+                }],
+            }))
+            .map_err(|err| WorkspaceError::ApplyEditsFailed {
+                buffer: buffer_id,
+                message: err.to_string(),
+            })?;
+        buffer.executor.mark_clean();
+
+        let delta = buffer.executor.take_last_text_delta().map(Arc::new);
+        let line_index = &buffer.executor.editor().line_index;
+        let line_count = line_index.line_count();
+        buffer
+            .executor
+            .editor_mut()
+            .folding_manager
+            .restore_regions(derived_folds, user_folds, line_count);
+        let editor = buffer.executor.editor_mut();
+        editor.refresh_fold_summaries();
+        editor.invalidate_visual_row_index_cache();
+        buffer.last_text_delta = delta.clone();
+        buffer.version = buffer.version.saturating_add(1);
+
+        let line_index = &buffer.executor.editor().line_index;
+        let mut view_ids = Vec::new();
+        for (id, view) in self.views.iter_mut() {
+            if view.buffer != buffer_id {
+                continue;
+            }
+            view.core.cursor_position = clamp_position(line_index, view.core.cursor_position);
+            if let Some(sel) = &mut view.core.selection {
+                clamp_selection(line_index, sel);
+            }
+            for sel in &mut view.core.secondary_selections {
+                clamp_selection(line_index, sel);
+            }
+            view.core.preferred_x_cells = None;
+            view.last_text_delta = delta.clone();
+            view_ids.push(*id);
+        }
+
+        for id in view_ids {
+            let total = self.total_visual_lines_for_view(id)?;
+            let Some(view) = self.views.get_mut(&id) else {
+                continue;
+            };
+            view.scroll_top = view.scroll_top.min(total.saturating_sub(1));
+            Self::notify_view(view, StateChangeType::DocumentModified, delta.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Replace all occurrences of `query` in every open buffer.
+    ///
+    /// - Each changed buffer gets a single undoable step ([`EditCommand::ReplaceAll`]).
+    /// - Buffers without matches are skipped and left untouched.
+    /// - Views keep their cursors/selections (mapped through the edit) and receive a
+    ///   `DocumentModified` notification.
+    /// - Returns `(buffer, replacement_count)` pairs in deterministic `BufferId` order.
+    pub fn replace_all_in_buffers(
+        &mut self,
+        query: &str,
+        replacement: &str,
+        options: SearchOptions,
+    ) -> Result<Vec<(BufferId, usize)>, WorkspaceError> {
+        let mut targets: Vec<BufferId> = Vec::new();
+        for (id, entry) in &self.buffers {
+            let text = entry.executor.editor().get_text();
+            let matches = find_all(&text, query, options).map_err(|err| {
+                WorkspaceError::ApplyEditsFailed {
+                    buffer: *id,
+                    message: err.to_string(),
+                }
+            })?;
+            if !matches.is_empty() {
+                targets.push(*id);
+            }
+        }
+        targets.sort();
+
+        let mut replaced: Vec<(BufferId, usize)> = Vec::new();
+        for buffer_id in targets {
+            let result = self.execute_on_buffer(
+                buffer_id,
+                Command::Edit(EditCommand::ReplaceAll {
+                    query: query.to_string(),
+                    replacement: replacement.to_string(),
+                    options,
+                }),
+            )?;
+            if let CommandResult::ReplaceResult { replaced: count } = result {
+                replaced.push((buffer_id, count));
+            }
+        }
+
+        Ok(replaced)
+    }
+
     /// Apply a set of text edits to multiple open buffers.
     ///
     /// - This is purely in-memory (no file I/O).
@@ -1300,96 +1914,109 @@ impl Workspace {
                 continue;
             }
 
-            let Some(buffer) = self.buffers.get_mut(&buffer_id) else {
-                return Err(WorkspaceError::BufferNotFound(buffer_id));
-            };
+            self.execute_on_buffer(
+                buffer_id,
+                Command::Edit(EditCommand::ApplyTextEdits {
+                    edits: buffer_edits,
+                }),
+            )?;
 
-            let before_line_index = buffer.executor.editor().line_index.clone();
-            let before_char_count = buffer.executor.editor().char_count();
-
-            // Apply without relying on any specific view selection: load a neutral view state.
-            let neutral = ViewCore {
-                cursor_position: Position::new(0, 0),
-                selection: None,
-                secondary_selections: Vec::new(),
-                viewport_width: buffer.executor.editor().viewport_width.max(1),
-                wrap_mode: buffer.executor.editor().layout_engine.wrap_mode(),
-                wrap_indent: buffer.executor.editor().layout_engine.wrap_indent(),
-                tab_width: buffer.executor.editor().layout_engine.tab_width(),
-                tab_key_behavior: buffer.executor.tab_key_behavior(),
-                preferred_x_cells: None,
-            };
-            neutral.apply_to_executor(&mut buffer.executor);
+            applied.push((buffer_id, edit_count));
+        }
+
+        Ok(applied)
+    }
+
+    /// Execute an edit command on a buffer without going through any of its views.
+    ///
+    /// The command runs against a neutral view state; every view of the buffer then has its
+    /// selections mapped through the resulting delta and receives a change notification.
+    fn execute_on_buffer(
+        &mut self,
+        buffer_id: BufferId,
+        command: Command,
+    ) -> Result<CommandResult, WorkspaceError> {
+        let Some(buffer) = self.buffers.get_mut(&buffer_id) else {
+            return Err(WorkspaceError::BufferNotFound(buffer_id));
+        };
 
+        let before_line_index = buffer.executor.editor().line_index.clone();
+        let before_char_count = buffer.executor.editor().char_count();
+
+        // Apply without relying on any specific view selection: load a neutral view state.
+        let neutral = ViewCore {
+            cursor_position: Position::new(0, 0),
+            selection: None,
+            secondary_selections: Vec::new(),
+            viewport_width: buffer.executor.editor().viewport_width.max(1),
+            wrap_mode: buffer.executor.editor().layout_engine.wrap_mode(),
+            wrap_indent: buffer.executor.editor().layout_engine.wrap_indent(),
+            tab_width: buffer.executor.editor().layout_engine.tab_width(),
+            tab_key_behavior: buffer.executor.tab_key_behavior(),
+            preferred_x_cells: None,
+        };
+        neutral.apply_to_executor(&mut buffer.executor);
+
+        let result =
             buffer
                 .executor
-                .execute(Command::Edit(EditCommand::ApplyTextEdits {
-                    edits: buffer_edits,
-                }))
+                .execute(command)
                 .map_err(|err| WorkspaceError::ApplyEditsFailed {
                     buffer: buffer_id,
                     message: err.to_string(),
                 })?;
 
-            let delta = buffer.executor.take_last_text_delta().map(Arc::new);
-            let after_char_count = buffer.executor.editor().char_count();
-            let changed = delta.is_some() || after_char_count != before_char_count;
-
-            if changed {
-                if let Some(ref delta_arc) = delta {
-                    buffer.last_text_delta = Some(delta_arc.clone());
-                    let new_index = &buffer.executor.editor().line_index;
-                    for view in self.views.values_mut() {
-                        if view.buffer != buffer_id {
-                            continue;
-                        }
+        let delta = buffer.executor.take_last_text_delta().map(Arc::new);
+        let after_char_count = buffer.executor.editor().char_count();
+        let changed = delta.is_some() || after_char_count != before_char_count;
 
-                        view.last_text_delta = Some(delta_arc.clone());
+        if changed {
+            if let Some(ref delta_arc) = delta {
+                buffer.last_text_delta = Some(delta_arc.clone());
+                let new_index = &buffer.executor.editor().line_index;
+                for view in self.views.values_mut() {
+                    if view.buffer != buffer_id {
+                        continue;
+                    }
+
+                    view.last_text_delta = Some(delta_arc.clone());
 
-                        view.core.cursor_position = apply_position_delta(
+                    view.core.cursor_position = apply_position_delta(
+                        &before_line_index,
+                        new_index,
+                        view.core.cursor_position,
+                        delta_arc,
+                    );
+                    if let Some(ref sel) = view.core.selection {
+                        view.core.selection = Some(apply_selection_delta(
                             &before_line_index,
                             new_index,
-                            view.core.cursor_position,
+                            sel,
                             delta_arc,
-                        );
-                        if let Some(ref sel) = view.core.selection {
-                            view.core.selection = Some(apply_selection_delta(
-                                &before_line_index,
-                                new_index,
-                                sel,
-                                delta_arc,
-                            ));
-                        }
-                        for sel in &mut view.core.secondary_selections {
-                            *sel = apply_selection_delta(
-                                &before_line_index,
-                                new_index,
-                                sel,
-                                delta_arc,
-                            );
-                        }
-
-                        Self::notify_view(
-                            view,
-                            StateChangeType::DocumentModified,
-                            Some(delta_arc.clone()),
-                        );
+                        ));
                     }
-                } else {
-                    buffer.last_text_delta = None;
-                    for view in self.views.values_mut() {
-                        if view.buffer == buffer_id {
-                            Self::notify_view(view, StateChangeType::DocumentModified, None);
-                        }
+                    for sel in &mut view.core.secondary_selections {
+                        *sel = apply_selection_delta(&before_line_index, new_index, sel, delta_arc);
                     }
-                }
 
-                buffer.version = buffer.version.saturating_add(1);
+                    Self::notify_view(
+                        view,
+                        StateChangeType::DocumentModified,
+                        Some(delta_arc.clone()),
+                    );
+                }
+            } else {
+                buffer.last_text_delta = None;
+                for view in self.views.values_mut() {
+                    if view.buffer == buffer_id {
+                        Self::notify_view(view, StateChangeType::DocumentModified, None);
+                    }
+                }
             }
 
-            applied.push((buffer_id, edit_count));
+            buffer.version = buffer.version.saturating_add(1);
         }
 
-        Ok(applied)
+        Ok(result)
     }
 }
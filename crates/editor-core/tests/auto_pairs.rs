@@ -0,0 +1,176 @@
+use editor_core::{
+    Command, CursorCommand, EditCommand, EditorStateManager, Position, Selection,
+    SelectionDirection,
+};
+
+const PAIRS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')];
+
+fn type_char(manager: &mut EditorStateManager, ch: char) {
+    manager
+        .execute(Command::Edit(EditCommand::InsertTextWithAutoPairs {
+            text: ch.to_string(),
+            pairs: PAIRS.to_vec(),
+        }))
+        .unwrap();
+}
+
+fn backspace(manager: &mut EditorStateManager) {
+    manager
+        .execute(Command::Edit(EditCommand::DeletePairBackspace {
+            pairs: PAIRS.to_vec(),
+        }))
+        .unwrap();
+}
+
+fn move_to(manager: &mut EditorStateManager, line: usize, column: usize) {
+    manager
+        .execute(Command::Cursor(CursorCommand::MoveTo { line, column }))
+        .unwrap();
+}
+
+fn caret(manager: &EditorStateManager) -> (usize, usize) {
+    let pos = manager.editor().cursor_position();
+    (pos.line, pos.column)
+}
+
+#[test]
+fn test_nested_pairs_and_type_over() {
+    let mut manager = EditorStateManager::new("", 80);
+
+    for ch in ['(', '[', '{'] {
+        type_char(&mut manager, ch);
+    }
+    assert_eq!(manager.editor().get_text(), "([{}])");
+    assert_eq!(caret(&manager), (0, 3));
+
+    type_char(&mut manager, 'x');
+    for ch in ['}', ']', ')'] {
+        type_char(&mut manager, ch);
+    }
+    assert_eq!(manager.editor().get_text(), "([{x}])");
+    assert_eq!(caret(&manager), (0, 7));
+
+    // Type-over at the end of a line; a closer with nothing to type over is inserted.
+    let mut manager = EditorStateManager::new("f()\nnext", 80);
+    move_to(&mut manager, 0, 2);
+    type_char(&mut manager, ')');
+    assert_eq!(manager.editor().get_text(), "f()\nnext");
+    assert_eq!(caret(&manager), (0, 3));
+    type_char(&mut manager, ')');
+    assert_eq!(manager.editor().get_text(), "f())\nnext");
+
+    // No auto-close right before a word.
+    move_to(&mut manager, 1, 0);
+    type_char(&mut manager, '(');
+    assert_eq!(manager.editor().get_text(), "f())\n(next");
+}
+
+#[test]
+fn test_quotes_pair_and_type_over() {
+    let mut manager = EditorStateManager::new("let s = ;", 80);
+    move_to(&mut manager, 0, 8);
+
+    type_char(&mut manager, '"');
+    assert_eq!(manager.editor().get_text(), "let s = \"\";");
+    assert_eq!(caret(&manager), (0, 9));
+
+    type_char(&mut manager, 'a');
+    type_char(&mut manager, '"');
+    assert_eq!(manager.editor().get_text(), "let s = \"a\";");
+    assert_eq!(caret(&manager), (0, 11));
+
+    // A quote typed right after a word char is not paired.
+    let mut manager = EditorStateManager::new("don", 80);
+    move_to(&mut manager, 0, 3);
+    type_char(&mut manager, '"');
+    assert_eq!(manager.editor().get_text(), "don\"");
+}
+
+#[test]
+fn test_wrap_selection_and_pair_backspace() {
+    let mut manager = EditorStateManager::new("call arg", 80);
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelection {
+            start: Position::new(0, 5),
+            end: Position::new(0, 8),
+        }))
+        .unwrap();
+
+    type_char(&mut manager, '(');
+    assert_eq!(manager.editor().get_text(), "call (arg)");
+    let selection = manager.editor().selection().cloned().unwrap();
+    assert_eq!(
+        (selection.start, selection.end),
+        (Position::new(0, 6), Position::new(0, 9))
+    );
+
+    // Touching selections are wrapped independently.
+    let mut manager = EditorStateManager::new("ab", 80);
+    let range = |start, end| Selection {
+        start: Position::new(0, start),
+        end: Position::new(0, end),
+        direction: SelectionDirection::Forward,
+    };
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![range(0, 1), range(1, 2)],
+            primary_index: 0,
+        }))
+        .unwrap();
+    type_char(&mut manager, '[');
+    assert_eq!(manager.editor().get_text(), "[a][b]");
+
+    // Backspace inside an empty pair removes both chars; elsewhere it's a plain backspace.
+    let mut manager = EditorStateManager::new("", 80);
+    type_char(&mut manager, '[');
+    assert_eq!(manager.editor().get_text(), "[]");
+    backspace(&mut manager);
+    assert_eq!(manager.editor().get_text(), "");
+
+    let mut manager = EditorStateManager::new("(a)", 80);
+    move_to(&mut manager, 0, 2);
+    backspace(&mut manager);
+    assert_eq!(manager.editor().get_text(), "()");
+    backspace(&mut manager);
+    assert_eq!(manager.editor().get_text(), "");
+}
+
+#[test]
+fn test_multi_caret_auto_pairs_are_one_undo_step() {
+    let mut manager = EditorStateManager::new("a\nb)\nc", 80);
+    let caret_at = |line, column| Selection {
+        start: Position::new(line, column),
+        end: Position::new(line, column),
+        direction: SelectionDirection::Forward,
+    };
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![caret_at(0, 1), caret_at(1, 1), caret_at(2, 1)],
+            primary_index: 0,
+        }))
+        .unwrap();
+
+    // The middle caret types over its `)`; the others insert a pair.
+    type_char(&mut manager, ')');
+    assert_eq!(manager.editor().get_text(), "a)\nb)\nc)");
+    type_char(&mut manager, '(');
+    assert_eq!(manager.editor().get_text(), "a)()\nb)()\nc)()");
+
+    let mut carets: Vec<(usize, usize)> = manager
+        .editor()
+        .secondary_selections()
+        .iter()
+        .map(|s| (s.end.line, s.end.column))
+        .collect();
+    carets.push(caret(&manager));
+    carets.sort();
+    assert_eq!(carets, vec![(0, 3), (1, 3), (2, 3)]);
+
+    manager
+        .execute(Command::Edit(EditCommand::EndUndoGroup))
+        .unwrap();
+    backspace(&mut manager);
+    assert_eq!(manager.editor().get_text(), "a)\nb)\nc)");
+    manager.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(manager.editor().get_text(), "a)()\nb)()\nc)()");
+}
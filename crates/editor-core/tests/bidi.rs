@@ -0,0 +1,79 @@
+use editor_core::{
+    Command, CursorCommand, EditorStateManager, Position, TextDirection, ViewCommand,
+    line_direction,
+};
+
+#[test]
+fn test_line_direction_follows_first_letter() {
+    assert_eq!(line_direction("שלום עולם"), TextDirection::Rtl);
+    assert_eq!(line_direction("  12. مرحبا"), TextDirection::Rtl);
+    assert_eq!(line_direction("let x = \"שלום\";"), TextDirection::Ltr);
+    assert_eq!(line_direction("123 + 4"), TextDirection::Ltr);
+    assert_eq!(line_direction(""), TextDirection::Ltr);
+}
+
+#[test]
+fn test_caret_x_on_rtl_line_is_mirrored() {
+    let state = EditorStateManager::new("abc\nשלום\n", 80);
+
+    // Column 0 is at the right edge of the text, the line end at the left.
+    let xs: Vec<usize> = (0..=4)
+        .map(|column| state.logical_position_to_visual(1, column).unwrap().1)
+        .collect();
+    assert_eq!(xs, vec![4, 3, 2, 1, 0]);
+    assert_eq!(state.logical_position_to_visual(0, 1), Some((0, 1)));
+
+    for column in 0..=4 {
+        let (row, x) = state.logical_position_to_visual(1, column).unwrap();
+        assert_eq!(
+            state.visual_position_to_logical(row, x),
+            Some(Position::new(1, column))
+        );
+    }
+    // Past the right edge is before the first character.
+    assert_eq!(
+        state.visual_position_to_logical(1, 10),
+        Some(Position::new(1, 0))
+    );
+
+    let grid = state.get_viewport_content_styled(1, 1);
+    let line = &grid.lines[0];
+    assert_eq!(line.direction, TextDirection::Rtl);
+    let text: String = line.cells.iter().map(|cell| cell.ch).collect();
+    assert_eq!(text, "םולש");
+}
+
+#[test]
+fn test_wrapped_rtl_segments_are_mirrored_separately() {
+    let mut state = EditorStateManager::new("אבגדהו", 4);
+    state
+        .execute(Command::View(ViewCommand::SetViewportWidth { width: 4 }))
+        .unwrap();
+
+    // Segments "אבגד" and "הו".
+    assert_eq!(state.logical_position_to_visual(0, 1), Some((0, 3)));
+    assert_eq!(state.logical_position_to_visual(0, 4), Some((1, 2)));
+    assert_eq!(state.logical_position_to_visual(0, 6), Some((1, 0)));
+
+    let grid = state.get_viewport_content_styled(0, 2);
+    let rows: Vec<String> = grid
+        .lines
+        .iter()
+        .map(|line| line.cells.iter().map(|cell| cell.ch).collect())
+        .collect();
+    assert_eq!(rows, vec!["דגבא".to_string(), "וה".to_string()]);
+
+    // Visual-row movement keeps the caret's screen x.
+    state
+        .execute(Command::Cursor(CursorCommand::MoveTo {
+            line: 0,
+            column: 1,
+        }))
+        .unwrap();
+    state
+        .execute(Command::Cursor(CursorCommand::MoveVisualBy {
+            delta_rows: 1,
+        }))
+        .unwrap();
+    assert_eq!(state.editor().cursor_position(), Position::new(0, 4));
+}
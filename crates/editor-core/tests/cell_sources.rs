@@ -0,0 +1,98 @@
+use editor_core::{
+    CellSource, Command, EditorStateManager, HeadlessLine, SnapshotGenerator, StyleCommand,
+    ViewCommand, WrapIndent,
+};
+
+fn sources(line: &HeadlessLine) -> Vec<CellSource> {
+    line.cells.iter().map(|cell| cell.source).collect()
+}
+
+fn doc(offset: usize, column: usize) -> CellSource {
+    CellSource::Document { offset, column }
+}
+
+#[test]
+fn test_cell_sources_across_tabs_and_wide_chars() {
+    // Tab width 4: `a` x=0, tab x=1..4, `b` x=4, `你` x=5..7, `c` x=7.
+    let state = EditorStateManager::new("xy\na\tb你c", 80);
+    let grid = state.get_viewport_content_styled(0, 2);
+    let line = &grid.lines[1];
+
+    assert_eq!(
+        sources(line),
+        vec![doc(3, 0), doc(4, 1), doc(5, 2), doc(6, 3), doc(7, 4)]
+    );
+    assert_eq!(
+        line.cells.iter().map(|cell| cell.width).collect::<Vec<_>>(),
+        vec![1, 3, 1, 2, 1]
+    );
+
+    // Hit-testing by visual column lands on the cell covering it.
+    let hit = |x: usize| line.cell_at_x(x).map(|cell| cell.source);
+    assert_eq!(hit(0), Some(doc(3, 0)));
+    assert_eq!(hit(3), Some(doc(4, 1)));
+    assert_eq!(hit(4), Some(doc(5, 2)));
+    assert_eq!(hit(6), Some(doc(6, 3)));
+    assert_eq!(hit(7), Some(doc(7, 4)));
+    assert_eq!(hit(8), None);
+
+    // The unstyled generator records the same sources.
+    let generator = SnapshotGenerator::from_text("xy\na\tb你c", 80);
+    assert_eq!(
+        sources(&generator.get_headless_grid(1, 1).lines[0]),
+        sources(line)
+    );
+}
+
+#[test]
+fn test_cell_sources_of_wrapped_segments() {
+    // Viewport width 6 with the line's indent repeated: "    ab" / "    cd" / ...
+    let mut state = EditorStateManager::new("    abcdefgh", 6);
+    state
+        .execute(Command::View(ViewCommand::SetWrapIndent {
+            indent: WrapIndent::SameAsLineIndent,
+        }))
+        .unwrap();
+    let grid = state.get_viewport_content_styled(0, 4);
+
+    assert_eq!(
+        sources(&grid.lines[0]),
+        vec![
+            doc(0, 0),
+            doc(1, 1),
+            doc(2, 2),
+            doc(3, 3),
+            doc(4, 4),
+            doc(5, 5)
+        ]
+    );
+    let mut wrapped = vec![CellSource::WrapIndent; 4];
+    wrapped.extend([doc(8, 8), doc(9, 9)]);
+    assert_eq!(sources(&grid.lines[2]), wrapped);
+}
+
+#[test]
+fn test_cell_sources_of_fold_placeholder() {
+    let mut state = EditorStateManager::new("fn f() {\n    x\n}\ny", 80);
+    state
+        .execute(Command::Style(StyleCommand::Fold {
+            start_line: 0,
+            end_line: 2,
+        }))
+        .unwrap();
+    let grid = state.get_viewport_content_styled(0, 2);
+    let line = &grid.lines[0];
+    assert!(line.is_fold_placeholder_appended);
+
+    let text_cells = "fn f() {".chars().count();
+    assert_eq!(line.cells[text_cells - 1].source, doc(7, 7));
+    assert!(line.cells.len() > text_cells);
+    assert!(
+        line.cells[text_cells..]
+            .iter()
+            .all(|cell| cell.source == CellSource::FoldPlaceholder)
+    );
+
+    // The next visible row is line 3, past the folded body.
+    assert_eq!(sources(&grid.lines[1]), vec![doc(17, 0)]);
+}
@@ -0,0 +1,170 @@
+use editor_core::{
+    Command, CompletionAcceptMode, CursorCommand, EditCommand, EditorStateManager, Position,
+    Selection, SelectionDirection, TextEditSpec, completion_edit_at_caret,
+    completion_edits_at_carets, fuzzy_match_score,
+};
+
+fn at(text: &str, line: usize, column: usize) -> EditorStateManager {
+    let mut manager = EditorStateManager::new(text, 80);
+    manager
+        .execute(Command::Cursor(CursorCommand::MoveTo { line, column }))
+        .unwrap();
+    manager
+}
+
+fn accept(
+    manager: &mut EditorStateManager,
+    new_text: &str,
+    mode: CompletionAcceptMode,
+) -> Vec<TextEditSpec> {
+    let edits = completion_edits_at_carets(manager.editor(), new_text, mode);
+    manager
+        .execute(Command::Edit(EditCommand::ApplyTextEdits {
+            edits: edits.clone(),
+        }))
+        .unwrap();
+    edits
+}
+
+fn caret(line: usize, column: usize) -> Selection {
+    Selection {
+        start: Position::new(line, column),
+        end: Position::new(line, column),
+        direction: SelectionDirection::Forward,
+    }
+}
+
+#[test]
+fn test_prefix_only_caret_is_identical_in_both_modes() {
+    for mode in [CompletionAcceptMode::Insert, CompletionAcceptMode::Replace] {
+        let manager = at("let x = pri;\n", 0, 11);
+        let edit = completion_edit_at_caret(manager.editor(), "println", mode);
+        assert_eq!(
+            edit,
+            TextEditSpec {
+                start: 8,
+                end: 11,
+                text: "println".to_string()
+            }
+        );
+    }
+}
+
+#[test]
+fn test_mid_word_caret_differs_by_mode() {
+    let mut manager = at("foo.get_value()\n", 0, 7);
+    let insert =
+        completion_edit_at_caret(manager.editor(), "get_name", CompletionAcceptMode::Insert);
+    assert_eq!((insert.start, insert.end), (4, 7));
+    let replace =
+        completion_edit_at_caret(manager.editor(), "get_name", CompletionAcceptMode::Replace);
+    assert_eq!((replace.start, replace.end), (4, 13));
+
+    accept(&mut manager, "get_name", CompletionAcceptMode::Insert);
+    assert_eq!(manager.editor().get_text(), "foo.get_name_value()\n");
+    manager.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    accept(&mut manager, "get_name", CompletionAcceptMode::Replace);
+    assert_eq!(manager.editor().get_text(), "foo.get_name()\n");
+}
+
+#[test]
+fn test_word_edges() {
+    // Caret at word end: nothing to consume after it.
+    let manager = at("alpha beta", 0, 5);
+    for mode in [CompletionAcceptMode::Insert, CompletionAcceptMode::Replace] {
+        let edit = completion_edit_at_caret(manager.editor(), "alphabet", mode);
+        assert_eq!((edit.start, edit.end), (0, 5));
+    }
+
+    // Caret at word start: Insert is a pure insertion, Replace swaps the following word.
+    let manager = at("alpha beta", 0, 6);
+    let insert = completion_edit_at_caret(manager.editor(), "gamma", CompletionAcceptMode::Insert);
+    assert_eq!((insert.start, insert.end), (6, 6));
+    let replace =
+        completion_edit_at_caret(manager.editor(), "gamma", CompletionAcceptMode::Replace);
+    assert_eq!((replace.start, replace.end), (6, 10));
+
+    // Non-word neighbours and non-ASCII identifiers.
+    let manager = at("(x) + größe", 0, 3);
+    let edit = completion_edit_at_caret(manager.editor(), "y", CompletionAcceptMode::Replace);
+    assert_eq!((edit.start, edit.end), (3, 3));
+    let manager = at("(x) + größe", 0, 8);
+    let edit = completion_edit_at_caret(manager.editor(), "größer", CompletionAcceptMode::Replace);
+    assert_eq!((edit.start, edit.end), (6, 11));
+
+    // A non-empty selection is replaced as-is.
+    let mut manager = EditorStateManager::new("alpha beta", 80);
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelection {
+            start: Position::new(0, 8),
+            end: Position::new(0, 2),
+        }))
+        .unwrap();
+    let edit = completion_edit_at_caret(manager.editor(), "z", CompletionAcceptMode::Insert);
+    assert_eq!((edit.start, edit.end), (2, 8));
+}
+
+#[test]
+fn test_multi_caret_edits_in_both_modes() {
+    let text = "vec.pu\nvec.push_back\nvec.\n";
+    let carets = vec![caret(0, 6), caret(1, 6), caret(2, 4)];
+
+    for (mode, expected) in [
+        (
+            CompletionAcceptMode::Insert,
+            "vec.push\nvec.pushsh_back\nvec.push\n",
+        ),
+        (
+            CompletionAcceptMode::Replace,
+            "vec.push\nvec.push\nvec.push\n",
+        ),
+    ] {
+        let mut manager = EditorStateManager::new(text, 80);
+        manager
+            .execute(Command::Cursor(CursorCommand::SetSelections {
+                selections: carets.clone(),
+                primary_index: 1,
+            }))
+            .unwrap();
+
+        let edits = accept(&mut manager, "push", mode);
+        assert_eq!(edits.len(), 3);
+        assert!(edits.windows(2).all(|w| w[0].end <= w[1].start));
+        assert_eq!(manager.editor().get_text(), expected, "{mode:?}");
+    }
+}
+
+#[test]
+fn test_multi_caret_in_same_word_yields_one_edit() {
+    let mut manager = EditorStateManager::new("abcdef", 80);
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![caret(0, 2), caret(0, 4)],
+            primary_index: 0,
+        }))
+        .unwrap();
+
+    let edits = accept(&mut manager, "xyz", CompletionAcceptMode::Replace);
+    assert_eq!(edits.len(), 1);
+    assert_eq!(manager.editor().get_text(), "xyz");
+}
+
+#[test]
+fn test_fuzzy_match_score_prefers_prefixes_and_word_starts() {
+    assert_eq!(fuzzy_match_score("", "anything"), Some(0));
+    assert_eq!(fuzzy_match_score("xyz", "println"), None);
+    assert_eq!(fuzzy_match_score("lnp", "println"), None);
+
+    let prefix = fuzzy_match_score("pri", "println").unwrap();
+    let scattered = fuzzy_match_score("pri", "parse_into").unwrap();
+    assert!(prefix > scattered);
+
+    // Word-start matches (snake_case and camelCase) beat mid-word ones.
+    let snake = fuzzy_match_score("gv", "get_value").unwrap();
+    let camel = fuzzy_match_score("gv", "getValue").unwrap();
+    let mid = fuzzy_match_score("gv", "grove").unwrap();
+    assert!(snake > mid && camel > mid);
+
+    // Matching is case-insensitive, but exact case scores higher.
+    assert!(fuzzy_match_score("Vec", "Vec").unwrap() > fuzzy_match_score("vec", "Vec").unwrap());
+}
@@ -1,6 +1,7 @@
 use editor_core::{
-    ComposedCellSource, ComposedLineKind, Decoration, DecorationKind, DecorationLayerId,
-    DecorationPlacement, DecorationRange, EditorStateManager, ProcessingEdit,
+    Command, ComposedCellSource, ComposedGrid, ComposedLineKind, CursorCommand, Decoration,
+    DecorationKind, DecorationLayerId, DecorationPlacement, DecorationRange, EditorStateManager,
+    Position, ProcessingEdit, Selection, SelectionDirection, SnapshotOptions, StyleCommand,
 };
 
 fn line_to_string(line: &editor_core::ComposedLine) -> String {
@@ -126,3 +127,110 @@ fn test_composed_snapshot_injects_above_line_virtual_text() {
     );
     assert_eq!(line_to_string(&grid.lines[3]), "");
 }
+
+fn inlay_hint(offset: usize, placement: DecorationPlacement, text: &str) -> ProcessingEdit {
+    ProcessingEdit::ReplaceDecorations {
+        layer: DecorationLayerId::INLAY_HINTS,
+        decorations: vec![Decoration {
+            range: DecorationRange::new(offset, offset),
+            placement,
+            kind: DecorationKind::InlayHint,
+            text: Some(text.to_string()),
+            styles: Vec::new(),
+            tooltip: None,
+            data_json: None,
+        }],
+    }
+}
+
+fn select(manager: &mut EditorStateManager, selections: &[(Position, Position)]) {
+    let selections = selections
+        .iter()
+        .map(|&(start, end)| Selection {
+            start,
+            end,
+            direction: SelectionDirection::Forward,
+        })
+        .collect();
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections,
+            primary_index: 0,
+        }))
+        .unwrap();
+}
+
+fn composed_with_selections(manager: &EditorStateManager, count: usize) -> ComposedGrid {
+    let options = SnapshotOptions {
+        include_selections: true,
+        ..SnapshotOptions::default()
+    };
+    manager.get_viewport_content_composed_with_options(0, count, &options)
+}
+
+#[test]
+fn test_composed_caret_after_inlay_hint_anchor() {
+    let mut manager = EditorStateManager::new("let x = 1;\n", 80);
+    manager.apply_processing_edits(vec![inlay_hint(5, DecorationPlacement::After, ": i32")]);
+    select(
+        &mut manager,
+        &[
+            (Position::new(0, 2), Position::new(0, 2)),
+            (Position::new(0, 5), Position::new(0, 5)),
+        ],
+    );
+
+    let grid = composed_with_selections(&manager, 1);
+    let line = &grid.lines[0];
+    assert_eq!(line_to_string(line), "let x: i32 = 1;");
+    // The caret at the hint's anchor is drawn after the hint, before the document cell.
+    assert_eq!(line.caret_columns, vec![2, 10]);
+    assert!(line.selection_columns.is_empty());
+
+    // Without the option no caret information is reported.
+    let plain = manager.get_viewport_content_composed(0, 1);
+    assert!(plain.lines[0].caret_columns.is_empty());
+}
+
+#[test]
+fn test_composed_selection_skips_inline_hint() {
+    let mut manager = EditorStateManager::new("abcd", 80);
+    manager.apply_processing_edits(vec![inlay_hint(2, DecorationPlacement::Before, "p:")]);
+    select(&mut manager, &[(Position::new(0, 1), Position::new(0, 3))]);
+
+    let grid = composed_with_selections(&manager, 1);
+    let line = &grid.lines[0];
+    assert_eq!(line_to_string(line), "abp:cd");
+    // `b` and `c` are selected; the hint cells between them are not.
+    assert_eq!(line.selection_columns, vec![1..2, 4..5]);
+    assert_eq!(line.caret_columns, vec![5]);
+}
+
+#[test]
+fn test_composed_caret_on_fold_placeholder_line() {
+    let mut manager = EditorStateManager::new("fn f() {\n    x\n}\ny", 80);
+    manager
+        .execute(Command::Style(StyleCommand::Fold {
+            start_line: 0,
+            end_line: 2,
+        }))
+        .unwrap();
+    select(&mut manager, &[(Position::new(0, 8), Position::new(0, 8))]);
+
+    let grid = composed_with_selections(&manager, 2);
+    let header = &grid.lines[0];
+    assert_eq!(line_to_string(header), "fn f() { [...]");
+    // The end-of-line caret sits before the placeholder.
+    assert_eq!(header.caret_columns, vec![8]);
+
+    // A selection across the folded body covers the header text but never the placeholder.
+    select(&mut manager, &[(Position::new(0, 3), Position::new(3, 1))]);
+    let grid = composed_with_selections(&manager, 2);
+    assert!(grid.lines[0].caret_columns.is_empty());
+    assert_eq!(grid.lines[0].selection_columns, vec![3..8]);
+
+    let last = &grid.lines[1];
+    assert_eq!(line_to_string(last), "y");
+    assert_eq!(last.caret_columns, vec![1]);
+    assert_eq!(last.selection_columns, vec![0..1]);
+}
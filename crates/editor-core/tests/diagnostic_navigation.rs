@@ -0,0 +1,163 @@
+use editor_core::{
+    Command, CommandResult, CursorCommand, Diagnostic, DiagnosticRange, DiagnosticSeverity,
+    EditorStateManager, Position, ProcessingEdit,
+};
+
+fn diagnostic(start: usize, end: usize, severity: Option<DiagnosticSeverity>) -> Diagnostic {
+    Diagnostic {
+        range: DiagnosticRange::new(start, end),
+        severity,
+        code: None,
+        source: None,
+        provider: None,
+        message: String::new(),
+        code_description_href: None,
+        tags: Vec::new(),
+        related: Vec::new(),
+        related_information_json: None,
+        data_json: None,
+    }
+}
+
+fn manager_with(diagnostics: Vec<Diagnostic>) -> EditorStateManager {
+    // Line starts: 0, 8, 16, 24.
+    let mut manager = EditorStateManager::new("line 00\nline 01\nline 02\nline 03\n", 80);
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceDiagnostics { diagnostics }]);
+    manager
+}
+
+fn go(
+    manager: &mut EditorStateManager,
+    forward: bool,
+    min_severity: DiagnosticSeverity,
+) -> Option<Position> {
+    let command = if forward {
+        CursorCommand::GoToNextDiagnostic { min_severity }
+    } else {
+        CursorCommand::GoToPrevDiagnostic { min_severity }
+    };
+    match manager.execute(Command::Cursor(command)).unwrap() {
+        CommandResult::Position(position) => Some(position),
+        CommandResult::SearchNotFound => None,
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn test_next_and_prev_wrap_around() {
+    let mut manager = manager_with(vec![
+        diagnostic(21, 23, Some(DiagnosticSeverity::Warning)),
+        diagnostic(5, 7, Some(DiagnosticSeverity::Error)),
+        diagnostic(13, 14, Some(DiagnosticSeverity::Hint)),
+    ]);
+    let hint = DiagnosticSeverity::Hint;
+
+    assert_eq!(go(&mut manager, true, hint), Some(Position::new(0, 5)));
+    assert_eq!(go(&mut manager, true, hint), Some(Position::new(1, 5)));
+    assert_eq!(go(&mut manager, true, hint), Some(Position::new(2, 5)));
+    assert_eq!(go(&mut manager, true, hint), Some(Position::new(0, 5)));
+
+    assert_eq!(go(&mut manager, false, hint), Some(Position::new(2, 5)));
+    assert_eq!(go(&mut manager, false, hint), Some(Position::new(1, 5)));
+    assert_eq!(manager.editor().cursor_position(), Position::new(1, 5));
+}
+
+#[test]
+fn test_min_severity_filters_stops() {
+    let mut manager = manager_with(vec![
+        diagnostic(5, 7, Some(DiagnosticSeverity::Information)),
+        diagnostic(13, 14, None),
+        diagnostic(21, 23, Some(DiagnosticSeverity::Warning)),
+    ]);
+
+    // Missing severity is treated as an error.
+    let error = DiagnosticSeverity::Error;
+    assert_eq!(go(&mut manager, true, error), Some(Position::new(1, 5)));
+    assert_eq!(go(&mut manager, true, error), Some(Position::new(1, 5)));
+
+    let warning = DiagnosticSeverity::Warning;
+    assert_eq!(go(&mut manager, true, warning), Some(Position::new(2, 5)));
+    assert_eq!(go(&mut manager, false, warning), Some(Position::new(1, 5)));
+}
+
+#[test]
+fn test_shared_start_is_a_single_stop_and_clears_selection() {
+    let mut manager = manager_with(vec![
+        diagnostic(8, 12, Some(DiagnosticSeverity::Hint)),
+        diagnostic(8, 9, Some(DiagnosticSeverity::Error)),
+        diagnostic(16, 18, Some(DiagnosticSeverity::Error)),
+    ]);
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelection {
+            start: Position::new(0, 0),
+            end: Position::new(0, 3),
+        }))
+        .unwrap();
+
+    let hint = DiagnosticSeverity::Hint;
+    assert_eq!(go(&mut manager, true, hint), Some(Position::new(1, 0)));
+    assert!(manager.editor().selection().is_none());
+    assert_eq!(go(&mut manager, true, hint), Some(Position::new(2, 0)));
+}
+
+#[test]
+fn test_no_qualifying_diagnostic_is_not_found() {
+    let mut manager = manager_with(Vec::new());
+    assert_eq!(go(&mut manager, true, DiagnosticSeverity::Hint), None);
+
+    let mut manager = manager_with(vec![diagnostic(5, 7, Some(DiagnosticSeverity::Hint))]);
+    assert_eq!(go(&mut manager, false, DiagnosticSeverity::Warning), None);
+    assert_eq!(manager.editor().cursor_position(), Position::new(0, 0));
+}
+
+#[test]
+fn test_diagnostic_queries_wrap_without_moving_the_cursor() {
+    let manager = manager_with(vec![
+        diagnostic(8, 12, Some(DiagnosticSeverity::Warning)),
+        diagnostic(10, 11, Some(DiagnosticSeverity::Hint)),
+        diagnostic(16, 18, None),
+        diagnostic(24, 26, Some(DiagnosticSeverity::Information)),
+    ]);
+
+    let starts =
+        |diags: Vec<&Diagnostic>| -> Vec<usize> { diags.iter().map(|d| d.range.start).collect() };
+    assert_eq!(starts(manager.diagnostics_at_offset(10)), vec![8, 10]);
+    assert_eq!(starts(manager.diagnostics_at_offset(12)), vec![8]);
+    assert!(manager.diagnostics_at_offset(13).is_empty());
+
+    let warning = DiagnosticSeverity::Warning;
+    let next = |offset| {
+        manager
+            .next_diagnostic(offset, warning)
+            .map(|d| d.range.start)
+    };
+    let prev = |offset| {
+        manager
+            .prev_diagnostic(offset, warning)
+            .map(|d| d.range.start)
+    };
+    assert_eq!(next(8), Some(16));
+    // Past the last qualifying diagnostic, the search wraps to the first one.
+    assert_eq!(next(16), Some(8));
+    assert_eq!(prev(8), Some(16));
+    assert_eq!(prev(30), Some(16));
+    assert_eq!(
+        manager
+            .next_diagnostic(16, DiagnosticSeverity::Hint)
+            .map(|d| d.range.start),
+        Some(24)
+    );
+    assert_eq!(manager.editor().cursor_position(), Position::new(0, 0));
+
+    let state = manager.get_diagnostics_state();
+    assert_eq!(state.diagnostics_count, 4);
+    assert_eq!(
+        (
+            state.error_count,
+            state.warning_count,
+            state.information_count,
+            state.hint_count
+        ),
+        (1, 1, 1, 1)
+    );
+}
@@ -0,0 +1,212 @@
+use editor_core::{
+    DIAGNOSTIC_DEPRECATED_STYLE_ID, DIAGNOSTIC_ERROR_STYLE_ID, DIAGNOSTIC_HINT_STYLE_ID,
+    DIAGNOSTIC_UNNECESSARY_STYLE_ID, DIAGNOSTIC_WARNING_STYLE_ID, Decoration, DecorationKind,
+    DecorationLayerId, DecorationPlacement, DecorationRange, Diagnostic, DiagnosticRange,
+    DiagnosticSeverity, DiagnosticTag, EditorStateManager, ProcessingEdit, UnderlineStyle,
+    diagnostic_underline_decorations,
+};
+
+fn diagnostic(start: usize, end: usize, severity: Option<DiagnosticSeverity>) -> Diagnostic {
+    Diagnostic {
+        range: DiagnosticRange::new(start, end),
+        severity,
+        code: None,
+        source: None,
+        provider: None,
+        message: format!("{start}..{end}"),
+        code_description_href: None,
+        tags: Vec::new(),
+        related: Vec::new(),
+        related_information_json: None,
+        data_json: None,
+    }
+}
+
+#[test]
+fn test_overlapping_diagnostics_use_highest_severity() {
+    let decorations = diagnostic_underline_decorations(&[
+        diagnostic(0, 10, Some(DiagnosticSeverity::Hint)),
+        diagnostic(4, 6, Some(DiagnosticSeverity::Error)),
+        diagnostic(5, 8, Some(DiagnosticSeverity::Warning)),
+        diagnostic(12, 12, Some(DiagnosticSeverity::Error)),
+    ]);
+
+    let spans: Vec<(DecorationRange, Vec<u32>)> = decorations
+        .iter()
+        .map(|d| (d.range, d.styles.clone()))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (DecorationRange::new(0, 4), vec![DIAGNOSTIC_HINT_STYLE_ID]),
+            (DecorationRange::new(4, 6), vec![DIAGNOSTIC_ERROR_STYLE_ID]),
+            (
+                DecorationRange::new(6, 8),
+                vec![DIAGNOSTIC_WARNING_STYLE_ID]
+            ),
+            (DecorationRange::new(8, 10), vec![DIAGNOSTIC_HINT_STYLE_ID]),
+        ]
+    );
+    assert!(decorations.iter().all(|d| d.kind
+        == DecorationKind::Underline {
+            style: UnderlineStyle::Wavy
+        }
+        && d.text.is_none()));
+    assert_eq!(decorations[1].tooltip.as_deref(), Some("0..10\n4..6\n5..8"));
+}
+
+#[test]
+fn test_missing_severity_is_treated_as_error() {
+    let decorations = diagnostic_underline_decorations(&[
+        diagnostic(0, 3, Some(DiagnosticSeverity::Warning)),
+        // Reversed ranges are normalized.
+        diagnostic(2, 1, None),
+    ]);
+
+    let spans: Vec<(DecorationRange, Vec<u32>)> = decorations
+        .iter()
+        .map(|d| (d.range, d.styles.clone()))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (
+                DecorationRange::new(0, 1),
+                vec![DIAGNOSTIC_WARNING_STYLE_ID]
+            ),
+            (DecorationRange::new(1, 2), vec![DIAGNOSTIC_ERROR_STYLE_ID]),
+            (
+                DecorationRange::new(2, 3),
+                vec![DIAGNOSTIC_WARNING_STYLE_ID]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_composed_grid_carries_underline_styles() {
+    let mut manager = EditorStateManager::new("let x = 1;\nfoo();\n", 80);
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceDiagnostics {
+        diagnostics: vec![
+            diagnostic(4, 5, Some(DiagnosticSeverity::Warning)),
+            diagnostic(11, 14, Some(DiagnosticSeverity::Error)),
+        ],
+    }]);
+    manager.sync_diagnostic_decorations();
+
+    assert_eq!(
+        manager
+            .editor()
+            .decorations
+            .get(&DecorationLayerId::DIAGNOSTICS)
+            .map(|d| d.len()),
+        Some(2)
+    );
+
+    let grid = manager.get_viewport_content_composed(0, 2);
+    let underlined: Vec<Vec<usize>> = grid
+        .lines
+        .iter()
+        .map(|line| {
+            line.cells
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| !c.styles.is_empty())
+                .map(|(i, _)| i)
+                .collect()
+        })
+        .collect();
+    assert_eq!(underlined, vec![vec![4], vec![0, 1, 2]]);
+    let wavy: Vec<Vec<usize>> = grid
+        .lines
+        .iter()
+        .map(|line| {
+            line.cells
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.underline == Some(UnderlineStyle::Wavy))
+                .map(|(i, _)| i)
+                .collect()
+        })
+        .collect();
+    assert_eq!(wavy, underlined);
+    assert_eq!(
+        grid.lines[0].cells[4].styles,
+        vec![DIAGNOSTIC_WARNING_STYLE_ID]
+    );
+    assert_eq!(
+        grid.lines[1].cells[0].styles,
+        vec![DIAGNOSTIC_ERROR_STYLE_ID]
+    );
+
+    manager.apply_processing_edits(vec![ProcessingEdit::ClearDiagnostics]);
+    manager.sync_diagnostic_decorations();
+    assert!(
+        !manager
+            .editor()
+            .decorations
+            .contains_key(&DecorationLayerId::DIAGNOSTICS)
+    );
+}
+
+#[test]
+fn test_style_less_underline_decoration_marks_only_covered_cells() {
+    let mut manager = EditorStateManager::new("teh quick fox", 80);
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceDecorations {
+        layer: DecorationLayerId::new(100),
+        decorations: vec![Decoration {
+            range: DecorationRange::new(0, 3),
+            placement: DecorationPlacement::Before,
+            kind: DecorationKind::Underline {
+                style: UnderlineStyle::Dotted,
+            },
+            text: None,
+            styles: Vec::new(),
+            tooltip: Some("spelling".to_string()),
+            data_json: None,
+        }],
+    }]);
+
+    let grid = manager.get_viewport_content_composed(0, 1);
+    let underlines: Vec<Option<UnderlineStyle>> =
+        grid.lines[0].cells.iter().map(|c| c.underline).collect();
+    let mut expected = vec![None; 13];
+    expected[..3].fill(Some(UnderlineStyle::Dotted));
+    assert_eq!(underlines, expected);
+    assert!(grid.lines[0].cells.iter().all(|c| c.styles.is_empty()));
+}
+
+#[test]
+fn test_tagged_diagnostics_add_tag_styles() {
+    let mut unused = diagnostic(0, 4, Some(DiagnosticSeverity::Hint));
+    unused.tags = vec![DiagnosticTag::Unnecessary];
+    let mut deprecated = diagnostic(2, 6, Some(DiagnosticSeverity::Warning));
+    deprecated.tags = vec![DiagnosticTag::Deprecated, DiagnosticTag::Deprecated];
+
+    let spans: Vec<(DecorationRange, Vec<u32>)> =
+        diagnostic_underline_decorations(&[unused, deprecated])
+            .iter()
+            .map(|d| (d.range, d.styles.clone()))
+            .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (
+                DecorationRange::new(0, 2),
+                vec![DIAGNOSTIC_HINT_STYLE_ID, DIAGNOSTIC_UNNECESSARY_STYLE_ID]
+            ),
+            (
+                DecorationRange::new(2, 4),
+                vec![
+                    DIAGNOSTIC_WARNING_STYLE_ID,
+                    DIAGNOSTIC_UNNECESSARY_STYLE_ID,
+                    DIAGNOSTIC_DEPRECATED_STYLE_ID
+                ]
+            ),
+            (
+                DecorationRange::new(4, 6),
+                vec![DIAGNOSTIC_WARNING_STYLE_ID, DIAGNOSTIC_DEPRECATED_STYLE_ID]
+            ),
+        ]
+    );
+}
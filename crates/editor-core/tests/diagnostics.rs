@@ -20,7 +20,11 @@ fn test_replace_and_clear_diagnostics() {
             severity: Some(DiagnosticSeverity::Hint),
             code: Some("H1".to_string()),
             source: Some("unit-test".to_string()),
+            provider: None,
             message: "hello".to_string(),
+            code_description_href: None,
+            tags: Vec::new(),
+            related: Vec::new(),
             related_information_json: None,
             data_json: None,
         },
@@ -29,7 +33,11 @@ fn test_replace_and_clear_diagnostics() {
             severity: Some(DiagnosticSeverity::Error),
             code: None,
             source: None,
+            provider: None,
             message: "emoji".to_string(),
+            code_description_href: None,
+            tags: Vec::new(),
+            related: Vec::new(),
             related_information_json: Some(r#"[{"note":"x"}]"#.to_string()),
             data_json: Some(r#"{"k":1}"#.to_string()),
         },
@@ -62,3 +70,80 @@ fn test_replace_and_clear_diagnostics() {
         ]
     );
 }
+
+#[test]
+fn test_provider_diagnostics_are_replaced_independently() {
+    let mut manager = EditorStateManager::new("let x = 1;\n", 80);
+    let diagnostic = |start: usize, message: &str| Diagnostic {
+        range: DiagnosticRange::new(start, start + 1),
+        severity: Some(DiagnosticSeverity::Warning),
+        code: None,
+        source: None,
+        provider: None,
+        message: message.to_string(),
+        code_description_href: None,
+        tags: Vec::new(),
+        related: Vec::new(),
+        related_information_json: None,
+        data_json: None,
+    };
+    let messages = |manager: &EditorStateManager| -> Vec<(Option<String>, String)> {
+        manager
+            .editor()
+            .diagnostics()
+            .iter()
+            .map(|d| (d.provider.clone(), d.message.clone()))
+            .collect()
+    };
+    let tagged = |provider: &str, message: &str| (Some(provider.to_string()), message.to_string());
+    let counts = |manager: &EditorStateManager| -> Vec<(String, usize)> {
+        manager
+            .get_diagnostics_state()
+            .provider_counts
+            .into_iter()
+            .collect()
+    };
+
+    manager.apply_processing_edits(vec![
+        ProcessingEdit::ReplaceProviderDiagnostics {
+            provider: "rust-analyzer".to_string(),
+            diagnostics: vec![diagnostic(4, "unused variable")],
+        },
+        ProcessingEdit::ReplaceProviderDiagnostics {
+            provider: "typos".to_string(),
+            diagnostics: vec![diagnostic(0, "spelling")],
+        },
+    ]);
+    assert_eq!(
+        messages(&manager),
+        vec![
+            tagged("rust-analyzer", "unused variable"),
+            tagged("typos", "spelling")
+        ]
+    );
+    assert_eq!(
+        counts(&manager),
+        vec![("rust-analyzer".to_string(), 1), ("typos".to_string(), 1)]
+    );
+
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceProviderDiagnostics {
+        provider: "rust-analyzer".to_string(),
+        diagnostics: vec![diagnostic(8, "literal out of range")],
+    }]);
+    assert_eq!(
+        messages(&manager),
+        vec![
+            tagged("typos", "spelling"),
+            tagged("rust-analyzer", "literal out of range")
+        ]
+    );
+
+    manager.apply_processing_edits(vec![ProcessingEdit::ClearProviderDiagnostics {
+        provider: "typos".to_string(),
+    }]);
+    assert_eq!(
+        messages(&manager),
+        vec![tagged("rust-analyzer", "literal out of range")]
+    );
+    assert_eq!(counts(&manager), vec![("rust-analyzer".to_string(), 1)]);
+}
@@ -1,5 +1,7 @@
+use editor_core::search::{find_all, find_next, find_prev};
 use editor_core::{
-    Command, CommandExecutor, CommandResult, CursorCommand, EditCommand, SearchOptions,
+    Command, CommandExecutor, CommandResult, CursorCommand, EditCommand, Position, SearchMatch,
+    SearchOptions,
 };
 
 fn opts(case_sensitive: bool, whole_word: bool, regex: bool) -> SearchOptions {
@@ -7,6 +9,7 @@ fn opts(case_sensitive: bool, whole_word: bool, regex: bool) -> SearchOptions {
         case_sensitive,
         whole_word,
         regex,
+        ..SearchOptions::default()
     }
 }
 
@@ -66,6 +69,33 @@ fn test_find_whole_word() {
     assert_eq!((start, end), (18, 21));
 }
 
+#[test]
+fn test_find_whole_word_uses_unicode_word_boundaries() {
+    let whole = |text: &str, query: &str| {
+        find_all(text, query, opts(true, true, false))
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.start, m.end))
+            .collect::<Vec<_>>()
+    };
+
+    // Underscore-joined identifiers are one word.
+    assert_eq!(whole("foo_bar foo _foo", "foo"), vec![(8, 11)]);
+    // A combining mark belongs to the word before it.
+    assert_eq!(whole("cafe\u{301} cafe", "cafe"), vec![(6, 10)]);
+    // Multi-byte queries.
+    assert_eq!(
+        whole("größe größer (größe)", "größe"),
+        vec![(0, 5), (14, 19)]
+    );
+    // Each CJK ideograph is a word of its own.
+    assert_eq!(whole("中文字 中文", "中文"), vec![(0, 2), (4, 6)]);
+    assert_eq!(whole("中文字", "文字"), vec![(1, 3)]);
+
+    let prev = find_prev("foo foo_bar", "foo", opts(true, true, false), 11).unwrap();
+    assert_eq!(prev, Some(SearchMatch { start: 0, end: 3 }));
+}
+
 #[test]
 fn test_replace_current_is_single_undo_step() {
     let mut executor = CommandExecutor::new("foo foo", 80);
@@ -119,3 +149,434 @@ fn test_replace_all_is_single_undo_step_and_supports_regex_replacement() {
     executor.execute(Command::Edit(EditCommand::Redo)).unwrap();
     assert_eq!(executor.editor().get_text(), "bar1 bar2 bar3");
 }
+
+fn replace_all(text: &str, query: &str, replacement: &str, options: SearchOptions) -> String {
+    let mut executor = CommandExecutor::new(text, 80);
+    let result = executor
+        .execute(Command::Edit(EditCommand::ReplaceAll {
+            query: query.to_string(),
+            replacement: replacement.to_string(),
+            options,
+        }))
+        .unwrap();
+    assert!(matches!(result, CommandResult::ReplaceResult { .. }));
+    executor.editor().get_text()
+}
+
+#[test]
+fn test_regex_replacement_expands_captures_per_match() {
+    let regex = opts(true, false, true);
+
+    // Swap-style replacement: each occurrence uses its own captures.
+    assert_eq!(
+        replace_all("a=1, bb=22\ncc=x", r"(\w+)=(\w+)", "$2=$1", regex),
+        "1=a, 22=bb\nx=cc"
+    );
+
+    // Named groups.
+    assert_eq!(
+        replace_all(
+            "key: value\nname: editor",
+            r"(?P<k>\w+): (?P<v>\w+)",
+            "${v} <- ${k}",
+            regex
+        ),
+        "value <- key\neditor <- name"
+    );
+
+    // `$$` is a literal dollar sign; `${1}` disambiguates from trailing word chars.
+    assert_eq!(
+        replace_all("price 10, price 20", r"price (\d+)", "$$${1}USD", regex),
+        "$10USD, $20USD"
+    );
+
+    // An optional group that did not participate expands to nothing.
+    assert_eq!(
+        replace_all("f(x) g()", r"(\w)\((\w)?\)", "$1[$2]", regex),
+        "f[x] g[]"
+    );
+
+    // Literal searches insert the replacement verbatim.
+    assert_eq!(
+        replace_all("a=1", "a=1", "$1$$", opts(true, false, false)),
+        "$1$$"
+    );
+}
+
+#[test]
+fn test_regex_replacement_respects_whole_word_and_replace_current() {
+    // Whole-word + regex: `foo_bar` and `xfoo` are skipped, captures still expand.
+    assert_eq!(
+        replace_all(
+            "foo1 xfoo2 foo3_bar foo4",
+            r"foo(\d)",
+            "bar$1",
+            opts(true, true, true)
+        ),
+        "bar1 xfoo2 foo3_bar bar4"
+    );
+
+    let mut executor = CommandExecutor::new("k1=v1 k2=v2", 80);
+    let query = r"(\w+)=(\w+)".to_string();
+    let options = opts(true, false, true);
+    executor
+        .execute(Command::Cursor(CursorCommand::MoveTo {
+            line: 0,
+            column: 6,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::Edit(EditCommand::ReplaceCurrent {
+            query: query.clone(),
+            replacement: "$2=$1".to_string(),
+            options,
+        }))
+        .unwrap();
+    assert_eq!(executor.editor().get_text(), "k1=v1 v2=k2");
+    assert_eq!(executor.undo_depth(), 1);
+}
+
+fn replace_in_range(
+    executor: &mut CommandExecutor,
+    query: &str,
+    replacement: &str,
+    options: SearchOptions,
+    range: Option<(usize, usize)>,
+) -> usize {
+    let result = executor
+        .execute(Command::Edit(EditCommand::ReplaceAllInRange {
+            query: query.to_string(),
+            replacement: replacement.to_string(),
+            options,
+            range,
+        }))
+        .unwrap();
+    let CommandResult::ReplaceResult { replaced } = result else {
+        panic!("expected CommandResult::ReplaceResult");
+    };
+    replaced
+}
+
+fn selected_text(executor: &CommandExecutor) -> String {
+    let editor = executor.editor();
+    let sel = editor.selection().expect("selection");
+    let start = editor
+        .line_index
+        .position_to_char_offset(sel.start.line, sel.start.column);
+    let end = editor
+        .line_index
+        .position_to_char_offset(sel.end.line, sel.end.column);
+    editor
+        .get_text()
+        .chars()
+        .skip(start)
+        .take(end - start)
+        .collect()
+}
+
+#[test]
+fn test_replace_all_in_selection_tracks_scope_length() {
+    // Shrinking replacement, scope taken from the selection.
+    let mut executor = CommandExecutor::new("foo foo\nfoo foo\nfoo", 80);
+    executor
+        .execute(Command::Cursor(CursorCommand::SetSelection {
+            start: Position::new(0, 4),
+            end: Position::new(1, 7),
+        }))
+        .unwrap();
+    let replaced = replace_in_range(&mut executor, "foo", "x", opts(true, false, false), None);
+    assert_eq!(replaced, 3);
+    assert_eq!(executor.editor().get_text(), "foo x\nx x\nfoo");
+    assert_eq!(selected_text(&executor), "x\nx x");
+
+    // Growing replacement with an explicit range; one undo step.
+    let replaced = replace_in_range(
+        &mut executor,
+        "x",
+        "long",
+        opts(true, false, false),
+        Some((4, 9)),
+    );
+    assert_eq!(replaced, 3);
+    assert_eq!(executor.editor().get_text(), "foo long\nlong long\nfoo");
+    assert_eq!(selected_text(&executor), "long\nlong long");
+
+    executor.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(executor.editor().get_text(), "foo x\nx x\nfoo");
+}
+
+#[test]
+fn test_scoped_matches_must_fit_inside_range() {
+    let text = "abc abc abc";
+
+    // Matches straddling either boundary are excluded.
+    let mut executor = CommandExecutor::new(text, 80);
+    let replaced = replace_in_range(
+        &mut executor,
+        "abc",
+        "X",
+        opts(true, false, false),
+        Some((2, 9)),
+    );
+    assert_eq!(replaced, 1);
+    assert_eq!(executor.editor().get_text(), "abc X abc");
+
+    // Whole-word looks outside the scope: "bc" is part of "abc" even though the scope starts at `b`.
+    let mut executor = CommandExecutor::new(text, 80);
+    let result = executor.execute(Command::Edit(EditCommand::ReplaceAllInRange {
+        query: "bc".to_string(),
+        replacement: "X".to_string(),
+        options: opts(true, true, false),
+        range: Some((1, 3)),
+    }));
+    assert!(result.is_err());
+    assert_eq!(executor.editor().get_text(), text);
+
+    // Greedy regex stops at the scope end instead of failing to align.
+    let mut executor = CommandExecutor::new("aaaa bbbb", 80);
+    let replaced = replace_in_range(
+        &mut executor,
+        r"(\w+)",
+        "<$1>",
+        opts(true, false, true),
+        Some((1, 7)),
+    );
+    assert_eq!(replaced, 2);
+    assert_eq!(executor.editor().get_text(), "a<aaa> <bb>bb");
+}
+
+#[test]
+fn test_scope_at_document_start_and_end() {
+    let mut executor = CommandExecutor::new("cat dog cat", 80);
+    let replaced = replace_in_range(
+        &mut executor,
+        "cat",
+        "c",
+        opts(true, true, false),
+        Some((0, 3)),
+    );
+    assert_eq!(replaced, 1);
+    assert_eq!(executor.editor().get_text(), "c dog cat");
+
+    let end = executor.editor().char_count();
+    let replaced = replace_in_range(
+        &mut executor,
+        r"c(a)t$",
+        "$1",
+        opts(true, true, true),
+        Some((end - 3, end)),
+    );
+    assert_eq!(replaced, 1);
+    assert_eq!(executor.editor().get_text(), "c dog a");
+
+    // No selection and no explicit range is an error; so is an out-of-bounds range.
+    executor
+        .execute(Command::Cursor(CursorCommand::ClearSelection))
+        .unwrap();
+    let no_scope = EditCommand::ReplaceAllInRange {
+        query: "a".to_string(),
+        replacement: "b".to_string(),
+        options: opts(true, false, false),
+        range: None,
+    };
+    assert!(executor.execute(Command::Edit(no_scope)).is_err());
+    let out_of_bounds = EditCommand::ReplaceAllInRange {
+        query: "a".to_string(),
+        replacement: "b".to_string(),
+        options: opts(true, false, false),
+        range: Some((0, 100)),
+    };
+    assert!(executor.execute(Command::Edit(out_of_bounds)).is_err());
+}
+
+#[test]
+fn test_find_next_in_range() {
+    let mut executor = CommandExecutor::new("ab ab ab ab", 80);
+    let find = |executor: &mut CommandExecutor| match executor
+        .execute(Command::Cursor(CursorCommand::FindNextInRange {
+            query: "ab".to_string(),
+            options: opts(true, false, false),
+            range: Some((2, 9)),
+        }))
+        .unwrap()
+    {
+        CommandResult::SearchMatch { start, end } => Some((start, end)),
+        CommandResult::SearchNotFound => None,
+        _ => panic!("unexpected result"),
+    };
+
+    assert_eq!(find(&mut executor), Some((3, 5)));
+    assert_eq!(find(&mut executor), Some((6, 8)));
+    assert_eq!(find(&mut executor), None);
+}
+
+#[test]
+fn test_find_next_in_selection_advances() {
+    let mut executor = CommandExecutor::new("ab ab ab ab", 80);
+    executor
+        .execute(Command::Cursor(CursorCommand::SetSelection {
+            start: Position::new(0, 2),
+            end: Position::new(0, 9),
+        }))
+        .unwrap();
+    let find = |executor: &mut CommandExecutor| match executor
+        .execute(Command::Cursor(CursorCommand::FindNextInRange {
+            query: "ab".to_string(),
+            options: opts(true, false, false),
+            range: None,
+        }))
+        .unwrap()
+    {
+        CommandResult::SearchMatch { start, end } => Some((start, end)),
+        CommandResult::SearchNotFound => None,
+        _ => panic!("unexpected result"),
+    };
+
+    // The scope stays the original selection, not the match selected by the first call.
+    assert_eq!(find(&mut executor), Some((3, 5)));
+    assert_eq!(find(&mut executor), Some((6, 8)));
+    assert_eq!(find(&mut executor), None);
+}
+
+#[test]
+fn test_incremental_find_from_fixed_anchor() {
+    let mut executor = CommandExecutor::new("foo bar\nfoobar baz", 80);
+    let find = |executor: &mut CommandExecutor, query: &str, from_offset: usize| match executor
+        .execute(Command::Cursor(CursorCommand::IncrementalFind {
+            query: query.to_string(),
+            options: opts(true, false, false),
+            from_offset,
+        }))
+        .unwrap()
+    {
+        CommandResult::SearchMatch { start, end } => Some((start, end)),
+        CommandResult::SearchNotFound => None,
+        _ => panic!("unexpected result"),
+    };
+
+    // Typing "f", "fo", "foob" from offset 2: each keystroke searches from the same origin.
+    assert_eq!(find(&mut executor, "f", 2), Some((8, 9)));
+    assert_eq!(find(&mut executor, "fo", 2), Some((8, 10)));
+    assert_eq!(find(&mut executor, "foob", 2), Some((8, 12)));
+    assert_eq!(executor.editor().cursor_position(), Position::new(1, 4));
+
+    // A match starting at the origin is found.
+    assert_eq!(find(&mut executor, "bar", 4), Some((4, 7)));
+
+    // Nothing after the origin: wraps around to the first match.
+    assert_eq!(find(&mut executor, "foo ", 10), Some((0, 4)));
+
+    // No match or empty query: the preview collapses back to the origin.
+    assert_eq!(find(&mut executor, "qux", 5), None);
+    assert_eq!(executor.editor().cursor_position(), Position::new(0, 5));
+    assert!(executor.editor().selection().is_none());
+    find(&mut executor, "baz", 0);
+    assert!(executor.editor().selection().is_some());
+    assert_eq!(find(&mut executor, "", 3), None);
+    assert_eq!(executor.editor().cursor_position(), Position::new(0, 3));
+    assert!(executor.editor().selection().is_none());
+
+    // The origin must be inside the document.
+    assert!(
+        executor
+            .execute(Command::Cursor(CursorCommand::IncrementalFind {
+                query: "foo".to_string(),
+                options: opts(true, false, false),
+                from_offset: 100,
+            }))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_regex_find_across_lines() {
+    // Blocks: `{` at 7 and 24, `}` at 15 and 32.
+    let text = "fn a() {\n    x\n}\nfn b() {\n    y\n}\n";
+    let regex = opts(true, false, true);
+
+    let matches = find_all(text, r"\{[^}]*\}", regex).unwrap();
+    assert_eq!(
+        matches,
+        vec![
+            SearchMatch { start: 7, end: 16 },
+            SearchMatch { start: 24, end: 33 }
+        ]
+    );
+
+    // From inside the second block, the previous match is the whole first block: the second
+    // one spans the search origin and is not cut short there.
+    assert_eq!(
+        find_prev(text, r"\{[^}]*", regex, 28).unwrap(),
+        Some(SearchMatch { start: 7, end: 15 })
+    );
+    assert_eq!(
+        find_next(text, r"\{[^}]*", regex, 11).unwrap(),
+        Some(SearchMatch { start: 24, end: 32 })
+    );
+
+    // `.` only crosses line breaks with `dot_matches_newline`.
+    assert_eq!(find_next(text, r"a.*x", regex, 0).unwrap(), None);
+    let dot_all = SearchOptions {
+        dot_matches_newline: true,
+        ..regex
+    };
+    assert_eq!(
+        find_next(text, r"a.*?x", dot_all, 0).unwrap(),
+        Some(SearchMatch { start: 3, end: 14 })
+    );
+
+    // Commands select multi-line matches and step back over them.
+    let mut executor = CommandExecutor::new(text, 80);
+    executor
+        .execute(Command::Cursor(CursorCommand::MoveTo {
+            line: 1,
+            column: 2,
+        }))
+        .unwrap();
+    let find = |executor: &mut CommandExecutor, forward: bool| {
+        let query = r"\{[^}]*\}".to_string();
+        let command = if forward {
+            CursorCommand::FindNext {
+                query,
+                options: regex,
+            }
+        } else {
+            CursorCommand::FindPrev {
+                query,
+                options: regex,
+            }
+        };
+        match executor.execute(Command::Cursor(command)).unwrap() {
+            CommandResult::SearchMatch { start, end } => Some((start, end)),
+            CommandResult::SearchNotFound => None,
+            _ => panic!("unexpected result"),
+        }
+    };
+    assert_eq!(find(&mut executor, true), Some((24, 33)));
+    assert_eq!(executor.editor().cursor_position(), Position::new(5, 1));
+    assert_eq!(find(&mut executor, false), Some((7, 16)));
+    assert_eq!(find(&mut executor, false), None);
+}
+
+#[test]
+fn test_regex_replace_across_lines_with_dot_matches_newline() {
+    let dot_all = SearchOptions {
+        dot_matches_newline: true,
+        ..opts(true, false, true)
+    };
+
+    assert_eq!(
+        replace_all("begin\nx\nend\n", "begin(.*)end", "<$1>", dot_all),
+        "<\nx\n>\n"
+    );
+
+    let mut executor = CommandExecutor::new("begin\nx\nend\n", 80);
+    executor
+        .execute(Command::Edit(EditCommand::ReplaceCurrent {
+            query: "begin.*end".to_string(),
+            replacement: "block".to_string(),
+            options: dot_all,
+        }))
+        .unwrap();
+    assert_eq!(executor.editor().get_text(), "block\n");
+}
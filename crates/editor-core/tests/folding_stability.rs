@@ -97,3 +97,198 @@ fn test_replace_derived_folds_keeps_user_folds() {
     assert_eq!(state.editor().folding_manager.derived_regions().len(), 1);
     assert_eq!(state.editor().folding_manager.regions().len(), 2);
 }
+
+#[test]
+fn test_collapsed_derived_fold_survives_newline_after_its_start_line() {
+    let mut state = EditorStateManager::new("a\nfn f() {\n    x\n}\nfn g() {\n    y\n}", 80);
+    let mut collapsed = FoldRegion::new(1, 2);
+    collapsed.collapse();
+    state.replace_folding_regions(vec![collapsed, FoldRegion::new(4, 5)], true);
+
+    // Enter at the end of `fn f() {`: the provider now reports the body one line longer.
+    let offset = state.editor().line_index.position_to_char_offset(1, 8);
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: "\n".to_string(),
+        }))
+        .unwrap();
+    state.replace_folding_regions(vec![FoldRegion::new(1, 3), FoldRegion::new(5, 6)], true);
+
+    let derived = state.editor().folding_manager.derived_regions();
+    assert_eq!(
+        derived
+            .iter()
+            .map(|r| (r.start_line, r.end_line, r.is_collapsed))
+            .collect::<Vec<_>>(),
+        vec![(1, 3, true), (5, 6, false)]
+    );
+}
+
+fn fold_lines(ex: &CommandExecutor) -> Vec<(usize, usize, bool)> {
+    ex.editor()
+        .folding_manager
+        .regions()
+        .iter()
+        .map(|r| (r.start_line, r.end_line, r.is_collapsed))
+        .collect()
+}
+
+#[test]
+fn test_collapsed_fold_stays_collapsed_when_lines_are_inserted_inside() {
+    let mut ex = CommandExecutor::new("fn f() {\n    x\n}\ntail", 80);
+    ex.execute(Command::Style(StyleCommand::Fold {
+        start_line: 0,
+        end_line: 2,
+    }))
+    .unwrap();
+
+    // Enter at the end of the header line: the header stays, the body grows.
+    let offset = ex.editor().line_index.position_to_char_offset(0, 8);
+    ex.execute(Command::Edit(EditCommand::Insert {
+        offset,
+        text: "\n    y".to_string(),
+    }))
+    .unwrap();
+    assert_eq!(fold_lines(&ex), vec![(0, 3, true)]);
+
+    // A line inserted at the start of the closing line also lands inside.
+    let offset = ex.editor().line_index.position_to_char_offset(3, 0);
+    ex.execute(Command::Edit(EditCommand::Insert {
+        offset,
+        text: "    z\n".to_string(),
+    }))
+    .unwrap();
+    assert_eq!(fold_lines(&ex), vec![(0, 4, true)]);
+
+    // A line inserted at the start of the header pushes the whole region down.
+    ex.execute(Command::Edit(EditCommand::Insert {
+        offset: 0,
+        text: "// doc\n".to_string(),
+    }))
+    .unwrap();
+    assert_eq!(fold_lines(&ex), vec![(1, 5, true)]);
+}
+
+#[test]
+fn test_deleting_fold_header_line_removes_region() {
+    let mut ex = CommandExecutor::new("a\nfn f() {\n    x\n}\nfn g() {\n    y\n}", 80);
+    ex.execute(Command::Style(StyleCommand::Fold {
+        start_line: 1,
+        end_line: 3,
+    }))
+    .unwrap();
+    ex.execute(Command::Style(StyleCommand::Fold {
+        start_line: 4,
+        end_line: 6,
+    }))
+    .unwrap();
+
+    // Delete `fn f() {\n`.
+    let start = ex.editor().line_index.position_to_char_offset(1, 0);
+    let end = ex.editor().line_index.position_to_char_offset(2, 0);
+    ex.execute(Command::Edit(EditCommand::Delete {
+        start,
+        length: end - start,
+    }))
+    .unwrap();
+    assert_eq!(fold_lines(&ex), vec![(3, 5, true)]);
+
+    // Deleting from the middle of a region through the next header removes only that region.
+    let start = ex.editor().line_index.position_to_char_offset(1, 5);
+    let end = ex.editor().line_index.position_to_char_offset(3, 3);
+    ex.execute(Command::Edit(EditCommand::Delete {
+        start,
+        length: end - start,
+    }))
+    .unwrap();
+    assert!(fold_lines(&ex).is_empty());
+}
+
+#[test]
+fn test_splitting_a_shared_line_keeps_regions_from_crossing() {
+    // `} else {` ends one region and starts the next.
+    let mut ex = CommandExecutor::new("if a {\n    x\n} else {\n    y\n}", 80);
+    ex.execute(Command::Style(StyleCommand::Fold {
+        start_line: 0,
+        end_line: 2,
+    }))
+    .unwrap();
+    ex.execute(Command::Style(StyleCommand::Fold {
+        start_line: 2,
+        end_line: 4,
+    }))
+    .unwrap();
+
+    // Enter between `}` and ` else {`.
+    let offset = ex.editor().line_index.position_to_char_offset(2, 1);
+    ex.execute(Command::Edit(EditCommand::Insert {
+        offset,
+        text: "\n".to_string(),
+    }))
+    .unwrap();
+
+    let regions = fold_lines(&ex);
+    assert_eq!(regions, vec![(0, 2, true), (2, 5, true)]);
+    for pair in regions.windows(2) {
+        assert!(pair[0].1 <= pair[1].0 || pair[0].1 >= pair[1].1);
+    }
+}
+
+fn first_row_text(state: &EditorStateManager) -> String {
+    let grid = state.get_viewport_content_styled(0, 1);
+    grid.lines[0].cells.iter().map(|cell| cell.ch).collect()
+}
+
+#[test]
+fn test_fold_summary_follows_placeholder_template() {
+    let mut state = EditorStateManager::new("fn f() {\n    x\n}\ny", 80);
+    state.set_fold_placeholder_template(Some("{ … {lines} lines }".to_string()));
+    state
+        .execute(Command::Style(StyleCommand::Fold {
+            start_line: 0,
+            end_line: 2,
+        }))
+        .unwrap();
+    assert_eq!(first_row_text(&state), "fn f() { { … 2 lines }");
+
+    // Edits inside the collapsed region refresh the count.
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset: 14,
+            text: "\n    z".to_string(),
+        }))
+        .unwrap();
+    assert_eq!(first_row_text(&state), "fn f() { { … 3 lines }");
+
+    // Without a template the static placeholder is shown again.
+    state.set_fold_placeholder_template(None);
+    let user = state.editor().folding_manager.user_regions();
+    assert_eq!(user[0].summary, None);
+    assert_eq!(first_row_text(&state), "fn f() { [...]");
+
+    state
+        .execute(Command::Style(StyleCommand::Unfold { start_line: 0 }))
+        .unwrap();
+    assert_eq!(first_row_text(&state), "fn f() {");
+}
+
+#[test]
+fn test_fold_summary_first_line_for_direct_collapse() {
+    let mut state = EditorStateManager::new("/*\n   docs here\n*/\nx", 80);
+    state.replace_folding_regions(vec![FoldRegion::new(0, 2)], false);
+    state.set_fold_placeholder_template(Some("{first_line} {placeholder} {unknown}".to_string()));
+
+    let editor = state.editor_mut();
+    assert!(editor.folding_manager.toggle_region_starting_at_line(0));
+    assert_eq!(editor.folding_manager.regions()[0].summary, None);
+    editor.refresh_fold_summaries();
+    assert_eq!(
+        editor.folding_manager.regions()[0].display_text(),
+        "docs here [...] {unknown}"
+    );
+
+    // Expanding drops the summary.
+    assert!(editor.folding_manager.toggle_region_starting_at_line(0));
+    assert_eq!(editor.folding_manager.regions()[0].summary, None);
+}
@@ -0,0 +1,78 @@
+use editor_core::layout::{calculate_wrap_points, grapheme_cell_widths, str_width};
+use editor_core::{EditorStateManager, Position};
+
+const FAMILY: &str = "👨\u{200d}👩\u{200d}👧";
+const E_ACUTE: &str = "e\u{301}";
+
+#[test]
+fn test_grapheme_widths() {
+    assert_eq!(str_width(FAMILY), 2);
+    assert_eq!(str_width(E_ACUTE), 1);
+    assert_eq!(grapheme_cell_widths(FAMILY, 4), vec![2, 0, 0, 0, 0]);
+    assert_eq!(grapheme_cell_widths("\te\u{301}x", 4), vec![4, 1, 0, 1]);
+}
+
+#[test]
+fn test_family_emoji_occupies_one_cell() {
+    let text = format!("a{FAMILY}b");
+    let state = EditorStateManager::new(&text, 80);
+
+    let grid = state.get_viewport_content_styled(0, 1);
+    let cells = &grid.lines[0].cells;
+    assert_eq!(cells.len(), 3);
+    assert_eq!(cells[1].text(), FAMILY);
+    assert_eq!(cells[1].width, 2);
+    assert_eq!(grid.lines[0].visual_width(), 4);
+    assert_eq!(grid.to_debug_string(false), format!("{text}\n"));
+
+    // Columns stay char-indexed: the emoji spans columns 1..6.
+    assert_eq!(state.logical_position_to_visual(0, 1), Some((0, 1)));
+    assert_eq!(state.logical_position_to_visual(0, 6), Some((0, 3)));
+    assert_eq!(state.logical_position_to_visual(0, 7), Some((0, 4)));
+    // Inside the cluster measures as after it.
+    assert_eq!(state.logical_position_to_visual(0, 3), Some((0, 3)));
+
+    assert_eq!(
+        state.visual_position_to_logical(0, 2),
+        Some(Position::new(0, 1))
+    );
+    assert_eq!(
+        state.visual_position_to_logical(0, 3),
+        Some(Position::new(0, 6))
+    );
+}
+
+#[test]
+fn test_combining_mark_shares_its_base_cell() {
+    let text = format!("{E_ACUTE}x");
+    let state = EditorStateManager::new(&text, 80);
+
+    let grid = state.get_viewport_content_styled(0, 1);
+    let cells = &grid.lines[0].cells;
+    assert_eq!(cells.len(), 2);
+    assert_eq!((cells[0].ch, cells[0].combining.as_str()), ('e', "\u{301}"));
+    assert_eq!(cells[0].width, 1);
+    assert_eq!(cells[1].ch, 'x');
+
+    assert_eq!(state.logical_position_to_visual(0, 2), Some((0, 1)));
+    assert_eq!(
+        state.visual_position_to_logical(0, 1),
+        Some(Position::new(0, 2))
+    );
+}
+
+#[test]
+fn test_soft_wrap_does_not_split_clusters() {
+    // Width 2 is filled by "ae"; the combining mark stays with its base.
+    let wraps: Vec<usize> = calculate_wrap_points(&format!("a{E_ACUTE}b"), 2)
+        .iter()
+        .map(|wp| wp.char_index)
+        .collect();
+    assert_eq!(wraps, vec![3]);
+
+    let wraps: Vec<usize> = calculate_wrap_points(&format!("ab{FAMILY}c"), 3)
+        .iter()
+        .map(|wp| wp.char_index)
+        .collect();
+    assert_eq!(wraps, vec![2]);
+}
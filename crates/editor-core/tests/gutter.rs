@@ -0,0 +1,175 @@
+use editor_core::{
+    Command, CursorCommand, Decoration, DecorationKind, DecorationLayerId, DecorationPlacement,
+    DecorationRange, Diagnostic, DiagnosticRange, DiagnosticSeverity, EditorStateManager,
+    FoldRegion, GutterFoldState, GutterLine, Position, ProcessingEdit, Selection,
+    SelectionDirection,
+};
+
+fn diagnostic(start: usize, end: usize, severity: Option<DiagnosticSeverity>) -> Diagnostic {
+    Diagnostic {
+        range: DiagnosticRange::new(start, end),
+        severity,
+        code: None,
+        source: None,
+        provider: None,
+        message: "test".to_string(),
+        code_description_href: None,
+        tags: Vec::new(),
+        related: Vec::new(),
+        related_information_json: None,
+        data_json: None,
+    }
+}
+
+fn line_numbers(gutter: &[GutterLine]) -> Vec<Option<usize>> {
+    gutter.iter().map(|g| g.logical_line).collect()
+}
+
+#[test]
+fn test_gutter_marks_wrapped_continuation_rows() {
+    // Viewport width 4 wraps "abcdefghij" into 3 visual rows.
+    let manager = EditorStateManager::new("abcdefghij\nxy\nz", 4);
+
+    let grid = manager.get_viewport_content_styled(0, 10);
+    let gutter = manager.get_viewport_gutter(0, 10);
+    assert_eq!(gutter.len(), grid.actual_line_count());
+    assert_eq!(
+        line_numbers(&gutter),
+        vec![Some(0), None, None, Some(1), Some(2)]
+    );
+
+    assert_eq!(
+        gutter
+            .iter()
+            .map(|g| g.is_wrap_continuation)
+            .collect::<Vec<_>>(),
+        vec![false, true, true, false, false]
+    );
+    assert_eq!(gutter[3].line_number(), Some(2));
+
+    // Starting mid-line keeps alignment with the grid.
+    let gutter = manager.get_viewport_gutter(1, 3);
+    assert_eq!(line_numbers(&gutter), vec![None, None, Some(1)]);
+    assert_eq!(gutter[0], GutterLine::wrap_continuation());
+    assert_eq!(gutter[0].line_number(), None);
+
+    assert!(manager.get_viewport_gutter(99, 3).is_empty());
+    assert!(manager.get_viewport_gutter(0, 0).is_empty());
+}
+
+#[test]
+fn test_gutter_reports_fold_state() {
+    let mut manager = EditorStateManager::new("fn a() {\n  1\n  2\n}\nfn b() {\n  3\n}\n", 80);
+
+    let mut collapsed = FoldRegion::new(4, 6);
+    collapsed.is_collapsed = true;
+    manager.replace_folding_regions(vec![FoldRegion::new(0, 3), collapsed], false);
+
+    let grid = manager.get_viewport_content_styled(0, 20);
+    let gutter = manager.get_viewport_gutter(0, 20);
+    assert_eq!(gutter.len(), grid.actual_line_count());
+    assert_eq!(
+        line_numbers(&gutter),
+        vec![Some(0), Some(1), Some(2), Some(3), Some(4), Some(7)]
+    );
+
+    let states: Vec<GutterFoldState> = gutter.iter().map(|g| g.fold_state).collect();
+    assert_eq!(
+        states,
+        vec![
+            GutterFoldState::Expanded,
+            GutterFoldState::Foldable,
+            GutterFoldState::Foldable,
+            GutterFoldState::Foldable,
+            GutterFoldState::Collapsed,
+            GutterFoldState::None,
+        ]
+    );
+}
+
+#[test]
+fn test_gutter_reports_max_diagnostic_severity_per_line() {
+    let mut manager = EditorStateManager::new("aaa\nbbb\nccc\nddd\n", 80);
+
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceDiagnostics {
+        diagnostics: vec![
+            diagnostic(0, 1, Some(DiagnosticSeverity::Hint)),
+            diagnostic(1, 2, Some(DiagnosticSeverity::Warning)),
+            // Spans lines 1..=2; the end at column 0 of line 3 does not mark line 3.
+            diagnostic(5, 12, Some(DiagnosticSeverity::Information)),
+            diagnostic(9, 9, None),
+        ],
+    }]);
+
+    let severities: Vec<Option<DiagnosticSeverity>> = manager
+        .get_viewport_gutter(0, 10)
+        .iter()
+        .map(|g| g.diagnostic_severity)
+        .collect();
+    assert_eq!(
+        severities,
+        vec![
+            Some(DiagnosticSeverity::Warning),
+            Some(DiagnosticSeverity::Information),
+            Some(DiagnosticSeverity::Error),
+            None,
+            None,
+        ]
+    );
+}
+
+#[test]
+fn test_composed_gutter_skips_virtual_rows() {
+    let mut manager = EditorStateManager::new("line1\nline2\n", 80);
+    let anchor = manager.editor().line_index.position_to_char_offset(1, 0);
+
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceDecorations {
+        layer: DecorationLayerId::CODE_LENS,
+        decorations: vec![Decoration {
+            range: DecorationRange::new(anchor, anchor),
+            placement: DecorationPlacement::AboveLine,
+            kind: DecorationKind::CodeLens,
+            text: Some("Lens".to_string()),
+            styles: vec![],
+            tooltip: None,
+            data_json: None,
+        }],
+    }]);
+
+    let grid = manager.get_viewport_content_composed(0, 10);
+    let gutter = manager.get_viewport_gutter_composed(0, 10);
+    assert_eq!(gutter.len(), grid.actual_line_count());
+    assert_eq!(line_numbers(&gutter), vec![Some(0), None, Some(1), Some(2)]);
+}
+
+#[test]
+fn test_gutter_marks_selected_lines() {
+    let mut manager = EditorStateManager::new("a\nb\nc\nd\ne\nf", 80);
+    let selection = |start: (usize, usize), end: (usize, usize)| Selection {
+        start: Position::new(start.0, start.1),
+        end: Position::new(end.0, end.1),
+        direction: SelectionDirection::Forward,
+    };
+
+    // Lines 0..=1 (the end at column 0 of line 2 does not count), plus a caret on line 4.
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![selection((0, 0), (2, 0)), selection((4, 1), (4, 1))],
+            primary_index: 0,
+        }))
+        .unwrap();
+
+    let selected: Vec<bool> = manager
+        .get_viewport_gutter(0, 10)
+        .iter()
+        .map(|g| g.is_selected)
+        .collect();
+    assert_eq!(selected, vec![true, true, false, false, true, false]);
+
+    let composed: Vec<bool> = manager
+        .get_viewport_gutter_composed(0, 10)
+        .iter()
+        .map(|g| g.is_selected)
+        .collect();
+    assert_eq!(composed, selected);
+}
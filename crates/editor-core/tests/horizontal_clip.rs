@@ -0,0 +1,94 @@
+use editor_core::{CellSource, Command, EditorStateManager, HeadlessLine, ViewCommand, WrapMode};
+
+fn doc(offset: usize, column: usize) -> CellSource {
+    CellSource::Document { offset, column }
+}
+
+fn cells(line: &HeadlessLine) -> Vec<(char, usize, CellSource)> {
+    line.cells
+        .iter()
+        .map(|cell| (cell.ch, cell.width, cell.source))
+        .collect()
+}
+
+fn unwrapped(text: &str) -> EditorStateManager {
+    let mut state = EditorStateManager::new(text, 10);
+    state
+        .execute(Command::View(ViewCommand::SetWrapMode {
+            mode: WrapMode::None,
+        }))
+        .unwrap();
+    state
+}
+
+#[test]
+fn test_clipping_through_tabs_and_wide_chars() {
+    // Tab width 4: `a` x=0, tab x=1..4, `b` x=4, `你` x=5..7, `c` x=7.
+    let state = unwrapped("a\tb你c");
+    let clipped = |x_start: usize, width: usize| {
+        state
+            .get_viewport_content_styled_clipped(0, 1, x_start, width)
+            .lines
+            .remove(0)
+    };
+
+    // The tab is cut on the left and `你` on the right: both become padding.
+    let line = clipped(2, 4);
+    assert_eq!(
+        cells(&line),
+        vec![
+            (' ', 2, doc(1, 1)),
+            ('b', 1, doc(2, 2)),
+            (' ', 1, doc(3, 3))
+        ]
+    );
+    assert_eq!(line.clip_x_start_cells, 2);
+    assert_eq!(line.clip_first_char_offset, Some(1));
+    assert_eq!(line.visual_width(), 4);
+
+    // Starting in the second half of `你`.
+    let line = clipped(6, 10);
+    assert_eq!(cells(&line), vec![(' ', 1, doc(3, 3)), ('c', 1, doc(4, 4))]);
+    assert_eq!(line.clip_first_char_offset, Some(3));
+
+    // Past the end of the line.
+    let line = clipped(8, 5);
+    assert!(line.cells.is_empty());
+    assert_eq!(line.clip_first_char_offset, None);
+
+    // A window covering the whole line matches the unclipped snapshot.
+    let full = state.get_viewport_content_styled(0, 1);
+    assert_eq!(cells(&clipped(0, 80)), cells(&full.lines[0]));
+    assert_eq!(full.lines[0].clip_first_char_offset, None);
+}
+
+#[test]
+fn test_clipped_cell_count_is_bounded_by_window_width() {
+    // 300k cells on one line: "ab你" is 3 chars and 4 cells.
+    let state = unwrapped(&"ab你".repeat(75_000));
+    let grid = state.get_viewport_content_styled_clipped(0, 1, 100_001, 80);
+    let line = &grid.lines[0];
+
+    assert!(line.cells.len() <= 80);
+    assert_eq!(line.visual_width(), 80);
+    assert_eq!(line.clip_first_char_offset, Some(75_001));
+    assert_eq!(line.cells[0].source, doc(75_001, 75_001));
+    assert_eq!(line.cells[0].ch, 'b');
+}
+
+#[test]
+fn test_scroll_left_reveals_x() {
+    let mut state = unwrapped(&"x".repeat(100));
+    assert_eq!(state.get_viewport_state().scroll_left, 0);
+
+    // Viewport width 10: revealing x=25 puts it in the last visible column.
+    state.reveal_x(25);
+    assert_eq!(state.get_viewport_state().scroll_left, 16);
+    state.reveal_x(20);
+    assert_eq!(state.get_viewport_state().scroll_left, 16);
+    state.reveal_x(3);
+    assert_eq!(state.get_viewport_state().scroll_left, 3);
+
+    state.set_scroll_left(40);
+    assert_eq!(state.get_viewport_state().scroll_left, 40);
+}
@@ -1,4 +1,7 @@
-use editor_core::{Command, CommandExecutor, CursorCommand, EditCommand, Position, ViewCommand};
+use editor_core::{
+    Command, CommandExecutor, CursorCommand, EditCommand, IndentProvider, IndentSettings,
+    LineIndex, Position, TabKeyBehavior, ViewCommand, detect_indentation,
+};
 
 #[test]
 fn test_indent_and_outdent_single_line_tab_mode() {
@@ -190,3 +193,241 @@ fn test_indent_outdent_multi_line_selection() {
 
     assert_eq!(executor.editor().get_text(), "a\nb\nc\n");
 }
+
+fn detect(text: &str) -> IndentSettings {
+    detect_indentation(&LineIndex::from_text(text))
+}
+
+#[test]
+fn test_detect_indentation_spaces_and_tabs() {
+    let two = "fn main() {\n  if x {\n    y();\n  }\n}\n";
+    let settings = detect(two);
+    assert!(!settings.use_tabs);
+    assert_eq!(settings.width, 2);
+    assert!(settings.confidence >= 90);
+
+    let four = "class A:\n    def f(self):\n        pass\n\n    def g(self):\n        return 1\n";
+    let settings = detect(four);
+    assert_eq!((settings.use_tabs, settings.width), (false, 4));
+
+    let tabs = "fn main() {\n\tif x {\n\t\ty();\n\t}\n}\n";
+    let settings = detect(tabs);
+    assert!(settings.use_tabs);
+    assert_eq!(settings.confidence, 100);
+}
+
+#[test]
+fn test_detect_indentation_edge_cases() {
+    // Entirely flat (or empty) documents give no evidence.
+    assert_eq!(detect("a\nb\nc\n"), IndentSettings::default());
+    assert_eq!(detect(""), IndentSettings::default());
+
+    // Continuation indents (3-space outliers) don't flip a 4-space file.
+    let continuation = "fn f(\n   a: u32,\n   b: u32,\n) {\n    let x = 1;\n    if x {\n        g(\n           1,\n        );\n    }\n}\n";
+    let settings = detect(continuation);
+    assert_eq!((settings.use_tabs, settings.width), (false, 4));
+    assert!(settings.confidence < 100);
+
+    // Mixed files follow the majority, with reduced confidence.
+    let mixed = "a\n\tb\n\tc\n\t\td\n    e\n";
+    let settings = detect(mixed);
+    assert!(settings.use_tabs);
+    assert_eq!(settings.confidence, 75);
+}
+
+#[test]
+fn test_apply_indent_settings_sets_tab_width_and_behavior() {
+    let mut executor = CommandExecutor::new("x", 80);
+    executor
+        .execute(Command::View(ViewCommand::ApplyIndentSettings {
+            settings: IndentSettings::spaces(2),
+        }))
+        .unwrap();
+    assert_eq!(executor.tab_key_behavior(), TabKeyBehavior::Spaces);
+    assert_eq!(executor.editor().layout_engine.tab_width(), 2);
+
+    executor
+        .execute(Command::Edit(EditCommand::InsertTab))
+        .unwrap();
+    assert_eq!(executor.editor().get_text(), "  x");
+
+    executor
+        .execute(Command::View(ViewCommand::ApplyIndentSettings {
+            settings: IndentSettings::tabs(8),
+        }))
+        .unwrap();
+    assert_eq!(executor.tab_key_behavior(), TabKeyBehavior::Tab);
+    assert_eq!(executor.editor().layout_engine.tab_width(), 8);
+
+    assert!(
+        executor
+            .execute(Command::View(ViewCommand::ApplyIndentSettings {
+                settings: IndentSettings::spaces(0),
+            }))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_reindent_document_is_single_undo_step() {
+    let original = "fn main() {\n\tif x {\n\t\ty(a,\n\t\t  b);\n\t}\n}\n";
+    let mut executor = CommandExecutor::new(original, 80);
+
+    executor
+        .execute(Command::Edit(EditCommand::ReindentDocument {
+            from: IndentSettings::tabs(4),
+            to: IndentSettings::spaces(2),
+        }))
+        .unwrap();
+    assert_eq!(
+        executor.editor().get_text(),
+        "fn main() {\n  if x {\n    y(a,\n      b);\n  }\n}\n"
+    );
+    assert_eq!(executor.undo_depth(), 1);
+
+    // Converting back measures levels with the new width, so the aligned line becomes 3 levels.
+    executor
+        .execute(Command::Edit(EditCommand::ReindentDocument {
+            from: IndentSettings::spaces(2),
+            to: IndentSettings::tabs(4),
+        }))
+        .unwrap();
+    assert_eq!(
+        executor.editor().get_text(),
+        "fn main() {\n\tif x {\n\t\ty(a,\n\t\t\tb);\n\t}\n}\n"
+    );
+
+    executor.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    executor.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(executor.editor().get_text(), original);
+}
+
+/// Indents one tab per `{` left open before the cursor; no opinion on lines ending in `;`.
+struct BraceIndent;
+
+impl IndentProvider for BraceIndent {
+    fn indent_for_new_line(&self, line_index: &LineIndex, cursor_offset: usize) -> Option<String> {
+        let before: String = line_index.get_text().chars().take(cursor_offset).collect();
+        if before.ends_with(';') {
+            return None;
+        }
+        let depth = before.matches('{').count() - before.matches('}').count();
+        Some("\t".repeat(depth))
+    }
+}
+
+#[test]
+fn test_insert_newline_consults_indent_provider() {
+    let mut executor = CommandExecutor::new("fn f() {\n  x;\n}", 80);
+    executor.set_indent_provider(Some(Box::new(BraceIndent)));
+    executor
+        .execute(Command::View(ViewCommand::SetTabKeyBehavior {
+            behavior: TabKeyBehavior::Spaces,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::View(ViewCommand::SetTabWidth { width: 4 }))
+        .unwrap();
+
+    executor
+        .execute(Command::Cursor(CursorCommand::MoveTo {
+            line: 0,
+            column: 8,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::Edit(EditCommand::InsertNewline {
+            auto_indent: true,
+        }))
+        .unwrap();
+    assert_eq!(executor.editor().get_text(), "fn f() {\n    \n  x;\n}");
+
+    // `None` falls back to copying the current line's whitespace.
+    executor
+        .execute(Command::Cursor(CursorCommand::MoveTo {
+            line: 2,
+            column: 4,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::Edit(EditCommand::InsertNewline {
+            auto_indent: true,
+        }))
+        .unwrap();
+    assert_eq!(executor.editor().get_text(), "fn f() {\n    \n  x;\n  \n}");
+
+    executor
+        .execute(Command::View(ViewCommand::SetTabKeyBehavior {
+            behavior: TabKeyBehavior::Tab,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::Cursor(CursorCommand::MoveTo {
+            line: 0,
+            column: 8,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::Edit(EditCommand::InsertNewline {
+            auto_indent: true,
+        }))
+        .unwrap();
+    assert!(executor.editor().get_text().starts_with("fn f() {\n\t\n"));
+}
+
+/// Paste `text` with `InsertTextReindented` at (`line`, `column`) using space indentation.
+fn paste_reindented(doc: &str, line: usize, column: usize, text: &str) -> CommandExecutor {
+    let mut executor = CommandExecutor::new(doc, 80);
+    executor
+        .execute(Command::View(ViewCommand::SetTabKeyBehavior {
+            behavior: TabKeyBehavior::Spaces,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::Cursor(CursorCommand::MoveTo { line, column }))
+        .unwrap();
+    executor
+        .execute(Command::Edit(EditCommand::InsertTextReindented {
+            text: text.to_string(),
+        }))
+        .unwrap();
+    executor
+}
+
+#[test]
+fn test_insert_text_reindented_adjusts_to_caret_line() {
+    // Copied with its original indentation (first line included).
+    let executor = paste_reindented(
+        "fn f() {\n    \n}",
+        1,
+        4,
+        "        if x {\n            y();\n\n        }\n",
+    );
+    assert_eq!(
+        executor.editor().get_text(),
+        "fn f() {\n    if x {\n        y();\n\n    }\n\n}"
+    );
+
+    // Copied from mid-line: the first line keeps the caret's column, the rest follows the
+    // caret line's indentation; tabs are rebuilt as spaces.
+    let executor = paste_reindented("    let a = ;", 0, 12, "vec![\n\t\t1,\n\t]");
+    assert_eq!(
+        executor.editor().get_text(),
+        "    let a = vec![\n        1,\n    ];"
+    );
+    assert_eq!(executor.editor().cursor_position(), Position::new(2, 5));
+
+    // A single line is inserted as-is.
+    let executor = paste_reindented("    x", 0, 5, "  y");
+    assert_eq!(executor.editor().get_text(), "    x  y");
+}
+
+#[test]
+fn test_insert_text_reindented_is_single_undo_step() {
+    let mut executor = paste_reindented("a\n\tb", 1, 2, "c\n  d\n  e");
+    assert_eq!(executor.editor().get_text(), "a\n\tbc\n    d\n    e");
+
+    executor.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(executor.editor().get_text(), "a\n\tb");
+    assert_eq!(executor.editor().cursor_position(), Position::new(1, 2));
+}
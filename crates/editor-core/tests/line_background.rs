@@ -0,0 +1,148 @@
+use editor_core::{
+    CURRENT_LINE_STYLE_ID, ComposedLineKind, Decoration, DecorationKind, DecorationLayerId,
+    DecorationPlacement, DecorationRange, EditorStateManager, ProcessingEdit,
+};
+
+fn line_background(start: usize, end: usize, style_id: u32) -> Decoration {
+    Decoration {
+        range: DecorationRange::new(start, end),
+        placement: DecorationPlacement::Before,
+        kind: DecorationKind::LineBackground { style_id },
+        text: None,
+        styles: Vec::new(),
+        tooltip: None,
+        data_json: None,
+    }
+}
+
+fn set_layer(manager: &mut EditorStateManager, layer: DecorationLayerId, items: Vec<Decoration>) {
+    manager.apply_processing_edits(vec![ProcessingEdit::ReplaceDecorations {
+        layer,
+        decorations: items,
+    }]);
+}
+
+#[test]
+fn test_line_background_covers_all_wrapped_segments() {
+    // Line 1 is 12 chars wide and wraps into 3 rows at width 5.
+    let mut manager = EditorStateManager::new("one\nabcdefghijkl\nend", 5);
+    let caret = manager.editor().line_index.position_to_char_offset(1, 3);
+    set_layer(
+        &mut manager,
+        DecorationLayerId::LINE_BACKGROUNDS,
+        vec![line_background(caret, caret, CURRENT_LINE_STYLE_ID)],
+    );
+
+    let grid = manager.get_viewport_content_styled(0, 10);
+    let rows: Vec<(usize, Option<u32>)> = grid
+        .lines
+        .iter()
+        .map(|l| (l.logical_line_index, l.background_style))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (0, None),
+            (1, Some(CURRENT_LINE_STYLE_ID)),
+            (1, Some(CURRENT_LINE_STYLE_ID)),
+            (1, Some(CURRENT_LINE_STYLE_ID)),
+            (2, None),
+        ]
+    );
+    // The background is per row; cells keep their own styles.
+    assert!(grid.lines[1].cells.iter().all(|c| c.styles.is_empty()));
+
+    let composed = manager.get_viewport_content_composed(0, 10);
+    let rows: Vec<(ComposedLineKind, Option<u32>)> = composed
+        .lines
+        .iter()
+        .map(|l| (l.kind, l.background_style))
+        .collect();
+    assert_eq!(rows.len(), 5);
+    assert!(rows[1..4].iter().all(|(kind, bg)| {
+        matches!(
+            kind,
+            ComposedLineKind::Document {
+                logical_line: 1,
+                ..
+            }
+        ) && *bg == Some(CURRENT_LINE_STYLE_ID)
+    }));
+    assert_eq!(rows[0].1, None);
+    assert_eq!(rows[4].1, None);
+    assert!(
+        composed.lines[1]
+            .cells
+            .iter()
+            .all(|c| c.styles.is_empty() && c.underline.is_none())
+    );
+}
+
+#[test]
+fn test_line_background_ranges_and_layer_order() {
+    let mut manager = EditorStateManager::new("a\nb\nc\nd\n", 80);
+    // "b\nc\n" ends at column 0 of line 3, which is therefore not covered.
+    set_layer(
+        &mut manager,
+        DecorationLayerId::DIAGNOSTICS,
+        vec![line_background(2, 6, 7)],
+    );
+    set_layer(
+        &mut manager,
+        DecorationLayerId::LINE_BACKGROUNDS,
+        vec![line_background(4, 4, 9), line_background(6, 7, 9)],
+    );
+
+    let grid = manager.get_viewport_content_styled(0, 5);
+    let backgrounds: Vec<Option<u32>> = grid.lines.iter().map(|l| l.background_style).collect();
+    // Lower layers win where decorations overlap.
+    assert_eq!(backgrounds, vec![None, Some(7), Some(7), Some(9), None]);
+
+    let composed = manager.get_viewport_content_composed(0, 5);
+    let composed_backgrounds: Vec<Option<u32>> =
+        composed.lines.iter().map(|l| l.background_style).collect();
+    assert_eq!(composed_backgrounds, backgrounds);
+}
+
+#[test]
+fn test_above_line_virtual_rows_have_no_line_background() {
+    let mut manager = EditorStateManager::new("x\ny\n", 80);
+    set_layer(
+        &mut manager,
+        DecorationLayerId::CODE_LENS,
+        vec![Decoration {
+            range: DecorationRange::new(2, 2),
+            placement: DecorationPlacement::AboveLine,
+            kind: DecorationKind::CodeLens,
+            text: Some("lens".to_string()),
+            styles: Vec::new(),
+            tooltip: None,
+            data_json: None,
+        }],
+    );
+    set_layer(
+        &mut manager,
+        DecorationLayerId::LINE_BACKGROUNDS,
+        vec![line_background(2, 2, CURRENT_LINE_STYLE_ID)],
+    );
+
+    let composed = manager.get_viewport_content_composed(0, 4);
+    let rows: Vec<(ComposedLineKind, Option<u32>)> = composed
+        .lines
+        .iter()
+        .map(|l| (l.kind, l.background_style))
+        .collect();
+    assert_eq!(
+        &rows[1..3],
+        &[
+            (ComposedLineKind::VirtualAboveLine { logical_line: 1 }, None),
+            (
+                ComposedLineKind::Document {
+                    logical_line: 1,
+                    visual_in_logical: 0
+                },
+                Some(CURRENT_LINE_STYLE_ID)
+            ),
+        ]
+    );
+}
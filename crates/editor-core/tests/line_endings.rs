@@ -37,3 +37,50 @@ fn test_cr_is_normalized_to_lf() {
     assert_eq!(manager.editor().line_index.get_line_text(0).unwrap(), "a");
     assert_eq!(manager.editor().line_index.get_line_text(1).unwrap(), "b");
 }
+
+#[test]
+fn test_mixed_line_endings_round_trip() {
+    let original = "a\r\nb\nc\r\n\nd";
+    let manager = EditorStateManager::new(original, 80);
+
+    assert_eq!(manager.editor().get_text(), "a\nb\nc\n\nd");
+    assert_eq!(manager.line_ending(), LineEnding::Mixed);
+
+    let line_index = &manager.editor().line_index;
+    assert_eq!(line_index.line_ending_at(0), Some(LineEnding::Crlf));
+    assert_eq!(line_index.line_ending_at(1), Some(LineEnding::Lf));
+    assert_eq!(line_index.line_ending_at(2), Some(LineEnding::Crlf));
+    assert_eq!(line_index.line_ending_at(3), Some(LineEnding::Lf));
+    assert_eq!(line_index.line_ending_at(4), None);
+
+    assert_eq!(line_index.get_text_with_original_endings(), original);
+    assert_eq!(manager.get_text_for_saving(), original);
+}
+
+#[test]
+fn test_mixed_line_endings_follow_edits() {
+    let mut manager = EditorStateManager::new("one\r\ntwo\nthree\r\n", 80);
+
+    // Splitting a CRLF line keeps CRLF on both halves.
+    manager
+        .execute(Command::Edit(EditCommand::Insert {
+            offset: 2,
+            text: "\n".to_string(),
+        }))
+        .unwrap();
+    assert_eq!(manager.get_text_for_saving(), "on\r\ne\r\ntwo\nthree\r\n");
+
+    // Joining "e" and "two" keeps the ending of the second line.
+    manager
+        .execute(Command::Edit(EditCommand::Delete {
+            start: 4,
+            length: 1,
+        }))
+        .unwrap();
+    assert_eq!(manager.editor().get_text(), "on\netwo\nthree\n");
+    assert_eq!(manager.get_text_for_saving(), "on\r\netwo\nthree\r\n");
+
+    // Choosing a single ending normalizes the whole document.
+    manager.set_line_ending(LineEnding::Lf);
+    assert_eq!(manager.get_text_for_saving(), "on\netwo\nthree\n");
+}
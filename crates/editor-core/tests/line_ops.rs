@@ -53,6 +53,121 @@ fn test_duplicate_lines_multi_cursor_disjoint_blocks() {
     assert_eq!(secondary[0].end, Position::new(4, 0));
 }
 
+fn selection(
+    start: (usize, usize),
+    end: (usize, usize),
+    direction: SelectionDirection,
+) -> Selection {
+    Selection {
+        start: Position::new(start.0, start.1),
+        end: Position::new(end.0, end.1),
+        direction,
+    }
+}
+
+#[test]
+fn test_duplicate_selection_selects_copies_with_multiple_selections() {
+    let mut ex = CommandExecutor::new(
+        "foo bar
+baz",
+        80,
+    );
+    ex.execute(Command::Cursor(CursorCommand::SetSelections {
+        selections: vec![
+            selection((0, 0), (0, 3), SelectionDirection::Forward),
+            selection((1, 3), (1, 0), SelectionDirection::Backward),
+            selection((0, 4), (0, 7), SelectionDirection::Forward),
+        ],
+        primary_index: 1,
+    }))
+    .unwrap();
+
+    ex.execute(Command::Edit(EditCommand::DuplicateSelection))
+        .unwrap();
+    assert_eq!(
+        ex.editor().get_text(),
+        "foofoo barbar
+bazbaz"
+    );
+
+    // Each copy is selected, keeping the original direction.
+    assert_eq!(
+        ex.editor().selection(),
+        Some(&selection((1, 6), (1, 3), SelectionDirection::Backward))
+    );
+    assert_eq!(ex.editor().cursor_position(), Position::new(1, 3));
+    assert_eq!(
+        ex.editor().secondary_selections(),
+        vec![
+            selection((0, 3), (0, 6), SelectionDirection::Forward),
+            selection((0, 10), (0, 13), SelectionDirection::Forward),
+        ]
+    );
+
+    let delta = ex.last_text_delta().unwrap();
+    let mut edits: Vec<_> = delta
+        .edits
+        .iter()
+        .map(|edit| (edit.start, edit.inserted_text.as_str()))
+        .collect();
+    edits.sort();
+    assert_eq!(edits, vec![(3, "foo"), (7, "bar"), (11, "baz")]);
+    assert_eq!(delta.after_char_count, delta.before_char_count + 9);
+
+    ex.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(
+        ex.editor().get_text(),
+        "foo bar
+baz"
+    );
+}
+
+#[test]
+fn test_duplicate_selection_mixes_carets_and_selections() {
+    let mut ex = CommandExecutor::new(
+        "ab
+cd", 80,
+    );
+    ex.execute(Command::Cursor(CursorCommand::SetSelections {
+        selections: vec![
+            caret(0, 1),
+            selection((1, 0), (1, 1), SelectionDirection::Forward),
+        ],
+        primary_index: 0,
+    }))
+    .unwrap();
+
+    // The caret duplicates its line; the selection duplicates its text.
+    ex.execute(Command::Edit(EditCommand::DuplicateSelection))
+        .unwrap();
+    assert_eq!(
+        ex.editor().get_text(),
+        "ab
+ab
+ccd"
+    );
+    assert_eq!(ex.editor().cursor_position(), Position::new(1, 1));
+    assert_eq!(
+        ex.editor().secondary_selections(),
+        vec![selection((2, 1), (2, 2), SelectionDirection::Forward)]
+    );
+}
+
+#[test]
+fn test_duplicate_selection_with_only_carets_duplicates_lines() {
+    let mut ex = CommandExecutor::new("a\nb\nc", 80);
+    ex.execute(Command::Cursor(CursorCommand::MoveTo {
+        line: 1,
+        column: 0,
+    }))
+    .unwrap();
+
+    ex.execute(Command::Edit(EditCommand::DuplicateSelection))
+        .unwrap();
+    assert_eq!(ex.editor().get_text(), "a\nb\nb\nc");
+    assert_eq!(ex.editor().cursor_position(), Position::new(2, 0));
+}
+
 #[test]
 fn test_delete_lines_removes_selected_line() {
     let mut ex = CommandExecutor::new("a\nb\nc", 80);
@@ -0,0 +1,89 @@
+use editor_core::intervals::Interval;
+use editor_core::{EditorStateManager, FoldRegion, MinimapCell, StyleLayerId};
+
+#[test]
+fn test_minimap_buckets_lines_and_picks_dominant_styles() {
+    // Lines:      0        1           2       3      4
+    let text = "fn a() {\n    x = 1;\n\n  }\nend";
+    let mut state = EditorStateManager::new(text, 80);
+    // `fn` keyword (style 1) and the whole of line 1 as style 2, `end` as style 3.
+    state.replace_style_layer(
+        StyleLayerId::SIMPLE_SYNTAX,
+        vec![
+            Interval::new(0, 2, 1),
+            Interval::new(9, 19, 2),
+            Interval::new(24, 27, 3),
+        ],
+    );
+    // A second layer over `x`: the lower style id wins for that character.
+    state.replace_style_layer(
+        StyleLayerId::SEMANTIC_TOKENS,
+        vec![Interval::new(13, 14, 5)],
+    );
+
+    let minimap = state.get_minimap(2, 6);
+    assert_eq!(minimap.visible_lines, 5);
+    assert_eq!(minimap.rows.len(), 3);
+
+    let first = &minimap.rows[0];
+    assert_eq!(
+        (first.start_line, first.end_line, first.line_count),
+        (0, 1, 2)
+    );
+    // Style 2 covers 5 non-whitespace chars of line 1, style 1 only 2.
+    assert_eq!(first.dominant_style, Some(2));
+    assert_eq!(first.indent, Some(0));
+    assert_eq!(first.cells.len(), 6);
+    assert_eq!(
+        first.cells[0],
+        MinimapCell {
+            occupancy: 1,
+            style: Some(1)
+        }
+    );
+    // Column 4: `(` of line 0 (unstyled) and `x` of line 1 (style 2).
+    assert_eq!(
+        first.cells[4],
+        MinimapCell {
+            occupancy: 2,
+            style: Some(2)
+        }
+    );
+
+    let second = &minimap.rows[1];
+    assert_eq!((second.start_line, second.end_line), (2, 3));
+    assert_eq!(second.dominant_style, None);
+    assert_eq!(second.indent, Some(2));
+    assert_eq!(second.cells[2].occupancy, 1);
+
+    let last = &minimap.rows[2];
+    assert_eq!((last.start_line, last.end_line, last.line_count), (4, 4, 1));
+    assert_eq!(last.dominant_style, Some(3));
+}
+
+#[test]
+fn test_minimap_excludes_collapsed_lines() {
+    let text = "a\n  b\n  c\nd\ne\n";
+    let mut state = EditorStateManager::new(text, 80);
+    let mut region = FoldRegion::new(0, 2);
+    region.collapse();
+    state.replace_folding_regions(vec![region], false);
+
+    let minimap = state.get_minimap(2, 4);
+    // Visible: 0, 3, 4 and the empty last line.
+    assert_eq!(minimap.visible_lines, 4);
+    assert_eq!(
+        minimap
+            .rows
+            .iter()
+            .map(|row| (row.start_line, row.end_line, row.line_count))
+            .collect::<Vec<_>>(),
+        vec![(0, 3, 2), (4, 5, 2)]
+    );
+    // The hidden indented lines don't contribute.
+    assert_eq!(minimap.rows[0].indent, Some(0));
+    assert_eq!(minimap.rows[0].cells[2].occupancy, 0);
+
+    // `rows_per_cell` of 0 is treated as 1.
+    assert_eq!(state.get_minimap(0, 4).rows.len(), 4);
+}
@@ -1,6 +1,6 @@
 use editor_core::{
-    Command, CursorCommand, EditCommand, OpenBufferResult, Position, ViewSmoothScrollState,
-    Workspace,
+    Command, CursorCommand, EditCommand, OpenBufferResult, Position, ScrollLinkMode, ViewCommand,
+    ViewSmoothScrollState, Workspace,
 };
 
 #[test]
@@ -96,3 +96,62 @@ fn test_two_views_share_buffer_but_keep_independent_view_state() {
     assert_eq!(viewport.smooth_scroll.sub_row_offset, 123);
     assert_eq!(viewport.prefetch_lines, 0..4);
 }
+
+#[test]
+fn test_linked_views_scroll_by_logical_line() {
+    let mut ws = Workspace::new();
+    let OpenBufferResult {
+        buffer_id,
+        view_id: view_a,
+    } = ws
+        .open_buffer(None, "aaaaaaaaaa\nbb\ncccccccccc\nd\n", 10)
+        .unwrap();
+    // At width 5 the long lines wrap into two rows each.
+    let view_b = ws.create_view(buffer_id, 5).unwrap();
+
+    ws.link_views(view_a, view_b, ScrollLinkMode::Follow)
+        .unwrap();
+    assert_eq!(
+        ws.linked_view(view_b),
+        Some((view_a, ScrollLinkMode::Follow))
+    );
+
+    ws.set_scroll_top(view_a, 2).unwrap();
+    assert_eq!(ws.scroll_top_for_view(view_b).unwrap(), 3);
+
+    // The follower can scroll on its own without moving the leader.
+    ws.set_scroll_top(view_b, 1).unwrap();
+    assert_eq!(ws.scroll_top_for_view(view_a).unwrap(), 2);
+
+    // Bidirectional links map the second half of a wrapped line to the same logical line.
+    ws.link_views(view_a, view_b, ScrollLinkMode::Bidirectional)
+        .unwrap();
+    ws.set_scroll_top(view_b, 4).unwrap();
+    assert_eq!(ws.scroll_top_for_view(view_a).unwrap(), 2);
+
+    // Edits keep the link; re-wrapping a view re-syncs its partner.
+    ws.execute(
+        view_a,
+        Command::Edit(EditCommand::InsertText {
+            text: "x".to_string(),
+        }),
+    )
+    .unwrap();
+    ws.execute(
+        view_a,
+        Command::View(ViewCommand::SetViewportWidth { width: 5 }),
+    )
+    .unwrap();
+    assert_eq!(ws.scroll_top_for_view(view_a).unwrap(), 2);
+    assert_eq!(ws.scroll_top_for_view(view_b).unwrap(), 2);
+
+    assert!(ws.unlink_views(view_b, view_a));
+    assert!(!ws.unlink_views(view_a, view_b));
+    ws.set_scroll_top(view_a, 0).unwrap();
+    assert_eq!(ws.scroll_top_for_view(view_b).unwrap(), 2);
+
+    ws.link_views(view_a, view_b, ScrollLinkMode::Bidirectional)
+        .unwrap();
+    ws.close_view(view_b).unwrap();
+    assert_eq!(ws.linked_view(view_a), None);
+}
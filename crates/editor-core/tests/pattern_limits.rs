@@ -0,0 +1,183 @@
+use editor_core::pattern::{CompiledPattern, compile_pattern};
+use editor_core::search::find_all;
+use editor_core::{
+    Command, CommandError, CommandExecutor, CursorCommand, PatternError, PatternLimits,
+    PatternOptions, SearchError, SearchOptions,
+};
+
+fn word(i: usize) -> String {
+    // Distinct lowercase words of varying length ("w0a", "w1ab", ...).
+    let suffix: String = (0..(i % 5) + 1)
+        .map(|n| char::from(b'a' + ((i + n) % 26) as u8))
+        .collect();
+    format!("w{}{}", i, suffix)
+}
+
+fn small_limits() -> PatternLimits {
+    PatternLimits {
+        size_limit: 64 * 1024,
+        ..PatternLimits::default()
+    }
+}
+
+#[test]
+fn test_large_literal_alternation_falls_back_to_literal_set() {
+    let words: Vec<String> = (0..10_000).map(word).collect();
+    let pattern = words.join("|");
+
+    // Case-sensitive literal alternations are special-cased by `regex`; case-insensitive ones
+    // go through the NFA compiler and hit the size limit.
+    let compiled = compile_pattern(
+        &pattern,
+        PatternOptions {
+            case_insensitive: true,
+            limits: small_limits(),
+            ..PatternOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(matches!(compiled, CompiledPattern::Literals(ref set) if set.len() == 10_000));
+
+    let mut text = String::new();
+    for i in (0..12_000).step_by(7) {
+        text.push_str(&word(i));
+        text.push_str(" filler é ");
+        text.push_str(&word(i + 3).to_uppercase());
+        text.push(' ');
+        // Prefix overlaps (w1 vs w10...) exercise leftmost-first ordering.
+        text.push_str(&format!("w{} ", i / 3));
+    }
+
+    let regex_options = SearchOptions {
+        case_sensitive: false,
+        regex: true,
+        limits: PatternLimits {
+            size_limit: 256 * (1 << 20),
+            dfa_size_limit: 64 * (1 << 20),
+        },
+        ..SearchOptions::default()
+    };
+    let fallback_options = SearchOptions {
+        case_sensitive: false,
+        regex: true,
+        limits: small_limits(),
+        ..SearchOptions::default()
+    };
+
+    let expected = find_all(&text, &pattern, regex_options).unwrap();
+    let actual = find_all(&text, &pattern, fallback_options).unwrap();
+    assert!(!expected.is_empty());
+    assert_eq!(actual, expected);
+
+    let whole_word = SearchOptions {
+        whole_word: true,
+        ..fallback_options
+    };
+    let expected = find_all(
+        &text,
+        &pattern,
+        SearchOptions {
+            whole_word: true,
+            ..regex_options
+        },
+    )
+    .unwrap();
+    assert_eq!(find_all(&text, &pattern, whole_word).unwrap(), expected);
+}
+
+#[test]
+fn test_case_insensitive_fallback_matches_regex_folding() {
+    let words: Vec<String> = (0..2_000).map(word).collect();
+    let pattern = words.join("|");
+    // U+212A KELVIN SIGN folds to 'k' under Unicode simple case folding.
+    let text = "W10KL w11lm W1999EFGHI xx W5F w36\u{212A}l";
+
+    let options = |limits| SearchOptions {
+        case_sensitive: false,
+        regex: true,
+        limits,
+        ..SearchOptions::default()
+    };
+
+    let expected = find_all(text, &pattern, options(PatternLimits::default())).unwrap();
+    let actual = find_all(
+        text,
+        &pattern,
+        options(PatternLimits {
+            size_limit: 4 * 1024,
+            ..PatternLimits::default()
+        }),
+    )
+    .unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_complex_pattern_reports_typed_error() {
+    let err = find_all(
+        "abc",
+        r"\w{1000}\w{1000}",
+        SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        },
+    )
+    .unwrap_err();
+    let SearchError::TooComplex { limit, estimate } = err else {
+        panic!("expected SearchError::TooComplex, got {err:?}");
+    };
+    assert_eq!(limit, PatternLimits::default().size_limit);
+    assert!(estimate >= limit);
+    assert!(err.to_string().contains("too complex"));
+
+    // The estimate is measured: the pattern does not fit in it, but fits in twice as much.
+    let limits = |size_limit| PatternOptions {
+        limits: PatternLimits {
+            size_limit,
+            ..PatternLimits::default()
+        },
+        ..PatternOptions::default()
+    };
+    let err = compile_pattern("[a-z]{100}", limits(1024)).unwrap_err();
+    let PatternError::TooComplex { estimate, .. } = err else {
+        panic!("expected PatternError::TooComplex, got {err:?}");
+    };
+    assert!(estimate > 1024);
+    assert!(compile_pattern("[a-z]{100}", limits(estimate)).is_err());
+    assert!(compile_pattern("[a-z]{100}", limits(estimate * 2)).is_ok());
+
+    let err = compile_pattern(
+        "[a-z]{200}",
+        PatternOptions {
+            limits: PatternLimits {
+                size_limit: 1024,
+                ..PatternLimits::default()
+            },
+            ..PatternOptions::default()
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, PatternError::TooComplex { limit: 1024, .. }));
+}
+
+#[test]
+fn test_find_command_surfaces_too_complex_message() {
+    let mut executor = CommandExecutor::new("abc", 80);
+    let err = executor
+        .execute(Command::Cursor(CursorCommand::FindNext {
+            query: "[a-z]{200}".to_string(),
+            options: SearchOptions {
+                regex: true,
+                limits: PatternLimits {
+                    size_limit: 1024,
+                    ..PatternLimits::default()
+                },
+                ..SearchOptions::default()
+            },
+        }))
+        .unwrap_err();
+    let CommandError::Other(message) = err else {
+        panic!("expected CommandError::Other");
+    };
+    assert!(message.contains("too complex"), "{message}");
+}
@@ -0,0 +1,144 @@
+use editor_core::intervals::{FoldRegion, Interval};
+use editor_core::{
+    Command, EditCommand, EditorStateManager, ProcessingEdit, ProcessingEditBatch, StaleBatchError,
+    StaleBatchPolicy, StyleLayerId,
+};
+
+const LAYER: StyleLayerId = StyleLayerId::SIMPLE_SYNTAX;
+
+fn insert(state: &mut EditorStateManager, offset: usize, text: &str) {
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: text.to_string(),
+        }))
+        .unwrap();
+}
+
+fn text_version(state: &EditorStateManager) -> u64 {
+    state.get_document_state().text_version
+}
+
+/// Highlight the given ranges (style id = start offset, to tell them apart).
+fn highlight(version: u64, ranges: &[(usize, usize)]) -> ProcessingEditBatch {
+    ProcessingEditBatch::new(
+        version,
+        vec![ProcessingEdit::ReplaceStyleLayer {
+            layer: LAYER,
+            intervals: ranges
+                .iter()
+                .map(|&(start, end)| Interval::new(start, end, start as u32))
+                .collect(),
+        }],
+    )
+}
+
+fn highlighted(state: &EditorStateManager) -> Vec<(usize, usize)> {
+    state
+        .editor()
+        .style_layers
+        .get(&LAYER)
+        .map(|tree| {
+            tree.query_range(0, usize::MAX)
+                .into_iter()
+                .map(|interval| (interval.start, interval.end))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_stale_batch_rejected() {
+    let mut state = EditorStateManager::new("let x = 1;\n", 80);
+    let version = text_version(&state);
+    let batch = highlight(version, &[(4, 5)]);
+
+    insert(&mut state, 0, "// hi\n");
+    assert_eq!(
+        state.apply_processing_batch(batch),
+        Err(StaleBatchError {
+            batch_version: version,
+            current_version: version + 1,
+        })
+    );
+    assert!(highlighted(&state).is_empty());
+
+    // A batch for the current text applies; future versions are rejected.
+    let current = text_version(&state);
+    state
+        .apply_processing_batch(highlight(current, &[(10, 11)]))
+        .unwrap();
+    assert_eq!(highlighted(&state), vec![(10, 11)]);
+    assert!(
+        state
+            .apply_processing_batch(highlight(current + 1, &[(0, 1)]))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_stale_batch_shifted_across_insert_above() {
+    let mut state = EditorStateManager::new("fn main() {}\nlet x = 1;\n", 80);
+    state.set_stale_batch_policy(StaleBatchPolicy::Shift);
+    let version = text_version(&state);
+    // `let` and `x` on the second line.
+    let mut batch = highlight(version, &[(13, 16), (17, 18)]);
+    batch.edits.push(ProcessingEdit::ReplaceFoldingRegions {
+        regions: vec![FoldRegion::new(0, 1)],
+        preserve_collapsed: false,
+    });
+
+    insert(&mut state, 0, "// hi\n");
+    assert_eq!(state.delta_journal_since(version).unwrap().len(), 1);
+    state.apply_processing_batch(batch).unwrap();
+
+    assert_eq!(highlighted(&state), vec![(19, 22), (23, 24)]);
+    let text = state.editor().get_text();
+    let chars: Vec<char> = text.chars().collect();
+    assert_eq!(chars[19..22].iter().collect::<String>(), "let");
+    // Line-based edits cannot be shifted and are dropped.
+    assert!(state.editor().folding_manager.regions().is_empty());
+}
+
+#[test]
+fn test_shift_clips_ranges_to_surviving_text() {
+    let mut state = EditorStateManager::new("abcdefgh", 80);
+    state.set_stale_batch_policy(StaleBatchPolicy::Shift);
+    let batch = highlight(text_version(&state), &[(0, 2), (2, 5), (6, 8)]);
+
+    // Delete `cd`, then insert at the start of `gh`.
+    state
+        .execute(Command::Edit(EditCommand::Delete {
+            start: 2,
+            length: 2,
+        }))
+        .unwrap();
+    insert(&mut state, 4, "__");
+    state.apply_processing_batch(batch).unwrap();
+
+    // `ab` | `e` (was `cde`) | `gh` after the inserted text.
+    assert_eq!(highlighted(&state), vec![(0, 2), (2, 3), (6, 8)]);
+}
+
+#[test]
+fn test_delta_journal_is_bounded() {
+    let mut state = EditorStateManager::new("", 80);
+    state.set_delta_journal_capacity(2);
+    let start = text_version(&state);
+    for _ in 0..3 {
+        insert(&mut state, 0, "a");
+    }
+    let current = text_version(&state);
+
+    assert_eq!(state.delta_journal_since(current), Some(Vec::new()));
+    assert_eq!(state.delta_journal_since(current - 2).unwrap().len(), 2);
+    assert!(state.delta_journal_since(start).is_none());
+    assert!(state.delta_journal_since(current + 1).is_none());
+
+    state.set_stale_batch_policy(StaleBatchPolicy::Shift);
+    assert!(
+        state
+            .apply_processing_batch(highlight(start, &[(0, 1)]))
+            .is_err()
+    );
+}
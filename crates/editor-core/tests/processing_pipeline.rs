@@ -0,0 +1,210 @@
+use editor_core::intervals::Interval;
+use editor_core::{
+    Command, DocumentProcessor, EditCommand, EditorStateManager, PipelineError, ProcessingEdit,
+    ProcessorFailure, ProcessorInput, ProcessorSpec, StyleLayerId,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Log = Rc<RefCell<Vec<&'static str>>>;
+
+/// Logs each run and returns fixed edits (or an error).
+struct Recorder {
+    name: &'static str,
+    log: Log,
+    edits: Vec<ProcessingEdit>,
+    fail: bool,
+}
+
+impl Recorder {
+    fn new(name: &'static str, log: &Log) -> Self {
+        Self {
+            name,
+            log: log.clone(),
+            edits: Vec::new(),
+            fail: false,
+        }
+    }
+
+    fn styling(mut self, layer: StyleLayerId) -> Self {
+        self.edits.push(ProcessingEdit::ReplaceStyleLayer {
+            layer,
+            intervals: vec![Interval::new(0, 1, 1)],
+        });
+        self
+    }
+}
+
+impl DocumentProcessor for Recorder {
+    type Error = String;
+
+    fn process(&mut self, _state: &EditorStateManager) -> Result<Vec<ProcessingEdit>, String> {
+        self.log.borrow_mut().push(self.name);
+        if self.fail {
+            return Err(format!("{} failed", self.name));
+        }
+        Ok(self.edits.clone())
+    }
+}
+
+fn take(log: &Log) -> Vec<&'static str> {
+    std::mem::take(&mut *log.borrow_mut())
+}
+
+#[test]
+fn test_pipeline_skips_processors_with_unchanged_input() {
+    let log = Log::default();
+    let mut state = EditorStateManager::new("hello", 80);
+    let pipeline = state.pipeline_mut();
+    pipeline
+        .register(
+            ProcessorSpec::new("poll").with_input(ProcessorInput::Always),
+            Recorder::new("poll", &log),
+        )
+        .unwrap();
+    pipeline
+        .register(
+            ProcessorSpec::new("syntax")
+                .with_layers([StyleLayerId::SIMPLE_SYNTAX])
+                .with_priority(-1),
+            Recorder::new("syntax", &log).styling(StyleLayerId::SIMPLE_SYNTAX),
+        )
+        .unwrap();
+
+    // Ascending priority.
+    assert!(state.run_pipeline().is_empty());
+    assert_eq!(take(&log), vec!["syntax", "poll"]);
+
+    // The style edits bumped the state version but not the text version.
+    assert!(state.run_pipeline().is_empty());
+    assert_eq!(take(&log), vec!["poll"]);
+
+    state
+        .execute(Command::Edit(EditCommand::Insert {
+            offset: 5,
+            text: "!".to_string(),
+        }))
+        .unwrap();
+    state.run_pipeline();
+    assert_eq!(take(&log), vec!["syntax", "poll"]);
+
+    state.pipeline_mut().invalidate();
+    state.run_pipeline();
+    assert_eq!(take(&log), vec!["syntax", "poll"]);
+
+    assert!(state.pipeline_mut().remove("poll"));
+    assert!(!state.pipeline_mut().remove("poll"));
+    state.pipeline_mut().invalidate();
+    state.run_pipeline();
+    assert_eq!(take(&log), vec!["syntax"]);
+}
+
+#[test]
+fn test_pipeline_rejects_layer_conflicts() {
+    let log = Log::default();
+    let mut state = EditorStateManager::new("", 80);
+    let pipeline = state.pipeline_mut();
+    let layer = StyleLayerId::new(100);
+    let shared = StyleLayerId::new(101);
+
+    pipeline
+        .register(
+            ProcessorSpec::new("a").with_layers([layer]),
+            Recorder::new("a", &log),
+        )
+        .unwrap();
+    assert_eq!(
+        pipeline.register(
+            ProcessorSpec::new("b")
+                .with_layers([shared, layer])
+                .additive(),
+            Recorder::new("b", &log),
+        ),
+        Err(PipelineError::LayerConflict {
+            layer,
+            processor: "b".to_string(),
+            owner: "a".to_string(),
+        })
+    );
+    assert_eq!(
+        pipeline.register(ProcessorSpec::new("a"), Recorder::new("a", &log)),
+        Err(PipelineError::DuplicateName("a".to_string()))
+    );
+
+    // Additive processors may share a layer.
+    pipeline
+        .register(
+            ProcessorSpec::new("c").with_layers([shared]).additive(),
+            Recorder::new("c", &log),
+        )
+        .unwrap();
+    pipeline
+        .register(
+            ProcessorSpec::new("d").with_layers([shared]).additive(),
+            Recorder::new("d", &log),
+        )
+        .unwrap();
+    assert!(
+        pipeline
+            .register(
+                ProcessorSpec::new("e").with_layers([shared]),
+                Recorder::new("e", &log),
+            )
+            .is_err()
+    );
+    assert_eq!(
+        pipeline
+            .specs()
+            .map(|spec| spec.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["a", "c", "d"]
+    );
+}
+
+#[test]
+fn test_pipeline_collects_failures_and_keeps_running() {
+    let log = Log::default();
+    let mut state = EditorStateManager::new("hello", 80);
+    let declared = StyleLayerId::new(100);
+    let foreign = StyleLayerId::new(101);
+    let mut failing = Recorder::new("failing", &log);
+    failing.fail = true;
+    state
+        .pipeline_mut()
+        .register(ProcessorSpec::new("failing").with_priority(0), failing)
+        .unwrap();
+    state
+        .pipeline_mut()
+        .register(
+            ProcessorSpec::new("sloppy")
+                .with_layers([declared])
+                .with_priority(1),
+            Recorder::new("sloppy", &log)
+                .styling(declared)
+                .styling(foreign),
+        )
+        .unwrap();
+
+    let failures = state.run_pipeline();
+    assert_eq!(
+        failures,
+        vec![
+            ProcessorFailure {
+                name: "failing".to_string(),
+                message: "failing failed".to_string(),
+            },
+            ProcessorFailure {
+                name: "sloppy".to_string(),
+                message: "wrote undeclared style layer 101".to_string(),
+            },
+        ]
+    );
+    assert_eq!(take(&log), vec!["failing", "sloppy"]);
+    let layers = &state.editor().style_layers;
+    assert!(layers.get(&declared).is_some_and(|tree| !tree.is_empty()));
+    assert!(layers.get(&foreign).is_none_or(|tree| tree.is_empty()));
+
+    // A failed processor runs again; the other one is up to date.
+    assert_eq!(state.run_pipeline().len(), 1);
+    assert_eq!(take(&log), vec!["failing"]);
+}
@@ -0,0 +1,200 @@
+use editor_core::search::find_all;
+use editor_core::{
+    Command, CursorCommand, EditCommand, EditorStateManager, SEARCH_CURRENT_MATCH_STYLE_ID,
+    SEARCH_MATCH_STYLE_ID, SearchOptions, StyleLayerId,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn layer(manager: &EditorStateManager) -> Vec<(usize, usize, u32)> {
+    let Some(tree) = manager
+        .editor()
+        .style_layers
+        .get(&StyleLayerId::SEARCH_MATCHES)
+    else {
+        return Vec::new();
+    };
+    let mut intervals: Vec<(usize, usize, u32)> = tree
+        .query_range(0, usize::MAX)
+        .iter()
+        .map(|i| (i.start, i.end, i.style_id))
+        .collect();
+    intervals.sort_unstable();
+    intervals
+}
+
+fn assert_in_sync(manager: &EditorStateManager, query: &str, options: SearchOptions) {
+    let text = manager.editor().get_text();
+    let expected = find_all(&text, query, options).unwrap();
+    assert_eq!(
+        manager.search_highlight_matches(),
+        expected.as_slice(),
+        "text {text:?}"
+    );
+    let spans: Vec<(usize, usize)> = layer(manager).iter().map(|&(s, e, _)| (s, e)).collect();
+    let expected_spans: Vec<(usize, usize)> = expected.iter().map(|m| (m.start, m.end)).collect();
+    assert_eq!(spans, expected_spans);
+}
+
+fn insert(manager: &mut EditorStateManager, offset: usize, text: &str) {
+    manager
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: text.to_string(),
+        }))
+        .unwrap();
+}
+
+#[test]
+fn test_search_highlight_tracks_edits() {
+    let mut manager = EditorStateManager::new("foo bar\nbaz foo\n", 80);
+    let options = SearchOptions::default();
+    manager
+        .set_search_highlight(Some(("foo".to_string(), options)))
+        .unwrap();
+
+    let state = manager.get_style_state();
+    assert_eq!(state.search_query.as_deref(), Some("foo"));
+    assert_eq!(state.search_match_count, 2);
+    assert_eq!(
+        layer(&manager),
+        vec![
+            (0, 3, SEARCH_MATCH_STYLE_ID),
+            (12, 15, SEARCH_MATCH_STYLE_ID)
+        ]
+    );
+
+    // Create a match by completing a partial word.
+    insert(&mut manager, 8, "fo");
+    assert_in_sync(&manager, "foo", options);
+    insert(&mut manager, 10, "o ");
+    assert_in_sync(&manager, "foo", options);
+    assert_eq!(manager.get_style_state().search_match_count, 3);
+
+    // Destroy a match by splitting it across lines.
+    insert(&mut manager, 1, "\n");
+    assert_in_sync(&manager, "foo", options);
+    assert_eq!(manager.get_style_state().search_match_count, 2);
+
+    manager.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_in_sync(&manager, "foo", options);
+    assert_eq!(manager.get_style_state().search_match_count, 3);
+
+    manager.set_search_highlight(None).unwrap();
+    assert!(layer(&manager).is_empty());
+    let state = manager.get_style_state();
+    assert_eq!(state.search_query, None);
+    assert_eq!(state.search_match_count, 0);
+}
+
+#[test]
+fn test_current_match_follows_find_next_and_prev() {
+    let mut manager = EditorStateManager::new("ab ab ab", 80);
+    let options = SearchOptions::default();
+    manager
+        .set_search_highlight(Some(("ab".to_string(), options)))
+        .unwrap();
+
+    let find = |manager: &mut EditorStateManager, next: bool| {
+        let query = "ab".to_string();
+        let command = if next {
+            CursorCommand::FindNext { query, options }
+        } else {
+            CursorCommand::FindPrev { query, options }
+        };
+        manager.execute(Command::Cursor(command)).unwrap();
+    };
+    let current = |manager: &EditorStateManager| -> Vec<usize> {
+        layer(manager)
+            .iter()
+            .filter(|(_, _, style)| *style == SEARCH_CURRENT_MATCH_STYLE_ID)
+            .map(|(start, _, _)| *start)
+            .collect()
+    };
+
+    assert!(current(&manager).is_empty());
+    find(&mut manager, true);
+    assert_eq!(current(&manager), vec![0]);
+    find(&mut manager, true);
+    assert_eq!(current(&manager), vec![3]);
+    find(&mut manager, true);
+    assert_eq!(current(&manager), vec![6]);
+    find(&mut manager, false);
+    assert_eq!(current(&manager), vec![3]);
+
+    manager
+        .execute(Command::Cursor(CursorCommand::ClearSelection))
+        .unwrap();
+    assert!(current(&manager).is_empty());
+    assert_eq!(layer(&manager).len(), 3);
+}
+
+#[test]
+fn test_search_highlight_stays_in_sync_under_random_edits() {
+    let queries = [
+        ("ab", SearchOptions::default()),
+        (
+            "ab",
+            SearchOptions {
+                whole_word: true,
+                ..SearchOptions::default()
+            },
+        ),
+        (
+            r"^a\w*",
+            SearchOptions {
+                regex: true,
+                ..SearchOptions::default()
+            },
+        ),
+        (
+            r"b\s+a",
+            SearchOptions {
+                regex: true,
+                ..SearchOptions::default()
+            },
+        ),
+    ];
+    let pieces = ["a", "b", "ab", " ", "\n", "\r\n", "xab"];
+    let mut rng = StdRng::seed_from_u64(0x5ea_7c4);
+
+    for (query, options) in queries {
+        let mut manager = EditorStateManager::new("ab cab\nab\n", 80);
+        manager
+            .set_search_highlight(Some((query.to_string(), options)))
+            .unwrap();
+
+        for _ in 0..150 {
+            let len = manager.editor().char_count();
+            if len > 0 && rng.gen_bool(0.35) {
+                let start = rng.gen_range(0..len);
+                let length = rng.gen_range(1..=(len - start).min(4));
+                manager
+                    .execute(Command::Edit(EditCommand::Delete { start, length }))
+                    .unwrap();
+            } else if rng.gen_bool(0.1) {
+                let _ = manager.execute(Command::Edit(EditCommand::Undo));
+            } else {
+                let offset = rng.gen_range(0..=len);
+                let piece = pieces[rng.gen_range(0..pieces.len())];
+                insert(&mut manager, offset, piece);
+            }
+            assert_in_sync(&manager, query, options);
+        }
+    }
+}
+
+#[test]
+fn test_invalid_search_highlight_query_is_reported() {
+    let mut manager = EditorStateManager::new("abc", 80);
+    let options = SearchOptions {
+        regex: true,
+        ..SearchOptions::default()
+    };
+    assert!(
+        manager
+            .set_search_highlight(Some(("(".to_string(), options)))
+            .is_err()
+    );
+    assert_eq!(manager.get_style_state().search_query, None);
+}
@@ -0,0 +1,269 @@
+use editor_core::{
+    Command, CommandExecutor, CursorCommand, EditCommand, EditorStateManager, Position, Selection,
+    SelectionDirection, SelectionMergePolicy,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn caret(line: usize, column: usize) -> Selection {
+    let pos = Position::new(line, column);
+    Selection {
+        start: pos,
+        end: pos,
+        direction: SelectionDirection::Forward,
+    }
+}
+
+fn range(start: Position, end: Position) -> Selection {
+    Selection {
+        start,
+        end,
+        direction: if start <= end {
+            SelectionDirection::Forward
+        } else {
+            SelectionDirection::Backward
+        },
+    }
+}
+
+fn min_max(sel: &Selection) -> (Position, Position) {
+    if sel.start <= sel.end {
+        (sel.start, sel.end)
+    } else {
+        (sel.end, sel.start)
+    }
+}
+
+fn all_policies() -> Vec<SelectionMergePolicy> {
+    let mut out = Vec::new();
+    for bits in 0..8u8 {
+        out.push(SelectionMergePolicy {
+            merge_overlapping: bits & 1 != 0,
+            merge_touching: bits & 2 != 0,
+            merge_carets_at_same_position: bits & 4 != 0,
+        });
+    }
+    out
+}
+
+fn set_selections(
+    manager: &mut EditorStateManager,
+    selections: Vec<Selection>,
+    primary_index: usize,
+) -> (Vec<Selection>, usize) {
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections,
+            primary_index,
+        }))
+        .unwrap();
+    let state = manager.get_cursor_state();
+    (state.selections, state.primary_selection_index)
+}
+
+fn assert_policy_compliant(selections: &[Selection], policy: SelectionMergePolicy) {
+    for pair in selections.windows(2) {
+        let (a_min, a_max) = min_max(&pair[0]);
+        let (b_min, b_max) = min_max(&pair[1]);
+        assert!(
+            (a_min, a_max) <= (b_min, b_max),
+            "selections not sorted: {:?}",
+            selections
+        );
+
+        let both_carets = a_min == a_max && b_min == b_max;
+        if b_min < a_max {
+            assert!(!policy.merge_overlapping, "overlap kept: {:?}", pair);
+        } else if b_min == a_max {
+            if both_carets {
+                assert!(
+                    !policy.merge_carets_at_same_position,
+                    "duplicate carets kept: {:?}",
+                    pair
+                );
+            } else {
+                assert!(!policy.merge_touching, "touching kept: {:?}", pair);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_default_policy_keeps_touching_and_merges_duplicates() {
+    let mut executor = CommandExecutor::new("abcdef", 80);
+    assert_eq!(
+        executor.selection_merge_policy(),
+        SelectionMergePolicy::default()
+    );
+
+    executor
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![
+                range(Position::new(0, 0), Position::new(0, 2)),
+                range(Position::new(0, 2), Position::new(0, 4)),
+                caret(0, 5),
+                caret(0, 5),
+            ],
+            primary_index: 0,
+        }))
+        .unwrap();
+
+    assert_eq!(executor.editor().secondary_selections().len(), 2);
+}
+
+#[test]
+fn test_aggressive_policy_merges_touching_ranges() {
+    let mut executor = CommandExecutor::new("abcdef", 80);
+    executor.set_selection_merge_policy(SelectionMergePolicy::aggressive());
+
+    executor
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![
+                range(Position::new(0, 0), Position::new(0, 2)),
+                range(Position::new(0, 2), Position::new(0, 4)),
+            ],
+            primary_index: 1,
+        }))
+        .unwrap();
+
+    assert!(executor.editor().secondary_selections().is_empty());
+    assert_eq!(
+        executor.editor().selection().cloned(),
+        Some(range(Position::new(0, 0), Position::new(0, 4)))
+    );
+}
+
+#[test]
+fn test_merge_preserves_primary_direction() {
+    let mut executor = CommandExecutor::new("abcdefgh", 80);
+
+    executor
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![
+                range(Position::new(0, 0), Position::new(0, 3)),
+                range(Position::new(0, 5), Position::new(0, 2)),
+            ],
+            primary_index: 1,
+        }))
+        .unwrap();
+
+    let primary = executor.editor().selection().cloned().unwrap();
+    assert_eq!(primary.direction, SelectionDirection::Backward);
+    assert_eq!(primary.start, Position::new(0, 5));
+    assert_eq!(primary.end, Position::new(0, 0));
+    assert_eq!(executor.editor().cursor_position(), Position::new(0, 0));
+}
+
+#[test]
+fn test_primary_index_tracks_merged_selection() {
+    let mut manager = EditorStateManager::new("abcdefghij", 80);
+
+    let (selections, primary) = set_selections(
+        &mut manager,
+        vec![
+            caret(0, 9),
+            range(Position::new(0, 0), Position::new(0, 4)),
+            caret(0, 2),
+        ],
+        2,
+    );
+
+    assert_eq!(selections.len(), 2);
+    assert_eq!(primary, 0);
+    assert_eq!(selections[0].start, Position::new(0, 0));
+    assert_eq!(selections[0].end, Position::new(0, 4));
+}
+
+#[test]
+fn test_edit_caret_collapse_honors_policy() {
+    // Deleting the selected text makes both carets land on the same position.
+    let mut executor = CommandExecutor::new("abcd", 80);
+    executor.set_selection_merge_policy(SelectionMergePolicy {
+        merge_carets_at_same_position: false,
+        ..SelectionMergePolicy::default()
+    });
+
+    executor
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![
+                range(Position::new(0, 0), Position::new(0, 2)),
+                range(Position::new(0, 2), Position::new(0, 4)),
+            ],
+            primary_index: 0,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::Edit(EditCommand::Backspace))
+        .unwrap();
+
+    assert_eq!(executor.editor().get_text(), "");
+    assert_eq!(executor.editor().secondary_selections().len(), 1);
+
+    let mut executor = CommandExecutor::new("abcd", 80);
+    executor
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![
+                range(Position::new(0, 0), Position::new(0, 2)),
+                range(Position::new(0, 2), Position::new(0, 4)),
+            ],
+            primary_index: 0,
+        }))
+        .unwrap();
+    executor
+        .execute(Command::Edit(EditCommand::Backspace))
+        .unwrap();
+    assert!(executor.editor().secondary_selections().is_empty());
+}
+
+#[test]
+fn test_normalization_properties_under_every_policy() {
+    let lines = ["abcdefghij"; 4].join("\n");
+    let mut rng = StdRng::seed_from_u64(0x5e1ec7);
+
+    for policy in all_policies() {
+        let mut manager = EditorStateManager::new(&lines, 80);
+        manager.set_selection_merge_policy(policy);
+
+        for _ in 0..200 {
+            let count = rng.gen_range(1..8);
+            let selections: Vec<Selection> = (0..count)
+                .map(|_| {
+                    let line = rng.gen_range(0..4);
+                    let a = Position::new(line, rng.gen_range(0..=10));
+                    let b = if rng.gen_bool(0.4) {
+                        a
+                    } else {
+                        Position::new(rng.gen_range(line..4), rng.gen_range(0..=10))
+                    };
+                    if rng.gen_bool(0.5) {
+                        range(a, b)
+                    } else {
+                        range(b, a)
+                    }
+                })
+                .collect();
+            let primary_index = rng.gen_range(0..count);
+            let primary_active = selections[primary_index].end;
+
+            let (normalized, primary) =
+                set_selections(&mut manager, selections.clone(), primary_index);
+
+            assert!(primary < normalized.len());
+            assert_policy_compliant(&normalized, policy);
+
+            let (min_pos, max_pos) = min_max(&normalized[primary]);
+            assert!(
+                min_pos <= primary_active && primary_active <= max_pos,
+                "primary {:?} not inside {:?} (input {:?})",
+                primary_active,
+                normalized[primary],
+                selections
+            );
+
+            // Idempotence: normalizing the output again yields the same set.
+            let (again, again_primary) = set_selections(&mut manager, normalized.clone(), primary);
+            assert_eq!(again, normalized, "policy {:?}", policy);
+            assert_eq!(again[again_primary], normalized[primary]);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use editor_core::{
+    Command, CursorCommand, EditCommand, EditorStateManager, Position, Selection,
+    SelectionDirection, SnippetTabstop, parse_snippet,
+};
+
+fn insert_snippet(manager: &mut EditorStateManager, template: &str) {
+    manager
+        .execute(Command::Edit(EditCommand::InsertSnippet {
+            template: template.to_string(),
+        }))
+        .unwrap();
+}
+
+fn type_text(manager: &mut EditorStateManager, text: &str) {
+    manager
+        .execute(Command::Edit(EditCommand::InsertText {
+            text: text.to_string(),
+        }))
+        .unwrap();
+}
+
+fn next_tabstop(manager: &mut EditorStateManager) {
+    manager
+        .execute(Command::Cursor(CursorCommand::NextTabstop))
+        .unwrap();
+}
+
+/// All selections as `(start, end)` char offsets, primary first.
+fn selected_ranges(manager: &EditorStateManager) -> Vec<(usize, usize)> {
+    let editor = manager.editor();
+    let to_offset = |pos: Position| {
+        editor
+            .line_index
+            .position_to_char_offset(pos.line, pos.column)
+    };
+    let primary = editor.selection().cloned().unwrap_or(Selection {
+        start: editor.cursor_position(),
+        end: editor.cursor_position(),
+        direction: SelectionDirection::Forward,
+    });
+    std::iter::once(&primary)
+        .chain(editor.secondary_selections())
+        .map(|sel| (to_offset(sel.start), to_offset(sel.end)))
+        .collect()
+}
+
+#[test]
+fn test_parse_snippet_tabstops_and_escapes() {
+    let parsed = parse_snippet("fn ${1:name}($2) -> \\$ {\n\t$0\n}");
+    assert_eq!(parsed.text, "fn name() -> $ {\n\t\n}");
+    assert_eq!(
+        parsed.tabstops,
+        vec![
+            SnippetTabstop {
+                index: 1,
+                start: 3,
+                end: 7
+            },
+            SnippetTabstop {
+                index: 2,
+                start: 8,
+                end: 8
+            },
+            SnippetTabstop {
+                index: 0,
+                start: 18,
+                end: 18
+            },
+        ]
+    );
+
+    // Implicit `$0` at the end; mirrors share the placeholder; unknown syntax stays literal.
+    let parsed = parse_snippet("$1 = ${1:x}; $HOME ${2|a|}");
+    assert_eq!(parsed.text, "x = x; $HOME ${2|a|}");
+    let indices: Vec<(usize, usize, usize)> = parsed
+        .tabstops
+        .iter()
+        .map(|t| (t.index, t.start, t.end))
+        .collect();
+    assert_eq!(indices, vec![(1, 0, 1), (1, 4, 5), (0, 20, 20)]);
+}
+
+#[test]
+fn test_parse_snippet_nested_placeholders() {
+    let parsed = parse_snippet("${2:let ${1:x} = ${3:f($1)}};$0 // $2");
+    assert_eq!(parsed.text, "let x = f(x); // let x = f(x)");
+    let ranges: Vec<(usize, usize, usize)> = parsed
+        .tabstops
+        .iter()
+        .map(|t| (t.index, t.start, t.end))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            (2, 0, 12),
+            (1, 4, 5),
+            (3, 8, 12),
+            (1, 10, 11),
+            (0, 13, 13),
+            (2, 17, 29)
+        ]
+    );
+
+    let order: Vec<usize> = parsed
+        .tabstops_in_tab_order()
+        .iter()
+        .map(|t| t.index)
+        .collect();
+    assert_eq!(order, vec![1, 1, 2, 2, 3, 0]);
+
+    // An unterminated outer placeholder is literal; the inner tabstop still parses.
+    let parsed = parse_snippet("${1:a ${2:b}");
+    assert_eq!(parsed.text, "${1:a b");
+    assert_eq!(
+        parsed.tabstops[0],
+        SnippetTabstop {
+            index: 2,
+            start: 6,
+            end: 7
+        }
+    );
+}
+
+#[test]
+fn test_tabstop_navigation_tracks_typing() {
+    let mut manager = EditorStateManager::new("", 80);
+    insert_snippet(&mut manager, "fn ${1:name}($2) {$0}");
+    assert_eq!(manager.editor().get_text(), "fn name() {}");
+    assert_eq!(selected_ranges(&manager), vec![(3, 7)]);
+    assert_eq!(manager.snippet_session().unwrap().current_index(), 1);
+
+    // Typing replaces the placeholder and shifts later tabstops.
+    type_text(&mut manager, "parse_all");
+    assert_eq!(manager.editor().get_text(), "fn parse_all() {}");
+
+    next_tabstop(&mut manager);
+    assert_eq!(selected_ranges(&manager), vec![(13, 13)]);
+    type_text(&mut manager, "a: u8");
+
+    manager
+        .execute(Command::Cursor(CursorCommand::PrevTabstop))
+        .unwrap();
+    assert_eq!(selected_ranges(&manager), vec![(3, 12)]);
+
+    next_tabstop(&mut manager);
+    assert_eq!(selected_ranges(&manager), vec![(13, 18)]);
+
+    // Reaching `$0` ends the session.
+    next_tabstop(&mut manager);
+    assert_eq!(manager.editor().get_text(), "fn parse_all(a: u8) {}");
+    assert_eq!(selected_ranges(&manager), vec![(21, 21)]);
+    assert!(manager.snippet_session().is_none());
+    assert!(
+        manager
+            .execute(Command::Cursor(CursorCommand::NextTabstop))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_snippet_at_every_caret_with_mirrors() {
+    let mut manager = EditorStateManager::new("a\nb", 80);
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![
+                Selection {
+                    start: Position::new(0, 1),
+                    end: Position::new(0, 1),
+                    direction: SelectionDirection::Forward,
+                },
+                Selection {
+                    start: Position::new(1, 1),
+                    end: Position::new(1, 1),
+                    direction: SelectionDirection::Forward,
+                },
+            ],
+            primary_index: 0,
+        }))
+        .unwrap();
+
+    insert_snippet(&mut manager, "<${1:div}>$0</$1>");
+    assert_eq!(manager.editor().get_text(), "a<div></div>\nb<div></div>");
+
+    let session = manager.snippet_session().unwrap();
+    let carets: Vec<usize> = session.current().ranges.iter().map(|r| r.caret).collect();
+    assert_eq!(carets, vec![0, 0, 1, 1]);
+    assert_eq!(
+        selected_ranges(&manager),
+        vec![(2, 5), (8, 11), (15, 18), (21, 24)]
+    );
+
+    type_text(&mut manager, "p");
+    assert_eq!(manager.editor().get_text(), "a<p></p>\nb<p></p>");
+
+    next_tabstop(&mut manager);
+    assert_eq!(selected_ranges(&manager), vec![(4, 4), (13, 13)]);
+}
+
+#[test]
+fn test_snippet_is_one_undo_step_and_undo_ends_session() {
+    let mut manager = EditorStateManager::new("", 80);
+    type_text(&mut manager, "x");
+    insert_snippet(&mut manager, "(${1:arg})");
+    assert_eq!(manager.editor().get_text(), "x(arg)");
+    assert!(manager.snippet_session().is_some());
+
+    manager.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(manager.editor().get_text(), "x");
+    assert!(manager.snippet_session().is_none());
+
+    // Without tabstops the caret simply ends up at `$0`.
+    insert_snippet(&mut manager, "[$0]");
+    assert_eq!(manager.editor().get_text(), "x[]");
+    assert_eq!(selected_ranges(&manager), vec![(2, 2)]);
+    assert!(manager.snippet_session().is_none());
+}
@@ -2,7 +2,10 @@
 //!
 //! End-to-end integration tests: validates the full pipeline from text input to headless grid output.
 
-use editor_core::{Cell, LayoutEngine, LineIndex, PieceTable, SnapshotGenerator};
+use editor_core::{
+    Cell, Command, EditCommand, EditorStateManager, LayoutEngine, LineIndex, PieceTable, RowDiff,
+    SnapshotGenerator, StyleCommand,
+};
 
 /// Test basic snapshot generation flow.
 #[test]
@@ -365,3 +368,82 @@ fn test_viewport_width_changes() {
 
     println!("✓ 视口宽度变化测试通过！");
 }
+
+/// Test the plain-text rendering of wrapped, tab-expanded and styled rows.
+#[test]
+fn test_debug_string_renders_wrapped_line_across_rows() {
+    let generator = SnapshotGenerator::from_text("abcdefghij\nx\ty", 6);
+    let grid = generator.get_headless_grid(0, 10);
+    assert_eq!(grid.to_debug_string(false), "abcdef\nghij\nx   y\n");
+
+    let mut state = EditorStateManager::new("let x = 1;", 80);
+    state
+        .execute(Command::Style(StyleCommand::AddStyle {
+            start: 0,
+            end: 3,
+            style_id: 7,
+        }))
+        .unwrap();
+    state
+        .execute(Command::Style(StyleCommand::AddStyle {
+            start: 8,
+            end: 9,
+            style_id: 9,
+        }))
+        .unwrap();
+    let grid = state.get_viewport_content_styled(0, 1);
+    assert_eq!(
+        grid.to_debug_string(true),
+        "let x = 1;\naaa     b\na: 7\nb: 9\n"
+    );
+}
+
+/// Test that diffing snapshots reports only the changed cells.
+#[test]
+fn test_grid_diff_reports_single_changed_cell() {
+    let mut state = EditorStateManager::new("fn main() {\n    let x = 1;\n}\n", 80);
+    let before = state.get_viewport_content_styled(0, 10);
+
+    let offset = "fn main() {\n    let ".chars().count();
+    state
+        .execute(Command::Edit(EditCommand::Replace {
+            start: offset,
+            length: 1,
+            text: "y".to_string(),
+        }))
+        .unwrap();
+    let after = state.get_viewport_content_styled(0, 10);
+
+    assert_eq!(
+        after.diff(&before),
+        vec![RowDiff {
+            row: 1,
+            cells: 8..9,
+            columns: 8..9,
+            full_row: false,
+        }]
+    );
+    assert!(after.diff(&after).is_empty());
+
+    // Scrolling compares rows by screen position: only rows showing other text change.
+    let generator = SnapshotGenerator::from_text("a\nsame\nsame\nlonger", 80);
+    let top = generator.get_headless_grid(0, 3);
+    let scrolled = generator.get_headless_grid(1, 3);
+    assert_eq!(
+        scrolled.diff(&top),
+        vec![
+            RowDiff {
+                row: 0,
+                cells: 0..4,
+                columns: 0..4,
+                full_row: false,
+            },
+            RowDiff {
+                row: 2,
+                cells: 0..6,
+                columns: 0..6,
+                full_row: false,
+            },
+        ]
+    );
+}
@@ -0,0 +1,150 @@
+use editor_core::search::{find_all, find_all_iter, find_all_limited};
+use editor_core::{EditorStateManager, LineIndex, SearchMatch, SearchMatchCount, SearchOptions};
+use std::time::Duration;
+
+fn regex() -> SearchOptions {
+    SearchOptions {
+        regex: true,
+        ..SearchOptions::default()
+    }
+}
+
+fn assert_matches_eager(text: &str, query: &str, options: SearchOptions) {
+    let index = LineIndex::from_text(text);
+    let streamed: Vec<SearchMatch> = find_all_iter(&index, query, options).unwrap().collect();
+    let eager = find_all(text, query, options).unwrap();
+    assert_eq!(streamed, eager, "query {query:?} options {options:?}");
+}
+
+#[test]
+fn test_find_all_iter_matches_eager_path() {
+    let text = "foo bar\r\nFoo_bar baz\rqux\u{2028}foo\n\nbar foo\u{1F600}foo\nend foo";
+    let queries = [
+        ("foo", SearchOptions::default()),
+        (
+            "FOO",
+            SearchOptions {
+                case_sensitive: false,
+                ..SearchOptions::default()
+            },
+        ),
+        (
+            "foo",
+            SearchOptions {
+                whole_word: true,
+                ..SearchOptions::default()
+            },
+        ),
+        ("bar\r\nFoo", SearchOptions::default()),
+        ("o\nend", SearchOptions::default()),
+        (r"\w+$", regex()),
+        (r"^\w+", regex()),
+        (r"\bfoo\b", regex()),
+        (r"o\s+\w", regex()),
+        (r"[^a-z]+", regex()),
+        (r"\Afoo", regex()),
+        (r"foo\z", regex()),
+        (r"(?s)bar.baz", regex()),
+        (r"x*", regex()),
+        (r"|o", regex()),
+        ("", SearchOptions::default()),
+    ];
+
+    for (query, options) in queries {
+        assert_matches_eager(text, query, options);
+    }
+}
+
+#[test]
+fn test_find_all_iter_yields_lazily() {
+    let text = "ab\n".repeat(10_000);
+    let index = LineIndex::from_text(&text);
+    let first: Vec<SearchMatch> = find_all_iter(&index, "b", SearchOptions::default())
+        .unwrap()
+        .take(2)
+        .collect();
+    assert_eq!(
+        first,
+        vec![
+            SearchMatch { start: 1, end: 2 },
+            SearchMatch { start: 4, end: 5 }
+        ]
+    );
+}
+
+#[test]
+fn test_find_all_limited_stops_at_match_cap() {
+    // Every char matches, and each candidate forces the engine to consider many alternatives.
+    let text = format!("{}\n", "a".repeat(2_000)).repeat(500);
+    let index = LineIndex::from_text(&text);
+
+    let (matches, truncated) =
+        find_all_limited(&index, r"(?:a|aa|aaa)*?a", regex(), 1_000, None).unwrap();
+    assert_eq!(matches.len(), 1_000);
+    assert!(truncated);
+    assert_eq!(
+        matches[999],
+        SearchMatch {
+            start: 999,
+            end: 1000
+        }
+    );
+
+    let (matches, truncated) =
+        find_all_limited(&index, "\n", SearchOptions::default(), 500, None).unwrap();
+    assert_eq!(matches.len(), 500);
+    assert!(!truncated);
+}
+
+#[test]
+fn test_find_all_limited_honors_deadline() {
+    let text = "abc\n".repeat(1_000);
+    let index = LineIndex::from_text(&text);
+
+    let (matches, truncated) = find_all_limited(
+        &index,
+        "b",
+        SearchOptions::default(),
+        usize::MAX,
+        Some(Duration::ZERO),
+    )
+    .unwrap();
+    assert!(matches.is_empty());
+    assert!(truncated);
+
+    let (matches, truncated) = find_all_limited(
+        &index,
+        "b",
+        SearchOptions::default(),
+        usize::MAX,
+        Some(Duration::from_secs(60)),
+    )
+    .unwrap();
+    assert_eq!(matches.len(), 1_000);
+    assert!(!truncated);
+}
+
+#[test]
+fn test_state_manager_count_matches_reports_truncation() {
+    let manager = EditorStateManager::new(&"x y\n".repeat(50), 80);
+
+    assert_eq!(
+        manager
+            .count_matches("x", SearchOptions::default(), 10, None)
+            .unwrap(),
+        SearchMatchCount {
+            count: 10,
+            truncated: true
+        }
+    );
+    assert_eq!(
+        manager
+            .count_matches("y", SearchOptions::default(), 100, None)
+            .unwrap(),
+        SearchMatchCount {
+            count: 50,
+            truncated: false
+        }
+    );
+    assert!(manager.count_matches("(", regex(), 10, None).is_err());
+}
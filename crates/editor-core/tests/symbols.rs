@@ -1,6 +1,7 @@
 use editor_core::{
     DocumentOutline, DocumentSymbol, EditorStateManager, ProcessingEdit, StateChangeType,
-    SymbolKind, SymbolRange,
+    SymbolKind, SymbolLocation, SymbolRange, Utf16Position, Utf16Range, WorkspaceSymbol,
+    filter_workspace_symbols,
 };
 use std::sync::{Arc, Mutex};
 
@@ -20,6 +21,8 @@ fn test_replace_and_clear_document_symbols() {
         kind: SymbolKind::Variable,
         range: SymbolRange::new(0, 1),
         selection_range: SymbolRange::new(0, 1),
+        utf16_range: None,
+        utf16_selection_range: None,
         children: Vec::new(),
         data_json: Some(r#"{"k":1}"#.to_string()),
     }]);
@@ -42,3 +45,89 @@ fn test_replace_and_clear_document_symbols() {
         ]
     );
 }
+
+fn symbol(name: &str, kind: SymbolKind, range: (usize, usize)) -> DocumentSymbol {
+    DocumentSymbol {
+        name: name.to_string(),
+        detail: None,
+        kind,
+        range: SymbolRange::new(range.0, range.1),
+        selection_range: SymbolRange::new(range.0, range.0),
+        utf16_range: None,
+        utf16_selection_range: None,
+        children: Vec::new(),
+        data_json: None,
+    }
+}
+
+#[test]
+fn test_symbol_path_at_follows_nested_symbols() {
+    // mod foo { struct S; impl Bar { fn baz() {} fn qux() {} } } fn main() {}
+    let mut baz = symbol("baz", SymbolKind::Method, (30, 40));
+    baz.children
+        .push(symbol("inner", SymbolKind::Function, (34, 38)));
+    let mut bar = symbol("Bar", SymbolKind::Object, (20, 55));
+    bar.children.push(baz);
+    bar.children
+        .push(symbol("qux", SymbolKind::Method, (41, 53)));
+    let mut foo = symbol("foo", SymbolKind::Module, (0, 57));
+    foo.children.push(symbol("S", SymbolKind::Struct, (10, 19)));
+    foo.children.push(bar);
+    let outline = DocumentOutline::new(vec![foo, symbol("main", SymbolKind::Function, (58, 70))]);
+
+    let names = |offset| -> Vec<&str> {
+        outline
+            .symbol_path_at(offset)
+            .into_iter()
+            .map(|symbol| symbol.name.as_str())
+            .collect()
+    };
+    assert_eq!(names(32), vec!["foo", "Bar", "baz"]);
+    assert_eq!(names(35), vec!["foo", "Bar", "baz", "inner"]);
+    assert_eq!(names(45), vec!["foo", "Bar", "qux"]);
+    assert_eq!(names(40), vec!["foo", "Bar"]);
+    assert_eq!(names(12), vec!["foo", "S"]);
+    assert_eq!(names(60), vec!["main"]);
+    assert!(names(57).is_empty());
+    assert!(names(100).is_empty());
+}
+
+fn workspace_symbol(name: &str) -> WorkspaceSymbol {
+    let position = Utf16Position::new(0, 0);
+    WorkspaceSymbol {
+        name: name.to_string(),
+        detail: None,
+        kind: SymbolKind::Struct,
+        location: SymbolLocation {
+            uri: format!("file:///{name}.rs"),
+            range: Utf16Range::new(position, position),
+        },
+        container_name: None,
+        data_json: None,
+    }
+}
+
+#[test]
+fn test_filter_workspace_symbols_ranks_word_start_matches_first() {
+    let symbols: Vec<WorkspaceSymbol> = ["FooAbcBar", "Unrelated", "FooBar", "fabric", "fb"]
+        .into_iter()
+        .map(workspace_symbol)
+        .collect();
+
+    let ranked = filter_workspace_symbols(&symbols, "fb");
+    let names: Vec<&str> = ranked.iter().map(|s| s.symbol.name.as_str()).collect();
+    assert_eq!(names, vec!["fb", "FooBar", "FooAbcBar", "fabric"]);
+    // `FooAbcBar` highlights the `B` hump rather than the earlier `b` in `Abc`.
+    assert_eq!(ranked[1].positions, vec![0, 3]);
+    assert_eq!(ranked[2].positions, vec![0, 6]);
+    assert!(ranked[1].score > ranked[2].score);
+
+    let names: Vec<&str> = filter_workspace_symbols(&symbols, "fab")
+        .iter()
+        .map(|s| s.symbol.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["fabric", "FooAbcBar"]);
+
+    assert_eq!(filter_workspace_symbols(&symbols, "").len(), symbols.len());
+    assert!(filter_workspace_symbols(&symbols, "zz").is_empty());
+}
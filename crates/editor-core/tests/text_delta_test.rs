@@ -1,4 +1,9 @@
-use editor_core::{Command, CursorCommand, EditCommand, EditorStateManager, Position, Selection};
+use editor_core::{
+    Command, CursorCommand, DeltaError, EditCommand, EditorStateManager, Position, Selection,
+    TextDelta, TextDeltaEdit,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::sync::{Arc, Mutex};
 
 #[test]
@@ -159,3 +164,194 @@ fn test_undo_redo_produce_group_delta() {
     assert_eq!(redo.edits[1].deleted_text, "");
     assert_eq!(redo.edits[1].inserted_text, "B");
 }
+
+#[test]
+fn test_apply_delta_to_mirror_text() {
+    let mut manager = EditorStateManager::new("fn a\nfn b\nfn c", 80);
+    let mut mirror = manager.editor().get_text();
+
+    let deltas: Arc<Mutex<Vec<TextDelta>>> = Arc::new(Mutex::new(Vec::new()));
+    let deltas_cb = deltas.clone();
+    manager.subscribe(move |change| {
+        if let Some(delta) = change.text_delta.as_ref() {
+            deltas_cb.lock().expect("lock").push((**delta).clone());
+        }
+    });
+
+    // Multi-caret insert at the end of each line, then a replacement and an undo.
+    let selections = (0..3)
+        .map(|line| Selection {
+            start: Position::new(line, 4),
+            end: Position::new(line, 4),
+            direction: editor_core::SelectionDirection::Forward,
+        })
+        .collect();
+    manager
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections,
+            primary_index: 0,
+        }))
+        .unwrap();
+    manager
+        .execute(Command::Edit(EditCommand::InsertText {
+            text: "()é".to_string(),
+        }))
+        .unwrap();
+    manager
+        .execute(Command::Edit(EditCommand::Replace {
+            start: 0,
+            length: 2,
+            text: "pub fn".to_string(),
+        }))
+        .unwrap();
+    manager.execute(Command::Edit(EditCommand::Undo)).unwrap();
+
+    let deltas = deltas.lock().expect("lock");
+    assert_eq!(deltas.len(), 3);
+    assert_eq!(deltas[0].edits.len(), 3);
+    for (i, delta) in deltas.iter().enumerate() {
+        delta.apply_to(&mut mirror).unwrap();
+        if i == 0 {
+            assert_eq!(mirror, "fn a()é\nfn b()é\nfn c()é");
+        }
+    }
+    assert_eq!(mirror, manager.editor().get_text());
+}
+
+#[test]
+fn test_apply_delta_rejects_mismatched_text() {
+    let delta = TextDelta {
+        before_char_count: 3,
+        after_char_count: 4,
+        edits: vec![TextDeltaEdit {
+            start: 1,
+            deleted_text: "b".to_string(),
+            inserted_text: "XY".to_string(),
+        }],
+        undo_group_id: None,
+    };
+
+    let mut text = "abcd".to_string();
+    assert_eq!(
+        delta.apply_to(&mut text),
+        Err(DeltaError::CharCountMismatch {
+            expected: 3,
+            actual: 4
+        })
+    );
+    assert_eq!(text, "abcd");
+
+    let mut text = "aXc".to_string();
+    assert_eq!(
+        delta.apply_to(&mut text),
+        Err(DeltaError::DeletedTextMismatch { index: 0 })
+    );
+    assert_eq!(text, "aXc");
+
+    let mut text = "abc".to_string();
+    delta.apply_to(&mut text).unwrap();
+    assert_eq!(text, "aXYc");
+}
+
+/// Random sequential edits on `text`, returned as a delta (and applied to `text`).
+fn random_delta(rng: &mut StdRng, text: &mut String) -> TextDelta {
+    let pieces = ["", "a", "bc", "é", "\n", "xyz", "🙂"];
+    let before_char_count = text.chars().count();
+    let mut edits = Vec::new();
+    for _ in 0..rng.gen_range(0..5) {
+        let len = text.chars().count();
+        let start = rng.gen_range(0..=len);
+        let delete = rng.gen_range(0..=(len - start).min(3));
+        let deleted_text: String = text.chars().skip(start).take(delete).collect();
+        let inserted_text = pieces[rng.gen_range(0..pieces.len())].to_string();
+        let edit = TextDeltaEdit {
+            start,
+            deleted_text,
+            inserted_text,
+        };
+        TextDelta {
+            before_char_count: len,
+            after_char_count: len - delete + edit.inserted_len(),
+            edits: vec![edit.clone()],
+            undo_group_id: None,
+        }
+        .apply_to(text)
+        .unwrap();
+        edits.push(edit);
+    }
+    TextDelta {
+        before_char_count,
+        after_char_count: text.chars().count(),
+        edits,
+        undo_group_id: None,
+    }
+}
+
+#[test]
+fn test_compose_and_invert_match_sequential_application() {
+    let mut rng = StdRng::seed_from_u64(0xde17a);
+    for _ in 0..500 {
+        let original: String = ["ab", "c\n", "dé", "fgh", "🙂i"][..rng.gen_range(0..=5)].concat();
+        let mut mid = original.clone();
+        let a = random_delta(&mut rng, &mut mid);
+        let mut after = mid.clone();
+        let b = random_delta(&mut rng, &mut after);
+
+        let composed = a.compose(&b).unwrap();
+        let mut text = original.clone();
+        composed.apply_to(&mut text).unwrap();
+        assert_eq!(text, after, "a={a:?} b={b:?} composed={composed:?}");
+        assert_eq!(composed.after_char_count, after.chars().count());
+
+        // Composed edits are non-overlapping and in descending order.
+        for pair in composed.edits.windows(2) {
+            assert!(pair[1].end() <= pair[0].start, "{composed:?}");
+        }
+
+        let mut text = mid.clone();
+        a.invert().apply_to(&mut text).unwrap();
+        assert_eq!(text, original);
+        let mut text = after.clone();
+        composed.invert().apply_to(&mut text).unwrap();
+        assert_eq!(text, original);
+
+        // A delta composed with its inverse is a no-op.
+        assert!(a.compose(&a.invert()).unwrap().is_empty(), "{a:?}");
+    }
+}
+
+#[test]
+fn test_compose_rejects_mismatched_deltas() {
+    let insert = TextDelta {
+        before_char_count: 2,
+        after_char_count: 3,
+        edits: vec![TextDeltaEdit {
+            start: 1,
+            deleted_text: String::new(),
+            inserted_text: "x".to_string(),
+        }],
+        undo_group_id: None,
+    };
+    assert_eq!(
+        insert.compose(&insert),
+        Err(DeltaError::CharCountMismatch {
+            expected: 2,
+            actual: 3
+        })
+    );
+
+    let wrong_delete = TextDelta {
+        before_char_count: 3,
+        after_char_count: 2,
+        edits: vec![TextDeltaEdit {
+            start: 1,
+            deleted_text: "y".to_string(),
+            inserted_text: String::new(),
+        }],
+        undo_group_id: None,
+    };
+    assert_eq!(
+        insert.compose(&wrong_delete),
+        Err(DeltaError::DeletedTextMismatch { index: 0 })
+    );
+}
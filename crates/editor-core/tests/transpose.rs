@@ -0,0 +1,121 @@
+use editor_core::{
+    Command, CommandExecutor, CursorCommand, EditCommand, Position, Selection, SelectionDirection,
+};
+
+fn caret(line: usize, column: usize) -> Selection {
+    let pos = Position::new(line, column);
+    Selection {
+        start: pos,
+        end: pos,
+        direction: SelectionDirection::Forward,
+    }
+}
+
+/// Run `command` with a single caret at `column` of the first line; returns text and caret column.
+fn transpose_at(text: &str, column: usize, command: EditCommand) -> (String, usize) {
+    let mut ex = CommandExecutor::new(text, 80);
+    ex.execute(Command::Cursor(CursorCommand::MoveTo { line: 0, column }))
+        .unwrap();
+    ex.execute(Command::Edit(command)).unwrap();
+    (ex.editor().get_text(), ex.editor().cursor_position().column)
+}
+
+#[test]
+fn test_transpose_chars_caret_positions() {
+    let chars = |text: &str, column: usize| transpose_at(text, column, EditCommand::TransposeChars);
+
+    // Middle: swap around the caret, caret moves past the pair.
+    assert_eq!(chars("abcd", 2), ("acbd".to_string(), 3));
+    // Line start: swap the first two.
+    assert_eq!(chars("abcd", 0), ("bacd".to_string(), 2));
+    // Line end: swap the last two, caret stays at the end.
+    assert_eq!(chars("abcd", 4), ("abdc".to_string(), 4));
+    // Too short: no-op.
+    assert_eq!(chars("a", 1), ("a".to_string(), 1));
+    assert_eq!(chars("", 0), (String::new(), 0));
+    // Grapheme clusters move as a unit.
+    assert_eq!(chars("ae\u{301}b", 1), ("e\u{301}ab".to_string(), 3));
+    assert_eq!(chars("你好", 1), ("好你".to_string(), 2));
+}
+
+#[test]
+fn test_transpose_chars_stays_on_its_line() {
+    let mut ex = CommandExecutor::new("ab\ncd", 80);
+    ex.execute(Command::Cursor(CursorCommand::MoveTo {
+        line: 1,
+        column: 0,
+    }))
+    .unwrap();
+    ex.execute(Command::Edit(EditCommand::TransposeChars))
+        .unwrap();
+    assert_eq!(ex.editor().get_text(), "ab\ndc");
+    assert_eq!(ex.editor().cursor_position(), Position::new(1, 2));
+}
+
+#[test]
+fn test_transpose_words_caret_positions() {
+    let words = |text: &str, column: usize| transpose_at(text, column, EditCommand::TransposeWords);
+
+    // Between words: swap them, keeping the separator; caret after the second word.
+    assert_eq!(words("foo, bar baz", 4), ("bar, foo baz".to_string(), 8));
+    // Inside a word: swap it with the next one.
+    assert_eq!(words("foo bar baz", 5), ("foo baz bar".to_string(), 11));
+    // Right after a word: that word and the next.
+    assert_eq!(words("foo bar baz", 3), ("bar foo baz".to_string(), 7));
+    // Before the first word / after the last word.
+    assert_eq!(words("  foo bar baz", 0), ("  bar foo baz".to_string(), 9));
+    assert_eq!(words("foo bar baz;", 12), ("foo baz bar;".to_string(), 11));
+    // Fewer than two words: no-op.
+    assert_eq!(words("foo ()", 2), ("foo ()".to_string(), 2));
+    // Non-ASCII words.
+    assert_eq!(words("héllo wörld", 5), ("wörld héllo".to_string(), 11));
+}
+
+#[test]
+fn test_transpose_is_one_undo_step_per_command_with_multiple_carets() {
+    let mut ex = CommandExecutor::new("ab cd\nef gh", 80);
+    ex.execute(Command::Cursor(CursorCommand::SetSelections {
+        selections: vec![caret(0, 1), caret(1, 1), caret(1, 1)],
+        primary_index: 1,
+    }))
+    .unwrap();
+
+    ex.execute(Command::Edit(EditCommand::TransposeChars))
+        .unwrap();
+    assert_eq!(ex.editor().get_text(), "ba cd\nfe gh");
+    assert_eq!(ex.editor().cursor_position(), Position::new(1, 2));
+    assert_eq!(
+        ex.editor()
+            .secondary_selections()
+            .iter()
+            .map(|selection| selection.end)
+            .collect::<Vec<_>>(),
+        vec![Position::new(0, 2)]
+    );
+
+    let delta = ex.last_text_delta().unwrap();
+    assert_eq!(delta.before_char_count, delta.after_char_count);
+    let mut edits: Vec<_> = delta
+        .edits
+        .iter()
+        .map(|edit| {
+            (
+                edit.start,
+                edit.deleted_text.as_str(),
+                edit.inserted_text.as_str(),
+            )
+        })
+        .collect();
+    edits.sort();
+    assert_eq!(edits, vec![(0, "ab", "ba"), (6, "ef", "fe")]);
+
+    ex.execute(Command::Edit(EditCommand::TransposeWords))
+        .unwrap();
+    assert_eq!(ex.editor().get_text(), "cd ba\ngh fe");
+
+    ex.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(ex.editor().get_text(), "ba cd\nfe gh");
+    ex.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(ex.editor().get_text(), "ab cd\nef gh");
+    assert_eq!(ex.editor().cursor_position(), Position::new(1, 1));
+}
@@ -0,0 +1,94 @@
+use editor_core::{Command, CommandError, EditCommand, EditorStateManager, PinId};
+
+fn insert(manager: &mut EditorStateManager, offset: usize, text: &str) {
+    manager
+        .execute(Command::Edit(EditCommand::Insert {
+            offset,
+            text: text.to_string(),
+        }))
+        .unwrap();
+}
+
+fn delete(manager: &mut EditorStateManager, start: usize, length: usize) {
+    manager
+        .execute(Command::Edit(EditCommand::Delete { start, length }))
+        .unwrap();
+}
+
+#[test]
+fn test_pins_survive_undo_redo_and_restore_round_trips() {
+    let original = "fn main() {\n    let a = 1;\n    let b = 2;\n}\n";
+    let mut manager = EditorStateManager::new(original, 80);
+    let before = manager.pin_version("before refactor");
+
+    // Heavy editing, including undo/redo.
+    insert(&mut manager, 0, "// header\n");
+    let offset = manager.editor().get_text().find("let b").unwrap();
+    delete(&mut manager, offset, "let b = 2;".len());
+    insert(&mut manager, offset, "let c = 3;");
+    manager.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    manager.execute(Command::Edit(EditCommand::Redo)).unwrap();
+    let end = manager.editor().char_count();
+    insert(&mut manager, end, "fn extra() {}\n");
+
+    let after = manager.pin_version("after review fixes");
+    let edited = manager.editor().get_text();
+    assert_eq!(
+        edited,
+        "// header\nfn main() {\n    let a = 1;\n    let c = 3;\n}\nfn extra() {}\n"
+    );
+
+    let labels: Vec<&str> = manager
+        .list_pins()
+        .iter()
+        .map(|p| p.label.as_str())
+        .collect();
+    assert_eq!(labels, vec!["before refactor", "after review fixes"]);
+    assert_eq!(&*manager.list_pins()[0].text, original);
+
+    let hunks = manager.diff_against_pin(before).unwrap();
+    assert_eq!(hunks.len(), 3);
+    assert_eq!(hunks[0].old_lines, 0..0);
+    assert_eq!(hunks[0].inserted, vec!["// header"]);
+    assert_eq!(hunks[1].removed, vec!["    let b = 2;"]);
+    assert_eq!(hunks[1].inserted, vec!["    let c = 3;"]);
+    assert_eq!(hunks[2].inserted, vec!["fn extra() {}"]);
+    assert!(manager.diff_against_pin(after).unwrap().is_empty());
+
+    // Restoring is a single undoable step.
+    let undo_depth = manager.get_undo_redo_state().undo_depth;
+    manager.restore_pin(before).unwrap();
+    assert_eq!(manager.editor().get_text(), original);
+    assert_eq!(manager.get_undo_redo_state().undo_depth, undo_depth + 1);
+    assert!(manager.diff_against_pin(before).unwrap().is_empty());
+
+    manager.execute(Command::Edit(EditCommand::Undo)).unwrap();
+    assert_eq!(manager.editor().get_text(), edited);
+
+    manager.restore_pin(after).unwrap();
+    assert_eq!(manager.editor().get_text(), edited);
+}
+
+#[test]
+fn test_unknown_pin_and_memory_accounting() {
+    let mut manager = EditorStateManager::new("abc", 80);
+    assert_eq!(manager.pinned_text_bytes(), 0);
+
+    let pin = manager.pin_version("one");
+    manager.pin_version("two");
+    assert_eq!(manager.pinned_text_bytes(), 3);
+
+    insert(&mut manager, 3, "de");
+    manager.pin_version("three");
+    assert_eq!(manager.pinned_text_bytes(), 8);
+
+    assert!(manager.unpin(pin));
+    assert!(!manager.unpin(pin));
+    assert_eq!(manager.pinned_text_bytes(), 8);
+
+    assert!(manager.diff_against_pin(PinId(99)).is_none());
+    assert!(matches!(
+        manager.restore_pin(PinId(99)),
+        Err(CommandError::Other(_))
+    ));
+}
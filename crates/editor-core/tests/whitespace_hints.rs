@@ -0,0 +1,114 @@
+use editor_core::{
+    CellSource, Command, CursorCommand, EditorStateManager, HeadlessLine, Position, Selection,
+    SelectionDirection, SnapshotOptions, WHITESPACE_STYLE_ID, WhitespaceMode,
+};
+
+fn whitespace(mode: WhitespaceMode) -> SnapshotOptions {
+    SnapshotOptions {
+        show_whitespace: mode,
+        ..SnapshotOptions::default()
+    }
+}
+
+fn text(line: &HeadlessLine) -> String {
+    line.cells.iter().map(|cell| cell.ch).collect()
+}
+
+fn widths(line: &HeadlessLine) -> Vec<usize> {
+    line.cells.iter().map(|cell| cell.width).collect()
+}
+
+#[test]
+fn test_tab_keeps_its_width() {
+    // Tab width 4: the tab after `a` covers x=1..4.
+    let state = EditorStateManager::new("a\tb", 80);
+    let plain = state.get_viewport_content_styled(0, 1);
+    let hinted =
+        state.get_viewport_content_styled_with_options(0, 1, &whitespace(WhitespaceMode::All));
+    let line = &hinted.lines[0];
+
+    assert_eq!(text(line), "a→  b");
+    assert_eq!(widths(line), vec![1, 1, 1, 1, 1]);
+    assert_eq!(
+        widths(line).iter().sum::<usize>(),
+        widths(&plain.lines[0]).iter().sum::<usize>()
+    );
+    // The arrow and its padding all map back to the tab.
+    let tab = CellSource::Document {
+        offset: 1,
+        column: 1,
+    };
+    assert!(line.cells[1..4].iter().all(|cell| cell.source == tab));
+    assert!(
+        line.cells[1..4]
+            .iter()
+            .all(|cell| cell.styles.contains(&WHITESPACE_STYLE_ID))
+    );
+    assert!(!line.cells[0].styles.contains(&WHITESPACE_STYLE_ID));
+
+    // The composed grid gets the same substitutes.
+    let composed =
+        state.get_viewport_content_composed_with_options(0, 1, &whitespace(WhitespaceMode::All));
+    let cells = &composed.lines[0].cells;
+    assert_eq!(
+        cells.iter().map(|cell| cell.ch).collect::<String>(),
+        "a→  b"
+    );
+    assert_eq!(cells.iter().map(|cell| cell.width).sum::<usize>(), 5);
+}
+
+#[test]
+fn test_whitespace_modes() {
+    let state = EditorStateManager::new("  a b  c\u{a0}d ", 80);
+    let row = |mode| {
+        text(
+            &state
+                .get_viewport_content_styled_with_options(0, 1, &whitespace(mode))
+                .lines[0],
+        )
+    };
+
+    assert_eq!(row(WhitespaceMode::None), "  a b  c\u{a0}d ");
+    assert_eq!(row(WhitespaceMode::All), "··a·b··c⍽d·");
+    // Single spaces between words stay plain; no-break spaces are always shown.
+    assert_eq!(row(WhitespaceMode::Boundary), "··a b··c⍽d·");
+}
+
+#[test]
+fn test_whitespace_in_selection_only() {
+    let mut state = EditorStateManager::new("a b c d", 80);
+    state
+        .execute(Command::Cursor(CursorCommand::SetSelections {
+            selections: vec![Selection {
+                start: Position::new(0, 4),
+                end: Position::new(0, 1),
+                direction: SelectionDirection::Backward,
+            }],
+            primary_index: 0,
+        }))
+        .unwrap();
+    let grid = state.get_viewport_content_styled_with_options(
+        0,
+        1,
+        &whitespace(WhitespaceMode::Selection),
+    );
+    assert_eq!(text(&grid.lines[0]), "a·b·c d");
+}
+
+#[test]
+fn test_control_characters() {
+    let state = EditorStateManager::new("a\u{1}b\u{200b}c\u{7f} ", 80);
+    let options = SnapshotOptions {
+        show_control_chars: true,
+        ..SnapshotOptions::default()
+    };
+    let plain = state.get_viewport_content_styled(0, 1);
+    let line = &state
+        .get_viewport_content_styled_with_options(0, 1, &options)
+        .lines[0];
+
+    // Whitespace stays as-is without `show_whitespace`.
+    assert_eq!(text(line), "a␁b◦c␡ ");
+    assert_eq!(widths(line), widths(&plain.lines[0]));
+    assert!(line.cells[3].styles.contains(&WHITESPACE_STYLE_ID));
+}
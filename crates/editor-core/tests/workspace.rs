@@ -1,4 +1,9 @@
-use editor_core::{OpenBufferResult, Workspace, WorkspaceError};
+use editor_core::{
+    Command, CommentConfig, CursorCommand, EditCommand, LanguageConfig, OpenBufferResult, Position,
+    StateChangeType, StyleCommand, SymbolLocation, Utf16Position, Utf16Range, Workspace,
+    WorkspaceError,
+};
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn test_workspace_open_lookup_active_close() {
@@ -38,6 +43,64 @@ fn test_workspace_open_lookup_active_close() {
     assert_eq!(ws.active_buffer_id(), Some(buf_a));
 }
 
+#[test]
+fn test_workspace_move_and_detach_views() {
+    let mut ws = Workspace::new();
+    let OpenBufferResult {
+        buffer_id: buf_a,
+        view_id: view_a,
+    } = ws
+        .open_buffer(Some("file:///a.txt".to_string()), "alpha\nbeta", 80)
+        .unwrap();
+    let OpenBufferResult {
+        buffer_id: buf_b,
+        view_id: view_b,
+    } = ws
+        .open_buffer(Some("file:///b.txt".to_string()), "b", 80)
+        .unwrap();
+    let view_a2 = ws.create_view(buf_a, 80).unwrap();
+    ws.execute(
+        view_a2,
+        Command::Cursor(CursorCommand::MoveTo { line: 1, column: 2 }),
+    )
+    .unwrap();
+
+    // buf_a still has `view_a`, so it stays open.
+    assert_eq!(ws.move_view_to_buffer(view_a2, buf_b).unwrap(), None);
+    assert_eq!(ws.buffer_id_for_view(view_a2).unwrap(), buf_b);
+    assert_eq!(
+        ws.cursor_position_for_view(view_a2).unwrap(),
+        Position::new(0, 0)
+    );
+    assert_eq!(ws.move_view_to_buffer(view_a2, buf_b).unwrap(), None);
+
+    // Moving the last view of buf_a closes it.
+    assert_eq!(ws.move_view_to_buffer(view_a, buf_b).unwrap(), Some(buf_a));
+    assert_eq!(ws.buffer_id_for_uri("file:///a.txt"), None);
+    assert_eq!(ws.len(), 1);
+    assert_eq!(ws.view_count(), 3);
+
+    assert_eq!(
+        ws.move_view_to_buffer(view_a, buf_a).unwrap_err(),
+        WorkspaceError::BufferNotFound(buf_a)
+    );
+
+    assert_eq!(ws.detach_view(view_a).unwrap(), None);
+    assert_eq!(ws.detach_view(view_a2).unwrap(), None);
+    assert_eq!(
+        ws.detach_view(view_a).unwrap_err(),
+        WorkspaceError::ViewNotFound(view_a)
+    );
+    assert_eq!(
+        ws.move_view_to_buffer(view_a, buf_b).unwrap_err(),
+        WorkspaceError::ViewNotFound(view_a)
+    );
+
+    assert_eq!(ws.detach_view(view_b).unwrap(), Some(buf_b));
+    assert!(ws.is_empty());
+    assert_eq!(ws.active_view_id(), None);
+}
+
 #[test]
 fn test_workspace_uri_conflicts_and_updates() {
     let mut ws = Workspace::new();
@@ -79,3 +142,211 @@ fn test_workspace_uri_conflicts_and_updates() {
     ws.set_buffer_uri(buf_a, None).unwrap();
     assert_eq!(ws.buffer_id_for_uri("file:///a.txt"), None);
 }
+
+#[test]
+fn test_workspace_reload_buffer_preserves_view_state() {
+    let mut ws = Workspace::new();
+    let OpenBufferResult {
+        buffer_id,
+        view_id: view_a,
+    } = ws
+        .open_buffer(None, "fn a() {\n    1\n}\nfn b() {\n    2\n}\n", 80)
+        .unwrap();
+    let view_b = ws.create_view(buffer_id, 80).unwrap();
+
+    ws.execute(
+        view_a,
+        Command::Style(StyleCommand::Fold {
+            start_line: 0,
+            end_line: 2,
+        }),
+    )
+    .unwrap();
+    ws.execute(
+        view_a,
+        Command::Style(StyleCommand::Fold {
+            start_line: 3,
+            end_line: 5,
+        }),
+    )
+    .unwrap();
+    ws.execute(
+        view_a,
+        Command::Cursor(CursorCommand::MoveTo { line: 1, column: 5 }),
+    )
+    .unwrap();
+    ws.execute(
+        view_b,
+        Command::Cursor(CursorCommand::MoveTo { line: 4, column: 5 }),
+    )
+    .unwrap();
+    ws.execute(
+        view_a,
+        Command::Edit(EditCommand::InsertText {
+            text: "0".to_string(),
+        }),
+    )
+    .unwrap();
+    assert!(!ws.is_buffer_clean(buffer_id).unwrap());
+
+    let changes: Arc<Mutex<Vec<StateChangeType>>> = Arc::default();
+    {
+        let changes = changes.clone();
+        ws.subscribe_view(view_b, move |change| {
+            changes.lock().unwrap().push(change.change_type)
+        })
+        .unwrap();
+    }
+
+    // The file shrank: the second function is gone.
+    ws.reload_buffer(buffer_id, "fn a() {\n    10\n}\n")
+        .unwrap();
+    assert_eq!(ws.buffer_text(buffer_id).unwrap(), "fn a() {\n    10\n}\n");
+    assert!(ws.is_buffer_clean(buffer_id).unwrap());
+    assert_eq!(
+        *changes.lock().unwrap(),
+        vec![StateChangeType::DocumentModified]
+    );
+
+    // Cursors stay on their line/column, clamped to the new text.
+    assert_eq!(
+        ws.cursor_position_for_view(view_a).unwrap(),
+        Position::new(1, 6)
+    );
+    assert_eq!(
+        ws.cursor_position_for_view(view_b).unwrap(),
+        Position::new(3, 0)
+    );
+
+    // The first fold survives (still collapsed); the one past EOF is dropped.
+    assert_eq!(ws.total_visual_lines_for_view(view_a).unwrap(), 2);
+    ws.execute(view_a, Command::Style(StyleCommand::UnfoldAll))
+        .unwrap();
+    assert_eq!(ws.total_visual_lines_for_view(view_a).unwrap(), 4);
+
+    // The reload itself can be undone.
+    ws.execute(view_a, Command::Edit(EditCommand::Undo))
+        .unwrap();
+    assert_eq!(
+        ws.buffer_text(buffer_id).unwrap(),
+        "fn a() {\n    10\n}\nfn b() {\n    2\n}\n"
+    );
+    assert!(!ws.is_buffer_clean(buffer_id).unwrap());
+}
+
+#[test]
+fn test_workspace_open_location_loads_or_focuses_buffers() {
+    let mut ws = Workspace::new();
+    let OpenBufferResult {
+        buffer_id: buf_a,
+        view_id: view_a,
+    } = ws
+        .open_buffer(Some("file:///a.rs".to_string()), "fn a() {}", 40)
+        .unwrap();
+
+    let location = |uri: &str, line, character| SymbolLocation {
+        uri: uri.to_string(),
+        range: Utf16Range::new(
+            Utf16Position::new(line, character),
+            Utf16Position::new(line, character + 1),
+        ),
+    };
+
+    // Not open yet: the loader provides the text; UTF-16 columns become char columns.
+    let view_b = ws
+        .open_location(&location("file:///b.rs", 1, 6), |uri| {
+            assert_eq!(uri, "file:///b.rs");
+            Ok("// b\nlet 👋 = x;".to_string())
+        })
+        .unwrap();
+    let buf_b = ws.buffer_id_for_uri("file:///b.rs").unwrap();
+    assert_eq!(ws.buffer_id_for_view(view_b).unwrap(), buf_b);
+    assert_eq!(ws.active_view_id(), Some(view_b));
+    assert_eq!(
+        ws.cursor_position_for_view(view_b).unwrap(),
+        Position::new(1, 5)
+    );
+    assert_eq!(ws.viewport_state_for_view(view_b).unwrap().width, 40);
+
+    // Already open: focus the existing view without calling the loader.
+    let focused = ws
+        .open_location(&location("file:///a.rs", 0, 3), |_| {
+            panic!("loader must not be called for open buffers")
+        })
+        .unwrap();
+    assert_eq!(focused, view_a);
+    assert_eq!(ws.active_buffer_id(), Some(buf_a));
+    assert_eq!(
+        ws.cursor_position_for_view(view_a).unwrap(),
+        Position::new(0, 3)
+    );
+
+    let err = ws
+        .open_location(&location("file:///missing.rs", 0, 0), |_| {
+            Err("not found".to_string())
+        })
+        .unwrap_err();
+    assert_eq!(
+        err,
+        WorkspaceError::LoadFailed {
+            uri: "file:///missing.rs".to_string(),
+            message: "not found".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_workspace_buffer_language_config_drives_commands() {
+    let mut ws = Workspace::new();
+    let OpenBufferResult {
+        buffer_id: py,
+        view_id: py_view,
+    } = ws.open_buffer(None, "x = 1", 80).unwrap();
+    let OpenBufferResult {
+        view_id: plain_view,
+        ..
+    } = ws.open_buffer(None, "text", 80).unwrap();
+
+    ws.set_buffer_language(
+        py,
+        LanguageConfig::new("python")
+            .with_comment(CommentConfig::line("#"))
+            .with_indent(2, false),
+    )
+    .unwrap();
+    assert_eq!(
+        ws.buffer_metadata(py).unwrap().language_id.as_deref(),
+        Some("python")
+    );
+    assert_eq!(ws.buffer_language(py).unwrap().language_id, "python");
+
+    let toggle = |config: CommentConfig| Command::Edit(EditCommand::ToggleComment { config });
+
+    // No tokens supplied: the buffer's language config is used.
+    ws.execute(py_view, toggle(CommentConfig::default()))
+        .unwrap();
+    assert_eq!(ws.buffer_text(py).unwrap(), "# x = 1");
+
+    // Explicit tokens still override it.
+    ws.execute(py_view, toggle(CommentConfig::default()))
+        .unwrap();
+    ws.execute(py_view, toggle(CommentConfig::line("//")))
+        .unwrap();
+    assert_eq!(ws.buffer_text(py).unwrap(), "// x = 1");
+
+    // Indentation settings apply to the buffer's views.
+    ws.execute(
+        py_view,
+        Command::Cursor(CursorCommand::MoveTo { line: 0, column: 0 }),
+    )
+    .unwrap();
+    ws.execute(py_view, Command::Edit(EditCommand::InsertTab))
+        .unwrap();
+    assert_eq!(ws.buffer_text(py).unwrap(), "  // x = 1");
+
+    // Buffers without a language still require explicit tokens.
+    assert!(
+        ws.execute(plain_view, toggle(CommentConfig::default()))
+            .is_err()
+    );
+}
@@ -1,4 +1,8 @@
-use editor_core::{Command, EditCommand, OpenBufferResult, SearchOptions, TextEditSpec, Workspace};
+use editor_core::{
+    Command, CursorCommand, EditCommand, OpenBufferResult, Position, SearchOptions,
+    StateChangeType, TextEditSpec, Workspace,
+};
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn test_workspace_search_all_open_documents() {
@@ -65,3 +69,54 @@ fn test_workspace_apply_text_edits_groups_undo_per_document() {
     assert_eq!(ws.buffer_text(a).unwrap(), "foo bar");
     assert_eq!(ws.buffer_text(b).unwrap(), "bar foo");
 }
+
+#[test]
+fn test_workspace_replace_all_in_buffers() {
+    let mut ws = Workspace::new();
+    let OpenBufferResult {
+        buffer_id: a,
+        view_id: a_view,
+    } = ws.open_buffer(None, "foo foo\nbar foo", 80).unwrap();
+    let OpenBufferResult {
+        buffer_id: b,
+        view_id: b_view,
+    } = ws.open_buffer(None, "nothing here", 80).unwrap();
+    let OpenBufferResult { buffer_id: c, .. } = ws.open_buffer(None, "a foo", 80).unwrap();
+
+    ws.execute(
+        a_view,
+        Command::Cursor(CursorCommand::MoveTo { line: 1, column: 2 }),
+    )
+    .unwrap();
+
+    let changes: Arc<Mutex<Vec<StateChangeType>>> = Arc::default();
+    for view in [a_view, b_view] {
+        let changes = changes.clone();
+        ws.subscribe_view(view, move |change| {
+            changes.lock().unwrap().push(change.change_type)
+        })
+        .unwrap();
+    }
+
+    let replaced = ws
+        .replace_all_in_buffers("foo", "quux", SearchOptions::default())
+        .unwrap();
+    assert_eq!(replaced, vec![(a, 3), (c, 1)]);
+    assert_eq!(ws.buffer_text(a).unwrap(), "quux quux\nbar quux");
+    assert_eq!(ws.buffer_text(b).unwrap(), "nothing here");
+    assert_eq!(ws.buffer_text(c).unwrap(), "a quux");
+
+    // The cursor stays in front of the same text; only the changed buffer notifies its view.
+    assert_eq!(
+        ws.cursor_position_for_view(a_view).unwrap(),
+        Position::new(1, 2)
+    );
+    assert_eq!(
+        *changes.lock().unwrap(),
+        vec![StateChangeType::DocumentModified]
+    );
+
+    ws.execute(a_view, Command::Edit(EditCommand::Undo))
+        .unwrap();
+    assert_eq!(ws.buffer_text(a).unwrap(), "foo foo\nbar foo");
+}
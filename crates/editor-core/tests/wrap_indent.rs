@@ -67,3 +67,38 @@ fn test_wrapped_indent_affects_snapshot_and_cursor_mapping() {
         .unwrap();
     assert_eq!(executor.editor().cursor_position(), Position::new(0, 7));
 }
+
+#[test]
+fn test_move_to_visual_past_segment_end_stays_on_row() {
+    // Same layout as above: "    ab" / "    cd" / "    ef" / "    gh".
+    let mut executor = CommandExecutor::new("    abcdefgh", 6);
+    executor
+        .execute(Command::View(ViewCommand::SetWrapIndent {
+            indent: WrapIndent::SameAsLineIndent,
+        }))
+        .unwrap();
+    let mut click = |row: usize, x_cells: usize| {
+        executor
+            .execute(Command::Cursor(CursorCommand::MoveToVisual {
+                row,
+                x_cells,
+            }))
+            .unwrap();
+        let pos = executor.editor().cursor_position();
+        let visual = executor
+            .editor()
+            .logical_position_to_visual(pos.line, pos.column)
+            .unwrap();
+        (pos.column, visual.0)
+    };
+
+    // Inside the wrap indent of each continuation row: that row's first char.
+    assert_eq!(click(2, 1), (8, 2));
+    assert_eq!(click(3, 3), (10, 3));
+
+    // Past the last char of a continued segment: its last char, not the next row's first.
+    assert_eq!(click(0, 6), (5, 0));
+    assert_eq!(click(1, 20), (7, 1));
+    // The last segment can hold the caret at the line end.
+    assert_eq!(click(3, 20), (12, 3));
+}
@@ -0,0 +1,23 @@
+; Indentation for the demo's Rust auto-indent (see `TreeSitterIndentProvider`).
+
+[
+  (block)
+  (declaration_list)
+  (field_declaration_list)
+  (enum_variant_list)
+  (match_block)
+  (struct_expression)
+  (field_initializer_list)
+  (use_list)
+  (arguments)
+  (parameters)
+  (array_expression)
+  (tuple_expression)
+  (token_tree)
+] @indent
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
@@ -10,8 +10,15 @@
 //!
 //! # Sublime `.sublime-syntax`（可选）
 //!
-//! 如果当前目录存在匹配的 `.sublime-syntax` 文件（例如 `TOML.sublime-syntax`），会自动启用
-//! `editor-core-sublime` 语法高亮与折叠；否则（JSON/INI 等）会回退到内置正则高亮。
+//! 启动时会加载当前目录下 `syntaxes/` 文件夹（含子目录）中的全部 `.sublime-syntax` 文件，
+//! 按文件扩展名（其次按首行 `first_line_match`）选择语法，启用 `editor-core-sublime`
+//! 语法高亮与折叠；没有匹配的定义时（JSON/INI 等）会回退到内置正则高亮。
+//!
+//! # 主题
+//!
+//! 所有高亮来源（正则、`.sublime-syntax` 作用域、LSP 语义 token）共用一个 `StyleRegistry`，
+//! 颜色由 `editor_core::theme::Theme` 按样式名称解析。默认使用内置的 `themes/default.toml`，
+//! 也可以通过环境变量 `EDITOR_CORE_THEME` 指定其他主题文件。
 //!
 //! # LSP（可选）
 //!
@@ -28,6 +35,17 @@
 //! 连接成功后会自动启用：
 //! - 语义高亮（semanticTokens/full）
 //! - 代码折叠（foldingRange）
+//! - 补全弹窗（Ctrl+Space 触发；↑/↓ 选择，Enter/Tab 接受，Esc 关闭）
+//! - 悬停信息（Ctrl+K，显示在状态栏）
+//! - 跳转到定义（F12，`.rs` 文件；目标在其他文件时显示路径与行号）
+//! - 快速修复（Alt+Enter，应用光标处诊断的首选 quick fix）
+//!
+//! # 缩进检测
+//!
+//! 打开文件时会自动检测缩进风格（Tab / 空格宽度），并据此设置 Tab 宽度与 Tab 键行为。
+//!
+//! Enter 换行时自动缩进：`.rs` 文件通过 `editor-core-treesitter` 的缩进查询
+//! （`queries/rust/indents.scm`）计算缩进层级，其他文件沿用上一行的前导空白。
 //!
 //! # 快捷键
 //!
@@ -42,14 +60,18 @@
 //! - Ctrl+B: 切换矩形选择模式（Box/Column Selection）
 //! - Ctrl+L: 折叠/展开（如果当前行有可折叠区域）
 //! - Ctrl+U: 展开所有折叠
-//! - Ctrl+F: 查找（输入完成后 Enter 查找下一个）
+//! - Ctrl+F: 查找（边输入边预览匹配，Enter 确认，Esc 恢复原光标）
 //! - F3 / Shift+F3: 查找下一个 / 上一个
 //! - Ctrl+Shift+H: 替换（两步输入：Find / Replace）
 //! - Ctrl+Shift+R: 替换当前
 //! - Ctrl+Shift+A: 全部替换
+//! - Ctrl+Space: LSP 补全
+//! - Ctrl+K: LSP 悬停信息
+//! - F12: 跳转到定义（`.rs` 文件）
+//! - Alt+Enter: LSP 快速修复
 //! - Alt+C / Alt+W / Alt+R: 切换大小写/整词/正则
 //! - Backspace/Delete: 删除字符
-//! - Enter: 插入换行
+//! - Enter: 插入换行（自动缩进）
 //! - 支持 IME 输入
 
 use crossterm::{
@@ -58,28 +80,27 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use editor_core::{
-    Command, CommandResult, CursorCommand, EditCommand, EditorStateManager,
-    FOLD_PLACEHOLDER_STYLE_ID, Position, SearchOptions, Selection, StyleLayerId, TextDelta,
-    ViewCommand,
-    layout::{cell_width_at, visual_x_for_column},
-};
-use editor_core_highlight_simple::{
-    RegexHighlightProcessor, SIMPLE_STYLE_BOOLEAN, SIMPLE_STYLE_COMMENT, SIMPLE_STYLE_KEY,
-    SIMPLE_STYLE_NULL, SIMPLE_STYLE_NUMBER, SIMPLE_STYLE_SECTION, SIMPLE_STYLE_STRING,
-    SimpleIniStyles, SimpleJsonStyles,
+    Command, CommandResult, ComposedCellSource, ComposedLine, CursorCommand, DiagnosticSeverity,
+    EditCommand, EditorCore, EditorStateManager, GutterFoldState, GutterLine, LineIndex, Position,
+    ProcessorSpec, Rgb, SearchOptions, Selection, SelectionDirection, SnippetTabstop, StyleLayerId,
+    StyleRegistry, TextAttributes, TextDelta, Theme, ViewCommand, detect_indentation,
+    layout::grapheme_cell_widths,
 };
+use editor_core_highlight_simple::{RegexHighlightProcessor, SimpleIniStyles, SimpleJsonStyles};
 use editor_core_lsp::{
-    DeltaCalculator, LspContentChange, LspDocument, LspSession, LspSessionStartOptions,
-    clear_lsp_state, decode_semantic_style_id, path_to_file_uri,
+    CompletionSession, CompletionTextEditMode, DeltaCalculator, InlayHintManager, LspContentChange,
+    LspDocument, LspEvent, LspRestartPolicy, LspSession, LspSessionStartOptions, clear_lsp_state,
+    completion_item_label, path_to_file_uri, preferred_quick_fix,
 };
-use editor_core_sublime::{SublimeProcessor, SublimeSyntaxSet};
+use editor_core_sublime::{SublimeProcessor, SublimeScopeMapper, SublimeSyntaxSet};
+use editor_core_treesitter::TreeSitterIndentProvider;
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph},
 };
 use serde_json::json;
 use std::{
@@ -98,15 +119,117 @@ enum InputMode {
     ReplaceWith,
 }
 
-fn sublime_syntax_filename_for_extension(ext: &str) -> Option<&'static str> {
-    // Demo map: keep small and explicit.
-    match ext {
-        "rs" => Some("Rust.sublime-syntax"),
-        "toml" => Some("TOML.sublime-syntax"),
-        "json" => Some("JSON.sublime-syntax"),
-        "ini" | "conf" => Some("INI.sublime-syntax"),
-        _ => None,
+/// 处理管线中 `.sublime-syntax` 高亮处理器的名称
+const SUBLIME_SYNTAX_PROCESSOR: &str = "sublime-syntax";
+/// 处理管线中正则高亮处理器（JSON/INI）的名称
+const REGEX_HIGHLIGHT_PROCESSOR: &str = "regex-highlight";
+
+/// 加载当前目录下 `syntaxes/` 中的全部 `.sublime-syntax`；返回语法集合与（加载失败时的）状态消息。
+fn load_sublime_syntaxes() -> (SublimeSyntaxSet, String) {
+    let mut syntax_set = SublimeSyntaxSet::new();
+    let Ok(dir) = env::current_dir().map(|cwd| cwd.join("syntaxes")) else {
+        return (syntax_set, String::new());
+    };
+    if !dir.is_dir() {
+        return (syntax_set, String::new());
+    }
+
+    let status = match syntax_set.load_from_directory(&dir) {
+        Ok(report) => match report.errors.first() {
+            Some((path, err)) => format!(
+                "{} 个 `.sublime-syntax` 加载失败（{}）: {}",
+                report.errors.len(),
+                path.display(),
+                err
+            ),
+            None => String::new(),
+        },
+        Err(err) => format!("读取 {} 失败: {}", dir.display(), err),
+    };
+    (syntax_set, status)
+}
+
+/// 内置默认主题。
+const DEFAULT_THEME: &str = include_str!("../themes/default.toml");
+
+/// 加载 `EDITOR_CORE_THEME` 指定的主题文件（未设置或加载失败时使用内置主题）；返回主题与状态消息。
+fn load_theme() -> (Theme, String) {
+    let default = || Theme::from_toml_str(DEFAULT_THEME).expect("built-in theme is valid");
+    let Some(path) = env::var_os("EDITOR_CORE_THEME").filter(|path| !path.is_empty()) else {
+        return (default(), String::new());
+    };
+    match Theme::load(&path) {
+        Ok(theme) => (theme, String::new()),
+        Err(err) => (
+            default(),
+            format!("加载主题 {} 失败: {}", Path::new(&path).display(), err),
+        ),
+    }
+}
+
+fn rgb_to_color(rgb: Rgb) -> Color {
+    Color::Rgb(rgb.r, rgb.g, rgb.b)
+}
+
+/// 把主题解析出的属性转换为 ratatui 样式。
+fn style_for_attributes(attributes: TextAttributes) -> Style {
+    let mut style = Style::default();
+    if let Some(fg) = attributes.fg {
+        style = style.fg(rgb_to_color(fg));
     }
+    if let Some(bg) = attributes.bg {
+        style = style.bg(rgb_to_color(bg));
+    }
+    let mut mods = Modifier::empty();
+    mods.set(Modifier::BOLD, attributes.bold.unwrap_or(false));
+    mods.set(Modifier::ITALIC, attributes.italic.unwrap_or(false));
+    mods.set(Modifier::UNDERLINED, attributes.underline.unwrap_or(false));
+    mods.set(
+        Modifier::CROSSED_OUT,
+        attributes.strikethrough.unwrap_or(false),
+    );
+    style.add_modifier(mods)
+}
+
+/// 行号栏宽度：诊断标记 + 行号 + 空格 + 折叠标记 + 空格。
+fn gutter_width(line_count: usize) -> usize {
+    line_count.max(1).to_string().len() + 4
+}
+
+/// 渲染一行行号栏（`width` 为 [`gutter_width`] 的结果）。
+fn gutter_spans(gutter: &GutterLine, width: usize) -> Vec<Span<'static>> {
+    let (marker, marker_style) = match gutter.diagnostic_severity {
+        Some(DiagnosticSeverity::Error) => ("●", Style::default().fg(Color::Red)),
+        Some(DiagnosticSeverity::Warning) => ("●", Style::default().fg(Color::Yellow)),
+        Some(DiagnosticSeverity::Information) => ("●", Style::default().fg(Color::Blue)),
+        Some(DiagnosticSeverity::Hint) => ("·", Style::default().fg(Color::DarkGray)),
+        None => (" ", Style::default()),
+    };
+    let number_width = width.saturating_sub(4);
+    let number = match gutter.line_number() {
+        Some(number) => format!("{:>number_width$} ", number),
+        None if gutter.is_wrap_continuation => format!("{:>number_width$} ", "↪"),
+        None => " ".repeat(number_width + 1),
+    };
+    let number_style = if gutter.is_selected {
+        Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let fold = match gutter.fold_state {
+        GutterFoldState::Collapsed => "▸ ",
+        GutterFoldState::Expanded => "▾ ",
+        GutterFoldState::Foldable => "│ ",
+        GutterFoldState::None => "  ",
+    };
+
+    vec![
+        Span::styled(marker, marker_style),
+        Span::styled(number, number_style),
+        Span::styled(fold, Style::default().fg(Color::DarkGray)),
+    ]
 }
 
 fn find_project_root(path: &Path) -> Option<PathBuf> {
@@ -184,14 +307,24 @@ struct App {
     status_message: String,
     /// 剪贴板
     clipboard: String,
-    /// 简单语法高亮（基于正则规则；JSON/INI 在找不到 `.sublime-syntax` 时使用）
-    syntax_highlighter: Option<RegexHighlightProcessor>,
-    /// Sublime Text `.sublime-syntax`（可选；找不到定义时禁用）
-    sublime_syntax: Option<SublimeProcessor>,
+    /// `syntaxes/` 目录中加载的全部 `.sublime-syntax` 定义
+    sublime_syntax_set: SublimeSyntaxSet,
+    /// 所有高亮来源共用的样式名称注册表
+    style_registry: StyleRegistry,
+    /// 按样式名称解析颜色的主题（与 `style_registry` 共享）
+    theme: Theme,
     /// LSP session over stdio (optional; auto-enabled based on file/env config)
     lsp: Option<LspSession>,
     /// Tracks the active LSP document text for incremental `didChange` (char-offset based).
     lsp_delta_calc: Option<DeltaCalculator>,
+    /// 最近一次拉取诊断（pull diagnostics）时的文档版本
+    lsp_diagnostics_version: Option<u64>,
+    /// LSP inlay hints（服务器支持 `inlayHintProvider` 时启用）
+    inlay_hints: Option<InlayHintManager>,
+    /// LSP 补全弹窗状态（Ctrl+Space 打开）
+    completion: Option<CompletionSession>,
+    /// 补全弹窗中选中的条目
+    completion_selected: usize,
     /// 矩形选择模式（column/box selection）
     rect_selection_mode: bool,
     /// 矩形选择锚点（开始 selection 的位置）
@@ -208,6 +341,8 @@ struct App {
     input_mode: InputMode,
     /// 输入缓冲区（用于查找/替换 prompt）
     input_buffer: String,
+    /// 增量查找开始时的选区（预览的起点；Esc 时恢复）
+    find_origin: Option<(Vec<Selection>, usize)>,
 }
 
 impl App {
@@ -222,22 +357,53 @@ impl App {
 
         let mut state_manager = EditorStateManager::new(&content, 80);
 
+        // 自动检测缩进风格（没有缩进行时保持默认设置）
+        let indent = detect_indentation(&state_manager.editor().line_index);
+        if indent.confidence > 0 {
+            let _ = state_manager.execute(Command::View(ViewCommand::ApplyIndentSettings {
+                settings: indent,
+            }));
+        }
+
+        // `.rs` 文件：使用 Tree-sitter 缩进查询计算换行缩进
+        if file_path.extension().and_then(|ext| ext.to_str()) == Some("rs")
+            && let Ok(provider) = TreeSitterIndentProvider::new(
+                tree_sitter_rust::LANGUAGE.into(),
+                include_str!("../queries/rust/indents.scm"),
+            )
+        {
+            state_manager.set_indent_provider(Some(Box::new(provider)));
+        }
+
         // 订阅状态变更
         state_manager.subscribe(|_change| {
             // 可以在这里处理状态变更通知
         });
 
+        let (sublime_syntax_set, mut status_message) = load_sublime_syntaxes();
+        let (theme, theme_status) = load_theme();
+        if status_message.is_empty() {
+            status_message = theme_status;
+        }
+        let style_registry = StyleRegistry::new();
+        let theme = theme.with_registry(style_registry.clone());
+
         let mut app = Self {
             state_manager,
             file_path,
             should_quit: false,
             confirm_quit: false,
-            status_message: String::new(),
+            status_message,
             clipboard: String::new(),
-            syntax_highlighter: None,
-            sublime_syntax: None,
+            sublime_syntax_set,
+            style_registry,
+            theme,
             lsp: None,
             lsp_delta_calc: None,
+            lsp_diagnostics_version: None,
+            inlay_hints: None,
+            completion: None,
+            completion_selected: 0,
             rect_selection_mode: false,
             rect_selection_anchor: None,
             last_insert_time: None,
@@ -246,6 +412,7 @@ impl App {
             replace_query: String::new(),
             input_mode: InputMode::Normal,
             input_buffer: String::new(),
+            find_origin: None,
         };
 
         // `editor-core` normalizes CRLF/Lone-CR to LF internally.
@@ -267,12 +434,13 @@ impl App {
 
     fn configure_syntax_highlighting(&mut self) {
         // Clear previous state first to avoid mixing highlight sources.
+        let pipeline = self.state_manager.pipeline_mut();
+        pipeline.remove(REGEX_HIGHLIGHT_PROCESSOR);
+        pipeline.remove(SUBLIME_SYNTAX_PROCESSOR);
         self.state_manager
             .clear_style_layer(StyleLayerId::SIMPLE_SYNTAX);
-        self.syntax_highlighter = None;
         self.state_manager
             .clear_style_layer(StyleLayerId::SUBLIME_SYNTAX);
-        self.sublime_syntax = None;
 
         // If LSP semantic tokens are available, prefer them for any language.
         if self
@@ -287,84 +455,91 @@ impl App {
             return;
         };
 
-        // Rust: prefer LSP. If LSP is not available, fallback to `Rust.sublime-syntax` when present.
+        // Rust: prefer LSP. If LSP is not available, fallback to a Rust `.sublime-syntax` when present.
         if ext == "rs" {
             if self.lsp.is_none()
-                && self.try_enable_sublime_syntax_by_filename("Rust.sublime-syntax")
+                && let Some(name) = self.try_enable_sublime_syntax()
             {
                 if !self.status_message.is_empty() {
-                    self.status_message =
-                        format!("{}；已回退到 Rust.sublime-syntax", self.status_message);
+                    self.status_message = format!("{}；已回退到 {}", self.status_message, name);
                 } else {
-                    self.status_message = "已启用 Rust.sublime-syntax（无 LSP）".to_string();
+                    self.status_message = format!("已启用 {}（无 LSP）", name);
                 }
             }
             return;
         }
 
-        // JSON/INI: prefer `.sublime-syntax` from CWD; fallback to internal regex highlighting.
-        if ext == "json" || ext == "ini" || ext == "conf" {
-            if let Some(syntax_file) = sublime_syntax_filename_for_extension(&ext)
-                && self.try_enable_sublime_syntax_by_filename(syntax_file)
-            {
-                return;
-            }
+        // Any syntax from `syntaxes/` matching the extension or first line.
+        if self.try_enable_sublime_syntax().is_some() {
+            return;
+        }
 
-            self.syntax_highlighter = match ext.as_str() {
-                "json" => RegexHighlightProcessor::json_default(SimpleJsonStyles::default()).ok(),
-                "ini" | "conf" => {
-                    RegexHighlightProcessor::ini_default(SimpleIniStyles::default()).ok()
-                }
+        // JSON/INI: fallback to internal regex highlighting.
+        if ext == "json" || ext == "ini" || ext == "conf" {
+            let highlighter = match ext.as_str() {
+                "json" => RegexHighlightProcessor::json_default(SimpleJsonStyles::from_registry(
+                    &self.style_registry,
+                ))
+                .ok(),
+                "ini" | "conf" => RegexHighlightProcessor::ini_default(
+                    SimpleIniStyles::from_registry(&self.style_registry),
+                )
+                .ok(),
                 _ => None,
             };
-            if let Some(highlighter) = self.syntax_highlighter.as_mut() {
-                let _ = self.state_manager.apply_processor(highlighter);
+            let spec = ProcessorSpec::new(REGEX_HIGHLIGHT_PROCESSOR)
+                .with_layers([StyleLayerId::SIMPLE_SYNTAX]);
+            if let Some(highlighter) = highlighter
+                && self
+                    .state_manager
+                    .pipeline_mut()
+                    .register(spec, highlighter)
+                    .is_ok()
+            {
+                self.refresh_syntax_highlighting();
             }
-            return;
-        }
-
-        // Other mapped extensions: try `.sublime-syntax` from CWD; otherwise disable.
-        if let Some(syntax_file) = sublime_syntax_filename_for_extension(&ext) {
-            let _ = self.try_enable_sublime_syntax_by_filename(syntax_file);
         }
     }
 
-    fn try_enable_sublime_syntax_by_filename(&mut self, syntax_file: &str) -> bool {
-        let cwd = match env::current_dir() {
-            Ok(dir) => dir,
-            Err(err) => {
-                self.status_message = format!("读取当前目录失败，无法加载语法: {}", err);
-                return false;
-            }
-        };
-
-        let path = cwd.join(syntax_file);
-        if !path.is_file() {
-            return false;
-        }
-
-        let mut syntax_set = SublimeSyntaxSet::new();
-        let syntax = match syntax_set.load_from_path(&path) {
-            Ok(s) => s,
-            Err(err) => {
-                self.status_message = format!(
-                    "解析/编译 `.sublime-syntax` 失败（{}）: {}",
-                    path.display(),
-                    err
-                );
-                return false;
-            }
-        };
-
-        let mut processor = SublimeProcessor::new(syntax, syntax_set);
-        if let Err(err) = self.state_manager.apply_processor(&mut processor) {
-            self.status_message =
-                format!("应用 `.sublime-syntax` 失败（{}）: {}", path.display(), err);
-            return false;
+    /// 按扩展名（其次按首行）从 `syntaxes/` 中选择语法并启用；成功时返回语法名称。
+    fn try_enable_sublime_syntax(&mut self) -> Option<String> {
+        let syntax_set = &self.sublime_syntax_set;
+        let first_line = self
+            .state_manager
+            .editor()
+            .line_index
+            .get_line_text(0)
+            .unwrap_or_default();
+        let syntax = self
+            .file_extension_lowercase()
+            .and_then(|ext| syntax_set.find_by_extension(&ext))
+            .or_else(|| syntax_set.find_by_first_line(&first_line))?;
+        let name = syntax.name.clone().unwrap_or_else(|| syntax.scope.clone());
+
+        let mut processor = SublimeProcessor::new(syntax, syntax_set.clone());
+        processor.scope_mapper = SublimeScopeMapper::with_registry(self.style_registry.clone());
+        let spec = ProcessorSpec::new(SUBLIME_SYNTAX_PROCESSOR)
+            .with_layers([StyleLayerId::SUBLIME_SYNTAX]);
+        self.state_manager
+            .pipeline_mut()
+            .register(spec, processor)
+            .ok()?;
+        let failures = self.state_manager.run_pipeline();
+        if let Some(failure) = failures
+            .iter()
+            .find(|failure| failure.name == SUBLIME_SYNTAX_PROCESSOR)
+        {
+            self.state_manager
+                .pipeline_mut()
+                .remove(SUBLIME_SYNTAX_PROCESSOR);
+            self.status_message = format!(
+                "应用 `.sublime-syntax` 失败（{}）: {}",
+                name, failure.message
+            );
+            return None;
         }
 
-        self.sublime_syntax = Some(processor);
-        true
+        Some(name)
     }
 
     fn maybe_enable_lsp(&mut self, initial_text: &str) {
@@ -454,7 +629,7 @@ impl App {
                 "textDocument": {
                     "semanticTokens": {
                         "dynamicRegistration": false,
-                        "requests": { "range": false, "full": { "delta": false } },
+                        "requests": { "range": true, "full": { "delta": true } },
                         "tokenTypes": token_types,
                         "tokenModifiers": token_modifiers,
                         "formats": ["relative"],
@@ -465,7 +640,22 @@ impl App {
                         "dynamicRegistration": false,
                         "lineFoldingOnly": true,
                     },
+                    "diagnostic": {
+                        "dynamicRegistration": false,
+                        "relatedDocumentSupport": true,
+                    },
+                    "codeAction": {
+                        "codeActionLiteralSupport": {
+                            "codeActionKind": {
+                                "valueSet": ["", "quickfix", "refactor", "source"],
+                            },
+                        },
+                        "isPreferredSupport": true,
+                        "dataSupport": true,
+                        "resolveSupport": { "properties": ["edit"] },
+                    },
                 },
+                "window": { "workDoneProgress": true },
             },
             "clientInfo": { "name": "editor-core tui_editor" },
         });
@@ -486,10 +676,16 @@ impl App {
                 version: 1,
             },
             initial_text: initial_text.to_string(),
+            restart_policy: LspRestartPolicy::new(
+                3,
+                Duration::from_millis(500),
+                Duration::from_secs(10),
+            ),
         };
 
         match LspSession::start(start) {
-            Ok(session) => {
+            Ok(mut session) => {
+                session.set_style_registry(Some(self.style_registry.clone()));
                 let server_label = session
                     .server_info()
                     .map(|info| match info.version.as_deref() {
@@ -498,6 +694,12 @@ impl App {
                     })
                     .unwrap_or_else(|| cmd_name.clone());
 
+                let supports_inlay_hints = session
+                    .server_capabilities()
+                    .get("inlayHintProvider")
+                    .is_some_and(|provider| !provider.is_null() && provider != false);
+                self.inlay_hints = supports_inlay_hints
+                    .then(|| InlayHintManager::new(session.document().uri.clone()));
                 self.lsp = Some(session);
                 self.lsp_delta_calc = Some(DeltaCalculator::from_text(initial_text));
                 self.status_message = format!("已连接 LSP: {}", server_label);
@@ -546,6 +748,11 @@ impl App {
             return;
         }
 
+        if self.completion.is_some() && self.handle_completion_key(key) {
+            self.adjust_scroll();
+            return;
+        }
+
         // 处理普通按键
         match (key.modifiers, key.code) {
             // Ctrl+S: 保存
@@ -641,6 +848,27 @@ impl App {
                 self.toggle_rect_selection_mode();
             }
 
+            // Ctrl+Space: LSP 补全
+            (KeyModifiers::CONTROL, KeyCode::Char(' ')) => {
+                self.trigger_completion();
+            }
+
+            // Ctrl+K: LSP 悬停信息
+            (KeyModifiers::CONTROL, KeyCode::Char('k')) => {
+                self.show_hover();
+            }
+
+            // F12: 跳转到定义
+            (KeyModifiers::NONE, KeyCode::F(12)) => {
+                self.go_to_definition();
+            }
+
+            // Alt+Enter: 快速修复
+            (KeyModifiers::ALT, KeyCode::Enter) => {
+                self.apply_quick_fix();
+                self.refresh_syntax_highlighting();
+            }
+
             // 方向键移动
             (mods, KeyCode::Left) => {
                 self.move_cursor_left(mods.contains(KeyModifiers::SHIFT));
@@ -699,10 +927,242 @@ impl App {
             _ => {}
         }
 
+        // 补全弹窗跟随输入重新过滤（或在光标离开单词时关闭）
+        self.update_completion();
+
         // 更新滚动位置以跟随光标
         self.adjust_scroll();
     }
 
+    fn trigger_completion(&mut self) {
+        let Some(lsp) = self.lsp.as_mut() else {
+            self.status_message = "补全需要 LSP".to_string();
+            return;
+        };
+        let mut session = CompletionSession::new(lsp.document().uri.clone());
+        match session.trigger(lsp, &self.state_manager) {
+            Ok(_) => {
+                self.completion = Some(session);
+                self.completion_selected = 0;
+            }
+            Err(err) => self.status_message = format!("补全请求失败: {}", err),
+        }
+    }
+
+    fn show_hover(&mut self) {
+        let Some(lsp) = self.lsp.as_mut() else {
+            self.status_message = "悬停信息需要 LSP".to_string();
+            return;
+        };
+        let editor = self.state_manager.editor();
+        let cursor = editor.cursor_position();
+        let offset = editor
+            .line_index
+            .position_to_char_offset(cursor.line, cursor.column);
+        self.status_message = match lsp.hover_at(&editor.line_index, offset) {
+            // 状态栏只有一行：把多行文本压成一行。
+            Ok(Some(hover)) => hover
+                .plain_text()
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ⏐ "),
+            Ok(None) => "无悬停信息".to_string(),
+            Err(err) => format!("悬停请求失败: {}", err),
+        };
+    }
+
+    fn go_to_definition(&mut self) {
+        if self.file_path.extension().and_then(|ext| ext.to_str()) != Some("rs") {
+            self.status_message = "跳转到定义仅支持 .rs 文件".to_string();
+            return;
+        }
+        let Some(lsp) = self.lsp.as_mut() else {
+            self.status_message = "跳转到定义需要 LSP".to_string();
+            return;
+        };
+        let editor = self.state_manager.editor();
+        let cursor = editor.cursor_position();
+        let offset = editor
+            .line_index
+            .position_to_char_offset(cursor.line, cursor.column);
+        let location = match lsp.definition_at(&editor.line_index, offset) {
+            Ok(locations) => locations.into_iter().next(),
+            Err(err) => {
+                self.status_message = format!("跳转到定义失败: {}", err);
+                return;
+            }
+        };
+
+        match location {
+            Some(location) => match location.char_range {
+                Some((start, _)) => {
+                    let (line, column) = self
+                        .state_manager
+                        .editor()
+                        .line_index
+                        .char_offset_to_position(start);
+                    self.move_cursor_to(Position::new(line, column), false);
+                    self.status_message = format!("已跳转到定义: {}:{}", line + 1, column + 1);
+                }
+                // 演示只编辑单个文件：其他文件中的定义只显示位置。
+                None => {
+                    let target = location
+                        .path
+                        .map(|path| path.display().to_string())
+                        .unwrap_or(location.uri);
+                    self.status_message =
+                        format!("定义位于 {}:{}", target, location.range.start.line + 1);
+                }
+            },
+            None => self.status_message = "未找到定义".to_string(),
+        }
+    }
+
+    /// 对光标处的诊断应用首选的快速修复（`textDocument/codeAction`）。
+    fn apply_quick_fix(&mut self) {
+        let Some(lsp) = self.lsp.as_mut() else {
+            self.status_message = "快速修复需要 LSP".to_string();
+            return;
+        };
+        let editor = self.state_manager.editor();
+        let cursor = editor.cursor_position();
+        let offset = editor
+            .line_index
+            .position_to_char_offset(cursor.line, cursor.column);
+        let items = match lsp.code_actions_for_range(
+            &editor.line_index,
+            offset,
+            offset,
+            editor.diagnostics(),
+        ) {
+            Ok(items) => items,
+            Err(err) => {
+                self.status_message = format!("Code action 请求失败: {}", err);
+                return;
+            }
+        };
+        let Some(fix) = preferred_quick_fix(&items).cloned() else {
+            self.status_message = "光标处没有可用的快速修复".to_string();
+            return;
+        };
+
+        let old_text = editor.get_text();
+        let command = match lsp.apply_code_action(&mut self.state_manager, &fix) {
+            Ok(command) => command,
+            Err(err) => {
+                self.status_message = format!("应用快速修复失败: {}", err);
+                return;
+            }
+        };
+
+        // 修复可能包含多处编辑，而 `last_text_delta` 只保留最后一次：整篇同步给服务器。
+        let _ = self.state_manager.take_last_text_delta();
+        let new_text = self.state_manager.editor().get_text();
+        if new_text != old_text {
+            let old_line_index = LineIndex::from_text(&old_text);
+            let change =
+                lsp.full_document_change(&old_line_index, old_text.chars().count(), &new_text);
+            if let Err(reason) = lsp.did_change(change) {
+                self.disable_lsp(reason);
+                return;
+            }
+            self.lsp_delta_calc = Some(DeltaCalculator::from_text(&new_text));
+        }
+
+        if let Some(command) = command
+            && let Err(err) = lsp.execute_command(&command)
+        {
+            self.status_message = format!("执行命令失败 ({}): {}", command.command, err);
+            return;
+        }
+        self.status_message = format!("已应用快速修复: {}", fix.title());
+    }
+
+    fn update_completion(&mut self) {
+        let (Some(session), Some(lsp)) = (self.completion.as_mut(), self.lsp.as_mut()) else {
+            self.completion = None;
+            return;
+        };
+        match session.update(lsp, &self.state_manager) {
+            Ok(true) => {
+                self.completion_selected = self
+                    .completion_selected
+                    .min(session.len().saturating_sub(1));
+            }
+            Ok(false) => self.completion = None,
+            Err(err) => {
+                self.status_message = format!("补全请求失败: {}", err);
+                self.completion = None;
+            }
+        }
+    }
+
+    /// 补全弹窗打开时的按键；返回 `true` 表示按键已被弹窗处理。
+    fn handle_completion_key(&mut self, key: KeyEvent) -> bool {
+        let Some(session) = self.completion.as_mut() else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.completion = None;
+            }
+            KeyCode::Up => {
+                self.completion_selected = self.completion_selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                if self.completion_selected + 1 < session.len() {
+                    self.completion_selected += 1;
+                }
+            }
+            KeyCode::Enter | KeyCode::Tab if !session.is_empty() => {
+                let selected = self.completion_selected;
+                let result = session.accept(
+                    &mut self.state_manager,
+                    selected,
+                    CompletionTextEditMode::Insert,
+                );
+                self.completion = None;
+                match result {
+                    Ok(tabstops) => {
+                        self.flush_lsp_did_change_from_delta();
+                        self.refresh_syntax_highlighting();
+                        self.select_first_snippet_tabstop(&tabstops);
+                    }
+                    Err(err) => self.status_message = err,
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Select every occurrence of the first tabstop of an accepted snippet completion.
+    fn select_first_snippet_tabstop(&mut self, tabstops: &[SnippetTabstop]) {
+        let Some(first) = tabstops.first() else {
+            return;
+        };
+        let line_index = &self.state_manager.editor().line_index;
+        let position = |offset: usize| {
+            let (line, column) = line_index.char_offset_to_position(offset);
+            Position::new(line, column)
+        };
+        let selections: Vec<Selection> = tabstops
+            .iter()
+            .filter(|tabstop| tabstop.index == first.index)
+            .map(|tabstop| Selection {
+                start: position(tabstop.start),
+                end: position(tabstop.end),
+                direction: SelectionDirection::Forward,
+            })
+            .collect();
+        self.execute(Command::Cursor(CursorCommand::SetSelections {
+            selections,
+            primary_index: 0,
+        }));
+    }
+
     fn toggle_rect_selection_mode(&mut self) {
         self.rect_selection_mode = !self.rect_selection_mode;
         self.rect_selection_anchor = None;
@@ -832,6 +1292,39 @@ impl App {
         self.input_mode = InputMode::Find;
         self.input_buffer = self.search_query.clone();
         self.status_message.clear();
+        let cursor = self.state_manager.get_cursor_state();
+        self.find_origin = Some((cursor.selections, cursor.primary_selection_index));
+        self.preview_incremental_find();
+    }
+
+    /// 从查找起点预览当前输入的最近匹配（不修改 `search_query`）。
+    fn preview_incremental_find(&mut self) {
+        let Some((selections, primary_index)) = self.find_origin.as_ref() else {
+            return;
+        };
+        let origin = selections[*primary_index].end;
+        let from_offset = self
+            .state_manager
+            .editor()
+            .line_index
+            .position_to_char_offset(origin.line, origin.column);
+
+        let Some(result) = self.execute_result(Command::Cursor(CursorCommand::IncrementalFind {
+            query: self.input_buffer.clone(),
+            options: self.search_options,
+            from_offset,
+        })) else {
+            return;
+        };
+
+        self.status_message = match result {
+            CommandResult::SearchMatch { start, end } if start < from_offset => {
+                format!("找到（已从头开始）: {}..{}", start, end)
+            }
+            CommandResult::SearchMatch { start, end } => format!("找到: {}..{}", start, end),
+            CommandResult::SearchNotFound if !self.input_buffer.is_empty() => "未找到".to_string(),
+            _ => String::new(),
+        };
     }
 
     fn start_replace_prompt(&mut self) {
@@ -843,31 +1336,44 @@ impl App {
     fn toggle_search_case_sensitive(&mut self) {
         self.search_options.case_sensitive = !self.search_options.case_sensitive;
         self.status_message = format!("查找选项: {}", self.search_options_label());
+        self.refresh_search_highlight();
     }
 
     fn toggle_search_whole_word(&mut self) {
         self.search_options.whole_word = !self.search_options.whole_word;
         self.status_message = format!("查找选项: {}", self.search_options_label());
+        self.refresh_search_highlight();
     }
 
     fn toggle_search_regex(&mut self) {
         self.search_options.regex = !self.search_options.regex;
         self.status_message = format!("查找选项: {}", self.search_options_label());
+        self.refresh_search_highlight();
     }
 
     fn handle_prompt_key(&mut self, key: KeyEvent) {
         match (key.modifiers, key.code) {
             (_, KeyCode::Esc) => {
+                // 取消增量查找：恢复开始查找前的选区。
+                if let Some((selections, primary_index)) = self.find_origin.take() {
+                    self.execute(Command::Cursor(CursorCommand::SetSelections {
+                        selections,
+                        primary_index,
+                    }));
+                }
                 self.input_mode = InputMode::Normal;
                 self.input_buffer.clear();
                 self.status_message.clear();
             }
             (_, KeyCode::Enter) => match self.input_mode {
                 InputMode::Find => {
+                    // 预览的匹配已被选中，直接确认。
+                    self.find_origin = None;
                     self.search_query = self.input_buffer.clone();
                     self.input_mode = InputMode::Normal;
                     self.input_buffer.clear();
-                    self.find_next();
+                    self.refresh_search_highlight();
+                    self.rect_selection_anchor = None;
                 }
                 InputMode::ReplaceFind => {
                     self.search_query = self.input_buffer.clone();
@@ -900,6 +1406,19 @@ impl App {
             }
             _ => {}
         }
+
+        if self.input_mode == InputMode::Find && !matches!(key.code, KeyCode::Esc | KeyCode::Enter)
+        {
+            self.preview_incremental_find();
+        }
+    }
+
+    fn refresh_search_highlight(&mut self) {
+        let query = (!self.search_query.is_empty())
+            .then(|| (self.search_query.clone(), self.search_options));
+        if let Err(err) = self.state_manager.set_search_highlight(query) {
+            self.status_message = format!("查找失败: {}", err);
+        }
     }
 
     fn find_next(&mut self) {
@@ -1026,39 +1545,30 @@ impl App {
         self.last_insert_time = None;
     }
 
+    /// 运行处理管线中的高亮处理器（文本未变化的处理器会被跳过）。
+    ///
+    /// 管线只包含 LSP 未接管的高亮来源（见 `configure_syntax_highlighting`），LSP 仍由
+    /// `poll_lsp` 单独驱动。
     fn refresh_syntax_highlighting(&mut self) {
-        // For Rust, LSP is the preferred highlighter when active.
-        if self.lsp.is_some() {
-            return;
-        }
-
-        if self.sublime_syntax.is_some() {
-            let mut processor = self.sublime_syntax.take().expect("checked");
-            if let Err(err) = self.state_manager.apply_processor(&mut processor) {
-                self.status_message = format!("刷新 `.sublime-syntax` 失败: {}", err);
-            }
-            self.sublime_syntax = Some(processor);
-            return;
-        }
-
-        if let Some(highlighter) = self.syntax_highlighter.as_mut() {
-            let _ = self.state_manager.apply_processor(highlighter);
+        for failure in self.state_manager.run_pipeline() {
+            self.status_message = format!("刷新 `{}` 失败: {}", failure.name, failure.message);
         }
     }
 
     fn disable_lsp(&mut self, reason: String) {
         self.lsp = None;
+        self.inlay_hints = None;
         self.lsp_delta_calc = None;
         clear_lsp_state(&mut self.state_manager);
         self.status_message = reason;
 
-        // Rust fallback: try `Rust.sublime-syntax` if available.
+        // Rust fallback: try a Rust `.sublime-syntax` if available.
         if self
             .file_extension_lowercase()
             .is_some_and(|ext| ext == "rs")
-            && self.try_enable_sublime_syntax_by_filename("Rust.sublime-syntax")
+            && let Some(name) = self.try_enable_sublime_syntax()
         {
-            self.status_message = format!("{}；已回退到 Rust.sublime-syntax", self.status_message);
+            self.status_message = format!("{}；已回退到 {}", self.status_message, name);
         }
     }
 
@@ -1067,11 +1577,93 @@ impl App {
             let Some(lsp) = self.lsp.as_mut() else {
                 return;
             };
+            // 让首次全量 semantic tokens 返回前，视口先通过 range 请求着色。
+            lsp.set_viewport_hint_from_state(&self.state_manager);
             self.state_manager.apply_processor(lsp)
         };
 
         if let Err(reason) = poll_result {
             self.disable_lsp(reason);
+            return;
+        }
+        self.maybe_pull_lsp_diagnostics();
+
+        // 把事件分发给重启提示、补全弹窗和 inlay hints。
+        let events = self
+            .lsp
+            .as_mut()
+            .map(|lsp| lsp.drain_events())
+            .unwrap_or_default();
+        for event in &events {
+            if let LspEvent::ServerRestarted(restart) = event {
+                self.status_message = format!("LSP 已重启: {}", restart.reason);
+            }
+        }
+        if let Some(session) = self.completion.as_mut() {
+            for event in &events {
+                if let LspEvent::Response(response) = event {
+                    session.handle_response(response);
+                }
+            }
+            self.completion_selected = self
+                .completion_selected
+                .min(session.len().saturating_sub(1));
+        }
+        self.update_inlay_hints(&events);
+    }
+
+    /// 按可见行请求 inlay hints，并把结果写入 `INLAY_HINTS` 装饰层。
+    fn update_inlay_hints(&mut self, events: &[LspEvent]) {
+        let (Some(hints), Some(lsp)) = (self.inlay_hints.as_mut(), self.lsp.as_mut()) else {
+            return;
+        };
+        for event in events {
+            hints.handle_event(event);
+        }
+
+        let viewport = self.state_manager.get_viewport_state();
+        let height = viewport.height.unwrap_or(0).max(1);
+        let last_row = viewport
+            .scroll_top
+            .saturating_add(height - 1)
+            .min(viewport.total_visual_lines.saturating_sub(1));
+        let (start_line, _) = self
+            .state_manager
+            .visual_to_logical_line(viewport.scroll_top);
+        let (end_line, _) = self.state_manager.visual_to_logical_line(last_row);
+
+        let version = lsp.document().version;
+        let line_index = &self.state_manager.editor().line_index;
+        if let Err(err) = hints.update(lsp, line_index, version, start_line..end_line + 1) {
+            self.status_message = err;
+            return;
+        }
+        if let Some(edit) = hints.take_processing_edit(line_index) {
+            self.state_manager.apply_processing_edits(vec![edit]);
+        }
+    }
+
+    /// 文本变化且停止输入后拉取诊断（仅当服务器支持 pull diagnostics）。
+    fn maybe_pull_lsp_diagnostics(&mut self) {
+        let version = self.state_manager.get_document_state().version;
+        if self.lsp_diagnostics_version == Some(version)
+            || self
+                .last_insert_time
+                .is_some_and(|t| t.elapsed() < Duration::from_millis(300))
+        {
+            return;
+        }
+        let Some(lsp) = self.lsp.as_mut() else {
+            return;
+        };
+        if !lsp.supports_pull_diagnostics() {
+            return;
+        }
+
+        self.lsp_diagnostics_version = Some(version);
+        match lsp.pull_diagnostics(&self.state_manager.editor().line_index) {
+            Ok(edits) => self.state_manager.apply_processing_edits(edits),
+            Err(err) => self.status_message = err,
         }
     }
 
@@ -1092,11 +1684,15 @@ impl App {
         if text.is_empty() {
             return;
         }
+        self.insert_with(EditCommand::InsertText {
+            text: text.to_string(),
+        });
+    }
 
+    /// 执行插入类编辑命令，并更新插入状态与语法高亮
+    fn insert_with(&mut self, command: EditCommand) {
         let before_version = self.state_manager.get_document_state().version;
-        if !self.execute(Command::Edit(EditCommand::InsertText {
-            text: text.to_string(),
-        })) {
+        if !self.execute(Command::Edit(command)) {
             return;
         }
         let after_version = self.state_manager.get_document_state().version;
@@ -1111,8 +1707,11 @@ impl App {
 
     /// 处理粘贴事件（IME 支持）
     fn handle_paste(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
         let len = text.chars().count();
-        self.insert_text(&text);
+        self.insert_with(EditCommand::InsertTextReindented { text });
         self.status_message = format!("粘贴了 {} 个字符", len);
         self.adjust_scroll();
     }
@@ -1122,25 +1721,14 @@ impl App {
         self.insert_text(&c.to_string());
     }
 
-    /// 插入换行
+    /// 插入换行（自动缩进）
     fn insert_newline(&mut self) {
-        self.insert_text("\n");
+        self.insert_with(EditCommand::InsertNewline { auto_indent: true });
     }
 
     /// 插入 Tab（由 editor-core 根据 tab 设置决定插入 `\\t` 或空格）
     fn insert_tab(&mut self) {
-        let before_version = self.state_manager.get_document_state().version;
-        if !self.execute(Command::Edit(EditCommand::InsertTab)) {
-            return;
-        }
-        let after_version = self.state_manager.get_document_state().version;
-        if after_version == before_version {
-            return;
-        }
-
-        self.rect_selection_anchor = None;
-        self.last_insert_time = Some(Instant::now());
-        self.refresh_syntax_highlighting();
+        self.insert_with(EditCommand::InsertTab);
     }
 
     /// 退格删除
@@ -1238,9 +1826,10 @@ impl App {
             return;
         }
 
+        // 多行内容按当前行缩进重新缩进
         let text = self.clipboard.clone();
         let len = text.chars().count();
-        self.insert_text(&text);
+        self.insert_with(EditCommand::InsertTextReindented { text });
         self.status_message = format!("粘贴了 {} 个字符", len);
     }
 
@@ -1546,19 +2135,16 @@ impl App {
     ) -> usize {
         let mut col = segment_start_col;
         let mut x_in_segment = 0usize;
-        let mut x_in_line = visual_x_for_column(line_text, segment_start_col, tab_width);
 
-        for ch in line_text
-            .chars()
+        for w in grapheme_cell_widths(line_text, tab_width)
+            .into_iter()
             .skip(segment_start_col)
             .take(segment_end_col.saturating_sub(segment_start_col))
         {
-            let w = cell_width_at(ch, x_in_line, tab_width);
             if x_in_segment + w > target_x {
                 break;
             }
             x_in_segment += w;
-            x_in_line += w;
             col += 1;
         }
 
@@ -1686,7 +2272,8 @@ impl App {
         // 视口信息（编辑区内侧，不包含边框）
         let editor_area = chunks[0];
         let viewport_height = editor_area.height.saturating_sub(2) as usize;
-        let viewport_width = editor_area.width.saturating_sub(2) as usize;
+        let viewport_width = (editor_area.width.saturating_sub(2) as usize)
+            .saturating_sub(gutter_width(self.state_manager.editor().line_count()));
 
         self.state_manager.set_viewport_height(viewport_height);
 
@@ -1710,6 +2297,9 @@ impl App {
         // 渲染编辑器内容
         self.render_editor(frame, editor_area);
 
+        // 渲染补全弹窗（光标下方）
+        self.render_completion_popup(frame, editor_area);
+
         // 渲染状态行
         self.render_status_line(frame, chunks[1]);
 
@@ -1718,149 +2308,41 @@ impl App {
     }
 
     fn style_for_style_ids(&self, style_ids: &[u32]) -> Style {
-        let mut fg = None::<Color>;
-        let mut mods = Modifier::empty();
-
-        let semantic_legend = self.lsp.as_ref().and_then(|lsp| lsp.semantic_legend());
-
-        for &style_id in style_ids {
-            match style_id {
-                SIMPLE_STYLE_STRING => fg = Some(Color::Green),
-                SIMPLE_STYLE_NUMBER => fg = Some(Color::Yellow),
-                SIMPLE_STYLE_BOOLEAN => fg = Some(Color::Magenta),
-                SIMPLE_STYLE_NULL => fg = Some(Color::DarkGray),
-                SIMPLE_STYLE_SECTION => {
-                    fg = Some(Color::Cyan);
-                    mods |= Modifier::BOLD;
-                }
-                SIMPLE_STYLE_KEY => fg = Some(Color::Blue),
-                SIMPLE_STYLE_COMMENT => {
-                    fg = Some(Color::DarkGray);
-                    mods |= Modifier::ITALIC;
-                }
-                FOLD_PLACEHOLDER_STYLE_ID => {
-                    fg = Some(Color::DarkGray);
-                    mods |= Modifier::ITALIC;
-                }
-                _ => {
-                    if let Some(scope) = self
-                        .sublime_syntax
-                        .as_ref()
-                        .and_then(|s| s.scope_mapper.scope_for_style_id(style_id))
-                    {
-                        let (scope_fg, scope_mods) = style_for_sublime_scope(scope);
-                        if scope_fg.is_some() {
-                            fg = scope_fg;
-                        }
-                        mods |= scope_mods;
-                        continue;
-                    }
-
-                    // `StyleId` 对语义 token 的默认编码：高 16 位 token_type，低 16 位 modifiers。
-                    // 这里用一个保守的启发式：只有小于 0x0100_0000 的 ID 才尝试当作语义 token 显示。
-                    if style_id < 0x0100_0000 {
-                        let (token_type_idx, token_modifiers_bits) =
-                            decode_semantic_style_id(style_id);
-
-                        let token_type_name = semantic_legend
-                            .and_then(|legend| legend.token_types.get(token_type_idx as usize))
-                            .map(|s| s.as_str());
-
-                        fg = match token_type_name {
-                            Some("comment") => Some(Color::DarkGray),
-                            Some("string") => Some(Color::Green),
-                            Some("number") => Some(Color::Yellow),
-                            Some("keyword") => Some(Color::LightBlue),
-                            Some("function") | Some("method") => Some(Color::Cyan),
-                            Some("macro") => Some(Color::Magenta),
-                            Some("type")
-                            | Some("struct")
-                            | Some("enum")
-                            | Some("class")
-                            | Some("interface")
-                            | Some("typeParameter") => Some(Color::LightCyan),
-                            Some("namespace") => Some(Color::LightMagenta),
-                            Some("parameter") => Some(Color::LightYellow),
-                            Some("operator") => Some(Color::LightRed),
-                            Some("variable") | Some("property") | Some("enumMember") => {
-                                Some(Color::White)
-                            }
-                            _ => {
-                                let fallback_palette = [
-                                    Color::Cyan,
-                                    Color::Green,
-                                    Color::Yellow,
-                                    Color::Magenta,
-                                    Color::Blue,
-                                    Color::Red,
-                                ];
-                                Some(
-                                    fallback_palette
-                                        [(token_type_idx as usize) % fallback_palette.len()],
-                                )
-                            }
-                        };
-
-                        // token_modifiers 的位含义由 LSP 服务器的 legend 决定。
-                        if let Some(legend) = semantic_legend {
-                            for (i, name) in legend.token_modifiers.iter().enumerate() {
-                                if i >= 32 {
-                                    break;
-                                }
-                                if token_modifiers_bits & (1u32 << i) == 0 {
-                                    continue;
-                                }
-                                match name.as_str() {
-                                    "declaration" | "definition" => mods |= Modifier::BOLD,
-                                    "documentation" => mods |= Modifier::ITALIC,
-                                    "readonly" => mods |= Modifier::UNDERLINED,
-                                    "static" => mods |= Modifier::DIM,
-                                    "deprecated" => mods |= Modifier::UNDERLINED,
-                                    "async" => mods |= Modifier::ITALIC,
-                                    _ => {}
-                                }
-                            }
-                        } else {
-                            // 没有 legend 时做一个保守的“演示映射”。
-                            if token_modifiers_bits & 0b0001 != 0 {
-                                mods |= Modifier::BOLD;
-                            }
-                            if token_modifiers_bits & 0b0010 != 0 {
-                                mods |= Modifier::ITALIC;
-                            }
-                            if token_modifiers_bits & 0b0100 != 0 {
-                                mods |= Modifier::UNDERLINED;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        let mut style = Style::default().fg(Color::White);
-        if let Some(color) = fg {
-            style = style.fg(color);
-        }
-        style.add_modifier(mods)
+        style_for_attributes(self.theme.resolve(style_ids))
     }
 
     /// 渲染编辑器内容
     fn render_editor(&self, frame: &mut Frame, area: Rect) {
         let editor = self.state_manager.editor();
-        let layout_engine = &editor.layout_engine;
         let line_index = &editor.line_index;
 
         let inner_height = area.height.saturating_sub(2) as usize;
-        let inner_width = area.width.saturating_sub(2) as usize;
+        let gutter_width = gutter_width(editor.line_count());
+        let inner_width = (area.width.saturating_sub(2) as usize).saturating_sub(gutter_width);
         let scroll_top = self.state_manager.get_viewport_state().scroll_top;
-        let total_visual = editor.visual_line_count();
 
         let cursor_state = self.state_manager.get_cursor_state();
-        let selections = cursor_state.selections;
+        let selection_ranges: Vec<(usize, usize)> = cursor_state
+            .selections
+            .iter()
+            .filter(|selection| selection.start != selection.end)
+            .map(|selection| {
+                let (start, end) = if selection.start <= selection.end {
+                    (selection.start, selection.end)
+                } else {
+                    (selection.end, selection.start)
+                };
+                (
+                    line_index.position_to_char_offset(start.line, start.column),
+                    line_index.position_to_char_offset(end.line, end.column),
+                )
+            })
+            .collect();
 
-        let grid = self
-            .state_manager
-            .get_viewport_content_styled(scroll_top, inner_height);
+        // 组合视图：文档文本 + 虚拟文本（inlay hints、折叠占位符）
+        let grid = editor.get_headless_grid_composed(scroll_top, inner_height);
+        // 行号栏与组合视图逐行对齐（虚拟行为空）
+        let gutter = editor.get_viewport_gutter_composed(scroll_top, inner_height);
 
         let mut display_lines = Vec::with_capacity(inner_height);
 
@@ -1870,87 +2352,41 @@ impl App {
                 continue;
             }
 
-            let visual_row = scroll_top + i;
-            if visual_row >= total_visual {
-                display_lines.push(Line::from(""));
-                continue;
-            }
-
-            let (logical_line, visual_in_line) = editor.visual_to_logical_line(visual_row);
-            let Some(layout) = layout_engine.get_line_layout(logical_line) else {
-                display_lines.push(Line::from(""));
-                continue;
-            };
-
-            let line_text = line_index.get_line_text(logical_line).unwrap_or_default();
-            let line_char_len = line_text.chars().count();
-
-            let segment_start_col = if visual_in_line == 0 {
-                0
-            } else {
-                layout
-                    .wrap_points
-                    .get(visual_in_line - 1)
-                    .map(|wp| wp.char_index)
-                    .unwrap_or(0)
-                    .min(line_char_len)
-            };
-
-            let mut selection_ranges: Vec<(usize, usize)> = Vec::new();
-            for selection in &selections {
-                if selection.start == selection.end {
-                    continue;
-                }
-
-                let (sel_start, sel_end) = if selection.start <= selection.end {
-                    (selection.start, selection.end)
-                } else {
-                    (selection.end, selection.start)
-                };
-
-                if logical_line < sel_start.line || logical_line > sel_end.line {
-                    continue;
-                }
-
-                let start_col = if logical_line == sel_start.line {
-                    sel_start.column.min(line_char_len)
-                } else {
-                    0
-                };
-                let end_col = if logical_line == sel_end.line {
-                    sel_end.column.min(line_char_len)
-                } else {
-                    line_char_len
-                };
-
-                if start_col < end_col {
-                    selection_ranges.push((start_col, end_col));
-                }
-            }
+            let mut spans: Vec<Span> = gutter
+                .get(i)
+                .map(|gutter| gutter_spans(gutter, gutter_width))
+                .unwrap_or_default();
 
-            let Some(headless_line) = grid.lines.get(i) else {
-                display_lines.push(Line::from(""));
+            let Some(composed_line) = grid.lines.get(i) else {
+                display_lines.push(Line::from(spans));
                 continue;
             };
 
-            if headless_line.cells.is_empty() {
-                display_lines.push(Line::from(""));
-                continue;
-            }
-
-            let mut spans: Vec<Span> = Vec::new();
             let mut current_style: Option<Style> = None;
             let mut buffer = String::new();
 
-            for (cell_idx, cell) in headless_line.cells.iter().enumerate() {
-                let col = segment_start_col + cell_idx;
+            for cell in &composed_line.cells {
                 let mut style = self.style_for_style_ids(&cell.styles);
 
-                let is_selected = selection_ranges
-                    .iter()
-                    .any(|(start, end)| col >= *start && col < *end);
-                if is_selected {
-                    style = style.bg(Color::Blue).fg(Color::White);
+                match cell.source {
+                    ComposedCellSource::Document { offset } => {
+                        // 多个样式层重叠时，前景色取优先级最高的样式，而不是按 id 顺序“后写者胜”。
+                        if let Some(fg) = editor
+                            .styles_at_offset_resolved(offset)
+                            .and_then(|winner| self.theme.style_attributes(&[winner]).fg)
+                        {
+                            style = style.fg(rgb_to_color(fg));
+                        }
+                        let is_selected = selection_ranges
+                            .iter()
+                            .any(|(start, end)| offset >= *start && offset < *end);
+                        if is_selected {
+                            style = style.bg(Color::Blue).fg(Color::White);
+                        }
+                    }
+                    ComposedCellSource::Virtual { .. } => {
+                        style = style.fg(Color::DarkGray).add_modifier(Modifier::ITALIC);
+                    }
                 }
 
                 if current_style.is_none() {
@@ -2014,7 +2450,7 @@ impl App {
             return;
         }
 
-        let inner_left = area.x + 1;
+        let inner_left = area.x + 1 + gutter_width as u16;
         let inner_top = area.y + 1;
         let inner_right = area.x + area.width.saturating_sub(2);
         let inner_bottom = area.y + area.height.saturating_sub(2);
@@ -2023,12 +2459,111 @@ impl App {
             return;
         }
 
-        let rel_row = (cursor_visual_row - scroll_top) as u16;
+        let rel_row = cursor_visual_row - scroll_top;
+        // 光标前的虚拟文本（inlay hints）会把光标向右推。
+        let cursor_x = grid
+            .lines
+            .get(rel_row)
+            .map(|line| {
+                let cursor_offset =
+                    line_index.position_to_char_offset(cursor_pos.line, cursor_pos.column);
+                composed_cursor_x(editor, line, cursor_offset, cursor_x)
+            })
+            .unwrap_or(cursor_x);
+        let rel_row = rel_row as u16;
         let cursor_x = (inner_left + cursor_x as u16).min(inner_right);
         let cursor_y = (inner_top + rel_row).min(inner_bottom);
         frame.set_cursor_position((cursor_x, cursor_y));
     }
 
+    /// 渲染补全弹窗（最多 8 项，显示在光标下一行）
+    fn render_completion_popup(&self, frame: &mut Frame, area: Rect) {
+        const MAX_ITEMS: usize = 8;
+
+        let Some(session) = self.completion.as_ref() else {
+            return;
+        };
+        if session.is_empty() {
+            return;
+        }
+
+        let editor = self.state_manager.editor();
+        let cursor_pos = editor.cursor_position();
+        let Some((cursor_visual_row, cursor_x)) =
+            editor.logical_position_to_visual(cursor_pos.line, cursor_pos.column)
+        else {
+            return;
+        };
+        let scroll_top = self.state_manager.get_viewport_state().scroll_top;
+        if cursor_visual_row < scroll_top {
+            return;
+        }
+
+        // 选中项保持在可见窗口内
+        let first = self.completion_selected.saturating_sub(MAX_ITEMS - 1);
+        let lines: Vec<Line> = session
+            .items()
+            .enumerate()
+            .skip(first)
+            .take(MAX_ITEMS)
+            .map(|(index, item)| {
+                let mut spans = vec![Span::raw(completion_item_label(item).to_string())];
+                if let Some(detail) = item.get("detail").and_then(|d| d.as_str()) {
+                    spans.push(Span::styled(
+                        format!("  {}", detail),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                let line = Line::from(spans);
+                if index == self.completion_selected {
+                    line.style(Style::default().bg(Color::Blue).fg(Color::White))
+                } else {
+                    line
+                }
+            })
+            .collect();
+
+        let width = lines
+            .iter()
+            .map(|line| line.width() as u16)
+            .max()
+            .unwrap_or(0)
+            .saturating_add(2)
+            .min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let gutter_width = gutter_width(editor.line_count()) as u16;
+        let x =
+            (area.x + 1 + gutter_width + cursor_x as u16).min(area.right().saturating_sub(width));
+        let below = area.y + 2 + (cursor_visual_row - scroll_top) as u16;
+        let y = if below + height <= area.bottom() {
+            below
+        } else {
+            below.saturating_sub(height + 1).max(area.y)
+        };
+
+        let popup = Rect::new(x, y, width, height);
+        let title = if session.is_incomplete() {
+            " 补全… "
+        } else {
+            " 补全 "
+        };
+        frame.render_widget(Clear, popup);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+            popup,
+        );
+    }
+
+    /// LSP 后台任务进度（如 rust-analyzer 预热时的 "indexing 42%"）。
+    fn lsp_progress_label(&self) -> Option<String> {
+        let item = self.lsp.as_ref()?.active_progress().into_iter().next()?;
+        let title = item.title.to_lowercase();
+        Some(match item.percentage {
+            Some(percentage) => format!("{} {}%", title, percentage),
+            None => title,
+        })
+    }
+
     /// 渲染状态行
     fn render_status_line(&self, frame: &mut Frame, area: Rect) {
         let doc_state = self.state_manager.get_document_state();
@@ -2059,19 +2594,25 @@ impl App {
                 ),
                 InputMode::Normal => String::new(),
             }
-        } else if !self.status_message.is_empty() {
-            self.status_message.clone()
         } else {
-            format!(
-                "行:{} 列:{} | 光标:{}{} | 总行数:{} 字符数:{} | 版本:{}",
-                cursor_pos.line + 1,
-                cursor_pos.column + 1,
-                cursor_count,
-                rect_tag,
-                doc_state.line_count,
-                doc_state.char_count,
-                doc_state.version
-            )
+            let text = if !self.status_message.is_empty() {
+                self.status_message.clone()
+            } else {
+                format!(
+                    "行:{} 列:{} | 光标:{}{} | 总行数:{} 字符数:{} | 版本:{}",
+                    cursor_pos.line + 1,
+                    cursor_pos.column + 1,
+                    cursor_count,
+                    rect_tag,
+                    doc_state.line_count,
+                    doc_state.char_count,
+                    doc_state.version
+                )
+            };
+            match self.lsp_progress_label() {
+                Some(progress) => format!("{} | {}", text, progress),
+                None => text,
+            }
         };
 
         let status_line = Paragraph::new(status_text).style(
@@ -2089,7 +2630,7 @@ impl App {
         let shortcuts = if self.confirm_quit {
             "Y:保存并退出  N:不保存退出  Esc:取消"
         } else {
-            "Ctrl-S:保存  Ctrl-X:退出  Ctrl-Z/Y:撤销/重做  Ctrl-C/V:复制/粘贴  Ctrl-B:矩形  Ctrl-L/U:折叠/全展开  Ctrl-F:查找  Ctrl-Shift-H:替换  F3/Shift-F3:下一个/上一个  Ctrl-Shift-R/A:替换/全部"
+            "Ctrl-S:保存  Ctrl-X:退出  Ctrl-Z/Y:撤销/重做  Ctrl-C/V:复制/粘贴  Ctrl-B:矩形  Ctrl-L/U:折叠/全展开  Ctrl-F:查找  Ctrl-Shift-H:替换  F3/Shift-F3:下一个/上一个  Ctrl-Shift-R/A:替换/全部  Ctrl-Space:补全  Ctrl-K:悬停  F12:定义  Alt-Enter:快速修复"
         };
 
         let shortcuts_line = Paragraph::new(shortcuts).style(
@@ -2103,48 +2644,34 @@ impl App {
     }
 }
 
-fn style_for_sublime_scope(scope: &str) -> (Option<Color>, Modifier) {
-    // Very small demo mapping: heuristics based on scope naming conventions.
-    let mut mods = Modifier::empty();
-
-    if scope.contains("invalid") || scope.contains("illegal") {
-        return (Some(Color::LightRed), Modifier::BOLD);
-    }
-
-    if scope.contains("comment") {
-        mods |= Modifier::ITALIC;
-        return (Some(Color::DarkGray), mods);
-    }
-
-    if scope.contains("string") {
-        return (Some(Color::Green), mods);
-    }
-
-    if scope.contains("constant.numeric")
-        || scope.contains("meta.number")
-        || scope.contains("constant.character.numeric")
-    {
-        return (Some(Color::Yellow), mods);
-    }
-
-    if scope.contains("keyword") {
-        return (Some(Color::LightBlue), mods);
-    }
-
-    if scope.contains("entity.name")
-        || scope.contains("support.type")
-        || scope.contains("support.class")
-        || scope.contains("storage.type")
-    {
-        mods |= Modifier::BOLD;
-        return (Some(Color::Cyan), mods);
+/// 在组合行中定位光标列：`cursor_x` 只按文档文本计算，这里把虚拟文本的宽度加进去。
+fn composed_cursor_x(
+    editor: &EditorCore,
+    line: &ComposedLine,
+    cursor_offset: usize,
+    cursor_x: usize,
+) -> usize {
+    let mut x = 0usize;
+    let mut after_last_document = None;
+    for cell in &line.cells {
+        if let ComposedCellSource::Document { offset } = cell.source {
+            if offset >= cursor_offset {
+                return x;
+            }
+            after_last_document = Some((offset, x + cell.width));
+        }
+        x += cell.width;
     }
 
-    if scope.contains("punctuation") {
-        return (Some(Color::DarkGray), mods);
+    // 行尾（或矩形选择的虚拟列）：保持与最后一个文档字符的距离。
+    let Some((offset, grid_x)) = after_last_document else {
+        return cursor_x;
+    };
+    let (line, column) = editor.line_index.char_offset_to_position(offset);
+    match editor.logical_position_to_visual(line, column + 1) {
+        Some((_, plain_x)) => grid_x + cursor_x.saturating_sub(plain_x),
+        None => cursor_x,
     }
-
-    (None, mods)
 }
 
 fn main() -> io::Result<()> {